@@ -0,0 +1,62 @@
+//! Expose the versions of the bundled engines, as locked in `Cargo.lock`, to
+//! `parsers.features`.
+
+use std::fs;
+
+/// The crates of the engines, and the variable their version is given in
+const ENGINES: [(&str, &str); 4] = [
+    ("jsonc-parser", "COSUTILS_JSONC_PARSER_VERSION"),
+    ("rnix", "COSUTILS_RNIX_VERSION"),
+    ("toml", "COSUTILS_TOML_VERSION"),
+    ("tvix-eval", "COSUTILS_TVIX_EVAL_VERSION"),
+];
+
+/// The value of a `key = "value"` field of a locked package
+fn field<'a>(package: &'a str, key: &str) -> Option<&'a str> {
+    package.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(key)?.trim_start();
+        Some(value.strip_prefix('=')?.trim().trim_matches('"'))
+    })
+}
+
+/// The version of a locked crate, followed by the short commit of git
+/// dependencies, like `0.1.0+0ef4144`
+fn locked_version(lock: &str, name: &str) -> Option<String> {
+    let packages = lock.split("[[package]]").collect::<Vec<_>>();
+    // When several versions of a crate are locked, the dependencies of this
+    // crate name the version they use, like `"toml 0.8.23"`
+    let wanted = packages
+        .iter()
+        .find(|package| field(package, "name") == Some("cosutils"))
+        .into_iter()
+        .flat_map(|package| package.lines())
+        .find_map(|line| {
+            let dependency = line.trim().strip_prefix('"')?;
+            let dependency = dependency.trim_end_matches(['"', ',']);
+            let (dependency, version) = dependency.split_once(' ')?;
+            let version = version.split(' ').next()?;
+            (dependency == name).then_some(version)
+        });
+    let package = packages.iter().find(|package| {
+        field(package, "name") == Some(name)
+            && wanted.is_none_or(|v| field(package, "version") == Some(v))
+    })?;
+    let version = field(package, "version")?;
+    let commit = field(package, "source")
+        .filter(|source| source.starts_with("git+"))
+        .and_then(|source| Some(source.rsplit_once('#')?.1));
+    match commit {
+        Some(commit) => Some(format!("{}+{:.7}", version, commit)),
+        None => Some(version.to_string()),
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (name, variable) in ENGINES {
+        let version =
+            locked_version(&lock, name).unwrap_or_else(|| "unknown".into());
+        println!("cargo:rustc-env={}={}", variable, version);
+    }
+}
@@ -1,8 +1,864 @@
+import os
+from typing import Any, Callable, Iterable, TypedDict
+
 class ParseError(ValueError):
-    pass
+    code: str
+    errors: list[ParseError | EvaluationError | ConversionError]
 
 class EvaluationError(ValueError):
-    pass
+    code: str
+    errors: list[ParseError | EvaluationError | ConversionError]
 
 class ConversionError(ValueError):
-    pass
+    code: str
+    errors: list[ParseError | EvaluationError | ConversionError]
+
+class ResourceLimitError(EvaluationError):
+    code: str
+
+class _Detection(TypedDict):
+    format: str | None
+    confidence: float
+    evidence: list[str]
+
+class _Features(TypedDict):
+    version: str
+    formats: dict[str, bool]
+    capabilities: dict[str, bool]
+    engines: dict[str, str]
+
+class _JsoncProfile(TypedDict):
+    strict: bool
+
+class _NixProfile(TypedDict):
+    strict: bool
+    timeout: float | None
+    max_memory_mb: int | None
+
+class _Profile(TypedDict):
+    name: str
+    color: bool | None
+    jsonc: _JsoncProfile
+    nix: _NixProfile
+
+def available_formats() -> list[str]:
+    """
+    List the formats compiled into this build.
+
+    Each parser family is gated behind a Cargo feature of the same name, so
+    slim builds may lack some of the `cosutils.rustlib.parsers` submodules.
+
+    Returns:
+      - list[str]: The names of the available parser submodules.
+
+    Example:
+    ```python
+    >>> available_formats()
+    ['jsonc', 'manifest', 'nix']
+    ```
+    """
+    ...
+
+def features() -> _Features:
+    """
+    Describe what this build of the parsers can do.
+
+    Tools depending on optional parts of the parsers can check for them
+    here rather than catching the errors of missing submodules or
+    functions.
+
+    Returns:
+      - _Features: The `version` of cosutils, whether each of the `formats`
+                   and optional `capabilities` is compiled in, and the
+                   `engines` bundled by the compiled formats with their
+                   version. Engines built from git have the short commit in
+                   their version, like `0.1.0+0ef4144`.
+
+    Example:
+    ```python
+    >>> features()
+    {'version': '0.1.0', 'formats': {'jsonc': True, 'manifest': True, 'nix': True}, 'capabilities': {'fetchers': True, 'watch': False, 'simd': False}, 'engines': {'jsonc-parser': '0.26.2', 'rnix': '0.11.0', 'toml': '0.8.23', 'tvix-eval': '0.1.0+0ef4144'}}
+    >>> features()["capabilities"]["watch"]
+    False
+    ```
+    """
+    ...
+
+def anonymize(
+    value: Any, rules: dict[str, bool | list[str]] | None = None
+) -> Any:
+    """
+    Anonymize a parsed document, so it can be shared in bug reports.
+
+    Usernames, hostnames, email addresses, IP addresses and secrets are
+    replaced by pseudonyms, such as `user1`, `host1`, `user1@example.com`,
+    `192.0.2.1` and `<redacted>`. Every occurrence of the same value gets the
+    same pseudonym, and the structure of the document is kept as is, dict
+    keys included.
+
+    Values are recognized by their form, by the key they are found under,
+    e.g. `password` or `hostName`, and by the user and host running the
+    function, along with the extra values given in the rules. Usernames are
+    also recognized as the directories of `/home` and `/Users`.
+
+    Args:
+      - value (Any): The parsed document, or the `os.PathLike` path of a
+                     document to load with the parser of its extension.
+                     Plain strings are values, never paths.
+      - rules (dict[str, bool | list[str]]): Rules by category, among
+                                             `usernames`, `hostnames`,
+                                             `emails`, `ips` and `secrets`.
+                                             `False` keeps the category as
+                                             is, a list gives extra values of
+                                             the category to replace. Every
+                                             category is replaced by default.
+
+    Returns:
+      - Any: An anonymized copy of the document.
+
+    Raises:
+      - ValueError: If a rule is unknown or malformed, or no parser handles
+                    the extension of the path.
+      - IOError, ParseError, EvaluationError: If the path cannot be loaded.
+
+    Example:
+    ```python
+    >>> anonymize({"user": "alice", "ssh": "alice@10.1.2.3", "token": "x"})
+    {'user': 'user1', 'ssh': 'user1@192.0.2.1', 'token': '<redacted>'}
+    ```
+    """
+    ...
+
+def completions_from(
+    value: dict[str, Any], style: str = "bash", command: str = "cosutils"
+) -> str:
+    """
+    Generate shell completions for the options declared by an options tree.
+
+    The tree is either a JSON Schema, whose objects declare their keys under
+    `properties`, or a nix options schema, whose options are attrsets with
+    `_type = "option"` and every other attrset is a group of options. Every
+    leaf option is completed by its path, written like a nix attribute path,
+    along with the first line of its `description` where the shell supports
+    it.
+
+    Args:
+      - value (dict): The options tree.
+      - style (str): The shell to generate the completions for, `"bash"`,
+                     `"zsh"` or `"fish"`.
+      - command (str): The command the options belong to. The bash and zsh
+                       snippets define a function `_<command>_options`
+                       completing the current word, which completion
+                       scripts can call, the fish snippet registers the
+                       completions of the command directly.
+
+    Returns:
+      - str: The completion snippet.
+
+    Raises:
+      - TypeError: If the `properties` of a JSON Schema are not a dict.
+      - ValueError: If the style is not supported.
+
+    Example:
+    ```python
+    >>> options = {
+    ...     "port": {"_type": "option", "description": "The port to bind"},
+    ... }
+    >>> print(completions_from(options, style="fish"))
+    complete -c 'cosutils' -f -a 'port' -d 'The port to bind'
+    ```
+    """
+    ...
+
+def detect(source: os.PathLike[str] | bytes | str) -> _Detection:
+    """
+    Guess the format of a document from its extension and its content.
+
+    Meant for files without extension or with a misleading one, so tools can
+    ask the user before parsing documents whose format is unclear. The
+    extension, an editor modeline like `# vim: ft=nix` and the parsers of
+    this build each weigh heavily, hints in the content like `[section]`
+    headers or `let ... in` bindings weigh less.
+
+    Args:
+      - source (os.PathLike | bytes | str): The path of the document, or its
+                                            content. Plain strings are
+                                            content, never paths.
+
+    Returns:
+      - _Detection: The best guess of `format`, among `jsonc`, `nix`, `toml`,
+                    `yaml` and `ini`, or `None` if nothing points to one, the
+                    `confidence` of the guess between 0 and 1, lower when the
+                    evidence is thin or fits several formats, and the
+                    `evidence` found for the guessed format.
+
+    Raises:
+      - IOError: If the path cannot be read.
+
+    Example:
+    ```python
+    >>> detect(b'{ "a": 1 }')["evidence"]
+    ['the content parses as jsonc', 'the content starts with `{` or `[`',
+     'the content has quoted keys followed by `:`']
+    >>> detect("let a = 1; in a")["format"]
+    'nix'
+    ```
+    """
+    ...
+
+def diagnostics_to_sarif(
+    diagnostics: Iterable[dict[str, Any] | BaseException],
+    path: str | None = None,
+    level: str = "warning",
+    tool: str = "cosutils",
+) -> str:
+    """
+    Convert diagnostics to a SARIF 2.1.0 log, as uploaded by code scanning
+    services.
+
+    Diagnostics are either dicts, like those returned by `nix.lint`, or
+    exceptions raised by the loaders, which give one result per error of
+    their `errors` attribute. Dicts need a `message`, and may give the
+    `code`, `level`, `path`, `line`, `column`, `start` and `end` of the
+    diagnostic, where `start` and `end` are byte offsets. ANSI escapes
+    of colored messages are removed.
+
+    Args:
+      - diagnostics (Iterable[dict | BaseException]): The diagnostics.
+      - path (str | None): The path of the file of diagnostics without a
+                           `path`.
+      - level (str): The level of dicts without a `level`, either `"error"`,
+                     `"warning"` or `"note"`. Exceptions are always errors.
+      - tool (str): The name of the tool reported in the log.
+
+    Returns:
+      - str: The SARIF log as JSON.
+
+    Raises:
+      - TypeError: If a diagnostic is neither a dict nor an exception.
+      - ValueError: If a level is unknown or a dict has no `message`.
+
+    Example:
+    ```python
+    >>> diags = nix.lint("default.nix")
+    >>> sarif = json.loads(diagnostics_to_sarif(diags, path="default.nix"))
+    >>> sarif["runs"][0]["results"][0]["ruleId"]
+    'unused_argument'
+    ```
+    """
+    ...
+
+def to_github_annotations(
+    diagnostics: Iterable[dict[str, Any] | BaseException],
+    path: str | None = None,
+    level: str = "warning",
+) -> str:
+    """
+    Convert diagnostics to GitHub Actions workflow commands, which annotate
+    the files of a pull request when printed by a step.
+
+    Diagnostics are given like to `diagnostics_to_sarif`, the `code` of each
+    becoming the title of its annotation.
+
+    Args:
+      - diagnostics (Iterable[dict | BaseException]): The diagnostics.
+      - path (str | None): The path of the file of diagnostics without a
+                           `path`.
+      - level (str): The level of dicts without a `level`, either `"error"`,
+                     `"warning"` or `"note"`. Exceptions are always errors.
+
+    Returns:
+      - str: One `::error`, `::warning` or `::notice` command per line.
+
+    Raises:
+      - TypeError: If a diagnostic is neither a dict nor an exception.
+      - ValueError: If a level is unknown or a dict has no `message`.
+
+    Example:
+    ```python
+    # `default.nix` contains:
+    # ```
+    # { pkgs, lib, ... }: let x = 1; in { a = lib.id 1; }
+    # ```
+    >>> print(to_github_annotations(nix.lint("default.nix"), "default.nix"))
+    ::warning file=default.nix,line=1,col=3,title=unused_argument::Unused function argument `pkgs`
+    ::warning file=default.nix,line=1,col=25,title=unused_let::Unused let binding `x`
+    ```
+    """
+    ...
+
+def explain(code: str) -> str:
+    """
+    Explain an error code, like the `code` of a `ParseError`, for `--explain`
+    options of command line tools.
+
+    The explanation is Markdown, titled by the code, telling what the error
+    means, its common causes, and an example of it with its fix. Every code
+    raised by the parsers is documented, whatever the formats of the build.
+
+    Args:
+      - code (str): The error code, like `JSONC_E010`, in any case.
+
+    Returns:
+      - str: The explanation of the code.
+
+    Raises:
+      - ValueError: If the code is unknown.
+
+    Example:
+    ```python
+    >>> print(explain("JSONC_E010").splitlines()[0])
+    # JSONC_E010: An object defines the same key more than once
+    ```
+    """
+    ...
+
+def apply_defaults(
+    value: dict[str, Any], schema: dict[str, Any]
+) -> tuple[dict[str, Any], list[str]]:
+    """
+    Fill the keys missing from a value with the defaults declared by a schema.
+
+    The schema is either a JSON Schema, whose objects declare their keys under
+    `properties`, or a nix options schema, whose options are attrsets with
+    `_type = "option"` and every other attrset is a group of options. Missing
+    objects are created when some of their keys have a default.
+
+    Args:
+      - value (dict): The value to complete, it is not modified.
+      - schema (dict): The schema declaring the defaults.
+
+    Returns:
+      - tuple[dict, list[str]]: The completed value, and the paths of the keys
+                                that were filled, written like nix attribute
+                                paths.
+
+    Raises:
+      - TypeError: If the `properties` of a JSON Schema are not a dict.
+
+    Example:
+    ```python
+    >>> schema = {
+    ...     "type": "object",
+    ...     "properties": {
+    ...         "port": {"type": "integer", "default": 8080},
+    ...         "tls": {"properties": {"enable": {"default": False}}},
+    ...     },
+    ... }
+    >>> apply_defaults({"port": 80}, schema)
+    ({'port': 80, 'tls': {'enable': False}}, ['tls.enable'])
+    ```
+    """
+    ...
+
+def transform(
+    value: Any,
+    visitor: Callable[[tuple[str | int, ...], Any], Any] | None = None,
+    rename: dict[str, str] | None = None,
+    coerce: dict[str, str] | None = None,
+    drop_nulls: bool = False,
+) -> Any:
+    """
+    Transform every node of a parsed document, for structural migrations of
+    configurations.
+
+    The rules apply to each node in turn: its keys are renamed, then values
+    are coerced, then the visitor is called, then nulls are dropped. Nodes are
+    transformed bottom-up, so the visitor of a dict or a list is given its
+    transformed items. Dicts, lists and tuples are copied, the value is not
+    modified.
+
+    Args:
+      - value (Any): The parsed document.
+      - visitor (Callable | None): Called with the path of each node, as a
+                                   tuple of its keys and list indices, and
+                                   the node, returning the node to put in
+                                   its place.
+      - rename (dict[str, str]): New names of dict keys, wherever they are
+                                 found.
+      - coerce (dict[str, str]): The types scalars are converted to, among
+                                 `str`, `int`, `float`, `bool` and the types
+                                 of `coerce`, by their path of keys separated
+                                 by `.`, where `*` matches any key, the most
+                                 specific path winning. Items of lists have
+                                 the path of their list. Strings like `"80"`,
+                                 `"1.5"` or `"yes"` are parsed, and `None` is
+                                 left as is.
+      - drop_nulls (bool): Remove the dict entries and list items that are
+                           `None`, once the visitor has been called on them.
+
+    Returns:
+      - Any: The transformed copy of the document.
+
+    Raises:
+      - ValueError: If a rule is malformed, or a value cannot be coerced to
+                    its type.
+      - Exception: Whatever the visitor raises.
+
+    Example:
+    ```python
+    >>> transform(
+    ...     {"listen": {"port": "80"}, "hosts": ["a", None]},
+    ...     rename={"listen": "server"},
+    ...     coerce={"server.port": "int"},
+    ...     drop_nulls=True,
+    ... )
+    {'server': {'port': 80}, 'hosts': ['a']}
+    >>> transform({"a": [1, 2]}, lambda p, v: v * 10 if len(p) == 2 else v)
+    {'a': [10, 20]}
+    ```
+    """
+    ...
+
+def coerce(value: Any, spec: str | dict[str, Any] | list[Any]) -> Any:
+    """
+    Coerce the leaves of a parsed document to types, normalizing the
+    string-typed values of legacy configurations in one pass.
+
+    The spec mirrors the structure of the value. The name of a type applies
+    to every leaf below it, a dict gives the specs of the values of a dict by
+    key, with `*` matching the other keys, and a list of one spec applies it
+    to every item of a list. Values without a spec, or with a `None` spec,
+    are left as is, and so is `None` wherever it is found. The types are:
+      - `int`: Integers, from floats without a fraction or strings like
+               `"80"`.
+      - `bool-ish`: Booleans, from `1` and `0` or strings like `"yes"`,
+                    `"on"` or `"false"`, whatever their case.
+      - `duration`: A `datetime.timedelta`, from a number of seconds or a
+                    string like `"90"`, `"1h30m"` or `"2d 12h"`, with units
+                    from `ns` to `w`.
+      - `size`: A number of bytes, from a string like `"512"`, `"10MB"` or
+                `"1.5GiB"`. SI units like `kB` are powers of 1000, IEC units
+                like `KiB` and bare `K`, `M`, `G` or `T` powers of 1024.
+      - `path`: A `pathlib.Path`, from a string, with a leading `~` expanded
+                to the home directory.
+      - `str`, `float` and `bool`: As with `transform`.
+
+    Args:
+      - value (Any): The parsed document, it is not modified.
+      - spec (str | dict | list): The types of its leaves.
+
+    Returns:
+      - Any: The coerced copy of the document.
+
+    Raises:
+      - ValueError: If the spec is malformed, a value does not have the
+                    structure of its spec, or cannot be coerced to its type.
+
+    Example:
+    ```python
+    >>> coerce(
+    ...     {"port": "8080", "timeout": "1m30s", "cache": {"max": "1.5GiB"},
+    ...      "debug": "off", "dirs": ["~/a"]},
+    ...     {"port": "int", "timeout": "duration", "cache": "size",
+    ...      "debug": "bool-ish", "dirs": ["path"]},
+    ... )
+    {'port': 8080, 'timeout': datetime.timedelta(seconds=90),
+     'cache': {'max': 1610612736}, 'debug': False,
+     'dirs': [PosixPath('/home/me/a')]}
+    ```
+    """
+    ...
+
+def rekey(
+    value: dict[Any, Any],
+    style: str = "snake",
+    deep: bool = True,
+) -> dict[Any, Any]:
+    """
+    Convert the keys of a mapping to a naming convention, for bridging nix
+    attrsets and the configuration files of tools using another one.
+
+    Keys are split into words at `_`, `-`, spaces and dots, and where the
+    case changes, so `HTTPServer` is made of `HTTP` and `Server`. Leading and
+    trailing underscores are kept, so `_type` stays `_type` in every style.
+    Keys that are not strings are kept as is.
+
+    Args:
+      - value (dict): The mapping whose keys are converted, it is not
+                      modified.
+      - style (str): The naming convention, `snake` for `max_connections`,
+                     `camel` for `maxConnections` or `kebab` for
+                     `max-connections`.
+      - deep (bool): Convert the keys of the nested mappings too, including
+                     those in lists.
+
+    Returns:
+      - dict: A copy of the mapping with converted keys.
+
+    Raises:
+      - TypeError: If the value is not a dict.
+      - ValueError: If the style is unknown, or two keys of a mapping are
+                    converted to the same key.
+
+    Example:
+    ```python
+    >>> rekey({"maxConnections": 1, "TLSConfig": {"certFile": "a"}})
+    {'max_connections': 1, 'tls_config': {'cert_file': 'a'}}
+    >>> rekey({"max_connections": 1}, style="kebab")
+    {'max-connections': 1}
+    ```
+    """
+    ...
+
+def codegen(
+    schema: dict[str, Any], target: str = "python-typeddict", name: str = "Root"
+) -> str:
+    """
+    Generate Python types from a JSON Schema, so parsed configurations get
+    static types.
+
+    The root schema and every object declaring `properties` become a class,
+    named by its `title`, else by its key in `PascalCase`, prefixed by the
+    class containing it if the name is taken. Definitions referenced as
+    `#/$defs/<name>` or `#/definitions/<name>` become a class of their name.
+    `type`, `enum`, `const`, `anyOf`, `oneOf`, `items` and
+    `additionalProperties` are turned into annotations, anything else, like
+    references to other documents, into `Any`. Descriptions become docstrings
+    and comments.
+
+    Args:
+      - schema (dict): The JSON Schema.
+      - target (str): `python-typeddict` for `TypedDict` classes, keys that
+                      are not `required` being `NotRequired`, or `dataclass`
+                      for dataclasses, fields that are not `required` being
+                      optional and defaulting to their `default` or `None`.
+                      Keys that are not identifiers are written with the
+                      functional syntax of `TypedDict`, and renamed in
+                      dataclasses, with their key in the `metadata` of their
+                      field.
+      - name (str): The name of the class of the root schema.
+
+    Returns:
+      - str: The source of a Python module defining the types, the root one
+             last.
+
+    Raises:
+      - ValueError: If the target is unknown.
+      - TypeError: If a keyword of the schema has the wrong type, like
+                   `properties` not being a dict.
+
+    Example:
+    ```python
+    >>> schema = {
+    ...     "type": "object",
+    ...     "properties": {
+    ...         "port": {"type": "integer"},
+    ...         "tls": {"properties": {"enable": {"type": "boolean"}}},
+    ...     },
+    ...     "required": ["port"],
+    ... }
+    >>> print(codegen(schema, name="Config"))
+    from __future__ import annotations
+
+    from typing import NotRequired, TypedDict
+
+
+    class Tls(TypedDict):
+        enable: NotRequired[bool]
+
+
+    class Config(TypedDict):
+        port: int
+        tls: NotRequired[Tls]
+    ```
+    """
+    ...
+
+def value_hash(value: Any, algo: str = "blake3") -> str:
+    """
+    Hash a parsed value by its structure.
+
+    The hash covers the canonical structural form of the value, so it does
+    not depend on the order of keys or the formatting of the document the
+    value was parsed from. Values of different types always hash differently,
+    e.g. `1` and `1.0`.
+
+    Args:
+      - value (Any): A value as returned by the parsers, made of `None`,
+                     `bool`, `int`, `float`, `str`, `list`, `tuple` and
+                     `dict`.
+      - algo (str): The hash algorithm, either `"blake3"` or `"sha256"`.
+
+    Returns:
+      - str: The hash as lowercase hexadecimal digits.
+
+    Raises:
+      - TypeError: If the value contains objects of other types.
+      - ValueError: If the algorithm is not supported.
+
+    Example:
+    ```python
+    >>> value_hash({"a": 1, "b": [True]}) == value_hash({"b": [True], "a": 1})
+    True
+    ```
+    """
+    ...
+
+def set_color(enabled: bool | None = None) -> None:
+    """
+    Choose whether error messages are colored with ANSI escapes.
+
+    Messages are rendered when the errors are raised, so the choice applies
+    to every later error of all the parsers, on all threads.
+
+    Args:
+      - enabled (bool | None): Whether to color messages. `None` restores the
+                               default, coloring them unless the `NO_COLOR`
+                               environment variable is set to a non-empty
+                               value.
+
+    Example:
+    ```python
+    >>> set_color(False)
+    >>> nix.evals("1 +")
+    Traceback (most recent call last):
+    ...
+    ParseError: error: failed to parse Nix code
+    ```
+    """
+    ...
+
+def set_int_overflow(policy: str | None = None) -> None:
+    """
+    Choose what the converters do with integers that do not fit into 64 bits.
+
+    Nix integers are 64-bit, and JSONC integers are read as such, so larger
+    integers are either given to nix from Python or written in JSONC
+    documents. With the default policy, nix converters raise a
+    `ConversionError` and JSONC loaders read them as floats, losing their
+    precision, except in strict mode. The policies are:
+      - `error`: JSONC loaders raise a `ParseError` too, even when not
+                 strict.
+      - `wrap`: The low 64 bits are kept, as a two's complement integer, like
+                C does.
+      - `bigint-via-string`: The integer is kept exactly through its decimal
+                             digits. JSONC loaders give Python integers of any
+                             size, and Python integers given to nix become
+                             strings of their digits.
+
+    The `wrap` and `bigint-via-string` policies apply to strict JSONC loaders
+    too, which no longer reject larger integers.
+
+    Larger integer literals of nix code cannot be evaluated by nix, and are
+    always rejected. The policy applies to all the converters, on all
+    threads, but the loaders and evaluators given their own `int_overflow`.
+
+    Args:
+      - policy (str | None): The overflow policy, `None` restores the
+                             default.
+
+    Raises:
+      - ValueError: If the policy is unknown.
+
+    Example:
+    ```python
+    >>> jsonc.loads("18446744073709551616")
+    1.8446744073709552e+19
+    >>> set_int_overflow("bigint-via-string")
+    >>> jsonc.loads("18446744073709551616")
+    18446744073709551616
+    >>> nix.dumps({"big": 2**64}, indent=None)
+    '{ big = "18446744073709551616"; }'
+    >>> set_int_overflow("wrap")
+    >>> jsonc.loads("18446744073709551617")
+    1
+    ```
+    """
+    ...
+
+def set_float_policy(
+    non_finite: str | None = None,
+    repr: str | None = None,
+    precision: int | None = None,
+) -> None:
+    """
+    Choose what the converters do with infinite and NaN floats, and how the
+    serializers write floats.
+
+    Nix evaluates `1.0e308 * 10` to an infinite float, and NaN floats come
+    out of such arithmetic too, while neither nix nor JSON has a literal for
+    them. With the default policy, they are converted to Python floats and
+    given to nix as they are, but `nix.dumps`, `nix.eval_to_json` and
+    `jsonc.dumps` raise a `ConversionError`, while `nix.eval_to_toml` and
+    `nix.eval_to_yaml` write the literals of TOML and YAML. The non-finite
+    policies are:
+      - `error`: The conversions between nix and Python raise a
+                 `ConversionError` too, naming the float.
+      - `none`: The float becomes `None`, or `null` in nix, JSON and YAML text.
+      - `float`: The float is kept where the target can represent it, which
+                 nix and JSON text cannot, so their serializers still raise.
+
+    The repr only changes how finite floats are written, always with a point
+    so they are read back as floats:
+      - `shortest`: The fewest digits reading back to the same float, with an
+                    exponent for very large or small ones, like `1e-7`.
+      - `decimal`: Positional notation, never with an exponent, like
+                   `0.0000001`.
+      - `scientific`: Always with an exponent, like `1.5e3`.
+
+    The policies apply to all the converters and serializers, on all
+    threads, but the serializers and evaluators given their own
+    `float_policy`, `float_repr` or `float_precision`. Each argument left to
+    `None` restores its default.
+
+    Args:
+      - non_finite (str | None): The non-finite float policy.
+      - repr (str | None): The repr of floats, `shortest` by default.
+      - precision (int | None): The number of digits written after the point,
+                                of the mantissa in `scientific` repr, rounding
+                                the float. Only `decimal` and `scientific`
+                                reprs have a precision.
+
+    Raises:
+      - ValueError: If the policy or the repr is unknown, or a precision is
+                    given with the `shortest` repr.
+
+    Example:
+    ```python
+    >>> nix.evals("1.0e308 * 10")
+    inf
+    >>> set_float_policy(non_finite="none")
+    >>> nix.evals("[ (1.0e308 * 10) 1.5 ]")
+    [None, 1.5]
+    >>> nix.dumps([float("nan"), 1.5e-7], indent=None)
+    '[ null 1.5e-7 ]'
+    >>> set_float_policy(repr="decimal", precision=3)
+    >>> nix.dumps(2 / 3)
+    '0.667'
+    ```
+    """
+    ...
+
+def set_threads(n: int | None = None) -> None:
+    """
+    Choose how many threads the batch operations of the parsers run on.
+
+    Batch operations process their items in parallel, each on a single
+    thread, and take a `threads` argument overriding this setting for one
+    call. By default they use the number in the `COSUTILS_THREADS`
+    environment variable, or else the number of CPUs available to the
+    process, which constrained environments like CI containers may report
+    too high. The setting applies to all the batch operations, on all
+    threads.
+
+    Args:
+      - n (int | None): The number of threads, `1` disabling parallelism so
+                        every item is processed on the calling thread.
+                        `None` restores the default.
+
+    Raises:
+      - ValueError: If the number of threads is not positive.
+
+    Example:
+    ```python
+    >>> set_threads(1)  # Run batch operations on the calling thread only
+    >>> set_threads(None)
+    ```
+    """
+    ...
+
+def profile(name: str | None = None) -> _Profile:
+    """
+    Get the settings of a profile of the parsers.
+
+    Profiles bundle the strictness, the coloring of error messages and the
+    resource limits of evaluations under a name, so scripts of a team parse
+    documents the same way. The settings of the `jsonc` and `nix` entries are
+    keyword arguments of the functions of these modules, to select a profile
+    for a single call, while `set_profile` selects it for all the calls.
+
+    The profiles are:
+      - `default`: The defaults of the arguments of the parsers.
+      - `vscode`: Plain error messages for the problems panes of editors, and
+                  evaluations timing out after 10 seconds.
+      - `nix-strict`: Nix warnings turned into errors, and evaluations
+                      limited to 60 seconds and 2 GiB.
+      - `ci`: Plain error messages, strict parsers, and evaluations limited
+              to 5 minutes and 4 GiB.
+
+    Strictness applies to the `jsonc` loaders and to `nix` evaluations only.
+    Manifests have no strict mode, as TOML itself rejects the duplicate keys
+    and the integers overflowing 64 bits that strict loaders reject, and none
+    of the loaders reads YAML.
+
+    Args:
+      - name (str | None): The name of the profile, the selected one if
+                           `None`.
+
+    Returns:
+      - _Profile: The `name` of the profile, whether it `color`s messages,
+                  `None` leaving it to `NO_COLOR`, and its `jsonc` and `nix`
+                  arguments.
+
+    Raises:
+      - ValueError: If no profile has that name.
+
+    Example:
+    ```python
+    >>> profile("nix-strict")["nix"]
+    {'strict': True, 'timeout': 60.0, 'max_memory_mb': 2048}
+    >>> nix.evals("let unused = 1; in 2", **profile("nix-strict")["nix"])
+    Traceback (most recent call last):
+    ...
+    EvaluationError: error: evaluation emitted warnings in strict mode
+    ```
+    """
+    ...
+
+def set_profile(name: str | None = None) -> None:
+    """
+    Select a profile of the parsers for all the later calls, see `profile`.
+
+    Arguments left unset take the values of the profile, so `nix.evals(expr)`
+    is strict under `nix-strict` but `nix.evals(expr, strict=False)` is not.
+    The coloring of error messages is set as by `set_color` too. The profile
+    applies to all the parsers, on all threads.
+
+    Args:
+      - name (str | None): The name of the profile, `None` restores
+                           `default`.
+
+    Raises:
+      - ValueError: If no profile has that name.
+    """
+    ...
+
+def set_error_hook(
+    hook: Callable[[Exception], BaseException | None] | None,
+) -> None:
+    """
+    Set a hook translating the errors raised by the parsers.
+
+    The hook is called with every `ParseError`, `EvaluationError` and
+    `ConversionError` about to be raised, once its message is rendered and
+    its `code` and `errors` are set, so applications can raise their own
+    exception types instead. An error gathering several others, see
+    `errors`, is given to the hook as a whole. The hook applies to every
+    later error of all the parsers, on all threads, but not to the errors
+    raised within the hook itself.
+
+    Errors can be recorded by the hook, but not suppressed, as the functions
+    raising them have no value to return instead.
+
+    Args:
+      - hook (Callable | None): The hook, returning the exception to raise
+                                instead of the error, or `None` to raise the
+                                error as is. Exceptions raised by the hook are
+                                raised instead of the error too. `None`
+                                removes the hook.
+
+    Raises:
+      - TypeError: If the hook is not callable.
+
+    Example:
+    ```python
+    >>> class ConfigError(Exception): ...
+    >>> set_error_hook(lambda e: ConfigError(e.code))
+    >>> nix.evals("1 +")
+    Traceback (most recent call last):
+    ...
+    ConfigError: NIX_E001
+    ```
+    """
+    ...
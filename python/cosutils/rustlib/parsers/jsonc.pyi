@@ -1,3 +1,6 @@
+import os
+from typing import IO, Any, TypedDict
+
 _JsonValue = (
     None
     | bool
@@ -8,33 +11,529 @@ _JsonValue = (
     | dict[str, "_JsonValue"]
 )
 
-def load(path: str) -> _JsonValue:
+class _Truncation(TypedDict):
+    message: str
+    start: int
+    line: int
+
+_TruncatedJsonValue = _JsonValue | tuple[_JsonValue, _Truncation | None]
+
+class _ManagedRegion(TypedDict):
+    name: str
+    start: int
+    end: int
+    line: int
+    content: str
+
+def dump(
+    obj: Any,
+    path: str | os.PathLike[str],
+    indent: int | None = 2,
+    trailing_commas: bool = False,
+    float_policy: str | None = None,
+    float_repr: str | None = None,
+    float_precision: int | None = None,
+) -> None:
+    """
+    Serialize a Python object to a JSON file, like `dumps`, ending it with a
+    newline.
+
+    Args:
+      - obj (Any): The value to serialize, see `dumps`.
+      - path (str | os.PathLike): The path of the file, replaced if it exists.
+      - indent (int | None): The number of spaces to indent nested arrays and
+                             objects by, `None` for a single line.
+      - trailing_commas (bool): Write a comma after the last item of each
+                                array and object spanning several lines.
+      - float_policy (str | None): What to do with infinite and NaN floats,
+                                   see `dumps`.
+      - float_repr (str | None): How to write finite floats, see `dumps`.
+      - float_precision (int | None): The digits written after the point,
+                                      see `dumps`.
+
+    Raises:
+      - IOError: If the file cannot be written.
+      - ConversionError: If a value has no JSON literal, or a dict key is not
+                         a string.
+      - ValueError: If a float policy, repr or precision is invalid.
+
+    Example:
+    ```python
+    >>> settings = load("settings.json")
+    >>> settings["editor.tabSize"] = 4
+    >>> dump(settings, "settings.json")
+    ```
+    """
+    ...
+
+def dumps(
+    obj: Any,
+    indent: int | None = 2,
+    trailing_commas: bool = False,
+    float_policy: str | None = None,
+    float_repr: str | None = None,
+    float_precision: int | None = None,
+) -> str:
+    """
+    Serialize a Python object to JSON, the inverse of `loads`.
+
+    Non-ASCII characters are written as they are, and only the quotes, the
+    backslashes and the control characters are escaped. Keys are written in
+    the order of the dict. Integers too large for 64 bits are written with
+    all their digits, like `json.dumps` does.
+
+    Args:
+      - obj (Any): The value to serialize, made of `None`, `bool`, `int`,
+                   `float`, `str`, `list`, `tuple` and `dict` with `str`
+                   keys.
+      - indent (int | None): The number of spaces to indent nested arrays and
+                             objects by, one item per line. `None` writes the
+                             document on a single line.
+      - trailing_commas (bool): Write a comma after the last item of each
+                                array and object spanning several lines, as
+                                JSONC allows, so that appending an item only
+                                changes one line.
+      - float_policy (str | None): What to do with infinite and NaN floats,
+                                   `"none"` writing them as `null`, or
+                                   `"error"`. `None` follows
+                                   `set_float_policy`.
+      - float_repr (str | None): How to write finite floats, `"shortest"`,
+                                 `"decimal"` or `"scientific"`. `None`
+                                 follows `set_float_policy`.
+      - float_precision (int | None): The digits written after the point by
+                                      the `decimal` and `scientific` reprs,
+                                      all of them if `None`.
+
+    Returns:
+      - str: The JSON document.
+
+    Raises:
+      - ConversionError: If a value has no JSON literal, like an infinite
+                         float or an object of another type, or a dict key is
+                         not a string.
+      - ValueError: If a float policy or repr is unknown, or a precision is
+                    given without the `decimal` or `scientific` repr.
+
+    Example:
+    ```python
+    >>> print(dumps({"name": "café", "ports": [80, 443]}))
+    {
+      "name": "café",
+      "ports": [
+        80,
+        443
+      ]
+    }
+    >>> dumps({"a": [1, 2], "b": {}}, indent=None)
+    '{"a": [1, 2], "b": {}}'
+    >>> print(dumps([1, 2], trailing_commas=True))
+    [
+      1,
+      2,
+    ]
+    >>> dumps([1.5, float("nan")], indent=None, float_policy="none",
+    ...       float_repr="decimal", float_precision=2)
+    '[1.50, null]'
+    ```
+    """
+    ...
+
+def format(
+    content: str,
+    style: str = "expanded",
+    indent: int = 2,
+    range: tuple[int, int] | None = None,
+) -> str:
+    """
+    Reformat a JSONC document to a style, keeping its comments and the text
+    of its strings and numbers as they are.
+
+    Only the whitespace between tokens changes, so the formatted document
+    parses to the same value, and trailing commas are kept. Comments written
+    after a value stay on its line, other comments get their own line, and a
+    blank line between items is kept. New line breaks use the line endings
+    of the document.
+
+    Args:
+      - content (str): The JSONC content as a string.
+      - style (str): The layout, `expanded` for every item of a container
+                     on its own line, or `compact` for every container on a
+                     single line, unless it holds comments.
+      - indent (int): The number of spaces per level of indentation.
+      - range (tuple[int, int] | None): The `start` and `end` byte offsets of
+                                        the selection to reformat, leaving
+                                        the whitespace outside of it as it
+                                        is, for formatting on demand in
+                                        editors.
+
+    Returns:
+      - str: The formatted content.
+
+    Raises:
+      - ValueError: If the style is unknown, or the range is out of the
+                    content.
+      - ParseError: If the content is not valid JSONC.
+
+    Example:
+    ```python
+    >>> print(format('{"a":1, // one\n"b":[true,"x"]}'), end="")
+    {
+      "a": 1, // one
+      "b": [
+        true,
+        "x"
+      ]
+    }
+    >>> format('{\n  "a": [1, 2]\n}', style="compact")
+    '{"a": [1, 2]}\n'
+    ```
+    """
+    ...
+
+def load(
+    path: str | os.PathLike[str] | IO[str] | IO[bytes],
+    strict: bool | None = None,
+    normalize_newlines: bool = True,
+    deadline_ms: float | None = None,
+    dialect: str = "jsonc",
+    on_duplicate: str | None = None,
+    allow_comments: bool | None = None,
+    allow_trailing_commas: bool | None = None,
+    allow_loose_object_property_names: bool | None = None,
+    int_overflow: str | None = None,
+) -> _TruncatedJsonValue:
     """
     Parse a JSONC (JSON with comments) file and convert it to a Python object.
 
     Args:
-      - path (str): The path to the JSONC file.
+      - path (str | os.PathLike | IO): The path to the JSONC file, or a
+                                       file-like object whose `read` returns
+                                       its content as a string or UTF-8
+                                       bytes, like `sys.stdin`. A stream is
+                                       labelled in errors by its `name`, like
+                                       `<stdin>`, or as `<stream>`.
+      - strict (bool | None): Reject irregularities that are tolerated by
+                              default: duplicate keys, unpaired surrogate
+                              escapes and numbers that cannot be represented
+                              without loss, but integers kept by
+                              `int_overflow`. `None` as in the selected
+                              profile.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n` before parsing, so offsets in errors
+                                   match the lines shown by editors.
+      - deadline_ms (float | None): Stop parsing and converting the document
+                                    after this many milliseconds, keeping the
+                                    values converted so far, so interactive
+                                    tools show a partial document instead of
+                                    freezing on a huge one. Only the part of
+                                    the document scanned within an eighth of
+                                    the deadline is parsed.
+      - dialect (str): The syntax of the document, `jsonc` for JSON with
+                       comments and trailing commas, or `json5` for the
+                       unquoted keys, single quoted strings, hexadecimal
+                       numbers, leading `+` and multiline strings of JSON5
+                       too, along with its escapes like `\x41`. JSON5
+                       `Infinity`, `NaN` and numbers starting or ending with
+                       a point are not supported.
+      - on_duplicate (str | None): What to do with a key an object defines
+                                   again: `error` raises a `ParseError`,
+                                   `first` keeps its first value, `last` its
+                                   last one, and `collect` all of them in a
+                                   list, in order. `None` is `error` when
+                                   strict and `last` otherwise.
+      - allow_comments (bool | None): Accept line and block comments. `None`
+                                      as the dialect allows, which both do.
+      - allow_trailing_commas (bool | None): Accept a comma after the last
+                                             item of an array or an object.
+                                             `None` as the dialect allows,
+                                             which both do.
+      - allow_loose_object_property_names (bool | None): Accept unquoted
+                                                         keys, like
+                                                         `{a: 1}`. `None` as
+                                                         the dialect allows,
+                                                         which both do.
+      - int_overflow (str | None): What to do with integers that do not fit
+                                   into 64 bits, `error`, `wrap` or
+                                   `bigint-via-string` as described by
+                                   `set_int_overflow`, `None` for the policy it
+                                   selected.
 
     Returns:
-      - _JsonValue: A Python object representing a valid JSON value.
+      - _TruncatedJsonValue: A Python object representing a valid JSON value,
+                             or with a deadline, a tuple of the value, whole
+                             or truncated, and where it got truncated, `None`
+                             if it did not, with a `message`, the `start`
+                             offset and `line` of the first value left out.
 
     Raises:
-      - IOError: If the file cannot be read.
+      - IOError: If the file or the stream cannot be read.
+      - TypeError: If `path` is neither a path nor a file-like object.
+      - ValueError: If the deadline is negative, or the dialect, the
+                    duplicate key policy or the overflow policy is unknown.
       - ParseError: If the content is not valid JSONC.
     """
     pass
 
-def loads(expr: str) -> _JsonValue:
+def loads(
+    expr: str,
+    strict: bool | None = None,
+    normalize_newlines: bool = True,
+    deadline_ms: float | None = None,
+    dialect: str = "jsonc",
+    on_duplicate: str | None = None,
+    allow_comments: bool | None = None,
+    allow_trailing_commas: bool | None = None,
+    allow_loose_object_property_names: bool | None = None,
+    int_overflow: str | None = None,
+) -> _TruncatedJsonValue:
     """
     Parse a JSONC (JSON with comments) string and convert it to a Python object.
 
     Args:
       - content (str): The JSONC content as a string.
+      - strict (bool | None): Reject irregularities that are tolerated by
+                              default: duplicate keys, unpaired surrogate
+                              escapes and numbers that cannot be represented
+                              without loss, but integers kept by
+                              `int_overflow`. `None` as in the selected
+                              profile.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n` before parsing, so offsets in errors
+                                   match the lines shown by editors.
+      - deadline_ms (float | None): Stop parsing and converting the document
+                                    after this many milliseconds, keeping the
+                                    values converted so far, so interactive
+                                    tools show a partial document instead of
+                                    freezing on a huge one. Only the part of
+                                    the document scanned within an eighth of
+                                    the deadline is parsed.
+      - dialect (str): The syntax of the document, `jsonc` for JSON with
+                       comments and trailing commas, or `json5` for the
+                       unquoted keys, single quoted strings, hexadecimal
+                       numbers, leading `+` and multiline strings of JSON5
+                       too, along with its escapes like `\x41`. JSON5
+                       `Infinity`, `NaN` and numbers starting or ending with
+                       a point are not supported.
+      - on_duplicate (str | None): What to do with a key an object defines
+                                   again: `error` raises a `ParseError`,
+                                   `first` keeps its first value, `last` its
+                                   last one, and `collect` all of them in a
+                                   list, in order. `None` is `error` when
+                                   strict and `last` otherwise.
+      - allow_comments (bool | None): Accept line and block comments. `None`
+                                      as the dialect allows, which both do.
+      - allow_trailing_commas (bool | None): Accept a comma after the last
+                                             item of an array or an object.
+                                             `None` as the dialect allows,
+                                             which both do.
+      - allow_loose_object_property_names (bool | None): Accept unquoted
+                                                         keys, like
+                                                         `{a: 1}`. `None` as
+                                                         the dialect allows,
+                                                         which both do.
+      - int_overflow (str | None): What to do with integers that do not fit
+                                   into 64 bits, `error`, `wrap` or
+                                   `bigint-via-string` as described by
+                                   `set_int_overflow`, `None` for the policy it
+                                   selected.
 
     Returns:
-      - _JsonValue: A Python object representing a valid JSON value.
+      - _TruncatedJsonValue: A Python object representing a valid JSON value,
+                             or with a deadline, a tuple of the value, whole
+                             or truncated, and where it got truncated, `None`
+                             if it did not, with a `message`, the `start`
+                             offset and `line` of the first value left out.
 
     Raises:
+      - ValueError: If the deadline is negative, or the dialect, the
+                    duplicate key policy or the overflow policy is unknown.
       - ParseError: If the content is not valid JSONC.
     """
     pass
+
+def managed_regions(content: str) -> list[_ManagedRegion]:
+    """
+    List the regions of a JSONC document managed by a tool.
+
+    A managed region is delimited by line comments, from
+    `// cosutils:begin <name>` to `// cosutils:end`, and its content is meant
+    to be regenerated with `replace_managed` while the user edits the rest of
+    the document.
+
+    Args:
+      - content (str): The JSONC content as a string.
+
+    Returns:
+      - list[_ManagedRegion]: The regions, with their `name`, the `start` and
+                              `end` byte offsets of the lines between their
+                              markers, the `line` of their begin marker and
+                              their `content`.
+
+    Raises:
+      - ParseError: If the markers are unnamed, nested, duplicated or do not
+                    pair up.
+    """
+    pass
+
+class JsoncDocument:
+    """
+    A JSONC document edited in place, keeping its comments, the order of its
+    keys and its formatting.
+
+    Values are set and removed by their path, either a single key of the
+    root object, like `"editor.fontSize"` in the flat `settings.json` of VS
+    Code, or a list of the keys and indices leading to them, like
+    `["launch", "configurations", 0]`. Setting an existing value replaces it
+    where it is, with the comments around it, while new keys are appended to
+    their object, indented like its other properties. Setting a value under
+    keys that do not exist creates the objects leading to it. The document is
+    written back with `str`.
+
+    Like `NixAst`, a document can only be used on the thread that parsed it.
+
+    Args:
+      - content (str): The JSONC content as a string.
+
+    Raises:
+      - ParseError: If the content is not valid JSONC.
+
+    Example:
+    ```python
+    >>> doc = JsoncDocument('{\n  // Big enough\n  "editor.fontSize": 12\n}\n')
+    >>> doc.set("editor.fontSize", 14)
+    >>> doc.set("files.autoSave", "onFocusChange")
+    >>> print(doc, end="")
+    {
+      // Big enough
+      "editor.fontSize": 14,
+      "files.autoSave": "onFocusChange"
+    }
+    >>> doc.remove("files.autoSave")
+    >>> doc.get("editor.fontSize")
+    14
+    ```
+    """
+
+    def __init__(self, content: str) -> None: ...
+    def get(self, path: str | list[str | int]) -> _JsonValue:
+        """
+        Get the value at a path of the document.
+
+        Args:
+          - path (str | list[str | int]): The key of the root object, or the
+                                          keys and indices leading to the
+                                          value.
+
+        Returns:
+          - _JsonValue: The value, converted like `loads` does.
+
+        Raises:
+          - KeyError: If the document has no value at the path.
+          - ValueError: If the path is empty.
+        """
+        ...
+
+    def set(self, path: str | list[str | int], value: Any) -> None:
+        """
+        Set the value at a path of the document, replacing the value there
+        or adding it.
+
+        Args:
+          - path (str | list[str | int]): The key of the root object, or the
+                                          keys and indices leading to the
+                                          value. An index may be the length
+                                          of its array, to append to it.
+          - value (Any): The value, made of the types `dumps` serializes.
+
+        Raises:
+          - TypeError: If a value on the path is not an object or an array,
+                       as the path needs.
+          - IndexError: If an index is past the end of its array.
+          - ValueError: If the path is empty.
+          - ConversionError: If the value cannot be serialized to JSON.
+        """
+        ...
+
+    def remove(self, path: str | list[str | int]) -> None:
+        """
+        Remove the value at a path of the document, with its key if it is a
+        property.
+
+        Args:
+          - path (str | list[str | int]): The key of the root object, or the
+                                          keys and indices leading to the
+                                          value.
+
+        Raises:
+          - KeyError: If the document has no value at the path.
+          - ValueError: If the path is empty.
+        """
+        ...
+
+    @property
+    def value(self) -> _JsonValue:
+        """
+        The value of the whole document, converted like `loads` does
+        """
+        ...
+
+    def __str__(self) -> str: ...
+    def __repr__(self) -> str: ...
+
+def parse_document(content: str) -> JsoncDocument:
+    """
+    Parse a JSONC document to edit it in place, keeping its comments and its
+    formatting, see `JsoncDocument`.
+
+    Args:
+      - content (str): The JSONC content as a string.
+
+    Returns:
+      - JsoncDocument: The document.
+
+    Raises:
+      - ParseError: If the content is not valid JSONC.
+
+    Example:
+    ```python
+    >>> doc = parse_document('{"a": 1 /* one */}')
+    >>> doc.set("a", 2)
+    >>> str(doc)
+    '{"a": 2 /* one */}'
+    ```
+    """
+    ...
+
+def replace_managed(content: str, name: str, replacement: str) -> str:
+    """
+    Replace the content of a region of a JSONC document managed by a tool,
+    see `managed_regions`.
+
+    The markers and everything outside of the region are kept as they are,
+    and the replacement is indented like the begin marker.
+
+    Args:
+      - content (str): The JSONC content as a string.
+      - name (str): The name of the region.
+      - replacement (str): The new content of the region.
+
+    Returns:
+      - str: The updated content.
+
+    Raises:
+      - KeyError: If the document has no region of that name.
+      - ParseError: If the markers are malformed, or the updated content is
+                    not valid JSONC.
+
+    Example:
+    ```python
+    >>> doc = '{\n  // cosutils:begin gen\n  "a": 1\n  // cosutils:end\n}\n'
+    >>> print(replace_managed(doc, "gen", '"b": 2'))
+    {
+      // cosutils:begin gen
+      "b": 2
+      // cosutils:end
+    }
+    ```
+    """
+    pass
@@ -0,0 +1,85 @@
+from typing import Any, TypedDict
+
+class _Target(TypedDict):
+    source: str
+    source_format: str
+    target: str
+    convert: bool
+    format: str
+    template: bool
+    vars: dict[str, Any]
+
+class _Plan(TypedDict):
+    manifest: str
+    root: str
+    vars: dict[str, Any]
+    targets: list[_Target]
+
+def load(path: str, case_sensitive: bool | None = None) -> _Plan:
+    """
+    Load a manifest of config files and resolve it into a deployment plan.
+
+    A manifest is either a TOML file, or a nix file evaluating to an attrset,
+    mapping source files to the targets they are deployed to:
+
+    ```toml
+    root = "~"                      # where relative targets are deployed
+
+    [vars]                          # variables available to templates
+    host = "laptop"
+
+    [[targets]]
+    source = "git/config.nix"       # relative to the manifest
+    target = ".config/git/{{ host }}.json"
+    format = "jsonc"                # convert to this format, if different
+    template = true                 # render the source as a template
+    vars = { user = "me" }          # override variables for this target
+    ```
+
+    `{{ name }}` placeholders of sources and targets are substituted with the
+    variables, and paths starting with `~` are relative to the home
+    directory, `HOME` or else `USERPROFILE`. Only the plan is resolved,
+    sources are neither converted nor rendered.
+
+    Paths of the manifest may use `/` or `\` as separators on every
+    platform, so one manifest serves Windows and Unix machines alike, and the
+    resolved paths use the separators of the platform. Drive letters and UNC
+    paths like `\\server\share` are absolute on Windows only.
+
+    TOML manifests are parsed the same way under every profile, TOML having no
+    irregularity that a strict mode would reject, see `profile`.
+
+    Args:
+      - path (str): The path to the manifest, a `.toml` or `.nix` file.
+      - case_sensitive (bool | None): Whether targets differing only by case
+                                      are distinct files, when checking that
+                                      no two sources are deployed to the same
+                                      target. `None` assumes the file systems
+                                      of the platform, case-insensitive on
+                                      Windows and macOS.
+
+    Returns:
+      - _Plan: The `manifest` path, the `root` of the targets, the global
+               `vars`, and the `targets` in order. Each target has the
+               absolute `source` and `target` paths, the `source_format`
+               detected from the extension of the source, the `format` to
+               deploy and whether it needs a conversion (`convert`), whether
+               it is a `template`, and the `vars` available to it.
+
+    Raises:
+      - IOError: If the manifest cannot be read.
+      - ParseError: If the manifest cannot be parsed, is malformed, uses
+                    undefined variables, refers to missing sources, or
+                    deploys several sources to the same target.
+      - EvaluationError: If a nix manifest cannot be evaluated.
+
+    Example:
+    ```python
+    >>> plan = load("dotfiles/cosutils.toml")
+    >>> plan["targets"][0]["target"]
+    '/home/me/.config/git/laptop.json'
+    >>> plan["targets"][0]["convert"]
+    True
+    ```
+    """
+    ...
@@ -1,3 +1,7 @@
+import os
+import pathlib
+from typing import IO, Any, Callable, Iterator, Protocol, TypedDict
+
 _EvaluatedNixValue = (
     None
     | bool
@@ -6,22 +10,1175 @@ _EvaluatedNixValue = (
     | str
     | list[_EvaluatedNixValue]
     | dict[str, _EvaluatedNixValue]
+    | pathlib.Path
+    | NixFunction
+    | NixThunk
+)
+
+_NixArgument = (
+    None
+    | bool
+    | int
+    | float
+    | str
+    | list[_NixArgument]
+    | tuple[_NixArgument, ...]
+    | dict[str, _NixArgument]
+)
+
+_Fetcher = Callable[[_EvaluatedNixValue], str | os.PathLike[str]]
+
+class _NixIO(Protocol):
+    def read_file(self, path: str) -> bytes | str: ...
+    def path_exists(self, path: str) -> bool: ...
+    def read_dir(self, path: str) -> dict[str, str]: ...
+
+class _Coverage(TypedDict):
+    set: list[str]
+    default: list[str]
+    unknown: list[str]
+
+class _FlakeInput(TypedDict):
+    node: str
+    follows: list[str] | None
+
+class _FlakeNode(TypedDict):
+    type: str | None
+    rev: str | None
+    narHash: str | None
+    lastModified: int | None
+    locked: dict[str, Any] | None
+    original: dict[str, Any] | None
+    flake: bool
+    inputs: dict[str, _FlakeInput]
+
+class _FlakeLock(TypedDict):
+    version: int | None
+    root: str
+    nodes: dict[str, _FlakeNode]
+
+class _Edit(TypedDict):
+    path: str
+    start: int
+    end: int
+    line: int
+    old: str
+    new: str
+
+class _NixNode(TypedDict):
+    kind: str
+    start: int
+    end: int
+    line: int
+    text: str
+    children: list[_NixNode]
+
+class _Trace(TypedDict):
+    kind: str
+    message: str
+    line: int | None
+
+class _BuiltinProfile(TypedDict):
+    name: str
+    calls: int
+    time: float
+
+class _ExpressionProfile(TypedDict):
+    file: str
+    line: int
+    column: int
+    text: str
+    forces: int
+    calls: int
+    time: float
+
+class _EvaluationProfile(TypedDict):
+    time: float
+    nix_time: float
+    frames: int
+    max_depth: int
+    builtins: list[_BuiltinProfile]
+    expressions: list[_ExpressionProfile]
+
+class _FileAccess(TypedDict):
+    path: str
+    access: str
+    denied: bool
+
+class _FetchAttempt(TypedDict):
+    fetcher: str
+    argument: str
+    outcome: str
+
+class _AccessReport(TypedDict):
+    files: list[_FileAccess]
+    fetches: list[_FetchAttempt]
+    time: float
+    peak_memory: int
+
+_ProfiledNixValue = (
+    _EvaluatedNixValue
+    | tuple[_EvaluatedNixValue, list[_Trace]]
+    | tuple[_EvaluatedNixValue, _EvaluationProfile]
+    | tuple[_EvaluatedNixValue, list[_Trace], _EvaluationProfile]
+    | tuple[_EvaluatedNixValue, _AccessReport]
+    | tuple[_EvaluatedNixValue, list[_Trace], _AccessReport]
+    | tuple[_EvaluatedNixValue, _EvaluationProfile, _AccessReport]
+    | tuple[
+        _EvaluatedNixValue, list[_Trace], _EvaluationProfile, _AccessReport
+    ]
 )
 
-def eval(path: str) -> _EvaluatedNixValue:
+_LiteralValue = None | bool | int | float | str | list[_LiteralValue]
+
+class _Derivation(TypedDict):
+    name: str | None
+    system: str | None
+    builder: str
+    args: list[str]
+    env: dict[str, str]
+    outputs: dict[str, str | None]
+    outPath: str | None
+    drvPath: str | None
+
+class _DiffChange(TypedDict):
+    old: _EvaluatedNixValue
+    new: _EvaluatedNixValue
+
+class _NixDiff(TypedDict):
+    added: dict[str, _EvaluatedNixValue]
+    removed: dict[str, _EvaluatedNixValue]
+    changed: dict[str, _DiffChange]
+
+class _AttrPosition(TypedDict):
+    file: str
+    line: int
+    column: int
+
+class _Diagnostic(TypedDict):
+    code: str
+    message: str
+    start: int
+    end: int
+    line: int
+    column: int
+
+class _OptionDeclaration(TypedDict):
+    path: str
+    type: str | None
+    default: str | None
+    example: str | None
+    description: str | None
+    line: int
+
+class _Formal(TypedDict):
+    name: str
+    default: str | None
+    line: int
+
+class _Formals(TypedDict):
+    name: str | None
+    formals: list[_Formal] | None
+    ellipsis: bool
+
+class _Literal(TypedDict):
+    path: str
+    value: _LiteralValue
+    start: int
+    end: int
+    line: int
+    column: int
+
+class _ManagedRegion(TypedDict):
+    name: str
+    start: int
+    end: int
+    line: int
+    content: str
+
+class _Metrics(TypedDict):
+    size: int
+    lines: int
+    bindings: int
+    functions: int
+    with_expressions: int
+    let_expressions: int
+    max_depth: int
+    imports: list[str]
+    import_fan_out: int
+
+class TraceWarning(UserWarning): ...
+
+class Evaluator:
+    """
+    A nix evaluator with custom settings.
+
+    Args:
+      - builtins (dict[str, Callable]): Extra builtins, available in nix as
+                                        `builtins.<name>`. Each one is backed
+                                        by a Python callable taking as many
+                                        arguments as it has positional
+                                        parameters without default. Arguments
+                                        and the return value are converted
+                                        like `evals` args and results.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - lazy (bool): Only evaluate what is needed to produce the top-level
+                     value, returning unevaluated parts as `NixThunk`.
+      - depth (str | int): How deep results are evaluated, `deep` for the
+                           whole value, `shallow` for its top-level value
+                           only like `lazy`, or a number of levels of
+                           attrsets and lists, `1` being `shallow`. Values
+                           below the depth are returned unevaluated as
+                           `NixThunk`, so listing the top-level attributes of
+                           a large attrset does not evaluate their values.
+                           Results evaluated to a depth are never cached.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, either
+                                    as entries like `nixpkgs=/path/to/nixpkgs`
+                                    or `/path/to/dir`, or joined by `:` like
+                                    the `NIX_PATH` environment variable, which
+                                    itself is never read.
+      - cache (bool | str): Cache converted results, keyed by the expression
+                            and the settings of the evaluator, and invalidated
+                            when a file accessed by the evaluation changes.
+                            `True` caches them in memory, a directory caches
+                            them on disk too. Lazy evaluations and results
+                            that are not plain JSON values are never cached,
+                            and builtins must be pure for the cache to be
+                            correct.
+      - timeout (float): Abort evaluations running longer than this many
+                         seconds, raising an `EvaluationError` that lists the
+                         calls entered last. Evaluations stop at the next
+                         builtin they apply, so code looping without ever
+                         applying one is not stopped. Every later call of a
+                         `NixFunction` or `NixThunk` gets the same time again.
+      - max_memory_mb (int): Abort evaluations once they allocated more than
+                             this many MiB, raising a `ResourceLimitError`.
+                             Only the allocations of the evaluating thread are
+                             counted, while it evaluates. The memory is
+                             checked whenever a builtin is applied, so a
+                             single builtin call may overshoot the limit, but
+                             `genList` checks its list fits before building
+                             it. Every later call of a `NixFunction` or
+                             `NixThunk` gets the same budget again.
+      - max_depth (int): Raise a `ParseError` before evaluating a file or
+                         expression nesting expressions deeper than this, so
+                         untrusted input cannot overflow the stack of the
+                         compiler. Files it imports are not checked. The
+                         parser of rnix has its own fixed limit, which this
+                         can only lower: code nested past it always raises a
+                         `ParseError`.
+      - max_call_depth (int): Abort evaluations nesting more than this many
+                              calls of nix functions and thunks, raising a
+                              `ResourceLimitError` that lists the calls
+                              entered last, at the next builtin they apply.
+                              tvix keeps its calls on the heap and has no
+                              limit of its own, deep recursions being only
+                              bounded by `max_memory_mb`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings of
+                                   the evaluated file or expression to `\n`,
+                                   so offsets in errors match the lines shown
+                                   by editors. Strings spanning several lines
+                                   then also contain `\n` only.
+      - collect_traces (bool): Return the messages of `builtins.trace` and
+                               the warnings of the evaluation along with the
+                               value, as `(value, traces)`, instead of
+                               emitting the messages as `TraceWarning`. Such
+                               results are never cached.
+      - collect_conversion_errors (bool): Convert the whole result even when
+                                          parts of it cannot be converted,
+                                          then raise a `ConversionError`
+                                          listing all of them with their path,
+                                          like `a.b[0]`.
+      - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+                        for `pathlib.Path`. Paths that are not valid UTF-8
+                        raise a `ConversionError` as `str`, and are decoded
+                        like `os.fsdecode` as `pathlib.Path`.
+      - ordered (bool): Convert attrsets with their attributes in the order
+                        they are defined in the evaluated file or
+                        expression, instead of the sorted order of nix, for
+                        results reviewed by humans. The order is found in the
+                        syntax, through functions, `let`, `with`, `if` and
+                        `//`, attributes defined elsewhere, like in imported
+                        files, coming last in sorted order. Values of
+                        `NixFunction` and `NixThunk` keep the sorted order,
+                        and ordered results are never cached.
+      - fetchers (dict[str, Callable]): Fetchers of nix, among `fetchGit`,
+                                        `fetchTarball`, `fetchTree` and
+                                        `fetchurl`, implemented by Python
+                                        callables instead of failing in pure
+                                        evaluations. Each callable is given the
+                                        url or attrset the fetcher is called
+                                        with, converted like `evals` results,
+                                        fetches it however the application
+                                        sees fit, downloading or looking it up
+                                        in a cache, and returns the local
+                                        absolute path of the result as a
+                                        `str` or `os.PathLike`. A builtin of
+                                        the same name takes precedence.
+      - io (_NixIO): An object serving the files of evaluations instead of
+                     the file system, like a database or an in-memory file
+                     system. `read_file(path)` returns the content of a file
+                     as `bytes` or `str`, `path_exists(path)` whether a path
+                     exists, and `read_dir(path)` maps the names of the
+                     entries of a directory to their type, `"regular"`,
+                     `"directory"`, `"symlink"` or `"unknown"` like
+                     `builtins.readDir`. An optional `file_type(path)` returns
+                     the type of a path, which is a directory otherwise if
+                     `read_dir` succeeds. Paths are given as `str`, and
+                     raising `FileNotFoundError` reports missing files like
+                     the file system would. Files evaluated by `eval` are
+                     read from it too. It cannot be used with `cache`.
+      - fetch_cache (FetchCache): Where the results of the `fetchers` are
+                                  cached, which may be shared with other
+                                  evaluators, see `FetchCache`.
+      - restrict_paths (list[str]): The only directories evaluations may
+                                    access, for evaluating untrusted
+                                    expressions. Importing, reading or
+                                    listing any other path, or a symlink
+                                    leading out of the directories, raises
+                                    an `EvaluationError`. The file given to
+                                    `eval` itself needs not be in them.
+      - allow_import (bool): Let evaluations import and read files. `False`
+                             raises an `EvaluationError` on any access to a
+                             path, like an empty `restrict_paths`.
+      - profile (bool): Measure where evaluations spend their time, returned
+                        along with the value and the traces, if collected,
+                        as `(value, profile)`. The profile has the total
+                        `time` in seconds, the `nix_time` spent running nix
+                        code itself, the number of `frames` of nix functions
+                        and thunks run, a frame counting again whenever it
+                        resumes after a call, their `max_depth`, the 20
+                        `builtins` taking the most time, with the `calls` of
+                        each and the `time` spent in it, excluding the nix
+                        code it forced, and the 20 `expressions` whose own
+                        code took the most time. Each expression has its
+                        `file`, `line` and `column`, the first line of its
+                        `text`, the `time` spent running it, excluding the
+                        expressions it contains, and the `forces` and `calls`
+                        of the thunks and functions whose code starts with
+                        it. Profiled results are never cached.
+      - report (bool): Return what each evaluation accessed along with the
+                       value, last in the tuple of collected results, as
+                       `(value, report)`, to audit untrusted expressions. The
+                       report lists the `files` read, listed or checked,
+                       each with its `path`, `access` and whether it was
+                       `denied` by `restrict_paths`, the `fetches` attempted,
+                       each with its `fetcher`, `argument` and `outcome`,
+                       fetchers the evaluator has no callable for being
+                       denied, and the `time` in seconds and `peak_memory`
+                       in bytes of the evaluation. Evaluations are pure, so
+                       they cannot query environment variables at all. A
+                       failed evaluation attaches its report to the
+                       exception raised, as its `report` attribute. Reported
+                       results are never cached.
+      - store_paths (bool): Give the paths nix would add to the store the
+                            `/nix/store` path nix computes for them, hashing
+                            them like nix does without copying them anywhere,
+                            instead of their own path. This applies to paths
+                            interpolated into strings, like `"${./src}"`, and
+                            to `builtins.path`, with its `name`, `filter`,
+                            `recursive` and `sha256` arguments. Files of a
+                            custom `io` are never executable. Such results
+                            are never cached.
+      - flake_registry (dict[str, str]): Define `builtins.getFlake`, getting
+                                         the flakes of this registry, which
+                                         maps flake ids or references to the
+                                         directory of a local checkout, like
+                                         `{"nixpkgs": "/src/nixpkgs"}`, so
+                                         expressions using it evaluate
+                                         offline. References are looked up as
+                                         written, then by their flake id,
+                                         without their `flake:` scheme and
+                                         their ref, and absolute paths, with
+                                         or without `path:`, are used as they
+                                         are. Flakes are evaluated like
+                                         `eval_flake`, with the inputs pinned
+                                         by their `flake.lock`. Without a
+                                         registry, `getFlake` is not defined.
+                                         Such results are never cached.
+      - pinned (dict[str, int | str]): Fixed values for the builtins that
+                                       depend on the machine or the time of
+                                       an evaluation, so that snapshots of
+                                       evaluated values are the same
+                                       everywhere. `currentTime` is pinned to
+                                       an int, which defines it in pure
+                                       evaluations, and `currentSystem` and
+                                       `nixVersion` to a str. nix has no
+                                       random builtin, and evaluations are
+                                       pure, so they cannot read the
+                                       environment at all.
+      - int_overflow (str | None): What to do with the Python integers that
+                                   do not fit into 64 bits, given to nix as
+                                   `args`, `scope` or `override` or returned
+                                   by builtins: `error`, `wrap` or
+                                   `bigint-via-string` as described by
+                                   `set_int_overflow`, `None` for the policy it
+                                   selected.
+      - float_policy (str | None): What to do with the infinite and NaN floats
+                                   of the results and of the values given to
+                                   nix, `"error"`, `"none"` or `"float"`, and
+                                   of the JSON, TOML and YAML written from
+                                   them. `None` follows `set_float_policy`.
+      - float_repr (str | None): How `eval_to_json`, `eval_to_toml` and
+                                 `eval_to_yaml` write finite floats,
+                                 `"shortest"`, `"decimal"` or `"scientific"`.
+                                 `None` follows `set_float_policy`.
+      - float_precision (int | None): The digits written after the point by
+                                      the `decimal` and `scientific` reprs,
+                                      all of them if `None`.
+
+    Raises:
+      - TypeError: If a builtin or a fetcher is not callable, the io lacks
+                   one of its methods, or a builtin is pinned to a value of
+                   another type than its own.
+      - ValueError: If a builtin accepts no positional argument, a fetcher is
+                    unknown or does not accept exactly one, the timeout, the
+                    memory limit or a depth limit is not positive, the depth,
+                    the path conversion, the overflow policy or a float policy
+                    is unknown, a float precision is given without its repr,
+                    both an io and a cache are given, or a builtin that cannot
+                    be pinned is.
+
+    Messages of `builtins.trace` are emitted with the Python `warnings`
+    module as `TraceWarning` by default, and so are those of later calls of a
+    `NixFunction` or `NixThunk`, or of an evaluation that fails. Warnings of
+    non-strict evaluations are only reported when collecting the traces.
+
+    When an evaluation fails with several errors, the exception raised is of
+    the type of the first one, renders all of them, and lists each of them in
+    its `errors` attribute. Errors raised in a function or an imported file
+    show where they were raised, then each call that led there, in the file
+    of each frame. Errors about an unknown variable or a missing attribute
+    suggest the names it may be a typo of, among the variables in scope and
+    the attributes of the attrset it is selected from, when the code defines
+    them.
+
+    Files importing each other so that their imports never finish, like
+    `a.nix` importing `b.nix` to extend its attrset and `b.nix` doing the same
+    with `a.nix`, raise an `EvaluationError` naming the files of the cycle,
+    like `b.nix -> a.nix -> b.nix`, instead of recursing until the memory
+    runs out. It shows each import of the cycle, and lists its files in its
+    `cycle` attribute.
+
+    Evaluators are immutable, so one can be shared between threads. Each
+    evaluation runs on the thread calling it, and the builtins are called on
+    that thread too.
+
+    Example:
+    ```python
+    >>> evaluator = Evaluator(builtins={"double": lambda x: x * 2})
+    >>> evaluator.evals("builtins.double 21")
+    42
+    ```
+    """
+
+    def __init__(
+        self,
+        builtins: dict[str, Callable[..., _NixArgument]] | None = None,
+        strict: bool | None = None,
+        lazy: bool = False,
+        depth: str | int | None = None,
+        nix_path: list[str] | str | None = None,
+        cache: bool | str | None = None,
+        timeout: float | None = None,
+        max_memory_mb: int | None = None,
+        max_depth: int | None = None,
+        max_call_depth: int | None = None,
+        normalize_newlines: bool = True,
+        collect_traces: bool = False,
+        collect_conversion_errors: bool = False,
+        paths_as: str = "str",
+        ordered: bool = False,
+        fetchers: dict[str, _Fetcher] | None = None,
+        io: _NixIO | None = None,
+        fetch_cache: FetchCache | None = None,
+        restrict_paths: list[str | os.PathLike[str]] | None = None,
+        allow_import: bool = True,
+        profile: bool = False,
+        report: bool = False,
+        store_paths: bool = False,
+        flake_registry: dict[str, str | os.PathLike[str]] | None = None,
+        pinned: dict[str, int | str] | None = None,
+        int_overflow: str | None = None,
+        float_policy: str | None = None,
+        float_repr: str | None = None,
+        float_precision: int | None = None,
+    ) -> None: ...
+    def __repr__(self) -> str: ...
+    def _repr_pretty_(self, p: Any, cycle: bool) -> None: ...
+    def eval(
+        self,
+        path: str | os.PathLike[str] | IO[str] | IO[bytes],
+        override: dict[str, _NixArgument] | None = None,
+    ) -> _ProfiledNixValue:
+        """
+        Evaluate a nix file with this evaluator, see `eval`.
+        """
+        ...
+
+    def eval_many(
+        self, paths: list[str], jobs: int | None = None
+    ) -> list[_ProfiledNixValue | Exception]:
+        """
+        Evaluate nix files in parallel with this evaluator, see `eval_many`.
+        """
+        ...
+
+    def eval_to_json(self, path: str) -> str:
+        """
+        Evaluate a nix file with this evaluator to a JSON string, see
+        `eval_to_json`.
+        """
+        ...
+
+    def eval_to_toml(self, path: str) -> str:
+        """
+        Evaluate a nix file with this evaluator to a TOML document, see
+        `eval_to_toml`.
+        """
+        ...
+
+    def eval_to_yaml(self, path: str) -> str:
+        """
+        Evaluate a nix file with this evaluator to a YAML document, see
+        `eval_to_yaml`.
+        """
+        ...
+
+    def diff(self, path_a: str, path_b: str) -> _NixDiff:
+        """
+        Evaluate two nix files with this evaluator and diff their values, see
+        `diff`.
+        """
+        ...
+    def eval_validated(
+        self,
+        path: str | os.PathLike[str] | IO[str] | IO[bytes],
+        schema: dict[str, Any],
+    ) -> _EvaluatedNixValue:
+        """
+        Evaluate a nix file with this evaluator and validate its value
+        against a JSON Schema, see `eval_validated`.
+        """
+        ...
+
+    def eval_with_positions(
+        self, path: str | os.PathLike[str] | IO[str] | IO[bytes]
+    ) -> tuple[_EvaluatedNixValue, dict[str, _AttrPosition]]:
+        """
+        Evaluate a nix file with this evaluator, along with the positions of
+        the definitions of its attributes, see `eval_with_positions`.
+        """
+        ...
+
+    def eval_attr(self, path: str, attr_path: str) -> _ProfiledNixValue:
+        """
+        Evaluate only an attribute of a nix file with this evaluator, see
+        `eval_attr`.
+        """
+        ...
+
+    def evals(
+        self,
+        expr: str,
+        dir: str | None = None,
+        args: dict[str, _NixArgument] | None = None,
+        scope: dict[str, _NixArgument] | None = None,
+        virtual_name: str | None = None,
+        jail: bool = False,
+    ) -> _ProfiledNixValue:
+        """
+        Evaluate a nix expression with this evaluator, see `evals`.
+        """
+        ...
+
+    def eval_drv(
+        self,
+        content: str,
+        dir: str | None = None,
+        virtual_name: str | None = None,
+    ) -> _Derivation:
+        """
+        Evaluate a nix expression to a derivation with this evaluator, and
+        describe it, see `eval_drv`.
+        """
+        ...
+
+class FetchCache:
+    """
+    A cache of the resources fetched by the `fetchers` of evaluators, shared
+    by all the evaluators given the same cache.
+
+    Each call of a fetcher is keyed by the name of the fetcher and its
+    argument, so `fetchTarball "https://..."` is fetched once, and later
+    calls get the path the first one returned as long as it still exists.
+    The cache only remembers paths, the fetched files stay wherever the
+    fetchers put them and are never deleted by the cache. Only the fetchers
+    given to an evaluator go through its cache.
+
+    Args:
+      - ttl (float | None): For how many seconds a fetched resource is used,
+                            after which it is fetched again. `None` keeps it
+                            forever.
+      - max_size_mb (int | None): How many MiB of fetched files the cache
+                                  remembers, forgetting the least recently
+                                  used resources first. `None` does not limit
+                                  it.
+      - offline (bool): Never call the fetchers. Evaluations needing a
+                        resource that is not cached raise an
+                        `EvaluationError` listing all such resources.
+
+    Raises:
+      - ValueError: If the ttl or the size limit is not positive.
+
+    Example:
+    ```python
+    >>> fetchers = {"fetchTarball": download}
+    >>> cache = FetchCache(ttl=3600)
+    >>> Evaluator(fetchers=fetchers, fetch_cache=cache).eval("default.nix")
+    >>> offline = FetchCache(offline=True)
+    >>> Evaluator(fetchers=fetchers, fetch_cache=offline).eval("default.nix")
+    Traceback (most recent call last):
+    ...
+    EvaluationError: The evaluation needs resources that are not fetched ...
+      - fetchTarball "https://example.com/src.tar.gz"
+    ```
+    """
+
+    def __init__(
+        self,
+        ttl: float | None = None,
+        max_size_mb: int | None = None,
+        offline: bool = False,
+    ) -> None: ...
+    def clear(self) -> None:
+        """
+        Forget every cached resource.
+        """
+        ...
+    def __len__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+class NixAst:
+    """
+    A node of the syntax tree of a nix file or expression, produced without
+    evaluating it.
+
+    Nodes know their `kind`, named like the `SyntaxKind` of rnix
+    (`NODE_APPLY`, `NODE_ATTRPATH_VALUE`, ...), their position and their
+    source `text`, and can be searched for the nodes they contain.
+
+    Like the values of evaluations, nodes can only be used on the thread that
+    parsed them.
+
+    Args:
+      - expr (str): The nix expression to parse.
+
+    Raises:
+      - ParseError: If the nix code cannot be parsed.
+
+    Example:
+    ```python
+    >>> root = NixAst('{ src = fetchurl { url = "x"; }; imports = [ ]; }')
+    >>> [(c.text, c.line, c.column) for c in root.calls("fetchurl")]
+    [('fetchurl { url = "x"; }', 1, 9)]
+    >>> [v.kind for v in root.get("imports")]
+    ['NODE_LIST']
+    ```
+    """
+
+    def __init__(self, expr: str) -> None: ...
+    @staticmethod
+    def load(path: str) -> NixAst:
+        """
+        Parse a nix file.
+
+        Args:
+          - path (str): The path to the nix file.
+
+        Returns:
+          - NixAst: The root of the syntax tree.
+
+        Raises:
+          - IOError: If the file cannot be read.
+          - ParseError: If the nix file cannot be parsed.
+        """
+        ...
+
+    @property
+    def kind(self) -> str: ...
+    @property
+    def start(self) -> int:
+        """
+        The byte offset the node starts at
+        """
+        ...
+
+    @property
+    def end(self) -> int:
+        """
+        The byte offset the node ends at
+        """
+        ...
+
+    @property
+    def line(self) -> int:
+        """
+        The 1-based line the node starts on
+        """
+        ...
+
+    @property
+    def column(self) -> int:
+        """
+        The 1-based column the node starts at, in bytes
+        """
+        ...
+
+    @property
+    def text(self) -> str: ...
+    @property
+    def parent(self) -> NixAst | None: ...
+    @property
+    def children(self) -> list[NixAst]: ...
+    def walk(self) -> list[NixAst]:
+        """
+        List the node and all the nodes it contains, in source order.
+
+        Returns:
+          - list[NixAst]: The nodes, each one before its children.
+        """
+        ...
+
+    def find(
+        self, kind: str | None = None, text: str | None = None
+    ) -> list[NixAst]:
+        """
+        Find the nodes matching all the given filters, among the node and all
+        the nodes it contains.
+
+        Args:
+          - kind (str): The kind of the nodes, like `NODE_LAMBDA`.
+          - text (str): The source text of the nodes.
+
+        Returns:
+          - list[NixAst]: The matching nodes, in source order.
+        """
+        ...
+
+    def calls(self, name: str) -> list[NixAst]:
+        """
+        Find the calls of a function, by the name of the variable or of the
+        last attribute it is selected by, so `calls("fetchurl")` finds both
+        `fetchurl { ... }` and `pkgs.fetchurl { ... }`.
+
+        Args:
+          - name (str): The name of the function.
+
+        Returns:
+          - list[NixAst]: The `NODE_APPLY` nodes of the calls, with all their
+                          arguments for curried functions.
+        """
+        ...
+
+    def bindings(self, attrpath: str) -> list[NixAst]:
+        """
+        Find the bindings of an attribute path anywhere in the node, like
+        every `imports = [ ... ];` for `"imports"`.
+
+        Args:
+          - attrpath (str): The attribute path, as written in the bindings.
+
+        Returns:
+          - list[NixAst]: The `NODE_ATTRPATH_VALUE` nodes of the bindings.
+        """
+        ...
+
+    def get(self, attrpath: str) -> list[NixAst]:
+        """
+        Look up the values bound to an attribute path, from the attrset the
+        node evaluates to, so `{ a.b = 1; }` and `{ a = { b = 1; }; }` both
+        bind `a.b`. Functions, `let` and `with` are looked through, like the
+        body of a module.
+
+        Args:
+          - attrpath (str): The attribute path, like `services.foo.enable`.
+
+        Returns:
+          - list[NixAst]: The nodes of the values, empty if the path is not
+                          bound.
+        """
+        ...
+
+    def to_dict(self, tokens: bool = False) -> _NixNode:
+        """
+        Convert the node to dicts, like `parse`.
+
+        Args:
+          - tokens (bool): Also list the tokens of each node among its
+                           children.
+
+        Returns:
+          - _NixNode: The node and all the nodes it contains.
+        """
+        ...
+
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+
+class NixFunction:
+    """
+    A nix function (lambda or builtin) produced by an evaluation.
+
+    Calling it applies the function to the arguments, converted like `evals`
+    args. As nix functions are curried, positional arguments are applied one
+    after another, and keyword arguments are passed as a single attrset after
+    them, so `f(pkgs=pkgs)` calls `{ pkgs, ... }: ...` functions.
+
+    Values of nix evaluations cannot leave the thread they were evaluated on,
+    so the function can only be called on the thread that produced it, and
+    cannot be pickled.
+
+    Raises:
+      - TypeError: If no argument is given.
+      - EvaluationError: If the application fails to evaluate.
+      - ConversionError: If the arguments or the result cannot be converted.
+
+    Example:
+    ```python
+    >>> add = evals("a: b: a + b")
+    >>> add(1, 2)
+    3
+    >>> evals('{ name }: "hello " + name')(name="nix")
+    'hello nix'
+    ```
+    """
+
+    def __call__(
+        self, *args: _NixArgument, **kwargs: _NixArgument
+    ) -> _EvaluatedNixValue: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool:
+        """
+        Functions are only equal to themselves, like in nix
+        """
+        ...
+
+    def _repr_pretty_(self, p: Any, cycle: bool) -> None: ...
+
+class NixThunk:
+    """
+    A part of a lazy evaluation that has not been evaluated yet.
+
+    Like `NixFunction`, it can only be forced on the thread that produced it.
+    Pickling it evaluates it deeply and pickles the plain data it evaluates
+    to instead, so lazy results can be sent to other processes.
+
+    Example:
+    ```python
+    >>> value = evals('{ a = 1 + 1; b = throw "unused"; }', lazy=True)
+    >>> value["a"].force()
+    2
+    >>> pickle.loads(pickle.dumps(value["a"]))
+    2
+    >>> value["a"]
+    <NixThunk evaluated: int>
+    ```
+    """
+
+    def force(self) -> _EvaluatedNixValue:
+        """
+        Evaluate the value and convert it to a Python object.
+
+        Nested values that are still unevaluated are returned as `NixThunk`.
+
+        Returns:
+          - _EvaluatedNixValue: The evaluated value as any Python object
+
+        Raises:
+          - EvaluationError: If the value cannot be evaluated.
+          - ConversionError: If the value cannot be converted.
+        """
+        ...
+
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool:
+        """
+        Thunks are equal when they are the same part of the evaluation, their
+        values are not compared
+        """
+        ...
+
+    def __len__(self) -> int:
+        """
+        The length of the evaluated value.
+        """
+        ...
+
+    def __iter__(self) -> Iterator[Any]:
+        """
+        Iterate over the evaluated value.
+        """
+        ...
+
+    def __getitem__(self, key: Any) -> _EvaluatedNixValue:
+        """
+        Look up an item of the evaluated value, so lazy results can be
+        explored like strict ones.
+        """
+        ...
+
+    def _repr_pretty_(self, p: Any, cycle: bool) -> None: ...
+    def __reduce__(self) -> tuple[Callable[..., Any], tuple[Any]]:
+        """
+        Pickle the plain data the value evaluates to, unpickled as is.
+
+        Raises:
+          - EvaluationError: If the value cannot be evaluated.
+          - ConversionError: If the value contains functions.
+        """
+        ...
+
+class Session:
+    """
+    A scope kept across evaluations, for interactive exploration like the
+    nix REPL.
+
+    Each variable defined is evaluated lazily and kept as is, so the values
+    later expressions force in it stay evaluated for the next ones: defining
+    `pkgs` as nixpkgs evaluates each package once however many times it is
+    explored. Expressions are evaluated with the settings of the evaluator,
+    and never cached. Files read and fetches attempted are recorded for the
+    whole session, so the reports of its evaluations list those of the
+    previous ones too.
+
+    Like `NixThunk`, a session can only be used on the thread that created
+    it.
+
+    Args:
+      - evaluator (Evaluator): The evaluator of the expressions, one with
+                               the default settings if not given.
+      - dir (str): The directory the expressions are evaluated in, relative
+                   paths being resolved from it.
+      - scope (dict): The variables the session starts with, converted
+                      like the `scope` of `evals`.
+
+    Raises:
+      - TypeError: If a variable of `scope` is not named by a string.
+      - ConversionError: If a value of `scope` cannot be converted.
+
+    Example:
+    ```python
+    >>> s = Session(scope={"name": "hello"})
+    >>> s.define("pkgs", "import <nixpkgs> {}")
+    >>> s.eval("pkgs.${name}.meta.license.spdxId")
+    'GPL-3.0-or-later'
+    >>> s.names
+    ['name', 'pkgs']
+    ```
+    """
+
+    def __init__(
+        self,
+        evaluator: Evaluator | None = None,
+        dir: str | None = None,
+        scope: dict[str, _NixArgument] | None = None,
+    ) -> None: ...
+    def define(self, name: str, expr: str) -> None:
+        """
+        Define a variable of the session as the value of a nix expression.
+
+        The expression is only evaluated to weak head normal form, the rest
+        of its value being evaluated as later expressions use it. It can
+        refer to the variables defined before, including the one it
+        redefines.
+
+        Args:
+          - name (str): The name of the variable.
+          - expr (str): The nix expression it is defined as.
+
+        Raises:
+          - ParseError: If the nix expression cannot be parsed.
+          - EvaluationError: If the nix expression cannot be evaluated, or
+                             times out.
+          - ResourceLimitError: If the evaluation exceeds its memory limit.
+        """
+        ...
+
+    def eval(self, expr: str) -> _ProfiledNixValue:
+        """
+        Evaluate a nix expression in the scope of the session, and convert
+        its result like `Evaluator.evals`.
+
+        Args:
+          - expr (str): The nix expression.
+
+        Returns:
+          - _ProfiledNixValue: The evaluated expression as any Python object,
+                               along with its traces, profile and report, as
+                               collected by the evaluator.
+
+        Raises:
+          - ParseError: If the nix expression cannot be parsed.
+          - EvaluationError: If the nix expression cannot be evaluated, or
+                             times out.
+          - ResourceLimitError: If the evaluation exceeds its memory limit.
+          - ConversionError: If the result cannot be converted.
+        """
+        ...
+
+    @property
+    def names(self) -> list[str]:
+        """
+        The variables of the session, sorted, to complete expressions.
+        """
+        ...
+
+    def __repr__(self) -> str: ...
+    def _repr_pretty_(self, p: Any, cycle: bool) -> None: ...
+
+class TokenKind:
+    """
+    The kind of a token for syntax highlighting, stable across versions of
+    rnix. Values are never reused.
+    """
+
+    Whitespace: TokenKind
+    Comment: TokenKind
+    Keyword: TokenKind
+    """`let`, `in`, `if`, `inherit`, `or`, ..."""
+    Identifier: TokenKind
+    """Variables and attribute names, `true`, `false` and `null` included"""
+    Integer: TokenKind
+    Float: TokenKind
+    StringDelimiter: TokenKind
+    """The quotes of a string, `"` or `''`"""
+    String: TokenKind
+    """The text and escapes of a string"""
+    Interpolation: TokenKind
+    """The `${` and `}` around an interpolation"""
+    Path: TokenKind
+    Uri: TokenKind
+    Operator: TokenKind
+    """Arithmetic, logical and attrset operators, `=` and `?` included"""
+    Punctuation: TokenKind
+    """Brackets, `;`, `:`, `,`, `.`, `@` and `...`"""
+    Error: TokenKind
+    """Text the lexer does not recognize"""
+
+    @property
+    def name(self) -> str:
+        """
+        The name of the kind in snake case, like `string_delimiter`, to name
+        the styles of a highlighter
+        """
+        ...
+
+    def __int__(self) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+
+def eval(
+    path: str | os.PathLike[str] | IO[str] | IO[bytes],
+    strict: bool | None = None,
+    lazy: bool = False,
+    depth: str | int | None = None,
+    nix_path: list[str] | str | None = None,
+    cache: bool | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    max_depth: int | None = None,
+    max_call_depth: int | None = None,
+    normalize_newlines: bool = True,
+    collect_traces: bool = False,
+    collect_conversion_errors: bool = False,
+    paths_as: str = "str",
+    ordered: bool = False,
+    profile: bool = False,
+    override: dict[str, _NixArgument] | None = None,
+    int_overflow: str | None = None,
+    float_policy: str | None = None,
+) -> _ProfiledNixValue:
     """
     Evaluate a nix file and convert it to Python object.
 
     Args:
-      - path (str): The path to the nix file.
+      - path (str | os.PathLike | IO): The path to the nix file, or a
+                                       file-like object whose `read` returns
+                                       its content as a string or UTF-8
+                                       bytes, like `sys.stdin`. A stream
+                                       read from a file is evaluated as that
+                                       file, other streams like `evals`
+                                       without `dir`, and labelled in errors
+                                       by their `name`, like `<stdin>`, or as
+                                       `<stream>`.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - lazy (bool): Only evaluate what is needed to produce the top-level
+                     value, returning unevaluated parts as `NixThunk`.
+      - depth (str | int): How deep the result is evaluated, `deep`,
+                           `shallow` or a number of levels of attrsets and
+                           lists, see `Evaluator`.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - cache (bool | str): Cache converted results in memory, or on disk in
+                            the given directory, see `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - max_depth (int): Refuse code nesting expressions deeper than this,
+                         which can only lower the fixed limit of the parser,
+                         see `Evaluator`.
+      - max_call_depth (int): Abort the evaluation once it nests more than
+                              this many calls, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - collect_traces (bool): Return the messages of `builtins.trace` and
+                               the warnings along with the value, instead of
+                               emitting them as `TraceWarning`.
+      - collect_conversion_errors (bool): Raise a single `ConversionError`
+                                          listing every part of the result
+                                          that cannot be converted, see
+                                          `Evaluator`.
+      - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+                        for `pathlib.Path`, see `Evaluator`.
+      - ordered (bool): Convert attrsets with their attributes in the order
+                        they are defined in the source, see `Evaluator`.
+      - profile (bool): Return where the evaluation spent its time along with
+                        the value, to find what slows it down, see
+                        `Evaluator`.
+      - override (dict | None): Attributes merged into the result like
+                                `lib.recursiveUpdate`, to tweak a config
+                                without editing its file. Values are converted
+                                like the args of `evals`, nested dicts are
+                                merged into the attrsets they override
+                                attribute by attribute, and any other value
+                                replaces the value it overrides.
+      - int_overflow (str | None): What to do with the integers of `override`
+                                   that do not fit into 64 bits, see
+                                   `Evaluator`.
+      - float_policy (str | None): What to do with the infinite and NaN floats
+                                   of the result and of `override`, see
+                                   `Evaluator`.
 
     Returns:
-      - _EvaluatedNixValue: The evaluated nix expression as any Python object
+      - _ProfiledNixValue: The evaluated nix expression as any Python object,
+                           or a tuple of it, its traces when collecting them,
+                           and its profile when profiling it.
 
     Raises:
-      - IOError: If the file cannot be read.
-      - ParseError: If the nix file cannot be parsed.
-      - EvaluationError: If the nix expression cannot be evaluated.
+      - IOError: If the file or the stream cannot be read.
+      - TypeError: If `path` is neither a path nor a file-like object.
+      - ValueError: If the depth, the overflow policy or the float policy is
+                    unknown, or a depth limit is not positive.
+      - ParseError: If the nix file cannot be parsed, or is nested too
+                    deeply.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If the evaluation exceeds its memory or call depth
+                            limit.
       - ConversionError: If the result cannot be converted to a Python object.
 
     Example:
@@ -32,31 +1189,1658 @@ def eval(path: str) -> _EvaluatedNixValue:
     # ```
     >>> eval("path/to/file.nix")
     {'a': 1}
+    >>> eval("path/to/file.nix", override={"a": 2, "b": {"c": True}})
+    {'a': 2, 'b': {'c': True}}
     ```
     """
     ...
 
-def evals(expr: str, dir: str | None = None) -> _EvaluatedNixValue:
+def eval_many(
+    paths: list[str],
+    jobs: int | None = None,
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+    collect_traces: bool = False,
+    paths_as: str = "str",
+    ordered: bool = False,
+) -> list[_ProfiledNixValue | Exception]:
     """
-    Evaluate a nix expression and convert it to Python object.
+    Evaluate independent nix files in parallel and convert them to Python
+    objects.
+
+    Each file is evaluated on its own, on one of the threads of the batch
+    operations, with the GIL released during the evaluation and only taken
+    to convert its result. A file failing does not stop the others, its
+    error is returned in place of its value. Results are evaluated deeply,
+    as unevaluated values and functions cannot leave the thread of their
+    evaluation, and are never cached.
 
     Args:
-      - expr (str): The nix expression to evaluate.
-      - dir (str): The base directory to evaluate the expression in, we will
-                   create a vitrual nix file as if the expr is in the file.
+      - paths (list[str]): The paths to the nix files.
+      - jobs (int | None): The number of files evaluated at once, `None` as
+                           set by `set_threads`.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation of a file after this many
+                         seconds.
+      - max_memory_mb (int): Abort the evaluation of a file once it allocated
+                             more than this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - collect_traces (bool): Return the messages of `builtins.trace` and
+                               the warnings along with each value, instead of
+                               emitting them as `TraceWarning`.
+      - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+                        for `pathlib.Path`, see `Evaluator`.
+      - ordered (bool): Convert attrsets with their attributes in the order
+                        they are defined in the source, see `Evaluator`.
+
+    Returns:
+      - list[_ProfiledNixValue | Exception]: The value of each file, in the
+                                             order of `paths`, or a tuple of it
+                                             and its traces when collecting
+                                             them. Files that cannot be read,
+                                             parsed, evaluated or converted give
+                                             the exception `eval` would raise
+                                             instead.
+
+    Raises:
+      - ValueError: If the number of jobs is not positive.
+
+    Example:
+    ```python
+    >>> hosts = sorted(glob.glob("hosts/*.nix"))
+    >>> results = eval_many(hosts, jobs=8)
+    >>> failed = {
+    ...     host: result
+    ...     for host, result in zip(hosts, results)
+    ...     if isinstance(result, Exception)
+    ... }
+    ```
+    """
+    ...
+
+def eval_to_json(
+    path: str,
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+    float_policy: str | None = None,
+    float_repr: str | None = None,
+    float_precision: int | None = None,
+) -> str:
+    """
+    Evaluate a nix file to a JSON string.
+
+    The value is serialized in Rust without converting it to Python objects,
+    which dominates the cost of `eval` on large attrsets, for pipelines
+    parsing the JSON with a faster library. Attributes are sorted like with
+    `builtins.toJSON`, and paths are written as strings. Results are never
+    cached, and traces are emitted as `TraceWarning`.
+
+    Args:
+      - path (str): The path to the nix file.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - float_policy (str | None): What to do with infinite and NaN floats,
+                                   see `Evaluator`.
+      - float_repr (str | None): How to write finite floats, `"shortest"`,
+                                 `"decimal"` or `"scientific"`, see
+                                 `Evaluator`.
+      - float_precision (int | None): The digits written after the point by
+                                      the `decimal` and `scientific` reprs.
 
     Returns:
-      - _EvaluatedNixValue: The evaluated nix expression as any Python object
+      - str: The evaluated nix expression as compact JSON.
 
     Raises:
+      - IOError: If the file cannot be read.
+      - ValueError: If a float policy or repr is unknown, or a precision is
+                    given without the `decimal` or `scientific` repr.
       - ParseError: If the nix file cannot be parsed.
-      - EvaluationError: If the nix expression cannot be evaluated.
-      - ConversionError: If the result cannot be converted to a Python object.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+      - ConversionError: If the result contains a function or a float that
+                         JSON cannot represent.
 
     Example:
     ```python
-    >>> evals("{a = 1;}")
-    {'a': 1}
+    # `path/to/file.nix` contains:
+    # ```
+    # { b = [ 1 2.5 ]; a = "x"; }
+    # ```
+    >>> eval_to_json("path/to/file.nix")
+    '{"a":"x","b":[1,2.5]}'
+    >>> orjson.loads(eval_to_json("path/to/file.nix"))
+    {'a': 'x', 'b': [1, 2.5]}
+    ```
+    """
+    ...
+
+def eval_to_toml(
+    path: str,
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+    float_policy: str | None = None,
+    float_repr: str | None = None,
+    float_precision: int | None = None,
+) -> str:
+    """
+    Evaluate a nix file to a TOML document.
+
+    Like `eval_to_json`, the value is serialized in Rust, for generating the
+    configuration of applications reading TOML. Attributes are sorted like
+    nix sorts them, attrsets become tables and lists of attrsets arrays of
+    tables, other lists and the attrsets in them being written inline. Paths
+    are written as strings. Infinite and NaN floats are written as `inf` and
+    `nan`, unless `float_policy` says otherwise. Results are never cached,
+    and traces are emitted as `TraceWarning`.
+
+    Args:
+      - path (str): The path to the nix file.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - float_policy (str | None): What to do with infinite and NaN floats,
+                                   see `Evaluator`.
+      - float_repr (str | None): How to write finite floats, `"shortest"`,
+                                 `"decimal"` or `"scientific"`, see
+                                 `Evaluator`.
+      - float_precision (int | None): The digits written after the point by
+                                      the `decimal` and `scientific` reprs.
+
+    Returns:
+      - str: The evaluated nix expression as a TOML document.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ValueError: If a float policy or repr is unknown, or a precision is
+                    given without the `decimal` or `scientific` repr.
+      - ParseError: If the nix file cannot be parsed.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+      - ConversionError: If the result is not an attrset, or contains a
+                         function or a `null`, which TOML has no literal for.
+
+    Example:
+    ```python
+    # `path/to/file.nix` contains:
+    # ```
+    # {
+    #   name = "app";
+    #   server = { port = 8080; hosts = [ "a" "b" ]; };
+    #   users = [ { name = "alice"; } { name = "bob"; } ];
+    # }
+    # ```
+    >>> print(eval_to_toml("path/to/file.nix"))
+    name = "app"
+
+    [server]
+    hosts = ["a", "b"]
+    port = 8080
+
+    [[users]]
+    name = "alice"
+
+    [[users]]
+    name = "bob"
+    ```
+    """
+    ...
+
+def eval_to_yaml(
+    path: str,
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+    float_policy: str | None = None,
+    float_repr: str | None = None,
+    float_precision: int | None = None,
+) -> str:
+    """
+    Evaluate a nix file to a YAML document.
+
+    Like `eval_to_json`, the value is serialized in Rust, for generating the
+    configuration of applications reading YAML. The document is in block
+    style, with attributes sorted like nix sorts them. Strings are written
+    plain when no YAML reader can take them for another type, like `yes` or
+    `1.0`, and double-quoted otherwise. Paths are written as strings.
+    Infinite and NaN floats are written as `.inf` and `.nan`, unless
+    `float_policy` says otherwise. Results are never cached, and traces are
+    emitted as `TraceWarning`.
+
+    Args:
+      - path (str): The path to the nix file.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - float_policy (str | None): What to do with infinite and NaN floats,
+                                   see `Evaluator`.
+      - float_repr (str | None): How to write finite floats, `"shortest"`,
+                                 `"decimal"` or `"scientific"`, see
+                                 `Evaluator`.
+      - float_precision (int | None): The digits written after the point by
+                                      the `decimal` and `scientific` reprs.
+
+    Returns:
+      - str: The evaluated nix expression as a YAML document.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ValueError: If a float policy or repr is unknown, or a precision is
+                    given without the `decimal` or `scientific` repr.
+      - ParseError: If the nix file cannot be parsed.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+      - ConversionError: If the result contains a function.
+
+    Example:
+    ```python
+    # `path/to/file.nix` contains:
+    # ```
+    # {
+    #   name = "app";
+    #   enabled = "yes";
+    #   users = [ { name = "alice"; groups = [ "wheel" ]; } ];
+    #   extra = { };
+    # }
+    # ```
+    >>> print(eval_to_yaml("path/to/file.nix"))
+    enabled: "yes"
+    extra: {}
+    name: app
+    users:
+      - groups:
+          - wheel
+        name: alice
+    ```
+    """
+    ...
+
+def diff(
+    path_a: str,
+    path_b: str,
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+) -> _NixDiff:
+    """
+    Evaluate two nix files and diff their values, attribute by attribute.
+
+    Both files are evaluated deeply, and their values compared in Rust
+    without converting the attributes that did not change to Python objects.
+    Attrsets are walked down to the attribute paths whose values differ,
+    other values like lists are compared as a whole, like nix compares them:
+    integers equal floats of the same value, and functions are never equal.
+    Only the values at the reported paths are converted, like with `eval`.
+    Traces of both evaluations are emitted as `TraceWarning`.
+
+    Args:
+      - path_a (str): The path to the nix file of the old value.
+      - path_b (str): The path to the nix file of the new value.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort each evaluation after this many seconds.
+      - max_memory_mb (int): Abort each evaluation once it allocated more
+                             than this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+
+    Returns:
+      - _NixDiff: The attribute paths `added` to the new value with their
+                  new values, `removed` from it with their old values, and
+                  `changed` with their `old` and `new` values, in the sorted
+                  order of nix. Paths are written like in nix, the empty path
+                  standing for whole values that are not both attrsets.
+
+    Raises:
+      - IOError: If a file cannot be read.
+      - ParseError: If a nix file cannot be parsed.
+      - EvaluationError: If a nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If an evaluation exceeds its memory limit.
+      - ConversionError: If a reported value cannot be converted to Python.
+
+    Example:
+    ```python
+    # `old.nix` contains:
+    # ```
+    # { networking.hostName = "a"; services.nginx.enable = true; }
+    # ```
+    # `new.nix` contains:
+    # ```
+    # {
+    #   networking.hostName = "b";
+    #   services."my.app".port = 8080;
+    #   services.nginx.enable = true;
+    # }
+    # ```
+    >>> changes = diff("old.nix", "new.nix")
+    >>> changes["added"], changes["removed"]
+    ({'services."my.app"': {'port': 8080}}, {})
+    >>> changes["changed"]
+    {'networking.hostName': {'old': 'a', 'new': 'b'}}
+    ```
+    """
+    ...
+
+def eval_validated(
+    path: str | os.PathLike[str] | IO[str] | IO[bytes],
+    schema: dict[str, Any],
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+) -> _EvaluatedNixValue:
+    """
+    Evaluate a nix file and validate its value against a JSON Schema, then
+    convert it to Python objects.
+
+    The file is evaluated deeply and its value validated in Rust, before
+    anything is converted. Every value that does not match the schema is
+    reported, each pointing at the expression defining it, found in the
+    syntax through functions, `let` and `with`, and into the files imported
+    with a path literal, like `import ./hosts.nix`, or at the closest
+    definition enclosing it when it is defined elsewhere, like by a function
+    call. Snippets show the files as the evaluation read them.
+
+    The schema supports the `type`, `enum`, `const`, `anyOf`, `properties`,
+    `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+    `minLength`, `maxLength`, `pattern`, `minimum`, `maximum`,
+    `exclusiveMinimum` and `exclusiveMaximum` keywords of JSON Schema, and
+    ignores the others. Nix attrsets are `object`, lists `array`, paths
+    `string`, and integers are also `number`. Patterns are searched with the
+    `re` module of Python.
+
+    Args:
+      - path (str | os.PathLike | IO): The path to the nix file, or a
+                                       file-like object, see `eval`.
+      - schema (dict): The JSON Schema of the value.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+
+    Returns:
+      - _EvaluatedNixValue: The evaluated value, converted like with `eval`.
+
+    Raises:
+      - IOError: If the file or the stream cannot be read.
+      - TypeError: If `path` is neither a path nor a file-like object, or a
+                   keyword of the schema has the wrong type, like
+                   `properties` that is not a dict.
+      - ParseError: If the nix file cannot be parsed.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out, or with the code `NIX_E040` if its value does
+                         not match the schema, listing each violation in its
+                         `errors` attribute.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+      - ConversionError: If the value cannot be converted to Python.
+
+    Example:
+    ```python
+    # `config.nix` contains:
+    # ```
+    # {
+    #   hosts = "a.example.com";
+    #   port = 8080;
+    # }
+    # ```
+    >>> schema = {
+    ...     "type": "object",
+    ...     "properties": {
+    ...         "hosts": {"type": "array", "items": {"type": "string"}},
+    ...         "port": {"type": "integer", "maximum": 65535},
+    ...     },
+    ...     "required": ["hosts", "port"],
+    ... }
+    >>> eval_validated("config.nix", schema)
+    Traceback (most recent call last):
+      ...
+    EvaluationError: error: `hosts`: expected array, got string
+     --> config.nix:2:11
+      |
+    2 |   hosts = "a.example.com";
+      |           ^^^^^^^^^^^^^^^ defined here
+      |
+    ```
+    """
+    ...
+
+
+def eval_with_positions(
+    path: str | os.PathLike[str] | IO[str] | IO[bytes],
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+) -> tuple[_EvaluatedNixValue, dict[str, _AttrPosition]]:
+    """
+    Evaluate a nix file and convert it to Python objects, along with where
+    each of its attributes is defined, to point users back to their nix
+    files.
+
+    The file is evaluated deeply, and the attributes of its value are looked
+    up in its syntax, through functions, `let`, `with` and nested attrsets,
+    down from the top-level attrset, and into the files imported with a path
+    literal, like `import ./hosts.nix`, as the evaluation read them. Each is
+    located at its name in the binding defining it, like
+    `builtins.unsafeGetAttrPos` does, so `a` of `a.b = 1;` is located at
+    `a`. Attributes defined elsewhere, like by a function call, have no
+    position, and neither have the attributes nested in them, nor the
+    attributes of attrsets in lists.
+
+    Args:
+      - path (str | os.PathLike | IO): The path to the nix file, or a
+                                       file-like object, see `eval`.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+
+    Returns:
+      - tuple[_EvaluatedNixValue, dict]: The evaluated value, converted like
+                                         with `eval`, and the positions of
+                                         its attributes by attribute path,
+                                         written like in nix, each with the
+                                         `file`, as given for the evaluated
+                                         file and as the canonical path of
+                                         an imported one, and the 1-based
+                                         `line` and `column`, in bytes.
+
+    Raises:
+      - IOError: If the file or the stream cannot be read.
+      - TypeError: If `path` is neither a path nor a file-like object.
+      - ParseError: If the nix file cannot be parsed.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+      - ConversionError: If the value cannot be converted to Python.
+
+    Example:
+    ```python
+    # `config.nix` contains:
+    # ```
+    # { lib, ... }: {
+    #   services.nginx.enable = true;
+    #   networking = {
+    #     hostName = "foo";
+    #   };
+    # }
+    # ```
+    >>> value, positions = eval_with_positions("config.nix")
+    >>> positions["networking.hostName"]
+    {'file': 'config.nix', 'line': 4, 'column': 5}
+    >>> positions["services"]["line"], positions["services.nginx"]["column"]
+    (2, 12)
+    ```
+    """
+    ...
+
+
+def eval_drv(
+    content: str,
+    dir: str | None = None,
+    strict: bool | None = None,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+    virtual_name: str | None = None,
+) -> _Derivation:
+    """
+    Evaluate a nix expression to a derivation, and describe what it would
+    build without building it.
+
+    The expression is evaluated with a `derivation` builtin that returns the
+    attrset nix would, but gives each output a placeholder store path derived
+    from the attributes of the derivation, instead of the path nix would
+    compute. Fixed-output derivations with a SHA-256 `outputHash` get the
+    path nix computes, which only depends on their name and hash. Derivations
+    made without the builtin are described too, as long as they are attrsets
+    with `type = "derivation"`. Only the description is evaluated deeply, and
+    it only contains strings, so it never raises a `ConversionError` on the
+    internal attributes of the derivation.
+
+    Args:
+      - content (str): The nix expression to evaluate.
+      - dir (str): The base directory to evaluate the expression in, see
+                   `evals`.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - virtual_name (str): The file name of the expression in errors, see
+                            `evals`.
+
+    Returns:
+      - _Derivation: The `name`, `system` and `builder` of the derivation,
+                     the `args` of the builder, its `env` with every
+                     attribute coerced to a string like nix does and the
+                     paths of the outputs, the `outputs` by name, and the
+                     `outPath` and `drvPath` of the derivation. Paths are
+                     not copied to the store. Results are never cached.
+
+    Raises:
+      - ValueError: If the virtual name is not a file name.
+      - ParseError: If the nix expression cannot be parsed.
+      - EvaluationError: If the expression does not evaluate to a
+                         derivation, an attribute cannot be coerced to a
+                         string, or the evaluation times out.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+
+    Example:
+    ```python
+    >>> drv = eval_drv('''
+    ... derivation {
+    ...   name = "hello";
+    ...   system = "x86_64-linux";
+    ...   builder = "/bin/sh";
+    ...   args = [ "-c" "echo hi > $out" ];
+    ...   outputs = [ "out" "doc" ];
+    ... }
+    ... ''')
+    >>> drv["args"], sorted(drv["outputs"])
+    (['-c', 'echo hi > $out'], ['doc', 'out'])
+    >>> drv["env"]["outputs"]
+    'out doc'
+    >>> drv["outputs"]["doc"] == drv["outPath"] + "-doc"
+    True
+    ```
+    """
+    ...
+
+def eval_attr(
+    path: str,
+    attr_path: str,
+    strict: bool | None = None,
+    lazy: bool = False,
+    nix_path: list[str] | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    normalize_newlines: bool = True,
+    collect_traces: bool = False,
+    collect_conversion_errors: bool = False,
+    paths_as: str = "str",
+    ordered: bool = False,
+) -> _EvaluatedNixValue | tuple[_EvaluatedNixValue, list[_Trace]]:
+    """
+    Evaluate only an attribute of a nix file and convert it to a Python
+    object.
+
+    The file is evaluated lazily, and only the attrsets on the way to the
+    attribute are forced, so the other attributes of a large configuration
+    cost nothing. The attribute itself is evaluated deeply unless `lazy`.
+
+    Args:
+      - path (str): The path to the nix file.
+      - attr_path (str): The attribute to evaluate, like `services.nginx`.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - lazy (bool): Only evaluate the attribute to its top-level value,
+                     returning unevaluated parts as `NixThunk`.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - collect_traces (bool): Return the messages of `builtins.trace` and
+                               the warnings along with the value, instead of
+                               emitting them as `TraceWarning`.
+      - collect_conversion_errors (bool): Raise a single `ConversionError`
+                                          listing every part of the result
+                                          that cannot be converted, see
+                                          `Evaluator`.
+      - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+                        for `pathlib.Path`, see `Evaluator`.
+      - ordered (bool): Convert attrsets with their attributes in the order
+                        they are defined in the source, see `Evaluator`.
+
+    Returns:
+      - _EvaluatedNixValue: The evaluated attribute as any Python object, or
+                            a tuple of it and its traces when collecting
+                            them. Results are never cached.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ParseError: If the nix file cannot be parsed.
+      - ValueError: If the attribute path is empty.
+      - EvaluationError: If the attribute is missing or cannot be evaluated,
+                         or the evaluation times out.
+      - ResourceLimitError: If the evaluation exceeds its memory limit.
+      - ConversionError: If the result cannot be converted to a Python object.
+
+    Example:
+    ```python
+    # `path/to/file.nix` contains:
+    # ```
+    # { a.b = 1; c = throw "expensive"; }
+    # ```
+    >>> eval_attr("path/to/file.nix", "a")
+    {'b': 1}
+    ```
+    """
+    ...
+
+def evals(
+    expr: str,
+    dir: str | None = None,
+    args: dict[str, _NixArgument] | None = None,
+    scope: dict[str, _NixArgument] | None = None,
+    strict: bool | None = None,
+    lazy: bool = False,
+    depth: str | int | None = None,
+    nix_path: list[str] | str | None = None,
+    cache: bool | str | None = None,
+    timeout: float | None = None,
+    max_memory_mb: int | None = None,
+    max_depth: int | None = None,
+    max_call_depth: int | None = None,
+    normalize_newlines: bool = True,
+    collect_traces: bool = False,
+    collect_conversion_errors: bool = False,
+    paths_as: str = "str",
+    ordered: bool = False,
+    virtual_name: str | None = None,
+    allow_import: bool = True,
+    jail: bool = False,
+    int_overflow: str | None = None,
+    float_policy: str | None = None,
+) -> _EvaluatedNixValue | tuple[_EvaluatedNixValue, list[_Trace]]:
+    """
+    Evaluate a nix expression and convert it to Python object.
+
+    Args:
+      - expr (str): The nix expression to evaluate.
+      - dir (str): The base directory to evaluate the expression in, as if
+                   it was the content of a file of the directory named
+                   `virtual_name`. Relative paths like `./data.json` or
+                   `import ./lib.nix` are resolved from it, whether the
+                   virtual file exists or not.
+      - args (dict): If given, the expression must evaluate to a function,
+                     which is called with `args` converted to a nix attrset,
+                     like `nix-instantiate --arg`.
+      - scope (dict): Variables visible in the top-level scope of the
+                      expression, converted to nix values, as if it was
+                      wrapped in a `let` binding them but without shifting
+                      the positions of its errors. They shadow the builtins
+                      of the same name, like `let` bindings do.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - lazy (bool): Only evaluate what is needed to produce the top-level
+                     value, returning unevaluated parts as `NixThunk`.
+      - depth (str | int): How deep the result is evaluated, `deep`,
+                           `shallow` or a number of levels of attrsets and
+                           lists, see `Evaluator`.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+      - cache (bool | str): Cache converted results in memory, or on disk in
+                            the given directory, see `Evaluator`.
+      - timeout (float): Abort the evaluation after this many seconds.
+      - max_memory_mb (int): Abort the evaluation once it allocated more than
+                             this many MiB, see `Evaluator`.
+      - max_depth (int): Refuse code nesting expressions deeper than this,
+                         which can only lower the fixed limit of the parser,
+                         see `Evaluator`.
+      - max_call_depth (int): Abort the evaluation once it nests more than
+                              this many calls, see `Evaluator`.
+      - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+                                   `\n`, see `Evaluator`.
+      - collect_traces (bool): Return the messages of `builtins.trace` and
+                               the warnings along with the value, instead of
+                               emitting them as `TraceWarning`.
+      - collect_conversion_errors (bool): Raise a single `ConversionError`
+                                          listing every part of the result
+                                          that cannot be converted, see
+                                          `Evaluator`.
+      - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+                        for `pathlib.Path`, see `Evaluator`.
+      - ordered (bool): Convert attrsets with their attributes in the order
+                        they are defined in the source, see `Evaluator`.
+      - virtual_name (str): The file name of the expression in errors, and
+                            in its directory if `dir` is given, so snippets
+                            evaluated in the same directory can be told
+                            apart. Defaults to `virtual.nix` in `dir`, and
+                            to `tempfile` without it.
+      - allow_import (bool): Let the expression import and read files.
+                             `False` evaluates the string alone, any
+                             `import`, `builtins.readFile` or other access to
+                             a path raising an `EvaluationError`, so inline
+                             snippets can be evaluated safely.
+      - jail (bool): Only let the expression access the files under `dir`,
+                     like a `restrict_paths` of `dir` alone, on top of the
+                     paths the evaluator restricts it to. Importing or reading
+                     any other file, like `../secret`, or a symlink leading
+                     out of `dir`, raises an `EvaluationError`.
+      - int_overflow (str | None): What to do with the integers of `args` and
+                                   the scope that do not fit into 64 bits, see
+                                   `Evaluator`.
+      - float_policy (str | None): What to do with the infinite and NaN floats
+                                   of the result, `args` and the scope, see
+                                   `Evaluator`.
+
+    Returns:
+      - _EvaluatedNixValue: The evaluated nix expression as any Python object,
+                            or a tuple of it and its traces when collecting
+                            them.
+
+    Raises:
+      - ValueError: If the virtual name is not a file name, the depth, the
+                    overflow policy or the float policy is unknown, a depth
+                    limit is not positive, or the expression is jailed
+                    without `dir`.
+      - ParseError: If the nix file cannot be parsed, or is nested too
+                    deeply.
+      - EvaluationError: If the nix expression cannot be evaluated, or times
+                         out.
+      - ResourceLimitError: If the evaluation exceeds its memory or call depth
+                            limit.
+      - TypeError: If a variable of the scope is not named by a string.
+      - ConversionError: If `args` or the scope cannot be converted to nix
+                         values, or the result cannot be converted to a
+                         Python object.
+
+    Example:
+    ```python
+    >>> evals("{a = 1;}")
+    {'a': 1}
+    >>> evals("{ name, enable ? false }: { inherit name enable; }",
+    ...       args={"name": "foo"})
+    {'enable': False, 'name': 'foo'}
+    >>> evals('"${hostname}.example.com"', scope={"hostname": "web01"})
+    'web01.example.com'
+    >>> evals('builtins.trace "hi" 1', collect_traces=True)
+    (1, [{'kind': 'trace', 'message': 'hi', 'line': None}])
+    >>> evals("(import ./lib.nix).version", dir="config", jail=True)
+    '1.2.0'
+    ```
+    """
+    ...
+
+def formals(content: str, attr_path: str | None = None) -> _Formals:
+    """
+    Get the parameters of a nix function without evaluating it, to document
+    module-style functions like `{ pkgs, lib, extra ? null, ... }: ...`.
+
+    Defaults are given as the source text of their expression, as nothing is
+    evaluated.
+
+    Args:
+      - content (str): The nix expression.
+      - attr_path (str | None): The attribute bound to the function, looked
+                                up like `NixAst.get`, the whole expression if
+                                `None`. The last binding wins if the
+                                attribute is defined several times.
+
+    Returns:
+      - _Formals: The `name` the argument is bound to, like `args` of
+                  `args@{ ... }` or `x` of `x: ...`, the `formals` of the
+                  attrset pattern with their `name`, `default` and `line`, or
+                  `None` for a plain argument, and whether the pattern has an
+                  `ellipsis`.
+
+    Raises:
+      - ParseError: If the nix code cannot be parsed.
+      - ValueError: If the attribute path is invalid or not bound, or its
+                    value is not a function.
+
+    Example:
+    ```python
+    >>> formals("{ pkgs, lib, extra ? null, ... }: { }")
+    {'name': None, 'formals': [{'name': 'pkgs', 'default': None, 'line': 1}, {'name': 'lib', 'default': None, 'line': 1}, {'name': 'extra', 'default': 'null', 'line': 1}], 'ellipsis': True}
+    >>> formals("{ lib }: { mk = name: { inherit name; }; }", "mk")
+    {'name': 'name', 'formals': None, 'ellipsis': False}
+    ```
+    """
+    ...
+
+def dumps(
+    obj: Any,
+    indent: int | None = 2,
+    float_policy: str | None = None,
+    float_repr: str | None = None,
+    float_precision: int | None = None,
+) -> str:
+    """
+    Convert a Python object to the text of a nix expression, the inverse of
+    `evals`.
+
+    Strings are escaped, `${` included, so they never interpolate, and
+    attribute names are quoted unless they are identifiers. Attributes are
+    written in the order of the dict, which nix sorts once evaluated.
+
+    Args:
+      - obj (Any): The value to convert, made of `None`, `bool`, `int`,
+                   `float`, `str`, `list`, `tuple` and `dict` with `str`
+                   keys.
+      - indent (int | None): The number of spaces to indent nested lists and
+                             attrsets by, one item per line. `None` writes the
+                             expression on a single line.
+      - float_policy (str | None): What to do with infinite and NaN floats,
+                                   `"none"` writing them as `null`, or
+                                   `"error"`. `None` follows
+                                   `set_float_policy`.
+      - float_repr (str | None): How to write finite floats, `"shortest"`,
+                                 `"decimal"` or `"scientific"`. `None`
+                                 follows `set_float_policy`.
+      - float_precision (int | None): The digits written after the point by
+                                      the `decimal` and `scientific` reprs,
+                                      all of them if `None`.
+
+    Returns:
+      - str: The nix expression.
+
+    Raises:
+      - ConversionError: If a value has no nix literal, like an integer
+                         overflowing 64 bits, an infinite float or an object
+                         of another type, or a dict key is not a string.
+      - ValueError: If a float policy or repr is unknown, or a precision is
+                    given without the `decimal` or `scientific` repr.
+
+    Example:
+    ```python
+    >>> print(dumps({"name": "hello", "meta.broken": False, "ports": [80, -1]}))
+    {
+      name = "hello";
+      "meta.broken" = false;
+      ports = [
+        80
+        (-1)
+      ];
+    }
+    >>> dumps({"a": "${x}"}, indent=None)
+    '{ a = "\\${x}"; }'
+    >>> dumps([0.1, float("inf")], indent=None, float_policy="none",
+    ...       float_repr="scientific")
+    '[ 1.0e-1 null ]'
+    ```
+    """
+    ...
+
+def escape_string(s: str, indented: bool = False) -> str:
+    """
+    Write a string as a nix string literal, so that nix reads it back as the
+    same string.
+
+    `${` is escaped so it never interpolates. Indented strings escape `''`
+    and `${` the way `''` strings do, and the whitespace the string starts
+    with, which nix would otherwise strip as indentation.
+
+    Args:
+      - s (str): The string to write.
+      - indented (bool): Write a `''` string rather than a `"` string, for
+                         strings spanning several lines.
+
+    Returns:
+      - str: The nix string literal, with its quotes.
+
+    Example:
+    ```python
+    >>> print(escape_string('say "hi" to ${name}\n'))
+    "say \"hi\" to \${name}\n"
+    >>> print(escape_string("  echo ''${HOME}''\n", indented=True))
+    ''''\  echo '''''${HOME}'''
+    ''
+    ```
+    """
+    ...
+
+def quote_attr(key: str) -> str:
+    """
+    Write an attribute name, quoted unless it is an identifier that is not a
+    keyword of nix.
+
+    Args:
+      - key (str): The attribute name.
+
+    Returns:
+      - str: The name as written in an attrset or an attribute path.
+
+    Example:
+    ```python
+    >>> quote_attr("enable"), quote_attr("my.app"), quote_attr("in")
+    ('enable', '"my.app"', '"in"')
+    ```
+    """
+    ...
+
+def format_attrpath(path: list[str]) -> str:
+    """
+    Write an attribute path, quoting the names that need it like
+    `quote_attr`.
+
+    Args:
+      - path (list[str]): The names of the path.
+
+    Returns:
+      - str: The attribute path, its names separated by dots.
+
+    Raises:
+      - ValueError: If the path is empty.
+
+    Example:
+    ```python
+    >>> print(format_attrpath(["services", "weird key", "${x}"]))
+    services."weird key"."\${x}"
+    ```
+    """
+    ...
+
+def coverage(entry: str, options_schema: dict[str, Any]) -> _Coverage:
+    """
+    Evaluate a nix config file and report which options of a schema it
+    defines.
+
+    Options are the nodes of the schema that are attrsets with
+    `_type = "option"`, like the result of `lib.mkOption`, every other attrset
+    of the schema is a group of options. Only the values needed to tell the
+    options apart are evaluated, so values of options are never forced.
+
+    Args:
+      - entry (str): The path to the nix file evaluating to the config.
+      - options_schema (dict): The options the config is checked against.
+
+    Returns:
+      - _Coverage: The attribute paths of the options that are `set` by the
+                   config, left to their `default`, and the paths that are set
+                   by the config but `unknown` to the schema.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ParseError: If the nix file cannot be parsed.
+      - EvaluationError: If the config cannot be evaluated, or is not an
+                         attrset.
+      - ConversionError: If an attribute name is not valid UTF-8.
+
+    Example:
+    ```python
+    # `path/to/config.nix` contains:
+    # ```
+    # { services.nginx.enable = true; services.foo = 1; }
+    # ```
+    >>> option = {"_type": "option"}
+    >>> coverage(
+    ...     "path/to/config.nix",
+    ...     {"services": {"nginx": {"enable": option, "package": option}}},
+    ... )
+    {'set': ['services.nginx.enable'], 'default': ['services.nginx.package'], 'unknown': ['services.foo']}
+    ```
+    """
+    ...
+
+def eval_flake(
+    path: str, output: str, strict: bool | None = None, lazy: bool = False
+) -> _EvaluatedNixValue:
+    """
+    Evaluate an output of a nix flake and convert it to Python object.
+
+    The inputs of the flake are resolved from its `flake.lock`, and must be
+    present on disk already: local `path` inputs are used in place, every
+    other input is looked up in the nix store by its `narHash`. Nothing is
+    fetched from the network.
+
+    Args:
+      - path (str): The directory of the flake, or its `flake.nix`.
+      - output (str): The attribute path of the output to evaluate, with
+                      names separated by `.`.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - lazy (bool): Only evaluate what is needed to produce the top-level
+                     value, returning unevaluated parts as `NixThunk`.
+
+    Returns:
+      - _EvaluatedNixValue: The evaluated output as any Python object
+
+    Raises:
+      - IOError: If the flake cannot be read.
+      - ParseError: If the flake cannot be parsed, or its lock file is
+                    malformed.
+      - EvaluationError: If an input is not available, or the output cannot
+                         be evaluated.
+      - ConversionError: If the result cannot be converted to a Python object.
+
+    Example:
+    ```python
+    >>> eval_flake("path/to/flake", "nixosConfigurations.foo.config.networking.hostName")
+    'foo'
+    ```
+    """
+    ...
+
+def eval_modules(
+    modules: list[str | os.PathLike[str] | dict[str, Any]],
+    special_args: dict[str, _NixArgument] | None = None,
+    strict: bool | None = None,
+    lazy: bool = False,
+    nix_path: list[str] | str | None = None,
+) -> _EvaluatedNixValue:
+    """
+    Evaluate NixOS-style modules and convert their final `config` to Python
+    object, like `lib.evalModules` of nixpkgs without running nix.
+
+    The modules, their `imports` and the options they declare are collected,
+    then the definitions of each option are merged by priority (`mkDefault`,
+    `mkForce`, `mkOverride`) and order (`mkBefore`, `mkAfter`), conditions
+    (`mkIf`) and merges (`mkMerge`) included, defaults and `apply` taken
+    into account, and checked against the type of the option. Modules are
+    given `config`, `options`, `lib` and the `special_args`, and can ask for
+    the arguments of `_module.args`, like `pkgs`.
+
+    nixpkgs itself is not needed: the `lib` given to modules is a bundled
+    subset of it, with the builtins, `mkOption`, `mkEnableOption`, the
+    `mk*` properties, common helpers like `optionalAttrs` or
+    `mapAttrsToList`, and the common `types`: `anything`, `raw`, `bool`,
+    `int`, `ints.*`, `port`, `float`, `number`, `str`, `lines`, `commas`,
+    `path`, `package`, `attrs`, `enum`, `nullOr`, `either`, `oneOf`,
+    `listOf`, `attrsOf`, `lazyAttrsOf`, `submodule` and `submoduleWith`.
+    Freeform modules and `disabledModules` are not supported.
+
+    Args:
+      - modules (list[str | os.PathLike | dict]): The modules, as files or as
+                                                  attrsets of definitions,
+                                                  like `{"networking":
+                                                  {"hostName": "foo"}}`.
+      - special_args (dict): Arguments given to every module, available in
+                             `imports` unlike `_module.args`, converted like
+                             `evals` args.
+      - strict (bool | None): Turn warnings emitted during evaluation
+                              (unused bindings, deprecated syntax, ...) into
+                              errors, `None` as in the selected profile.
+      - lazy (bool): Only evaluate what is needed to produce the top-level
+                     value, returning unevaluated parts as `NixThunk`.
+      - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+                                    `Evaluator`.
+
+    Returns:
+      - _EvaluatedNixValue: The final `config` as any Python object
+
+    Raises:
+      - IOError: If a module file cannot be found or read.
+      - ParseError: If a module cannot be parsed.
+      - EvaluationError: If a module cannot be evaluated, defines an option
+                         that does not exist, or with a value not of its
+                         type, or conflicting values.
+      - ConversionError: If a module or an argument cannot be converted to a
+                         nix value, or the result to a Python object.
+
+    Example:
+    ```python
+    >>> eval_modules(["hosts/laptop.nix", {"networking": {"hostName": "x"}}])["networking"]
+    {'hostName': 'x', 'firewall': {'enable': True}}
+    ```
+    """
+    ...
+
+def extract_options(path: str) -> list[_OptionDeclaration]:
+    """
+    Extract the options a nix module declares without evaluating it, to
+    document a set of modules.
+
+    Options are found under the `options` attribute of the attrset the module
+    is made of, through functions, `let` and `with`, where they are declared
+    by `mkOption` or `mkEnableOption`, with or without `lib.`. Their `type`,
+    `default` and `example` are given as the source text of their
+    expression, as nothing is evaluated, and their `description` as the
+    string it is when it is a string without interpolation, unwrapping
+    `mdDoc` and `literalMD`. `mkEnableOption` options are described like
+    nixpkgs declares them. Options declared by other functions, or nested in
+    the `options` of a submodule type, are not extracted.
+
+    Args:
+      - path (str): The path to the nix module.
+
+    Returns:
+      - list[_OptionDeclaration]: The options in the order of the source, with
+                                  their attribute `path` under `options`,
+                                  their `type`, `default`, `example` and
+                                  `description`, `None` when not declared, and
+                                  the `line` of their declaration.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ParseError: If the nix file cannot be parsed.
+
+    Example:
+    ```python
+    # `module.nix` contains:
+    # ```
+    # { lib, ... }: {
+    #   options.services.app = {
+    #     enable = lib.mkEnableOption "the app";
+    #     port = lib.mkOption {
+    #       type = lib.types.port;
+    #       default = 8080;
+    #       description = "The port the app listens on.";
+    #     };
+    #   };
+    # }
+    # ```
+    >>> options = extract_options("module.nix")
+    >>> [(o["path"], o["type"], o["default"]) for o in options]
+    [('services.app.enable', 'types.bool', 'false'),
+     ('services.app.port', 'lib.types.port', '8080')]
+    >>> options[1]["description"]
+    'The port the app listens on.'
+    ```
+    """
+    ...
+
+def compact(content: str) -> str:
+    """
+    Compact a nix expression by removing comments and redundant whitespace.
+
+    Only whitespace and comments between tokens are removed, the content of
+    strings is kept as is, so the indentation stripped from indented strings
+    (`''...''`) stays the same.
+
+    Args:
+      - content (str): The nix expression to compact.
+
+    Returns:
+      - str: An equivalent expression without comments and with as little
+             whitespace as possible.
+
+    Raises:
+      - ParseError: If the nix expression cannot be parsed.
+
+    Example:
+    ```python
+    >>> compact("let x = 42; /* the answer */ in { inherit x; }")
+    'let x=42;in{inherit x;}'
+    ```
+    """
+    ...
+
+def compare(a: Any, b: Any) -> int:
+    """
+    Compare two values like nix does.
+
+    Both values are converted like `evals` args, and compared by the `==` and
+    `<` operators of nix, so Python assertions conclude what nix would:
+    integers equal floats of the same value, strings compare bytewise, lists
+    compare element by element, and attrsets are deeply equal when they have
+    the same names and equal values.
+
+    Args:
+      - a (Any): The first value.
+      - b (Any): The second value.
+
+    Returns:
+      - int: `0` if the values are equal, `-1` if `a` is less than `b` and `1`
+             if it is greater.
+
+    Raises:
+      - ConversionError: If a value cannot be converted to a nix value.
+      - EvaluationError: If the values differ but nix cannot order them, like
+                         attrsets, booleans or values of different types.
+
+    Example:
+    ```python
+    >>> compare(1, 1.0), compare([1, "b"], [1, "a"]), compare("a", "b")
+    (0, 1, -1)
+    >>> compare({"a": [1]}, {"a": [1.0]})
+    0
+    ```
+    """
+    ...
+
+def load_flake_lock(path: str) -> _FlakeLock:
+    """
+    Parse the `flake.lock` of a flake and resolve the graph of its inputs.
+
+    Every node of the lock is returned with its pins, and with its inputs
+    resolved to the names of the nodes they refer to. Inputs that `follows`
+    another input keep the path they follow, starting at the root node.
+
+    Args:
+      - path (str): The path to the `flake.lock`, or the directory of the
+                    flake.
+
+    Returns:
+      - _FlakeLock: The version of the lock file, the name of the `root`
+                    node, and the `nodes` by name. Each node has the `type`,
+                    `rev`, `narHash` and `lastModified` of its pin (`None`
+                    when not pinned), the raw `locked` and `original`
+                    references, whether it is a `flake`, and its `inputs`
+                    with the `node` they resolve to and the path they
+                    `follows`, if any.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ParseError: If the lock file is malformed, or a `follows` cannot be
+                    resolved.
+
+    Example:
+    ```python
+    >>> lock = load_flake_lock("path/to/flake")
+    >>> lock["nodes"]["nixpkgs"]["rev"]
+    '7e2a6a2c2b0f3f9a7b1c4f5a3e2d1c0b9a8f7e6d'
+    >>> lock["nodes"]["home-manager"]["inputs"]["nixpkgs"]
+    {'node': 'nixpkgs', 'follows': ['nixpkgs']}
+    ```
+    """
+    ...
+
+def metrics(source: str | os.PathLike[str]) -> _Metrics:
+    """
+    Measure the complexity of a nix file or expression.
+
+    Only the syntax is inspected, nothing is evaluated, so imports are the
+    paths written as the argument of `import` or `callPackage`, or listed in
+    the `imports` of a module.
+
+    Args:
+      - source (str | os.PathLike): The nix expression, or the path to the
+                                    nix file. Plain strings are expressions,
+                                    never paths.
+
+    Returns:
+      - _Metrics: The `size` in bytes and the `lines` of the source, the
+                  number of `bindings` (including inherited ones), of
+                  `functions`, of `with_expressions` and of `let_expressions`,
+                  the `max_depth` of nested attrsets, lists, lets, functions
+                  and withs, the distinct `imports` as written, and their
+                  number as `import_fan_out`.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ParseError: If the nix code cannot be parsed.
+
+    Example:
+    ```python
+    >>> m = metrics("{ pkgs, ... }: { imports = [ ./a.nix ]; x = with pkgs; hello; }")
+    >>> m["bindings"], m["functions"], m["with_expressions"], m["imports"]
+    (2, 1, 1, ['./a.nix'])
+    ```
+    """
+    ...
+
+def rename(
+    root_entry: str,
+    from_attr_path: str,
+    to_attr_path: str,
+    apply: bool = False,
+) -> list[_Edit]:
+    """
+    Rename an attribute across the files imported by a nix entry point.
+
+    The files are those reachable from the entry point through `import`,
+    `callPackage` or the `imports` of modules, as written in the code. Only the
+    syntax is inspected, nothing is evaluated:
+      - definitions are bindings of the path, matched from the top-level
+        attrset of each file, through functions, `let`, `with`, `if`, lists and
+        `mk*` helpers like `mkIf`, so `{ a.b = 1; }` and `{ a = { b = 1; }; }`
+        both define `a.b`,
+      - references are selections of the path, or of its tail by a variable
+        named like its first segment, so `x.a.b` and `a.b` both refer to
+        `a.b`, as does `x ? a.b`.
+
+    Values bound to other names, like `cfg = config.a;`, are not followed.
+
+    Args:
+      - root_entry (str): The path to the nix entry point.
+      - from_attr_path (str): The attribute path to rename, like
+                              `services.foo.enable`.
+      - to_attr_path (str): The new attribute path.
+      - apply (bool): Write the edits to the files too.
+
+    Returns:
+      - list[_Edit]: The edits, sorted by `path` and `start`, each replacing
+                     the bytes from `start` to `end` of the file, on the given
+                     `line`, with `new` instead of `old`.
+
+    Raises:
+      - IOError: If a file cannot be read or written.
+      - ParseError: If a file cannot be parsed.
+      - ValueError: If an attribute path is empty, or a definition is nested
+                    in an attrset that the rename would have to move.
+
+    Example:
+    ```python
+    # `default.nix` contains:
+    # ```
+    # { imports = [ ./foo.nix ]; foo.enable = true; }
+    # ```
+    # `foo.nix` contains:
+    # ```
+    # { config, ... }: { bar = config.foo.enable; }
+    # ```
+    >>> [e["new"] for e in rename("default.nix", "foo.enable", "foo.on")]
+    ['foo.on', 'foo.on']
+    ```
+    """
+    ...
+
+def parse(
+    expr: str, tokens: bool = False, max_depth: int | None = None
+) -> _NixNode:
+    """
+    Parse a nix expression into its syntax tree, without evaluating it.
+
+    Every node of the tree is a dict with its `kind`, named like the
+    `SyntaxKind` of rnix (`NODE_ATTR_SET`, `TOKEN_IDENT`, ...), its `start`
+    and `end` byte offsets, the `line` it starts on, its source `text` and
+    its `children`. The root is a `NODE_ROOT` node.
+
+    Args:
+      - expr (str): The nix expression to parse.
+      - tokens (bool): Also list the tokens of each node among its children,
+                       whitespace and comments included, so the texts of the
+                       children of a node add up to the text of the node.
+      - max_depth (int): Refuse code nesting expressions deeper than this,
+                         for untrusted input. The parser of rnix has its own
+                         fixed limit, which this can only lower.
+
+    Returns:
+      - _NixNode: The root of the syntax tree.
+
+    Raises:
+      - ValueError: If the nesting limit is not positive.
+      - ParseError: If the nix code cannot be parsed, or is nested too
+                    deeply.
+
+    Example:
+    ```python
+    >>> root = parse("{ a = 1; }")
+    >>> [c["kind"] for c in root["children"][0]["children"]]
+    ['NODE_ATTRPATH_VALUE']
+    ```
+    """
+    ...
+
+def parse_file(
+    path: str, tokens: bool = False, max_depth: int | None = None
+) -> _NixNode:
+    """
+    Parse a nix file into its syntax tree, without evaluating it, see
+    `parse`.
+
+    Args:
+      - path (str): The path to the nix file.
+      - tokens (bool): Also list the tokens of each node among its children.
+      - max_depth (int): Refuse code nesting expressions deeper than this.
+
+    Returns:
+      - _NixNode: The root of the syntax tree.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ValueError: If the nesting limit is not positive.
+      - ParseError: If the nix file cannot be parsed, or is nested too
+                    deeply.
+    """
+    ...
+
+def parse_to_json(
+    expr: str, tokens: bool = True, max_depth: int | None = None
+) -> str:
+    """
+    Parse a nix expression into its concrete syntax tree as a JSON string,
+    without evaluating it.
+
+    The tree is the one `parse` returns, serialized in Rust without building
+    Python objects, for tools in other languages and snapshots of the syntax
+    of nix sources. Tokens are listed by default, whitespace and comments
+    included, so the source can be rebuilt from the texts of the tokens.
+    Object keys are sorted, so the same source always gives the same JSON.
+
+    Args:
+      - expr (str): The nix expression to parse.
+      - tokens (bool): List the tokens of each node among its children.
+      - max_depth (int): Refuse code nesting expressions deeper than this,
+                         see `parse`.
+
+    Returns:
+      - str: The root of the syntax tree as compact JSON.
+
+    Raises:
+      - ValueError: If the nesting limit is not positive.
+      - ParseError: If the nix code cannot be parsed, or is nested too
+                    deeply.
+
+    Example:
+    ```python
+    >>> parse_to_json("1 # one", tokens=False)
+    '{"children":[{"children":[],"end":1,"kind":"NODE_LITERAL","line":1,"start":0,"text":"1"}],"end":7,"kind":"NODE_ROOT","line":1,"start":0,"text":"1 # one"}'
+    >>> root = orjson.loads(parse_to_json("1 # one"))
+    >>> [t["kind"] for t in root["children"]]
+    ['NODE_LITERAL', 'TOKEN_WHITESPACE', 'TOKEN_COMMENT']
+    ```
+    """
+    ...
+
+def managed_regions(content: str) -> list[_ManagedRegion]:
+    """
+    List the regions of a nix file managed by a tool.
+
+    A managed region is delimited by line comments, from
+    `# cosutils:begin <name>` to `# cosutils:end`, and its content is meant to
+    be regenerated with `replace_managed` while the user edits the rest of the
+    file, like the bindings of an attrset owned by a generator.
+
+    Args:
+      - content (str): The nix code as a string.
+
+    Returns:
+      - list[_ManagedRegion]: The regions, with their `name`, the `start` and
+                              `end` byte offsets of the lines between their
+                              markers, the `line` of their begin marker and
+                              their `content`.
+
+    Raises:
+      - ParseError: If the markers are unnamed, nested, duplicated or do not
+                    pair up.
+    """
+    ...
+
+def replace_managed(content: str, name: str, replacement: str) -> str:
+    """
+    Replace the content of a region of a nix file managed by a tool, see
+    `managed_regions`.
+
+    The markers and everything outside of the region are kept as they are,
+    comments and formatting included, and the replacement is indented like
+    the begin marker.
+
+    Args:
+      - content (str): The nix code as a string.
+      - name (str): The name of the region.
+      - replacement (str): The new content of the region.
+
+    Returns:
+      - str: The updated code.
+
+    Raises:
+      - KeyError: If the code has no region of that name.
+      - ParseError: If the markers are malformed, or the updated code cannot
+                    be parsed.
+
+    Example:
+    ```python
+    >>> code = "{\n  # cosutils:begin gen\n  a = 1;\n  # cosutils:end\n}\n"
+    >>> print(replace_managed(code, "gen", "b = 2;\nc = 3;"))
+    {
+      # cosutils:begin gen
+      b = 2;
+      c = 3;
+      # cosutils:end
+    }
+    ```
+    """
+    ...
+
+def lint(path: str) -> list[_Diagnostic]:
+    """
+    Lint nix code, finding code that is dead or would fail to evaluate.
+
+    Only the syntax is inspected, nothing is evaluated, so variables are
+    resolved to the bindings of the functions, `let`s and recursive attrsets
+    enclosing them, and `with` expressions are ignored since they never hide
+    those bindings. The diagnostics are identified by their `code`:
+      - `unused_let`: A `let` binding that is never referred to.
+      - `unused_argument`: A function argument, formal or `@` binding that is
+                           never referred to.
+      - `empty_inherit`: An `inherit` of no attribute.
+      - `duplicate_key`: An attribute of an attrset or a `let` that is already
+                         defined, which nix refuses to evaluate. The attrpaths
+                         of nested attributes are merged like nix does, so
+                         `{ a.b = 1; a.c = 2; }` is valid.
+
+    Like deadnix, bindings and arguments whose name starts with `_` are never
+    reported unused.
+
+    Args:
+      - path (str): The path to the nix file.
+
+    Returns:
+      - list[_Diagnostic]: The diagnostics, in source order, with their
+                           `code`, their `message`, the `start` and `end` byte
+                           offsets of the code they are about, and the `line`
+                           and `column` it starts at.
+
+    Raises:
+      - IOError: If the file cannot be read.
+      - ParseError: If the nix file cannot be parsed.
+
+    Example:
+    ```python
+    # `default.nix` contains:
+    # ```
+    # { pkgs, lib, ... }: let x = 1; in { a = lib.id 1; a = 2; }
+    # ```
+    >>> [(d["code"], d["message"]) for d in lint("default.nix")]
+    [('unused_argument', 'Unused function argument `pkgs`'),
+     ('unused_let', 'Unused let binding `x`'),
+     ('duplicate_key', '`a` is already defined on line 1')]
+    ```
+    """
+    ...
+
+def literals(content: str) -> list[_Literal]:
+    """
+    Extract the literal bindings of a nix expression without evaluating it.
+
+    Bindings are found in the attrset the expression is made of, through
+    functions, `let` and `with`, and in its nested attrsets. Strings without
+    interpolation, numbers, `true`, `false`, `null` and lists of them are
+    literals, every other value, like a variable or a function call, is
+    skipped. As nothing is evaluated, shadowed constants like a `true`
+    variable are not told apart, and the bindings of `let` are not
+    extracted.
+
+    Args:
+      - content (str): The nix expression.
+
+    Returns:
+      - list[_Literal]: The literal bindings in the order of the source,
+                        with their `path`, their `value`, and the
+                        `start` and `end` byte offsets, `line` and `column`
+                        of the value.
+
+    Raises:
+      - ParseError: If the nix code cannot be parsed.
+
+    Example:
+    ```python
+    >>> literals('{ pkgs }: { networking.hostName = "foo"; ports = [ 22 ]; }')
+    [{'path': 'networking.hostName', 'value': 'foo', 'start': 34, 'end': 39, 'line': 1, 'column': 35}, {'path': 'ports', 'value': [22], 'start': 49, 'end': 55, 'line': 1, 'column': 50}]
+    ```
+    """
+    ...
+
+def tokenize(content: str) -> list[tuple[TokenKind, int, int]]:
+    """
+    Split nix code into tokens for syntax highlighting.
+
+    The code is only lexed, not parsed, so incomplete or invalid code is
+    tokenized too, the text the lexer does not recognize becoming `Error`
+    tokens. The tokens cover the whole code, whitespace and comments
+    included, so joining their text gives the code back.
+
+    Args:
+      - content (str): The nix code.
+
+    Returns:
+      - list[tuple[TokenKind, int, int]]: The `kind` of each token, and the
+                                          `start` and `end` byte offsets of
+                                          its text, in source order.
+
+    Example:
+    ```python
+    >>> [(k.name, s, e) for k, s, e in tokenize('{ a = "x${b}"; }')][:7]
+    [('punctuation', 0, 1), ('whitespace', 1, 2), ('identifier', 2, 3),
+     ('whitespace', 3, 4), ('operator', 4, 5), ('whitespace', 5, 6),
+     ('string_delimiter', 6, 7)]
+    >>> tokenize("1.5")[0][0] == TokenKind.Float
+    True
     ```
     """
     ...
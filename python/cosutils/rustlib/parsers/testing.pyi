@@ -0,0 +1,57 @@
+import os
+from typing import Any, Callable
+
+def assert_matches(
+    path: str | os.PathLike[str],
+    golden_path: str | os.PathLike[str],
+    normalize: dict[str, Any] | Callable[[Any], Any] | None = None,
+) -> None:
+    """
+    Assert that a document matches a golden file, for snapshot tests.
+
+    Both files are loaded with the parser of their extension, so a nix file
+    can be checked against the JSON it should evaluate to, and normalized
+    before they are compared. On mismatch, the assertion lists where the
+    documents differ, by the path of each value, instead of showing both
+    documents. The normalizations are:
+      - `ignore` (list[str]): The dotted paths of values to remove, `*`
+                              matching any key or list index.
+      - `sort_lists` (bool): Compare lists regardless of the order of their
+                             items.
+      - `strip_strings` (bool): Strip the leading and trailing whitespace of
+                                strings.
+      - `rel_tol` (float): The relative tolerance numbers are compared with.
+
+    Args:
+      - path (str | os.PathLike): The document under test.
+      - golden_path (str | os.PathLike): The golden file the document is
+                                         expected to match.
+      - normalize (dict | Callable): The normalizations applied to both
+                                     documents by name, or a function
+                                     normalizing each document instead.
+
+    Raises:
+      - AssertionError: If the documents do not match.
+      - ValueError: If a normalization is unknown, or no parser handles the
+                    extension of a file.
+      - TypeError: If `normalize` is neither a dict nor callable, or lists
+                   to sort contain values of other types than the parsers
+                   return.
+      - IOError, ParseError, EvaluationError: If a file cannot be loaded.
+
+    Example:
+    ```python
+    >>> assert_matches("hosts/laptop.nix", "golden/laptop.json")
+    Traceback (most recent call last):
+      ...
+    AssertionError: hosts/laptop.nix does not match golden/laptop.json, 2 differences:
+      - `networking.hostName`: expected 'laptop', got 'desktop'
+      - `users.bob`: unexpected {'isNormalUser': True}
+    >>> assert_matches(
+    ...     "hosts/laptop.nix",
+    ...     "golden/laptop.json",
+    ...     normalize={"ignore": ["system.build", "users.*.uid"]},
+    ... )
+    ```
+    """
+    ...
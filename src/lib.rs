@@ -11,11 +11,11 @@ fn init_submodule(m: &Bound<'_, PyModule>, name: &str) -> PyResult<()> {
 }
 
 /// A set of utilities for cosutils implemented in Rust.
-#[pymodule]
+#[pymodule(gil_used = false)]
 mod rustlib {
     use super::*;
 
-    #[pymodule]
+    #[pymodule(gil_used = false)]
     mod parsers {
         use super::*;
 
@@ -30,8 +30,54 @@ mod rustlib {
         use crate::parsers::utils::EvaluationError;
         #[pymodule_export]
         use crate::parsers::utils::ParseError;
+        #[pymodule_export]
+        use crate::parsers::utils::ResourceLimitError;
+
+        #[pymodule_export]
+        use crate::parsers::anonymize::anonymize;
+        #[pymodule_export]
+        use crate::parsers::codegen::codegen;
+        #[pymodule_export]
+        use crate::parsers::completions::completions_from;
+        #[pymodule_export]
+        use crate::parsers::detect::detect;
+        #[pymodule_export]
+        use crate::parsers::diagnostics::diagnostics_to_sarif;
+        #[pymodule_export]
+        use crate::parsers::diagnostics::to_github_annotations;
+        #[pymodule_export]
+        use crate::parsers::explain::explain;
+        #[pymodule_export]
+        use crate::parsers::floats::set_float_policy;
+        #[pymodule_export]
+        use crate::parsers::formats::available_formats;
+        #[pymodule_export]
+        use crate::parsers::formats::features;
+        #[pymodule_export]
+        use crate::parsers::hash::value_hash;
+        #[pymodule_export]
+        use crate::parsers::hook::set_error_hook;
+        #[pymodule_export]
+        use crate::parsers::overflow::set_int_overflow;
+        #[pymodule_export]
+        use crate::parsers::profile::profile;
+        #[pymodule_export]
+        use crate::parsers::profile::set_profile;
+        #[pymodule_export]
+        use crate::parsers::rekey::rekey;
+        #[pymodule_export]
+        use crate::parsers::render::set_color;
+        #[pymodule_export]
+        use crate::parsers::schema::apply_defaults;
+        #[pymodule_export]
+        use crate::parsers::threads::set_threads;
+        #[pymodule_export]
+        use crate::parsers::transform::coerce;
+        #[pymodule_export]
+        use crate::parsers::transform::transform;
 
-        #[pymodule]
+        #[cfg(feature = "nix")]
+        #[pymodule(gil_used = false)]
         mod nix {
             use super::*;
 
@@ -40,13 +86,106 @@ mod rustlib {
                 init_submodule(m, "cosutils.rustlib.parsers.nix")
             }
 
+            #[pymodule_export]
+            use crate::parsers::nix::compact;
+            #[pymodule_export]
+            use crate::parsers::nix::compare;
+            #[pymodule_export]
+            use crate::parsers::nix::coverage;
+            #[pymodule_export]
+            use crate::parsers::nix::diff;
+            #[pymodule_export]
+            use crate::parsers::nix::dumps;
+            #[pymodule_export]
+            use crate::parsers::nix::escape_string;
             #[pymodule_export]
             use crate::parsers::nix::eval;
             #[pymodule_export]
+            use crate::parsers::nix::eval_attr;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_drv;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_flake;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_many;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_modules;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_to_json;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_to_toml;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_to_yaml;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_validated;
+            #[pymodule_export]
+            use crate::parsers::nix::eval_with_positions;
+            #[pymodule_export]
             use crate::parsers::nix::evals;
+            #[pymodule_export]
+            use crate::parsers::nix::extract_options;
+            #[pymodule_export]
+            use crate::parsers::nix::formals;
+            #[pymodule_export]
+            use crate::parsers::nix::format_attrpath;
+            #[pymodule_export]
+            use crate::parsers::nix::lint;
+            #[pymodule_export]
+            use crate::parsers::nix::literals;
+            #[pymodule_export]
+            use crate::parsers::nix::load_flake_lock;
+            #[pymodule_export]
+            use crate::parsers::nix::managed_regions;
+            #[pymodule_export]
+            use crate::parsers::nix::metrics;
+            #[pymodule_export]
+            use crate::parsers::nix::parse;
+            #[pymodule_export]
+            use crate::parsers::nix::parse_file;
+            #[pymodule_export]
+            use crate::parsers::nix::parse_to_json;
+            #[pymodule_export]
+            use crate::parsers::nix::quote_attr;
+            #[pymodule_export]
+            use crate::parsers::nix::rename;
+            #[pymodule_export]
+            use crate::parsers::nix::replace_managed;
+            #[pymodule_export]
+            use crate::parsers::nix::tokenize;
+            #[pymodule_export]
+            use crate::parsers::nix::Evaluator;
+            #[pymodule_export]
+            use crate::parsers::nix::FetchCache;
+            #[pymodule_export]
+            use crate::parsers::nix::NixAst;
+            #[pymodule_export]
+            use crate::parsers::nix::NixFunction;
+            #[pymodule_export]
+            use crate::parsers::nix::NixThunk;
+            #[pymodule_export]
+            use crate::parsers::nix::Session;
+            #[pymodule_export]
+            use crate::parsers::nix::TokenKind;
+            #[pymodule_export]
+            use crate::parsers::nix::TraceWarning;
         }
 
-        #[pymodule]
+        #[cfg(feature = "manifest")]
+        #[pymodule(gil_used = false)]
+        mod manifest {
+            use super::*;
+
+            #[pymodule_init]
+            fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+                init_submodule(m, "cosutils.rustlib.parsers.manifest")
+            }
+
+            #[pymodule_export]
+            use crate::parsers::manifest::load;
+        }
+
+        #[cfg(feature = "jsonc")]
+        #[pymodule(gil_used = false)]
         mod jsonc {
             use super::*;
 
@@ -55,10 +194,37 @@ mod rustlib {
                 init_submodule(m, "cosutils.rustlib.parsers.jsonc")
             }
 
+            #[pymodule_export]
+            use crate::parsers::jsonc::dump;
+            #[pymodule_export]
+            use crate::parsers::jsonc::dumps;
+            #[pymodule_export]
+            use crate::parsers::jsonc::format;
             #[pymodule_export]
             use crate::parsers::jsonc::load;
             #[pymodule_export]
             use crate::parsers::jsonc::loads;
+            #[pymodule_export]
+            use crate::parsers::jsonc::managed_regions;
+            #[pymodule_export]
+            use crate::parsers::jsonc::parse_document;
+            #[pymodule_export]
+            use crate::parsers::jsonc::replace_managed;
+            #[pymodule_export]
+            use crate::parsers::jsonc::JsoncDocument;
+        }
+
+        #[pymodule(gil_used = false)]
+        mod testing {
+            use super::*;
+
+            #[pymodule_init]
+            fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+                init_submodule(m, "cosutils.rustlib.parsers.testing")
+            }
+
+            #[pymodule_export]
+            use crate::parsers::testing::assert_matches;
         }
     }
 }
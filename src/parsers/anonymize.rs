@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString, PyTuple};
+use pyo3::{PyObject, PyResult};
+
+use crate::into_pyany;
+use crate::parsers::formats::load;
+
+/// What a replaced piece of a document identified
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Category {
+    Username,
+    Hostname,
+    Email,
+    Ip,
+    Secret,
+}
+
+impl Category {
+    const ALL: [Category; 5] = [
+        Category::Username,
+        Category::Hostname,
+        Category::Email,
+        Category::Ip,
+        Category::Secret,
+    ];
+
+    /// The name of the category in the rules
+    fn name(self) -> &'static str {
+        match self {
+            Category::Username => "usernames",
+            Category::Hostname => "hostnames",
+            Category::Email => "emails",
+            Category::Ip => "ips",
+            Category::Secret => "secrets",
+        }
+    }
+
+    /// The category of the values of a key, from its name alone
+    fn of_key(key: &str) -> Option<Self> {
+        let key = key
+            .chars()
+            .filter(|c| !matches!(c, '_' | '-'))
+            .collect::<String>()
+            .to_ascii_lowercase();
+        const SECRETS: [&str; 8] = [
+            "password",
+            "passwd",
+            "secret",
+            "token",
+            "apikey",
+            "privatekey",
+            "credential",
+            "passphrase",
+        ];
+        if SECRETS.iter().any(|s| key.contains(s)) {
+            Some(Category::Secret)
+        } else if matches!(
+            key.as_str(),
+            "user" | "username" | "login" | "owner"
+        ) {
+            Some(Category::Username)
+        } else if matches!(key.as_str(), "host" | "hostname" | "fqdn") {
+            Some(Category::Hostname)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether a string looks like a credential, by well-known prefixes or by
+/// being a long run of mixed letters and digits
+fn is_secret(s: &str) -> bool {
+    const PREFIXES: [&str; 7] = [
+        "ghp_",
+        "gho_",
+        "github_pat_",
+        "glpat-",
+        "sk-",
+        "xox",
+        "AKIA",
+    ];
+    if s.contains("-----BEGIN") && s.contains("PRIVATE KEY") {
+        return true;
+    }
+    let token_like = s.len() >= 24
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+/=_-".contains(c));
+    token_like
+        && (PREFIXES.iter().any(|p| s.starts_with(p))
+            || (s.chars().any(|c| c.is_ascii_uppercase())
+                && s.chars().any(|c| c.is_ascii_lowercase())
+                && s.chars().any(|c| c.is_ascii_digit())))
+}
+
+/// Whether an email address is well formed, and not already anonymous
+fn is_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+    let tld = domain.rsplit('.').next().unwrap_or_default();
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c))
+        && domain.contains('.')
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-".contains(c))
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+        && !domain.ends_with("example.com")
+}
+
+/// Whether an address identifies a machine, unlike loopback or unspecified
+/// addresses, or the ranges reserved for documentation
+fn is_identifying_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || (a, b, c) == (192, 0, 2))
+        }
+        IpAddr::V6(v6) => {
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.segments()[..2] == [0x2001, 0xdb8])
+        }
+    }
+}
+
+/// Replace the occurrences of `word` not surrounded by alphanumeric
+/// characters
+fn replace_word(s: &str, word: &str, replacement: &str) -> String {
+    let mut replaced = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find(word) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[i + word.len()..].chars().next();
+        let bounded = !before.is_some_and(|c| c.is_alphanumeric())
+            && !after.is_some_and(|c| c.is_alphanumeric());
+        replaced.push_str(&rest[..i]);
+        replaced.push_str(if bounded { replacement } else { word });
+        rest = &rest[i + word.len()..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// Replaces identifying parts of a document with consistent pseudonyms
+struct Anonymizer {
+    enabled: Vec<Category>,
+    /// Known usernames and hostnames, replaced wherever they appear as words
+    known: Vec<(Category, String)>,
+    pseudonyms: HashMap<(Category, String), String>,
+    counts: HashMap<Category, usize>,
+}
+
+impl Anonymizer {
+    fn new(rules: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut known = Vec::new();
+        if let Ok(user) = env::var("USER").or_else(|_| env::var("USERNAME")) {
+            known.push((Category::Username, user));
+        }
+        let hostname = fs::read_to_string("/etc/hostname")
+            .ok()
+            .or_else(|| env::var("HOSTNAME").ok());
+        if let Some(hostname) = hostname {
+            known.push((Category::Hostname, hostname.trim().to_string()));
+        }
+
+        let mut enabled = Category::ALL.to_vec();
+        for (name, rule) in rules.into_iter().flat_map(|r| r.iter()) {
+            let name = name.extract::<String>()?;
+            let category = Category::ALL
+                .into_iter()
+                .find(|c| c.name() == name)
+                .ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "Unknown anonymization rule `{}`, expected one of {}",
+                        name,
+                        Category::ALL.map(|c| c.name()).join(", ")
+                    ))
+                })?;
+            if let Ok(enable) = rule.downcast::<PyBool>() {
+                if !enable.is_true() {
+                    enabled.retain(|&c| c != category);
+                }
+            } else {
+                let values = rule.extract::<Vec<String>>().map_err(|_| {
+                    PyValueError::new_err(format!(
+                        "Rule `{}` must be a bool or a list of str",
+                        name
+                    ))
+                })?;
+                known.extend(values.into_iter().map(|v| (category, v)));
+            }
+        }
+        Ok(Self {
+            enabled,
+            known,
+            pseudonyms: HashMap::new(),
+            counts: HashMap::new(),
+        })
+    }
+
+    fn is_enabled(&self, category: Category) -> bool {
+        self.enabled.contains(&category)
+    }
+
+    /// Learn the usernames and hostnames given under keys naming them, so
+    /// they are also replaced where they appear in other values
+    fn learn(
+        &mut self,
+        obj: &Bound<'_, PyAny>,
+        context: Option<Category>,
+    ) -> PyResult<()> {
+        if let Ok(s) = obj.downcast::<PyString>() {
+            if let Some(c @ (Category::Username | Category::Hostname)) = context
+            {
+                let s = s.to_str()?;
+                if s.parse::<IpAddr>().is_err() {
+                    self.known.push((c, s.to_string()));
+                }
+            }
+        } else if let Ok(dict) = obj.downcast::<PyDict>() {
+            for (key, value) in dict.iter() {
+                let context = match key.downcast::<PyString>() {
+                    Ok(name) => Category::of_key(name.to_str()?).or(context),
+                    Err(_) => context,
+                };
+                self.learn(&value, context)?;
+            }
+        } else if obj.is_instance_of::<PyList>()
+            || obj.is_instance_of::<PyTuple>()
+        {
+            for item in obj.try_iter()? {
+                self.learn(&item?, context)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepare the known words to be replaced, once all are learned
+    fn finish_learning(&mut self) {
+        let enabled = &self.enabled;
+        self.known.retain(|(category, word)| {
+            word.len() >= 2 && word != "localhost" && enabled.contains(category)
+        });
+        // Replace longer words first, so `alice` does not break `alice-pc`
+        self.known.sort_by(|(_, a), (_, b)| {
+            b.len().cmp(&a.len()).then_with(|| a.cmp(b))
+        });
+        self.known.dedup_by(|a, b| a.1 == b.1);
+    }
+
+    /// The pseudonym of an identifying value, the same for every occurrence
+    fn pseudonym(&mut self, category: Category, original: &str) -> String {
+        let key = (category, original.to_string());
+        if let Some(pseudonym) = self.pseudonyms.get(&key) {
+            return pseudonym.clone();
+        }
+        let count = self.counts.entry(category).or_default();
+        *count += 1;
+        let n = *count;
+        let pseudonym = match category {
+            Category::Username => format!("user{}", n),
+            Category::Hostname => format!("host{}", n),
+            Category::Email => format!("user{}@example.com", n),
+            Category::Ip if original.contains(':') => {
+                format!("2001:db8::{:x}", n)
+            }
+            Category::Ip => format!("192.0.2.{}", (n - 1) % 254 + 1),
+            Category::Secret => "<redacted>".to_string(),
+        };
+        self.pseudonyms.insert(key, pseudonym.clone());
+        pseudonym
+    }
+
+    /// Anonymize the parts of a string, e.g. addresses or home directories
+    fn string(&mut self, s: &str) -> String {
+        if self.is_enabled(Category::Secret) && is_secret(s.trim()) {
+            return self.pseudonym(Category::Secret, s);
+        }
+
+        // Split into words that may be addresses, keeping what is between
+        let is_word_char =
+            |c: char| c.is_ascii_alphanumeric() || "._%+-@:".contains(c);
+        let mut anonymized = String::with_capacity(s.len());
+        let mut rest = s;
+        while !rest.is_empty() {
+            let end = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+            let (word, tail) = rest.split_at(end);
+            anonymized.push_str(&self.word(word));
+            let sep = tail.find(is_word_char).unwrap_or(tail.len());
+            anonymized.push_str(&tail[..sep]);
+            rest = &tail[sep..];
+        }
+
+        if self.is_enabled(Category::Username) {
+            for home in ["/home/", "/Users/"] {
+                let mut replaced = String::with_capacity(anonymized.len());
+                let mut rest = anonymized.as_str();
+                while let Some(i) = rest.find(home) {
+                    let start = i + home.len();
+                    let end = rest[start..]
+                        .find('/')
+                        .map_or(rest.len(), |e| start + e);
+                    replaced.push_str(&rest[..start]);
+                    let user = &rest[start..end];
+                    if user.is_empty() {
+                        replaced.push_str(user);
+                    } else {
+                        replaced.push_str(
+                            &self.pseudonym(Category::Username, user),
+                        );
+                    }
+                    rest = &rest[end..];
+                }
+                replaced.push_str(rest);
+                anonymized = replaced;
+            }
+        }
+
+        for (category, word) in self.known.clone() {
+            if anonymized.contains(&word) {
+                let pseudonym = self.pseudonym(category, &word);
+                anonymized = replace_word(&anonymized, &word, &pseudonym);
+            }
+        }
+        anonymized
+    }
+
+    /// Anonymize a word if it is an email or IP address
+    fn word(&mut self, word: &str) -> String {
+        let trimmed = word.trim_end_matches(['.', ':']);
+        let suffix = &word[trimmed.len()..];
+        if self.is_enabled(Category::Email) && is_email(trimmed) {
+            return self.pseudonym(Category::Email, trimmed) + suffix;
+        }
+        // Such as `user@host` of ssh, whose user is left to the known words
+        if let Some((user, host)) = trimmed.split_once('@') {
+            return format!("{}@{}{}", user, self.word(host), suffix);
+        }
+        if self.is_enabled(Category::Ip) {
+            // A port may follow IPv4 addresses
+            let (address, port) = match trimmed.rsplit_once(':') {
+                Some((address, port))
+                    if !address.contains(':')
+                        && port.chars().all(|c| c.is_ascii_digit()) =>
+                {
+                    (address, &trimmed[address.len()..])
+                }
+                _ => (trimmed, ""),
+            };
+            if let Ok(ip) = address.parse::<IpAddr>() {
+                if is_identifying_ip(&ip) {
+                    let pseudonym = self.pseudonym(Category::Ip, address);
+                    return pseudonym + port + suffix;
+                }
+            }
+        }
+        word.to_string()
+    }
+
+    /// Anonymize a value, whose values are identifying as a whole if the key
+    /// it is found under says so
+    fn value(
+        &mut self,
+        py: Python<'_>,
+        obj: &Bound<'_, PyAny>,
+        context: Option<Category>,
+    ) -> PyResult<PyObject> {
+        if let Ok(s) = obj.downcast::<PyString>() {
+            let s = s.to_str()?;
+            let whole = context.filter(|&c| self.is_enabled(c));
+            let anonymized = match whole {
+                Some(category) if !s.is_empty() => {
+                    let is_ip = s.parse::<IpAddr>().is_ok();
+                    if category == Category::Hostname && is_ip {
+                        self.string(s)
+                    } else {
+                        self.pseudonym(category, s)
+                    }
+                }
+                _ => self.string(s),
+            };
+            Ok(into_pyany!(PyString::new(py, &anonymized)))
+        } else if let Ok(list) = obj.downcast::<PyList>() {
+            let items = list
+                .iter()
+                .map(|item| self.value(py, &item, context))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(into_pyany!(PyList::new(py, items)?))
+        } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+            let items = tuple
+                .iter()
+                .map(|item| self.value(py, &item, context))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(into_pyany!(PyTuple::new(py, items)?))
+        } else if let Ok(dict) = obj.downcast::<PyDict>() {
+            let anonymized = PyDict::new(py);
+            for (key, value) in dict.iter() {
+                let (key, context) = match key.downcast::<PyString>() {
+                    Ok(name) => {
+                        let name = name.to_str()?;
+                        let context = Category::of_key(name).or(context);
+                        (
+                            into_pyany!(PyString::new(py, &self.string(name))),
+                            context,
+                        )
+                    }
+                    Err(_) => (key.unbind(), context),
+                };
+                anonymized.set_item(key, self.value(py, &value, context)?)?;
+            }
+            Ok(into_pyany!(anonymized))
+        } else {
+            Ok(obj.clone().unbind())
+        }
+    }
+}
+
+/// Anonymize a parsed document, so it can be shared in bug reports.
+///
+/// Usernames, hostnames, email addresses, IP addresses and secrets are
+/// replaced by pseudonyms, such as `user1`, `host1`, `user1@example.com`,
+/// `192.0.2.1` and `<redacted>`. Every occurrence of the same value gets the
+/// same pseudonym, and the structure of the document is kept as is, dict
+/// keys included.
+///
+/// Values are recognized by their form, by the key they are found under,
+/// e.g. `password` or `hostName`, and by the user and host running the
+/// function, along with the extra values given in the rules. Usernames are
+/// also recognized as the directories of `/home` and `/Users`.
+///
+/// Args:
+///   - value (Any): The parsed document, or the `os.PathLike` path of a
+///                  document to load with the parser of its extension.
+///                  Plain strings are values, never paths.
+///   - rules (dict[str, bool | list[str]]): Rules by category, among
+///                                          `usernames`, `hostnames`,
+///                                          `emails`, `ips` and `secrets`.
+///                                          `False` keeps the category as
+///                                          is, a list gives extra values of
+///                                          the category to replace. Every
+///                                          category is replaced by default.
+///
+/// Returns:
+///   - Any: An anonymized copy of the document.
+///
+/// Raises:
+///   - ValueError: If a rule is unknown or malformed, or no parser handles
+///                 the extension of the path.
+///   - IOError, ParseError, EvaluationError: If the path cannot be loaded.
+///
+/// Example:
+/// ```python
+/// >>> anonymize({"user": "alice", "ssh": "alice@10.1.2.3", "token": "x"})
+/// {'user': 'user1', 'ssh': 'user1@192.0.2.1', 'token': '<redacted>'}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (value, rules = None))]
+pub fn anonymize(
+    py: Python<'_>,
+    value: &Bound<'_, PyAny>,
+    rules: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let is_path = value.is_instance(&py.import("os")?.getattr("PathLike")?)?;
+    let value = if is_path {
+        load(py, &value.extract::<PathBuf>()?)?.into_bound(py)
+    } else {
+        value.clone()
+    };
+    let mut anonymizer = Anonymizer::new(rules)?;
+    anonymizer.learn(&value, None)?;
+    anonymizer.finish_learning();
+    anonymizer.value(py, &value, None)
+}
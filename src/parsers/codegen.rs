@@ -0,0 +1,600 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString};
+use pyo3::PyResult;
+
+use crate::parsers::rekey::words;
+use crate::parsers::schema::Dialect;
+
+/// The keywords of Python, which cannot name a field
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break",
+    "class", "continue", "def", "del", "elif", "else", "except", "finally",
+    "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+/// The kind of Python classes generated
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    TypedDict,
+    Dataclass,
+}
+
+impl Target {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "python-typeddict" => Ok(Target::TypedDict),
+            "dataclass" => Ok(Target::Dataclass),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown target `{}`, expected `python-typeddict` or \
+                `dataclass`",
+                name
+            ))),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Make a name in `PascalCase` fit for a class, like `TlsConfig` for
+/// `tls_config`
+fn class_name(name: &str) -> String {
+    let pascal = words(name)
+        .iter()
+        .filter_map(|word| {
+            let mut chars = word.chars().filter(char::is_ascii_alphanumeric);
+            let first = chars.next()?;
+            Some(
+                first.to_ascii_uppercase().to_string()
+                    + &chars.collect::<String>(),
+            )
+        })
+        .collect::<String>();
+    match pascal.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => pascal,
+        Some(_) => format!("Model{}", pascal),
+        None => "Model".to_string(),
+    }
+}
+
+/// Make a key fit for a dataclass field, like `max_connections` for
+/// `max-connections`
+fn field_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", name),
+        None => "_".to_string(),
+        _ if KEYWORDS.contains(&name.as_str()) => format!("{}_", name),
+        _ => name,
+    }
+}
+
+/// A Python literal for a value, as written by `repr`
+fn literal(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    Ok(value.repr()?.to_string())
+}
+
+/// Join the members of a union, without repeating any
+fn joined(members: Vec<String>) -> String {
+    let mut seen = HashSet::new();
+    let members = members
+        .into_iter()
+        .filter(|m| seen.insert(m.clone()))
+        .collect::<Vec<_>>();
+    match members.is_empty() {
+        true => "Any".to_string(),
+        false => members.join(" | "),
+    }
+}
+
+/// A field of a generated class
+struct Field<'py> {
+    key: String,
+    annotation: Vec<String>,
+    required: bool,
+    default: Option<Bound<'py, PyAny>>,
+    description: Option<String>,
+}
+
+/// Generates the Python classes of the objects of a JSON Schema
+struct Generator<'py> {
+    py: Python<'py>,
+    target: Target,
+    root: Bound<'py, PyDict>,
+    /// The source of each class, dependencies first
+    classes: Vec<String>,
+    taken: HashSet<String>,
+    /// The classes of the definitions already referenced
+    references: HashMap<String, String>,
+    /// The names imported from `typing`
+    typing: BTreeSet<&'static str>,
+    /// Whether `dataclasses.field` is used
+    uses_field: bool,
+}
+
+impl<'py> Generator<'py> {
+    /// Reserve a class name, numbered if it is taken
+    fn reserve(&mut self, candidates: &[String]) -> String {
+        let name = candidates
+            .iter()
+            .find(|c| !self.taken.contains(*c))
+            .cloned()
+            .unwrap_or_else(|| {
+                let base = &candidates[0];
+                (2..)
+                    .map(|i| format!("{}{}", base, i))
+                    .find(|c| !self.taken.contains(c))
+                    .unwrap_or_default()
+            });
+        self.taken.insert(name.clone());
+        name
+    }
+
+    /// The members of the union of types a schema node accepts. `owner` and
+    /// `key` name the classes of the objects it declares.
+    fn annotation(
+        &mut self,
+        node: &Bound<'py, PyAny>,
+        owner: &str,
+        key: &str,
+    ) -> PyResult<Vec<String>> {
+        let Ok(node) = node.downcast::<PyDict>() else {
+            self.typing.insert("Any");
+            return Ok(vec!["Any".to_string()]);
+        };
+        if let Some(reference) = node.get_item("$ref")? {
+            return Ok(vec![self.reference(&reference.extract::<String>()?)?]);
+        }
+        if let Some(value) = node.get_item("const")? {
+            return Ok(vec![self.literals(&[value])?]);
+        }
+        if let Some(values) = node.get_item("enum")? {
+            let values = values.downcast_into::<PyList>()?;
+            let (nulls, values): (Vec<_>, Vec<_>) =
+                values.iter().partition(|v| v.is_none());
+            let mut members = Vec::new();
+            if !values.is_empty() {
+                members.push(self.literals(&values)?);
+            }
+            if !nulls.is_empty() {
+                members.push("None".to_string());
+            }
+            return Ok(members);
+        }
+        for combinator in ["anyOf", "oneOf"] {
+            if let Some(schemas) = node.get_item(combinator)? {
+                let mut members = Vec::new();
+                for schema in schemas.downcast_into::<PyList>()?.iter() {
+                    members.extend(self.annotation(&schema, owner, key)?);
+                }
+                return Ok(members);
+            }
+        }
+
+        let types = match node.get_item("type")? {
+            Some(kind) if kind.is_instance_of::<PyList>() => {
+                kind.extract::<Vec<String>>()?
+            }
+            Some(kind) => vec![kind.extract::<String>()?],
+            None if node.contains("properties")? => vec!["object".to_string()],
+            None if node.contains("items")? => vec!["array".to_string()],
+            None => Vec::new(),
+        };
+        if types.is_empty() {
+            self.typing.insert("Any");
+            return Ok(vec!["Any".to_string()]);
+        }
+        let mut members = Vec::new();
+        for kind in types {
+            let member = match kind.as_str() {
+                "string" => "str".to_string(),
+                "integer" => "int".to_string(),
+                "number" => "float".to_string(),
+                "boolean" => "bool".to_string(),
+                "null" => "None".to_string(),
+                "array" => {
+                    let items = match node.get_item("items")? {
+                        Some(items) => self.annotation(&items, owner, key)?,
+                        None => Vec::new(),
+                    };
+                    format!("list[{}]", self.union(items))
+                }
+                "object" if node.contains("properties")? => {
+                    self.class(node, owner, key, None)?
+                }
+                "object" => {
+                    let values = match node.get_item("additionalProperties")? {
+                        Some(values) if !values.is_instance_of::<PyBool>() => {
+                            self.annotation(&values, owner, key)?
+                        }
+                        _ => Vec::new(),
+                    };
+                    format!("dict[str, {}]", self.union(values))
+                }
+                _ => {
+                    self.typing.insert("Any");
+                    "Any".to_string()
+                }
+            };
+            members.push(member);
+        }
+        Ok(members)
+    }
+
+    fn union(&mut self, members: Vec<String>) -> String {
+        if members.is_empty() {
+            self.typing.insert("Any");
+        }
+        joined(members)
+    }
+
+    fn literals(&mut self, values: &[Bound<'py, PyAny>]) -> PyResult<String> {
+        self.typing.insert("Literal");
+        let values =
+            values.iter().map(literal).collect::<PyResult<Vec<_>>>()?;
+        Ok(format!("Literal[{}]", values.join(", ")))
+    }
+
+    /// The class of a definition of the schema, like `#/$defs/Server`.
+    /// References to other documents are typed as `Any`.
+    fn reference(&mut self, reference: &str) -> PyResult<String> {
+        if let Some(name) = self.references.get(reference) {
+            return Ok(name.clone());
+        }
+        let definition = ["#/$defs/", "#/definitions/"]
+            .iter()
+            .find_map(|prefix| {
+                let name = reference.strip_prefix(prefix)?;
+                let section = &prefix[2..prefix.len() - 1];
+                Some((section, name))
+            })
+            .map(|(section, name)| -> PyResult<_> {
+                let node = self
+                    .root
+                    .get_item(section)?
+                    .and_then(|d| d.downcast_into::<PyDict>().ok())
+                    .and_then(|d| d.get_item(name).ok().flatten());
+                Ok(node.map(|node| (name.to_string(), node)))
+            })
+            .transpose()?
+            .flatten();
+        let Some((name, node)) = definition else {
+            self.typing.insert("Any");
+            return Ok("Any".to_string());
+        };
+        let is_object = node
+            .downcast::<PyDict>()
+            .is_ok_and(|d| d.contains("properties").unwrap_or_default());
+        if !is_object {
+            // Not a class, the definition is inlined
+            let members = self.annotation(&node, "", &name)?;
+            return Ok(self.union(members));
+        }
+        // Reserved before generating it, so recursive definitions end
+        let class = self.reserve(&[class_name(&name)]);
+        self.references.insert(reference.to_string(), class.clone());
+        self.class(node.downcast()?, "", &name, Some(class))
+    }
+
+    /// Generate the class of an object declaring its `properties`, named by
+    /// its `title`, else its `key`, prefixed by its `owner` if taken
+    fn class(
+        &mut self,
+        node: &Bound<'py, PyDict>,
+        owner: &str,
+        key: &str,
+        reserved: Option<String>,
+    ) -> PyResult<String> {
+        let name = match reserved {
+            Some(name) => name,
+            None => {
+                let own = match node.get_item("title")? {
+                    Some(title) => class_name(&title.extract::<String>()?),
+                    None => class_name(key),
+                };
+                let prefixed = format!("{}{}", owner, own);
+                self.reserve(&[own, prefixed])
+            }
+        };
+        let required = match node.get_item("required")? {
+            Some(required) => required.extract::<HashSet<String>>()?,
+            None => HashSet::new(),
+        };
+        let mut fields = Vec::new();
+        for (key, child) in Dialect::JsonSchema.children(node)? {
+            let annotation = self.annotation(child.as_any(), &name, &key)?;
+            let description = child
+                .get_item("description")?
+                .map(|d| d.extract::<String>())
+                .transpose()?;
+            fields.push(Field {
+                required: required.contains(&key),
+                default: child.get_item("default")?,
+                key,
+                annotation,
+                description,
+            });
+        }
+        let description = node
+            .get_item("description")?
+            .map(|d| d.extract::<String>())
+            .transpose()?;
+        let source = match self.target {
+            Target::TypedDict => self.typed_dict(&name, description, fields)?,
+            Target::Dataclass => self.dataclass(&name, description, fields)?,
+        };
+        self.classes.push(source);
+        Ok(name)
+    }
+
+    fn typed_dict(
+        &mut self,
+        name: &str,
+        description: Option<String>,
+        fields: Vec<Field<'py>>,
+    ) -> PyResult<String> {
+        self.typing.insert("TypedDict");
+        let annotated = fields
+            .iter()
+            .map(|field| {
+                let annotation = joined(field.annotation.clone());
+                match field.required {
+                    true => annotation,
+                    false => {
+                        self.typing.insert("NotRequired");
+                        format!("NotRequired[{}]", annotation)
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut source = String::new();
+        if fields.iter().all(|f| is_identifier(&f.key)) {
+            source.push_str(&format!("class {}(TypedDict):\n", name));
+            source.push_str(&docstring(description.as_deref()));
+            if description.is_some() && !fields.is_empty() {
+                source.push('\n');
+            }
+            for (field, annotation) in fields.iter().zip(&annotated) {
+                source.push_str(&comment(field.description.as_deref()));
+                source
+                    .push_str(&format!("    {}: {}\n", field.key, annotation));
+            }
+            if fields.is_empty() && description.is_none() {
+                source.push_str("    pass\n");
+            }
+        } else {
+            // Keys that are not identifiers need the functional syntax
+            if let Some(description) = &description {
+                source.push_str(&format!("# {}\n", first_line(description)));
+            }
+            source.push_str(&format!(
+                "{} = TypedDict(\n    {},\n    {{\n",
+                name,
+                literal(PyString::new(self.py, name).as_any())?
+            ));
+            for (field, annotation) in fields.iter().zip(&annotated) {
+                source.push_str(&format!(
+                    "        {}: {},\n",
+                    literal(PyString::new(self.py, &field.key).as_any())?,
+                    literal(PyString::new(self.py, annotation).as_any())?
+                ));
+            }
+            source.push_str("    },\n)\n");
+        }
+        Ok(source)
+    }
+
+    fn dataclass(
+        &mut self,
+        name: &str,
+        description: Option<String>,
+        fields: Vec<Field<'py>>,
+    ) -> PyResult<String> {
+        let mut source = format!("@dataclass\nclass {}:\n", name);
+        source.push_str(&docstring(description.as_deref()));
+        if description.is_some() && !fields.is_empty() {
+            source.push('\n');
+        }
+        // Fields with a default must come after those without one
+        let (mandatory, optional): (Vec<_>, Vec<_>) = fields
+            .into_iter()
+            .partition(|f| f.required && f.default.is_none());
+        let empty = mandatory.is_empty() && optional.is_empty();
+        for field in mandatory.iter().chain(&optional) {
+            let mut annotation = field.annotation.clone();
+            let mut arguments = Vec::new();
+            match &field.default {
+                Some(default)
+                    if default.is_instance_of::<PyList>()
+                        || default.is_instance_of::<PyDict>() =>
+                {
+                    arguments.push(format!(
+                        "default_factory=lambda: {}",
+                        literal(default)?
+                    ));
+                }
+                Some(default) => {
+                    arguments.push(format!("default={}", literal(default)?))
+                }
+                None if !field.required => {
+                    annotation.push("None".to_string());
+                    arguments.push("default=None".to_string());
+                }
+                None => {}
+            }
+            let identifier = field_name(&field.key);
+            if identifier != field.key {
+                arguments.push(format!(
+                    "metadata={{\"key\": {}}}",
+                    literal(PyString::new(self.py, &field.key).as_any())?
+                ));
+            }
+            let value = match arguments.as_slice() {
+                [] => String::new(),
+                [default] if default.starts_with("default=") => {
+                    format!(" = {}", &default["default=".len()..])
+                }
+                _ => {
+                    self.uses_field = true;
+                    format!(" = field({})", arguments.join(", "))
+                }
+            };
+            source.push_str(&comment(field.description.as_deref()));
+            source.push_str(&format!(
+                "    {}: {}{}\n",
+                identifier,
+                joined(annotation),
+                value
+            ));
+        }
+        if empty && description.is_none() {
+            source.push_str("    pass\n");
+        }
+        Ok(source)
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default().trim()
+}
+
+/// The docstring of a class
+fn docstring(description: Option<&str>) -> String {
+    let Some(description) = description else {
+        return String::new();
+    };
+    let escaped = description
+        .trim()
+        .replace('\\', "\\\\")
+        .replace("\"\"\"", "\\\"\\\"\\\"");
+    let indented = escaped.lines().collect::<Vec<_>>().join("\n    ");
+    format!("    \"\"\"{}\"\"\"\n", indented)
+}
+
+fn comment(description: Option<&str>) -> String {
+    match description.map(first_line) {
+        Some(line) if !line.is_empty() => format!("    # {}\n", line),
+        _ => String::new(),
+    }
+}
+
+/// Generate Python types from a JSON Schema, so parsed configurations get
+/// static types.
+///
+/// The root schema and every object declaring `properties` become a class,
+/// named by its `title`, else by its key in `PascalCase`, prefixed by the
+/// class containing it if the name is taken. Definitions referenced as
+/// `#/$defs/<name>` or `#/definitions/<name>` become a class of their name.
+/// `type`, `enum`, `const`, `anyOf`, `oneOf`, `items` and
+/// `additionalProperties` are turned into annotations, anything else, like
+/// references to other documents, into `Any`. Descriptions become docstrings
+/// and comments.
+///
+/// Args:
+///   - schema (dict): The JSON Schema.
+///   - target (str): `python-typeddict` for `TypedDict` classes, keys that
+///                   are not `required` being `NotRequired`, or `dataclass`
+///                   for dataclasses, fields that are not `required` being
+///                   optional and defaulting to their `default` or `None`.
+///                   Keys that are not identifiers are written with the
+///                   functional syntax of `TypedDict`, and renamed in
+///                   dataclasses, with their key in the `metadata` of their
+///                   field.
+///   - name (str): The name of the class of the root schema.
+///
+/// Returns:
+///   - str: The source of a Python module defining the types, the root one
+///          last.
+///
+/// Raises:
+///   - ValueError: If the target is unknown.
+///   - TypeError: If a keyword of the schema has the wrong type, like
+///                `properties` not being a dict.
+///
+/// Example:
+/// ```python
+/// >>> schema = {
+/// ...     "type": "object",
+/// ...     "properties": {
+/// ...         "port": {"type": "integer"},
+/// ...         "tls": {"properties": {"enable": {"type": "boolean"}}},
+/// ...     },
+/// ...     "required": ["port"],
+/// ... }
+/// >>> print(codegen(schema, name="Config"))
+/// from __future__ import annotations
+///
+/// from typing import NotRequired, TypedDict
+///
+///
+/// class Tls(TypedDict):
+///     enable: NotRequired[bool]
+///
+///
+/// class Config(TypedDict):
+///     port: int
+///     tls: NotRequired[Tls]
+/// ```
+#[pyfunction]
+#[pyo3(signature = (schema, target = "python-typeddict", name = "Root"))]
+pub fn codegen<'py>(
+    py: Python<'py>,
+    schema: Bound<'py, PyDict>,
+    target: &str,
+    name: &str,
+) -> PyResult<String> {
+    let mut generator = Generator {
+        py,
+        target: Target::of_name(target)?,
+        root: schema.clone(),
+        classes: Vec::new(),
+        taken: HashSet::new(),
+        references: HashMap::new(),
+        typing: BTreeSet::new(),
+        uses_field: false,
+    };
+    let root = class_name(name);
+    let is_object = schema.contains("properties")?
+        || schema.get_item("type")?.is_some_and(|t| {
+            t.extract::<String>().is_ok_and(|t| t == "object")
+        });
+    if is_object {
+        let reserved = generator.reserve(&[root]);
+        generator.class(&schema, "", name, Some(reserved))?;
+    } else {
+        // Not an object, the root is an alias of its type
+        let members = generator.annotation(schema.as_any(), "", name)?;
+        let alias = format!("{} = {}\n", root, generator.union(members));
+        generator.classes.push(alias);
+    }
+
+    let mut module = String::from("from __future__ import annotations\n\n");
+    if generator.target == Target::Dataclass {
+        match generator.uses_field {
+            true => {
+                module.push_str("from dataclasses import dataclass, field\n")
+            }
+            false => module.push_str("from dataclasses import dataclass\n"),
+        }
+    }
+    if !generator.typing.is_empty() {
+        let names = generator.typing.iter().copied().collect::<Vec<_>>();
+        module.push_str(&format!("from typing import {}\n", names.join(", ")));
+    }
+    module.push_str("\n\n");
+    module.push_str(&generator.classes.join("\n\n"));
+    Ok(module)
+}
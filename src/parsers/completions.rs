@@ -0,0 +1,179 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+use pyo3::PyResult;
+
+use crate::parsers::schema::{attrpath, is_option, Dialect};
+
+/// An option that can be completed, with the first line of its description
+struct Completion {
+    path: String,
+    description: Option<String>,
+}
+
+/// The first line of the description of a schema node, which nix options
+/// may also give as a documentation attrset with a `text`
+fn description(node: &Bound<'_, PyDict>) -> PyResult<Option<String>> {
+    let Some(description) = node.get_item("description")? else {
+        return Ok(None);
+    };
+    let text = match description.downcast::<PyDict>() {
+        Ok(doc) => doc.get_item("text")?,
+        Err(_) => Some(description.clone()),
+    };
+    Ok(text
+        .and_then(|t| t.downcast_into::<PyString>().ok())
+        .and_then(|t| {
+            let text = t.to_str().ok()?;
+            let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+            Some(line.to_string())
+        }))
+}
+
+/// Collect the leaf options of a tree, in the order they are declared
+fn collect(
+    dialect: Dialect,
+    node: &Bound<'_, PyDict>,
+    path: &mut Vec<String>,
+    completions: &mut Vec<Completion>,
+) -> PyResult<()> {
+    let children = dialect.children(node)?;
+    if children.is_empty() {
+        // Empty groups of nix options declare nothing to complete
+        let is_leaf = match dialect {
+            Dialect::JsonSchema => !path.is_empty(),
+            Dialect::NixOptions => is_option(node)?,
+        };
+        if is_leaf {
+            completions.push(Completion {
+                path: attrpath(path),
+                description: description(node)?,
+            });
+        }
+        return Ok(());
+    }
+    for (name, child) in children {
+        path.push(name);
+        collect(dialect, &child, path, completions)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Quote a word for POSIX shells and fish, where single quotes cannot be
+/// escaped inside of single quotes
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+fn bash(function: &str, completions: &[Completion]) -> String {
+    let mut script = format!("{}() {{\n    local options=(\n", function);
+    for completion in completions {
+        script.push_str(&format!("        {}\n", quote(&completion.path)));
+    }
+    script.push_str(
+        "    )\n    COMPREPLY=($(compgen -W \"${options[*]}\" -- \
+        \"${COMP_WORDS[COMP_CWORD]}\"))\n}\n",
+    );
+    script
+}
+
+fn zsh(function: &str, completions: &[Completion]) -> String {
+    let mut script = format!("{}() {{\n    local -a options=(\n", function);
+    for completion in completions {
+        // `_describe` separates the completion from its description by the
+        // first unescaped colon
+        let mut entry = completion.path.replace(':', "\\:");
+        if let Some(description) = &completion.description {
+            entry = format!("{}:{}", entry, description);
+        }
+        script.push_str(&format!("        {}\n", quote(&entry)));
+    }
+    script.push_str("    )\n    _describe 'option' options\n}\n");
+    script
+}
+
+fn fish(command: &str, completions: &[Completion]) -> String {
+    completions
+        .iter()
+        .map(|completion| {
+            let mut line = format!(
+                "complete -c {} -f -a {}",
+                quote(command),
+                quote(&completion.path)
+            );
+            if let Some(description) = &completion.description {
+                line.push_str(&format!(" -d {}", quote(description)));
+            }
+            line + "\n"
+        })
+        .collect()
+}
+
+/// Generate shell completions for the options declared by an options tree.
+///
+/// The tree is either a JSON Schema, whose objects declare their keys under
+/// `properties`, or a nix options schema, whose options are attrsets with
+/// `_type = "option"` and every other attrset is a group of options. Every
+/// leaf option is completed by its path, written like a nix attribute path,
+/// along with the first line of its `description` where the shell supports
+/// it.
+///
+/// Args:
+///   - value (dict): The options tree.
+///   - style (str): The shell to generate the completions for, `"bash"`,
+///                  `"zsh"` or `"fish"`.
+///   - command (str): The command the options belong to. The bash and zsh
+///                    snippets define a function `_<command>_options`
+///                    completing the current word, which completion
+///                    scripts can call, the fish snippet registers the
+///                    completions of the command directly.
+///
+/// Returns:
+///   - str: The completion snippet.
+///
+/// Raises:
+///   - TypeError: If the `properties` of a JSON Schema are not a dict.
+///   - ValueError: If the style is not supported.
+///
+/// Example:
+/// ```python
+/// >>> options = {
+/// ...     "port": {"_type": "option", "description": "The port to bind"},
+/// ... }
+/// >>> print(completions_from(options, style="fish"))
+/// complete -c 'cosutils' -f -a 'port' -d 'The port to bind'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (value, style = "bash", command = "cosutils"))]
+pub fn completions_from(
+    value: &Bound<'_, PyDict>,
+    style: &str,
+    command: &str,
+) -> PyResult<String> {
+    let mut completions = Vec::new();
+    collect(
+        Dialect::detect(value)?,
+        value,
+        &mut Vec::new(),
+        &mut completions,
+    )?;
+
+    let function = format!(
+        "_{}_options",
+        command
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>()
+    );
+    match style {
+        "bash" => Ok(bash(&function, &completions)),
+        "zsh" => Ok(zsh(&function, &completions)),
+        "fish" => Ok(fish(command, &completions)),
+        _ => Err(PyValueError::new_err(format!(
+            "Unsupported completion style `{}`, expected `bash`, `zsh` or \
+            `fish`",
+            style
+        ))),
+    }
+}
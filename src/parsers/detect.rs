@@ -0,0 +1,298 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use pyo3::{PyObject, PyResult};
+
+use crate::parsers::utils::TryToPyObject;
+
+/// The formats `detect` tells apart, in the order ties are broken
+const FORMATS: [&str; 5] = ["jsonc", "nix", "toml", "yaml", "ini"];
+
+/// How much a piece of evidence weighs towards a format
+const PARSES: f64 = 0.5;
+const DECLARED: f64 = 0.4;
+const CUE: f64 = 0.1;
+
+/// The extension of a path in lowercase, as `.NIX` and `.nix` files are the
+/// same on case-insensitive file systems
+pub fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+/// The format of a file by its extension
+pub fn format_of_extension(path: &Path) -> Option<&'static str> {
+    match extension(path).as_deref() {
+        Some("nix") => Some("nix"),
+        Some("json" | "jsonc") => Some("jsonc"),
+        Some("toml") => Some("toml"),
+        Some("yaml" | "yml") => Some("yaml"),
+        Some("ini") => Some("ini"),
+        _ => None,
+    }
+}
+
+/// The format an editor modeline declares, like `# vim: ft=nix` or
+/// `-*- mode: yaml -*-`, looked up in the first and last lines
+fn format_of_modeline(content: &str) -> Option<&'static str> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let edges = lines.iter().take(5).chain(lines.iter().rev().take(5));
+    for line in edges {
+        let line = line.to_ascii_lowercase();
+        if !["vim:", "vi:", "-*-"].iter().any(|m| line.contains(m)) {
+            continue;
+        }
+        let declared = ["ft=", "filetype=", "mode:"].iter().find_map(|key| {
+            let (_, rest) = line.split_once(key)?;
+            let name = rest.trim_start();
+            let end = name
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(name.len());
+            Some(name[..end].to_string())
+        });
+        let format = match declared.as_deref() {
+            Some("nix") => "nix",
+            Some("json" | "jsonc") => "jsonc",
+            Some("toml") => "toml",
+            Some("yaml") => "yaml",
+            Some("ini" | "dosini") => "ini",
+            _ => continue,
+        };
+        return Some(format);
+    }
+    None
+}
+
+/// Whether the content is valid in a format, `None` if this build has no
+/// parser for it
+fn parses(format: &str, content: &str) -> Option<bool> {
+    match format {
+        #[cfg(feature = "jsonc")]
+        "jsonc" => Some(
+            jsonc_parser::parse_to_value(content, &Default::default())
+                .is_ok_and(|v| v.is_some()),
+        ),
+        #[cfg(feature = "nix")]
+        "nix" => Some(rnix::Root::parse(content).errors().is_empty()),
+        #[cfg(feature = "manifest")]
+        "toml" => Some(toml::from_str::<toml::Table>(content).is_ok()),
+        _ => None,
+    }
+}
+
+/// Hints of a format in the content, which do not prove it like a parser
+fn cues(format: &str, content: &str) -> Vec<&'static str> {
+    let lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+    let most = |f: &dyn Fn(&str) -> bool| {
+        lines.iter().filter(|l| f(l)).count() * 2 > lines.len()
+    };
+    let is_key = |k: &str| {
+        !k.is_empty()
+            && k.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c))
+    };
+    let mut cues = Vec::new();
+    match format {
+        "jsonc" => {
+            let first = content.trim_start().chars().next();
+            if matches!(first, Some('{' | '[')) {
+                cues.push("starts with `{` or `[`");
+            }
+            if content.contains("\":") {
+                cues.push("has quoted keys followed by `:`");
+            }
+            if lines.iter().any(|l| l.starts_with("//")) {
+                cues.push("has `//` comments");
+            }
+        }
+        "nix" => {
+            let words = content
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .collect::<Vec<_>>();
+            if words.contains(&"let") && words.contains(&"in") {
+                cues.push("has `let ... in` bindings");
+            }
+            if words.contains(&"inherit") {
+                cues.push("has `inherit` bindings");
+            }
+            if content.contains("}:") || content.contains("...") {
+                cues.push("defines a function taking an attrset");
+            }
+        }
+        "toml" | "ini" => {
+            if lines.iter().any(|l| {
+                l.starts_with('[') && l.ends_with(']') && !l.contains(',')
+            }) {
+                cues.push("has `[section]` headers");
+            }
+            if most(&|l| {
+                l.starts_with(['#', ';', '['])
+                    || l.split_once('=').is_some_and(|(k, _)| {
+                        is_key(k.trim().trim_matches('"'))
+                    })
+            }) {
+                cues.push("most lines are `key = value` pairs");
+            }
+            if format == "ini" && lines.iter().any(|l| l.starts_with(';')) {
+                cues.push("has `;` comments");
+            }
+        }
+        "yaml" => {
+            if content.starts_with("---") {
+                cues.push("starts with a `---` document marker");
+            }
+            if most(&|l| {
+                l.starts_with(['#', '-'])
+                    || l.split_once(':').is_some_and(|(k, v)| {
+                        is_key(k.trim_start_matches("- "))
+                            && (v.is_empty() || v.starts_with(' '))
+                    })
+            }) {
+                cues.push("most lines are `key: value` pairs or list items");
+            }
+        }
+        _ => {}
+    }
+    cues
+}
+
+/// The best guess of `detect`
+struct Detection {
+    format: Option<&'static str>,
+    confidence: f64,
+    evidence: Vec<String>,
+}
+
+impl TryToPyObject for Detection {
+    fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("format", self.format)?;
+        dict.set_item("confidence", self.confidence)?;
+        dict.set_item("evidence", &self.evidence)?;
+        Ok(dict.into_any().unbind())
+    }
+}
+
+fn detect_content(content: &[u8], path: Option<&Path>) -> Detection {
+    let undetected = |evidence: &str| Detection {
+        format: None,
+        confidence: 0.0,
+        evidence: vec![evidence.to_string()],
+    };
+    let Ok(content) = std::str::from_utf8(content) else {
+        return undetected("the content is not UTF-8 text");
+    };
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    if content.trim().is_empty() {
+        return undetected("the content is empty");
+    }
+
+    let extension = path.and_then(format_of_extension);
+    let modeline = format_of_modeline(content);
+    let candidates = FORMATS.map(|format| {
+        let mut score = 0.0;
+        let mut evidence = Vec::new();
+        if extension == Some(format) {
+            score += DECLARED;
+            evidence.push(format!(
+                "the extension is `.{}`",
+                path.and_then(self::extension).unwrap_or_default()
+            ));
+        }
+        if modeline == Some(format) {
+            score += DECLARED;
+            evidence.push(format!("a modeline declares `{}`", format));
+        }
+        if parses(format, content) == Some(true) {
+            score += PARSES;
+            evidence.push(format!("the content parses as {}", format));
+        }
+        for cue in cues(format, content) {
+            score += CUE;
+            evidence.push(format!("the content {}", cue));
+        }
+        (format, score, evidence)
+    });
+
+    let total = candidates.iter().map(|c| c.1).sum::<f64>();
+    // Ties go to the first format, as `max_by` keeps the last maximum
+    let best = candidates
+        .into_iter()
+        .rev()
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match best {
+        Some((format, score, evidence)) if score > 0.0 => Detection {
+            format: Some(format),
+            // Evidence fitting other formats as well makes the guess weaker
+            confidence: score.min(1.0) * score / total,
+            evidence,
+        },
+        _ => undetected("nothing in the content points to a format"),
+    }
+}
+
+/// What can be detected, a path to read, or the content itself
+#[derive(FromPyObject)]
+pub enum Source<'py> {
+    Content(String),
+    Bytes(Bound<'py, PyBytes>),
+    Path(PathBuf),
+}
+
+/// Guess the format of a document from its extension and its content.
+///
+/// Meant for files without extension or with a misleading one, so tools can
+/// ask the user before parsing documents whose format is unclear. The
+/// extension, an editor modeline like `# vim: ft=nix` and the parsers of
+/// this build each weigh heavily, hints in the content like `[section]`
+/// headers or `let ... in` bindings weigh less.
+///
+/// Args:
+///   - source (os.PathLike | bytes | str): The path of the document, or its
+///                                         content. Plain strings are
+///                                         content, never paths.
+///
+/// Returns:
+///   - _Detection: The best guess of `format`, among `jsonc`, `nix`, `toml`,
+///                 `yaml` and `ini`, or `None` if nothing points to one, the
+///                 `confidence` of the guess between 0 and 1, lower when the
+///                 evidence is thin or fits several formats, and the
+///                 `evidence` found for the guessed format.
+///
+/// Raises:
+///   - IOError: If the path cannot be read.
+///
+/// Example:
+/// ```python
+/// >>> detect(b'{ "a": 1 }')["evidence"]
+/// ['the content parses as jsonc', 'the content starts with `{` or `[`',
+///  'the content has quoted keys followed by `:`']
+/// >>> detect("let a = 1; in a")["format"]
+/// 'nix'
+/// ```
+#[pyfunction]
+pub fn detect(py: Python<'_>, source: Source<'_>) -> PyResult<PyObject> {
+    let detection = match source {
+        Source::Content(content) => detect_content(content.as_bytes(), None),
+        Source::Bytes(bytes) => detect_content(bytes.as_bytes(), None),
+        Source::Path(path) => {
+            let content = fs::read(&path).map_err(|e| {
+                PyIOError::new_err(format!(
+                    "Failed to read file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            detect_content(&content, Some(&path))
+        }
+    };
+    detection.try_to_pyobject(py)
+}
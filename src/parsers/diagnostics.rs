@@ -0,0 +1,343 @@
+use pyo3::exceptions::{PyBaseException, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict, PyList};
+use pyo3::PyResult;
+
+/// The severity of a diagnostic
+#[derive(Clone, Copy)]
+enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "error" => Ok(Level::Error),
+            "warning" => Ok(Level::Warning),
+            "note" | "notice" => Ok(Level::Note),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown level `{}`, expected `error`, `warning` or `note`",
+                name
+            ))),
+        }
+    }
+
+    fn sarif(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+        }
+    }
+
+    fn github(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "notice",
+        }
+    }
+}
+
+/// A diagnostic of any of the parsers, in the shape shared by the output
+/// formats
+struct Diagnostic {
+    level: Level,
+    code: Option<String>,
+    message: String,
+    path: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+/// Remove the ANSI escapes of a message rendered with colors
+fn plain(message: &str) -> String {
+    let mut plain = String::with_capacity(message.len());
+    let mut chars = message.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip the escape up to its final byte, like the `m` of `\e[1m`
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Collect the diagnostics of a list of dicts, as returned by the linters,
+/// and of exceptions raised by the loaders, each of which gives one
+/// diagnostic per error of its `errors` attribute
+fn collect(
+    diagnostics: &Bound<'_, PyAny>,
+    path: Option<&str>,
+    level: Level,
+) -> PyResult<Vec<Diagnostic>> {
+    fn get<'py, T: FromPyObject<'py>>(
+        dict: &Bound<'py, PyDict>,
+        key: &str,
+    ) -> PyResult<Option<T>> {
+        match dict.get_item(key)? {
+            Some(v) if !v.is_none() => v.extract().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    let mut collected = Vec::new();
+    for item in diagnostics.try_iter()? {
+        let item = item?;
+        if item.is_instance_of::<PyBaseException>() {
+            let errors = match item.getattr("errors") {
+                Ok(errors) => errors.downcast_into::<PyList>()?,
+                Err(_) => PyList::new(item.py(), [&item])?,
+            };
+            for err in errors.iter() {
+                let code = match err.getattr("code") {
+                    Ok(code) => Some(code.extract()?),
+                    Err(_) => None,
+                };
+                collected.push(Diagnostic {
+                    level: Level::Error,
+                    code,
+                    message: plain(&err.str()?.to_cow()?),
+                    path: path.map(str::to_string),
+                    line: None,
+                    column: None,
+                    start: None,
+                    end: None,
+                });
+            }
+        } else if let Ok(dict) = item.downcast::<PyDict>() {
+            let message = get::<String>(dict, "message")?.ok_or_else(|| {
+                PyValueError::new_err("Every diagnostic needs a `message`")
+            })?;
+            let level = match get::<String>(dict, "level")? {
+                Some(name) => Level::of_name(&name)?,
+                None => level,
+            };
+            collected.push(Diagnostic {
+                level,
+                code: get(dict, "code")?,
+                message: plain(&message),
+                path: get(dict, "path")?.or_else(|| path.map(str::to_string)),
+                line: get(dict, "line")?,
+                column: get(dict, "column")?,
+                start: get(dict, "start")?,
+                end: get(dict, "end")?,
+            });
+        } else {
+            return Err(PyTypeError::new_err(format!(
+                "Cannot convert python type {} to a diagnostic, expected a \
+                 dict or an exception",
+                item.get_type().name()?
+            )));
+        }
+    }
+    Ok(collected)
+}
+
+/// Convert diagnostics to a SARIF 2.1.0 log, as uploaded by code scanning
+/// services.
+///
+/// Diagnostics are either dicts, like those returned by `nix.lint`, or
+/// exceptions raised by the loaders, which give one result per error of
+/// their `errors` attribute. Dicts need a `message`, and may give the
+/// `code`, `level`, `path`, `line`, `column`, `start` and `end` of the
+/// diagnostic, where `start` and `end` are byte offsets. ANSI escapes
+/// of colored messages are removed.
+///
+/// Args:
+///   - diagnostics (Iterable[dict | BaseException]): The diagnostics.
+///   - path (str | None): The path of the file of diagnostics without a
+///                        `path`.
+///   - level (str): The level of dicts without a `level`, either `"error"`,
+///                  `"warning"` or `"note"`. Exceptions are always errors.
+///   - tool (str): The name of the tool reported in the log.
+///
+/// Returns:
+///   - str: The SARIF log as JSON.
+///
+/// Raises:
+///   - TypeError: If a diagnostic is neither a dict nor an exception.
+///   - ValueError: If a level is unknown or a dict has no `message`.
+///
+/// Example:
+/// ```python
+/// >>> diags = nix.lint("default.nix")
+/// >>> sarif = json.loads(diagnostics_to_sarif(diags, path="default.nix"))
+/// >>> sarif["runs"][0]["results"][0]["ruleId"]
+/// 'unused_argument'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    diagnostics, path = None, level = "warning", tool = "cosutils"
+))]
+pub fn diagnostics_to_sarif(
+    py: Python<'_>,
+    diagnostics: &Bound<'_, PyAny>,
+    path: Option<&str>,
+    level: &str,
+    tool: &str,
+) -> PyResult<String> {
+    let diagnostics = collect(diagnostics, path, Level::of_name(level)?)?;
+
+    let results = PyList::empty(py);
+    let rules = PyList::empty(py);
+    let mut rule_ids = Vec::new();
+    for diag in &diagnostics {
+        let result = PyDict::new(py);
+        if let Some(code) = &diag.code {
+            result.set_item("ruleId", code)?;
+            if !rule_ids.contains(code) {
+                let rule = PyDict::new(py);
+                rule.set_item("id", code)?;
+                rules.append(rule)?;
+                rule_ids.push(code.clone());
+            }
+        }
+        result.set_item("level", diag.level.sarif())?;
+        let message = PyDict::new(py);
+        message.set_item("text", &diag.message)?;
+        result.set_item("message", message)?;
+
+        if let Some(path) = &diag.path {
+            let physical = PyDict::new(py);
+            let artifact = PyDict::new(py);
+            artifact.set_item("uri", path)?;
+            physical.set_item("artifactLocation", artifact)?;
+            let region = PyDict::new(py);
+            if let Some(line) = diag.line {
+                region.set_item("startLine", line)?;
+            }
+            if let Some(column) = diag.column {
+                region.set_item("startColumn", column)?;
+            }
+            if let Some(start) = diag.start {
+                region.set_item("byteOffset", start)?;
+                if let Some(end) = diag.end {
+                    region.set_item("byteLength", end.saturating_sub(start))?;
+                }
+            }
+            if !region.is_empty() {
+                physical.set_item("region", region)?;
+            }
+            let location = PyDict::new(py);
+            location.set_item("physicalLocation", physical)?;
+            result.set_item("locations", [location])?;
+        }
+        results.append(result)?;
+    }
+
+    let driver = PyDict::new(py);
+    driver.set_item("name", tool)?;
+    driver.set_item("rules", rules)?;
+    let run = PyDict::new(py);
+    run.set_item("tool", [("driver", driver)].into_py_dict(py)?)?;
+    run.set_item("results", results)?;
+    let log = PyDict::new(py);
+    log.set_item("$schema", "https://json.schemastore.org/sarif-2.1.0.json")?;
+    log.set_item("version", "2.1.0")?;
+    log.set_item("runs", [run])?;
+
+    let kwargs = [("indent", 2)].into_py_dict(py)?;
+    py.import("json")?
+        .call_method("dumps", (log,), Some(&kwargs))?
+        .extract()
+}
+
+/// Escape the data of a workflow command, and also its separators when it
+/// is the value of a property
+fn escaped(data: &str, property: bool) -> String {
+    let mut escaped = String::with_capacity(data.len());
+    for c in data.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            ':' if property => escaped.push_str("%3A"),
+            ',' if property => escaped.push_str("%2C"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Convert diagnostics to GitHub Actions workflow commands, which annotate
+/// the files of a pull request when printed by a step.
+///
+/// Diagnostics are given like to `diagnostics_to_sarif`, the `code` of each
+/// becoming the title of its annotation.
+///
+/// Args:
+///   - diagnostics (Iterable[dict | BaseException]): The diagnostics.
+///   - path (str | None): The path of the file of diagnostics without a
+///                        `path`.
+///   - level (str): The level of dicts without a `level`, either `"error"`,
+///                  `"warning"` or `"note"`. Exceptions are always errors.
+///
+/// Returns:
+///   - str: One `::error`, `::warning` or `::notice` command per line.
+///
+/// Raises:
+///   - TypeError: If a diagnostic is neither a dict nor an exception.
+///   - ValueError: If a level is unknown or a dict has no `message`.
+///
+/// Example:
+/// ```python
+/// # `default.nix` contains:
+/// # ```
+/// # { pkgs, lib, ... }: let x = 1; in { a = lib.id 1; }
+/// # ```
+/// >>> print(to_github_annotations(nix.lint("default.nix"), "default.nix"))
+/// ::warning file=default.nix,line=1,col=3,title=unused_argument::Unused function argument `pkgs`
+/// ::warning file=default.nix,line=1,col=25,title=unused_let::Unused let binding `x`
+/// ```
+#[pyfunction]
+#[pyo3(signature = (diagnostics, path = None, level = "warning"))]
+pub fn to_github_annotations(
+    diagnostics: &Bound<'_, PyAny>,
+    path: Option<&str>,
+    level: &str,
+) -> PyResult<String> {
+    let diagnostics = collect(diagnostics, path, Level::of_name(level)?)?;
+    let lines = diagnostics
+        .iter()
+        .map(|diag| {
+            let mut properties = Vec::new();
+            if let Some(path) = &diag.path {
+                properties.push(format!("file={}", escaped(path, true)));
+            }
+            if let Some(line) = diag.line {
+                properties.push(format!("line={}", line));
+            }
+            if let Some(column) = diag.column {
+                properties.push(format!("col={}", column));
+            }
+            if let Some(code) = &diag.code {
+                properties.push(format!("title={}", escaped(code, true)));
+            }
+            let properties = if properties.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", properties.join(","))
+            };
+            format!(
+                "::{}{}::{}",
+                diag.level.github(),
+                properties,
+                escaped(&diag.message, false)
+            )
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
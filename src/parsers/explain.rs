@@ -0,0 +1,70 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::parsers::utils::ErrorCode;
+
+/// The documentation of an error code, which every code must have
+fn explanation(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::JsoncSyntax => include_str!("explain/JSONC_E001.md"),
+        ErrorCode::JsoncEmpty => include_str!("explain/JSONC_E002.md"),
+        ErrorCode::JsoncInvalidNumber => include_str!("explain/JSONC_E003.md"),
+        ErrorCode::JsoncConversion => include_str!("explain/JSONC_E004.md"),
+        ErrorCode::JsoncDuplicateKey => include_str!("explain/JSONC_E010.md"),
+        ErrorCode::JsoncLoneSurrogate => include_str!("explain/JSONC_E011.md"),
+        ErrorCode::JsoncOverlongNumber => {
+            include_str!("explain/JSONC_E012.md")
+        }
+        ErrorCode::NixSyntax => include_str!("explain/NIX_E001.md"),
+        ErrorCode::NixEvaluation => include_str!("explain/NIX_E002.md"),
+        ErrorCode::NixConversion => include_str!("explain/NIX_E003.md"),
+        ErrorCode::NixImportCycle => include_str!("explain/NIX_E004.md"),
+        ErrorCode::NixWarning => include_str!("explain/NIX_E010.md"),
+        ErrorCode::NixFlakeLock => include_str!("explain/NIX_E020.md"),
+        ErrorCode::NixFlakeInput => include_str!("explain/NIX_E021.md"),
+        ErrorCode::NixFetchOffline => include_str!("explain/NIX_E022.md"),
+        ErrorCode::NixTimeout => include_str!("explain/NIX_E030.md"),
+        ErrorCode::NixMemoryLimit => include_str!("explain/NIX_E031.md"),
+        ErrorCode::NixCallDepth => include_str!("explain/NIX_E032.md"),
+        ErrorCode::NixNestingLimit => include_str!("explain/NIX_E033.md"),
+        ErrorCode::NixSchema => include_str!("explain/NIX_E040.md"),
+        ErrorCode::ManifestSyntax => include_str!("explain/MANIFEST_E001.md"),
+        ErrorCode::ManifestInvalid => include_str!("explain/MANIFEST_E002.md"),
+        ErrorCode::ManifestConflict => {
+            include_str!("explain/MANIFEST_E003.md")
+        }
+        ErrorCode::ManagedMarkers => include_str!("explain/MANAGED_E001.md"),
+    }
+}
+
+/// Explain an error code, like the `code` of a `ParseError`, for `--explain`
+/// options of command line tools.
+///
+/// The explanation is Markdown, titled by the code, telling what the error
+/// means, its common causes, and an example of it with its fix. Every code
+/// raised by the parsers is documented, whatever the formats of the build.
+///
+/// Args:
+///   - code (str): The error code, like `JSONC_E010`, in any case.
+///
+/// Returns:
+///   - str: The explanation of the code.
+///
+/// Raises:
+///   - ValueError: If the code is unknown.
+///
+/// Example:
+/// ```python
+/// >>> print(explain("JSONC_E010").splitlines()[0])
+/// # JSONC_E010: An object defines the same key more than once
+/// ```
+#[pyfunction]
+pub fn explain(code: &str) -> PyResult<&'static str> {
+    let Some(code) = ErrorCode::of_str(code.trim()) else {
+        return Err(PyValueError::new_err(format!(
+            "Unknown error code `{}`, expected one like `JSONC_E001`",
+            code
+        )));
+    };
+    Ok(explanation(code))
+}
@@ -0,0 +1,279 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::PyResult;
+
+/// What the converters do with infinite and NaN floats
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Raise an error naming the float
+    Error,
+    /// Replace the float by `None`, or `null` in text
+    Null,
+    /// Keep the float, where the target can represent it
+    Float,
+}
+
+impl NonFinite {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "error" => Ok(NonFinite::Error),
+            "none" => Ok(NonFinite::Null),
+            "float" => Ok(NonFinite::Float),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown non-finite float policy `{}`, expected `error`, \
+                 `none` or `float`",
+                name
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NonFinite::Error => "error",
+            NonFinite::Null => "none",
+            NonFinite::Float => "float",
+        }
+    }
+}
+
+/// How the serializers write finite floats
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FloatRepr {
+    /// The fewest digits reading back to the same float, with an exponent
+    /// for very large or small ones
+    Shortest,
+    /// Positional notation, never with an exponent
+    Decimal,
+    /// Always with an exponent, one digit before the point
+    Scientific,
+}
+
+impl FloatRepr {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "shortest" => Ok(FloatRepr::Shortest),
+            "decimal" => Ok(FloatRepr::Decimal),
+            "scientific" => Ok(FloatRepr::Scientific),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown float repr `{}`, expected `shortest`, `decimal` or \
+                 `scientific`",
+                name
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FloatRepr::Shortest => "shortest",
+            FloatRepr::Decimal => "decimal",
+            FloatRepr::Scientific => "scientific",
+        }
+    }
+}
+
+const UNSET: u8 = 0;
+const ERROR: u8 = 1;
+const NULL: u8 = 2;
+const FLOAT: u8 = 3;
+
+const SHORTEST: u8 = 0;
+const DECIMAL: u8 = 1;
+const SCIENTIFIC: u8 = 2;
+
+/// No precision, the float is written in full
+const UNLIMITED: usize = usize::MAX;
+
+/// The policies set from Python
+static NON_FINITE: AtomicU8 = AtomicU8::new(UNSET);
+static REPR: AtomicU8 = AtomicU8::new(SHORTEST);
+static PRECISION: AtomicUsize = AtomicUsize::new(UNLIMITED);
+
+/// The float policies a conversion runs under
+#[derive(Clone, Copy)]
+pub struct FloatPolicy {
+    /// What is done with infinite and NaN floats, `None` leaving it to each
+    /// converter
+    pub non_finite: Option<NonFinite>,
+    pub repr: FloatRepr,
+    /// The digits written after the point, all of them if `None`
+    pub precision: Option<usize>,
+}
+
+impl FloatPolicy {
+    /// The policies selected by `set_float_policy`
+    pub fn current() -> Self {
+        let non_finite = match NON_FINITE.load(Ordering::Relaxed) {
+            ERROR => Some(NonFinite::Error),
+            NULL => Some(NonFinite::Null),
+            FLOAT => Some(NonFinite::Float),
+            _ => None,
+        };
+        let repr = match REPR.load(Ordering::Relaxed) {
+            DECIMAL => FloatRepr::Decimal,
+            SCIENTIFIC => FloatRepr::Scientific,
+            _ => FloatRepr::Shortest,
+        };
+        let precision = match PRECISION.load(Ordering::Relaxed) {
+            UNLIMITED => None,
+            precision => Some(precision),
+        };
+        Self {
+            non_finite,
+            repr,
+            precision,
+        }
+    }
+
+    /// Write a finite float in the repr of the policy, with a point so that
+    /// nix and JSON readers read it back as a float
+    pub fn literal(&self, f: f64) -> String {
+        let literal = match (self.repr, self.precision) {
+            (FloatRepr::Shortest, _) => format!("{:?}", f),
+            (FloatRepr::Decimal, None) => format!("{}", f),
+            (FloatRepr::Decimal, Some(precision)) => {
+                format!("{:.*}", precision, f)
+            }
+            (FloatRepr::Scientific, None) => format!("{:e}", f),
+            (FloatRepr::Scientific, Some(precision)) => {
+                format!("{:.*e}", precision, f)
+            }
+        };
+        if literal.contains('.') {
+            return literal;
+        }
+        match literal.split_once('e') {
+            Some((mantissa, exponent)) => {
+                format!("{}.0e{}", mantissa, exponent)
+            }
+            None => format!("{}.0", literal),
+        }
+    }
+}
+
+/// The float policies given to a call, those left to `None` following
+/// `set_float_policy`
+#[derive(Clone, Copy, Default)]
+pub struct FloatOptions {
+    pub non_finite: Option<NonFinite>,
+    pub repr: Option<FloatRepr>,
+    /// The precision of `repr`, which is only given along with it
+    pub precision: Option<usize>,
+}
+
+impl FloatOptions {
+    pub fn new(
+        non_finite: Option<&str>,
+        repr: Option<&str>,
+        precision: Option<usize>,
+    ) -> PyResult<Self> {
+        let non_finite = non_finite.map(NonFinite::of_name).transpose()?;
+        let repr = repr.map(FloatRepr::of_name).transpose()?;
+        if precision.is_some()
+            && matches!(repr, None | Some(FloatRepr::Shortest))
+        {
+            return Err(PyValueError::new_err(
+                "A precision needs the `decimal` or `scientific` repr",
+            ));
+        }
+        Ok(Self {
+            non_finite,
+            repr,
+            precision,
+        })
+    }
+
+    /// The policies of the call, completed by those `set_float_policy`
+    /// selected
+    pub fn resolve(self) -> FloatPolicy {
+        let current = FloatPolicy::current();
+        FloatPolicy {
+            non_finite: self.non_finite.or(current.non_finite),
+            repr: self.repr.unwrap_or(current.repr),
+            precision: match self.repr {
+                Some(_) => self.precision,
+                None => current.precision,
+            },
+        }
+    }
+}
+
+/// Choose what the converters do with infinite and NaN floats, and how the
+/// serializers write floats.
+///
+/// Nix evaluates `1.0e308 * 10` to an infinite float, and NaN floats come
+/// out of such arithmetic too, while neither nix nor JSON has a literal for
+/// them. With the default policy, they are converted to Python floats and
+/// given to nix as they are, but `nix.dumps`, `nix.eval_to_json` and
+/// `jsonc.dumps` raise a `ConversionError`, while `nix.eval_to_toml` and
+/// `nix.eval_to_yaml` write the literals of TOML and YAML. The non-finite
+/// policies are:
+///   - `error`: The conversions between nix and Python raise a
+///              `ConversionError` too, naming the float.
+///   - `none`: The float becomes `None`, or `null` in nix, JSON and YAML text.
+///   - `float`: The float is kept where the target can represent it, which
+///              nix and JSON text cannot, so their serializers still raise.
+///
+/// The repr only changes how finite floats are written, always with a point
+/// so they are read back as floats:
+///   - `shortest`: The fewest digits reading back to the same float, with an
+///                 exponent for very large or small ones, like `1e-7`.
+///   - `decimal`: Positional notation, never with an exponent, like
+///                `0.0000001`.
+///   - `scientific`: Always with an exponent, like `1.5e3`.
+///
+/// The policies apply to all the converters and serializers, on all
+/// threads, but the serializers and evaluators given their own
+/// `float_policy`, `float_repr` or `float_precision`. Each argument left to
+/// `None` restores its default.
+///
+/// Args:
+///   - non_finite (str | None): The non-finite float policy.
+///   - repr (str | None): The repr of floats, `shortest` by default.
+///   - precision (int | None): The number of digits written after the point,
+///                             of the mantissa in `scientific` repr, rounding
+///                             the float. Only `decimal` and `scientific`
+///                             reprs have a precision.
+///
+/// Raises:
+///   - ValueError: If the policy or the repr is unknown, or a precision is
+///                 given with the `shortest` repr.
+///
+/// Example:
+/// ```python
+/// >>> nix.evals("1.0e308 * 10")
+/// inf
+/// >>> set_float_policy(non_finite="none")
+/// >>> nix.evals("[ (1.0e308 * 10) 1.5 ]")
+/// [None, 1.5]
+/// >>> nix.dumps([float("nan"), 1.5e-7], indent=None)
+/// '[ null 1.5e-7 ]'
+/// >>> set_float_policy(repr="decimal", precision=3)
+/// >>> nix.dumps(2 / 3)
+/// '0.667'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (non_finite = None, repr = None, precision = None))]
+pub fn set_float_policy(
+    non_finite: Option<&str>,
+    repr: Option<&str>,
+    precision: Option<usize>,
+) -> PyResult<()> {
+    let options = FloatOptions::new(non_finite, repr, precision)?;
+    let non_finite = match options.non_finite {
+        None => UNSET,
+        Some(NonFinite::Error) => ERROR,
+        Some(NonFinite::Null) => NULL,
+        Some(NonFinite::Float) => FLOAT,
+    };
+    let repr = match options.repr {
+        None | Some(FloatRepr::Shortest) => SHORTEST,
+        Some(FloatRepr::Decimal) => DECIMAL,
+        Some(FloatRepr::Scientific) => SCIENTIFIC,
+    };
+    NON_FINITE.store(non_finite, Ordering::Relaxed);
+    REPR.store(repr, Ordering::Relaxed);
+    PRECISION.store(precision.unwrap_or(UNLIMITED), Ordering::Relaxed);
+    Ok(())
+}
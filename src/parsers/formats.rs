@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[cfg(any(feature = "jsonc", feature = "nix"))]
+use crate::parsers::utils::FileSource;
+
+/// A family of formats handled by one of the parser modules
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Jsonc,
+    Manifest,
+    Nix,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Jsonc, Format::Manifest, Format::Nix];
+
+    /// The name of the format, which is also the name of its Python module
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Jsonc => "jsonc",
+            Format::Manifest => "manifest",
+            Format::Nix => "nix",
+        }
+    }
+
+    /// Whether the parser of the format is compiled into this build
+    pub fn is_available(self) -> bool {
+        match self {
+            Format::Jsonc => cfg!(feature = "jsonc"),
+            Format::Manifest => cfg!(feature = "manifest"),
+            Format::Nix => cfg!(feature = "nix"),
+        }
+    }
+}
+
+/// Load the document at a path with the parser of its extension
+pub fn load(py: Python<'_>, path: &Path) -> PyResult<PyObject> {
+    let extension = path.extension().and_then(|e| e.to_str());
+    match extension {
+        #[cfg(feature = "jsonc")]
+        Some("json" | "jsonc") => {
+            let source = FileSource::Path(path.to_path_buf());
+            let options = crate::parsers::jsonc::LoadOptions::default();
+            crate::parsers::jsonc::load_with(py, source, &options)
+        }
+        #[cfg(feature = "nix")]
+        Some("nix") => {
+            let source = FileSource::Path(path.to_path_buf());
+            crate::parsers::nix::eval(
+                py, source, None, false, None, None, None, None, None, None,
+                None, true, false, false, "str", false, false, None,
+            )
+        }
+        _ => Err(PyValueError::new_err(format!(
+            "Cannot load {}, no parser of this build handles its extension",
+            path.display()
+        ))),
+    }
+}
+
+/// List the formats compiled into this build.
+///
+/// Each parser family is gated behind a Cargo feature of the same name, so
+/// slim builds may lack some of the `cosutils.rustlib.parsers` submodules.
+///
+/// Returns:
+///   - list[str]: The names of the available parser submodules.
+///
+/// Example:
+/// ```python
+/// >>> available_formats()
+/// ['jsonc', 'manifest', 'nix']
+/// ```
+#[pyfunction]
+pub fn available_formats() -> Vec<&'static str> {
+    Format::ALL
+        .into_iter()
+        .filter(|f| f.is_available())
+        .map(Format::name)
+        .collect()
+}
+
+/// A capability beyond parsing that builds may lack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Capability {
+    /// The `fetchers` of `nix.Evaluator`, providing `fetchurl`, `fetchGit`,
+    /// `fetchTarball` and `fetchTree` to nix code
+    Fetchers,
+    /// Reloading documents when their files change
+    Watch,
+    /// SIMD accelerated parsing
+    Simd,
+}
+
+impl Capability {
+    const ALL: [Capability; 3] =
+        [Capability::Fetchers, Capability::Watch, Capability::Simd];
+
+    fn name(self) -> &'static str {
+        match self {
+            Capability::Fetchers => "fetchers",
+            Capability::Watch => "watch",
+            Capability::Simd => "simd",
+        }
+    }
+
+    fn is_available(self) -> bool {
+        match self {
+            Capability::Fetchers => cfg!(feature = "nix"),
+            // Neither is implemented yet, they are listed so tools can
+            // already check for them
+            Capability::Watch | Capability::Simd => false,
+        }
+    }
+}
+
+/// The engines bundled by the parsers, with the format they are compiled
+/// with and their locked version, given by the build script
+const ENGINES: [(&str, Format, &str); 4] = [
+    (
+        "jsonc-parser",
+        Format::Jsonc,
+        env!("COSUTILS_JSONC_PARSER_VERSION"),
+    ),
+    ("rnix", Format::Nix, env!("COSUTILS_RNIX_VERSION")),
+    ("toml", Format::Manifest, env!("COSUTILS_TOML_VERSION")),
+    ("tvix-eval", Format::Nix, env!("COSUTILS_TVIX_EVAL_VERSION")),
+];
+
+/// Describe what this build of the parsers can do.
+///
+/// Tools depending on optional parts of the parsers can check for them
+/// here rather than catching the errors of missing submodules or
+/// functions.
+///
+/// Returns:
+///   - _Features: The `version` of cosutils, whether each of the `formats`
+///                and optional `capabilities` is compiled in, and the
+///                `engines` bundled by the compiled formats with their
+///                version. Engines built from git have the short commit in
+///                their version, like `0.1.0+0ef4144`.
+///
+/// Example:
+/// ```python
+/// >>> features()
+/// {'version': '0.1.0', 'formats': {'jsonc': True, 'manifest': True, 'nix': True}, 'capabilities': {'fetchers': True, 'watch': False, 'simd': False}, 'engines': {'jsonc-parser': '0.26.2', 'rnix': '0.11.0', 'toml': '0.8.23', 'tvix-eval': '0.1.0+0ef4144'}}
+/// >>> features()["capabilities"]["watch"]
+/// False
+/// ```
+#[pyfunction]
+pub fn features(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let formats = PyDict::new(py);
+    for format in Format::ALL {
+        formats.set_item(format.name(), format.is_available())?;
+    }
+    let capabilities = PyDict::new(py);
+    for capability in Capability::ALL {
+        capabilities.set_item(capability.name(), capability.is_available())?;
+    }
+    let engines = PyDict::new(py);
+    for (name, format, version) in ENGINES {
+        if format.is_available() {
+            engines.set_item(name, version)?;
+        }
+    }
+
+    let features = PyDict::new(py);
+    features.set_item("version", env!("CARGO_PKG_VERSION"))?;
+    features.set_item("formats", formats)?;
+    features.set_item("capabilities", capabilities)?;
+    features.set_item("engines", engines)?;
+    Ok(features)
+}
@@ -0,0 +1,108 @@
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::PyResult;
+use sha2::{Digest, Sha256};
+
+/// Append the canonical structural form of a parsed value to `out`.
+///
+/// Every value is tagged with its type and every variable-length part is
+/// prefixed with its length, so distinct values never share an encoding.
+/// Entries of dicts are sorted by their encoding, making the result
+/// independent of key order.
+pub fn encode(obj: &Bound<'_, PyAny>, out: &mut Vec<u8>) -> PyResult<()> {
+    let tagged = |out: &mut Vec<u8>, tag: u8, bytes: &[u8]| {
+        out.push(tag);
+        out.extend((bytes.len() as u64).to_le_bytes());
+        out.extend(bytes);
+    };
+
+    if obj.is_none() {
+        out.push(b'n');
+    } else if let Ok(b) = obj.downcast::<PyBool>() {
+        out.push(if b.is_true() { b't' } else { b'f' });
+    } else if let Ok(i) = obj.downcast::<PyInt>() {
+        // Python integers are unbounded, their decimal form is canonical
+        tagged(out, b'i', i.to_string().as_bytes());
+    } else if let Ok(f) = obj.downcast::<PyFloat>() {
+        let f = f.value();
+        let f = if f.is_nan() { f64::NAN } else { f };
+        tagged(out, b'd', &f.to_bits().to_le_bytes());
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        tagged(out, b's', s.to_str()?.as_bytes());
+    } else if obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>()
+    {
+        let mut items = Vec::new();
+        let mut count = 0u64;
+        for item in obj.try_iter()? {
+            encode(&item?, &mut items)?;
+            count += 1;
+        }
+        out.push(b'l');
+        out.extend(count.to_le_bytes());
+        out.extend(items);
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut entries = dict
+            .iter()
+            .map(|(k, v)| {
+                let mut entry = Vec::new();
+                encode(&k, &mut entry)?;
+                encode(&v, &mut entry)?;
+                Ok(entry)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        entries.sort_unstable();
+        out.push(b'm');
+        out.extend((entries.len() as u64).to_le_bytes());
+        entries.iter().for_each(|e| out.extend(e));
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "Cannot hash python type {}, only parsed values are supported",
+            obj.get_type().name()?
+        )));
+    }
+    Ok(())
+}
+
+/// Hash a parsed value by its structure.
+///
+/// The hash covers the canonical structural form of the value, so it does
+/// not depend on the order of keys or the formatting of the document the
+/// value was parsed from. Values of different types always hash differently,
+/// e.g. `1` and `1.0`.
+///
+/// Args:
+///   - value (Any): A value as returned by the parsers, made of `None`,
+///                  `bool`, `int`, `float`, `str`, `list`, `tuple` and
+///                  `dict`.
+///   - algo (str): The hash algorithm, either `"blake3"` or `"sha256"`.
+///
+/// Returns:
+///   - str: The hash as lowercase hexadecimal digits.
+///
+/// Raises:
+///   - TypeError: If the value contains objects of other types.
+///   - ValueError: If the algorithm is not supported.
+///
+/// Example:
+/// ```python
+/// >>> value_hash({"a": 1, "b": [True]}) == value_hash({"b": [True], "a": 1})
+/// True
+/// ```
+#[pyfunction]
+#[pyo3(signature = (value, algo = "blake3"))]
+pub fn value_hash(value: &Bound<'_, PyAny>, algo: &str) -> PyResult<String> {
+    let mut encoded = Vec::new();
+    encode(value, &mut encoded)?;
+    match algo {
+        "blake3" => Ok(blake3::hash(&encoded).to_hex().to_string()),
+        "sha256" => Ok(Sha256::digest(&encoded)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()),
+        _ => Err(PyValueError::new_err(format!(
+            "Unsupported hash algorithm `{}`, expected `blake3` or `sha256`",
+            algo
+        ))),
+    }
+}
@@ -0,0 +1,120 @@
+use std::cell::Cell;
+use std::sync::Mutex;
+
+use pyo3::exceptions::{PyBaseException, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::{PyObject, PyResult};
+
+use crate::parsers::utils::{ConversionError, EvaluationError, ParseError};
+
+/// The callable translating the errors of the parsers, set from Python
+static HOOK: Mutex<Option<PyObject>> = Mutex::new(None);
+
+thread_local! {
+    /// How many `unhooked` calls are running on this thread
+    static SUSPENDED: Cell<usize> = const { Cell::new(0) };
+}
+
+struct Suspended;
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        SUSPENDED.with(|s| s.set(s.get() - 1));
+    }
+}
+
+/// Run `f` without translating the errors it creates, for the errors that
+/// are inspected or combined before being raised, which must be given to
+/// `hooked` once they are final
+pub fn unhooked<T>(f: impl FnOnce() -> T) -> T {
+    SUSPENDED.with(|s| s.set(s.get() + 1));
+    let _suspended = Suspended;
+    f()
+}
+
+/// Translate an error of the parsers with the hook set by `set_error_hook`,
+/// other errors are kept as they are
+pub fn hooked(err: PyErr) -> PyErr {
+    if SUSPENDED.with(Cell::get) > 0 {
+        return err;
+    }
+    Python::with_gil(|py| {
+        let Some(hook) = HOOK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|h| h.clone_ref(py))
+        else {
+            return err;
+        };
+        let value = err.value(py);
+        let is_parser_error = value.is_instance_of::<ParseError>()
+            || value.is_instance_of::<EvaluationError>()
+            || value.is_instance_of::<ConversionError>();
+        if !is_parser_error {
+            return err;
+        }
+
+        // The errors of the parsers called by the hook are its own business
+        match unhooked(|| hook.call1(py, (value,))) {
+            Ok(replaced) if replaced.is_none(py) => err,
+            Ok(replaced) => {
+                let replaced = replaced.into_bound(py);
+                if replaced.is_instance_of::<PyBaseException>() {
+                    PyErr::from_value(replaced)
+                } else {
+                    PyTypeError::new_err(format!(
+                        "The error hook must return an exception or None, \
+                        not {}",
+                        replaced.get_type()
+                    ))
+                }
+            }
+            Err(raised) => raised,
+        }
+    })
+}
+
+/// Set a hook translating the errors raised by the parsers.
+///
+/// The hook is called with every `ParseError`, `EvaluationError` and
+/// `ConversionError` about to be raised, once its message is rendered and
+/// its `code` and `errors` are set, so applications can raise their own
+/// exception types instead. An error gathering several others, see
+/// `errors`, is given to the hook as a whole. The hook applies to every
+/// later error of all the parsers, on all threads, but not to the errors
+/// raised within the hook itself.
+///
+/// Errors can be recorded by the hook, but not suppressed, as the functions
+/// raising them have no value to return instead.
+///
+/// Args:
+///   - hook (Callable | None): The hook, returning the exception to raise
+///                             instead of the error, or `None` to raise the
+///                             error as is. Exceptions raised by the hook are
+///                             raised instead of the error too. `None`
+///                             removes the hook.
+///
+/// Raises:
+///   - TypeError: If the hook is not callable.
+///
+/// Example:
+/// ```python
+/// >>> class ConfigError(Exception): ...
+/// >>> set_error_hook(lambda e: ConfigError(e.code))
+/// >>> nix.evals("1 +")
+/// Traceback (most recent call last):
+/// ...
+/// ConfigError: NIX_E001
+/// ```
+#[pyfunction]
+pub fn set_error_hook(hook: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+    if let Some(hook) = hook.as_ref().filter(|h| !h.is_callable()) {
+        return Err(PyTypeError::new_err(format!(
+            "The error hook must be callable, not {}",
+            hook.get_type()
+        )));
+    }
+    *HOOK.lock().unwrap_or_else(|e| e.into_inner()) = hook.map(Bound::unbind);
+    Ok(())
+}
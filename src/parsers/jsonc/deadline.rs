@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+use jsonc_parser::tokens::Token;
+use jsonc_parser::Scanner;
+
+/// How many tokens are scanned between two looks at the clock
+const CHECKED_EVERY: usize = 256;
+
+/// A prefix of a document, closed so that it parses on its own
+pub(super) struct Cut {
+    /// The prefix, followed by the brackets closing the containers it leaves
+    /// open
+    pub text: String,
+    /// The offset of the first value left out
+    pub offset: usize,
+}
+
+/// Scan `scanned` until `deadline`, and cut it after the last complete value
+/// scanned if the deadline passes first. `None` when the whole document was
+/// scanned in time, or when it does not scan, for the parser to report why.
+pub(super) fn cut(scanned: &str, deadline: Instant) -> Option<Cut> {
+    cut_when(scanned, |tokens| {
+        tokens % CHECKED_EVERY == 0 && Instant::now() >= deadline
+    })
+}
+
+/// `cut`, stopping once `expired` holds for the number of values, keys and
+/// brackets scanned so far
+fn cut_when(
+    scanned: &str,
+    mut expired: impl FnMut(usize) -> bool,
+) -> Option<Cut> {
+    let mut scanner = Scanner::new(scanned);
+    // The brackets closing the containers open after the last token
+    let mut open = Vec::new();
+    // The end of the last token the document can be closed after, with how
+    // many containers are open there
+    let mut safe = (0, 0);
+    // Whether the next scalar is the value of a property, rather than its key
+    let mut after_colon = false;
+    // The start of the key of the property whose value is scanned next
+    let mut key = None;
+    let mut tokens = 0;
+    let mut stopped = false;
+    loop {
+        let Ok(Some(token)) = scanner.scan() else {
+            return None;
+        };
+        match token {
+            Token::Comma | Token::CommentLine(_) | Token::CommentBlock(_) => {
+                continue
+            }
+            Token::Colon => {
+                after_colon = true;
+                continue;
+            }
+            Token::CloseBrace | Token::CloseBracket => {
+                open.pop();
+                safe = (scanner.token_end(), open.len());
+                key = None;
+            }
+            _ if stopped => {
+                let (end, depth) = safe;
+                let mut text = String::with_capacity(end + depth);
+                text.push_str(&scanned[..end]);
+                text.extend(open[..depth].iter().rev());
+                return Some(Cut {
+                    text,
+                    offset: key.unwrap_or(scanner.token_start()),
+                });
+            }
+            Token::OpenBrace | Token::OpenBracket => {
+                open.push(if token == Token::OpenBrace { '}' } else { ']' });
+                safe = (scanner.token_end(), open.len());
+                key = None;
+            }
+            _ if after_colon || open.last() != Some(&'}') => {
+                safe = (scanner.token_end(), open.len());
+                key = None;
+            }
+            _ => key = Some(scanner.token_start()),
+        }
+        after_colon = false;
+        tokens += 1;
+        stopped = stopped || expired(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonc_parser::parse_to_ast;
+
+    use super::*;
+
+    const DOCUMENT: &str = r#"{"a": [1, 2, {"b": null}], // c
+        "c": {}, d: 'e',}"#;
+
+    fn cut_after(tokens: usize) -> Option<(String, usize)> {
+        cut_when(DOCUMENT, |scanned| scanned == tokens)
+            .map(|cut| (cut.text, cut.offset))
+    }
+
+    #[test]
+    fn cuts_after_complete_values() {
+        let offset = |of: &str| DOCUMENT.find(of).unwrap();
+        assert_eq!(cut_after(1), Some(("{}".to_string(), offset("\"a\""))));
+        assert_eq!(cut_after(2), Some(("{}".to_string(), offset("\"a\""))));
+        assert_eq!(
+            cut_after(4),
+            Some((r#"{"a": [1]}"#.to_string(), offset("2")))
+        );
+        assert_eq!(
+            cut_after(6),
+            Some((r#"{"a": [1, 2, {}]}"#.to_string(), offset("\"b\"")))
+        );
+        assert_eq!(
+            cut_after(9),
+            Some((
+                r#"{"a": [1, 2, {"b": null}]}"#.to_string(),
+                offset("\"c\"")
+            ))
+        );
+        assert_eq!(
+            cut_after(14),
+            Some((
+                format!("{}}}", &DOCUMENT[..offset("{},") + 2]),
+                offset("d:")
+            ))
+        );
+        assert_eq!(cut_after(15), None);
+        assert!(cut_when(r#"["a"#, |_| true).is_none());
+    }
+
+    #[test]
+    fn cuts_parse() {
+        for tokens in 1..16 {
+            if let Some((text, offset)) = cut_after(tokens) {
+                let parsed = parse_to_ast(
+                    &text,
+                    &Default::default(),
+                    &Default::default(),
+                );
+                assert!(parsed.is_ok_and(|parsed| parsed.value.is_some()));
+                assert!(offset >= text.trim_end_matches(['}', ']']).len());
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use jsonc_parser::cst::{
+    CstArray, CstInputValue, CstNode, CstObject, CstRootNode,
+};
+use jsonc_parser::ParseOptions;
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::{PyObject, PyResult};
+
+use super::serialize::float;
+use super::{parse, syntax_error, Dialect, Duplicates};
+use crate::parsers::floats::FloatPolicy;
+use crate::parsers::overflow;
+use crate::parsers::utils::{coded_err, ConversionError, ErrorCode};
+
+/// A step of the path to a value of a document
+#[derive(FromPyObject)]
+enum Segment {
+    Index(usize),
+    Key(String),
+}
+
+/// The path to a value of a document, a single key of the root object or
+/// the keys and indices leading to it
+#[derive(FromPyObject)]
+enum DocumentPath {
+    Key(String),
+    Segments(Vec<Segment>),
+}
+
+impl DocumentPath {
+    fn into_segments(self) -> Vec<Segment> {
+        match self {
+            DocumentPath::Key(key) => vec![Segment::Key(key)],
+            DocumentPath::Segments(segments) => segments,
+        }
+    }
+}
+
+/// An object or an array of a document, holding the value a path leads to
+enum Container {
+    Object(CstObject),
+    Array(CstArray),
+}
+
+impl Container {
+    /// The node as the container `segment` selects from, if it is one
+    fn of(node: CstNode, segment: &Segment) -> Option<Self> {
+        match segment {
+            Segment::Key(_) => node.as_object().map(Container::Object),
+            Segment::Index(_) => node.as_array().map(Container::Array),
+        }
+    }
+}
+
+/// Where the value a path leads to is, whether it exists or not
+enum Slot<'p> {
+    Property(CstObject, &'p str),
+    Element(CstArray, usize),
+}
+
+/// Convert a Python object to the value inserted into a document
+fn input_value(obj: &Bound<'_, PyAny>) -> PyResult<CstInputValue> {
+    let value = if obj.is_none() {
+        CstInputValue::Null
+    } else if let Ok(b) = obj.downcast::<PyBool>() {
+        CstInputValue::Bool(b.is_true())
+    } else if let Ok(i) = obj.downcast::<PyInt>() {
+        CstInputValue::Number(i.str()?.to_string())
+    } else if let Ok(f) = obj.downcast::<PyFloat>() {
+        match float(f.value(), &FloatPolicy::current())?.as_str() {
+            "null" => CstInputValue::Null,
+            literal => CstInputValue::Number(literal.to_string()),
+        }
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        CstInputValue::String(s.to_str()?.to_string())
+    } else if obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>()
+    {
+        CstInputValue::Array(
+            obj.try_iter()?
+                .map(|item| input_value(&item?))
+                .collect::<PyResult<_>>()?,
+        )
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        CstInputValue::Object(
+            dict.iter()
+                .map(|(k, v)| {
+                    let key = k.downcast::<PyString>().map_err(|_| {
+                        coded_err::<ConversionError>(
+                            ErrorCode::JsoncConversion,
+                            format!(
+                                "Object keys must be strings, got {}",
+                                k.get_type()
+                            ),
+                        )
+                    })?;
+                    Ok((key.to_str()?.to_string(), input_value(&v)?))
+                })
+                .collect::<PyResult<_>>()?,
+        )
+    } else {
+        return Err(coded_err::<ConversionError>(
+            ErrorCode::JsoncConversion,
+            format!(
+                "Cannot convert python type {} to JSON",
+                obj.get_type().name()?
+            ),
+        ));
+    };
+    Ok(value)
+}
+
+/// A JSONC document edited in place, keeping its comments, the order of its
+/// keys and its formatting.
+///
+/// Values are set and removed by their path, either a single key of the
+/// root object, like `"editor.fontSize"` in the flat `settings.json` of VS
+/// Code, or a list of the keys and indices leading to them, like
+/// `["launch", "configurations", 0]`. Setting an existing value replaces it
+/// where it is, with the comments around it, while new keys are appended to
+/// their object, indented like its other properties. Setting a value under
+/// keys that do not exist creates the objects leading to it. The document is
+/// written back with `str`.
+///
+/// Like `NixAst`, a document can only be used on the thread that parsed it.
+///
+/// Args:
+///   - content (str): The JSONC content as a string.
+///
+/// Raises:
+///   - ParseError: If the content is not valid JSONC.
+///
+/// Example:
+/// ```python
+/// >>> doc = JsoncDocument('{\n  // Big enough\n  "editor.fontSize": 12\n}\n')
+/// >>> doc.set("editor.fontSize", 14)
+/// >>> doc.set("files.autoSave", "onFocusChange")
+/// >>> print(doc, end="")
+/// {
+///   // Big enough
+///   "editor.fontSize": 14,
+///   "files.autoSave": "onFocusChange"
+/// }
+/// >>> doc.remove("files.autoSave")
+/// >>> doc.get("editor.fontSize")
+/// 14
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.jsonc", unsendable)]
+pub struct JsoncDocument {
+    root: CstRootNode,
+}
+
+impl JsoncDocument {
+    /// Where the value at `path` is, creating the objects missing on the way
+    /// if `create`
+    fn slot<'p>(
+        &self,
+        path: &'p [Segment],
+        create: bool,
+        described: &str,
+    ) -> PyResult<Slot<'p>> {
+        let missing = || {
+            if create {
+                PyTypeError::new_err(format!(
+                    "Cannot set {}, a value on its path is not an object or \
+                    an array",
+                    described
+                ))
+            } else {
+                PyKeyError::new_err(described.to_string())
+            }
+        };
+        let Some((last, ancestors)) = path.split_last() else {
+            return Err(PyValueError::new_err(
+                "A path has at least one key or index",
+            ));
+        };
+        let first = ancestors.first().unwrap_or(last);
+        let mut container = match first {
+            Segment::Key(_) if create => {
+                self.root.object_value_or_create().map(Container::Object)
+            }
+            _ => self
+                .root
+                .value()
+                .and_then(|node| Container::of(node, first)),
+        }
+        .ok_or_else(missing)?;
+        for (i, segment) in ancestors.iter().enumerate() {
+            let next = ancestors.get(i + 1).unwrap_or(last);
+            container = match (&container, segment) {
+                (Container::Object(object), Segment::Key(key))
+                    if create && matches!(next, Segment::Key(_)) =>
+                {
+                    object.object_value_or_create(key).map(Container::Object)
+                }
+                (Container::Object(object), Segment::Key(key)) => object
+                    .get(key)
+                    .and_then(|prop| prop.value())
+                    .and_then(|node| Container::of(node, next)),
+                (Container::Array(array), Segment::Index(index)) => array
+                    .elements()
+                    .into_iter()
+                    .nth(*index)
+                    .and_then(|node| Container::of(node, next)),
+                _ => None,
+            }
+            .ok_or_else(missing)?;
+        }
+        // The container is of the kind the last segment needs
+        match (container, last) {
+            (Container::Object(object), Segment::Key(key)) => {
+                Ok(Slot::Property(object, key))
+            }
+            (Container::Array(array), Segment::Index(index)) => {
+                Ok(Slot::Element(array, *index))
+            }
+            _ => Err(missing()),
+        }
+    }
+}
+
+#[pymethods]
+impl JsoncDocument {
+    #[new]
+    fn new(content: &str) -> PyResult<Self> {
+        let root = CstRootNode::parse(content, &Default::default())
+            .map_err(|error| syntax_error(content, &error))?;
+        Ok(Self { root })
+    }
+
+    /// Get the value at a path of the document.
+    ///
+    /// Args:
+    ///   - path (str | list[str | int]): The key of the root object, or the
+    ///                                   keys and indices leading to the
+    ///                                   value.
+    ///
+    /// Returns:
+    ///   - _JsonValue: The value, converted like `loads` does.
+    ///
+    /// Raises:
+    ///   - KeyError: If the document has no value at the path.
+    ///   - ValueError: If the path is empty.
+    fn get(
+        &self,
+        py: Python<'_>,
+        path: &Bound<'_, PyAny>,
+    ) -> PyResult<PyObject> {
+        let described = path.repr()?.to_string();
+        let segments = path.extract::<DocumentPath>()?.into_segments();
+        let node = match self.slot(&segments, false, &described)? {
+            Slot::Property(object, key) => {
+                object.get(key).and_then(|prop| prop.value())
+            }
+            Slot::Element(array, index) => {
+                array.elements().into_iter().nth(index)
+            }
+        };
+        let Some(node) = node else {
+            return Err(PyKeyError::new_err(described));
+        };
+        parse(
+            py,
+            &node.to_string(),
+            None,
+            false,
+            Dialect::Jsonc,
+            &ParseOptions::default(),
+            Duplicates::Last,
+            overflow::policy(),
+            None,
+        )
+    }
+
+    /// Set the value at a path of the document, replacing the value there
+    /// or adding it.
+    ///
+    /// Args:
+    ///   - path (str | list[str | int]): The key of the root object, or the
+    ///                                   keys and indices leading to the
+    ///                                   value. An index may be the length
+    ///                                   of its array, to append to it.
+    ///   - value (Any): The value, made of the types `dumps` serializes.
+    ///
+    /// Raises:
+    ///   - TypeError: If a value on the path is not an object or an array,
+    ///                as the path needs.
+    ///   - IndexError: If an index is past the end of its array.
+    ///   - ValueError: If the path is empty.
+    ///   - ConversionError: If the value cannot be serialized to JSON.
+    fn set(
+        &self,
+        path: &Bound<'_, PyAny>,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let described = path.repr()?.to_string();
+        let segments = path.extract::<DocumentPath>()?.into_segments();
+        let value = input_value(value)?;
+        match self.slot(&segments, true, &described)? {
+            Slot::Property(object, key) => match object.get(key) {
+                Some(prop) => prop.set_value(value),
+                None => {
+                    object.append(key, value);
+                }
+            },
+            Slot::Element(array, index) => {
+                let elements = array.elements();
+                if index > elements.len() {
+                    return Err(PyIndexError::new_err(format!(
+                        "Cannot set {}, past the end of its array of {} \
+                        elements",
+                        described,
+                        elements.len()
+                    )));
+                }
+                // Insert the new element before removing the old one, so
+                // that the array keeps its layout
+                array.insert(index, value);
+                if let Some(old) = elements.into_iter().nth(index) {
+                    old.remove();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the value at a path of the document, with its key if it is a
+    /// property.
+    ///
+    /// Args:
+    ///   - path (str | list[str | int]): The key of the root object, or the
+    ///                                   keys and indices leading to the
+    ///                                   value.
+    ///
+    /// Raises:
+    ///   - KeyError: If the document has no value at the path.
+    ///   - ValueError: If the path is empty.
+    fn remove(&self, path: &Bound<'_, PyAny>) -> PyResult<()> {
+        let described = path.repr()?.to_string();
+        let segments = path.extract::<DocumentPath>()?.into_segments();
+        match self.slot(&segments, false, &described)? {
+            Slot::Property(object, key) => {
+                if let Some(prop) = object.get(key) {
+                    prop.remove();
+                    return Ok(());
+                }
+            }
+            Slot::Element(array, index) => {
+                if let Some(node) = array.elements().into_iter().nth(index) {
+                    node.remove();
+                    return Ok(());
+                }
+            }
+        }
+        Err(PyKeyError::new_err(described))
+    }
+
+    /// The value of the whole document, converted like `loads` does
+    #[getter]
+    fn value(&self, py: Python<'_>) -> PyResult<PyObject> {
+        parse(
+            py,
+            &self.root.to_string(),
+            None,
+            false,
+            Dialect::Jsonc,
+            &ParseOptions::default(),
+            Duplicates::Last,
+            overflow::policy(),
+            None,
+        )
+    }
+
+    fn __str__(&self) -> String {
+        self.root.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("JsoncDocument({:?})", self.root.to_string())
+    }
+}
+
+/// Parse a JSONC document to edit it in place, keeping its comments and its
+/// formatting, see `JsoncDocument`.
+///
+/// Args:
+///   - content (str): The JSONC content as a string.
+///
+/// Returns:
+///   - JsoncDocument: The document.
+///
+/// Raises:
+///   - ParseError: If the content is not valid JSONC.
+///
+/// Example:
+/// ```python
+/// >>> doc = parse_document('{"a": 1 /* one */}')
+/// >>> doc.set("a", 2)
+/// >>> str(doc)
+/// '{"a": 2 /* one */}'
+/// ```
+#[pyfunction]
+pub fn parse_document(content: &str) -> PyResult<JsoncDocument> {
+    JsoncDocument::new(content)
+}
@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+
+use super::Dialect;
+
+/// The characters JSON strings may escape, which jsonc-parser scans
+const JSON_ESCAPES: &[u8] = b"\"\\/bfnrtu";
+
+/// Whether the byte before `i` continues a word, like the `a` of `a0x1`
+fn in_word(bytes: &[u8], i: usize) -> bool {
+    i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_')
+}
+
+/// Replace the syntax jsonc-parser does not scan by syntax of the same length
+/// it does, so offsets into the result are offsets into `content`:
+///
+/// - the `\uXXXX` escapes of UTF-16 surrogates, which jsonc-parser rejects
+///   even when they pair up, become `\/` followed by slashes,
+/// - in JSON5, the escapes JSON lacks, like `\x41`, line continuations, and
+///   quotes escaped in strings quoted by the other kind, become `\/`
+///   followed by slashes too,
+/// - in JSON5, the leading `+` of numbers becomes a space,
+/// - in JSON5, hexadecimal numbers become decimal ones of the same length.
+///
+/// Strings and numbers are decoded from `content` itself, by `string_value`
+/// and `decimal`.
+pub(super) fn scannable(content: &str, dialect: Dialect) -> Cow<'_, str> {
+    let json5 = dialect == Dialect::Json5;
+    let bytes = content.as_bytes();
+    let mut replaced: Option<Vec<u8>> = None;
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (None, b'/') if bytes.get(i + 1) == Some(&b'/') => {
+                i = content[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            (None, b'/') if bytes.get(i + 1) == Some(&b'*') => {
+                let closing = content[i + 2..].find("*/");
+                i = closing.map_or(bytes.len(), |n| i + n + 4);
+                continue;
+            }
+            (None, q @ (b'"' | b'\'')) => quote = Some(q),
+            (None, b'+')
+                if json5
+                    && !in_word(bytes, i)
+                    && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) =>
+            {
+                replaced.get_or_insert_with(|| bytes.to_vec())[i] = b' ';
+            }
+            (None, b'0')
+                if json5
+                    && !in_word(bytes, i)
+                    && matches!(bytes.get(i + 1), Some(b'x' | b'X'))
+                    && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit) =>
+            {
+                let digits = bytes[i + 2..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .count();
+                let end = i + 2 + digits;
+                let replaced = replaced.get_or_insert_with(|| bytes.to_vec());
+                replaced[i] = b'1';
+                replaced[i + 1..end].fill(b'0');
+                i = end;
+                continue;
+            }
+            (Some(q), b) if b == q => quote = None,
+            (Some(q), b'\\') => {
+                let Some(escaped) = content[i + 1..].chars().next() else {
+                    break;
+                };
+                let mut len = 1 + escaped.len_utf8();
+                if escaped == '\r' && bytes.get(i + 2) == Some(&b'\n') {
+                    len += 1;
+                }
+                let surrogate = escaped == 'u'
+                    && content
+                        .get(i + 2..i + 6)
+                        .filter(|hex| {
+                            hex.bytes().all(|b| b.is_ascii_hexdigit())
+                        })
+                        .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                        .is_some_and(|unit| (0xD800..=0xDFFF).contains(&unit));
+                if surrogate {
+                    len = 6;
+                }
+                // jsonc-parser rejects `\"` in single quoted strings
+                let unscannable = !escaped.is_ascii()
+                    || !JSON_ESCAPES.contains(&(escaped as u8))
+                    || (q == b'\'' && escaped == '"');
+                if surrogate || (json5 && unscannable) {
+                    let replaced =
+                        replaced.get_or_insert_with(|| bytes.to_vec());
+                    replaced[i + 1..i + len].fill(b'/');
+                }
+                i += len;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    match replaced {
+        // Only whole characters were replaced by ASCII bytes, the content
+        // stays valid UTF-8
+        Some(replaced) => {
+            Cow::Owned(String::from_utf8_lossy(&replaced).into_owned())
+        }
+        None => Cow::Borrowed(content),
+    }
+}
+
+/// The hexadecimal value of the `digits` next characters, if they all are
+/// hexadecimal digits
+fn hex(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    digits: usize,
+) -> Option<u32> {
+    let mut value = 0;
+    for _ in 0..digits {
+        value = value * 16
+            + chars.next_if(char::is_ascii_hexdigit)?.to_digit(16)?;
+    }
+    Some(value)
+}
+
+/// Decode a JSON5 string literal, quotes included, with the escapes JSON5
+/// adds to JSON: `\'`, `\v`, `\0`, `\xHH`, line continuations, and any
+/// other character escaping itself. Unpaired surrogates become U+FFFD.
+pub(super) fn string_value(raw: &str) -> String {
+    let inner = &raw[1..raw.len() - 1];
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let Some(escaped) = chars.next() else {
+            break;
+        };
+        let decoded = match escaped {
+            'b' => '\u{08}',
+            'f' => '\u{0c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\u{0b}',
+            '0' => '\0',
+            'x' => hex(&mut chars, 2).and_then(char::from_u32).unwrap_or('x'),
+            'u' => {
+                let Some(unit) = hex(&mut chars, 4) else {
+                    value.push('u');
+                    continue;
+                };
+                let mut units = vec![unit as u16];
+                // A high surrogate pairs with the low one escaped after it
+                if (0xD800..=0xDBFF).contains(&unit) {
+                    let mut ahead = chars.clone();
+                    if ahead.next() == Some('\\') && ahead.next() == Some('u') {
+                        if let Some(low @ 0xDC00..=0xDFFF) = hex(&mut ahead, 4)
+                        {
+                            units.push(low as u16);
+                            chars = ahead;
+                        }
+                    }
+                }
+                value.extend(
+                    char::decode_utf16(units)
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+                );
+                continue;
+            }
+            // A line continuation leaves the line break out of the string
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                continue;
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => continue,
+            c => c,
+        };
+        value.push(decoded);
+    }
+    value
+}
+
+/// The decimal text of a JSON5 number as written in the content, which may
+/// start with `+` or be hexadecimal, or `None` if it is a hexadecimal number
+/// too large for 128 bits
+pub(super) fn decimal(number: &str) -> Option<Cow<'_, str>> {
+    let number = number.strip_prefix('+').unwrap_or(number);
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number),
+    };
+    let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    else {
+        return Some(Cow::Borrowed(number));
+    };
+    let value = u128::from_str_radix(hex, 16).ok()?;
+    Some(Cow::Owned(format!("{}{}", sign, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+
+    use super::*;
+    use crate::parsers::jsonc::LoadOptions;
+
+    fn loads(content: &str) -> String {
+        let options = LoadOptions {
+            dialect: "json5",
+            ..Default::default()
+        };
+        Python::with_gil(|py| {
+            let value = options.parse(py, content, None).unwrap();
+            value.bind(py).repr().unwrap().to_string()
+        })
+    }
+
+    #[test]
+    fn scanning_keeps_offsets() {
+        let content = r#"{a: +1, b: 0x1F, 'c': 'it\'s \"q\"\x41'} // +0x1"#;
+        let scanned = scannable(content, Dialect::Json5);
+        assert_eq!(
+            scanned,
+            r#"{a:  1, b: 1000, 'c': 'it\/s \/q\/\/41'} // +0x1"#
+        );
+        // JSONC only gets its surrogate escapes replaced
+        let content = r#"{"a": "\ud83d\ude00", b: +1}"#;
+        let scanned = scannable(content, Dialect::Jsonc);
+        assert_eq!(scanned, r#"{"a": "\/////\/////", b: +1}"#);
+        assert!(matches!(
+            scannable(r#"{"a": "é"}"#, Dialect::Jsonc),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(string_value(r#"'it\'s \"q\"'"#), r#"it's "q""#);
+        assert_eq!(string_value(r#""\x41\v\0""#), "A\u{0b}\0");
+        assert_eq!(string_value("'a\\\r\nb\\\nc'"), "abc");
+        assert_eq!(
+            string_value(r#""\ud83d\ude00\ud83d""#),
+            "\u{1f600}\u{fffd}"
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(decimal("+12").as_deref(), Some("12"));
+        assert_eq!(decimal("0x1F").as_deref(), Some("31"));
+        assert_eq!(decimal("-0Xff").as_deref(), Some("-255"));
+        assert_eq!(decimal("1.5e3").as_deref(), Some("1.5e3"));
+        assert_eq!(decimal(&format!("0x1{}", "0".repeat(32))), None);
+    }
+
+    #[test]
+    fn loads_json5() {
+        assert_eq!(
+            loads("{a: +1, b: 0x1F, c: -0xff, d: 'it\\'s \"q\"', e: [1, 2,],}"),
+            r#"{'a': 1, 'b': 31, 'c': -255, 'd': 'it\'s "q"', 'e': [1, 2]}"#
+        );
+        assert_eq!(loads("'multi\\\nline'"), "'multiline'");
+    }
+}
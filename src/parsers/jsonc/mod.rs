@@ -0,0 +1,1045 @@
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use annotate_snippets::{Level, Snippet};
+use jsonc_parser::ast::{ObjectPropName, StringLit, Value as AstValue};
+use jsonc_parser::common::{Range as JsoncRange, Ranged};
+use jsonc_parser::errors::ParseError as JsoncParseError;
+use jsonc_parser::{parse_to_ast, ParseOptions};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyDict, PyFloat, PyInt, PyList, PyNone, PyString, PyTuple,
+};
+use pyo3::{PyObject, PyResult};
+
+use crate::into_pyany;
+use crate::parsers::managed;
+use crate::parsers::overflow::{self, IntOverflow};
+use crate::parsers::profile;
+use crate::parsers::render::renderer;
+use crate::parsers::utils::IntoRange;
+use crate::parsers::utils::{
+    coded_err, dict_key, display_source, normalized_newlines, read_file,
+    ErrorCode, FileSource, ParseError, TryToPyObject,
+};
+
+mod deadline;
+mod document;
+mod json5;
+mod reformat;
+mod serialize;
+
+pub use document::{parse_document, JsoncDocument};
+pub use reformat::format;
+pub use serialize::{dump, dumps};
+
+impl IntoRange<usize> for JsoncRange {
+    fn into_range(self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// The syntax a document is parsed with
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    /// JSON with comments and trailing commas, like VS Code settings
+    Jsonc,
+    /// JSONC with the unquoted keys, single quoted and multiline strings,
+    /// hexadecimal numbers and leading `+` of JSON5
+    Json5,
+}
+
+impl Dialect {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "jsonc" => Ok(Dialect::Jsonc),
+            "json5" => Ok(Dialect::Json5),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown dialect `{}`, expected `jsonc` or `json5`",
+                name
+            ))),
+        }
+    }
+}
+
+/// The options jsonc-parser scans documents with, with the syntax it allows
+/// overridden where the caller asks so. jsonc-parser reads comments, trailing
+/// commas, unquoted keys and single quoted strings by default, and
+/// `json5::scannable` rewrites the rest of JSON5 for it.
+fn parse_options(
+    comments: Option<bool>,
+    trailing_commas: Option<bool>,
+    loose_property_names: Option<bool>,
+) -> ParseOptions {
+    let options = ParseOptions::default();
+    ParseOptions {
+        allow_comments: comments.unwrap_or(options.allow_comments),
+        allow_trailing_commas: trailing_commas
+            .unwrap_or(options.allow_trailing_commas),
+        allow_loose_object_property_names: loose_property_names
+            .unwrap_or(options.allow_loose_object_property_names),
+    }
+}
+
+/// What the conversion does with a key an object defines more than once
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Duplicates {
+    Error,
+    First,
+    Last,
+    Collect,
+}
+
+impl Duplicates {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "error" => Ok(Duplicates::Error),
+            "first" => Ok(Duplicates::First),
+            "last" => Ok(Duplicates::Last),
+            "collect" => Ok(Duplicates::Collect),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown duplicate key policy `{}`, expected `error`, \
+                `first`, `last` or `collect`",
+                name
+            ))),
+        }
+    }
+
+    /// The policy named `name`, or the one strict conversions default to
+    fn resolve(name: Option<&str>, strict: bool) -> PyResult<Self> {
+        match name {
+            Some(name) => Duplicates::of_name(name),
+            None if strict => Ok(Duplicates::Error),
+            None => Ok(Duplicates::Last),
+        }
+    }
+}
+
+/// Converts a parsed JSONC document into Python objects
+struct Converter<'a> {
+    content: &'a str,
+    /// The content as rendered in error snippets, only built for errors as it
+    /// takes a pass over the whole content
+    display: OnceCell<Cow<'a, str>>,
+    path: Option<String>,
+    strict: bool,
+    dialect: Dialect,
+    /// Whether escapes of the content were rewritten for jsonc-parser, which
+    /// then decoded them wrong
+    rewritten: bool,
+    duplicates: Duplicates,
+    /// What is done with integers that do not fit into 64 bits, `None` for
+    /// the default of reading them as floats
+    int_overflow: Option<IntOverflow>,
+    /// When the conversion stops, keeping what it converted so far
+    deadline: Option<Instant>,
+    /// The offset of the first value left out once the deadline passed
+    truncated: Cell<Option<usize>>,
+    /// The offset of the first value the parsing left out, once the share of
+    /// the deadline given to scanning passed
+    cut: Option<usize>,
+}
+
+impl Converter<'_> {
+    /// Whether the conversion stops before the value at `offset`, once the
+    /// deadline passed
+    fn expired(&self, offset: usize) -> bool {
+        if self.truncated.get().is_some() {
+            return true;
+        }
+        let expired = self.deadline.is_some_and(|d| Instant::now() >= d);
+        if expired {
+            self.truncated.set(Some(offset));
+        }
+        expired
+    }
+
+    /// Describe where the conversion stopped, if it did
+    fn truncation(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let Some(offset) = self.truncated.get().or(self.cut) else {
+            return Ok(py.None());
+        };
+        let line = self.content[..offset].matches('\n').count() + 1;
+        let truncation = PyDict::new(py);
+        truncation.set_item(
+            "message",
+            format!(
+                "the deadline passed, the document is truncated from line {}",
+                line
+            ),
+        )?;
+        truncation.set_item("start", offset)?;
+        truncation.set_item("line", line)?;
+        Ok(truncation.into_any().unbind())
+    }
+
+    fn snippet(&self) -> Snippet<'_> {
+        let display = self.display.get_or_init(|| display_source(self.content));
+        if let Some(path) = &self.path {
+            Snippet::source(display).fold(true).origin(path)
+        } else {
+            Snippet::source(display).fold(true)
+        }
+    }
+
+    /// Render an error pointing at `range`, with optional secondary notes
+    fn error(
+        &self,
+        code: ErrorCode,
+        title: &str,
+        range: JsoncRange,
+        notes: &[(JsoncRange, &str)],
+    ) -> PyErr {
+        let snippet = self
+            .snippet()
+            .annotation(Level::Error.span(range.into_range()))
+            .annotations(notes.iter().map(|(range, label)| {
+                Level::Info.span(range.into_range()).label(label)
+            }));
+        let message = renderer()
+            .render(Level::Error.title(title).snippet(snippet))
+            .to_string();
+        coded_err::<ParseError>(code, message)
+    }
+
+    /// The value of a string literal, decoded from the content for the
+    /// escapes JSON5 adds and the surrogates jsonc-parser rejects
+    fn string<'v>(&self, literal: &'v StringLit) -> Cow<'v, str> {
+        match (self.dialect, self.rewritten) {
+            (Dialect::Jsonc, false) => Cow::Borrowed(literal.value.as_ref()),
+            _ => Cow::Owned(json5::string_value(
+                &self.content[literal.range.start..literal.range.end],
+            )),
+        }
+    }
+
+    fn key<'v>(&self, name: &'v ObjectPropName) -> Cow<'v, str> {
+        match name {
+            ObjectPropName::String(literal) => self.string(literal),
+            ObjectPropName::Word(word) => Cow::Borrowed(word.value),
+        }
+    }
+
+    fn convert(&self, py: Python<'_>, value: &AstValue) -> PyResult<PyObject> {
+        let object = match value {
+            AstValue::NullKeyword(_) => into_pyany!(PyNone::get(py)),
+            AstValue::BooleanLit(b) => into_pyany!(PyBool::new(py, b.value)),
+            AstValue::NumberLit(n) => {
+                self.convert_number(py, n.value, n.range)?
+            }
+            AstValue::StringLit(s) => {
+                if self.strict {
+                    self.check_surrogates(s.range)?;
+                }
+                into_pyany!(PyString::new(py, &self.string(s)))
+            }
+            AstValue::Array(arr) => {
+                let mut converted = Vec::with_capacity(arr.elements.len());
+                for element in arr.elements.iter() {
+                    if self.expired(element.range().start) {
+                        break;
+                    }
+                    converted.push(self.convert(py, element)?);
+                }
+                into_pyany!(PyList::new(py, converted)?)
+            }
+            AstValue::Object(obj) => {
+                let dict = PyDict::new(py);
+                // Keys are only tracked when a definition does not simply
+                // replace the previous one
+                let tracked = self.duplicates != Duplicates::Last;
+                let mut seen: HashMap<Cow<str>, (JsoncRange, usize)> =
+                    HashMap::with_capacity(if tracked {
+                        obj.properties.len()
+                    } else {
+                        0
+                    });
+                for prop in obj.properties.iter() {
+                    if self.expired(prop.range().start) {
+                        break;
+                    }
+                    let key = self.key(&prop.name);
+                    // How many times the key was defined before
+                    let mut defined = 0;
+                    if tracked {
+                        let (first, count) = seen
+                            .entry(key.clone())
+                            .or_insert((prop.name.range(), 0));
+                        defined = *count;
+                        *count += 1;
+                        if defined > 0 {
+                            match self.duplicates {
+                                Duplicates::Error => {
+                                    return Err(self.error(
+                                        ErrorCode::JsoncDuplicateKey,
+                                        &format!("duplicate key `{}`", key),
+                                        prop.name.range(),
+                                        &[(*first, "first defined here")],
+                                    ));
+                                }
+                                Duplicates::First => continue,
+                                Duplicates::Last | Duplicates::Collect => {}
+                            }
+                        }
+                    }
+                    if self.strict {
+                        self.check_surrogates(prop.name.range())?;
+                    }
+                    let value = match &prop.value {
+                        // Flat maps of strings dominate settings files, so
+                        // their values skip the generic conversion
+                        AstValue::StringLit(s) if !self.strict => {
+                            into_pyany!(PyString::new(py, &self.string(s)))
+                        }
+                        value => self.convert(py, value)?,
+                    };
+                    let key = dict_key(py, &key);
+                    if self.duplicates == Duplicates::Collect && defined > 0 {
+                        // A key defined again collects its values in a list,
+                        // started with its first value
+                        let previous = dict.as_any().get_item(&key)?;
+                        if defined == 1 {
+                            let values =
+                                PyList::new(py, [previous.unbind(), value])?;
+                            dict.set_item(key, values)?;
+                        } else {
+                            previous.downcast::<PyList>()?.append(value)?;
+                        }
+                    } else {
+                        dict.set_item(key, value)?;
+                    }
+                }
+                into_pyany!(dict)
+            }
+        };
+        Ok(object)
+    }
+
+    fn convert_number(
+        &self,
+        py: Python<'_>,
+        number: &str,
+        range: JsoncRange,
+    ) -> PyResult<PyObject> {
+        let number = match self.dialect {
+            Dialect::Jsonc => Cow::Borrowed(number),
+            Dialect::Json5 => {
+                json5::decimal(&self.content[range.start..range.end])
+                    .ok_or_else(|| {
+                        self.error(
+                            ErrorCode::JsoncOverlongNumber,
+                            "hexadecimal integer does not fit into 128 bits",
+                            range,
+                            &[],
+                        )
+                    })?
+            }
+        };
+        let number = number.as_ref();
+        let is_integer = !number.contains(['.', 'e', 'E']);
+        if let Ok(int) = number.parse::<i64>() {
+            return Ok(into_pyany!(PyInt::new(py, int)));
+        }
+        let policy = self.int_overflow;
+        if is_integer && policy.is_some_and(|p| p != IntOverflow::Error) {
+            let int = py.get_type::<PyInt>().call1((number,))?;
+            if policy == Some(IntOverflow::Wrap) {
+                let int = overflow::wrapped(int.downcast::<PyInt>()?)?;
+                return Ok(into_pyany!(PyInt::new(py, int)));
+            }
+            return Ok(int.unbind());
+        }
+        if is_integer && (self.strict || policy.is_some()) {
+            return Err(self.error(
+                ErrorCode::JsoncOverlongNumber,
+                "integer does not fit into 64 bits",
+                range,
+                &[],
+            ));
+        }
+        match number.parse::<f64>() {
+            Ok(float) if self.strict && float.is_infinite() => Err(self.error(
+                ErrorCode::JsoncOverlongNumber,
+                "number overflows a double precision floating point number",
+                range,
+                &[],
+            )),
+            Ok(float) => Ok(into_pyany!(PyFloat::new(py, float))),
+            Err(_) => Err(self.error(
+                ErrorCode::JsoncInvalidNumber,
+                &format!(
+                    "Could not parse number `{}` as either 64-bit integer \
+                    or double precision floating point number",
+                    number
+                ),
+                range,
+                &[],
+            )),
+        }
+    }
+
+    /// Reject `\uXXXX` escapes of UTF-16 surrogates that are not part of a
+    /// high-low pair, as they cannot be represented in a Python `str`
+    /// without loss
+    fn check_surrogates(&self, range: JsoncRange) -> PyResult<()> {
+        match lone_surrogate(&self.content[range.start..range.end]) {
+            Some(offset) => Err(self.error(
+                ErrorCode::JsoncLoneSurrogate,
+                "unpaired UTF-16 surrogate escape",
+                JsoncRange {
+                    start: range.start + offset,
+                    end: range.start + offset + 6,
+                },
+                &[],
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Find the offset of the first unpaired surrogate escape in a raw string
+/// literal
+fn lone_surrogate(raw: &str) -> Option<usize> {
+    let bytes = raw.as_bytes();
+    // Offset of a high surrogate escape still waiting for its low half
+    let mut high = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            if high.is_some() {
+                return high;
+            }
+            i += 1;
+            continue;
+        }
+        let unit = raw
+            .get(i + 2..i + 6)
+            .filter(|_| bytes.get(i + 1) == Some(&b'u'))
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+        match unit {
+            Some(0xD800..=0xDBFF) => {
+                if high.is_some() {
+                    return high;
+                }
+                high = Some(i);
+            }
+            Some(0xDC00..=0xDFFF) => {
+                if high.take().is_none() {
+                    return Some(i);
+                }
+            }
+            _ => {
+                if high.is_some() {
+                    return high;
+                }
+            }
+        }
+        // Skip the whole escape sequence, so `\\u` is not mistaken for one
+        i += if unit.is_some() { 6 } else { 2 };
+    }
+    high
+}
+
+/// Render an error pointing at where a JSONC document stops being valid
+fn syntax_error(content: &str, error: &JsoncParseError) -> PyErr {
+    let converter = Converter {
+        content,
+        display: OnceCell::new(),
+        path: None,
+        strict: false,
+        dialect: Dialect::Jsonc,
+        rewritten: false,
+        duplicates: Duplicates::Last,
+        int_overflow: None,
+        deadline: None,
+        truncated: Cell::new(None),
+        cut: None,
+    };
+    converter.error(
+        ErrorCode::JsoncSyntax,
+        &error.kind().to_string(),
+        error.range(),
+        &[],
+    )
+}
+
+/// The part of the deadline that scanning a document may take, as a divisor
+const SCANNING_SHARE: u32 = 8;
+
+#[allow(clippy::too_many_arguments)]
+fn parse(
+    py: Python<'_>,
+    content: &str,
+    path: Option<PathBuf>,
+    strict: bool,
+    dialect: Dialect,
+    options: &ParseOptions,
+    duplicates: Duplicates,
+    int_overflow: Option<IntOverflow>,
+    deadline_ms: Option<f64>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let deadline = deadline_ms
+        .map(|ms| {
+            Duration::try_from_secs_f64(ms / 1000.0).map_err(|_| {
+                PyValueError::new_err(format!(
+                    "Deadline must be a non-negative number of milliseconds, \
+                    got {}",
+                    ms
+                ))
+            })
+        })
+        .transpose()?;
+    // The escapes jsonc-parser does not scan are replaced by ones of the
+    // same length, so ranges still point into the content the strings are
+    // decoded from
+    let scanned = json5::scannable(content, dialect);
+    // Building the syntax tree takes about three times as long as scanning
+    // the document, so scanning gets an eighth of the budget and the
+    // document is cut where it stands then, leaving half of the budget to
+    // the conversion
+    let cut = deadline
+        .and_then(|d| deadline::cut(&scanned, started + d / SCANNING_SHARE));
+    let converter = Converter {
+        content,
+        display: OnceCell::new(),
+        path: path.as_ref().map(|p| p.to_string_lossy().to_string()),
+        strict,
+        dialect,
+        rewritten: matches!(scanned, Cow::Owned(_)),
+        duplicates,
+        int_overflow,
+        deadline: deadline.map(|d| started + d),
+        truncated: Cell::new(None),
+        cut: cut.as_ref().map(|cut| cut.offset),
+    };
+    let parsed = parse_to_ast(
+        cut.as_ref().map_or(&*scanned, |cut| cut.text.as_str()),
+        &Default::default(),
+        options,
+    );
+
+    match parsed {
+        Ok(parsed) => {
+            let value = parsed.value.ok_or(coded_err::<ParseError>(
+                ErrorCode::JsoncEmpty,
+                "Parsed JSONC content is empty or invalid".to_string(),
+            ))?;
+            let object = converter.convert(py, &value)?;
+            match deadline {
+                Some(_) => Ok(into_pyany!(PyTuple::new(
+                    py,
+                    [object, converter.truncation(py)?]
+                )?)),
+                None => Ok(object),
+            }
+        }
+        Err(error) => Err(converter.error(
+            ErrorCode::JsoncSyntax,
+            &error.kind().to_string(),
+            error.range(),
+            &[],
+        )),
+    }
+}
+
+/// The arguments of `load` and `loads` past the document, so that Rust
+/// callers do not break when one is added
+pub struct LoadOptions<'a> {
+    pub strict: Option<bool>,
+    pub normalize_newlines: bool,
+    pub deadline_ms: Option<f64>,
+    pub dialect: &'a str,
+    pub on_duplicate: Option<&'a str>,
+    pub allow_comments: Option<bool>,
+    pub allow_trailing_commas: Option<bool>,
+    pub allow_loose_object_property_names: Option<bool>,
+    pub int_overflow: Option<&'a str>,
+}
+
+impl Default for LoadOptions<'_> {
+    /// The defaults of the arguments of `load` and `loads`
+    fn default() -> Self {
+        Self {
+            strict: None,
+            normalize_newlines: true,
+            deadline_ms: None,
+            dialect: "jsonc",
+            on_duplicate: None,
+            allow_comments: None,
+            allow_trailing_commas: None,
+            allow_loose_object_property_names: None,
+            int_overflow: None,
+        }
+    }
+}
+
+impl LoadOptions<'_> {
+    fn parse(
+        &self,
+        py: Python<'_>,
+        content: &str,
+        path: Option<PathBuf>,
+    ) -> PyResult<PyObject> {
+        let strict = self.strict.unwrap_or(profile::current().jsonc_strict);
+        let content = normalized_newlines(content, self.normalize_newlines);
+        let dialect = Dialect::of_name(self.dialect)?;
+        let options = parse_options(
+            self.allow_comments,
+            self.allow_trailing_commas,
+            self.allow_loose_object_property_names,
+        );
+        let duplicates = Duplicates::resolve(self.on_duplicate, strict)?;
+        let int_overflow = IntOverflow::resolve(self.int_overflow)?;
+        parse(
+            py,
+            &content,
+            path,
+            strict,
+            dialect,
+            &options,
+            duplicates,
+            int_overflow,
+            self.deadline_ms,
+        )
+    }
+}
+
+/// Parse a JSONC file like `load`, with its other arguments in `options`
+pub fn load_with(
+    py: Python<'_>,
+    path: FileSource<'_>,
+    options: &LoadOptions<'_>,
+) -> PyResult<PyObject> {
+    let content = path.read(read_file)?;
+    options.parse(py, &content, Some(path.origin()))
+}
+
+/// Parse a JSONC (JSON with comments) file and convert it to a Python object.
+///
+/// Args:
+///   - path (str | os.PathLike | IO): The path to the JSONC file, or a
+///                                    file-like object whose `read` returns
+///                                    its content as a string or UTF-8
+///                                    bytes, like `sys.stdin`. A stream is
+///                                    labelled in errors by its `name`, like
+///                                    `<stdin>`, or as `<stream>`.
+///   - strict (bool | None): Reject irregularities that are tolerated by
+///                           default: duplicate keys, unpaired surrogate
+///                           escapes and numbers that cannot be represented
+///                           without loss, but integers kept by
+///                           `int_overflow`. `None` as in the selected
+///                           profile.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n` before parsing, so offsets in errors
+///                                match the lines shown by editors.
+///   - deadline_ms (float | None): Stop parsing and converting the document
+///                                 after this many milliseconds, keeping the
+///                                 values converted so far, so interactive
+///                                 tools show a partial document instead of
+///                                 freezing on a huge one. Only the part of
+///                                 the document scanned within an eighth of
+///                                 the deadline is parsed.
+///   - dialect (str): The syntax of the document, `jsonc` for JSON with
+///                    comments and trailing commas, or `json5` for the
+///                    unquoted keys, single quoted strings, hexadecimal
+///                    numbers, leading `+` and multiline strings of JSON5
+///                    too, along with its escapes like `\x41`. JSON5
+///                    `Infinity`, `NaN` and numbers starting or ending with
+///                    a point are not supported.
+///   - on_duplicate (str | None): What to do with a key an object defines
+///                                again: `error` raises a `ParseError`,
+///                                `first` keeps its first value, `last` its
+///                                last one, and `collect` all of them in a
+///                                list, in order. `None` is `error` when
+///                                strict and `last` otherwise.
+///   - allow_comments (bool | None): Accept line and block comments. `None`
+///                                   as the dialect allows, which both do.
+///   - allow_trailing_commas (bool | None): Accept a comma after the last
+///                                          item of an array or an object.
+///                                          `None` as the dialect allows,
+///                                          which both do.
+///   - allow_loose_object_property_names (bool | None): Accept unquoted
+///                                                      keys, like
+///                                                      `{a: 1}`. `None` as
+///                                                      the dialect allows,
+///                                                      which both do.
+///   - int_overflow (str | None): What to do with integers that do not fit
+///                                into 64 bits, `error`, `wrap` or
+///                                `bigint-via-string` as described by
+///                                `set_int_overflow`, `None` for the policy it
+///                                selected.
+///
+/// Returns:
+///   - _TruncatedJsonValue: A Python object representing a valid JSON value,
+///                          or with a deadline, a tuple of the value, whole
+///                          or truncated, and where it got truncated, `None`
+///                          if it did not, with a `message`, the `start`
+///                          offset and `line` of the first value left out.
+///
+/// Raises:
+///   - IOError: If the file or the stream cannot be read.
+///   - TypeError: If `path` is neither a path nor a file-like object.
+///   - ValueError: If the deadline is negative, or the dialect, the
+///                 duplicate key policy or the overflow policy is unknown.
+///   - ParseError: If the content is not valid JSONC.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    strict = None,
+    normalize_newlines = true,
+    deadline_ms = None,
+    dialect = "jsonc",
+    on_duplicate = None,
+    allow_comments = None,
+    allow_trailing_commas = None,
+    allow_loose_object_property_names = None,
+    int_overflow = None
+))]
+pub fn load(
+    py: Python<'_>,
+    path: FileSource<'_>,
+    strict: Option<bool>,
+    normalize_newlines: bool,
+    deadline_ms: Option<f64>,
+    dialect: &str,
+    on_duplicate: Option<&str>,
+    allow_comments: Option<bool>,
+    allow_trailing_commas: Option<bool>,
+    allow_loose_object_property_names: Option<bool>,
+    int_overflow: Option<&str>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions {
+        strict,
+        normalize_newlines,
+        deadline_ms,
+        dialect,
+        on_duplicate,
+        allow_comments,
+        allow_trailing_commas,
+        allow_loose_object_property_names,
+        int_overflow,
+    };
+    load_with(py, path, &options)
+}
+
+/// Parse a JSONC (JSON with comments) string and convert it to a Python object.
+///
+/// Args:
+///   - content (str): The JSONC content as a string.
+///   - strict (bool | None): Reject irregularities that are tolerated by
+///                           default: duplicate keys, unpaired surrogate
+///                           escapes and numbers that cannot be represented
+///                           without loss, but integers kept by
+///                           `int_overflow`. `None` as in the selected
+///                           profile.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n` before parsing, so offsets in errors
+///                                match the lines shown by editors.
+///   - deadline_ms (float | None): Stop parsing and converting the document
+///                                 after this many milliseconds, keeping the
+///                                 values converted so far, so interactive
+///                                 tools show a partial document instead of
+///                                 freezing on a huge one. Only the part of
+///                                 the document scanned within an eighth of
+///                                 the deadline is parsed.
+///   - dialect (str): The syntax of the document, `jsonc` for JSON with
+///                    comments and trailing commas, or `json5` for the
+///                    unquoted keys, single quoted strings, hexadecimal
+///                    numbers, leading `+` and multiline strings of JSON5
+///                    too, along with its escapes like `\x41`. JSON5
+///                    `Infinity`, `NaN` and numbers starting or ending with
+///                    a point are not supported.
+///   - on_duplicate (str | None): What to do with a key an object defines
+///                                again: `error` raises a `ParseError`,
+///                                `first` keeps its first value, `last` its
+///                                last one, and `collect` all of them in a
+///                                list, in order. `None` is `error` when
+///                                strict and `last` otherwise.
+///   - allow_comments (bool | None): Accept line and block comments. `None`
+///                                   as the dialect allows, which both do.
+///   - allow_trailing_commas (bool | None): Accept a comma after the last
+///                                          item of an array or an object.
+///                                          `None` as the dialect allows,
+///                                          which both do.
+///   - allow_loose_object_property_names (bool | None): Accept unquoted
+///                                                      keys, like
+///                                                      `{a: 1}`. `None` as
+///                                                      the dialect allows,
+///                                                      which both do.
+///   - int_overflow (str | None): What to do with integers that do not fit
+///                                into 64 bits, `error`, `wrap` or
+///                                `bigint-via-string` as described by
+///                                `set_int_overflow`, `None` for the policy it
+///                                selected.
+///
+/// Returns:
+///   - _TruncatedJsonValue: A Python object representing a valid JSON value,
+///                          or with a deadline, a tuple of the value, whole
+///                          or truncated, and where it got truncated, `None`
+///                          if it did not, with a `message`, the `start`
+///                          offset and `line` of the first value left out.
+///
+/// Raises:
+///   - ValueError: If the deadline is negative, or the dialect, the
+///                 duplicate key policy or the overflow policy is unknown.
+///   - ParseError: If the content is not valid JSONC.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    expr,
+    strict = None,
+    normalize_newlines = true,
+    deadline_ms = None,
+    dialect = "jsonc",
+    on_duplicate = None,
+    allow_comments = None,
+    allow_trailing_commas = None,
+    allow_loose_object_property_names = None,
+    int_overflow = None
+))]
+pub fn loads(
+    py: Python<'_>,
+    expr: String,
+    strict: Option<bool>,
+    normalize_newlines: bool,
+    deadline_ms: Option<f64>,
+    dialect: &str,
+    on_duplicate: Option<&str>,
+    allow_comments: Option<bool>,
+    allow_trailing_commas: Option<bool>,
+    allow_loose_object_property_names: Option<bool>,
+    int_overflow: Option<&str>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions {
+        strict,
+        normalize_newlines,
+        deadline_ms,
+        dialect,
+        on_duplicate,
+        allow_comments,
+        allow_trailing_commas,
+        allow_loose_object_property_names,
+        int_overflow,
+    };
+    options.parse(py, &expr, None)
+}
+
+/// List the regions of a JSONC document managed by a tool.
+///
+/// A managed region is delimited by line comments, from
+/// `// cosutils:begin <name>` to `// cosutils:end`, and its content is meant
+/// to be regenerated with `replace_managed` while the user edits the rest of
+/// the document.
+///
+/// Args:
+///   - content (str): The JSONC content as a string.
+///
+/// Returns:
+///   - list[_ManagedRegion]: The regions, with their `name`, the `start` and
+///                           `end` byte offsets of the lines between their
+///                           markers, the `line` of their begin marker and
+///                           their `content`.
+///
+/// Raises:
+///   - ParseError: If the markers are unnamed, nested, duplicated or do not
+///                 pair up.
+#[pyfunction]
+pub fn managed_regions(
+    py: Python<'_>,
+    content: &str,
+) -> PyResult<Vec<PyObject>> {
+    managed::regions(content, "//")?
+        .iter()
+        .map(|r| r.try_to_pyobject(py))
+        .collect()
+}
+
+/// Replace the content of a region of a JSONC document managed by a tool,
+/// see `managed_regions`.
+///
+/// The markers and everything outside of the region are kept as they are,
+/// and the replacement is indented like the begin marker.
+///
+/// Args:
+///   - content (str): The JSONC content as a string.
+///   - name (str): The name of the region.
+///   - replacement (str): The new content of the region.
+///
+/// Returns:
+///   - str: The updated content.
+///
+/// Raises:
+///   - KeyError: If the document has no region of that name.
+///   - ParseError: If the markers are malformed, or the updated content is
+///                 not valid JSONC.
+///
+/// Example:
+/// ```python
+/// >>> doc = '{\n  // cosutils:begin gen\n  "a": 1\n  // cosutils:end\n}\n'
+/// >>> print(replace_managed(doc, "gen", '"b": 2'))
+/// {
+///   // cosutils:begin gen
+///   "b": 2
+///   // cosutils:end
+/// }
+/// ```
+#[pyfunction]
+pub fn replace_managed(
+    py: Python<'_>,
+    content: &str,
+    name: &str,
+    replacement: &str,
+) -> PyResult<String> {
+    let replaced = managed::replace(content, "//", name, replacement)?;
+    parse(
+        py,
+        &replaced,
+        None,
+        false,
+        Dialect::Jsonc,
+        &ParseOptions::default(),
+        Duplicates::Last,
+        overflow::policy(),
+        None,
+    )?;
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loads(content: &str, options: LoadOptions) -> PyResult<String> {
+        Python::with_gil(|py| {
+            let value = options.parse(py, content, None)?;
+            Ok(value.bind(py).repr()?.to_string())
+        })
+    }
+
+    fn strict() -> LoadOptions<'static> {
+        LoadOptions {
+            strict: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// The code of the error raised by loading `content`
+    fn code(content: &str, options: LoadOptions) -> String {
+        let error = loads(content, options).unwrap_err();
+        Python::with_gil(|py| {
+            let code = error.value(py).getattr("code").unwrap();
+            code.extract::<String>().unwrap()
+        })
+    }
+
+    #[test]
+    fn deadline_cuts_large_documents() {
+        let element = r#""value", "#;
+        let content = format!("[{}0]", element.repeat(3_000_000));
+        let options = LoadOptions {
+            deadline_ms: Some(100.0),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        Python::with_gil(|py| {
+            let loaded = options.parse(py, &content, None).unwrap();
+            let (value, truncation) = loaded
+                .extract::<(Bound<PyList>, Bound<PyDict>)>(py)
+                .unwrap();
+            assert!(started.elapsed() < Duration::from_secs(1));
+            let start = truncation.get_item("start").unwrap().unwrap();
+            let start = start.extract::<usize>().unwrap();
+            assert_eq!(start, 1 + value.len() * element.len());
+            assert!(value.len() < 3_000_000);
+        });
+    }
+
+    #[test]
+    fn surrogate_pair() {
+        let emoji = r#""\ud83d\ude00""#;
+        assert_eq!(loads(emoji, strict()).unwrap(), "'😀'");
+        assert_eq!(loads(emoji, LoadOptions::default()).unwrap(), "'😀'");
+        let key = r#"{"\uD83D\uDE00": "\u00e9\ud83d\ude00"}"#;
+        assert_eq!(loads(key, strict()).unwrap(), "{'😀': 'é😀'}");
+    }
+
+    #[test]
+    fn lone_surrogate() {
+        assert_eq!(code(r#""\ud83d""#, strict()), "JSONC_E011");
+        assert_eq!(code(r#"["a\ude00b"]"#, strict()), "JSONC_E011");
+        let lenient = loads(r#""\ud83d""#, LoadOptions::default());
+        assert_eq!(lenient.unwrap(), "'\u{fffd}'");
+    }
+
+    #[test]
+    fn strict_codes() {
+        assert_eq!(code(r#"{"a": 1, "a": 2}"#, strict()), "JSONC_E010");
+        assert_eq!(code("18446744073709551616", strict()), "JSONC_E012");
+        assert_eq!(code("1e400", strict()), "JSONC_E012");
+        assert_eq!(code("{", LoadOptions::default()), "JSONC_E001");
+        let lenient = LoadOptions::default();
+        assert_eq!(loads(r#"{"a": 1, "a": 2}"#, lenient).unwrap(), "{'a': 2}");
+    }
+
+    #[test]
+    fn int_overflow_policies() {
+        let policy = |int_overflow| LoadOptions {
+            int_overflow: Some(int_overflow),
+            ..Default::default()
+        };
+        let big = "[18446744073709551617, -9223372036854775809]";
+        assert_eq!(
+            loads(big, LoadOptions::default()).unwrap(),
+            "[1.8446744073709552e+19, -9.223372036854776e+18]"
+        );
+        assert_eq!(
+            loads(big, policy("bigint-via-string")).unwrap(),
+            "[18446744073709551617, -9223372036854775809]"
+        );
+        assert_eq!(
+            loads(big, policy("wrap")).unwrap(),
+            "[1, 9223372036854775807]"
+        );
+        assert_eq!(code(big, policy("error")), "JSONC_E012");
+        let strict_digits = LoadOptions {
+            int_overflow: Some("bigint-via-string"),
+            ..strict()
+        };
+        assert_eq!(
+            loads("18446744073709551616", strict_digits).unwrap(),
+            "18446744073709551616"
+        );
+        assert!(loads("1", policy("saturate")).is_err());
+    }
+
+    #[test]
+    fn duplicate_policies() {
+        let content = r#"{"a": 1, "b": 0, "a": 2, "a": 3}"#;
+        let policy = |on_duplicate| LoadOptions {
+            on_duplicate: Some(on_duplicate),
+            ..Default::default()
+        };
+        assert_eq!(
+            loads(content, policy("first")).unwrap(),
+            "{'a': 1, 'b': 0}"
+        );
+        assert_eq!(loads(content, policy("last")).unwrap(), "{'a': 3, 'b': 0}");
+        assert_eq!(
+            loads(content, policy("collect")).unwrap(),
+            "{'a': [1, 2, 3], 'b': 0}"
+        );
+        assert_eq!(code(content, policy("error")), "JSONC_E010");
+        // An explicit policy wins over strict mode
+        let strict_last = LoadOptions {
+            on_duplicate: Some("last"),
+            ..strict()
+        };
+        assert_eq!(loads(content, strict_last).unwrap(), "{'a': 3, 'b': 0}");
+    }
+}
@@ -0,0 +1,298 @@
+use jsonc_parser::ParseOptions;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::PyResult;
+
+use super::{parse, Dialect, Duplicates};
+use crate::parsers::overflow;
+
+/// How a document is laid out
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// Every item of a non-empty container on its own line
+    Expanded,
+    /// Every container on a single line, unless it holds comments
+    Compact,
+}
+
+impl Style {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "expanded" => Ok(Style::Expanded),
+            "compact" => Ok(Style::Compact),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown style `{}`, expected `expanded` or `compact`",
+                name
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Open,
+    Close,
+    Colon,
+    Comma,
+    LineComment,
+    BlockComment,
+    /// A string, number, keyword or unquoted key, kept as it is written
+    Atom,
+}
+
+impl Kind {
+    fn is_comment(self) -> bool {
+        matches!(self, Kind::LineComment | Kind::BlockComment)
+    }
+}
+
+struct Token {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+/// Split a valid JSONC document into tokens, whitespace left out
+fn tokens(content: &str) -> Vec<Token> {
+    let bytes = content.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'{' | b'[' => Kind::Open,
+            b'}' | b']' => Kind::Close,
+            b':' => Kind::Colon,
+            b',' => Kind::Comma,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = content[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                tokens.push(Token {
+                    kind: Kind::LineComment,
+                    start,
+                    end: content[start..i].trim_end().len() + start,
+                });
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let closing = content[i + 2..].find("*/");
+                i = closing.map_or(bytes.len(), |n| i + n + 4);
+                tokens.push(Token {
+                    kind: Kind::BlockComment,
+                    start,
+                    end: i,
+                });
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                tokens.push(Token {
+                    kind: Kind::Atom,
+                    start,
+                    end: i,
+                });
+                continue;
+            }
+            _ => {
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !b"{}[]:,/\"'".contains(&bytes[i])
+                {
+                    i += 1;
+                }
+                tokens.push(Token {
+                    kind: Kind::Atom,
+                    start,
+                    end: i,
+                });
+                continue;
+            }
+        };
+        i += 1;
+        tokens.push(Token {
+            kind,
+            start,
+            end: i,
+        });
+    }
+    tokens
+}
+
+/// Whether each opening token starts a container holding comments, at any
+/// depth
+fn commented(tokens: &[Token]) -> Vec<bool> {
+    let mut commented = vec![false; tokens.len()];
+    let mut opened = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            Kind::Open => opened.push(i),
+            Kind::Close => {
+                let open = opened.pop();
+                if let (Some(open), Some(&parent)) = (open, opened.last()) {
+                    commented[parent] |= commented[open];
+                }
+            }
+            kind if kind.is_comment() => {
+                if let Some(&open) = opened.last() {
+                    commented[open] = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    commented
+}
+
+/// Lays out the whitespace between the tokens of a document
+struct Layout<'a> {
+    indent: &'a str,
+    newline: &'a str,
+}
+
+impl Layout<'_> {
+    /// A line break to `depth`, keeping a blank line if the original had one
+    fn line(&self, depth: usize, gap: &str, blank: bool) -> String {
+        let mut line = self.newline.to_string();
+        if blank && gap.matches('\n').count() > 1 {
+            line.push_str(self.newline);
+        }
+        line.push_str(&self.indent.repeat(depth));
+        line
+    }
+}
+
+/// Reformat a JSONC document to a style, keeping its comments and the text
+/// of its strings and numbers as they are.
+///
+/// Only the whitespace between tokens changes, so the formatted document
+/// parses to the same value, and trailing commas are kept. Comments written
+/// after a value stay on its line, other comments get their own line, and a
+/// blank line between items is kept. New line breaks use the line endings
+/// of the document.
+///
+/// Args:
+///   - content (str): The JSONC content as a string.
+///   - style (str): The layout, `expanded` for every item of a container
+///                  on its own line, or `compact` for every container on a
+///                  single line, unless it holds comments.
+///   - indent (int): The number of spaces per level of indentation.
+///   - range (tuple[int, int] | None): The `start` and `end` byte offsets of
+///                                     the selection to reformat, leaving
+///                                     the whitespace outside of it as it
+///                                     is, for formatting on demand in
+///                                     editors.
+///
+/// Returns:
+///   - str: The formatted content.
+///
+/// Raises:
+///   - ValueError: If the style is unknown, or the range is out of the
+///                 content.
+///   - ParseError: If the content is not valid JSONC.
+///
+/// Example:
+/// ```python
+/// >>> print(format('{"a":1, // one\n"b":[true,"x"]}'), end="")
+/// {
+///   "a": 1, // one
+///   "b": [
+///     true,
+///     "x"
+///   ]
+/// }
+/// >>> format('{\n  "a": [1, 2]\n}', style="compact")
+/// '{"a": [1, 2]}\n'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (content, style = "expanded", indent = 2, range = None))]
+pub fn format(
+    py: Python<'_>,
+    content: &str,
+    style: &str,
+    indent: usize,
+    range: Option<(usize, usize)>,
+) -> PyResult<String> {
+    let style = Style::of_name(style)?;
+    let (start, end) = range.unwrap_or((0, content.len()));
+    if start > end || end > content.len() {
+        return Err(PyValueError::new_err(format!(
+            "The range {}..{} is out of the content of {} bytes",
+            start,
+            end,
+            content.len()
+        )));
+    }
+    parse(
+        py,
+        content,
+        None,
+        false,
+        Dialect::Jsonc,
+        &ParseOptions::default(),
+        Duplicates::Last,
+        overflow::policy(),
+        None,
+    )?;
+
+    let tokens = tokens(content);
+    let commented = commented(&tokens);
+    let layout = Layout {
+        indent: &" ".repeat(indent),
+        newline: if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        },
+    };
+    let mut formatted = String::with_capacity(content.len());
+    // Whether each open container is laid out on a single line
+    let mut inline = Vec::new();
+    let mut written = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let gap = &content[written..token.start];
+        let previous = i.checked_sub(1).map(|i| tokens[i].kind);
+        // A closing token is laid out like its container
+        let closes_inline =
+            token.kind == Kind::Close && inline.pop() == Some(true);
+        let (depth, on_line) = (inline.len(), inline.last() == Some(&true));
+        let spaced = match (previous, token.kind) {
+            (None, _) => String::new(),
+            (Some(Kind::Open), Kind::Close) => String::new(),
+            (_, Kind::Close) if closes_inline => String::new(),
+            (_, Kind::Close) => layout.line(depth, gap, false),
+            (Some(Kind::LineComment), _) => layout.line(depth, gap, true),
+            (_, kind) if kind.is_comment() && !gap.contains('\n') => {
+                " ".to_string()
+            }
+            (_, kind) if kind.is_comment() => layout.line(depth, gap, true),
+            (_, Kind::Comma | Kind::Colon) => String::new(),
+            (Some(Kind::BlockComment), _) if !gap.contains('\n') => {
+                " ".to_string()
+            }
+            (Some(Kind::BlockComment), _) => layout.line(depth, gap, true),
+            (Some(Kind::Open), _) if on_line => String::new(),
+            (Some(Kind::Open), _) => layout.line(depth, gap, false),
+            (Some(Kind::Comma), _) if !on_line => layout.line(depth, gap, true),
+            _ => " ".to_string(),
+        };
+        if token.kind == Kind::Open {
+            inline.push(style == Style::Compact && !commented[i]);
+        }
+
+        let selected = start <= written && token.start <= end;
+        formatted.push_str(if selected { &spaced } else { gap });
+        formatted.push_str(&content[token.start..token.end]);
+        written = token.end;
+    }
+    let selected = start <= written && content.len() <= end;
+    let gap = &content[written..];
+    formatted.push_str(if selected { layout.newline } else { gap });
+    Ok(formatted)
+}
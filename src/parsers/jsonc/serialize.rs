@@ -0,0 +1,352 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::PyResult;
+
+use crate::parsers::floats::{FloatOptions, FloatPolicy, NonFinite};
+use crate::parsers::utils::{coded_err, ConversionError, ErrorCode};
+
+/// Write a string as a JSON string literal, escaping the quotes, the
+/// backslashes and the control characters JSON does not allow unescaped
+fn string(out: &mut String, s: &str) {
+    out.reserve(s.len() + 2);
+    out.push('"');
+    // Copy the runs of characters needing no escape at once
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped: Cow<'_, str> = match c {
+            '"' => "\\\"".into(),
+            '\\' => "\\\\".into(),
+            '\n' => "\\n".into(),
+            '\r' => "\\r".into(),
+            '\t' => "\\t".into(),
+            '\u{08}' => "\\b".into(),
+            '\u{0c}' => "\\f".into(),
+            c if c < ' ' => format!("\\u{:04x}", c as u32).into(),
+            _ => continue,
+        };
+        out.push_str(&s[start..i]);
+        out.push_str(&escaped);
+        start = i + c.len_utf8();
+    }
+    out.push_str(&s[start..]);
+    out.push('"');
+}
+
+/// Write a float as a JSON number, or as `null` if it is not finite and the
+/// policy asks so, as JSON has no literal for them
+pub(super) fn float(f: f64, floats: &FloatPolicy) -> PyResult<String> {
+    if f.is_finite() {
+        return Ok(floats.literal(f));
+    }
+    if floats.non_finite == Some(NonFinite::Null) {
+        return Ok("null".to_string());
+    }
+    Err(coded_err::<ConversionError>(
+        ErrorCode::JsoncConversion,
+        format!("Float {} has no JSON literal", f),
+    ))
+}
+
+/// Writes Python values as JSON
+struct Writer {
+    indent: Option<usize>,
+    trailing_commas: bool,
+    floats: FloatPolicy,
+    out: String,
+}
+
+impl Writer {
+    /// Start a new line at `depth` levels of indentation, unless writing on
+    /// a single line
+    fn newline(&mut self, depth: usize) {
+        if let Some(indent) = self.indent {
+            self.out.push('\n');
+            self.out.push_str(&" ".repeat(indent * depth));
+        }
+    }
+
+    /// Separate an item from the previous one of its container, by a space
+    /// after the comma when writing on a single line
+    fn separate(&mut self, first: bool, depth: usize) {
+        if !first {
+            self.out.push(',');
+            if self.indent.is_none() {
+                self.out.push(' ');
+            }
+        }
+        self.newline(depth);
+    }
+
+    fn write(&mut self, obj: &Bound<'_, PyAny>, depth: usize) -> PyResult<()> {
+        // Strings come first, they are most of the values of settings files
+        if let Ok(s) = obj.downcast::<PyString>() {
+            string(&mut self.out, s.to_str()?);
+        } else if obj.is_none() {
+            self.out.push_str("null");
+        } else if let Ok(b) = obj.downcast::<PyBool>() {
+            self.out
+                .push_str(if b.is_true() { "true" } else { "false" });
+        } else if let Ok(i) = obj.downcast::<PyInt>() {
+            // JSON numbers have no size limit, larger integers are written
+            // with all their digits like `json.dumps` does
+            match i.extract::<i64>() {
+                Ok(i) => self.out.push_str(&i.to_string()),
+                Err(_) => {
+                    let digits = i.str()?;
+                    self.out.push_str(digits.to_str()?);
+                }
+            }
+        } else if let Ok(f) = obj.downcast::<PyFloat>() {
+            self.out.push_str(&float(f.value(), &self.floats)?);
+        } else if let Ok(dict) = obj.downcast::<PyDict>() {
+            self.out.push('{');
+            for (i, (k, v)) in dict.iter().enumerate() {
+                let key = k.downcast::<PyString>().map_err(|_| {
+                    coded_err::<ConversionError>(
+                        ErrorCode::JsoncConversion,
+                        format!(
+                            "Object keys must be strings, got {}",
+                            k.get_type()
+                        ),
+                    )
+                })?;
+                self.separate(i == 0, depth + 1);
+                string(&mut self.out, key.to_str()?);
+                self.out.push_str(": ");
+                self.write(&v, depth + 1)?;
+            }
+            self.close('}', depth, dict.is_empty());
+        } else if obj.is_instance_of::<PyList>()
+            || obj.is_instance_of::<PyTuple>()
+        {
+            self.out.push('[');
+            let mut empty = true;
+            for item in obj.try_iter()? {
+                self.separate(empty, depth + 1);
+                self.write(&item?, depth + 1)?;
+                empty = false;
+            }
+            self.close(']', depth, empty);
+        } else {
+            return Err(coded_err::<ConversionError>(
+                ErrorCode::JsoncConversion,
+                format!(
+                    "Cannot convert python type {} to JSON",
+                    obj.get_type().name()?
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Close an array or an object, written `[]` and `{}` when empty
+    fn close(&mut self, bracket: char, depth: usize, empty: bool) {
+        if !empty && self.indent.is_some() {
+            if self.trailing_commas {
+                self.out.push(',');
+            }
+            self.newline(depth);
+        }
+        self.out.push(bracket);
+    }
+}
+
+fn write(
+    obj: &Bound<'_, PyAny>,
+    indent: Option<usize>,
+    trailing_commas: bool,
+    floats: FloatPolicy,
+) -> PyResult<String> {
+    let mut writer = Writer {
+        indent,
+        trailing_commas,
+        floats,
+        out: String::new(),
+    };
+    writer.write(obj, 0)?;
+    Ok(writer.out)
+}
+
+/// Serialize a Python object to JSON, the inverse of `loads`.
+///
+/// Non-ASCII characters are written as they are, and only the quotes, the
+/// backslashes and the control characters are escaped. Keys are written in
+/// the order of the dict. Integers too large for 64 bits are written with
+/// all their digits, like `json.dumps` does.
+///
+/// Args:
+///   - obj (Any): The value to serialize, made of `None`, `bool`, `int`,
+///                `float`, `str`, `list`, `tuple` and `dict` with `str`
+///                keys.
+///   - indent (int | None): The number of spaces to indent nested arrays and
+///                          objects by, one item per line. `None` writes the
+///                          document on a single line.
+///   - trailing_commas (bool): Write a comma after the last item of each
+///                             array and object spanning several lines, as
+///                             JSONC allows, so that appending an item only
+///                             changes one line.
+///   - float_policy (str | None): What to do with infinite and NaN floats,
+///                                `"none"` writing them as `null`, or
+///                                `"error"`. `None` follows
+///                                `set_float_policy`.
+///   - float_repr (str | None): How to write finite floats, `"shortest"`,
+///                              `"decimal"` or `"scientific"`. `None`
+///                              follows `set_float_policy`.
+///   - float_precision (int | None): The digits written after the point by
+///                                   the `decimal` and `scientific` reprs,
+///                                   all of them if `None`.
+///
+/// Returns:
+///   - str: The JSON document.
+///
+/// Raises:
+///   - ConversionError: If a value has no JSON literal, like an infinite
+///                      float or an object of another type, or a dict key is
+///                      not a string.
+///   - ValueError: If a float policy or repr is unknown, or a precision is
+///                 given without the `decimal` or `scientific` repr.
+///
+/// Example:
+/// ```python
+/// >>> print(dumps({"name": "café", "ports": [80, 443]}))
+/// {
+///   "name": "café",
+///   "ports": [
+///     80,
+///     443
+///   ]
+/// }
+/// >>> dumps({"a": [1, 2], "b": {}}, indent=None)
+/// '{"a": [1, 2], "b": {}}'
+/// >>> print(dumps([1, 2], trailing_commas=True))
+/// [
+///   1,
+///   2,
+/// ]
+/// >>> dumps([1.5, float("nan")], indent=None, float_policy="none",
+/// ...       float_repr="decimal", float_precision=2)
+/// '[1.50, null]'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    obj,
+    indent = Some(2),
+    trailing_commas = false,
+    float_policy = None,
+    float_repr = None,
+    float_precision = None,
+))]
+pub fn dumps(
+    obj: &Bound<'_, PyAny>,
+    indent: Option<usize>,
+    trailing_commas: bool,
+    float_policy: Option<&str>,
+    float_repr: Option<&str>,
+    float_precision: Option<usize>,
+) -> PyResult<String> {
+    let floats = FloatOptions::new(float_policy, float_repr, float_precision)?;
+    write(obj, indent, trailing_commas, floats.resolve())
+}
+
+/// Serialize a Python object to a JSON file, like `dumps`, ending it with a
+/// newline.
+///
+/// Args:
+///   - obj (Any): The value to serialize, see `dumps`.
+///   - path (str | os.PathLike): The path of the file, replaced if it exists.
+///   - indent (int | None): The number of spaces to indent nested arrays and
+///                          objects by, `None` for a single line.
+///   - trailing_commas (bool): Write a comma after the last item of each
+///                             array and object spanning several lines.
+///   - float_policy (str | None): What to do with infinite and NaN floats,
+///                                see `dumps`.
+///   - float_repr (str | None): How to write finite floats, see `dumps`.
+///   - float_precision (int | None): The digits written after the point,
+///                                   see `dumps`.
+///
+/// Raises:
+///   - IOError: If the file cannot be written.
+///   - ConversionError: If a value has no JSON literal, or a dict key is not
+///                      a string.
+///   - ValueError: If a float policy, repr or precision is invalid.
+///
+/// Example:
+/// ```python
+/// >>> settings = load("settings.json")
+/// >>> settings["editor.tabSize"] = 4
+/// >>> dump(settings, "settings.json")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    obj,
+    path,
+    indent = Some(2),
+    trailing_commas = false,
+    float_policy = None,
+    float_repr = None,
+    float_precision = None,
+))]
+pub fn dump(
+    obj: &Bound<'_, PyAny>,
+    path: PathBuf,
+    indent: Option<usize>,
+    trailing_commas: bool,
+    float_policy: Option<&str>,
+    float_repr: Option<&str>,
+    float_precision: Option<usize>,
+) -> PyResult<()> {
+    let floats = FloatOptions::new(float_policy, float_repr, float_precision)?;
+    let mut content = write(obj, indent, trailing_commas, floats.resolve())?;
+    content.push('\n');
+    fs::write(&path, content).map_err(|e| {
+        PyIOError::new_err(format!(
+            "Failed to write file {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+
+    fn dumped(
+        obj: &str,
+        float_policy: Option<&str>,
+        float_repr: Option<&str>,
+        float_precision: Option<usize>,
+    ) -> PyResult<String> {
+        Python::with_gil(|py| {
+            let obj = py.eval(&CString::new(obj)?, None, None)?;
+            dumps(&obj, None, false, float_policy, float_repr, float_precision)
+        })
+    }
+
+    #[test]
+    fn float_policies() {
+        let obj = "[1.5, 1e20, 0.25]";
+        assert_eq!(
+            dumped(obj, None, Some("decimal"), Some(2)).unwrap(),
+            "[1.50, 100000000000000000000.00, 0.25]"
+        );
+        assert_eq!(
+            dumped(obj, None, Some("scientific"), None).unwrap(),
+            "[1.5e0, 1.0e20, 2.5e-1]"
+        );
+        assert_eq!(
+            dumped("[float('nan'), 1.0]", Some("none"), None, None).unwrap(),
+            "[null, 1.0]"
+        );
+        assert!(dumped("[float('inf')]", Some("error"), None, None).is_err());
+        assert!(dumped(obj, None, None, Some(2)).is_err());
+        assert!(dumped(obj, Some("nan"), None, None).is_err());
+    }
+}
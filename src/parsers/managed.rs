@@ -0,0 +1,127 @@
+use std::ops::Range;
+
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+
+use crate::parsers::utils::{coded_err, ErrorCode, ParseError, TryToPyObject};
+
+/// Marks the first line of a managed region, followed by its name
+const BEGIN: &str = "cosutils:begin";
+/// Marks the last line of a managed region
+const END: &str = "cosutils:end";
+
+/// A region of a document delimited by marker comments, whose content is
+/// owned by a tool rather than by the user
+pub struct Region {
+    pub name: String,
+    /// The lines between the markers
+    pub content: Range<usize>,
+    pub text: String,
+    /// The indentation of the begin marker, given to the replacement lines
+    pub indent: String,
+    /// The line of the begin marker
+    pub line: usize,
+}
+
+impl TryToPyObject for Region {
+    fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let region = PyDict::new(py);
+        region.set_item("name", &self.name)?;
+        region.set_item("start", self.content.start)?;
+        region.set_item("end", self.content.end)?;
+        region.set_item("line", self.line)?;
+        region.set_item("content", &self.text)?;
+        Ok(region.into_any().unbind())
+    }
+}
+
+/// Find the managed regions of a document, whose markers are line comments
+/// starting with `comment`, like `// cosutils:begin generated` and
+/// `// cosutils:end`
+pub fn regions(content: &str, comment: &str) -> PyResult<Vec<Region>> {
+    let invalid = |line: usize, message: &str| {
+        coded_err::<ParseError>(
+            ErrorCode::ManagedMarkers,
+            format!("Managed region marker on line {}: {}", line, message),
+        )
+    };
+    let mut regions = Vec::new();
+    let mut open: Option<Region> = None;
+    let mut offset = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let (number, start) = (i + 1, offset);
+        offset += line.len();
+        let trimmed = line.trim();
+        let Some(marker) = trimmed.strip_prefix(comment).map(str::trim) else {
+            continue;
+        };
+        let begin = marker
+            .strip_prefix(BEGIN)
+            .filter(|n| n.is_empty() || n.starts_with(char::is_whitespace));
+        if let Some(name) = begin {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(invalid(number, "the region has no name"));
+            }
+            if open.is_some() {
+                return Err(invalid(number, "regions cannot be nested"));
+            }
+            if regions.iter().any(|r: &Region| r.name == name) {
+                return Err(invalid(
+                    number,
+                    &format!("region `{}` is defined twice", name),
+                ));
+            }
+            open = Some(Region {
+                name: name.to_string(),
+                content: offset..offset,
+                text: String::new(),
+                indent: line[..line.len() - line.trim_start().len()]
+                    .to_string(),
+                line: number,
+            });
+        } else if marker == END {
+            let Some(mut region) = open.take() else {
+                return Err(invalid(number, "no region was begun"));
+            };
+            region.content.end = start;
+            region.text = content[region.content.clone()].to_string();
+            regions.push(region);
+        }
+    }
+    match open {
+        Some(region) => Err(invalid(
+            region.line,
+            &format!("region `{}` is never ended", region.name),
+        )),
+        None => Ok(regions),
+    }
+}
+
+/// Replace the content of the managed region `name`, leaving the rest of
+/// the document and the markers untouched
+pub fn replace(
+    content: &str,
+    comment: &str,
+    name: &str,
+    replacement: &str,
+) -> PyResult<String> {
+    let regions = regions(content, comment)?;
+    let region = regions.iter().find(|r| r.name == name).ok_or_else(|| {
+        PyKeyError::new_err(format!("No managed region named `{}`", name))
+    })?;
+
+    let mut replaced = String::with_capacity(content.len() + replacement.len());
+    replaced.push_str(&content[..region.content.start]);
+    for line in replacement.lines() {
+        if !line.trim().is_empty() {
+            replaced.push_str(&region.indent);
+            replaced.push_str(line);
+        }
+        replaced.push('\n');
+    }
+    replaced.push_str(&content[region.content.end..]);
+    Ok(replaced)
+}
@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::{is_separator, Component, Path, PathBuf};
+
+use annotate_snippets::{Level, Snippet};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString};
+use pyo3::{PyObject, PyResult};
+use toml::Value as TomlValue;
+
+use crate::into_pyany;
+use crate::parsers::detect::{extension, format_of_extension};
+use crate::parsers::render::renderer;
+#[cfg(feature = "nix")]
+use crate::parsers::utils::FileSource;
+use crate::parsers::utils::{
+    coded_err, dict_key, display_source, read_file, ErrorCode, IntoPyErr,
+    ParseError, TryToPyObject,
+};
+
+fn invalid(manifest: &Path, message: String) -> PyErr {
+    coded_err::<ParseError>(
+        ErrorCode::ManifestInvalid,
+        format!("Invalid manifest {}: {}", manifest.display(), message),
+    )
+}
+
+impl TryToPyObject for TomlValue {
+    fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let object = match self {
+            TomlValue::String(s) => into_pyany!(PyString::new(py, s)),
+            TomlValue::Integer(i) => into_pyany!(PyInt::new(py, *i)),
+            TomlValue::Float(f) => into_pyany!(PyFloat::new(py, *f)),
+            TomlValue::Boolean(b) => into_pyany!(PyBool::new(py, *b)),
+            // Manifests use dates as plain values, keep them as written
+            TomlValue::Datetime(d) => {
+                into_pyany!(PyString::new(py, &d.to_string()))
+            }
+            TomlValue::Array(items) => {
+                let converted = items
+                    .iter()
+                    .map(|v| v.try_to_pyobject(py))
+                    .collect::<PyResult<Vec<_>>>()?;
+                into_pyany!(PyList::new(py, converted)?)
+            }
+            TomlValue::Table(table) => {
+                let dict = PyDict::new(py);
+                for (k, v) in table.iter() {
+                    dict.set_item(dict_key(py, k), v.try_to_pyobject(py)?)?;
+                }
+                into_pyany!(dict)
+            }
+        };
+        Ok(object)
+    }
+}
+
+impl IntoPyErr for toml::de::Error {
+    fn into_pyerr(self, snippet: Snippet) -> PyErr {
+        let snippet = match self.span() {
+            Some(range) => snippet.annotation(Level::Error.span(range)),
+            None => snippet,
+        };
+        let message = Level::Error.title(self.message()).snippet(snippet);
+        let message = renderer().render(message).to_string();
+        coded_err::<ParseError>(ErrorCode::ManifestSyntax, message)
+    }
+}
+
+/// Read a manifest into Python objects, evaluating it if it is written in nix
+fn read_manifest<'py>(
+    py: Python<'py>,
+    path: &Path,
+) -> PyResult<Bound<'py, PyAny>> {
+    match extension(path).as_deref() {
+        Some("toml") => {
+            let content = read_file(path)?;
+            let table =
+                toml::from_str::<toml::Table>(&content).map_err(|e| {
+                    let source = display_source(&content);
+                    let origin = path.to_string_lossy();
+                    e.into_pyerr(
+                        Snippet::source(&source).origin(&origin).fold(true),
+                    )
+                })?;
+            Ok(TomlValue::Table(table).try_to_pyobject(py)?.into_bound(py))
+        }
+        #[cfg(feature = "nix")]
+        Some("nix") => {
+            let source = FileSource::Path(path.to_path_buf());
+            let value = crate::parsers::nix::eval(
+                py, source, None, false, None, None, None, None, None, None,
+                None, true, false, false, "str", false, false, None,
+            )?;
+            Ok(value.into_bound(py))
+        }
+        #[cfg(not(feature = "nix"))]
+        Some("nix") => Err(invalid(
+            path,
+            "nix manifests need the `nix` feature of cosutils".to_string(),
+        )),
+        _ => Err(invalid(
+            path,
+            "expected a `.toml` or `.nix` file".to_string(),
+        )),
+    }
+}
+
+/// The format of a source file, by its extension
+fn source_format(path: &Path) -> &'static str {
+    format_of_extension(path).unwrap_or("text")
+}
+
+/// Remove `.` and `..` components without touching the file system, as
+/// targets usually do not exist yet
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Resolves the entries of a manifest into a deployment plan
+struct Resolver<'a, 'py> {
+    /// The path of the manifest
+    manifest: &'a Path,
+    /// The directory sources are relative to
+    dir: &'a Path,
+    home: Option<PathBuf>,
+    vars: Bound<'py, PyDict>,
+}
+
+impl<'py> Resolver<'_, 'py> {
+    /// Get an optional entry of a table of the manifest, of type `T`
+    fn get<T: FromPyObject<'py>>(
+        &self,
+        table: &Bound<'py, PyDict>,
+        key: &str,
+        at: &str,
+        expected: &str,
+    ) -> PyResult<Option<T>> {
+        match table.get_item(key)? {
+            Some(value) if !value.is_none() => {
+                Ok(Some(value.extract::<T>().map_err(|_| {
+                    invalid(
+                        self.manifest,
+                        format!("`{}{}` must be {}", at, key, expected),
+                    )
+                })?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Substitute the `{{ name }}` placeholders of a template
+    fn render(
+        &self,
+        template: &str,
+        vars: &Bound<'py, PyDict>,
+        at: &str,
+    ) -> PyResult<String> {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            rendered.push_str(&rest[..start]);
+            let name = rest[start + 2..start + end].trim();
+            let value = vars.get_item(name)?.ok_or_else(|| {
+                invalid(
+                    self.manifest,
+                    format!("`{}` uses the undefined variable `{}`", at, name),
+                )
+            })?;
+            rendered.push_str(&value.str()?.to_string_lossy());
+            rest = &rest[start + end + 2..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    /// Resolve a path the way the shell does for `~`, or relative to `base`
+    fn resolve(&self, path: &str, base: &Path, at: &str) -> PyResult<PathBuf> {
+        let home = || {
+            self.home.clone().ok_or_else(|| {
+                invalid(
+                    self.manifest,
+                    format!(
+                        "`{}` refers to `~`, but neither HOME nor USERPROFILE \
+                        is set",
+                        at
+                    ),
+                )
+            })
+        };
+        // Manifests are shared across platforms, so `\` separates components
+        // everywhere. Windows already accepts both separators, and turning
+        // `\\server\share` into `//server/share` would lose the UNC prefix
+        let path = if cfg!(windows) {
+            path.to_string()
+        } else {
+            path.replace('\\', "/")
+        };
+        let resolved = if path == "~" {
+            home()?
+        } else if let Some(rest) = path
+            .strip_prefix('~')
+            .and_then(|p| p.strip_prefix(is_separator))
+        {
+            home()?.join(rest)
+        } else {
+            base.join(path)
+        };
+        Ok(normalize(&resolved))
+    }
+
+    fn target(
+        &self,
+        py: Python<'py>,
+        entry: &Bound<'py, PyAny>,
+        root: &Path,
+        at: &str,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let entry = entry.downcast::<PyDict>().map_err(|_| {
+            invalid(self.manifest, format!("`{}` must be a table", at))
+        })?;
+        let field = format!("{}.", at);
+        let source = self
+            .get::<String>(entry, "source", &field, "a string")?
+            .ok_or_else(|| {
+            invalid(self.manifest, format!("`{}` has no `source`", at))
+        })?;
+        let target = self
+            .get::<String>(entry, "target", &field, "a string")?
+            .ok_or_else(|| {
+            invalid(self.manifest, format!("`{}` has no `target`", at))
+        })?;
+        let template = self
+            .get::<bool>(entry, "template", &field, "a boolean")?
+            .unwrap_or(false);
+
+        let vars = self.vars.copy()?;
+        if let Some(own) =
+            self.get::<Bound<PyDict>>(entry, "vars", &field, "a table")?
+        {
+            vars.update(own.as_mapping())?;
+        }
+
+        let source_at = format!("{}source", field);
+        let source = self.render(&source, &vars, &source_at)?;
+        let source = self.resolve(&source, self.dir, &source_at)?;
+        if !source.exists() {
+            return Err(invalid(
+                self.manifest,
+                format!(
+                    "source {} of `{}` does not exist",
+                    source.display(),
+                    at
+                ),
+            ));
+        }
+        let target_at = format!("{}target", field);
+        let target = self.render(&target, &vars, &target_at)?;
+        let target = self.resolve(&target, root, &target_at)?;
+
+        let from = source_format(&source);
+        let format = self
+            .get::<String>(entry, "format", &field, "a string")?
+            .unwrap_or_else(|| from.to_string());
+
+        let resolved = PyDict::new(py);
+        resolved.set_item("source", source.to_string_lossy())?;
+        resolved.set_item("source_format", from)?;
+        resolved.set_item("target", target.to_string_lossy())?;
+        resolved.set_item("convert", format != from)?;
+        resolved.set_item("format", format)?;
+        resolved.set_item("template", template)?;
+        resolved.set_item("vars", vars)?;
+        Ok(resolved)
+    }
+}
+
+/// Load a manifest of config files and resolve it into a deployment plan.
+///
+/// A manifest is either a TOML file, or a nix file evaluating to an attrset,
+/// mapping source files to the targets they are deployed to:
+///
+/// ```toml
+/// root = "~"                      # where relative targets are deployed
+///
+/// [vars]                          # variables available to templates
+/// host = "laptop"
+///
+/// [[targets]]
+/// source = "git/config.nix"       # relative to the manifest
+/// target = ".config/git/{{ host }}.json"
+/// format = "jsonc"                # convert to this format, if different
+/// template = true                 # render the source as a template
+/// vars = { user = "me" }          # override variables for this target
+/// ```
+///
+/// `{{ name }}` placeholders of sources and targets are substituted with the
+/// variables, and paths starting with `~` are relative to the home
+/// directory, `HOME` or else `USERPROFILE`. Only the plan is resolved,
+/// sources are neither converted nor rendered.
+///
+/// Paths of the manifest may use `/` or `\` as separators on every
+/// platform, so one manifest serves Windows and Unix machines alike, and the
+/// resolved paths use the separators of the platform. Drive letters and UNC
+/// paths like `\\server\share` are absolute on Windows only.
+///
+/// TOML manifests are parsed the same way under every profile, TOML having no
+/// irregularity that a strict mode would reject, see `profile`.
+///
+/// Args:
+///   - path (str): The path to the manifest, a `.toml` or `.nix` file.
+///   - case_sensitive (bool | None): Whether targets differing only by case
+///                                   are distinct files, when checking that
+///                                   no two sources are deployed to the same
+///                                   target. `None` assumes the file systems
+///                                   of the platform, case-insensitive on
+///                                   Windows and macOS.
+///
+/// Returns:
+///   - _Plan: The `manifest` path, the `root` of the targets, the global
+///            `vars`, and the `targets` in order. Each target has the
+///            absolute `source` and `target` paths, the `source_format`
+///            detected from the extension of the source, the `format` to
+///            deploy and whether it needs a conversion (`convert`), whether
+///            it is a `template`, and the `vars` available to it.
+///
+/// Raises:
+///   - IOError: If the manifest cannot be read.
+///   - ParseError: If the manifest cannot be parsed, is malformed, uses
+///                 undefined variables, refers to missing sources, or
+///                 deploys several sources to the same target.
+///   - EvaluationError: If a nix manifest cannot be evaluated.
+///
+/// Example:
+/// ```python
+/// >>> plan = load("dotfiles/cosutils.toml")
+/// >>> plan["targets"][0]["target"]
+/// '/home/me/.config/git/laptop.json'
+/// >>> plan["targets"][0]["convert"]
+/// True
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, case_sensitive = None))]
+pub fn load(
+    py: Python<'_>,
+    path: String,
+    case_sensitive: Option<bool>,
+) -> PyResult<PyObject> {
+    let case_sensitive =
+        case_sensitive.unwrap_or(!cfg!(any(windows, target_os = "macos")));
+    let manifest = normalize(&env::current_dir()?.join(path));
+    let dir = manifest.parent().unwrap_or(Path::new("/"));
+    let content = read_manifest(py, &manifest)?;
+    let content = content.downcast::<PyDict>().map_err(|_| {
+        invalid(&manifest, "the manifest must be a table".to_string())
+    })?;
+
+    let mut resolver = Resolver {
+        manifest: &manifest,
+        dir,
+        home: env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(PathBuf::from),
+        vars: PyDict::new(py),
+    };
+    if let Some(vars) =
+        resolver.get::<Bound<PyDict>>(content, "vars", "", "a table")?
+    {
+        resolver.vars = vars.copy()?;
+    }
+    let root = resolver
+        .get::<String>(content, "root", "", "a string")?
+        .unwrap_or_else(|| "~".to_string());
+    let root = resolver.resolve(&root, dir, "root")?;
+
+    let targets = resolver
+        .get::<Bound<PyList>>(content, "targets", "", "a list")?
+        .unwrap_or_else(|| PyList::empty(py));
+    let mut deployed = HashMap::<String, usize>::new();
+    let resolved = PyList::empty(py);
+    for (i, entry) in targets.iter().enumerate() {
+        let at = format!("targets[{}]", i);
+        let target = resolver.target(py, &entry, &root, &at)?;
+        let path = target.as_any().get_item("target")?.extract::<PathBuf>()?;
+        let key = path.to_string_lossy();
+        let key = if case_sensitive {
+            key.into_owned()
+        } else {
+            key.to_lowercase()
+        };
+        if let Some(first) = deployed.insert(key, i) {
+            return Err(coded_err::<ParseError>(
+                ErrorCode::ManifestConflict,
+                format!(
+                    "Invalid manifest {}: `targets[{}]` and `{}` are both \
+                    deployed to {}",
+                    manifest.display(),
+                    first,
+                    at,
+                    path.display()
+                ),
+            ));
+        }
+        resolved.append(target)?;
+    }
+
+    let plan = PyDict::new(py);
+    plan.set_item("manifest", manifest.to_string_lossy())?;
+    plan.set_item("root", root.to_string_lossy())?;
+    plan.set_item("vars", &resolver.vars)?;
+    plan.set_item("targets", resolved)?;
+    Ok(plan.into_any().unbind())
+}
@@ -1,3 +1,27 @@
+pub mod anonymize;
+pub mod codegen;
+pub mod completions;
+pub mod detect;
+pub mod diagnostics;
+pub mod explain;
+pub mod floats;
+pub mod formats;
+pub mod hash;
+pub mod hook;
+#[cfg(feature = "jsonc")]
 pub mod jsonc;
+#[cfg(any(feature = "jsonc", feature = "nix"))]
+pub mod managed;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+#[cfg(feature = "nix")]
 pub mod nix;
+pub mod overflow;
+pub mod profile;
+pub mod rekey;
+pub mod render;
+pub mod schema;
+pub mod testing;
+pub mod threads;
+pub mod transform;
 pub mod utils;
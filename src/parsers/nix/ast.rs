@@ -0,0 +1,547 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Attr, Expr, HasEntry};
+use rnix::{NodeOrToken, SyntaxElement, SyntaxNode};
+use serde_json::{json, Value as JsonValue};
+
+use super::limits::depth_limit;
+use super::{check_nesting, parse_root};
+use crate::parsers::utils::read_file;
+
+/// Finds the line of byte offsets of a source
+pub(super) struct Lines(Vec<usize>);
+
+impl Lines {
+    pub(super) fn new(content: &str) -> Self {
+        let starts = content.match_indices('\n').map(|(i, _)| i + 1);
+        Self([0].into_iter().chain(starts).collect())
+    }
+
+    /// The 1-based line of an offset
+    pub(super) fn of(&self, offset: usize) -> usize {
+        self.0.partition_point(|&start| start <= offset)
+    }
+
+    /// The 1-based column of an offset, in bytes
+    pub(super) fn column(&self, offset: usize) -> usize {
+        offset - self.0[self.of(offset) - 1] + 1
+    }
+}
+
+/// The name of a static attribute, `None` for interpolated ones
+pub(super) fn attr_name(attr: &Attr) -> Option<String> {
+    match attr {
+        Attr::Ident(ident) => ident.ident_token().map(|t| t.to_string()),
+        Attr::Str(s) => match s.normalized_parts().as_slice() {
+            [] => Some(String::new()),
+            [ast::InterpolPart::Literal(name)] => Some(name.clone()),
+            _ => None,
+        },
+        Attr::Dynamic(_) => None,
+    }
+}
+
+/// The names of the attributes of an attrpath, `None` if one is
+/// interpolated
+pub(super) fn attrpath_names(
+    attrpath: Option<ast::Attrpath>,
+) -> Option<Vec<String>> {
+    attrpath?.attrs().map(|a| attr_name(&a)).collect()
+}
+
+/// The name of the function applied, through curried applications, either
+/// a variable or the last attribute selected, like `fetchurl` for
+/// `pkgs.fetchurl { ... }`
+pub(super) fn function_name(apply: &ast::Apply) -> Option<String> {
+    let mut function = apply.lambda();
+    while let Some(Expr::Apply(inner)) = &function {
+        function = inner.lambda();
+    }
+    match function? {
+        Expr::Ident(ident) => ident.ident_token().map(|t| t.to_string()),
+        Expr::Select(select) => select
+            .attrpath()?
+            .attrs()
+            .last()
+            .and_then(|a| attr_name(&a)),
+        _ => None,
+    }
+}
+
+/// The values bound to `path` in an expression, looking into nested
+/// attrsets and through functions, `let` and `with`
+pub(super) fn lookup(
+    expr: Option<Expr>,
+    path: &[String],
+    found: &mut Vec<Expr>,
+) {
+    let Some(expr) = expr else {
+        return;
+    };
+    if path.is_empty() {
+        found.push(expr);
+        return;
+    }
+    match expr {
+        Expr::AttrSet(set) => {
+            for binding in set.attrpath_values() {
+                let Some(names) = attrpath_names(binding.attrpath()) else {
+                    continue;
+                };
+                let matched =
+                    names.iter().zip(path).take_while(|(a, b)| a == b).count();
+                if matched == names.len() {
+                    lookup(binding.value(), &path[matched..], found);
+                }
+            }
+        }
+        Expr::Lambda(lambda) => lookup(lambda.body(), path, found),
+        Expr::LetIn(let_in) => lookup(let_in.body(), path, found),
+        Expr::With(with) => lookup(with.body(), path, found),
+        Expr::Paren(paren) => lookup(paren.expr(), path, found),
+        _ => {}
+    }
+}
+
+/// Convert a node of the syntax tree, with its tokens if `tokens`
+fn convert(
+    py: Python<'_>,
+    element: SyntaxElement,
+    lines: &Lines,
+    tokens: bool,
+) -> PyResult<PyObject> {
+    let range = element.text_range();
+    let dict = PyDict::new(py);
+    dict.set_item("kind", format!("{:?}", element.kind()))?;
+    dict.set_item("start", usize::from(range.start()))?;
+    dict.set_item("end", usize::from(range.end()))?;
+    dict.set_item("line", lines.of(usize::from(range.start())))?;
+    let children = match element {
+        NodeOrToken::Node(node) => {
+            dict.set_item("text", node.text().to_string())?;
+            node.children_with_tokens()
+                .filter(|c| tokens || c.as_node().is_some())
+                .map(|c| convert(py, c, lines, tokens))
+                .collect::<PyResult<Vec<_>>>()?
+        }
+        NodeOrToken::Token(token) => {
+            dict.set_item("text", token.text())?;
+            Vec::new()
+        }
+    };
+    dict.set_item("children", children)?;
+    Ok(dict.into_any().unbind())
+}
+
+/// Convert a node of the syntax tree to JSON, like `convert`
+fn convert_json(
+    element: SyntaxElement,
+    lines: &Lines,
+    tokens: bool,
+) -> JsonValue {
+    let range = element.text_range();
+    let (text, children) = match element {
+        NodeOrToken::Node(node) => (
+            node.text().to_string(),
+            node.children_with_tokens()
+                .filter(|c| tokens || c.as_node().is_some())
+                .map(|c| convert_json(c, lines, tokens))
+                .collect(),
+        ),
+        NodeOrToken::Token(token) => (token.text().to_string(), Vec::new()),
+    };
+    json!({
+        "kind": format!("{:?}", element.kind()),
+        "start": usize::from(range.start()),
+        "end": usize::from(range.end()),
+        "line": lines.of(usize::from(range.start())),
+        "text": text,
+        "children": children,
+    })
+}
+
+fn parse_to_python(
+    py: Python<'_>,
+    content: &str,
+    origin: Option<&str>,
+    tokens: bool,
+    max_depth: Option<usize>,
+) -> PyResult<PyObject> {
+    let root = parse_root(content, origin)?;
+    if let Some(max_depth) = max_depth {
+        let max_depth = depth_limit(max_depth, "Nesting limit")?;
+        check_nesting(content, origin, &root, max_depth)?;
+    }
+    let lines = Lines::new(content);
+    convert(py, NodeOrToken::Node(root.syntax().clone()), &lines, tokens)
+}
+
+/// Parse a nix expression into its syntax tree, without evaluating it.
+///
+/// Every node of the tree is a dict with its `kind`, named like the
+/// `SyntaxKind` of rnix (`NODE_ATTR_SET`, `TOKEN_IDENT`, ...), its `start`
+/// and `end` byte offsets, the `line` it starts on, its source `text` and
+/// its `children`. The root is a `NODE_ROOT` node.
+///
+/// Args:
+///   - expr (str): The nix expression to parse.
+///   - tokens (bool): Also list the tokens of each node among its children,
+///                    whitespace and comments included, so the texts of the
+///                    children of a node add up to the text of the node.
+///   - max_depth (int): Refuse code nesting expressions deeper than this,
+///                      for untrusted input. The parser of rnix has its own
+///                      fixed limit, which this can only lower.
+///
+/// Returns:
+///   - _NixNode: The root of the syntax tree.
+///
+/// Raises:
+///   - ValueError: If the nesting limit is not positive.
+///   - ParseError: If the nix code cannot be parsed, or is nested too
+///                 deeply.
+///
+/// Example:
+/// ```python
+/// >>> root = parse("{ a = 1; }")
+/// >>> [c["kind"] for c in root["children"][0]["children"]]
+/// ['NODE_ATTRPATH_VALUE']
+/// ```
+#[pyfunction]
+#[pyo3(signature = (expr, tokens = false, max_depth = None))]
+pub fn parse(
+    py: Python<'_>,
+    expr: &str,
+    tokens: bool,
+    max_depth: Option<usize>,
+) -> PyResult<PyObject> {
+    parse_to_python(py, expr, None, tokens, max_depth)
+}
+
+/// Parse a nix file into its syntax tree, without evaluating it, see
+/// `parse`.
+///
+/// Args:
+///   - path (str): The path to the nix file.
+///   - tokens (bool): Also list the tokens of each node among its children.
+///   - max_depth (int): Refuse code nesting expressions deeper than this.
+///
+/// Returns:
+///   - _NixNode: The root of the syntax tree.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ValueError: If the nesting limit is not positive.
+///   - ParseError: If the nix file cannot be parsed, or is nested too
+///                 deeply.
+#[pyfunction]
+#[pyo3(signature = (path, tokens = false, max_depth = None))]
+pub fn parse_file(
+    py: Python<'_>,
+    path: String,
+    tokens: bool,
+    max_depth: Option<usize>,
+) -> PyResult<PyObject> {
+    let path = PathBuf::from(path);
+    let content = read_file(&path)?;
+    let origin = path.to_string_lossy();
+    parse_to_python(py, &content, Some(&origin), tokens, max_depth)
+}
+
+/// Parse a nix expression into its concrete syntax tree as a JSON string,
+/// without evaluating it.
+///
+/// The tree is the one `parse` returns, serialized in Rust without building
+/// Python objects, for tools in other languages and snapshots of the syntax
+/// of nix sources. Tokens are listed by default, whitespace and comments
+/// included, so the source can be rebuilt from the texts of the tokens.
+/// Object keys are sorted, so the same source always gives the same JSON.
+///
+/// Args:
+///   - expr (str): The nix expression to parse.
+///   - tokens (bool): List the tokens of each node among its children.
+///   - max_depth (int): Refuse code nesting expressions deeper than this,
+///                      see `parse`.
+///
+/// Returns:
+///   - str: The root of the syntax tree as compact JSON.
+///
+/// Raises:
+///   - ValueError: If the nesting limit is not positive.
+///   - ParseError: If the nix code cannot be parsed, or is nested too
+///                 deeply.
+///
+/// Example:
+/// ```python
+/// >>> parse_to_json("1 # one", tokens=False)
+/// '{"children":[{"children":[],"end":1,"kind":"NODE_LITERAL","line":1,"start":0,"text":"1"}],"end":7,"kind":"NODE_ROOT","line":1,"start":0,"text":"1 # one"}'
+/// >>> root = orjson.loads(parse_to_json("1 # one"))
+/// >>> [t["kind"] for t in root["children"]]
+/// ['NODE_LITERAL', 'TOKEN_WHITESPACE', 'TOKEN_COMMENT']
+/// ```
+#[pyfunction]
+#[pyo3(signature = (expr, tokens = true, max_depth = None))]
+pub fn parse_to_json(
+    expr: &str,
+    tokens: bool,
+    max_depth: Option<usize>,
+) -> PyResult<String> {
+    let root = parse_root(expr, None)?;
+    if let Some(max_depth) = max_depth {
+        let max_depth = depth_limit(max_depth, "Nesting limit")?;
+        check_nesting(expr, None, &root, max_depth)?;
+    }
+    let lines = Lines::new(expr);
+    let tree =
+        convert_json(NodeOrToken::Node(root.syntax().clone()), &lines, tokens);
+    Ok(tree.to_string())
+}
+
+/// A node of the syntax tree of a nix file or expression, produced without
+/// evaluating it.
+///
+/// Nodes know their `kind`, named like the `SyntaxKind` of rnix
+/// (`NODE_APPLY`, `NODE_ATTRPATH_VALUE`, ...), their position and their
+/// source `text`, and can be searched for the nodes they contain.
+///
+/// Like the values of evaluations, nodes can only be used on the thread that
+/// parsed them.
+///
+/// Args:
+///   - expr (str): The nix expression to parse.
+///
+/// Raises:
+///   - ParseError: If the nix code cannot be parsed.
+///
+/// Example:
+/// ```python
+/// >>> root = NixAst('{ src = fetchurl { url = "x"; }; imports = [ ]; }')
+/// >>> [(c.text, c.line, c.column) for c in root.calls("fetchurl")]
+/// [('fetchurl { url = "x"; }', 1, 9)]
+/// >>> [v.kind for v in root.get("imports")]
+/// ['NODE_LIST']
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.nix", unsendable)]
+pub struct NixAst {
+    node: SyntaxNode,
+    lines: Rc<Lines>,
+}
+
+impl NixAst {
+    fn parsed(content: &str, origin: Option<&str>) -> PyResult<Self> {
+        let root = parse_root(content, origin)?;
+        Ok(Self {
+            node: root.syntax().clone(),
+            lines: Rc::new(Lines::new(content)),
+        })
+    }
+
+    fn wrap(&self, node: SyntaxNode) -> Self {
+        Self {
+            node,
+            lines: Rc::clone(&self.lines),
+        }
+    }
+
+    fn start(&self) -> usize {
+        usize::from(self.node.text_range().start())
+    }
+}
+
+#[pymethods]
+impl NixAst {
+    #[new]
+    fn new(expr: &str) -> PyResult<Self> {
+        Self::parsed(expr, None)
+    }
+
+    /// Parse a nix file.
+    ///
+    /// Args:
+    ///   - path (str): The path to the nix file.
+    ///
+    /// Returns:
+    ///   - NixAst: The root of the syntax tree.
+    ///
+    /// Raises:
+    ///   - IOError: If the file cannot be read.
+    ///   - ParseError: If the nix file cannot be parsed.
+    #[staticmethod]
+    fn load(path: String) -> PyResult<Self> {
+        let path = PathBuf::from(path);
+        let content = read_file(&path)?;
+        Self::parsed(&content, Some(&path.to_string_lossy()))
+    }
+
+    #[getter]
+    fn kind(&self) -> String {
+        format!("{:?}", self.node.kind())
+    }
+
+    /// The byte offset the node starts at
+    #[getter(start)]
+    fn start_offset(&self) -> usize {
+        self.start()
+    }
+
+    /// The byte offset the node ends at
+    #[getter]
+    fn end(&self) -> usize {
+        usize::from(self.node.text_range().end())
+    }
+
+    /// The 1-based line the node starts on
+    #[getter]
+    fn line(&self) -> usize {
+        self.lines.of(self.start())
+    }
+
+    /// The 1-based column the node starts at, in bytes
+    #[getter]
+    fn column(&self) -> usize {
+        self.lines.column(self.start())
+    }
+
+    #[getter]
+    fn text(&self) -> String {
+        self.node.text().to_string()
+    }
+
+    #[getter]
+    fn parent(&self) -> Option<Self> {
+        self.node.parent().map(|p| self.wrap(p))
+    }
+
+    #[getter]
+    fn children(&self) -> Vec<Self> {
+        self.node.children().map(|c| self.wrap(c)).collect()
+    }
+
+    /// List the node and all the nodes it contains, in source order.
+    ///
+    /// Returns:
+    ///   - list[NixAst]: The nodes, each one before its children.
+    fn walk(&self) -> Vec<Self> {
+        self.node.descendants().map(|n| self.wrap(n)).collect()
+    }
+
+    /// Find the nodes matching all the given filters, among the node and all
+    /// the nodes it contains.
+    ///
+    /// Args:
+    ///   - kind (str): The kind of the nodes, like `NODE_LAMBDA`.
+    ///   - text (str): The source text of the nodes.
+    ///
+    /// Returns:
+    ///   - list[NixAst]: The matching nodes, in source order.
+    #[pyo3(signature = (kind = None, text = None))]
+    fn find(&self, kind: Option<&str>, text: Option<&str>) -> Vec<Self> {
+        self.node
+            .descendants()
+            .filter(|n| kind.is_none_or(|k| format!("{:?}", n.kind()) == k))
+            .filter(|n| text.is_none_or(|t| n.text() == t))
+            .map(|n| self.wrap(n))
+            .collect()
+    }
+
+    /// Find the calls of a function, by the name of the variable or of the
+    /// last attribute it is selected by, so `calls("fetchurl")` finds both
+    /// `fetchurl { ... }` and `pkgs.fetchurl { ... }`.
+    ///
+    /// Args:
+    ///   - name (str): The name of the function.
+    ///
+    /// Returns:
+    ///   - list[NixAst]: The `NODE_APPLY` nodes of the calls, with all their
+    ///                   arguments for curried functions.
+    fn calls(&self, name: &str) -> Vec<Self> {
+        self.node
+            .descendants()
+            .filter_map(ast::Apply::cast)
+            // Curried calls are only reported once, by their outermost node
+            .filter(|apply| {
+                !apply
+                    .syntax()
+                    .parent()
+                    .and_then(ast::Apply::cast)
+                    .is_some_and(|p| {
+                        p.lambda().is_some_and(|l| l.syntax() == apply.syntax())
+                    })
+            })
+            .filter(|apply| function_name(apply).as_deref() == Some(name))
+            .map(|apply| self.wrap(apply.syntax().clone()))
+            .collect()
+    }
+
+    /// Find the bindings of an attribute path anywhere in the node, like
+    /// every `imports = [ ... ];` for `"imports"`.
+    ///
+    /// Args:
+    ///   - attrpath (str): The attribute path, as written in the bindings.
+    ///
+    /// Returns:
+    ///   - list[NixAst]: The `NODE_ATTRPATH_VALUE` nodes of the bindings.
+    fn bindings(&self, attrpath: &str) -> Vec<Self> {
+        let path = attrpath.split('.').map(str::to_string).collect::<Vec<_>>();
+        self.node
+            .descendants()
+            .filter_map(ast::AttrpathValue::cast)
+            .filter(|b| attrpath_names(b.attrpath()).as_ref() == Some(&path))
+            .map(|b| self.wrap(b.syntax().clone()))
+            .collect()
+    }
+
+    /// Look up the values bound to an attribute path, from the attrset the
+    /// node evaluates to, so `{ a.b = 1; }` and `{ a = { b = 1; }; }` both
+    /// bind `a.b`. Functions, `let` and `with` are looked through, like the
+    /// body of a module.
+    ///
+    /// Args:
+    ///   - attrpath (str): The attribute path, like `services.foo.enable`.
+    ///
+    /// Returns:
+    ///   - list[NixAst]: The nodes of the values, empty if the path is not
+    ///                   bound.
+    fn get(&self, attrpath: &str) -> Vec<Self> {
+        let path = attrpath.split('.').map(str::to_string).collect::<Vec<_>>();
+        let expr = match ast::Root::cast(self.node.clone()) {
+            Some(root) => root.expr(),
+            None => Expr::cast(self.node.clone()),
+        };
+        let mut found = Vec::new();
+        lookup(expr, &path, &mut found);
+        found
+            .into_iter()
+            .map(|e| self.wrap(e.syntax().clone()))
+            .collect()
+    }
+
+    /// Convert the node to dicts, like `parse`.
+    ///
+    /// Args:
+    ///   - tokens (bool): Also list the tokens of each node among its
+    ///                    children.
+    ///
+    /// Returns:
+    ///   - _NixNode: The node and all the nodes it contains.
+    #[pyo3(signature = (tokens = false))]
+    fn to_dict(&self, py: Python<'_>, tokens: bool) -> PyResult<PyObject> {
+        convert(
+            py,
+            NodeOrToken::Node(self.node.clone()),
+            &self.lines,
+            tokens,
+        )
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<NixAst {} {}:{}>", self.kind(), self.line(), self.column())
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
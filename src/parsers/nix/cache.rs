@@ -0,0 +1,344 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use bytes::Bytes;
+use pyo3::prelude::*;
+use serde_json::{json, Value as JsonValue};
+use tvix_eval::{EvalIO, FileType};
+
+/// How an evaluation accessed a path
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    List,
+    Exists,
+}
+
+impl Access {
+    fn name(self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::List => "list",
+            Access::Exists => "exists",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "read" => Some(Access::Read),
+            "list" => Some(Access::List),
+            "exists" => Some(Access::Exists),
+            _ => None,
+        }
+    }
+
+    /// Summarize what an access to `path` currently observes, `None` if the
+    /// path cannot be accessed
+    fn fingerprint(self, path: &Path) -> Option<String> {
+        match self {
+            Access::Read => fs::read(path)
+                .ok()
+                .map(|c| blake3::hash(&c).to_hex().to_string()),
+            Access::List => {
+                let mut entries = fs::read_dir(path)
+                    .ok()?
+                    .map(|e| {
+                        let e = e.ok()?;
+                        let kind = e.file_type().ok()?;
+                        Some(format!(
+                            "{:?}:{}",
+                            kind,
+                            e.file_name().to_string_lossy()
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                entries.sort_unstable();
+                Some(
+                    blake3::hash(entries.join("\n").as_bytes())
+                        .to_hex()
+                        .to_string(),
+                )
+            }
+            Access::Exists => Some(path.exists().to_string()),
+        }
+    }
+}
+
+/// Wraps the IO of an evaluation to record the paths it accessed, so cached
+/// results can be invalidated when imported files change
+pub(super) struct RecordingIO {
+    inner: Rc<dyn EvalIO>,
+    accessed: RefCell<Vec<(PathBuf, Access)>>,
+    /// The paths the sandbox of the evaluation denied access to
+    denied: RefCell<Vec<PathBuf>>,
+}
+
+impl RecordingIO {
+    pub fn new(inner: Rc<dyn EvalIO>) -> Self {
+        Self {
+            inner,
+            accessed: RefCell::default(),
+            denied: RefCell::default(),
+        }
+    }
+
+    fn record<T>(
+        &self,
+        path: &Path,
+        access: Access,
+        result: io::Result<T>,
+    ) -> io::Result<T> {
+        self.accessed
+            .borrow_mut()
+            .push((path.to_path_buf(), access));
+        let is_denied =
+            |e: &io::Error| e.kind() == io::ErrorKind::PermissionDenied;
+        if result.as_ref().is_err_and(is_denied) {
+            self.denied.borrow_mut().push(path.to_path_buf());
+        }
+        result
+    }
+
+    /// The paths accessed so far, sorted, with how they were accessed and
+    /// whether the access was denied
+    pub fn accesses(&self) -> Vec<(PathBuf, &'static str, bool)> {
+        let mut accessed = self.accessed.borrow().clone();
+        accessed.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        accessed.dedup();
+        let denied = self.denied.borrow();
+        accessed
+            .into_iter()
+            .map(|(path, access)| {
+                let is_denied = denied.contains(&path);
+                (path, access.name(), is_denied)
+            })
+            .collect()
+    }
+
+    /// The paths accessed so far, with what they looked like
+    fn dependencies(&self) -> Vec<Dependency> {
+        let mut accessed = self.accessed.borrow().clone();
+        accessed.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        accessed.dedup();
+        accessed
+            .into_iter()
+            .map(|(path, access)| Dependency {
+                fingerprint: access.fingerprint(&path),
+                path,
+                access,
+            })
+            .collect()
+    }
+}
+
+impl EvalIO for RecordingIO {
+    fn path_exists(&self, path: &Path) -> io::Result<bool> {
+        self.record(path, Access::Exists, self.inner.path_exists(path))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        self.record(path, Access::Read, self.inner.open(path))
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<FileType> {
+        self.record(path, Access::Exists, self.inner.file_type(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(Bytes, FileType)>> {
+        self.record(path, Access::List, self.inner.read_dir(path))
+    }
+
+    fn import_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.record(path, Access::Read, self.inner.import_path(path))
+    }
+
+    fn store_dir(&self) -> Option<String> {
+        self.inner.store_dir()
+    }
+}
+
+/// A path a cached result depends on
+#[derive(Clone)]
+struct Dependency {
+    path: PathBuf,
+    access: Access,
+    fingerprint: Option<String>,
+}
+
+impl Dependency {
+    fn is_fresh(&self) -> bool {
+        self.access.fingerprint(&self.path) == self.fingerprint
+    }
+
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "path": self.path.to_string_lossy(),
+            "access": self.access.name(),
+            "fingerprint": self.fingerprint,
+        })
+    }
+
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        Some(Self {
+            path: PathBuf::from(value.get("path")?.as_str()?),
+            access: Access::from_name(value.get("access")?.as_str()?)?,
+            fingerprint: value.get("fingerprint")?.as_str().map(str::to_string),
+        })
+    }
+}
+
+/// A converted result, along with the paths it was computed from
+#[derive(Clone)]
+struct Entry {
+    value: JsonValue,
+    dependencies: Vec<Dependency>,
+}
+
+/// The most results kept in memory, the least recently used are evicted
+/// past it
+const MEMORY_ENTRIES: usize = 256;
+
+/// Where a result cached in memory belongs, the directory of its cache and
+/// its key
+type MemoryKey = (Option<PathBuf>, String);
+
+/// Results cached in memory, shared by all evaluators of the process, with
+/// when they were last used
+#[derive(Default)]
+struct Memory {
+    entries: HashMap<MemoryKey, (Entry, u64)>,
+    uses: u64,
+}
+
+impl Memory {
+    fn get(&mut self, key: &MemoryKey) -> Option<Entry> {
+        self.uses += 1;
+        let (entry, used) = self.entries.get_mut(key)?;
+        *used = self.uses;
+        Some(entry.clone())
+    }
+
+    fn insert(&mut self, key: MemoryKey, entry: Entry) {
+        self.uses += 1;
+        self.entries.insert(key, (entry, self.uses));
+        if self.entries.len() > MEMORY_ENTRIES {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+static MEMORY: LazyLock<Mutex<Memory>> = LazyLock::new(Default::default);
+
+/// Numbers temporary files, so that threads storing the same entry at once
+/// do not write to the same file
+static TEMPORARY: AtomicUsize = AtomicUsize::new(0);
+
+/// Where results of evaluations are cached, either `True` to cache them in
+/// memory, or a directory to also cache them on disk
+#[derive(FromPyObject)]
+pub enum CacheOption {
+    Enabled(bool),
+    Directory(PathBuf),
+}
+
+impl CacheOption {
+    pub fn into_cache(self) -> Option<Cache> {
+        match self {
+            CacheOption::Enabled(false) => None,
+            CacheOption::Enabled(true) => Some(Cache { dir: None }),
+            CacheOption::Directory(dir) => Some(Cache { dir: Some(dir) }),
+        }
+    }
+}
+
+/// A cache of evaluation results, keyed by the hash of everything the
+/// result depends on besides the files accessed during the evaluation
+#[derive(Clone)]
+pub struct Cache {
+    dir: Option<PathBuf>,
+}
+
+impl Cache {
+    /// The directory the cache is also stored in, if any
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Look up a result whose dependencies have not changed since
+    pub fn get(&self, key: &str) -> Option<JsonValue> {
+        let key = (self.dir.clone(), key.to_string());
+        let cached = MEMORY.lock().ok()?.get(&key);
+        let entry = cached.or_else(|| {
+            let file = self.dir.as_ref()?.join(&key.1);
+            let content = fs::read_to_string(file).ok()?;
+            let content = serde_json::from_str::<JsonValue>(&content).ok()?;
+            let dependencies = content
+                .get("dependencies")?
+                .as_array()?
+                .iter()
+                .map(Dependency::from_json)
+                .collect::<Option<Vec<_>>>()?;
+            Some(Entry {
+                value: content.get("value")?.clone(),
+                dependencies,
+            })
+        })?;
+
+        let fresh = entry.dependencies.iter().all(Dependency::is_fresh);
+        let value = fresh.then(|| entry.value.clone());
+        if let Ok(mut memory) = MEMORY.lock() {
+            if fresh {
+                memory.insert(key, entry);
+            } else {
+                memory.entries.remove(&key);
+            }
+        }
+        value
+    }
+
+    /// Store a result along with the paths accessed to compute it, failing
+    /// to write the cache to disk is not an error
+    pub fn put(&self, key: &str, value: JsonValue, io: &RecordingIO) {
+        let entry = Entry {
+            value,
+            dependencies: io.dependencies(),
+        };
+        if let Some(dir) = &self.dir {
+            let content = json!({
+                "value": entry.value,
+                "dependencies": entry
+                    .dependencies
+                    .iter()
+                    .map(Dependency::to_json)
+                    .collect::<Vec<_>>(),
+            });
+            // Write to a temporary file first, so concurrent readers never
+            // see a partial entry
+            let temporary = dir.join(format!(
+                "{}.{}.{}.tmp",
+                key,
+                std::process::id(),
+                TEMPORARY.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::create_dir_all(dir)
+                .and_then(|_| fs::write(&temporary, content.to_string()))
+                .and_then(|_| fs::rename(&temporary, dir.join(key)));
+        }
+        if let Ok(mut memory) = MEMORY.lock() {
+            memory.insert((self.dir.clone(), key.to_string()), entry);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+# Evaluate a locked flake like `call-flake.nix` of nix, with the sources of
+# the inputs already present on disk instead of being fetched
+{ lock, sources, root, output }:
+
+let
+  lockFile = builtins.fromJSON lock;
+  nodes = lockFile.nodes or { root = { }; };
+  rootName = lockFile.root or "root";
+
+  # `follows` are paths of input names starting at the root node
+  resolveInput = spec:
+    if builtins.isList spec then followPath rootName spec else spec;
+  followPath = nodeName: path:
+    if path == [ ] then
+      nodeName
+    else
+      followPath
+        (resolveInput nodes.${nodeName}.inputs.${builtins.head path})
+        (builtins.tail path);
+
+  allNodes = builtins.mapAttrs
+    (key: node:
+      let
+        isRoot = key == rootName;
+        sourceInfo = (if isRoot then { } else node.locked or { }) // {
+          outPath = if isRoot then root else sources.${key};
+        };
+        subdir = if isRoot then "" else node.locked.dir or "";
+        flakeDir =
+          sourceInfo.outPath + (if subdir == "" then "" else "/${subdir}");
+        flake = import (/. + flakeDir + "/flake.nix");
+        inputs = builtins.mapAttrs
+          (_: spec: allNodes.${resolveInput spec})
+          (node.inputs or { });
+        outputs = flake.outputs (inputs // { self = result; });
+        result = outputs // sourceInfo // {
+          inherit inputs outputs sourceInfo;
+          _type = "flake";
+        };
+      in
+      if node.flake or true then result else sourceInfo)
+    nodes;
+in
+builtins.foldl' (value: name: value.${name}) allNodes.${rootName} output
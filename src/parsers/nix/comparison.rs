@@ -0,0 +1,61 @@
+use pyo3::prelude::*;
+use pyo3::PyResult;
+use tvix_eval::{NixAttrs, Value as TvixValue};
+
+use super::evaluator::Evaluator;
+use crate::parsers::utils::{
+    coded_err, ErrorCode, EvaluationError, TryFromPyObject,
+};
+
+/// Compares its arguments with the operators of nix
+const COMPARE: &str = "{ a, b }: if a == b then 0 else if a < b then -1 else 1";
+
+/// Compare two values like nix does.
+///
+/// Both values are converted like `evals` args, and compared by the `==` and
+/// `<` operators of nix, so Python assertions conclude what nix would:
+/// integers equal floats of the same value, strings compare bytewise, lists
+/// compare element by element, and attrsets are deeply equal when they have
+/// the same names and equal values.
+///
+/// Args:
+///   - a (Any): The first value.
+///   - b (Any): The second value.
+///
+/// Returns:
+///   - int: `0` if the values are equal, `-1` if `a` is less than `b` and `1`
+///          if it is greater.
+///
+/// Raises:
+///   - ConversionError: If a value cannot be converted to a nix value.
+///   - EvaluationError: If the values differ but nix cannot order them, like
+///                      attrsets, booleans or values of different types.
+///
+/// Example:
+/// ```python
+/// >>> compare(1, 1.0), compare([1, "b"], [1, "a"]), compare("a", "b")
+/// (0, 1, -1)
+/// >>> compare({"a": [1]}, {"a": [1.0]})
+/// 0
+/// ```
+#[pyfunction]
+pub fn compare(a: &Bound<'_, PyAny>, b: &Bound<'_, PyAny>) -> PyResult<i64> {
+    let args = TvixValue::attrs(NixAttrs::from_iter([
+        ("a".to_string(), TvixValue::try_from_pyobject(a)?),
+        ("b".to_string(), TvixValue::try_from_pyobject(b)?),
+    ]));
+    let (value, _) = Evaluator::default().eval_expr(
+        COMPARE,
+        None,
+        None,
+        Some(args),
+        None,
+    )?;
+    match value {
+        TvixValue::Integer(ordering) => Ok(ordering),
+        value => Err(coded_err::<EvaluationError>(
+            ErrorCode::NixEvaluation,
+            format!("Comparison evaluated to a {}", value.type_of()),
+        )),
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Attr, Expr};
+use rnix::{SyntaxKind, SyntaxNode};
+
+use super::parse_root;
+use crate::parsers::utils::read_file;
+
+/// Whether an expression opens a new level of nesting
+fn is_nesting(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::NODE_ATTR_SET
+            | SyntaxKind::NODE_LIST
+            | SyntaxKind::NODE_LET_IN
+            | SyntaxKind::NODE_LEGACY_LET
+            | SyntaxKind::NODE_LAMBDA
+            | SyntaxKind::NODE_WITH
+    )
+}
+
+fn max_depth(node: &SyntaxNode) -> usize {
+    let nested = node.children().map(|c| max_depth(&c)).max().unwrap_or(0);
+    nested + usize::from(is_nesting(node.kind()))
+}
+
+/// Whether an expression is `import`, `callPackage` or one of them selected
+/// from an attrset, like `pkgs.callPackage`
+pub(super) fn is_importer(expr: &Expr) -> bool {
+    let name = match expr {
+        Expr::Ident(ident) => ident.ident_token().map(|t| t.to_string()),
+        Expr::Select(select) => select
+            .attrpath()
+            .and_then(|p| p.attrs().last())
+            .and_then(|a| match a {
+                Attr::Ident(ident) => {
+                    ident.ident_token().map(|t| t.to_string())
+                }
+                _ => None,
+            }),
+        _ => None,
+    };
+    matches!(name.as_deref(), Some("import" | "callPackage"))
+}
+
+/// The paths imported by a node, by `import`, `callPackage` or listed in
+/// the `imports` of a module
+pub(super) fn imported(node: &SyntaxNode) -> Vec<String> {
+    let path = |expr: Option<Expr>| match expr {
+        Some(Expr::Path(path)) => Some(path.syntax().text().to_string()),
+        _ => None,
+    };
+    if let Some(apply) = ast::Apply::cast(node.clone()) {
+        if apply.lambda().is_some_and(|l| is_importer(&l)) {
+            return path(apply.argument()).into_iter().collect();
+        }
+    } else if let Some(binding) = ast::AttrpathValue::cast(node.clone()) {
+        let is_imports = binding
+            .attrpath()
+            .is_some_and(|p| p.syntax().text() == "imports");
+        if let (true, Some(Expr::List(list))) = (is_imports, binding.value()) {
+            return list.items().filter_map(|i| path(Some(i))).collect();
+        }
+    }
+    Vec::new()
+}
+
+/// Measure the complexity of a nix file or expression.
+///
+/// Only the syntax is inspected, nothing is evaluated, so imports are the
+/// paths written as the argument of `import` or `callPackage`, or listed in
+/// the `imports` of a module.
+///
+/// Args:
+///   - source (str | os.PathLike): The nix expression, or the path to the
+///                                 nix file. Plain strings are expressions,
+///                                 never paths.
+///
+/// Returns:
+///   - _Metrics: The `size` in bytes and the `lines` of the source, the
+///               number of `bindings` (including inherited ones), of
+///               `functions`, of `with_expressions` and of `let_expressions`,
+///               the `max_depth` of nested attrsets, lists, lets, functions
+///               and withs, the distinct `imports` as written, and their
+///               number as `import_fan_out`.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ParseError: If the nix code cannot be parsed.
+///
+/// Example:
+/// ```python
+/// >>> m = metrics("{ pkgs, ... }: { imports = [ ./a.nix ]; x = with pkgs; hello; }")
+/// >>> m["bindings"], m["functions"], m["with_expressions"], m["imports"]
+/// (2, 1, 1, ['./a.nix'])
+/// ```
+#[pyfunction]
+pub fn metrics(
+    py: Python<'_>,
+    source: &Bound<'_, PyAny>,
+) -> PyResult<PyObject> {
+    let (content, origin) = match source.downcast::<PyString>() {
+        Ok(content) => (content.to_str()?.to_string(), None),
+        Err(_) => {
+            let path = source.extract::<PathBuf>()?;
+            (read_file(&path)?, Some(path.to_string_lossy().into_owned()))
+        }
+    };
+
+    let root = parse_root(&content, origin.as_deref())?;
+    let root = root.syntax();
+    let (mut bindings, mut functions, mut withs, mut lets) = (0, 0, 0, 0);
+    let mut imports = BTreeSet::new();
+    for node in root.descendants() {
+        match node.kind() {
+            SyntaxKind::NODE_ATTRPATH_VALUE => bindings += 1,
+            SyntaxKind::NODE_INHERIT => {
+                bindings += ast::Inherit::cast(node.clone())
+                    .map_or(0, |i| i.attrs().count());
+            }
+            SyntaxKind::NODE_LAMBDA => functions += 1,
+            SyntaxKind::NODE_WITH => withs += 1,
+            SyntaxKind::NODE_LET_IN | SyntaxKind::NODE_LEGACY_LET => lets += 1,
+            _ => {}
+        }
+        imports.extend(imported(&node));
+    }
+
+    let metrics = PyDict::new(py);
+    metrics.set_item("size", content.len())?;
+    metrics.set_item("lines", content.lines().count())?;
+    metrics.set_item("bindings", bindings)?;
+    metrics.set_item("functions", functions)?;
+    metrics.set_item("with_expressions", withs)?;
+    metrics.set_item("let_expressions", lets)?;
+    metrics.set_item("max_depth", max_depth(&root))?;
+    metrics.set_item("import_fan_out", imports.len())?;
+    metrics.set_item("imports", imports.into_iter().collect::<Vec<_>>())?;
+    Ok(metrics.into_any().unbind())
+}
@@ -0,0 +1,407 @@
+use std::rc::Rc;
+use std::str::from_utf8;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyDict, PyFloat, PyInt, PyList, PyNone, PyString, PyTuple,
+};
+use pyo3::{PyObject, PyResult};
+use tvix_eval::{NixAttrs, NixList, Value as TvixValue};
+
+use super::evaluator::Context;
+use super::function::{NixFunction, NixThunk};
+use super::order::KeyOrder;
+use crate::into_pyany;
+use crate::parsers::floats::{FloatPolicy, NonFinite};
+use crate::parsers::hook::{hooked, unhooked};
+use crate::parsers::overflow::{self, narrowed, IntOverflow, Narrowed};
+use crate::parsers::schema::attrpath;
+use crate::parsers::utils::{
+    aggregated_err, coded_err, dict_key, ConversionError, ErrorCode,
+    TryFromPyObject, TryToPyObject,
+};
+
+/// The error of converting an infinite or NaN float under the `error` policy
+fn non_finite_err(f: f64) -> PyErr {
+    coded_err::<ConversionError>(
+        ErrorCode::NixConversion,
+        format!("Float {} is not finite", f),
+    )
+}
+
+/// How the Python numbers nix has no value for are converted to nix
+#[derive(Clone, Copy)]
+pub(super) struct NumberPolicies {
+    pub int_overflow: Option<IntOverflow>,
+    pub non_finite: Option<NonFinite>,
+}
+
+impl NumberPolicies {
+    /// The policies selected by `set_int_overflow` and `set_float_policy`
+    pub fn current() -> Self {
+        Self {
+            int_overflow: overflow::policy(),
+            non_finite: FloatPolicy::current().non_finite,
+        }
+    }
+}
+
+impl TryFromPyObject for TvixValue {
+    fn try_from_pyobject(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        from_python(obj, NumberPolicies::current())
+    }
+}
+
+/// Convert a Python object to a nix value, with the integers that do not fit
+/// into 64 bits and the floats that are not finite converted under
+/// `policies`
+pub(super) fn from_python(
+    obj: &Bound<'_, PyAny>,
+    policies: NumberPolicies,
+) -> PyResult<TvixValue> {
+    let value = if obj.is_none() {
+        TvixValue::Null
+    } else if let Ok(b) = obj.downcast::<PyBool>() {
+        TvixValue::Bool(b.is_true())
+    } else if let Ok(i) = obj.downcast::<PyInt>() {
+        match narrowed(i, policies.int_overflow)? {
+            Some(Narrowed::Int(i)) => TvixValue::Integer(i),
+            Some(Narrowed::Digits(digits)) => TvixValue::String(digits.into()),
+            None => Err(coded_err::<ConversionError>(
+                ErrorCode::NixConversion,
+                format!("Integer {} does not fit into a 64-bit nix integer", i),
+            ))?,
+        }
+    } else if let Ok(f) = obj.downcast::<PyFloat>() {
+        match f.value() {
+            f if f.is_finite() => TvixValue::Float(f),
+            f => match policies.non_finite {
+                None | Some(NonFinite::Float) => TvixValue::Float(f),
+                Some(NonFinite::Null) => TvixValue::Null,
+                Some(NonFinite::Error) => Err(non_finite_err(f))?,
+            },
+        }
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        TvixValue::String(s.to_str()?.into())
+    } else if obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>()
+    {
+        let converted = obj
+            .try_iter()?
+            .map(|v| from_python(&v?, policies))
+            .collect::<PyResult<Vec<_>>>()?;
+        TvixValue::List(NixList::from(converted))
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut attrs = Vec::with_capacity(dict.len());
+        for (k, v) in dict.iter() {
+            let key = k.downcast::<PyString>().map_err(|_| {
+                coded_err::<ConversionError>(
+                    ErrorCode::NixConversion,
+                    format!(
+                        "Attribute names must be strings, got {}",
+                        k.get_type()
+                    ),
+                )
+            })?;
+            attrs.push((key.to_str()?.to_string(), from_python(&v, policies)?));
+        }
+        TvixValue::attrs(NixAttrs::from_iter(attrs))
+    } else {
+        Err(coded_err::<ConversionError>(
+            ErrorCode::NixConversion,
+            format!(
+                "Cannot convert python type {} to nix value",
+                obj.get_type().name()?
+            ),
+        ))?
+    };
+    Ok(value)
+}
+
+/// What nix paths are converted to
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathsAs {
+    #[default]
+    Str,
+    /// `pathlib.Path`, which keeps the paths that are not valid UTF-8
+    Pathlib,
+}
+
+impl PathsAs {
+    pub fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "str" => Ok(PathsAs::Str),
+            "pathlib" => Ok(PathsAs::Pathlib),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown path conversion `{}`, expected `str` or `pathlib`",
+                name
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PathsAs::Str => "str",
+            PathsAs::Pathlib => "pathlib",
+        }
+    }
+}
+
+/// Converts evaluated nix values into Python objects
+#[derive(Default)]
+pub(super) struct Converter<'a> {
+    /// The evaluation the values come from, functions and thunks can only
+    /// be converted to `NixFunction` and `NixThunk` when it is known
+    pub context: Option<&'a Rc<Context>>,
+    /// The order of the attributes in the source, attrsets are converted in
+    /// the sorted order of nix without it
+    pub order: Option<&'a KeyOrder>,
+    /// Whether the objects are used on another thread than the evaluation,
+    /// where its functions cannot be called
+    pub detached: bool,
+}
+
+/// Conversion failures collected with the path of their value, instead of
+/// stopping at the first one
+#[derive(Default)]
+struct Failures {
+    path: Vec<String>,
+    errors: Vec<PyErr>,
+}
+
+impl Failures {
+    /// Record the failure to convert the value at the current path, which is
+    /// replaced by `None` to go on with the others
+    fn record(&mut self, py: Python<'_>, error: PyErr) -> PyResult<PyObject> {
+        if !error.is_instance_of::<ConversionError>(py) {
+            return Err(error);
+        }
+        let path = self.path.concat();
+        let message = format!(
+            "at `{}`: {}",
+            path.trim_start_matches('.'),
+            error.value(py)
+        );
+        self.errors.push(coded_err::<ConversionError>(
+            ErrorCode::NixConversion,
+            message,
+        ));
+        Ok(py.None())
+    }
+}
+
+impl Converter<'_> {
+    fn paths_as(&self) -> PathsAs {
+        self.context
+            .map_or_else(PathsAs::default, |c| c.evaluator.paths_as)
+    }
+
+    fn floats(&self) -> FloatPolicy {
+        self.context
+            .map_or_else(FloatPolicy::current, |c| c.evaluator.floats())
+    }
+
+    /// Convert a value, collecting every failure to raise them at once if
+    /// the evaluation asks so
+    pub fn convert(
+        &self,
+        py: Python<'_>,
+        value: &TvixValue,
+    ) -> PyResult<PyObject> {
+        let collect = self
+            .context
+            .is_some_and(|c| c.evaluator.collect_conversion_errors);
+        if !collect {
+            return self.convert_value(py, value, self.order, &mut None);
+        }
+        let mut failures = Some(Failures::default());
+        let object = unhooked(|| {
+            self.convert_value(py, value, self.order, &mut failures)
+        })
+        .map_err(hooked)?;
+        let mut errors = failures.unwrap_or_default().errors.into_iter();
+        match errors.next() {
+            Some(first) => Err(hooked(aggregated_err(first, errors.collect()))),
+            None => Ok(object),
+        }
+    }
+
+    /// Convert a value nested in another one under `segment` of the path
+    fn convert_child(
+        &self,
+        py: Python<'_>,
+        value: &TvixValue,
+        segment: impl FnOnce() -> String,
+        order: Option<&KeyOrder>,
+        failures: &mut Option<Failures>,
+    ) -> PyResult<PyObject> {
+        if let Some(failures) = failures.as_mut() {
+            failures.path.push(segment());
+        }
+        let result = self.convert_value(py, value, order, failures);
+        match failures.as_mut() {
+            Some(failures) => {
+                let result = result.or_else(|e| failures.record(py, e));
+                failures.path.pop();
+                result
+            }
+            None => result,
+        }
+    }
+
+    /// Convert a value, its attributes in `order` if known
+    fn convert_value(
+        &self,
+        py: Python<'_>,
+        value: &TvixValue,
+        order: Option<&KeyOrder>,
+        failures: &mut Option<Failures>,
+    ) -> PyResult<PyObject> {
+        let object = match value {
+            TvixValue::Null => into_pyany!(PyNone::get(py)),
+            TvixValue::Bool(b) => into_pyany!(PyBool::new(py, *b)),
+            // CPython hands out its cached objects of small integers
+            TvixValue::Integer(i) => into_pyany!(PyInt::new(py, *i)),
+            TvixValue::Float(f) if !f.is_finite() => {
+                match self.floats().non_finite {
+                    None | Some(NonFinite::Float) => {
+                        into_pyany!(PyFloat::new(py, *f))
+                    }
+                    Some(NonFinite::Null) => into_pyany!(PyNone::get(py)),
+                    Some(NonFinite::Error) => return Err(non_finite_err(*f)),
+                }
+            }
+            TvixValue::Float(f) => into_pyany!(PyFloat::new(py, *f)),
+            TvixValue::String(s) => {
+                into_pyany!(PyString::new(py, &s.to_string()))
+            }
+            TvixValue::Path(s) if self.paths_as() == PathsAs::Pathlib => {
+                // Decoded like `os.fsdecode`, undecodable bytes surviving as
+                // surrogate escapes
+                let path = py.import("pathlib")?.getattr("Path")?;
+                path.call1((s.as_os_str(),))?.unbind()
+            }
+            TvixValue::Path(s) => {
+                let converted = s.clone().into_os_string().into_string().map_err(|_| {
+                    coded_err::<ConversionError>(
+                        ErrorCode::NixConversion,
+                        "Failed to convert path to string, try wrap your path as `\"${path}\"` or set `paths_as=\"pathlib\"`".to_string(),
+                    )
+                })?;
+                into_pyany!(PyString::new(py, &converted))
+            }
+
+            TvixValue::List(l) => {
+                let mut converted = Vec::with_capacity(l.len());
+                for (i, v) in l.into_iter().enumerate() {
+                    converted.push(self.convert_child(
+                        py,
+                        v,
+                        || format!("[{}]", i),
+                        None,
+                        failures,
+                    )?);
+                }
+                into_pyany!(PyList::new(py, converted)?)
+            }
+            TvixValue::Attrs(attrs) => {
+                let dict = PyDict::new(py);
+                let mut attrs = attrs.iter().collect::<Vec<_>>();
+                if let Some(order) = order {
+                    // Attributes missing from the source come last, sorted
+                    attrs.sort_by_key(|(k, _)| {
+                        from_utf8(k.as_bytes())
+                            .ok()
+                            .and_then(|k| order.position(k))
+                            .unwrap_or(usize::MAX)
+                    });
+                }
+                for (k, v) in attrs {
+                    let key = from_utf8(k.as_bytes()).map_err(|e| {
+                        coded_err::<ConversionError>(
+                            ErrorCode::NixConversion,
+                            format!(
+                                "Failed to convert bytes to string ({}) on {}",
+                                e, k
+                            ),
+                        )
+                    })?;
+                    let value = match v {
+                        // Flat maps of strings dominate settings, so their
+                        // values skip the bookkeeping of children
+                        TvixValue::String(s) => {
+                            into_pyany!(PyString::new(py, &s.to_string()))
+                        }
+                        v => self.convert_child(
+                            py,
+                            v,
+                            || format!(".{}", attrpath(&[key.to_string()])),
+                            order.and_then(|o| o.child(key)),
+                            failures,
+                        )?,
+                    };
+                    dict.set_item(dict_key(py, key), value)?;
+                }
+                into_pyany!(dict)
+            }
+            TvixValue::Thunk(thunk) => {
+                if thunk.is_evaluated() {
+                    self.convert_value(py, &thunk.value(), order, failures)?
+                } else if let Some(context) =
+                    self.context.filter(|c| c.evaluator.leaves_thunks())
+                {
+                    let thunk = NixThunk {
+                        thunk: value.clone(),
+                        context: Rc::clone(context),
+                    };
+                    Py::new(py, thunk)?.into_any()
+                } else {
+                    Err(coded_err::<ConversionError>(
+                        ErrorCode::NixConversion,
+                        format!(
+                            "Cannot convert nix thunk to python object: {}",
+                            value
+                        ),
+                    ))?
+                }
+            }
+            TvixValue::Closure(_) | TvixValue::Builtin(_) => match self.context
+            {
+                Some(_) if self.detached => Err(coded_err::<ConversionError>(
+                    ErrorCode::NixConversion,
+                    format!(
+                        "Cannot convert nix function {} to python object \
+                        usable outside of the thread of its evaluation",
+                        value
+                    ),
+                ))?,
+                Some(context) => {
+                    let function = NixFunction {
+                        function: value.clone(),
+                        context: Rc::clone(context),
+                    };
+                    Py::new(py, function)?.into_any()
+                }
+                None => Err(coded_err::<ConversionError>(
+                    ErrorCode::NixConversion,
+                    format!(
+                        "Cannot convert nix function {} to python object \
+                        outside of an evaluation",
+                        value
+                    ),
+                ))?,
+            },
+            _ => Err(coded_err::<ConversionError>(
+                ErrorCode::NixConversion,
+                format!("Cannot convert nix type {} to python object", value),
+            ))?,
+        };
+        Ok(object)
+    }
+}
+
+impl TryToPyObject for TvixValue {
+    fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Converter::default().convert(py, self)
+    }
+}
@@ -0,0 +1,44 @@
+# Stand in for the `derivation` builtin like `derivation.nix` of nix, without
+# instantiating the derivation. Outputs get placeholder store paths, derived
+# from the attributes of the derivation but not computed like nix does, except
+# for fixed-output derivations with a SHA-256 hash, whose path only depends on
+# their name and hash.
+drvAttrs @ { name, outputs ? [ "out" ], ... }:
+
+let
+  # The attributes as strings, like the environment of the builder
+  strings = builtins.mapAttrs (_: toString)
+    (removeAttrs drvAttrs [ "__ignoreNulls" ]);
+  hash = builtins.substring 0 32
+    (builtins.hashString "sha256" (builtins.toJSON strings));
+  prefix = "/nix/store/${hash}-${name}";
+  fixedOutPath =
+    if drvAttrs ? outputHash then
+      builtins.__cosutilsFixedOutputPath {
+        inherit name;
+        hash = drvAttrs.outputHash;
+        algo = drvAttrs.outputHashAlgo or null;
+        mode = drvAttrs.outputHashMode or "flat";
+      }
+    else
+      null;
+
+  commonAttrs = drvAttrs // (builtins.listToAttrs outputsList) // {
+    all = map (x: x.value) outputsList;
+    inherit drvAttrs;
+  };
+  outputToAttrListElement = outputName: {
+    name = outputName;
+    value = commonAttrs // {
+      outPath =
+        if outputName == "out" && fixedOutPath != null then fixedOutPath
+        else if outputName == "out" then prefix
+        else "${prefix}-${outputName}";
+      drvPath = "${prefix}.drv";
+      type = "derivation";
+      inherit outputName;
+    };
+  };
+  outputsList = map outputToAttrListElement outputs;
+in
+(builtins.head outputsList).value
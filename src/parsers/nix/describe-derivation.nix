@@ -0,0 +1,34 @@
+# Describe a derivation by what it would be built with: its builder, the
+# arguments and environment given to it, and the paths of its outputs
+drv:
+
+let
+  # Derivations made without the `derivation` builtin have no `drvAttrs`
+  drvAttrs = drv.drvAttrs or
+    (removeAttrs drv [ "all" "drvPath" "outPath" "outputName" "type" ]);
+  # Like nix, `__ignoreNulls` leaves out the attributes that are `null`
+  kept =
+    if drvAttrs.__ignoreNulls or false then
+      removeAttrs drvAttrs
+        (builtins.filter (n: drvAttrs.${n} == null)
+          (builtins.attrNames drvAttrs))
+    else
+      drvAttrs;
+  outputs = builtins.listToAttrs (map
+    (output: {
+      name = output;
+      value = (drv.${output} or drv).outPath or null;
+    })
+    (drvAttrs.outputs or [ "out" ]));
+in
+{
+  name = drv.name or drvAttrs.name or null;
+  system = drvAttrs.system or null;
+  builder = toString (drvAttrs.builder or "");
+  args = map toString (drvAttrs.args or [ ]);
+  env = builtins.mapAttrs (_: toString)
+    (removeAttrs kept [ "args" "__ignoreNulls" ]) // outputs;
+  inherit outputs;
+  outPath = drv.outPath or null;
+  drvPath = drv.drvPath or null;
+}
@@ -0,0 +1,408 @@
+# Evaluate NixOS modules like `lib.evalModules` of nixpkgs, with a bundled
+# subset of `lib` instead of nixpkgs: collect the modules and their imports,
+# declare their options, and merge the definitions of each option by
+# priority and order into the final `config`
+{ modules, specialArgs }:
+
+let
+  inherit (builtins)
+    all any attrNames concatMap concatStringsSep elem elemAt filter foldl'
+    functionArgs genList head isAttrs isBool isFloat isFunction isInt isList
+    isPath isString length mapAttrs sort tail;
+
+  showLoc = loc: concatStringsSep "." loc;
+  showFiles = defs: concatStringsSep ", " (map (d: "`${d.file}'") defs);
+  imap = f: list: genList (i: f i (elemAt list i)) (length list);
+  isType = type: value: isAttrs value && value._type or null == type;
+  isOption = isType "option";
+
+  # The merges shared by types
+  mergeEqual = loc: defs:
+    let first = head defs; in
+    if all (d: d.value == first.value) defs then
+      first.value
+    else
+      throw "The option `${showLoc loc}' has conflicting definition values, in ${showFiles defs}";
+  mergeOne = loc: defs:
+    if length defs == 1 then
+      (head defs).value
+    else
+      throw "The unique option `${showLoc loc}' is defined multiple times, in ${showFiles defs}";
+  mergeDefault = loc: defs:
+    let values = map (d: d.value) defs; in
+    if length defs == 1 then
+      (head defs).value
+    else if all isList values then
+      concatMap (v: v) values
+    else if all isAttrs values then
+      foldl' (merged: v: merged // v) { } values
+    else if all isBool values then
+      any (v: v) values
+    else
+      mergeEqual loc defs;
+
+  mkOptionType = { name, description ? name, check ? (_: true), merge ? mergeDefault, ... }@type:
+    type // {
+      _type = "option-type";
+      inherit name description check merge;
+    };
+
+  separatedString = sep: mkOptionType {
+    name = "separatedString";
+    description = "strings separated by `${sep}'";
+    check = isString;
+    merge = _: defs: concatStringsSep sep (map (d: d.value) defs);
+  };
+
+  between = lowest: highest: mkOptionType {
+    name = "intBetween";
+    description = "integer between ${toString lowest} and ${toString highest}";
+    check = v: isInt v && v >= lowest && v <= highest;
+    merge = mergeEqual;
+  };
+
+  types = rec {
+    anything = mkOptionType {
+      name = "anything";
+      description = "anything";
+      merge = loc: defs:
+        let values = map (d: d.value) defs; in
+        if all isAttrs values then
+          mapAttrs
+            (name: _: anything.merge (loc ++ [ name ])
+              (concatMap
+                (d: if d.value ? ${name} then [{ inherit (d) file; value = d.value.${name}; }] else [ ])
+                defs))
+            (foldl' (names: v: names // v) { } values)
+        else if all isList values then
+          concatMap (v: v) values
+        else
+          mergeEqual loc defs;
+    };
+    raw = mkOptionType { name = "raw"; description = "raw value"; merge = mergeOne; };
+    unspecified = mkOptionType { name = "unspecified"; description = "unspecified value"; };
+    bool = mkOptionType { name = "bool"; description = "boolean"; check = isBool; merge = mergeEqual; };
+    int = mkOptionType { name = "int"; description = "signed integer"; check = isInt; merge = mergeEqual; };
+    ints = {
+      inherit between;
+      unsigned = mkOptionType { name = "unsignedInt"; description = "unsigned integer"; check = v: isInt v && v >= 0; merge = mergeEqual; };
+      positive = mkOptionType { name = "positiveInt"; description = "positive integer"; check = v: isInt v && v > 0; merge = mergeEqual; };
+      u8 = between 0 255;
+      u16 = between 0 65535;
+      u32 = between 0 4294967295;
+    };
+    port = ints.u16;
+    float = mkOptionType { name = "float"; description = "floating point number"; check = isFloat; merge = mergeEqual; };
+    number = either int float;
+    str = mkOptionType { name = "str"; description = "string"; check = isString; merge = mergeEqual; };
+    nonEmptyStr = mkOptionType { name = "nonEmptyStr"; description = "non-empty string"; check = v: isString v && v != ""; merge = mergeEqual; };
+    inherit separatedString;
+    lines = separatedString "\n";
+    commas = separatedString ",";
+    path = mkOptionType {
+      name = "path";
+      description = "path";
+      check = v: isPath v || (isString v && builtins.substring 0 1 v == "/");
+      merge = mergeEqual;
+    };
+    package = mkOptionType { name = "package"; description = "package"; check = v: isAttrs v || isString v; merge = mergeOne; };
+    attrs = mkOptionType { name = "attrs"; description = "attribute set"; check = isAttrs; merge = _: defs: foldl' (merged: d: merged // d.value) { } defs; };
+    enum = values: mkOptionType {
+      name = "enum";
+      description = "one of ${concatStringsSep ", " (map (v: builtins.toJSON v) values)}";
+      check = v: elem v values;
+      merge = mergeEqual;
+    };
+    nullOr = type: mkOptionType {
+      name = "nullOr";
+      description = "null or ${type.description}";
+      check = v: v == null || type.check v;
+      merge = loc: defs:
+        let nulls = filter (d: d.value == null) defs; in
+        if length nulls == length defs then
+          null
+        else if nulls != [ ] then
+          throw "The option `${showLoc loc}' is defined both null and not null, in ${showFiles defs}"
+        else
+          type.merge loc defs;
+    };
+    either = left: right: mkOptionType {
+      name = "either";
+      description = "${left.description} or ${right.description}";
+      check = v: left.check v || right.check v;
+      merge = loc: defs:
+        if all (d: left.check d.value) defs then
+          left.merge loc defs
+        else if all (d: right.check d.value) defs then
+          right.merge loc defs
+        else
+          mergeOne loc defs;
+    };
+    oneOf = alternatives: foldl' either (head alternatives) (tail alternatives);
+    listOf = elemType: mkOptionType {
+      name = "listOf";
+      description = "list of ${elemType.description}";
+      check = isList;
+      merge = loc: defs:
+        concatMap
+          (d: imap
+            (i: value: (mergeDefinitions (loc ++ [ "[${toString i}]" ]) elemType [{ inherit (d) file; inherit value; }]).value)
+            d.value)
+          defs;
+    };
+    attrsOf = elemType: mkOptionType {
+      name = "attrsOf";
+      description = "attribute set of ${elemType.description}";
+      check = isAttrs;
+      merge = loc: defs:
+        let
+          merged = mapAttrs
+            (name: _: mergeDefinitions (loc ++ [ name ]) elemType
+              (concatMap (d: if d.value ? ${name} then [{ inherit (d) file; value = d.value.${name}; }] else [ ]) defs))
+            (foldl' (names: d: names // d.value) { } defs);
+        in
+        mapAttrs (_: m: m.value) (removeAttrs merged (filter (name: !merged.${name}.isDefined) (attrNames merged)));
+    };
+    # Values are not forced to tell which are defined, so all of them are
+    lazyAttrsOf = elemType: attrsOf elemType // {
+      name = "lazyAttrsOf";
+      merge = loc: defs:
+        mapAttrs
+          (name: _: (mergeDefinitions (loc ++ [ name ]) elemType
+            (concatMap (d: if d.value ? ${name} then [{ inherit (d) file; value = d.value.${name}; }] else [ ]) defs)).value)
+          (foldl' (names: d: names // d.value) { } defs);
+    };
+    submoduleWith = { modules, specialArgs ? { } }: mkOptionType {
+      name = "submodule";
+      description = "submodule";
+      check = v: isAttrs v || isFunction v || isPath v;
+      merge = loc: defs:
+        (evalModules {
+          modules = modules ++ map (d: { _file = d.file; imports = [ d.value ]; }) defs;
+          specialArgs = specialArgs // { name = elemAt loc (length loc - 1); };
+        }).config;
+      getSubModules = modules;
+    };
+    submodule = module: submoduleWith { modules = [ module ]; };
+  };
+
+  mkOverride = priority: content: { _type = "override"; inherit priority content; };
+  mkOrder = priority: content: { _type = "order"; inherit priority content; };
+
+  # The subset of `lib` modules commonly use, along with the builtins
+  lib = builtins // rec {
+    inherit types mkOverride mkOrder;
+    mkOption = attrs: attrs // { _type = "option"; };
+    mkEnableOption = name: mkOption {
+      type = types.bool;
+      default = false;
+      description = "Whether to enable ${name}.";
+    };
+    mkIf = condition: content: { _type = "if"; inherit condition content; };
+    mkMerge = contents: { _type = "merge"; inherit contents; };
+    mkDefault = mkOverride 1000;
+    mkForce = mkOverride 50;
+    mkOptionDefault = mkOverride 1500;
+    mkBefore = mkOrder 500;
+    mkAfter = mkOrder 1500;
+    literalExpression = text: { _type = "literalExpression"; inherit text; };
+    literalMD = text: { _type = "literalMD"; inherit text; };
+    mdDoc = text: text;
+    id = x: x;
+    const = x: _: x;
+    optional = condition: value: if condition then [ value ] else [ ];
+    optionals = condition: values: if condition then values else [ ];
+    optionalAttrs = condition: attrs: if condition then attrs else { };
+    optionalString = condition: s: if condition then s else "";
+    mapAttrsToList = f: attrs: map (name: f name attrs.${name}) (attrNames attrs);
+    filterAttrs = pred: attrs:
+      removeAttrs attrs (filter (name: !pred name attrs.${name}) (attrNames attrs));
+    concatMapStrings = f: list: concatStringsSep "" (map f list);
+    concatMapStringsSep = sep: f: list: concatStringsSep sep (map f list);
+    hasPrefix = prefix: s: builtins.substring 0 (builtins.stringLength prefix) s == prefix;
+    recursiveUpdate = left: right:
+      left // mapAttrs
+        (name: value:
+          if isAttrs value && isAttrs (left.${name} or null) then
+            recursiveUpdate left.${name} value
+          else
+            value)
+        right;
+  };
+
+  # Split the definitions of a level of `config` into attrsets, pushing
+  # `mkIf` and `mkOverride` down to their attributes so that conditions are
+  # only evaluated by the options they define
+  pushDown = value:
+    if isType "merge" value then
+      concatMap pushDown value.contents
+    else if isType "if" value then
+      map (mapAttrs (_: lib.mkIf value.condition)) (pushDown value.content)
+    else if isType "override" value then
+      map (mapAttrs (_: mkOverride value.priority)) (pushDown value.content)
+    else
+      [ value ];
+
+  # Expand the `mkMerge` and `mkIf` of a definition of an option
+  discharge = value:
+    if isType "merge" value then
+      concatMap discharge value.contents
+    else if isType "if" value then
+      if !isBool value.condition then
+        throw "`mkIf' called with a non-Boolean condition"
+      else if value.condition then
+        discharge value.content
+      else
+        [ ]
+    else
+      [ value ];
+
+  # Keep the definitions of the highest priority, ordered, and merge them
+  mergeDefinitions = loc: type: defs:
+    let
+      discharged = concatMap
+        (d: map (value: { inherit (d) file; inherit value; }) (discharge d.value))
+        defs;
+      priorityOf = d: if isType "override" d.value then d.value.priority else 100;
+      highest = foldl' (p: d: if priorityOf d < p then priorityOf d else p) 9999 discharged;
+      kept = map
+        (d: if isType "override" d.value then d // { value = d.value.content; } else d)
+        (filter (d: priorityOf d == highest) discharged);
+      orderOf = d: if isType "order" d.value then d.value.priority else 1000;
+      sorted = map
+        (d: if isType "order" d.value then d // { value = d.value.content; } else d)
+        (sort (a: b: orderOf a < orderOf b) kept);
+      checked = map
+        (d:
+          if type.check d.value then
+            d
+          else
+            throw "A definition for option `${showLoc loc}' is not of type `${type.description}', in `${d.file}'")
+        sorted;
+    in
+    {
+      isDefined = sorted != [ ];
+      value = type.merge loc checked;
+    };
+
+  evalOption = loc: option: defs:
+    let
+      type = option.type or types.unspecified;
+      defaults = if option ? default then [{ file = "<default>"; value = lib.mkOptionDefault option.default; }] else [ ];
+      merged = mergeDefinitions loc type (defaults ++ defs);
+    in
+    option // {
+      inherit (merged) isDefined;
+      files = map (d: d.file) defs;
+      value =
+        if merged.isDefined then
+          (option.apply or (v: v)) merged.value
+        else
+          throw "The option `${showLoc loc}' is used but not defined.";
+    };
+
+  # Merge the declarations of options, submodules declared several times
+  # getting the options of each declaration
+  mergeDeclarations = left: right:
+    left // mapAttrs
+      (name: declared:
+        let previous = left.${name} or null; in
+        if previous == null then
+          declared
+        else if !isOption previous && !isOption declared then
+          mergeDeclarations previous declared
+        else if isOption previous && isOption declared
+          && previous.type.getSubModules or null != null
+          && declared.type.getSubModules or null != null then
+          previous // declared // {
+            type = types.submoduleWith {
+              modules = previous.type.getSubModules ++ declared.type.getSubModules;
+            };
+          }
+        else
+          previous // declared)
+      right;
+
+  # Evaluate the options declared under `loc`, along with their values
+  evalDeclarations = loc: declarations: defs:
+    if isOption declarations then
+      let option = evalOption loc declarations defs; in
+      { options = option; config = option.value; }
+    else
+      let
+        pushed = concatMap
+          (d: map (value: { inherit (d) file; inherit value; }) (pushDown d.value))
+          defs;
+        definitionsOf = name: concatMap
+          (d: if d.value ? ${name} then [{ inherit (d) file; value = d.value.${name}; }] else [ ])
+          pushed;
+        evaluated = mapAttrs
+          (name: declaration: evalDeclarations (loc ++ [ name ]) declaration (definitionsOf name))
+          declarations;
+        undeclared = concatMap
+          (d: map (name: { inherit name; inherit (d) file; })
+            (filter (name: !declarations ? ${name}) (attrNames d.value)))
+          pushed;
+      in
+      {
+        options = mapAttrs (_: e: e.options) evaluated;
+        config =
+          if undeclared == [ ] then
+            mapAttrs (_: e: e.config) evaluated
+          else
+            let first = head undeclared; in
+            throw "The option `${showLoc (loc ++ [ first.name ])}' does not exist, in `${first.file}'";
+      };
+
+  evalModules = { modules, specialArgs }:
+    let
+      # Modules may ask for the arguments of `_module.args`, like `pkgs`
+      args = specialArgs // { inherit lib config options; };
+      call = module:
+        module (args // mapAttrs
+          (name: _: args.${name} or config._module.args.${name})
+          (functionArgs module));
+
+      load = parent: i: module:
+        let
+          isFile = isString module || isPath module;
+          imported =
+            if isPath module then import module
+            else if isString module then import (/. + module)
+            else module;
+          value = if isFunction imported then call imported else imported;
+          structured = value ? config || value ? options;
+        in
+        {
+          key = if isFile then toString module else "${parent.key}:${toString i}";
+          file = value._file or (if isFile then toString module else parent.file);
+          imports = value.imports or [ ];
+          options = if structured then value.options or { } else { };
+          config =
+            if structured then
+              value.config or { }
+            else
+              removeAttrs value [ "_file" "key" "imports" "disabledModules" "meta" ];
+        };
+
+      collected = builtins.genericClosure {
+        startSet = imap (load { key = "<root>"; file = "<unknown-file>"; }) (modules ++ [ internal ]);
+        operator = module: imap (load module) module.imports;
+      };
+
+      internal = {
+        _file = "<internal>";
+        options._module.args = lib.mkOption {
+          type = types.lazyAttrsOf types.raw;
+          default = { };
+        };
+      };
+
+      declarations = foldl' mergeDeclarations { } (map (m: m.options) collected);
+      evaluated = evalDeclarations [ ] declarations
+        (map (m: { inherit (m) file; value = m.config; }) collected);
+      inherit (evaluated) config options;
+    in
+    { inherit config options; };
+in
+(evalModules { inherit modules specialArgs; }).config
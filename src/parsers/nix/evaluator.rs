@@ -0,0 +1,2283 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use annotate_snippets::Snippet;
+use genawaiter::rc::Gen;
+use pyo3::exceptions::{PyIOError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{
+    IntoPyDict, PyBool, PyDict, PyInt, PyList, PyString, PyTuple,
+};
+use pyo3::{PyObject, PyResult};
+use rustc_hash::FxHashMap;
+use smol_str::SmolStr;
+use tvix_eval::builtins::pure_builtins;
+use tvix_eval::generators::{self, pin_generator};
+use tvix_eval::observer::RuntimeObserver;
+use tvix_eval::{
+    Builtin, EvalIO, EvalMode, Evaluation, EvaluationResult, SourceCode, StdIO,
+};
+use tvix_eval::{ErrorKind as TvixErrorKind, Value as TvixValue};
+
+use super::cache::{Cache, CacheOption, RecordingIO};
+use super::convert::{from_python, Converter, NumberPolicies, PathsAs};
+use super::fetch::{fetch, FetchCache, Missing};
+use super::flake::{
+    resolve_flake_builtin, FlakeRegistry, CALL_FLAKE, GET_FLAKE,
+};
+use super::imports::{ImportIO, Imports};
+use super::json::{json_to_py, py_to_json, tvix_to_json};
+use super::limits::{
+    depth_limit, memory_from_mb, peak_memory, timeout_from_secs, Budget, Limits,
+};
+use super::order::KeyOrder;
+use super::positions::positions;
+use super::profiler::Profile;
+use super::pyio::PyIO;
+use super::renaming::split;
+use super::report::{denied_fetcher, Fetches, Report};
+use super::sandbox::{allowed_paths, SandboxIO};
+use super::sources::Sources;
+use super::store::{fixed_output_builtin, path_builtin, StoreIO};
+use super::validate::{check_violations, violations};
+use super::value_diff::{changes, changes_to_py};
+use super::writers::{tvix_to_toml, tvix_to_yaml};
+use super::{
+    check_nesting, describe_warning, parse_root, warnings_into_pyerr,
+    TraceWarning,
+};
+use crate::into_pyany;
+use crate::parsers::floats::{FloatOptions, FloatPolicy, FloatRepr, NonFinite};
+use crate::parsers::hash::encode;
+use crate::parsers::hook::{hooked, unhooked};
+use crate::parsers::overflow::{self, IntOverflow};
+use crate::parsers::profile;
+use crate::parsers::schema::attrpath;
+use crate::parsers::threads;
+use crate::parsers::utils::{
+    aggregated_err, coded_err, display_source, normalized_newlines, read_file,
+    ErrorCode, EvaluationError, FileSource, IntoPyErr, IntoRange,
+    TryToPyObject,
+};
+
+/// Extra bindings visible to an evaluated expression
+pub(super) type Scope = FxHashMap<SmolStr, TvixValue>;
+
+/// Names under which values of a finished evaluation are bound when
+/// evaluating further on them
+const FUNCTION_BINDING: &str = "__cosutilsFunction";
+const ARGUMENT_BINDING: &str = "__cosutilsArgument";
+const VALUE_BINDING: &str = "__cosutilsValue";
+
+/// Stands in for the `derivation` builtin, which tvix only provides along
+/// with a store
+const DERIVATION: &str = include_str!("derivation.nix");
+
+/// Describes a derivation by the attributes it would be built with
+const DESCRIBE_DERIVATION: &str = include_str!("describe-derivation.nix");
+
+/// Forces a value down to a number of levels of attrsets and lists
+const FORCE_LEVELS: &str = include_str!("force-levels.nix");
+
+/// Merges an attrset into another like `lib.recursiveUpdate`
+const RECURSIVE_UPDATE: &str = include_str!("recursive-update.nix");
+
+/// Python exception raised by a builtin, kept to be chained to the final error
+pub(super) type PendingError = Rc<RefCell<Option<PyErr>>>;
+
+/// Messages recorded by evaluations sharing the same context
+type Traces = Rc<RefCell<Vec<Trace>>>;
+
+/// A message of an evaluation, from `builtins.trace` or a tvix warning
+pub(super) struct Trace {
+    kind: &'static str,
+    message: String,
+    /// The line of the evaluated file the message points to, if known
+    line: Option<usize>,
+}
+
+impl TryToPyObject for Trace {
+    fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let trace = PyDict::new(py);
+        trace.set_item("kind", self.kind)?;
+        trace.set_item("message", &self.message)?;
+        trace.set_item("line", self.line)?;
+        Ok(trace.into_any().unbind())
+    }
+}
+
+/// Replace `builtins.trace`, recording its messages instead of printing them
+/// to stderr
+fn trace_builtin(traces: &Traces) -> TvixValue {
+    let traces = traces.clone();
+    let builtin = Builtin::new("trace", None, 2, move |args| {
+        let traces = traces.clone();
+        Gen::new(|co| {
+            pin_generator(async move {
+                let message =
+                    generators::request_deep_force(&co, args[0].clone()).await;
+                let message = match &message {
+                    TvixValue::String(s) => s.to_string(),
+                    other => other.to_string(),
+                };
+                traces.borrow_mut().push(Trace {
+                    kind: "trace",
+                    message,
+                    line: None,
+                });
+                Ok(args[1].clone())
+            })
+        })
+    });
+    TvixValue::Builtin(builtin)
+}
+
+/// The fetchers of nix that can be backed by Python callables
+const FETCHERS: &[&str] =
+    &["fetchGit", "fetchTarball", "fetchTree", "fetchurl"];
+
+/// A Python callable exposed to nix as a builtin
+#[derive(Clone)]
+pub(super) struct PyBuiltin {
+    name: &'static str,
+    arity: usize,
+    callable: Arc<PyObject>,
+    /// Whether the builtin replaces a fetcher, returning a path
+    fetcher: bool,
+}
+
+/// The builtin names leaked so far
+static BUILTIN_NAMES: LazyLock<Mutex<HashSet<&'static str>>> =
+    LazyLock::new(Default::default);
+
+/// A builtin name as tvix requires it, `'static`. Each distinct name is
+/// leaked once, however many evaluators define it.
+fn interned(name: String) -> &'static str {
+    let mut names = BUILTIN_NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(interned) = names.get(name.as_str()) {
+        return *interned;
+    }
+    let leaked: &'static str = Box::leak(name.into_boxed_str());
+    names.insert(leaked);
+    leaked
+}
+
+impl PyBuiltin {
+    fn new(name: String, callable: &Bound<'_, PyAny>) -> PyResult<Self> {
+        if !callable.is_callable() {
+            return Err(PyTypeError::new_err(format!(
+                "Builtin `{}` must be callable",
+                name
+            )));
+        }
+        let arity = positional_arity(callable)?;
+        if arity == 0 {
+            return Err(PyValueError::new_err(format!(
+                "Builtin `{}` must accept at least one positional argument",
+                name
+            )));
+        }
+        Ok(Self {
+            name: interned(name),
+            arity,
+            callable: Arc::new(callable.clone().unbind()),
+            fetcher: false,
+        })
+    }
+
+    /// Back one of the nix fetchers by a callable, which fetches what its
+    /// argument describes and returns the local path of the result
+    fn fetcher(name: String, callable: &Bound<'_, PyAny>) -> PyResult<Self> {
+        if !FETCHERS.contains(&name.as_str()) {
+            return Err(PyValueError::new_err(format!(
+                "Unknown fetcher `{}`, expected one of `{}`",
+                name,
+                FETCHERS.join("`, `")
+            )));
+        }
+        let fetcher = Self::new(name, callable)?;
+        if fetcher.arity != 1 {
+            return Err(PyValueError::new_err(format!(
+                "Fetcher `{}` must accept exactly one positional argument",
+                fetcher.name
+            )));
+        }
+        Ok(Self {
+            fetcher: true,
+            ..fetcher
+        })
+    }
+
+    /// Wrap the callable as a tvix builtin, which converts its arguments to
+    /// Python objects and its result back to a nix value
+    fn to_builtin(
+        &self,
+        pending: &PendingError,
+        fetch_cache: Option<&FetchCache>,
+        missing: &Missing,
+        fetches: &Fetches,
+        policies: NumberPolicies,
+    ) -> TvixValue {
+        let (name, fetcher) = (self.name, self.fetcher);
+        let callable = self.callable.clone();
+        let pending = pending.clone();
+        let fetch_cache = fetch_cache.cloned();
+        let missing = missing.clone();
+        let fetches = fetches.clone();
+        let builtin = Builtin::new(name, None, self.arity, move |args| {
+            let callable = callable.clone();
+            let pending = pending.clone();
+            let fetch_cache = fetch_cache.clone();
+            let missing = missing.clone();
+            let fetches = fetches.clone();
+            Gen::new(|co| {
+                pin_generator(async move {
+                    let mut forced = Vec::with_capacity(args.len());
+                    for arg in args {
+                        forced.push(
+                            generators::request_deep_force(&co, arg).await,
+                        );
+                    }
+                    Python::with_gil(|py| {
+                        let mut args = forced
+                            .iter()
+                            .map(|a| a.try_to_pyobject(py))
+                            .collect::<PyResult<Vec<_>>>()?;
+                        if fetcher {
+                            let path = fetch(
+                                py,
+                                name,
+                                &callable,
+                                args.remove(0),
+                                fetch_cache.as_ref(),
+                                &missing,
+                                &fetches,
+                            )?;
+                            // Strings of absolute paths are imported like
+                            // the store paths nix fetchers return
+                            let path = path.to_string_lossy();
+                            return Ok(TvixValue::String(path.as_ref().into()));
+                        }
+                        let result =
+                            callable.call1(py, PyTuple::new(py, args)?)?;
+                        from_python(result.bind(py), policies)
+                    })
+                    .map_err(|e| {
+                        let message =
+                            format!("builtin `{}` failed: {}", name, e);
+                        *pending.borrow_mut() = Some(e);
+                        TvixErrorKind::Abort(message)
+                    })
+                })
+            })
+        });
+        TvixValue::Builtin(builtin)
+    }
+}
+
+/// The builtins whose value depends on the machine or the time of an
+/// evaluation, which can be pinned, with whether their value is an int
+const PINNABLE: &[(&str, bool)] = &[
+    ("currentSystem", false),
+    ("currentTime", true),
+    ("nixVersion", false),
+];
+
+/// The fixed value of a pinned builtin
+#[derive(Clone)]
+pub(super) enum Pin {
+    Int(i64),
+    Str(String),
+}
+
+impl Pin {
+    fn to_value(&self) -> TvixValue {
+        match self {
+            Pin::Int(i) => TvixValue::Integer(*i),
+            Pin::Str(s) => TvixValue::String(s.as_str().into()),
+        }
+    }
+
+    fn object(&self, py: Python<'_>) -> PyObject {
+        match self {
+            Pin::Int(i) => into_pyany!(PyInt::new(py, *i)),
+            Pin::Str(s) => into_pyany!(PyString::new(py, s)),
+        }
+    }
+}
+
+/// The builtins pinned to fixed values, sorted by name
+pub(super) type Pinned = Arc<[(&'static str, Pin)]>;
+
+/// Read the values pinned builtins are given, checking that they are of the
+/// type nix gives them
+fn pinned_builtins(pinned: &Bound<'_, PyDict>) -> PyResult<Pinned> {
+    let mut pins = pinned
+        .iter()
+        .map(|(name, value)| {
+            let name = name.extract::<String>()?;
+            let Some(&(name, int)) =
+                PINNABLE.iter().find(|(pinnable, _)| *pinnable == name)
+            else {
+                return Err(PyValueError::new_err(format!(
+                    "Builtin `{}` cannot be pinned, expected one of `{}`",
+                    name,
+                    PINNABLE
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join("`, `")
+                )));
+            };
+            let pin = if value.is_instance_of::<PyBool>() {
+                None
+            } else if int {
+                value.extract().ok().map(Pin::Int)
+            } else {
+                value.extract().ok().map(Pin::Str)
+            };
+            let Some(pin) = pin else {
+                return Err(PyTypeError::new_err(format!(
+                    "Builtin `{}` must be pinned to {}, got {}",
+                    name,
+                    if int { "an int" } else { "a str" },
+                    value.get_type()
+                )));
+            };
+            Ok((name, pin))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    pins.sort_by_key(|(name, _)| *name);
+    Ok(Arc::from(pins))
+}
+
+/// Count the positional parameters without default of a Python callable
+fn positional_arity(callable: &Bound<'_, PyAny>) -> PyResult<usize> {
+    let inspect = callable.py().import("inspect")?;
+    let parameter = inspect.getattr("Parameter")?;
+    let positional = [
+        parameter.getattr("POSITIONAL_ONLY")?,
+        parameter.getattr("POSITIONAL_OR_KEYWORD")?,
+    ];
+    let empty = parameter.getattr("empty")?;
+
+    let signature = inspect.call_method1("signature", (callable,))?;
+    let mut arity = 0;
+    for param in signature
+        .getattr("parameters")?
+        .call_method0("values")?
+        .try_iter()?
+    {
+        let param = param?;
+        let kind = param.getattr("kind")?;
+        if positional.iter().any(|k| k.eq(&kind).unwrap_or(false))
+            && param.getattr("default")?.is(&empty)
+        {
+            arity += 1;
+        }
+    }
+    Ok(arity)
+}
+
+/// Search path for `<name>` lookups, either in the format of `NIX_PATH` or
+/// as a list of its entries
+#[derive(FromPyObject)]
+pub enum NixPath {
+    Joined(String),
+    Entries(Vec<String>),
+}
+
+impl NixPath {
+    pub fn into_string(self) -> String {
+        match self {
+            NixPath::Joined(path) => path,
+            NixPath::Entries(entries) => entries.join(":"),
+        }
+    }
+}
+
+/// How deep results are evaluated, either `shallow`, `deep` or a number of
+/// levels of attrsets and lists
+#[derive(FromPyObject)]
+pub enum DepthOption {
+    Levels(usize),
+    Named(String),
+}
+
+impl DepthOption {
+    /// The number of levels evaluated, `None` for all of them
+    pub fn into_levels(self) -> PyResult<Option<usize>> {
+        match self {
+            DepthOption::Levels(0) => Err(PyValueError::new_err(
+                "Depth must be a positive number of levels, got 0",
+            )),
+            DepthOption::Levels(levels) => Ok(Some(levels)),
+            DepthOption::Named(name) => match name.as_str() {
+                "shallow" => Ok(Some(1)),
+                "deep" => Ok(None),
+                name => Err(PyValueError::new_err(format!(
+                    "Unknown depth `{}`, expected `shallow`, `deep` or a \
+                    number of levels",
+                    name
+                ))),
+            },
+        }
+    }
+}
+
+/// A nix evaluator with custom settings.
+///
+/// Args:
+///   - builtins (dict[str, Callable]): Extra builtins, available in nix as
+///                                     `builtins.<name>`. Each one is backed
+///                                     by a Python callable taking as many
+///                                     arguments as it has positional
+///                                     parameters without default. Arguments
+///                                     and the return value are converted
+///                                     like `evals` args and results.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - lazy (bool): Only evaluate what is needed to produce the top-level
+///                  value, returning unevaluated parts as `NixThunk`.
+///   - depth (str | int): How deep results are evaluated, `deep` for the
+///                        whole value, `shallow` for its top-level value
+///                        only like `lazy`, or a number of levels of
+///                        attrsets and lists, `1` being `shallow`. Values
+///                        below the depth are returned unevaluated as
+///                        `NixThunk`, so listing the top-level attributes of
+///                        a large attrset does not evaluate their values.
+///                        Results evaluated to a depth are never cached.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, either
+///                                 as entries like `nixpkgs=/path/to/nixpkgs`
+///                                 or `/path/to/dir`, or joined by `:` like
+///                                 the `NIX_PATH` environment variable, which
+///                                 itself is never read.
+///   - cache (bool | str): Cache converted results, keyed by the expression
+///                         and the settings of the evaluator, and invalidated
+///                         when a file accessed by the evaluation changes.
+///                         `True` caches them in memory, a directory caches
+///                         them on disk too. Lazy evaluations and results
+///                         that are not plain JSON values are never cached,
+///                         and builtins must be pure for the cache to be
+///                         correct.
+///   - timeout (float): Abort evaluations running longer than this many
+///                      seconds, raising an `EvaluationError` that lists the
+///                      calls entered last. Evaluations stop at the next
+///                      builtin they apply, so code looping without ever
+///                      applying one is not stopped. Every later call of a
+///                      `NixFunction` or `NixThunk` gets the same time again.
+///   - max_memory_mb (int): Abort evaluations once they allocated more than
+///                          this many MiB, raising a `ResourceLimitError`.
+///                          Only the allocations of the evaluating thread are
+///                          counted, while it evaluates. The memory is
+///                          checked whenever a builtin is applied, so a
+///                          single builtin call may overshoot the limit, but
+///                          `genList` checks its list fits before building
+///                          it. Every later call of a `NixFunction` or
+///                          `NixThunk` gets the same budget again.
+///   - max_depth (int): Raise a `ParseError` before evaluating a file or
+///                      expression nesting expressions deeper than this, so
+///                      untrusted input cannot overflow the stack of the
+///                      compiler. Files it imports are not checked. The
+///                      parser of rnix has its own fixed limit, which this
+///                      can only lower: code nested past it always raises a
+///                      `ParseError`.
+///   - max_call_depth (int): Abort evaluations nesting more than this many
+///                           calls of nix functions and thunks, raising a
+///                           `ResourceLimitError` that lists the calls
+///                           entered last, at the next builtin they apply.
+///                           tvix keeps its calls on the heap and has no
+///                           limit of its own, deep recursions being only
+///                           bounded by `max_memory_mb`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings of
+///                                the evaluated file or expression to `\n`,
+///                                so offsets in errors match the lines shown
+///                                by editors. Strings spanning several lines
+///                                then also contain `\n` only.
+///   - collect_traces (bool): Return the messages of `builtins.trace` and
+///                            the warnings of the evaluation along with the
+///                            value, as `(value, traces)`, instead of
+///                            emitting the messages as `TraceWarning`. Such
+///                            results are never cached.
+///   - collect_conversion_errors (bool): Convert the whole result even when
+///                                       parts of it cannot be converted,
+///                                       then raise a `ConversionError`
+///                                       listing all of them with their path,
+///                                       like `a.b[0]`.
+///   - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+///                     for `pathlib.Path`. Paths that are not valid UTF-8
+///                     raise a `ConversionError` as `str`, and are decoded
+///                     like `os.fsdecode` as `pathlib.Path`.
+///   - ordered (bool): Convert attrsets with their attributes in the order
+///                     they are defined in the evaluated file or
+///                     expression, instead of the sorted order of nix, for
+///                     results reviewed by humans. The order is found in the
+///                     syntax, through functions, `let`, `with`, `if` and
+///                     `//`, attributes defined elsewhere, like in imported
+///                     files, coming last in sorted order. Values of
+///                     `NixFunction` and `NixThunk` keep the sorted order,
+///                     and ordered results are never cached.
+///   - fetchers (dict[str, Callable]): Fetchers of nix, among `fetchGit`,
+///                                     `fetchTarball`, `fetchTree` and
+///                                     `fetchurl`, implemented by Python
+///                                     callables instead of failing in pure
+///                                     evaluations. Each callable is given the
+///                                     url or attrset the fetcher is called
+///                                     with, converted like `evals` results,
+///                                     fetches it however the application
+///                                     sees fit, downloading or looking it up
+///                                     in a cache, and returns the local
+///                                     absolute path of the result as a
+///                                     `str` or `os.PathLike`. A builtin of
+///                                     the same name takes precedence.
+///   - io (_NixIO): An object serving the files of evaluations instead of
+///                  the file system, like a database or an in-memory file
+///                  system. `read_file(path)` returns the content of a file
+///                  as `bytes` or `str`, `path_exists(path)` whether a path
+///                  exists, and `read_dir(path)` maps the names of the
+///                  entries of a directory to their type, `"regular"`,
+///                  `"directory"`, `"symlink"` or `"unknown"` like
+///                  `builtins.readDir`. An optional `file_type(path)` returns
+///                  the type of a path, which is a directory otherwise if
+///                  `read_dir` succeeds. Paths are given as `str`, and
+///                  raising `FileNotFoundError` reports missing files like
+///                  the file system would. Files evaluated by `eval` are
+///                  read from it too. It cannot be used with `cache`.
+///   - fetch_cache (FetchCache): Where the results of the `fetchers` are
+///                               cached, which may be shared with other
+///                               evaluators, see `FetchCache`.
+///   - restrict_paths (list[str]): The only directories evaluations may
+///                                 access, for evaluating untrusted
+///                                 expressions. Importing, reading or
+///                                 listing any other path, or a symlink
+///                                 leading out of the directories, raises
+///                                 an `EvaluationError`. The file given to
+///                                 `eval` itself needs not be in them.
+///   - allow_import (bool): Let evaluations import and read files. `False`
+///                          raises an `EvaluationError` on any access to a
+///                          path, like an empty `restrict_paths`.
+///   - profile (bool): Measure where evaluations spend their time, returned
+///                     along with the value and the traces, if collected,
+///                     as `(value, profile)`. The profile has the total
+///                     `time` in seconds, the `nix_time` spent running nix
+///                     code itself, the number of `frames` of nix functions
+///                     and thunks run, a frame counting again whenever it
+///                     resumes after a call, their `max_depth`, the 20
+///                     `builtins` taking the most time, with the `calls` of
+///                     each and the `time` spent in it, excluding the nix
+///                     code it forced, and the 20 `expressions` whose own
+///                     code took the most time. Each expression has its
+///                     `file`, `line` and `column`, the first line of its
+///                     `text`, the `time` spent running it, excluding the
+///                     expressions it contains, and the `forces` and `calls`
+///                     of the thunks and functions whose code starts with
+///                     it. Profiled results are never cached.
+///   - report (bool): Return what each evaluation accessed along with the
+///                    value, last in the tuple of collected results, as
+///                    `(value, report)`, to audit untrusted expressions. The
+///                    report lists the `files` read, listed or checked,
+///                    each with its `path`, `access` and whether it was
+///                    `denied` by `restrict_paths`, the `fetches` attempted,
+///                    each with its `fetcher`, `argument` and `outcome`,
+///                    fetchers the evaluator has no callable for being
+///                    denied, and the `time` in seconds and `peak_memory`
+///                    in bytes of the evaluation. Evaluations are pure, so
+///                    they cannot query environment variables at all. A
+///                    failed evaluation attaches its report to the
+///                    exception raised, as its `report` attribute. Reported
+///                    results are never cached.
+///   - store_paths (bool): Give the paths nix would add to the store the
+///                         `/nix/store` path nix computes for them, hashing
+///                         them like nix does without copying them anywhere,
+///                         instead of their own path. This applies to paths
+///                         interpolated into strings, like `"${./src}"`, and
+///                         to `builtins.path`, with its `name`, `filter`,
+///                         `recursive` and `sha256` arguments. Files of a
+///                         custom `io` are never executable. Such results
+///                         are never cached.
+///   - flake_registry (dict[str, str]): Define `builtins.getFlake`, getting
+///                                      the flakes of this registry, which
+///                                      maps flake ids or references to the
+///                                      directory of a local checkout, like
+///                                      `{"nixpkgs": "/src/nixpkgs"}`, so
+///                                      expressions using it evaluate
+///                                      offline. References are looked up as
+///                                      written, then by their flake id,
+///                                      without their `flake:` scheme and
+///                                      their ref, and absolute paths, with
+///                                      or without `path:`, are used as they
+///                                      are. Flakes are evaluated like
+///                                      `eval_flake`, with the inputs pinned
+///                                      by their `flake.lock`. Without a
+///                                      registry, `getFlake` is not defined.
+///                                      Such results are never cached.
+///   - pinned (dict[str, int | str]): Fixed values for the builtins that
+///                                    depend on the machine or the time of
+///                                    an evaluation, so that snapshots of
+///                                    evaluated values are the same
+///                                    everywhere. `currentTime` is pinned to
+///                                    an int, which defines it in pure
+///                                    evaluations, and `currentSystem` and
+///                                    `nixVersion` to a str. nix has no
+///                                    random builtin, and evaluations are
+///                                    pure, so they cannot read the
+///                                    environment at all.
+///   - int_overflow (str | None): What to do with the Python integers that
+///                                do not fit into 64 bits, given to nix as
+///                                `args`, `scope` or `override` or returned
+///                                by builtins: `error`, `wrap` or
+///                                `bigint-via-string` as described by
+///                                `set_int_overflow`, `None` for the policy it
+///                                selected.
+///   - float_policy (str | None): What to do with the infinite and NaN floats
+///                                of the results and of the values given to
+///                                nix, `"error"`, `"none"` or `"float"`, and
+///                                of the JSON, TOML and YAML written from
+///                                them. `None` follows `set_float_policy`.
+///   - float_repr (str | None): How `eval_to_json`, `eval_to_toml` and
+///                              `eval_to_yaml` write finite floats,
+///                              `"shortest"`, `"decimal"` or `"scientific"`.
+///                              `None` follows `set_float_policy`.
+///   - float_precision (int | None): The digits written after the point by
+///                                   the `decimal` and `scientific` reprs,
+///                                   all of them if `None`.
+///
+/// Raises:
+///   - TypeError: If a builtin or a fetcher is not callable, the io lacks
+///                one of its methods, or a builtin is pinned to a value of
+///                another type than its own.
+///   - ValueError: If a builtin accepts no positional argument, a fetcher is
+///                 unknown or does not accept exactly one, the timeout, the
+///                 memory limit or a depth limit is not positive, the depth,
+///                 the path conversion, the overflow policy or a float policy
+///                 is unknown, a float precision is given without its repr,
+///                 both an io and a cache are given, or a builtin that cannot
+///                 be pinned is.
+///
+/// Messages of `builtins.trace` are emitted with the Python `warnings`
+/// module as `TraceWarning` by default, and so are those of later calls of a
+/// `NixFunction` or `NixThunk`, or of an evaluation that fails. Warnings of
+/// non-strict evaluations are only reported when collecting the traces.
+///
+/// When an evaluation fails with several errors, the exception raised is of
+/// the type of the first one, renders all of them, and lists each of them in
+/// its `errors` attribute. Errors raised in a function or an imported file
+/// show where they were raised, then each call that led there, in the file
+/// of each frame. Errors about an unknown variable or a missing attribute
+/// suggest the names it may be a typo of, among the variables in scope and
+/// the attributes of the attrset it is selected from, when the code defines
+/// them.
+///
+/// Files importing each other so that their imports never finish, like
+/// `a.nix` importing `b.nix` to extend its attrset and `b.nix` doing the same
+/// with `a.nix`, raise an `EvaluationError` naming the files of the cycle,
+/// like `b.nix -> a.nix -> b.nix`, instead of recursing until the memory
+/// runs out. It shows each import of the cycle, and lists its files in its
+/// `cycle` attribute.
+///
+/// Evaluators are immutable, so one can be shared between threads. Each
+/// evaluation runs on the thread calling it, and the builtins are called on
+/// that thread too.
+///
+/// Example:
+/// ```python
+/// >>> evaluator = Evaluator(builtins={"double": lambda x: x * 2})
+/// >>> evaluator.evals("builtins.double 21")
+/// 42
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.nix", frozen)]
+#[derive(Clone, Default)]
+pub struct Evaluator {
+    pub(super) builtins: Vec<PyBuiltin>,
+    pub(super) strict: bool,
+    pub(super) lazy: bool,
+    /// The levels of attrsets and lists results are evaluated down to, all
+    /// of them if `None`
+    pub(super) depth: Option<usize>,
+    pub(super) nix_path: Option<String>,
+    pub(super) cache: Option<Cache>,
+    pub(super) timeout: Option<Duration>,
+    /// The memory limit in bytes
+    pub(super) max_memory: Option<usize>,
+    /// The deepest the evaluated code may nest expressions
+    pub(super) max_depth: Option<usize>,
+    pub(super) max_call_depth: Option<usize>,
+    pub(super) normalize_newlines: bool,
+    pub(super) collect_traces: bool,
+    pub(super) collect_conversion_errors: bool,
+    pub(super) paths_as: PathsAs,
+    pub(super) ordered: bool,
+    /// Where evaluations read files from, the file system if `None`
+    pub(super) io: Option<PyIO>,
+    pub(super) fetch_cache: Option<FetchCache>,
+    /// The only directories evaluations may access, if restricted
+    pub(super) restrict_paths: Option<Arc<[PathBuf]>>,
+    /// The directory an expression is jailed in, on top of `restrict_paths`
+    pub(super) jail: Option<Arc<[PathBuf]>>,
+    pub(super) profile: bool,
+    /// Whether evaluations define `derivation`, describing derivations
+    /// instead of instantiating them
+    pub(super) derivations: bool,
+    /// Whether results come with a report of what their evaluation accessed
+    pub(super) report: bool,
+    /// Whether paths added to the store get the store path nix computes,
+    /// instead of staying where they are
+    pub(super) store_paths: bool,
+    /// The flakes `builtins.getFlake` gets, which is only defined with one
+    pub(super) flake_registry: Option<FlakeRegistry>,
+    /// The builtins given fixed values, for reproducible results
+    pub(super) pinned: Option<Pinned>,
+    /// What is done with Python integers too large for nix, as
+    /// `set_int_overflow` selects if `None`
+    pub(super) int_overflow: Option<IntOverflow>,
+    /// How floats are converted and written, as `set_float_policy` selects
+    /// for the policies left to `None`
+    pub(super) floats: FloatOptions,
+    /// Whether evaluations keep the content of the files they import, to
+    /// locate the definitions of their values
+    pub(super) keep_sources: bool,
+}
+
+impl Evaluator {
+    /// Whether results may hold values left unevaluated, converted to
+    /// `NixThunk`
+    pub(super) fn leaves_thunks(&self) -> bool {
+        self.lazy || self.depth.is_some()
+    }
+
+    /// The overflow policy the Python integers given to evaluations are
+    /// converted under
+    pub(super) fn int_overflow(&self) -> Option<IntOverflow> {
+        self.int_overflow.or_else(overflow::policy)
+    }
+
+    /// The float policies the results of evaluations are converted and
+    /// written under
+    pub(super) fn floats(&self) -> FloatPolicy {
+        self.floats.resolve()
+    }
+
+    /// The policies the Python numbers given to evaluations are converted
+    /// under
+    pub(super) fn number_policies(&self) -> NumberPolicies {
+        NumberPolicies {
+            int_overflow: self.int_overflow(),
+            non_finite: self.floats().non_finite,
+        }
+    }
+
+    /// Evaluate a nix file deeply, whatever the mode, to work on its value
+    /// without converting it to Python objects
+    fn eval_forced(&self, path: String) -> PyResult<(TvixValue, Rc<Context>)> {
+        let path = PathBuf::from(path);
+        let content = self.read_file(&path)?;
+        let content = normalized_newlines(&content, self.normalize_newlines);
+        let forcing = Evaluator {
+            lazy: false,
+            depth: None,
+            ..self.clone()
+        };
+        forcing.eval_expr(&content, Some(path), None, None, None)
+    }
+
+    /// Evaluate a nix file or stream deeply, keeping the files it imports
+    /// to locate the definitions of its values in them
+    fn eval_located(
+        &self,
+        path: FileSource<'_>,
+    ) -> PyResult<(TvixValue, Rc<Context>)> {
+        let content = path.read(|path| self.read_file(path))?;
+        let content = normalized_newlines(&content, self.normalize_newlines);
+        let (location, name) = location_of(&path);
+        let forcing = Evaluator {
+            lazy: false,
+            depth: None,
+            keep_sources: true,
+            ..self.clone()
+        };
+        forcing.eval_expr(&content, location, name.as_deref(), None, None)
+    }
+
+    /// Evaluate a nix file deeply and serialize its value with `write`, as
+    /// functions and thunks have no text form
+    fn eval_to_text(
+        &self,
+        py: Python<'_>,
+        path: String,
+        write: fn(&TvixValue, &FloatPolicy) -> PyResult<String>,
+    ) -> PyResult<String> {
+        let (value, context) = self.eval_forced(path)?;
+        let text = write(&value, &self.floats())?;
+        context.emit_traces(py)?;
+        Ok(text)
+    }
+
+    /// Read a nix file to evaluate, from the IO of this evaluator
+    fn read_file(&self, path: &Path) -> PyResult<String> {
+        let Some(io) = &self.io else {
+            return read_file(path);
+        };
+        io.read(path)
+            .and_then(|content| {
+                String::from_utf8(content).map_err(|e| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+                })
+            })
+            .map_err(|e| {
+                PyIOError::new_err(format!(
+                    "Failed to read file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+
+    /// The IO evaluations access files with, before recording the accesses
+    fn io_handle(&self) -> Rc<dyn EvalIO> {
+        let io: Rc<dyn EvalIO> = match &self.io {
+            Some(io) => Rc::new(io.clone()),
+            None => Rc::new(StdIO),
+        };
+        let io = match &self.restrict_paths {
+            Some(allowed) => Rc::new(SandboxIO::new(io, allowed.clone())),
+            None => io,
+        };
+        let io = match &self.jail {
+            Some(dir) => Rc::new(SandboxIO::new(io, dir.clone())),
+            None => io,
+        };
+        if self.store_paths {
+            Rc::new(StoreIO::new(io))
+        } else {
+            io
+        }
+    }
+
+    /// Build an evaluation with the settings of this evaluator
+    fn evaluation<'ro, 'env>(
+        &self,
+        scope: Option<&'env Scope>,
+        source: Option<SourceCode>,
+        pending: &PendingError,
+        traces: &Traces,
+        missing: &Missing,
+        fetches: &Fetches,
+        io: Rc<RecordingIO>,
+        observer: Option<&'ro mut dyn RuntimeObserver>,
+        budget: Option<&Rc<Budget>>,
+    ) -> Evaluation<'static, 'ro, 'env, Rc<dyn EvalIO>> {
+        // FIXME: This is a hack to make the evaluation result to be a JSON
+        // object
+        let mode = if self.leaves_thunks() {
+            EvalMode::Lazy
+        } else {
+            EvalMode::Strict
+        };
+        let io = io as Rc<dyn EvalIO>;
+        let mut builder = Evaluation::builder_pure()
+            .io_handle(io.clone())
+            .enable_import()
+            .nix_path(self.nix_path.clone())
+            .mode(mode)
+            // Builtins check the limits of the evaluation as they are applied
+            .add_builtins(budget.into_iter().flat_map(|budget| {
+                pure_builtins().into_iter().map(move |(name, value)| {
+                    (name, budget.guarded(name, value))
+                })
+            }))
+            // Custom builtins come last, they may replace `trace` too
+            .add_builtins([("trace", trace_builtin(traces))])
+            .add_builtins(
+                FETCHERS
+                    .iter()
+                    .filter(|&&name| {
+                        self.report
+                            && !self.builtins.iter().any(|b| b.name == name)
+                    })
+                    .map(|&name| (name, denied_fetcher(name, fetches))),
+            )
+            .add_builtins(self.builtins.iter().map(|b| {
+                let fetch_cache = self.fetch_cache.as_ref();
+                let policies = self.number_policies();
+                (
+                    b.name,
+                    b.to_builtin(
+                        pending,
+                        fetch_cache,
+                        missing,
+                        fetches,
+                        policies,
+                    ),
+                )
+            }))
+            .env(scope)
+            .runtime_observer(observer);
+        if let Some(source) = source {
+            builder = builder.source_map(source);
+        }
+        if self.store_paths {
+            builder = builder.add_builtins([("path", path_builtin(io))]);
+        }
+        if let Some(pinned) = &self.pinned {
+            builder = builder.add_builtins(
+                pinned.iter().map(|(name, pin)| (*name, pin.to_value())),
+            );
+        }
+        if let Some(registry) = &self.flake_registry {
+            builder = builder
+                .add_builtins([(
+                    "__cosutilsResolveFlake",
+                    resolve_flake_builtin(registry, pending),
+                )])
+                .add_src_builtin("__cosutilsCallFlake", CALL_FLAKE)
+                .add_src_builtin("getFlake", GET_FLAKE);
+        }
+        if self.derivations {
+            builder = builder
+                .add_builtins([(
+                    "__cosutilsFixedOutputPath",
+                    fixed_output_builtin(),
+                )])
+                .add_src_builtin("derivation", DERIVATION);
+        }
+        builder.build()
+    }
+
+    /// Parse and evaluate a nix expression, applying it to `args` if given,
+    /// with the variables of `scope` in its top-level scope. Diagnostics
+    /// name the expression by its `location`, or by `name` when it has none.
+    pub(super) fn eval_expr(
+        &self,
+        expr: &str,
+        location: Option<PathBuf>,
+        name: Option<&str>,
+        args: Option<TvixValue>,
+        scope: Option<&Scope>,
+    ) -> PyResult<(TvixValue, Rc<Context>)> {
+        self.eval_expr_within(expr, location, name, args, scope, None)
+    }
+
+    /// Like `eval_expr`, but sharing the source map, the recorded traces,
+    /// fetches and accessed files of an earlier evaluation `within`, so
+    /// that its values can be in `scope`
+    pub(super) fn eval_expr_within(
+        &self,
+        expr: &str,
+        location: Option<PathBuf>,
+        name: Option<&str>,
+        args: Option<TvixValue>,
+        scope: Option<&Scope>,
+        within: Option<&Context>,
+    ) -> PyResult<(TvixValue, Rc<Context>)> {
+        let origin = match &location {
+            Some(location) => location.to_string_lossy().into_owned(),
+            None => name.unwrap_or("tempfile").to_string(),
+        };
+        let (shared, pending, traces, missing, fetches, imports, io) =
+            match within {
+                Some(c) => (
+                    Some(c.source.clone()),
+                    c.pending.clone(),
+                    c.traces.clone(),
+                    c.missing.clone(),
+                    c.fetches.clone(),
+                    c.imports.clone(),
+                    Rc::clone(&c.io),
+                ),
+                None => {
+                    let imports = match self.keep_sources {
+                        true => Imports::keeping_sources(),
+                        false => Imports::default(),
+                    };
+                    let io = ImportIO::new(self.io_handle(), imports.clone());
+                    (
+                        None,
+                        PendingError::default(),
+                        Traces::default(),
+                        Missing::default(),
+                        Fetches::default(),
+                        imports,
+                        Rc::new(RecordingIO::new(Rc::new(io))),
+                    )
+                }
+            };
+        if let Some(max_depth) = self.max_depth {
+            let root = parse_root(expr, Some(&origin))?;
+            check_nesting(expr, Some(&origin), &root, max_depth)?;
+        }
+        let mut limits =
+            Limits::new(self.timeout, self.max_memory, self.max_call_depth);
+        let mut profile = self.profile.then(Profile::default);
+        let started = Instant::now();
+        let (run, peak_memory) = peak_memory(self.report, || {
+            Limits::run(limits.as_mut(), |observer, budget| {
+                Profile::run(profile.as_mut(), observer, |observer| {
+                    imports.run(observer, |observer| {
+                        let eval = self.evaluation(
+                            scope,
+                            shared,
+                            &pending,
+                            &traces,
+                            &missing,
+                            &fetches,
+                            Rc::clone(&io),
+                            observer,
+                            budget,
+                        );
+                        let location = base_location(location);
+                        (eval.source_map(), eval.evaluate(expr, location))
+                    })
+                })
+            })
+        });
+        let report = self.report.then(|| Report {
+            time: started.elapsed(),
+            peak_memory,
+            fetches: fetches.clone(),
+        });
+        let (source, result) = match (run, &report) {
+            (Ok(run), _) => run,
+            (Err(error), Some(report)) => {
+                return Err(report.attached(error, &io))
+            }
+            (Err(error), None) => return Err(error),
+        };
+        let context = Rc::new(Context {
+            expr: expr.to_string(),
+            origin,
+            source,
+            pending,
+            traces,
+            missing,
+            fetches,
+            imports,
+            io,
+            profile: profile.map(RefCell::new),
+            report,
+            evaluator: self.clone(),
+        });
+        let value = match context.into_value(result) {
+            Ok(value) => value,
+            Err(error) => {
+                // The traces often explain the failure, they are not dropped
+                Python::with_gil(|py| context.emit_traces(py))?;
+                return Err(context.reported(error));
+            }
+        };
+
+        match args {
+            Some(args) => match context.apply(value, args) {
+                Ok(value) => Ok((value, context)),
+                Err(error) => Err(context.reported(error)),
+            },
+            None => Ok((value, context)),
+        }
+    }
+
+    /// An evaluator with the settings shared by the module functions, the
+    /// ones not given taken from the selected profile, and the others left
+    /// to their defaults
+    pub(super) fn from_profile(
+        strict: Option<bool>,
+        nix_path: Option<NixPath>,
+        timeout: Option<f64>,
+        max_memory_mb: Option<usize>,
+        normalize_newlines: bool,
+    ) -> PyResult<Self> {
+        let selected = profile::current();
+        Ok(Self {
+            strict: strict.unwrap_or(selected.nix_strict),
+            nix_path: nix_path.map(NixPath::into_string),
+            timeout: timeout
+                .or(selected.nix_timeout)
+                .map(timeout_from_secs)
+                .transpose()?,
+            max_memory: max_memory_mb
+                .or(selected.nix_max_memory_mb)
+                .map(memory_from_mb)
+                .transpose()?,
+            normalize_newlines,
+            ..Default::default()
+        })
+    }
+
+    /// This evaluator, only letting evaluations access the files of `dir`
+    fn jailed(&self, dir: Option<&str>) -> PyResult<Evaluator> {
+        let Some(dir) = dir else {
+            return Err(PyValueError::new_err(
+                "An expression can only be jailed in the directory given as \
+                `dir`",
+            ));
+        };
+        Ok(Evaluator {
+            jail: Some(allowed_paths(vec![PathBuf::from(dir)])),
+            ..self.clone()
+        })
+    }
+
+    /// Key of the cache under which the result of an evaluation is stored
+    fn cache_key(
+        &self,
+        expr: &str,
+        location: Option<&PathBuf>,
+        bindings: [Option<&Bound<'_, PyDict>>; 3],
+    ) -> PyResult<String> {
+        let mut hasher = blake3::Hasher::new();
+        let mut field = |bytes: &[u8]| {
+            hasher.update(&(bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        field(env!("CARGO_PKG_VERSION").as_bytes());
+        field(&[self.strict as u8]);
+        field(self.paths_as.name().as_bytes());
+        field(self.int_overflow().map_or("", IntOverflow::name).as_bytes());
+        field(
+            self.floats()
+                .non_finite
+                .map_or("", NonFinite::name)
+                .as_bytes(),
+        );
+        field(self.nix_path.as_deref().unwrap_or_default().as_bytes());
+        for builtin in &self.builtins {
+            field(
+                format!(
+                    "{}/{}/{}",
+                    builtin.name, builtin.arity, builtin.fetcher
+                )
+                .as_bytes(),
+            );
+        }
+        // The sandbox decides which files the evaluation could read, with
+        // `allow_import=False` restricting it to no path at all
+        field(&[self.restrict_paths.is_some() as u8]);
+        for path in self.restrict_paths.iter().flat_map(|p| p.iter()) {
+            field(path.to_string_lossy().as_bytes());
+        }
+        // So does the directory of an expression when it is jailed
+        field(&[self.jail.is_some() as u8]);
+        for path in self.jail.iter().flat_map(|p| p.iter()) {
+            field(path.to_string_lossy().as_bytes());
+        }
+        for (name, pin) in self.pinned.iter().flat_map(|p| p.iter()) {
+            let value = match pin {
+                Pin::Int(i) => i.to_string(),
+                Pin::Str(s) => format!("{:?}", s),
+            };
+            field(format!("{}={}", name, value).as_bytes());
+        }
+        // Relative paths of an expression without location are resolved
+        // from the current directory
+        let base = location.cloned().or_else(|| env::current_dir().ok());
+        field(
+            base.as_ref()
+                .map(|l| l.to_string_lossy())
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+        field(expr.as_bytes());
+        // The args, scope and overrides of the evaluation
+        for bindings in bindings {
+            let mut encoded = Vec::new();
+            if let Some(bindings) = bindings {
+                encode(bindings.as_any(), &mut encoded)?;
+            }
+            field(&encoded);
+        }
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    /// Evaluate an expression and convert its result, going through the
+    /// cache if enabled, with `overrides` merged into its result
+    #[allow(clippy::too_many_arguments)]
+    fn eval_to_python(
+        &self,
+        py: Python<'_>,
+        expr: &str,
+        location: Option<PathBuf>,
+        name: Option<&str>,
+        args: Option<Bound<'_, PyDict>>,
+        scope: Option<Bound<'_, PyDict>>,
+        overrides: Option<Bound<'_, PyDict>>,
+    ) -> PyResult<PyObject> {
+        // Lazy results still refer to their evaluation, they cannot be cached,
+        // and neither can the traces, profiles or reports of collecting
+        // evaluations, or the order of attributes, lost by JSON objects.
+        // Store paths depend on files under the paths, and flakes on their
+        // lock files, which the cache does not watch, and files read through
+        // a Python `io` object are not the ones on disk it fingerprints.
+        let cached = match self.cache.as_ref().filter(|_| {
+            !self.leaves_thunks()
+                && !self.collect_traces
+                && !self.profile
+                && !self.report
+                && !self.ordered
+                && !self.store_paths
+                && self.flake_registry.is_none()
+                && self.io.is_none()
+        }) {
+            Some(cache) => Some((
+                cache,
+                self.cache_key(
+                    expr,
+                    location.as_ref(),
+                    [args.as_ref(), scope.as_ref(), overrides.as_ref()],
+                )?,
+            )),
+            None => None,
+        };
+        if let Some(value) = cached.as_ref().and_then(|(c, k)| c.get(k)) {
+            return json_to_py(py, &value);
+        }
+
+        let policies = self.number_policies();
+        let args = args
+            .map(|a| from_python(a.as_any(), policies))
+            .transpose()?;
+        let scope =
+            scope.as_ref().map(|s| scope_of(s, policies)).transpose()?;
+        let overrides = overrides
+            .map(|o| from_python(o.as_any(), policies))
+            .transpose()?;
+        let (value, context) =
+            self.eval_expr(expr, location, name, args, scope.as_ref())?;
+        let value = match overrides {
+            Some(overrides) => context.recursive_update(value, overrides)?,
+            None => value,
+        };
+        let object = self.converted(py, expr, &value, &context, false)?;
+
+        if let Some((cache, key)) = cached {
+            if let Some(value) = py_to_json(object.bind(py)) {
+                cache.put(&key, value, &context.io);
+            }
+        }
+        Ok(object)
+    }
+
+    /// Convert the value an evaluation of `expr` produced, along with its
+    /// traces and profile when collecting them, or else emitting its traces.
+    /// Objects `detached` from the thread of the evaluation cannot hold its
+    /// functions.
+    pub(super) fn converted(
+        &self,
+        py: Python<'_>,
+        expr: &str,
+        value: &TvixValue,
+        context: &Rc<Context>,
+        detached: bool,
+    ) -> PyResult<PyObject> {
+        let forced;
+        let value = match self.depth {
+            Some(levels) if !self.lazy => {
+                forced = context.force_levels(value.clone(), levels)?;
+                &forced
+            }
+            _ => value,
+        };
+        let order = self.ordered.then(|| KeyOrder::of_source(expr));
+        let object = Converter {
+            context: Some(context),
+            order: order.as_ref(),
+            detached,
+        }
+        .convert(py, value)?;
+
+        if self.collect_traces || self.profile || self.report {
+            return context.collected(py, object);
+        }
+        context.emit_traces(py)?;
+        Ok(object)
+    }
+}
+
+/// What is left of a finished evaluation, needed to keep evaluating the
+/// values it produced
+pub(super) struct Context {
+    expr: String,
+    /// The name of the evaluated file or expression in diagnostics
+    origin: String,
+    source: SourceCode,
+    pending: PendingError,
+    traces: Traces,
+    missing: Missing,
+    fetches: Fetches,
+    /// The imports in progress, to fail on an import cycle
+    imports: Imports,
+    /// The IO of the evaluation, recording the paths it accessed
+    pub io: Rc<RecordingIO>,
+    /// Where the evaluations of the context spent their time, if profiled
+    profile: Option<RefCell<Profile>>,
+    /// What the evaluation accessed and consumed, if reported
+    report: Option<Report>,
+    /// The settings the evaluation was run with
+    pub evaluator: Evaluator,
+}
+
+impl Context {
+    /// The files the evaluation read, the evaluated one and, if its
+    /// evaluator kept them, those it imported
+    pub fn sources(&self) -> Sources {
+        Sources::new(&self.origin, &self.expr, self.imports.clone())
+    }
+
+    /// Extract the value of an evaluation, or render its error
+    fn into_value(&self, result: EvaluationResult) -> PyResult<TvixValue> {
+        let location = &self.origin;
+
+        // Whatever the result, it was computed from placeholders
+        let missing = self.missing.take();
+        if !missing.is_empty() {
+            return Err(coded_err::<EvaluationError>(
+                ErrorCode::NixFetchOffline,
+                format!(
+                    "The evaluation needs resources that are not fetched \
+                    yet, but its fetch cache is offline:\n{}",
+                    missing
+                        .iter()
+                        .map(|m| format!("  - {}", m))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            ));
+        }
+
+        if let Some(value) = result.value {
+            if self.evaluator.strict && !result.warnings.is_empty() {
+                return Err(warnings_into_pyerr(
+                    &result.warnings,
+                    &self.expr,
+                    location,
+                ));
+            }
+            if self.evaluator.collect_traces {
+                let warnings = result.warnings.iter().map(|w| {
+                    // Warnings of imported files have no line in `expr`
+                    let range = w.span.into_range();
+                    Trace {
+                        kind: "warning",
+                        message: describe_warning(&w.kind),
+                        line: (range.end <= self.expr.len()).then(|| {
+                            self.expr[..range.start].matches('\n').count() + 1
+                        }),
+                    }
+                });
+                self.traces.borrow_mut().extend(warnings);
+            }
+            Ok(value)
+        } else {
+            let source = display_source(&self.expr);
+            let mut errors = unhooked(|| {
+                result
+                    .errors
+                    .into_iter()
+                    .map(|e| {
+                        e.into_pyerr(
+                            Snippet::source(&source)
+                                .origin(location)
+                                .fold(true),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .into_iter();
+            let Some(first) = errors.next() else {
+                return Err(coded_err::<EvaluationError>(
+                    ErrorCode::NixEvaluation,
+                    "No error is throwed but evaluation failed".to_string(),
+                ));
+            };
+            // The error of an import cycle is raised by the IO, and only
+            // rendered as an I/O error by tvix
+            if let Some(cycle) = self.imports.take_cycle() {
+                Python::with_gil(|py| {
+                    let value = first.value(py);
+                    let cycle = cycle
+                        .iter()
+                        .map(|p| p.to_string_lossy())
+                        .collect::<Vec<_>>();
+                    // Setting attributes on an exception cannot fail
+                    let code = ErrorCode::NixImportCycle.as_str();
+                    let _ = value.setattr("code", code);
+                    let _ = value.setattr("cycle", cycle);
+                });
+            }
+            // Render every error, not only the first one
+            let error = aggregated_err(first, errors.collect());
+            if let Some(cause) = self.pending.borrow_mut().take() {
+                Python::with_gil(|py| error.set_cause(py, Some(cause)));
+            }
+            Err(hooked(error))
+        }
+    }
+
+    /// Attach the report of the evaluation to an error it failed with, if
+    /// reporting
+    fn reported(&self, error: PyErr) -> PyErr {
+        match &self.report {
+            Some(report) => report.attached(error, &self.io),
+            None => error,
+        }
+    }
+
+    /// Take the messages recorded by the evaluations of this context so far
+    fn take_traces(&self, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self.traces
+            .take()
+            .iter()
+            .map(|t| t.try_to_pyobject(py))
+            .collect()
+    }
+
+    /// Return a converted value of this context along with its traces,
+    /// profile and report when collecting them, emitting the traces
+    /// otherwise
+    pub fn collected(
+        &self,
+        py: Python<'_>,
+        object: PyObject,
+    ) -> PyResult<PyObject> {
+        let traces = if self.evaluator.collect_traces {
+            Some(self.take_traces(py)?)
+        } else {
+            self.emit_traces(py)?;
+            None
+        };
+        let profile = self
+            .profile
+            .as_ref()
+            .map(|p| p.borrow().to_pyobject(py, &self.source))
+            .transpose()?;
+        let report = self
+            .report
+            .as_ref()
+            .map(|r| r.to_pyobject(py, &self.io))
+            .transpose()?;
+        if traces.is_none() && profile.is_none() && report.is_none() {
+            return Ok(object);
+        }
+        let mut collected = vec![object];
+        if let Some(traces) = traces {
+            collected.push(PyList::new(py, traces)?.into_any().unbind());
+        }
+        collected.extend(profile);
+        collected.extend(report);
+        Ok(PyTuple::new(py, collected)?.into_any().unbind())
+    }
+
+    /// Emit the messages recorded by the evaluations of this context so far
+    /// as `TraceWarning`
+    pub fn emit_traces(&self, py: Python<'_>) -> PyResult<()> {
+        let warnings = py.import("warnings")?;
+        for trace in self.traces.take() {
+            warnings.call_method1(
+                "warn",
+                (
+                    format!("{}: {}", trace.kind, trace.message),
+                    py.get_type::<TraceWarning>(),
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Call a function value of this evaluation with `argument`
+    pub fn apply(
+        &self,
+        function: TvixValue,
+        argument: TvixValue,
+    ) -> PyResult<TvixValue> {
+        if !matches!(function, TvixValue::Closure(_) | TvixValue::Builtin(_)) {
+            return Err(coded_err::<EvaluationError>(
+                ErrorCode::NixEvaluation,
+                format!(
+                    "Arguments were given, but the expression evaluates to a \
+                    {} instead of a function",
+                    function.type_of()
+                ),
+            ));
+        }
+
+        self.evaluate_in_scope(
+            &format!("{} {}", FUNCTION_BINDING, ARGUMENT_BINDING),
+            [(FUNCTION_BINDING, function), (ARGUMENT_BINDING, argument)],
+        )
+    }
+
+    /// Evaluate a thunk of this evaluation, nested values are only forced in
+    /// strict mode
+    pub fn force(&self, thunk: TvixValue) -> PyResult<TvixValue> {
+        self.evaluate_in_scope(VALUE_BINDING, [(VALUE_BINDING, thunk)])
+    }
+
+    /// Evaluate a value of this evaluation to weak head normal form, only
+    /// forcing thunks that are not evaluated yet
+    pub fn whnf(&self, value: TvixValue) -> PyResult<TvixValue> {
+        match value {
+            TvixValue::Thunk(thunk) if thunk.is_evaluated() => {
+                Ok(thunk.value().clone())
+            }
+            TvixValue::Thunk(_) => self.force(value),
+            value => Ok(value),
+        }
+    }
+
+    /// Evaluate a value of this evaluation and all the values nested in it,
+    /// so that it converts to plain data
+    pub fn force_deep(&self, value: TvixValue) -> PyResult<TvixValue> {
+        self.evaluate_in_scope(
+            &format!("builtins.deepSeq {0} {0}", VALUE_BINDING),
+            [(VALUE_BINDING, value)],
+        )
+    }
+
+    /// Evaluate a value of this evaluation down to `levels` of attrsets and
+    /// lists, the values below them being left as they are
+    pub fn force_levels(
+        &self,
+        value: TvixValue,
+        levels: usize,
+    ) -> PyResult<TvixValue> {
+        self.evaluate_in_scope(
+            &format!("({}) {} {}", FORCE_LEVELS, levels, VALUE_BINDING),
+            [(VALUE_BINDING, value)],
+        )
+    }
+
+    /// Merge `overrides` into a value of this evaluation like
+    /// `lib.recursiveUpdate`
+    pub fn recursive_update(
+        &self,
+        value: TvixValue,
+        overrides: TvixValue,
+    ) -> PyResult<TvixValue> {
+        self.evaluate_in_scope(
+            &format!(
+                "({}) {} {}",
+                RECURSIVE_UPDATE, VALUE_BINDING, ARGUMENT_BINDING
+            ),
+            [(VALUE_BINDING, value), (ARGUMENT_BINDING, overrides)],
+        )
+    }
+
+    /// Describe a derivation of this evaluation by the attributes it would
+    /// be built with, evaluated deeply
+    pub fn describe_derivation(&self, drv: TvixValue) -> PyResult<TvixValue> {
+        self.evaluate_in_scope(
+            &format!(
+                "let d = ({}) {}; in builtins.deepSeq d d",
+                DESCRIBE_DERIVATION, VALUE_BINDING
+            ),
+            [(VALUE_BINDING, drv)],
+        )
+    }
+
+    /// Evaluate `code` referring to values of this evaluation by `bindings`
+    fn evaluate_in_scope<const N: usize>(
+        &self,
+        code: &str,
+        bindings: [(&str, TvixValue); N],
+    ) -> PyResult<TvixValue> {
+        // Evaluate in another evaluation sharing the same source map, so
+        // that errors raised inside the values still point into `expr`
+        let scope = Scope::from_iter(
+            bindings
+                .into_iter()
+                .map(|(name, value)| (SmolStr::new(name), value)),
+        );
+        let mut limits = Limits::new(
+            self.evaluator.timeout,
+            self.evaluator.max_memory,
+            self.evaluator.max_call_depth,
+        );
+        // A builtin evaluating in this context while it is being profiled is
+        // measured as part of the builtin
+        let mut profile =
+            self.profile.as_ref().and_then(|p| p.try_borrow_mut().ok());
+        let result = Limits::run(limits.as_mut(), |observer, budget| {
+            Profile::run(profile.as_deref_mut(), observer, |observer| {
+                self.imports.run(observer, |observer| {
+                    self.evaluator
+                        .evaluation(
+                            Some(&scope),
+                            Some(self.source.clone()),
+                            &self.pending,
+                            &self.traces,
+                            &self.missing,
+                            &self.fetches,
+                            Rc::clone(&self.io),
+                            observer,
+                            budget,
+                        )
+                        .evaluate(code, None)
+                })
+            })
+        })?;
+        self.into_value(result)
+    }
+}
+
+#[pymethods]
+impl Evaluator {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        builtins = None,
+        strict = None,
+        lazy = false,
+        depth = None,
+        nix_path = None,
+        cache = None,
+        timeout = None,
+        max_memory_mb = None,
+        max_depth = None,
+        max_call_depth = None,
+        normalize_newlines = true,
+        collect_traces = false,
+        collect_conversion_errors = false,
+        paths_as = "str",
+        ordered = false,
+        fetchers = None,
+        io = None,
+        fetch_cache = None,
+        restrict_paths = None,
+        allow_import = true,
+        profile = false,
+        report = false,
+        store_paths = false,
+        flake_registry = None,
+        pinned = None,
+        int_overflow = None,
+        float_policy = None,
+        float_repr = None,
+        float_precision = None
+    ))]
+    fn new(
+        builtins: Option<Bound<'_, PyDict>>,
+        strict: Option<bool>,
+        lazy: bool,
+        depth: Option<DepthOption>,
+        nix_path: Option<NixPath>,
+        cache: Option<CacheOption>,
+        timeout: Option<f64>,
+        max_memory_mb: Option<usize>,
+        max_depth: Option<usize>,
+        max_call_depth: Option<usize>,
+        normalize_newlines: bool,
+        collect_traces: bool,
+        collect_conversion_errors: bool,
+        paths_as: &str,
+        ordered: bool,
+        fetchers: Option<Bound<'_, PyDict>>,
+        io: Option<Bound<'_, PyAny>>,
+        fetch_cache: Option<FetchCache>,
+        restrict_paths: Option<Vec<PathBuf>>,
+        allow_import: bool,
+        profile: bool,
+        report: bool,
+        store_paths: bool,
+        flake_registry: Option<FxHashMap<String, PathBuf>>,
+        pinned: Option<Bound<'_, PyDict>>,
+        int_overflow: Option<&str>,
+        float_policy: Option<&str>,
+        float_repr: Option<&str>,
+        float_precision: Option<usize>,
+    ) -> PyResult<Self> {
+        // Nothing can be imported from an empty list of directories
+        let restrict_paths = if allow_import {
+            restrict_paths.map(allowed_paths)
+        } else {
+            Some(Arc::from([]))
+        };
+        let io = io.as_ref().map(PyIO::new).transpose()?;
+        let cache = cache.and_then(CacheOption::into_cache);
+        if io.is_some() && cache.is_some() {
+            // Cached results are invalidated by watching the file system
+            return Err(PyValueError::new_err(
+                "The cache cannot be used with a custom io",
+            ));
+        }
+        // The builtins come last, so they replace fetchers of the same name
+        let fetchers =
+            fetchers
+                .iter()
+                .flat_map(|f| f.iter())
+                .map(|(name, callable)| {
+                    PyBuiltin::fetcher(name.extract::<String>()?, &callable)
+                });
+        let builtins = fetchers
+            .chain(builtins.iter().flat_map(|b| b.iter()).map(
+                |(name, callable)| {
+                    PyBuiltin::new(name.extract::<String>()?, &callable)
+                },
+            ))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Self {
+            builtins,
+            lazy,
+            depth: depth.map(DepthOption::into_levels).transpose()?.flatten(),
+            cache,
+            max_depth: max_depth
+                .map(|d| depth_limit(d, "Nesting limit"))
+                .transpose()?,
+            max_call_depth: max_call_depth
+                .map(|d| depth_limit(d, "Call depth limit"))
+                .transpose()?,
+            collect_traces,
+            collect_conversion_errors,
+            paths_as: PathsAs::of_name(paths_as)?,
+            ordered,
+            io,
+            fetch_cache,
+            restrict_paths,
+            jail: None,
+            profile,
+            derivations: false,
+            report,
+            store_paths,
+            flake_registry: flake_registry.map(Arc::new),
+            pinned: pinned.as_ref().map(pinned_builtins).transpose()?,
+            int_overflow: int_overflow.map(IntOverflow::of_name).transpose()?,
+            floats: FloatOptions::new(
+                float_policy,
+                float_repr,
+                float_precision,
+            )?,
+            ..Self::from_profile(
+                strict,
+                nix_path,
+                timeout,
+                max_memory_mb,
+                normalize_newlines,
+            )?
+        })
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let settings = PyDict::new(py);
+        let (fetchers, builtins): (Vec<_>, Vec<_>) =
+            self.builtins.iter().partition(|b| b.fetcher);
+        let names = |b: Vec<&PyBuiltin>| b.iter().map(|b| b.name).collect();
+        settings.set_item("builtins", names(builtins) as Vec<_>)?;
+        settings.set_item("strict", self.strict)?;
+        settings.set_item("lazy", self.lazy)?;
+        settings.set_item("depth", self.depth)?;
+        settings.set_item("nix_path", &self.nix_path)?;
+        match self.cache.as_ref().map(Cache::dir) {
+            None => settings.set_item("cache", py.None())?,
+            Some(None) => settings.set_item("cache", true)?,
+            Some(Some(dir)) => {
+                settings.set_item("cache", dir.to_string_lossy())?
+            }
+        }
+        settings.set_item("timeout", self.timeout.map(|t| t.as_secs_f64()))?;
+        settings.set_item("max_memory_mb", self.max_memory.map(|m| m >> 20))?;
+        settings.set_item("max_depth", self.max_depth)?;
+        settings.set_item("max_call_depth", self.max_call_depth)?;
+        settings.set_item("normalize_newlines", self.normalize_newlines)?;
+        settings.set_item("collect_traces", self.collect_traces)?;
+        settings.set_item(
+            "collect_conversion_errors",
+            self.collect_conversion_errors,
+        )?;
+        settings.set_item("paths_as", self.paths_as.name())?;
+        settings.set_item("ordered", self.ordered)?;
+        settings.set_item("fetchers", names(fetchers) as Vec<_>)?;
+        settings.set_item("io", self.io.as_ref().map(|io| io.object(py)))?;
+        settings.set_item("fetch_cache", self.fetch_cache.clone())?;
+        settings.set_item(
+            "restrict_paths",
+            self.restrict_paths.as_ref().map(|p| {
+                p.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>()
+            }),
+        )?;
+        let allow_import =
+            !self.restrict_paths.as_ref().is_some_and(|p| p.is_empty());
+        settings.set_item("allow_import", allow_import)?;
+        settings.set_item("profile", self.profile)?;
+        settings.set_item("report", self.report)?;
+        settings.set_item("store_paths", self.store_paths)?;
+        let registry = self.flake_registry.as_ref().map(|registry| {
+            let mut flakes = registry
+                .iter()
+                .map(|(id, dir)| (id.as_str(), dir.to_string_lossy()))
+                .collect::<Vec<_>>();
+            flakes.sort();
+            flakes.into_py_dict(py)
+        });
+        settings.set_item("flake_registry", registry.transpose()?)?;
+        let pinned = self.pinned.as_ref().map(|pinned| {
+            pinned
+                .iter()
+                .map(|(name, pin)| (*name, pin.object(py)))
+                .collect::<Vec<_>>()
+                .into_py_dict(py)
+        });
+        settings.set_item("pinned", pinned.transpose()?)?;
+        settings.set_item(
+            "int_overflow",
+            self.int_overflow.map(IntOverflow::name),
+        )?;
+        settings.set_item(
+            "float_policy",
+            self.floats.non_finite.map(NonFinite::name),
+        )?;
+        settings
+            .set_item("float_repr", self.floats.repr.map(FloatRepr::name))?;
+        settings.set_item("float_precision", self.floats.precision)?;
+        let settings = settings
+            .iter()
+            .map(|(name, value)| Ok(format!("{}={}", name, value.repr()?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(format!("Evaluator({})", settings.join(", ")))
+    }
+
+    fn _repr_pretty_(
+        slf: &Bound<'_, Self>,
+        printer: &Bound<'_, PyAny>,
+        _cycle: bool,
+    ) -> PyResult<()> {
+        printer.call_method1("text", (slf.repr()?,))?;
+        Ok(())
+    }
+
+    /// Evaluate a nix file with this evaluator, see `eval`.
+    #[pyo3(signature = (path, r#override = None))]
+    pub fn eval(
+        &self,
+        py: Python<'_>,
+        path: FileSource<'_>,
+        r#override: Option<Bound<'_, PyDict>>,
+    ) -> PyResult<PyObject> {
+        let content = path.read(|path| self.read_file(path))?;
+        let content = normalized_newlines(&content, self.normalize_newlines);
+        let (location, name) = location_of(&path);
+        self.eval_to_python(
+            py,
+            &content,
+            location,
+            name.as_deref(),
+            None,
+            None,
+            r#override,
+        )
+    }
+
+    /// Evaluate nix files in parallel with this evaluator, see `eval_many`.
+    #[pyo3(signature = (paths, jobs = None))]
+    pub fn eval_many(
+        &self,
+        py: Python<'_>,
+        paths: Vec<String>,
+        jobs: Option<usize>,
+    ) -> PyResult<Vec<PyObject>> {
+        let jobs = threads::threads(jobs)?;
+        // Thunks cannot leave the thread of their evaluation
+        let forcing = Evaluator {
+            lazy: false,
+            depth: None,
+            ..self.clone()
+        };
+        let results = threads::map(py, &paths, jobs, |path| {
+            let path = PathBuf::from(path);
+            let content = forcing.read_file(&path)?;
+            let content =
+                normalized_newlines(&content, forcing.normalize_newlines);
+            // Only the conversion needs the GIL, not the evaluation
+            let (value, context) =
+                forcing.eval_expr(&content, Some(path), None, None, None)?;
+            Python::with_gil(|py| {
+                forcing.converted(py, &content, &value, &context, true)
+            })
+        });
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|e| e.into_value(py).into_any())
+            })
+            .collect())
+    }
+
+    /// Evaluate a nix file with this evaluator to a JSON string, see
+    /// `eval_to_json`.
+    pub fn eval_to_json(
+        &self,
+        py: Python<'_>,
+        path: String,
+    ) -> PyResult<String> {
+        self.eval_to_text(py, path, tvix_to_json)
+    }
+
+    /// Evaluate a nix file with this evaluator to a TOML document, see
+    /// `eval_to_toml`.
+    pub fn eval_to_toml(
+        &self,
+        py: Python<'_>,
+        path: String,
+    ) -> PyResult<String> {
+        self.eval_to_text(py, path, tvix_to_toml)
+    }
+
+    /// Evaluate a nix file with this evaluator to a YAML document, see
+    /// `eval_to_yaml`.
+    pub fn eval_to_yaml(
+        &self,
+        py: Python<'_>,
+        path: String,
+    ) -> PyResult<String> {
+        self.eval_to_text(py, path, tvix_to_yaml)
+    }
+
+    /// Evaluate two nix files with this evaluator and diff their values, see
+    /// `diff`.
+    pub fn diff(
+        &self,
+        py: Python<'_>,
+        path_a: String,
+        path_b: String,
+    ) -> PyResult<PyObject> {
+        let (old, old_context) = self.eval_forced(path_a)?;
+        let (new, new_context) = self.eval_forced(path_b)?;
+        let changes = changes(&old, &new);
+        old_context.emit_traces(py)?;
+        new_context.emit_traces(py)?;
+        changes_to_py(py, &changes, &old_context, &new_context)
+    }
+
+    /// Evaluate a nix file with this evaluator and validate its value
+    /// against a JSON Schema, see `eval_validated`.
+    pub fn eval_validated(
+        &self,
+        py: Python<'_>,
+        path: FileSource<'_>,
+        schema: Bound<'_, PyDict>,
+    ) -> PyResult<PyObject> {
+        let (value, context) = self.eval_located(path)?;
+        let violations = violations(&value, &schema)?;
+        context.emit_traces(py)?;
+        if !violations.is_empty() {
+            check_violations(&context.sources(), &violations)?;
+        }
+        Converter {
+            context: Some(&context),
+            order: None,
+            detached: false,
+        }
+        .convert(py, &value)
+    }
+
+    /// Evaluate a nix file with this evaluator, along with the positions of
+    /// the definitions of its attributes, see `eval_with_positions`.
+    pub fn eval_with_positions(
+        &self,
+        py: Python<'_>,
+        path: FileSource<'_>,
+    ) -> PyResult<PyObject> {
+        let (value, context) = self.eval_located(path)?;
+        let positions = positions(py, &value, &context.sources())?;
+        let object = Converter {
+            context: Some(&context),
+            order: None,
+            detached: false,
+        }
+        .convert(py, &value)?;
+        context.emit_traces(py)?;
+        Ok(PyTuple::new(py, [object, positions])?.into_any().unbind())
+    }
+
+    /// Evaluate only an attribute of a nix file with this evaluator, see
+    /// `eval_attr`.
+    pub fn eval_attr(
+        &self,
+        py: Python<'_>,
+        path: String,
+        attr_path: &str,
+    ) -> PyResult<PyObject> {
+        let names = split(attr_path, "selected")?;
+        let path = PathBuf::from(path);
+        let content = self.read_file(&path)?;
+        let content = normalized_newlines(&content, self.normalize_newlines);
+
+        // The other attributes are never forced, whatever the mode
+        let selecting = Evaluator {
+            lazy: true,
+            ..self.clone()
+        };
+        let (mut value, context) =
+            selecting.eval_expr(&content, Some(path), None, None, None)?;
+        for (i, name) in names.iter().enumerate() {
+            let value_of = context.whnf(value)?;
+            let TvixValue::Attrs(attrs) = &value_of else {
+                let selected = match i {
+                    0 => "the file".to_string(),
+                    _ => format!("`{}`", attrpath(&names[..i])),
+                };
+                return Err(coded_err::<EvaluationError>(
+                    ErrorCode::NixEvaluation,
+                    format!(
+                        "Cannot select `{}`, {} is a {} instead of an attrset",
+                        attr_path,
+                        selected,
+                        value_of.type_of()
+                    ),
+                ));
+            };
+            value = attrs
+                .iter()
+                .find(|(k, _)| k.as_bytes() == name.as_bytes())
+                .map(|(_, v)| v.clone())
+                .ok_or_else(|| {
+                    coded_err::<EvaluationError>(
+                        ErrorCode::NixEvaluation,
+                        format!(
+                            "Attribute `{}` is missing",
+                            attrpath(&names[..=i])
+                        ),
+                    )
+                })?;
+        }
+        let value = match self.depth {
+            _ if self.lazy => context.whnf(value)?,
+            Some(levels) => context.force_levels(value, levels)?,
+            None => context.force_deep(value)?,
+        };
+
+        let order = self.ordered.then(|| KeyOrder::of_source(&content));
+        let object = Converter {
+            context: Some(&context),
+            order: order.as_ref().and_then(|o| o.at(&names)),
+            detached: false,
+        }
+        .convert(py, &value)?;
+        context.collected(py, object)
+    }
+
+    /// Evaluate a nix expression with this evaluator, see `evals`.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        content,
+        dir = None,
+        args = None,
+        scope = None,
+        virtual_name = None,
+        jail = false
+    ))]
+    pub fn evals(
+        &self,
+        py: Python<'_>,
+        content: String,
+        dir: Option<String>,
+        args: Option<Bound<'_, PyDict>>,
+        scope: Option<Bound<'_, PyDict>>,
+        virtual_name: Option<&str>,
+        jail: bool,
+    ) -> PyResult<PyObject> {
+        let jailed = jail.then(|| self.jailed(dir.as_deref())).transpose()?;
+        let evaluator = jailed.as_ref().unwrap_or(self);
+        let path = virtual_path(dir, virtual_name)?;
+        let content = normalized_newlines(&content, self.normalize_newlines);
+        evaluator.eval_to_python(
+            py,
+            &content,
+            path,
+            virtual_name,
+            args,
+            scope,
+            None,
+        )
+    }
+
+    /// Evaluate a nix expression to a derivation with this evaluator, and
+    /// describe it, see `eval_drv`.
+    #[pyo3(signature = (content, dir = None, virtual_name = None))]
+    pub fn eval_drv(
+        &self,
+        py: Python<'_>,
+        content: String,
+        dir: Option<String>,
+        virtual_name: Option<&str>,
+    ) -> PyResult<PyObject> {
+        let path = virtual_path(dir, virtual_name)?;
+        let content = normalized_newlines(&content, self.normalize_newlines);
+        // A derivation refers to itself through its outputs, only its
+        // description is forced
+        let describing = Evaluator {
+            lazy: true,
+            derivations: true,
+            ..self.clone()
+        };
+        let (value, context) =
+            describing.eval_expr(&content, path, virtual_name, None, None)?;
+        let value = context.whnf(value)?;
+        let is_derivation = match &value {
+            TvixValue::Attrs(attrs) => match attrs
+                .iter()
+                .find(|(k, _)| k.as_bytes() == b"type")
+                .map(|(_, v)| context.whnf(v.clone()))
+                .transpose()?
+            {
+                Some(TvixValue::String(s)) => s.as_bytes() == b"derivation",
+                _ => false,
+            },
+            _ => false,
+        };
+        if !is_derivation {
+            return Err(coded_err::<EvaluationError>(
+                ErrorCode::NixEvaluation,
+                format!(
+                    "The expression evaluates to a {} instead of a derivation",
+                    value.type_of()
+                ),
+            ));
+        }
+
+        let description = context.describe_derivation(value)?;
+        let object = Converter {
+            context: Some(&context),
+            order: None,
+            detached: false,
+        }
+        .convert(py, &description)?;
+        context.emit_traces(py)?;
+        Ok(object)
+    }
+}
+
+/// Convert variables given from Python to the scope of an expression
+pub(super) fn scope_of(
+    variables: &Bound<'_, PyDict>,
+    policies: NumberPolicies,
+) -> PyResult<Scope> {
+    variables
+        .iter()
+        .map(|(name, value)| {
+            let name = name.extract::<String>().map_err(|_| {
+                PyTypeError::new_err(format!(
+                    "Variables of the scope must be named by strings, got {}",
+                    name.get_type()
+                ))
+            })?;
+            Ok((SmolStr::new(name), from_python(&value, policies)?))
+        })
+        .collect()
+}
+
+/// The location tvix evaluates an expression at. tvix resolves relative
+/// paths from the directory of the location only if it is an existing file,
+/// so the directory of a virtual file stands in for it.
+fn base_location(location: Option<PathBuf>) -> Option<PathBuf> {
+    match location {
+        Some(path) if !path.is_file() => match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => Some(dir.to_path_buf()),
+            _ => Some(path),
+        },
+        location => location,
+    }
+}
+
+/// Where a nix file or stream is evaluated at, and the name of a stream not
+/// read from a file, which is evaluated like `evals` without `dir`
+fn location_of(path: &FileSource<'_>) -> (Option<PathBuf>, Option<String>) {
+    let origin = path.origin();
+    match path {
+        FileSource::Stream(_) if !origin.is_file() => {
+            (None, Some(origin.to_string_lossy().into_owned()))
+        }
+        _ => (Some(origin), None),
+    }
+}
+
+/// The path an expression is evaluated at, as if it was the content of the
+/// file `virtual_name` of `dir`
+pub(super) fn virtual_path(
+    dir: Option<String>,
+    virtual_name: Option<&str>,
+) -> PyResult<Option<PathBuf>> {
+    if let Some(name) = virtual_name {
+        if Path::new(name).file_name() != Some(OsStr::new(name)) {
+            return Err(PyValueError::new_err(format!(
+                "The virtual name `{}` is not a file name",
+                name
+            )));
+        }
+    }
+    let path = dir
+        .map(|d| PathBuf::from(d).join(virtual_name.unwrap_or("virtual.nix")));
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+
+    /// The repr of the value of `expr` evaluated by `evaluator`, called with
+    /// the Python dict `args`
+    fn evals(
+        evaluator: &Evaluator,
+        expr: &str,
+        args: &str,
+    ) -> PyResult<String> {
+        Python::with_gil(|py| {
+            let args = py.eval(&CString::new(args)?, None, None)?;
+            let value = evaluator.evals(
+                py,
+                expr.to_string(),
+                None,
+                Some(args.downcast_into::<PyDict>()?),
+                None,
+                None,
+                false,
+            )?;
+            Ok(value.bind(py).repr()?.to_string())
+        })
+    }
+
+    #[test]
+    fn int_overflow_of_args() {
+        let (expr, args) = ("{ n }: n", "{'n': 2**64 + 1}");
+        let evaluator = |int_overflow| Evaluator {
+            int_overflow: Some(int_overflow),
+            ..Default::default()
+        };
+        let wrapping = evaluator(IntOverflow::Wrap);
+        assert_eq!(evals(&wrapping, expr, args).unwrap(), "1");
+        let digits = evaluator(IntOverflow::Digits);
+        assert_eq!(
+            evals(&digits, expr, args).unwrap(),
+            "'18446744073709551617'"
+        );
+        assert!(evals(&evaluator(IntOverflow::Error), expr, args).is_err());
+        assert_eq!(evals(&wrapping, expr, "{'n': 3}").unwrap(), "3");
+    }
+
+    #[test]
+    fn float_policy_of_args_and_results() {
+        let (given, overflowing) = ("{ x }: x", "{ x }: x * 1.0e308");
+        let (infinite, finite) = ("{'x': float('inf')}", "{'x': 10.0}");
+        let evaluator = |non_finite| Evaluator {
+            floats: FloatOptions {
+                non_finite: Some(non_finite),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let nulls = evaluator(NonFinite::Null);
+        assert_eq!(evals(&nulls, given, infinite).unwrap(), "None");
+        assert_eq!(evals(&nulls, overflowing, finite).unwrap(), "None");
+        let floats = evaluator(NonFinite::Float);
+        assert_eq!(evals(&floats, given, infinite).unwrap(), "inf");
+        assert_eq!(evals(&floats, overflowing, finite).unwrap(), "inf");
+        let errors = evaluator(NonFinite::Error);
+        assert!(evals(&errors, given, infinite).is_err());
+        assert!(evals(&errors, overflowing, finite).is_err());
+        assert_eq!(evals(&errors, given, "{'x': 0.5}").unwrap(), "0.5");
+    }
+}
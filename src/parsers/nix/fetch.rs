@@ -0,0 +1,268 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::{PyObject, PyResult};
+
+use super::json::py_to_json;
+use super::report::{Fetch, Fetches, Outcome};
+
+/// Where the fetchers of an offline evaluation pretend to have fetched what
+/// is not cached, so the evaluation goes on and reports every resource it
+/// needs, not only the first one
+const OFFLINE_PATH: &str = "/nonexistent/cosutils-offline";
+
+/// The resources an offline evaluation needed but found no cached copy of
+pub(super) type Missing = Rc<RefCell<Vec<String>>>;
+
+/// A resource remembered by a `FetchCache`
+struct Fetched {
+    path: PathBuf,
+    fetched: Instant,
+    used: Instant,
+    /// The size of the fetched files in bytes
+    size: u64,
+}
+
+/// The size of a file, or of all the files of a directory, in bytes
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| disk_usage(&e.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// A cache of the resources fetched by the `fetchers` of evaluators, shared
+/// by all the evaluators given the same cache.
+///
+/// Each call of a fetcher is keyed by the name of the fetcher and its
+/// argument, so `fetchTarball "https://..."` is fetched once, and later
+/// calls get the path the first one returned as long as it still exists.
+/// The cache only remembers paths, the fetched files stay wherever the
+/// fetchers put them and are never deleted by the cache. Only the fetchers
+/// given to an evaluator go through its cache.
+///
+/// Args:
+///   - ttl (float | None): For how many seconds a fetched resource is used,
+///                         after which it is fetched again. `None` keeps it
+///                         forever.
+///   - max_size_mb (int | None): How many MiB of fetched files the cache
+///                               remembers, forgetting the least recently
+///                               used resources first. `None` does not limit
+///                               it.
+///   - offline (bool): Never call the fetchers. Evaluations needing a
+///                     resource that is not cached raise an
+///                     `EvaluationError` listing all such resources.
+///
+/// Raises:
+///   - ValueError: If the ttl or the size limit is not positive.
+///
+/// Example:
+/// ```python
+/// >>> fetchers = {"fetchTarball": download}
+/// >>> cache = FetchCache(ttl=3600)
+/// >>> Evaluator(fetchers=fetchers, fetch_cache=cache).eval("default.nix")
+/// >>> offline = FetchCache(offline=True)
+/// >>> Evaluator(fetchers=fetchers, fetch_cache=offline).eval("default.nix")
+/// Traceback (most recent call last):
+/// ...
+/// EvaluationError: The evaluation needs resources that are not fetched ...
+///   - fetchTarball "https://example.com/src.tar.gz"
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.nix", frozen)]
+#[derive(Clone)]
+pub struct FetchCache {
+    entries: Arc<Mutex<HashMap<String, Fetched>>>,
+    ttl: Option<Duration>,
+    /// The size limit in bytes
+    max_size: Option<usize>,
+    offline: bool,
+}
+
+impl FetchCache {
+    /// The path a fresh cached resource was fetched to
+    fn get(&self, key: &str) -> Option<PathBuf> {
+        let mut entries = self.entries.lock().ok()?;
+        let entry = entries.get_mut(key)?;
+        let expired = self.ttl.is_some_and(|t| entry.fetched.elapsed() > t);
+        if expired || !entry.path.exists() {
+            entries.remove(key);
+            return None;
+        }
+        entry.used = Instant::now();
+        Some(entry.path.clone())
+    }
+
+    /// Remember a fetched resource, then forget the least recently used ones
+    /// until the cache fits its size limit
+    fn put(&self, key: String, path: PathBuf) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        let size = disk_usage(&path);
+        entries.insert(
+            key,
+            Fetched {
+                path,
+                fetched: now,
+                used: now,
+                size,
+            },
+        );
+        let Some(max_size) = self.max_size else {
+            return;
+        };
+        let mut total = entries.values().map(|e| e.size).sum::<u64>();
+        while total > max_size as u64 {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = entries.remove(&oldest) {
+                total -= entry.size;
+            }
+        }
+    }
+}
+
+/// Call the fetcher `name` with `argument`, or take its result from the
+/// cache, and return the path of what it fetched, recording the attempt in
+/// `fetches`
+pub(super) fn fetch(
+    py: Python<'_>,
+    name: &'static str,
+    callable: &PyObject,
+    argument: PyObject,
+    cache: Option<&FetchCache>,
+    missing: &Missing,
+    fetches: &Fetches,
+) -> PyResult<PathBuf> {
+    let argument = argument.into_bound(py);
+    let described = match py_to_json(&argument) {
+        Some(json) => json.to_string(),
+        None => argument.repr()?.to_string(),
+    };
+    let key = format!("{} {}", name, described);
+    let record = |outcome| {
+        fetches.borrow_mut().push(Fetch {
+            fetcher: name,
+            argument: described.clone(),
+            outcome,
+        })
+    };
+    let call = |argument: Bound<'_, PyAny>| {
+        let fetched = callable
+            .call1(py, (argument,))
+            .and_then(|path| path.extract::<PathBuf>(py));
+        record(match fetched {
+            Ok(_) => Outcome::Fetched,
+            Err(_) => Outcome::Failed,
+        });
+        fetched
+    };
+    let Some(cache) = cache else {
+        return call(argument);
+    };
+    if let Some(path) = cache.get(&key) {
+        record(Outcome::Cached);
+        return Ok(path);
+    }
+    if cache.offline {
+        record(Outcome::Offline);
+        let mut missing = missing.borrow_mut();
+        let path = Path::new(OFFLINE_PATH).join(missing.len().to_string());
+        if !missing.contains(&key) {
+            missing.push(key);
+        }
+        return Ok(path);
+    }
+
+    let path = call(argument)?;
+    cache.put(key, path.clone());
+    Ok(path)
+}
+
+#[pymethods]
+impl FetchCache {
+    #[new]
+    #[pyo3(signature = (ttl = None, max_size_mb = None, offline = false))]
+    fn new(
+        ttl: Option<f64>,
+        max_size_mb: Option<usize>,
+        offline: bool,
+    ) -> PyResult<Self> {
+        let ttl = ttl
+            .map(|secs| {
+                Duration::try_from_secs_f64(secs)
+                    .ok()
+                    .filter(|t| !t.is_zero())
+                    .ok_or_else(|| {
+                        PyValueError::new_err(format!(
+                            "TTL must be a positive number of seconds, got {}",
+                            secs
+                        ))
+                    })
+            })
+            .transpose()?;
+        Ok(Self {
+            entries: Arc::default(),
+            ttl,
+            max_size: max_size_mb
+                .map(|mb| {
+                    mb.checked_mul(1 << 20).filter(|&b| b > 0).ok_or_else(
+                        || {
+                            PyValueError::new_err(format!(
+                                "Size limit must be a positive number of MiB, \
+                            got {}",
+                                mb
+                            ))
+                        },
+                    )
+                })
+                .transpose()?,
+            offline,
+        })
+    }
+
+    /// Forget every cached resource.
+    fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    fn __len__(&self) -> usize {
+        self.entries.lock().map(|e| e.len()).unwrap_or(0)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "FetchCache(ttl={}, max_size_mb={}, offline={})",
+            self.ttl
+                .map_or("None".to_string(), |t| t.as_secs_f64().to_string()),
+            self.max_size
+                .map_or("None".to_string(), |s| (s >> 20).to_string()),
+            if self.offline { "True" } else { "False" }
+        )
+    }
+}
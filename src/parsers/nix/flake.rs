@@ -0,0 +1,277 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use std::sync::Arc;
+
+use genawaiter::rc::Gen;
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::{PyObject, PyResult};
+use rustc_hash::FxHashMap;
+use serde_json::Value as JsonValue;
+use tvix_eval::generators::{self, pin_generator};
+use tvix_eval::{Builtin, NixAttrs, NixList};
+use tvix_eval::{ErrorKind as TvixErrorKind, Value as TvixValue};
+
+use super::convert::Converter;
+use super::evaluator::{Evaluator, PendingError};
+use super::lock::FlakeLock;
+use super::store::{fixed_output_path, parse_sri_sha256};
+use crate::parsers::profile;
+use crate::parsers::utils::{
+    coded_err, ErrorCode, EvaluationError, ParseError,
+};
+
+/// Evaluates a flake given its lock file and the sources of its inputs
+pub(super) const CALL_FLAKE: &str = include_str!("call-flake.nix");
+
+/// Stands in for `builtins.getFlake`, evaluating the flakes of the registry
+/// of the evaluator
+pub(super) const GET_FLAKE: &str = "reference: \
+    builtins.__cosutilsCallFlake (builtins.__cosutilsResolveFlake reference)";
+
+/// The flakes `builtins.getFlake` gets, by flake id or reference
+pub(super) type FlakeRegistry = Arc<FxHashMap<String, PathBuf>>;
+
+/// Find the sources of the locked inputs of a flake in the nix store
+fn locked_sources(
+    lock: &FlakeLock,
+    flake_dir: &Path,
+) -> PyResult<Vec<(String, TvixValue)>> {
+    let invalid = |message: String| {
+        coded_err::<ParseError>(ErrorCode::NixFlakeLock, message)
+    };
+    let mut sources = Vec::with_capacity(lock.nodes.len());
+    for (name, node) in lock.nodes.iter().filter(|(n, _)| **n != lock.root) {
+        let locked = node.get("locked").ok_or_else(|| {
+            invalid(format!("Input `{}` of flake.lock is not locked", name))
+        })?;
+        let kind = locked.get("type").and_then(JsonValue::as_str);
+        let local = locked.get("path").and_then(JsonValue::as_str);
+        let source = if let (Some("path"), Some(local)) = (kind, local) {
+            // Local inputs are used in place, as long as they still exist
+            flake_dir.join(local)
+        } else {
+            let nar_hash = locked
+                .get("narHash")
+                .and_then(JsonValue::as_str)
+                .and_then(parse_sri_sha256)
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "Input `{}` of flake.lock has no valid SHA-256 \
+                        `narHash`",
+                        name
+                    ))
+                })?;
+            fixed_output_path(&nar_hash, "source")
+        };
+        if !source.exists() {
+            return Err(coded_err::<EvaluationError>(
+                ErrorCode::NixFlakeInput,
+                format!(
+                    "Input `{}` is not available at {}, fetch it first, e.g. \
+                    with `nix flake archive`",
+                    name,
+                    source.display()
+                ),
+            ));
+        }
+        sources.push((
+            name.clone(),
+            TvixValue::String(source.to_string_lossy().as_ref().into()),
+        ));
+    }
+    Ok(sources)
+}
+
+/// The directory of a flake given as its directory or its `flake.nix`, with
+/// its symlinks resolved
+fn flake_dir(path: &Path) -> PyResult<PathBuf> {
+    let path = if path.file_name().is_some_and(|f| f == "flake.nix") {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.into(),
+            _ => PathBuf::from("."),
+        }
+    } else {
+        path.to_path_buf()
+    };
+    fs::canonicalize(&path).map_err(|e| {
+        PyIOError::new_err(format!(
+            "Failed to find flake {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// The arguments of `call-flake.nix` evaluating the flake in a directory,
+/// down to the attribute path `output`
+fn call_flake_args(
+    flake_dir: &Path,
+    output: Vec<TvixValue>,
+) -> PyResult<TvixValue> {
+    let lock_path = flake_dir.join("flake.lock");
+    let lock = if lock_path.exists() {
+        FlakeLock::read(&lock_path)?
+    } else {
+        FlakeLock::empty()
+    };
+    let sources = locked_sources(&lock, flake_dir)?;
+    Ok(TvixValue::attrs(NixAttrs::from_iter([
+        (
+            "lock".to_string(),
+            TvixValue::String(lock.raw.as_str().into()),
+        ),
+        (
+            "sources".to_string(),
+            TvixValue::attrs(NixAttrs::from_iter(sources)),
+        ),
+        (
+            "root".to_string(),
+            TvixValue::String(flake_dir.to_string_lossy().as_ref().into()),
+        ),
+        ("output".to_string(), TvixValue::List(NixList::from(output))),
+    ])))
+}
+
+/// Find the directory of the flake a reference given to `getFlake` refers
+/// to. The reference is looked up in the registry as written, then by its
+/// flake id without its `flake:` scheme, ref and parameters, and absolute
+/// paths are used as they are.
+fn resolve_reference(
+    registry: &FxHashMap<String, PathBuf>,
+    reference: &str,
+) -> PyResult<PathBuf> {
+    if let Some(dir) = registry.get(reference) {
+        return flake_dir(dir);
+    }
+    let path = reference.strip_prefix("path:").unwrap_or(reference);
+    if path.starts_with('/') {
+        return flake_dir(Path::new(path.split('?').next().unwrap_or(path)));
+    }
+    let id = reference.strip_prefix("flake:").unwrap_or(reference);
+    let id = id.split(['/', '?']).next().unwrap_or(id);
+    match registry.get(id) {
+        Some(dir) => flake_dir(dir),
+        None => {
+            let mut known = registry.keys().cloned().collect::<Vec<_>>();
+            known.sort();
+            Err(coded_err::<EvaluationError>(
+                ErrorCode::NixFlakeInput,
+                format!(
+                    "Flake `{}` is not in the flake registry of the \
+                    evaluator, which has `{}`",
+                    reference,
+                    known.join("`, `")
+                ),
+            ))
+        }
+    }
+}
+
+/// The builtin `getFlake` resolves references with, to the arguments of
+/// `call-flake.nix` for the whole flake. Its errors are left pending, to be
+/// raised as they are once the evaluation fails.
+pub(super) fn resolve_flake_builtin(
+    registry: &FlakeRegistry,
+    pending: &PendingError,
+) -> TvixValue {
+    let registry = registry.clone();
+    let pending = pending.clone();
+    let builtin =
+        Builtin::new("__cosutilsResolveFlake", None, 1, move |args| {
+            let registry = registry.clone();
+            let pending = pending.clone();
+            Gen::new(|co| {
+                pin_generator(async move {
+                    let reference =
+                        generators::request_force(&co, args[0].clone()).await;
+                    let TvixValue::String(reference) = reference else {
+                        return Err(TvixErrorKind::Abort(format!(
+                            "`builtins.getFlake` expects a flake reference \
+                        string, got a {}",
+                            reference.type_of()
+                        )));
+                    };
+                    let reference = reference.to_string();
+                    resolve_reference(&registry, &reference)
+                        .and_then(|dir| call_flake_args(&dir, Vec::new()))
+                        .map_err(|e| {
+                            let message = format!(
+                                "`builtins.getFlake` failed to get `{}`: {}",
+                                reference, e
+                            );
+                            *pending.borrow_mut() = Some(e);
+                            TvixErrorKind::Abort(message)
+                        })
+                })
+            })
+        });
+    TvixValue::Builtin(builtin)
+}
+
+/// Evaluate an output of a nix flake and convert it to Python object.
+///
+/// The inputs of the flake are resolved from its `flake.lock`, and must be
+/// present on disk already: local `path` inputs are used in place, every
+/// other input is looked up in the nix store by its `narHash`. Nothing is
+/// fetched from the network.
+///
+/// Args:
+///   - path (str): The directory of the flake, or its `flake.nix`.
+///   - output (str): The attribute path of the output to evaluate, with
+///                   names separated by `.`.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - lazy (bool): Only evaluate what is needed to produce the top-level
+///                  value, returning unevaluated parts as `NixThunk`.
+///
+/// Returns:
+///   - _EvaluatedNixValue: The evaluated output as any Python object
+///
+/// Raises:
+///   - IOError: If the flake cannot be read.
+///   - ParseError: If the flake cannot be parsed, or its lock file is
+///                 malformed.
+///   - EvaluationError: If an input is not available, or the output cannot
+///                      be evaluated.
+///   - ConversionError: If the result cannot be converted to a Python object.
+///
+/// Example:
+/// ```python
+/// >>> eval_flake("path/to/flake", "nixosConfigurations.foo.config.networking.hostName")
+/// 'foo'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, output, strict = None, lazy = false))]
+pub fn eval_flake(
+    py: Python<'_>,
+    path: String,
+    output: String,
+    strict: Option<bool>,
+    lazy: bool,
+) -> PyResult<PyObject> {
+    let flake_dir = flake_dir(Path::new(&path))?;
+    let output = output
+        .split('.')
+        .map(|name| TvixValue::String(name.into()))
+        .collect::<Vec<_>>();
+    let args = call_flake_args(&flake_dir, output)?;
+
+    let evaluator = Evaluator {
+        strict: strict.unwrap_or(profile::current().nix_strict),
+        lazy,
+        ..Default::default()
+    };
+    let (value, context) =
+        evaluator.eval_expr(CALL_FLAKE, None, None, Some(args), None)?;
+    let object = Converter {
+        context: Some(&context),
+        order: None,
+        detached: false,
+    }
+    .convert(py, &value)?;
+    context.emit_traces(py)?;
+    Ok(object)
+}
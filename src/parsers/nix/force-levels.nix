@@ -0,0 +1,24 @@
+# Force a value down to `depth` levels of attrsets and lists, evaluating each
+# of their items to weak head normal form and leaving the values below them
+# unevaluated
+let
+  force = depth: value:
+    let
+      forceItem = forced: item: builtins.seq (force (depth - 1) item) forced;
+    in
+    if depth <= 1 then
+      value
+    else
+      builtins.seq
+        (if builtins.isAttrs value then
+          builtins.foldl'
+            (forced: name: forceItem forced value.${name})
+            null
+            (builtins.attrNames value)
+        else if builtins.isList value then
+          builtins.foldl' forceItem null value
+        else
+          null)
+        value;
+in
+force
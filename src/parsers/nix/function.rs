@@ -0,0 +1,221 @@
+use std::rc::Rc;
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use pyo3::{PyObject, PyResult};
+use tvix_eval::Value as TvixValue;
+
+use super::convert::{from_python, Converter};
+use super::evaluator::Context;
+
+/// A nix function (lambda or builtin) produced by an evaluation.
+///
+/// Calling it applies the function to the arguments, converted like `evals`
+/// args. As nix functions are curried, positional arguments are applied one
+/// after another, and keyword arguments are passed as a single attrset after
+/// them, so `f(pkgs=pkgs)` calls `{ pkgs, ... }: ...` functions.
+///
+/// Values of nix evaluations cannot leave the thread they were evaluated on,
+/// so the function can only be called on the thread that produced it, and
+/// cannot be pickled.
+///
+/// Raises:
+///   - TypeError: If no argument is given.
+///   - EvaluationError: If the application fails to evaluate.
+///   - ConversionError: If the arguments or the result cannot be converted.
+///
+/// Example:
+/// ```python
+/// >>> add = evals("a: b: a + b")
+/// >>> add(1, 2)
+/// 3
+/// >>> evals('{ name }: "hello " + name')(name="nix")
+/// 'hello nix'
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.nix", unsendable)]
+pub struct NixFunction {
+    pub(super) function: TvixValue,
+    pub(super) context: Rc<Context>,
+}
+
+#[pymethods]
+impl NixFunction {
+    #[pyo3(signature = (*args, **kwargs))]
+    fn __call__(
+        &self,
+        py: Python<'_>,
+        args: &Bound<'_, PyTuple>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PyObject> {
+        let policies = self.context.evaluator.number_policies();
+        let mut arguments = args
+            .iter()
+            .map(|a| from_python(&a, policies))
+            .collect::<PyResult<Vec<_>>>()?;
+        if let Some(kwargs) = kwargs {
+            arguments.push(from_python(kwargs.as_any(), policies)?);
+        }
+        if arguments.is_empty() {
+            return Err(PyTypeError::new_err(
+                "A nix function must be called with at least one argument",
+            ));
+        }
+
+        let mut value = self.function.clone();
+        for argument in arguments {
+            value = self.context.apply(value, argument)?;
+        }
+        let object = Converter {
+            context: Some(&self.context),
+            order: None,
+            detached: false,
+        }
+        .convert(py, &value)?;
+        self.context.emit_traces(py)?;
+        Ok(object)
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.function {
+            TvixValue::Builtin(builtin) => {
+                format!("<NixFunction builtins.{}>", builtin.name())
+            }
+            _ => "<NixFunction lambda>".to_string(),
+        }
+    }
+
+    /// Functions are only equal to themselves, like in nix
+    fn __eq__(slf: &Bound<'_, Self>, other: &Bound<'_, Self>) -> bool {
+        match (&slf.get().function, &other.get().function) {
+            (TvixValue::Closure(a), TvixValue::Closure(b)) => Rc::ptr_eq(a, b),
+            _ => slf.is(other),
+        }
+    }
+
+    fn _repr_pretty_(
+        slf: &Bound<'_, Self>,
+        printer: &Bound<'_, PyAny>,
+        _cycle: bool,
+    ) -> PyResult<()> {
+        printer.call_method1("text", (slf.repr()?,))?;
+        Ok(())
+    }
+
+    fn __reduce__(&self) -> PyResult<PyObject> {
+        Err(PyTypeError::new_err(
+            "Nix functions cannot be pickled, pickle the results of calling \
+            them instead",
+        ))
+    }
+}
+
+/// A part of a lazy evaluation that has not been evaluated yet.
+///
+/// Like `NixFunction`, it can only be forced on the thread that produced it.
+/// Pickling it evaluates it deeply and pickles the plain data it evaluates
+/// to instead, so lazy results can be sent to other processes.
+///
+/// Example:
+/// ```python
+/// >>> value = evals('{ a = 1 + 1; b = throw "unused"; }', lazy=True)
+/// >>> value["a"].force()
+/// 2
+/// >>> pickle.loads(pickle.dumps(value["a"]))
+/// 2
+/// >>> value["a"]
+/// <NixThunk evaluated: int>
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.nix", unsendable)]
+pub struct NixThunk {
+    pub(super) thunk: TvixValue,
+    pub(super) context: Rc<Context>,
+}
+
+#[pymethods]
+impl NixThunk {
+    /// Evaluate the value and convert it to a Python object.
+    ///
+    /// Nested values that are still unevaluated are returned as `NixThunk`.
+    ///
+    /// Returns:
+    ///   - _EvaluatedNixValue: The evaluated value as any Python object
+    ///
+    /// Raises:
+    ///   - EvaluationError: If the value cannot be evaluated.
+    ///   - ConversionError: If the value cannot be converted.
+    fn force(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let value = self.context.force(self.thunk.clone())?;
+        let object = Converter {
+            context: Some(&self.context),
+            order: None,
+            detached: false,
+        }
+        .convert(py, &value)?;
+        self.context.emit_traces(py)?;
+        Ok(object)
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.thunk {
+            TvixValue::Thunk(thunk) if thunk.is_evaluated() => {
+                format!("<NixThunk evaluated: {}>", thunk.value().type_of())
+            }
+            _ => "<NixThunk>".to_string(),
+        }
+    }
+
+    /// Thunks are equal when they are the same part of the evaluation, their
+    /// values are not compared
+    fn __eq__(&self, other: &Self) -> bool {
+        match (&self.thunk, &other.thunk) {
+            (TvixValue::Thunk(a), TvixValue::Thunk(b)) => a.ptr_eq(b),
+            _ => false,
+        }
+    }
+
+    /// The length of the evaluated value.
+    fn __len__(&self, py: Python<'_>) -> PyResult<usize> {
+        self.force(py)?.bind(py).len()
+    }
+
+    /// Iterate over the evaluated value.
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(self.force(py)?.bind(py).try_iter()?.into_any().unbind())
+    }
+
+    /// Look up an item of the evaluated value, so lazy results can be
+    /// explored like strict ones.
+    fn __getitem__(
+        &self,
+        py: Python<'_>,
+        key: &Bound<'_, PyAny>,
+    ) -> PyResult<PyObject> {
+        Ok(self.force(py)?.bind(py).get_item(key)?.unbind())
+    }
+
+    fn _repr_pretty_(
+        slf: &Bound<'_, Self>,
+        printer: &Bound<'_, PyAny>,
+        _cycle: bool,
+    ) -> PyResult<()> {
+        printer.call_method1("text", (slf.repr()?,))?;
+        Ok(())
+    }
+
+    /// Pickle the plain data the value evaluates to, unpickled as is.
+    ///
+    /// Raises:
+    ///   - EvaluationError: If the value cannot be evaluated.
+    ///   - ConversionError: If the value contains functions.
+    fn __reduce__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Bound<'py, PyAny>, (PyObject,))> {
+        let value = self.context.force_deep(self.thunk.clone())?;
+        let object = Converter::default().convert(py, &value)?;
+        self.context.emit_traces(py)?;
+        // Unpickling a copy of the data yields the data itself
+        Ok((py.import("copy")?.getattr("copy")?, (object,)))
+    }
+}
@@ -0,0 +1,250 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use bytes::Bytes;
+use tvix_eval::generators::VMRequest;
+use tvix_eval::observer::RuntimeObserver;
+use tvix_eval::{CodeIdx, EvalIO, FileType, Lambda, Op, Value as TvixValue};
+
+/// A builtin entered by an evaluation and not left yet, with the file it
+/// opened first if it is an `import`
+struct Entered {
+    name: &'static str,
+    opened: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct State {
+    entered: Vec<Entered>,
+    /// The chain of imports that led back to one of its files, if any
+    cycle: Option<Vec<PathBuf>>,
+    /// The content of the files imported so far, by canonical path, if
+    /// kept
+    sources: Option<HashMap<PathBuf, String>>,
+}
+
+/// The imports in progress in the evaluations of a context, shared by the
+/// IO opening the imported files and the observer seeing the imports enter
+/// and leave, so that a file importing itself, directly or not, fails
+/// instead of recursing until the memory runs out
+#[derive(Clone, Default)]
+pub(super) struct Imports(Rc<RefCell<State>>);
+
+impl Imports {
+    /// Track the imports of evaluations keeping the content of the files
+    /// they import, to locate what the files define without reading them
+    /// again
+    pub fn keeping_sources() -> Self {
+        Self(Rc::new(RefCell::new(State {
+            sources: Some(HashMap::new()),
+            ..State::default()
+        })))
+    }
+
+    /// The content of the file at the canonical `path` as it was imported,
+    /// if it was and its content was kept
+    pub fn source(&self, path: &Path) -> Option<String> {
+        self.0.borrow().sources.as_ref()?.get(path).cloned()
+    }
+
+    /// Take the cycle an evaluation failed on, if it did
+    pub fn take_cycle(&self) -> Option<Vec<PathBuf>> {
+        self.0.borrow_mut().cycle.take()
+    }
+
+    /// Record that `path` is opened, failing if it is the file of an import
+    /// still in progress, returning its canonical path if an import
+    /// evaluates it and its content is kept
+    fn open(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        let mut state = self.0.borrow_mut();
+        // Only the file an `import` opens first is the one it evaluates, the
+        // builtins it calls open files of their own
+        if !matches!(
+            state.entered.last(),
+            Some(Entered {
+                name: "import",
+                opened: None
+            })
+        ) {
+            return Ok(None);
+        }
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+        let chain = state
+            .entered
+            .iter()
+            .filter_map(|e| e.opened.clone())
+            .collect::<Vec<_>>();
+        if let Some(start) = chain.iter().position(|p| *p == path) {
+            let mut cycle = chain[start..].to_vec();
+            cycle.push(path);
+            let message = format!(
+                "import cycle: {}",
+                cycle
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            );
+            state.cycle = Some(cycle);
+            return Err(io::Error::other(message));
+        }
+        if let Some(entered) = state.entered.last_mut() {
+            entered.opened = Some(path.clone());
+        }
+        Ok(state.sources.is_some().then_some(path))
+    }
+
+    /// Run an evaluation tracking its imports, forwarding the events to the
+    /// `observer` of its limits and profile
+    pub fn run<T>(
+        &self,
+        observer: Option<&mut dyn RuntimeObserver>,
+        evaluate: impl FnOnce(Option<&mut dyn RuntimeObserver>) -> T,
+    ) -> T {
+        // Builtins left by an evaluation aborted by its limits never resume
+        self.0.borrow_mut().entered.clear();
+        let mut tracker = ImportTracker {
+            imports: self,
+            inner: observer,
+        };
+        evaluate(Some(&mut tracker))
+    }
+}
+
+/// Wraps the IO of an evaluation to fail when an import opens a file whose
+/// import is still in progress
+pub(super) struct ImportIO {
+    inner: Rc<dyn EvalIO>,
+    imports: Imports,
+}
+
+impl ImportIO {
+    pub fn new(inner: Rc<dyn EvalIO>, imports: Imports) -> Self {
+        Self { inner, imports }
+    }
+}
+
+impl EvalIO for ImportIO {
+    fn path_exists(&self, path: &Path) -> io::Result<bool> {
+        self.inner.path_exists(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        let kept = self.imports.open(path)?;
+        let mut file = self.inner.open(path)?;
+        let Some(kept) = kept else {
+            return Ok(file);
+        };
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        if let Some(sources) = &mut self.imports.0.borrow_mut().sources {
+            sources.insert(kept, content.clone());
+        }
+        Ok(Box::new(io::Cursor::new(content)))
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<FileType> {
+        self.inner.file_type(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(Bytes, FileType)>> {
+        self.inner.read_dir(path)
+    }
+
+    fn import_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.inner.import_path(path)
+    }
+
+    fn store_dir(&self) -> Option<String> {
+        self.inner.store_dir()
+    }
+}
+
+/// Observes an evaluation to know which builtins are running, the imports
+/// among them being in progress until they are left
+struct ImportTracker<'a> {
+    imports: &'a Imports,
+    inner: Option<&'a mut dyn RuntimeObserver>,
+}
+
+impl RuntimeObserver for ImportTracker<'_> {
+    fn observe_enter_call_frame(
+        &mut self,
+        arg_count: usize,
+        lambda: &Rc<Lambda>,
+        call_depth: usize,
+    ) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_enter_call_frame(arg_count, lambda, call_depth);
+        }
+    }
+
+    fn observe_exit_call_frame(
+        &mut self,
+        frame_at: usize,
+        stack: &[TvixValue],
+    ) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_exit_call_frame(frame_at, stack);
+        }
+    }
+
+    fn observe_suspend_call_frame(
+        &mut self,
+        frame_at: usize,
+        stack: &[TvixValue],
+    ) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_suspend_call_frame(frame_at, stack);
+        }
+    }
+
+    fn observe_tvix_tail_call(&mut self, frame_at: usize, lambda: &Rc<Lambda>) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_tvix_tail_call(frame_at, lambda);
+        }
+    }
+
+    fn observe_enter_builtin(&mut self, name: &'static str) {
+        self.imports
+            .0
+            .borrow_mut()
+            .entered
+            .push(Entered { name, opened: None });
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_enter_builtin(name);
+        }
+    }
+
+    fn observe_exit_builtin(
+        &mut self,
+        name: &'static str,
+        stack: &[TvixValue],
+    ) {
+        self.imports.0.borrow_mut().entered.pop();
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_exit_builtin(name, stack);
+        }
+    }
+
+    fn observe_execute_op(
+        &mut self,
+        ip: CodeIdx,
+        op: &Op,
+        stack: &[TvixValue],
+    ) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_execute_op(ip, op, stack);
+        }
+    }
+
+    fn observe_generator_request(&mut self, name: &str, msg: &VMRequest) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_generator_request(name, msg);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::str::from_utf8;
+
+use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyDict, PyFloat, PyInt, PyList, PyNone, PyString, PyTuple,
+};
+use pyo3::{PyObject, PyResult};
+use serde_json::{Map, Number, Value as JsonValue};
+use tvix_eval::Value as TvixValue;
+
+use crate::into_pyany;
+use crate::parsers::floats::{FloatPolicy, FloatRepr, NonFinite};
+use crate::parsers::utils::{coded_err, ConversionError, ErrorCode};
+
+pub fn json_to_py(py: Python<'_>, value: &JsonValue) -> PyResult<PyObject> {
+    let object = match value {
+        JsonValue::Null => into_pyany!(PyNone::get(py)),
+        JsonValue::Bool(b) => into_pyany!(PyBool::new(py, *b)),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => into_pyany!(PyInt::new(py, i)),
+            None => {
+                into_pyany!(PyFloat::new(py, n.as_f64().unwrap_or_default()))
+            }
+        },
+        JsonValue::String(s) => into_pyany!(PyString::new(py, s)),
+        JsonValue::Array(items) => {
+            let converted = items
+                .iter()
+                .map(|v| json_to_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            into_pyany!(PyList::new(py, converted)?)
+        }
+        JsonValue::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map.iter() {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            into_pyany!(dict)
+        }
+    };
+    Ok(object)
+}
+
+/// Convert a Python object made of JSON values back to JSON, `None` if it
+/// contains anything JSON cannot represent exactly, such as functions or
+/// non-finite floats
+pub fn py_to_json(obj: &Bound<'_, PyAny>) -> Option<JsonValue> {
+    let value = if obj.is_none() {
+        JsonValue::Null
+    } else if let Ok(b) = obj.downcast::<PyBool>() {
+        JsonValue::Bool(b.is_true())
+    } else if let Ok(i) = obj.downcast::<PyInt>() {
+        JsonValue::Number(i.extract::<i64>().ok()?.into())
+    } else if let Ok(f) = obj.downcast::<PyFloat>() {
+        JsonValue::Number(Number::from_f64(f.value())?)
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        JsonValue::String(s.to_str().ok()?.to_string())
+    } else if obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>()
+    {
+        let items = obj
+            .try_iter()
+            .ok()?
+            .map(|item| py_to_json(&item.ok()?))
+            .collect::<Option<Vec<_>>>()?;
+        JsonValue::Array(items)
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut map = Map::new();
+        for (k, v) in dict.iter() {
+            let key = k.downcast::<PyString>().ok()?.to_str().ok()?;
+            map.insert(key.to_string(), py_to_json(&v)?);
+        }
+        JsonValue::Object(map)
+    } else {
+        return None;
+    };
+    Some(value)
+}
+
+/// Write a string as a JSON string, escaped
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    // Strings are always representable, and writing to a `Vec` cannot fail
+    let _ = serde_json::to_writer(out, s);
+}
+
+fn write_tvix(
+    value: &TvixValue,
+    floats: &FloatPolicy,
+    out: &mut Vec<u8>,
+) -> PyResult<()> {
+    let unconvertible = |what: String| {
+        coded_err::<ConversionError>(
+            ErrorCode::NixConversion,
+            format!("Cannot convert {} to JSON", what),
+        )
+    };
+    match value {
+        TvixValue::Null => out.extend_from_slice(b"null"),
+        TvixValue::Bool(b) => out.extend_from_slice(b.to_string().as_bytes()),
+        TvixValue::Integer(i) => {
+            out.extend_from_slice(i.to_string().as_bytes())
+        }
+        TvixValue::Float(f) if f.is_finite() => {
+            let literal = match floats.repr {
+                FloatRepr::Shortest => Number::from_f64(*f)
+                    .map(|n| n.to_string())
+                    .unwrap_or_default(),
+                _ => floats.literal(*f),
+            };
+            out.extend_from_slice(literal.as_bytes())
+        }
+        TvixValue::Float(_) if floats.non_finite == Some(NonFinite::Null) => {
+            out.extend_from_slice(b"null")
+        }
+        TvixValue::Float(f) => Err(unconvertible(format!("float {}", f)))?,
+        TvixValue::String(s) => write_str(out, &s.to_string()),
+        TvixValue::Path(p) => match p.to_str() {
+            Some(p) => write_str(out, p),
+            None => Err(unconvertible(format!(
+                "path {}, try wrap your path as `\"${{path}}\"`",
+                p.display()
+            )))?,
+        },
+        TvixValue::List(l) => {
+            out.push(b'[');
+            for (i, item) in l.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_tvix(item, floats, out)?;
+            }
+            out.push(b']');
+        }
+        TvixValue::Attrs(attrs) => {
+            out.push(b'{');
+            for (i, (k, v)) in attrs.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                let key = from_utf8(k.as_bytes()).map_err(|e| {
+                    unconvertible(format!("attribute name {} ({})", k, e))
+                })?;
+                write_str(out, key);
+                out.push(b':');
+                write_tvix(v, floats, out)?;
+            }
+            out.push(b'}');
+        }
+        TvixValue::Thunk(thunk) if thunk.is_evaluated() => {
+            write_tvix(&thunk.value(), floats, out)?
+        }
+        value => Err(unconvertible(format!("nix {}", value.type_of())))?,
+    }
+    Ok(())
+}
+
+/// Serialize an evaluated nix value to compact JSON, without building the
+/// Python objects of a conversion or the tree of a `JsonValue`. Attributes are
+/// written in the sorted order of nix, like `builtins.toJSON`.
+pub fn tvix_to_json(
+    value: &TvixValue,
+    floats: &FloatPolicy,
+) -> PyResult<String> {
+    let mut out = Vec::new();
+    write_tvix(value, floats, &mut out)?;
+    // Only valid strings and ASCII are written
+    String::from_utf8(out).map_err(|e| {
+        coded_err::<ConversionError>(ErrorCode::NixConversion, e.to_string())
+    })
+}
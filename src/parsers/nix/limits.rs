@@ -0,0 +1,508 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use annotate_snippets::Level;
+use genawaiter::rc::Gen;
+use pyo3::exceptions::PyValueError;
+use pyo3::{PyErr, PyResult};
+use tvix_eval::generators::{self, pin_generator, VMRequest};
+use tvix_eval::observer::RuntimeObserver;
+use tvix_eval::{Builtin, ErrorKind as TvixErrorKind};
+use tvix_eval::{Lambda, Value as TvixValue};
+
+use crate::parsers::render::renderer;
+use crate::parsers::utils::{
+    coded_err, ErrorCode, EvaluationError, ResourceLimitError,
+};
+
+/// Number of recently entered frames reported when a limit is exceeded
+const RECENT_FRAMES: usize = 8;
+
+thread_local! {
+    /// Whether the allocations of the thread are counted, only while it
+    /// evaluates with a memory limit or measures its peak memory
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    /// Bytes allocated by the thread since it started counting, less those
+    /// it freed, which were maybe allocated before
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    /// The most bytes allocated by the thread at once, since the measure of
+    /// `peak_memory` started
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// The system allocator, counting the bytes allocated by the threads
+/// evaluating with a memory budget, and only by them
+struct CountingAllocator;
+
+impl CountingAllocator {
+    fn count(delta: isize) {
+        // The counters may already be destroyed while the thread exits
+        if !COUNTING.try_with(Cell::get).unwrap_or(false) {
+            return;
+        }
+        let _ = ALLOCATED.try_with(|a| {
+            let allocated = a.get().wrapping_add(delta);
+            a.set(allocated);
+            if delta > 0 {
+                let _ = PEAK.try_with(|p| p.set(p.get().max(allocated)));
+            }
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size() as isize);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::count(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        Self::count(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocated() -> isize {
+    ALLOCATED.try_with(Cell::get).unwrap_or_default()
+}
+
+/// Counts the allocations of the thread while alive, the counters starting
+/// from zero for the outermost one and reset when it is dropped, however
+/// the evaluation it counts for ends
+struct Counting {
+    outermost: bool,
+}
+
+impl Counting {
+    fn start() -> Self {
+        let outermost = !COUNTING.with(|c| c.replace(true));
+        if outermost {
+            ALLOCATED.with(|a| a.set(0));
+            PEAK.with(|p| p.set(0));
+        }
+        Self { outermost }
+    }
+}
+
+impl Drop for Counting {
+    fn drop(&mut self) {
+        if self.outermost {
+            COUNTING.with(|c| c.set(false));
+            ALLOCATED.with(|a| a.set(0));
+            PEAK.with(|p| p.set(0));
+        }
+    }
+}
+
+/// Run `f`, also returning the most bytes it had allocated at once on top
+/// of what the thread allocated before, if `measured`
+pub fn peak_memory<T>(measured: bool, f: impl FnOnce() -> T) -> (T, usize) {
+    if !measured {
+        return (f(), 0);
+    }
+    let _counting = Counting::start();
+    let start = allocated();
+    // Measures may be nested, the outer one still sees the peak of this one
+    let outer = PEAK.with(|p| p.replace(start));
+    let value = f();
+    let peak = PEAK.with(|p| p.replace(p.get().max(outer)));
+    (value, peak.saturating_sub(start).max(0) as usize)
+}
+
+/// The limit an evaluation exceeded
+#[derive(Clone, Copy)]
+enum Exceeded {
+    Timeout,
+    Memory,
+    CallDepth,
+}
+
+/// The limits of an evaluation, shared by its observer, which notes when
+/// one is exceeded, and by its builtins, which abort the evaluation then.
+///
+/// tvix has no way to stop an evaluation from its observer, so the limits
+/// are enforced each time a builtin is applied, which nix code does all the
+/// time, and before `genList` allocates its list.
+pub(super) struct Budget {
+    deadline: Option<(Duration, Instant)>,
+    /// The budget in bytes, and the bytes allocated when the evaluation
+    /// started
+    memory: Option<(usize, Cell<isize>)>,
+    max_call_depth: Option<usize>,
+    exceeded: Cell<Option<Exceeded>>,
+}
+
+impl Budget {
+    /// Note the limit exceeded first, and abort the evaluation
+    fn exceed(&self, exceeded: Exceeded) -> TvixErrorKind {
+        let exceeded = self.exceeded.get().unwrap_or(exceeded);
+        self.exceeded.set(Some(exceeded));
+        TvixErrorKind::Abort(self.title(exceeded))
+    }
+
+    /// Fail if the evaluation exceeded one of its limits, or would by
+    /// allocating `bytes` more
+    fn check(&self, bytes: usize) -> Result<(), TvixErrorKind> {
+        if let Some(exceeded) = self.exceeded.get() {
+            return Err(self.exceed(exceeded));
+        }
+        if self.deadline.is_some_and(|(_, at)| Instant::now() >= at) {
+            return Err(self.exceed(Exceeded::Timeout));
+        }
+        let over = self.memory.as_ref().is_some_and(|(budget, start)| {
+            let allocated = allocated().saturating_sub(start.get());
+            allocated.saturating_add_unsigned(bytes) > *budget as isize
+        });
+        if over {
+            return Err(self.exceed(Exceeded::Memory));
+        }
+        Ok(())
+    }
+
+    fn title(&self, exceeded: Exceeded) -> String {
+        match (exceeded, self.deadline, &self.memory) {
+            (Exceeded::Timeout, Some((timeout, _)), _) => {
+                format!("evaluation timed out after {:?}", timeout)
+            }
+            (Exceeded::Memory, _, Some((budget, _))) => format!(
+                "evaluation allocated more than its limit of {} MiB",
+                budget >> 20
+            ),
+            (Exceeded::CallDepth, _, _) => format!(
+                "evaluation nested more calls than its limit of {}",
+                self.max_call_depth.unwrap_or_default()
+            ),
+            _ => "evaluation exceeded its limits".to_string(),
+        }
+    }
+
+    /// Wrap a builtin to check the limits each time it is applied, and
+    /// `genList` to check its list fits into the memory budget before
+    /// building it. Other values are returned as they are.
+    pub fn guarded(
+        self: &Rc<Self>,
+        name: &'static str,
+        value: TvixValue,
+    ) -> TvixValue {
+        if !matches!(value, TvixValue::Builtin(_)) {
+            return value;
+        }
+        let budget = Rc::clone(self);
+        // Applied to its first argument, the wrapped builtin returns the
+        // builtin partially applied, which takes the others as usual
+        let arity = if name == "genList" { 2 } else { 1 };
+        let builtin = Builtin::new(name, None, arity, move |args| {
+            let budget = Rc::clone(&budget);
+            let value = value.clone();
+            Gen::new(|co| {
+                pin_generator(async move {
+                    let mut bytes = 0;
+                    if arity == 2 {
+                        let length =
+                            generators::request_force(&co, args[1].clone())
+                                .await;
+                        if let TvixValue::Integer(length) = length {
+                            bytes = usize::try_from(length)
+                                .unwrap_or_default()
+                                .saturating_mul(size_of::<TvixValue>());
+                        }
+                    }
+                    budget.check(bytes)?;
+                    Ok(generators::request_call_with(&co, value, args).await)
+                })
+            })
+        });
+        TvixValue::Builtin(builtin)
+    }
+}
+
+/// A frame entered by an evaluation
+enum Frame {
+    Function { depth: usize },
+    Builtin(&'static str),
+}
+
+/// Observes an evaluation to note once it runs past its deadline,
+/// allocates more than its memory budget or nests too many calls,
+/// remembering the frames it entered last to explain where it was stuck
+pub(super) struct Limits {
+    budget: Rc<Budget>,
+    recent: VecDeque<Frame>,
+}
+
+impl Limits {
+    /// Start counting the limits of an evaluation, `None` if it has none
+    pub fn new(
+        timeout: Option<Duration>,
+        max_memory: Option<usize>,
+        max_call_depth: Option<usize>,
+    ) -> Option<Self> {
+        if timeout.is_none() && max_memory.is_none() && max_call_depth.is_none()
+        {
+            return None;
+        }
+        let budget = Budget {
+            deadline: timeout.map(|t| (t, Instant::now() + t)),
+            memory: max_memory.map(|m| (m, Cell::new(0))),
+            max_call_depth,
+            exceeded: Cell::new(None),
+        };
+        Some(Self {
+            budget: Rc::new(budget),
+            recent: VecDeque::with_capacity(RECENT_FRAMES),
+        })
+    }
+
+    fn enter(&mut self, frame: Frame) {
+        // A guarded builtin is entered twice, once by its guard
+        if let (Frame::Builtin(name), Some(Frame::Builtin(last))) =
+            (&frame, self.recent.back())
+        {
+            if name == last {
+                return self.note();
+            }
+        }
+        if self.recent.len() == RECENT_FRAMES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(frame);
+        self.note();
+    }
+
+    /// Note whether a limit is exceeded, for the next builtin to abort
+    fn note(&self) {
+        let _ = self.budget.check(0);
+    }
+
+    /// Run an evaluation observed by these limits, whose builtins are
+    /// guarded by their budget, failing if one is exceeded
+    pub fn run<T>(
+        limits: Option<&mut Limits>,
+        evaluate: impl FnOnce(
+            Option<&mut dyn RuntimeObserver>,
+            Option<&Rc<Budget>>,
+        ) -> T,
+    ) -> PyResult<T> {
+        let Some(limits) = limits else {
+            return Ok(evaluate(None, None));
+        };
+        let budget = Rc::clone(&limits.budget);
+        // Reset when the evaluation ends, whether it fails or not
+        let _counting = budget.memory.as_ref().map(|(_, start)| {
+            let counting = Counting::start();
+            start.set(allocated());
+            counting
+        });
+        let value = evaluate(
+            Some(&mut *limits as &mut dyn RuntimeObserver),
+            Some(&budget),
+        );
+        // The error the builtins aborted with is replaced by the limit
+        match budget.exceeded.get() {
+            Some(exceeded) => Err(limits.exceeded(exceeded)),
+            None => Ok(value),
+        }
+    }
+
+    fn exceeded(&self, exceeded: Exceeded) -> PyErr {
+        let title = self.budget.title(exceeded);
+        let frames = self
+            .recent
+            .iter()
+            .rev()
+            .map(|frame| match frame {
+                Frame::Function { depth } => {
+                    format!("in a nix function, at call depth {}", depth)
+                }
+                Frame::Builtin(name) => format!("in builtins.{}", name),
+            })
+            .collect::<Vec<_>>();
+        let message = Level::Error
+            .title(&title)
+            .footers(frames.iter().map(|f| Level::Note.title(f)));
+        let message = renderer().render(message).to_string();
+        match exceeded {
+            Exceeded::Timeout => {
+                coded_err::<EvaluationError>(ErrorCode::NixTimeout, message)
+            }
+            Exceeded::Memory => coded_err::<ResourceLimitError>(
+                ErrorCode::NixMemoryLimit,
+                message,
+            ),
+            Exceeded::CallDepth => coded_err::<ResourceLimitError>(
+                ErrorCode::NixCallDepth,
+                message,
+            ),
+        }
+    }
+}
+
+impl RuntimeObserver for Limits {
+    fn observe_enter_call_frame(
+        &mut self,
+        _arg_count: usize,
+        _lambda: &Rc<Lambda>,
+        call_depth: usize,
+    ) {
+        self.enter(Frame::Function { depth: call_depth });
+        if self
+            .budget
+            .max_call_depth
+            .is_some_and(|max| call_depth > max)
+        {
+            self.budget.exceed(Exceeded::CallDepth);
+        }
+    }
+
+    fn observe_tvix_tail_call(&mut self, _frame_at: usize, _: &Rc<Lambda>) {
+        self.note();
+    }
+
+    fn observe_enter_builtin(&mut self, name: &'static str) {
+        self.enter(Frame::Builtin(name));
+    }
+
+    fn observe_exit_builtin(&mut self, _name: &'static str, _: &[TvixValue]) {
+        self.note();
+    }
+
+    fn observe_generator_request(&mut self, _name: &str, _msg: &VMRequest) {
+        self.note();
+    }
+
+    fn observe_exit_call_frame(&mut self, _frame_at: usize, _: &[TvixValue]) {
+        self.note();
+    }
+}
+
+/// Convert a timeout in seconds given from Python
+pub fn timeout_from_secs(secs: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(secs)
+        .ok()
+        .filter(|t| !t.is_zero())
+        .ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "Timeout must be a positive number of seconds, got {}",
+                secs
+            ))
+        })
+}
+
+/// Convert a memory limit in MiB given from Python to bytes
+pub fn memory_from_mb(mb: usize) -> PyResult<usize> {
+    mb.checked_mul(1 << 20).filter(|&b| b > 0).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "Memory limit must be a positive number of MiB, got {}",
+            mb
+        ))
+    })
+}
+
+/// Convert a nesting or call depth limit given from Python, `what` naming it
+pub fn depth_limit(depth: usize, what: &str) -> PyResult<usize> {
+    Some(depth).filter(|&d| d > 0).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "{} must be a positive depth, got {}",
+            what, depth
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+
+    use super::*;
+    use crate::parsers::nix::evaluator::Evaluator;
+
+    /// The repr of the value of `expr`, or the code of the error it raised
+    fn evals(evaluator: &Evaluator, expr: &str) -> Result<String, String> {
+        Python::with_gil(|py| {
+            let evaluated = evaluator
+                .evals(py, expr.to_string(), None, None, None, None, false)
+                .and_then(|value| Ok(value.bind(py).repr()?.to_string()));
+            evaluated.map_err(|e| {
+                let code = e.value(py).getattr("code");
+                code.and_then(|c| c.extract())
+                    .unwrap_or_else(|_| e.to_string())
+            })
+        })
+    }
+
+    fn counting() -> (bool, isize, isize) {
+        (
+            COUNTING.with(Cell::get),
+            ALLOCATED.with(Cell::get),
+            PEAK.with(Cell::get),
+        )
+    }
+
+    #[test]
+    fn gen_list_bombs_are_aborted() {
+        let evaluator = Evaluator {
+            max_memory: Some(64 << 20),
+            ..Default::default()
+        };
+        let flat = "builtins.genList (x: x) 1000000000";
+        assert_eq!(evals(&evaluator, flat), Err("NIX_E031".to_string()));
+        let nested = "builtins.length (builtins.concatLists \
+            (builtins.genList (_: builtins.genList (x: x) 100000) 100000))";
+        assert_eq!(evals(&evaluator, nested), Err("NIX_E031".to_string()));
+        assert_eq!(counting(), (false, 0, 0));
+    }
+
+    #[test]
+    fn evaluates_again_after_an_abort() {
+        let evaluator = Evaluator {
+            max_memory: Some(16 << 20),
+            ..Default::default()
+        };
+        let bomb = "builtins.genList (x: x) 1000000000";
+        assert_eq!(evals(&evaluator, bomb), Err("NIX_E031".to_string()));
+        let list = "builtins.length (builtins.genList (x: x * 2) 1000)";
+        assert_eq!(evals(&evaluator, list), Ok("1000".to_string()));
+        let unlimited = Evaluator::default();
+        let small = "builtins.genList (x: x) 3";
+        assert_eq!(evals(&unlimited, small), Ok("[0, 1, 2]".to_string()));
+        assert_eq!(counting(), (false, 0, 0));
+    }
+
+    #[test]
+    fn loops_time_out() {
+        let evaluator = Evaluator {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        // tvix notices the thunk forcing itself
+        let recursive = evals(&evaluator, "let x = x; in x");
+        assert!(recursive.is_err_and(|code| code != "NIX_E030"));
+        let looping = "let f = n: if builtins.lessThan n 0 then n \
+            else f (n + 1); in f 0";
+        assert_eq!(evals(&evaluator, looping), Err("NIX_E030".to_string()));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(evals(&evaluator, "1 + 1"), Ok("2".to_string()));
+    }
+}
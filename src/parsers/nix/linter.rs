@@ -0,0 +1,460 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Expr, HasEntry};
+use rnix::{SyntaxKind, SyntaxNode};
+
+use super::ast::{attr_name, Lines};
+use super::parse_root;
+use crate::parsers::schema::attrpath;
+use crate::parsers::utils::read_file;
+
+/// A problem found in the code, on the bytes from `start` to `end`
+struct Diagnostic {
+    code: &'static str,
+    message: String,
+    start: usize,
+    end: usize,
+}
+
+fn span(node: &SyntaxNode) -> (usize, usize) {
+    let range = node.text_range();
+    (usize::from(range.start()), usize::from(range.end()))
+}
+
+/// What binds a name in a scope
+#[derive(Clone, Copy)]
+enum BinderKind {
+    Let,
+    Argument,
+    /// The attributes of recursive attrsets, which are also used by the
+    /// code selecting them, so are never reported
+    Attribute,
+}
+
+struct Binder {
+    name: String,
+    kind: BinderKind,
+    start: usize,
+    end: usize,
+    used: bool,
+}
+
+/// Whether an identifier refers to a variable, rather than naming an
+/// attribute or binding an argument
+fn is_reference(ident: &ast::Ident) -> bool {
+    let Some(parent) = ident.syntax().parent() else {
+        return true;
+    };
+    match parent.kind() {
+        SyntaxKind::NODE_ATTRPATH
+        | SyntaxKind::NODE_IDENT_PARAM
+        | SyntaxKind::NODE_PAT_BIND => false,
+        SyntaxKind::NODE_PAT_ENTRY => ast::PatEntry::cast(parent)
+            .and_then(|e| e.ident())
+            .is_none_or(|name| name.syntax() != ident.syntax()),
+        // `inherit x;` refers to `x`, `inherit (e) x;` to an attribute of `e`
+        SyntaxKind::NODE_INHERIT => {
+            ast::Inherit::cast(parent).is_some_and(|i| i.from().is_none())
+        }
+        _ => true,
+    }
+}
+
+/// Resolves the variables of the code to the bindings in scope
+#[derive(Default)]
+struct Scopes {
+    binders: Vec<Binder>,
+    scopes: Vec<Vec<usize>>,
+}
+
+impl Scopes {
+    fn push(&mut self, names: Vec<(String, SyntaxNode)>, kind: BinderKind) {
+        let mut scope: Vec<usize> = Vec::new();
+        for (name, node) in names {
+            if scope.iter().any(|&i| self.binders[i].name == name) {
+                continue;
+            }
+            let (start, end) = span(&node);
+            scope.push(self.binders.len());
+            self.binders.push(Binder {
+                name,
+                kind,
+                start,
+                end,
+                used: false,
+            });
+        }
+        self.scopes.push(scope);
+    }
+
+    fn resolve(&mut self, name: &str) {
+        let binder = self
+            .scopes
+            .iter()
+            .rev()
+            .flatten()
+            .find(|&&i| self.binders[i].name == name);
+        if let Some(&i) = binder {
+            self.binders[i].used = true;
+        }
+    }
+
+    fn visit(&mut self, node: &SyntaxNode) {
+        if let Some(ident) = ast::Ident::cast(node.clone()) {
+            if let Some(name) =
+                ident.ident_token().filter(|_| is_reference(&ident))
+            {
+                self.resolve(name.text());
+            }
+        } else if let Some(lambda) = ast::Lambda::cast(node.clone()) {
+            self.lambda(&lambda);
+        } else if let Some(let_in) = ast::LetIn::cast(node.clone()) {
+            self.recursive(&let_in, BinderKind::Let, let_in.body());
+        } else if let Some(set) =
+            ast::AttrSet::cast(node.clone()).filter(|s| s.rec_token().is_some())
+        {
+            self.recursive(&set, BinderKind::Attribute, None);
+        } else if let Some(legacy) = ast::LegacyLet::cast(node.clone()) {
+            self.recursive(&legacy, BinderKind::Attribute, None);
+        } else {
+            for child in node.children() {
+                self.visit(&child);
+            }
+        }
+    }
+
+    fn lambda(&mut self, lambda: &ast::Lambda) {
+        let ident = |ident: Option<ast::Ident>| {
+            let ident = ident?;
+            let name = ident.ident_token()?.to_string();
+            Some((name, ident.syntax().clone()))
+        };
+        let mut names = Vec::new();
+        let mut defaults = Vec::new();
+        match lambda.param() {
+            Some(ast::Param::IdentParam(param)) => {
+                names.extend(ident(param.ident()));
+            }
+            Some(ast::Param::Pattern(pattern)) => {
+                for entry in pattern.pat_entries() {
+                    names.extend(ident(entry.ident()));
+                    defaults.extend(entry.default());
+                }
+                names.extend(ident(pattern.pat_bind().and_then(|b| b.ident())));
+            }
+            None => {}
+        }
+
+        // The defaults of the arguments may refer to the other arguments
+        self.push(names, BinderKind::Argument);
+        for default in defaults {
+            self.visit(default.syntax());
+        }
+        if let Some(body) = lambda.body() {
+            self.visit(body.syntax());
+        }
+        self.scopes.pop();
+    }
+
+    /// Visit the entries of a `let` or a recursive attrset, whose names are
+    /// in scope of their values and of the body
+    fn recursive(
+        &mut self,
+        entries: &impl HasEntry,
+        kind: BinderKind,
+        body: Option<Expr>,
+    ) {
+        let mut names = Vec::new();
+        for entry in entries.entries() {
+            match entry {
+                ast::Entry::Inherit(inherit) => {
+                    for attr in inherit.attrs() {
+                        // `inherit x;` takes `x` from the enclosing scope
+                        if inherit.from().is_none() {
+                            self.visit(attr.syntax());
+                        }
+                        names.extend(
+                            attr_name(&attr)
+                                .map(|n| (n, attr.syntax().clone())),
+                        );
+                    }
+                }
+                ast::Entry::AttrpathValue(binding) => {
+                    let first =
+                        binding.attrpath().and_then(|p| p.attrs().next());
+                    names.extend(first.and_then(|attr| {
+                        Some((attr_name(&attr)?, attr.syntax().clone()))
+                    }));
+                }
+            }
+        }
+
+        self.push(names, kind);
+        for entry in entries.entries() {
+            match entry {
+                ast::Entry::Inherit(inherit) => {
+                    if let Some(from) = inherit.from() {
+                        self.visit(from.syntax());
+                    }
+                }
+                ast::Entry::AttrpathValue(binding) => {
+                    // Interpolated names may refer to variables too
+                    if let Some(attrpath) = binding.attrpath() {
+                        self.visit(attrpath.syntax());
+                    }
+                    if let Some(value) = binding.value() {
+                        self.visit(value.syntax());
+                    }
+                }
+            }
+        }
+        if let Some(body) = body {
+            self.visit(body.syntax());
+        }
+        self.scopes.pop();
+    }
+}
+
+/// How an attribute is defined by the entries of an attrset seen so far,
+/// with the offset of its first definition
+#[derive(Clone, Copy, Default)]
+enum Definition {
+    #[default]
+    Undefined,
+    /// By the attrpaths of its attributes, like `a` by `a.b = 1;`
+    Implicit(usize),
+    /// By an attrset, which the attrpaths of its attributes extend
+    Literal(usize),
+    Value(usize),
+}
+
+#[derive(Default)]
+struct Key {
+    definition: Definition,
+    children: BTreeMap<String, Key>,
+}
+
+/// Finds the attributes defined more than once by the entries of attrsets
+/// and `let`s, as nix merges the attrpaths of nested attributes
+struct Duplicates<'a> {
+    lines: &'a Lines,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Duplicates<'_> {
+    fn entries(
+        &mut self,
+        entries: &impl HasEntry,
+        key: &mut Key,
+        path: &mut Vec<String>,
+    ) {
+        for entry in entries.entries() {
+            match entry {
+                ast::Entry::Inherit(inherit) => {
+                    for attr in inherit.attrs() {
+                        if let Some(name) = attr_name(&attr) {
+                            let span = span(attr.syntax());
+                            self.define(key, &[name], None, span, path);
+                        }
+                    }
+                }
+                ast::Entry::AttrpathValue(binding) => {
+                    let Some(attrpath) = binding.attrpath() else {
+                        continue;
+                    };
+                    // Interpolated names are only known once evaluated
+                    let Some(names) = attrpath
+                        .attrs()
+                        .map(|a| attr_name(&a))
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        continue;
+                    };
+                    let span = span(attrpath.syntax());
+                    self.define(key, &names, binding.value(), span, path);
+                }
+            }
+        }
+    }
+
+    fn define(
+        &mut self,
+        key: &mut Key,
+        names: &[String],
+        value: Option<Expr>,
+        (start, end): (usize, usize),
+        path: &mut Vec<String>,
+    ) {
+        let Some((name, rest)) = names.split_first() else {
+            return;
+        };
+        path.push(name.clone());
+        let child = key.children.entry(name.clone()).or_default();
+        let literal = match &value {
+            Some(Expr::AttrSet(set)) if rest.is_empty() => Some(set.clone()),
+            _ => None,
+        };
+        let conflict = match (child.definition, rest.is_empty()) {
+            (Definition::Undefined | Definition::Implicit(_), true)
+                if literal.is_some() =>
+            {
+                child.definition = Definition::Literal(start);
+                None
+            }
+            (Definition::Undefined, true) => {
+                child.definition = Definition::Value(start);
+                None
+            }
+            (Definition::Undefined, false) => {
+                child.definition = Definition::Implicit(start);
+                None
+            }
+            (Definition::Value(first), false) => Some(first),
+            (_, false) => None,
+            (
+                Definition::Implicit(first)
+                | Definition::Literal(first)
+                | Definition::Value(first),
+                true,
+            ) => Some(first),
+        };
+
+        if let Some(first) = conflict {
+            self.diagnostics.push(Diagnostic {
+                code: "duplicate_key",
+                message: format!(
+                    "`{}` is already defined on line {}",
+                    attrpath(path),
+                    self.lines.of(first)
+                ),
+                start,
+                end,
+            });
+        } else if let Some(set) = literal {
+            self.entries(&set, child, path);
+        } else if !rest.is_empty() {
+            self.define(child, rest, value, (start, end), path);
+        }
+        path.pop();
+    }
+}
+
+/// Lint nix code, finding code that is dead or would fail to evaluate.
+///
+/// Only the syntax is inspected, nothing is evaluated, so variables are
+/// resolved to the bindings of the functions, `let`s and recursive attrsets
+/// enclosing them, and `with` expressions are ignored since they never hide
+/// those bindings. The diagnostics are identified by their `code`:
+///   - `unused_let`: A `let` binding that is never referred to.
+///   - `unused_argument`: A function argument, formal or `@` binding that is
+///                        never referred to.
+///   - `empty_inherit`: An `inherit` of no attribute.
+///   - `duplicate_key`: An attribute of an attrset or a `let` that is already
+///                      defined, which nix refuses to evaluate. The attrpaths
+///                      of nested attributes are merged like nix does, so
+///                      `{ a.b = 1; a.c = 2; }` is valid.
+///
+/// Like deadnix, bindings and arguments whose name starts with `_` are never
+/// reported unused.
+///
+/// Args:
+///   - path (str): The path to the nix file.
+///
+/// Returns:
+///   - list[_Diagnostic]: The diagnostics, in source order, with their
+///                        `code`, their `message`, the `start` and `end` byte
+///                        offsets of the code they are about, and the `line`
+///                        and `column` it starts at.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ParseError: If the nix file cannot be parsed.
+///
+/// Example:
+/// ```python
+/// # `default.nix` contains:
+/// # ```
+/// # { pkgs, lib, ... }: let x = 1; in { a = lib.id 1; a = 2; }
+/// # ```
+/// >>> [(d["code"], d["message"]) for d in lint("default.nix")]
+/// [('unused_argument', 'Unused function argument `pkgs`'),
+///  ('unused_let', 'Unused let binding `x`'),
+///  ('duplicate_key', '`a` is already defined on line 1')]
+/// ```
+#[pyfunction]
+pub fn lint(py: Python<'_>, path: String) -> PyResult<Vec<PyObject>> {
+    let path = PathBuf::from(path);
+    let content = read_file(&path)?;
+    let root = parse_root(&content, Some(&path.to_string_lossy()))?;
+    let lines = Lines::new(&content);
+
+    let mut scopes = Scopes::default();
+    scopes.visit(root.syntax());
+    let mut diagnostics = scopes
+        .binders
+        .into_iter()
+        .filter(|b| !b.used && !b.name.starts_with('_'))
+        .filter_map(|binder| {
+            let (code, message) = match binder.kind {
+                BinderKind::Let => ("unused_let", "Unused let binding"),
+                BinderKind::Argument => {
+                    ("unused_argument", "Unused function argument")
+                }
+                BinderKind::Attribute => return None,
+            };
+            Some(Diagnostic {
+                code,
+                message: format!("{} `{}`", message, binder.name),
+                start: binder.start,
+                end: binder.end,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut duplicates = Duplicates {
+        lines: &lines,
+        diagnostics: &mut diagnostics,
+    };
+    for node in root.syntax().descendants() {
+        let mut key = Key::default();
+        if let Some(set) = ast::AttrSet::cast(node.clone()) {
+            duplicates.entries(&set, &mut key, &mut Vec::new());
+        } else if let Some(let_in) = ast::LetIn::cast(node.clone()) {
+            duplicates.entries(&let_in, &mut key, &mut Vec::new());
+        } else if let Some(legacy) = ast::LegacyLet::cast(node.clone()) {
+            duplicates.entries(&legacy, &mut key, &mut Vec::new());
+        } else if let Some(inherit) = ast::Inherit::cast(node.clone()) {
+            if inherit.attrs().next().is_none() {
+                let (start, end) = span(inherit.syntax());
+                duplicates.diagnostics.push(Diagnostic {
+                    code: "empty_inherit",
+                    message: "Inherit of no attribute".to_string(),
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    // Nested attrsets are checked both on their own and merged into the
+    // attrset defining them
+    diagnostics.sort_by_key(|d| (d.start, d.code));
+    diagnostics.dedup_by_key(|d| (d.start, d.code));
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let dict = PyDict::new(py);
+            dict.set_item("code", diagnostic.code)?;
+            dict.set_item("message", &diagnostic.message)?;
+            dict.set_item("start", diagnostic.start)?;
+            dict.set_item("end", diagnostic.end)?;
+            dict.set_item("line", lines.of(diagnostic.start))?;
+            dict.set_item("column", lines.column(diagnostic.start))?;
+            Ok(dict.into_any().unbind())
+        })
+        .collect()
+}
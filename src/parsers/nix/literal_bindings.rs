@@ -0,0 +1,169 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString};
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Expr, HasEntry, LiteralKind, UnaryOpKind};
+
+use super::ast::{attrpath_names, Lines};
+use super::parse_root;
+use crate::into_pyany;
+use crate::parsers::schema::attrpath;
+
+/// The Python value of a literal expression, `None` if the expression needs
+/// evaluating, like interpolated strings or variables
+fn literal(py: Python<'_>, expr: &Expr) -> PyResult<Option<PyObject>> {
+    let value = match expr {
+        Expr::Literal(literal) => match literal.kind() {
+            LiteralKind::Integer(i) => match i.value() {
+                Ok(i) => into_pyany!(i.into_pyobject(py)?),
+                Err(_) => return Ok(None),
+            },
+            LiteralKind::Float(f) => match f.value() {
+                Ok(f) => into_pyany!(f.into_pyobject(py)?),
+                Err(_) => return Ok(None),
+            },
+            LiteralKind::Uri(_) => return Ok(None),
+        },
+        Expr::Str(s) => match s.normalized_parts().as_slice() {
+            [] => into_pyany!(PyString::new(py, "")),
+            [ast::InterpolPart::Literal(s)] => {
+                into_pyany!(PyString::new(py, s))
+            }
+            _ => return Ok(None),
+        },
+        // Builtin constants, as nothing tells whether a binding shadows them
+        Expr::Ident(ident) => {
+            match ident.ident_token().as_ref().map(|t| t.text()) {
+                Some("true") => into_pyany!(PyBool::new(py, true)),
+                Some("false") => into_pyany!(PyBool::new(py, false)),
+                Some("null") => py.None(),
+                _ => return Ok(None),
+            }
+        }
+        Expr::UnaryOp(op) if op.operator() == Some(UnaryOpKind::Negate) => {
+            let Some(Expr::Literal(number)) = op.expr() else {
+                return Ok(None);
+            };
+            match literal(py, &Expr::Literal(number))? {
+                Some(number) => number.bind(py).neg()?.unbind(),
+                None => return Ok(None),
+            }
+        }
+        Expr::Paren(paren) => match paren.expr() {
+            Some(expr) => return literal(py, &expr),
+            None => return Ok(None),
+        },
+        Expr::List(list) => {
+            let mut items = Vec::new();
+            for item in list.items() {
+                match literal(py, &item)? {
+                    Some(item) => items.push(item),
+                    None => return Ok(None),
+                }
+            }
+            into_pyany!(PyList::new(py, items)?)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Collects the literal bindings of an expression
+struct Literals<'py> {
+    py: Python<'py>,
+    lines: Lines,
+    found: Vec<PyObject>,
+}
+
+impl Literals<'_> {
+    /// Collect the bindings of the attrsets an expression evaluates to,
+    /// nested `path` deep
+    fn expr(
+        &mut self,
+        expr: Option<Expr>,
+        path: &mut Vec<String>,
+    ) -> PyResult<()> {
+        match expr {
+            Some(Expr::AttrSet(set)) => {
+                for binding in set.attrpath_values() {
+                    self.binding(&binding, path)?;
+                }
+            }
+            Some(Expr::Lambda(lambda)) => self.expr(lambda.body(), path)?,
+            Some(Expr::LetIn(let_in)) => self.expr(let_in.body(), path)?,
+            Some(Expr::With(with)) => self.expr(with.body(), path)?,
+            Some(Expr::Paren(paren)) => self.expr(paren.expr(), path)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn binding(
+        &mut self,
+        binding: &ast::AttrpathValue,
+        path: &mut Vec<String>,
+    ) -> PyResult<()> {
+        // Interpolated names are only known once evaluated
+        let (Some(names), Some(value)) =
+            (attrpath_names(binding.attrpath()), binding.value())
+        else {
+            return Ok(());
+        };
+        let depth = path.len();
+        path.extend(names);
+        if let Some(literal) = literal(self.py, &value)? {
+            let range = value.syntax().text_range();
+            let start = usize::from(range.start());
+            let found = PyDict::new(self.py);
+            found.set_item("path", attrpath(path))?;
+            found.set_item("value", literal)?;
+            found.set_item("start", start)?;
+            found.set_item("end", usize::from(range.end()))?;
+            found.set_item("line", self.lines.of(start))?;
+            found.set_item("column", self.lines.column(start))?;
+            self.found.push(found.into_any().unbind());
+        } else {
+            self.expr(Some(value), path)?;
+        }
+        path.truncate(depth);
+        Ok(())
+    }
+}
+
+/// Extract the literal bindings of a nix expression without evaluating it.
+///
+/// Bindings are found in the attrset the expression is made of, through
+/// functions, `let` and `with`, and in its nested attrsets. Strings without
+/// interpolation, numbers, `true`, `false`, `null` and lists of them are
+/// literals, every other value, like a variable or a function call, is
+/// skipped. As nothing is evaluated, shadowed constants like a `true`
+/// variable are not told apart, and the bindings of `let` are not
+/// extracted.
+///
+/// Args:
+///   - content (str): The nix expression.
+///
+/// Returns:
+///   - list[_Literal]: The literal bindings in the order of the source,
+///                     with their `path`, their `value`, and the
+///                     `start` and `end` byte offsets, `line` and `column`
+///                     of the value.
+///
+/// Raises:
+///   - ParseError: If the nix code cannot be parsed.
+///
+/// Example:
+/// ```python
+/// >>> literals('{ pkgs }: { networking.hostName = "foo"; ports = [ 22 ]; }')
+/// [{'path': 'networking.hostName', 'value': 'foo', 'start': 34, 'end': 39, 'line': 1, 'column': 35}, {'path': 'ports', 'value': [22], 'start': 49, 'end': 55, 'line': 1, 'column': 50}]
+/// ```
+#[pyfunction]
+pub fn literals(py: Python<'_>, content: &str) -> PyResult<Vec<PyObject>> {
+    let root = parse_root(content, None)?;
+    let mut literals = Literals {
+        py,
+        lines: Lines::new(content),
+        found: Vec::new(),
+    };
+    literals.expr(root.expr(), &mut Vec::new())?;
+    Ok(literals.found)
+}
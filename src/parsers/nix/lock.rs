@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyNone};
+use pyo3::{PyObject, PyResult};
+use serde_json::{Map, Value as JsonValue};
+
+use super::json::json_to_py;
+use crate::into_pyany;
+use crate::parsers::utils::{coded_err, read_file, ErrorCode, ParseError};
+
+fn invalid(message: String) -> PyErr {
+    coded_err::<ParseError>(ErrorCode::NixFlakeLock, message)
+}
+
+/// The `flake.lock` of a flake, pinning its inputs
+pub(super) struct FlakeLock {
+    /// The content of the lock file
+    pub raw: String,
+    pub version: Option<i64>,
+    /// The name of the node of the flake itself
+    pub root: String,
+    pub nodes: Map<String, JsonValue>,
+}
+
+impl FlakeLock {
+    /// The lock of a flake without inputs
+    pub fn empty() -> Self {
+        Self {
+            raw: "{}".to_string(),
+            version: None,
+            root: "root".to_string(),
+            nodes: Map::new(),
+        }
+    }
+
+    pub fn read(path: &Path) -> PyResult<Self> {
+        let raw = read_file(path)?;
+        let parsed = serde_json::from_str::<JsonValue>(&raw).map_err(|e| {
+            invalid(format!("Failed to parse {}: {}", path.display(), e))
+        })?;
+        let nodes = match parsed.get("nodes") {
+            Some(JsonValue::Object(nodes)) => nodes.clone(),
+            Some(_) => Err(invalid(format!(
+                "`nodes` of {} must be an object",
+                path.display()
+            )))?,
+            None => Map::new(),
+        };
+        Ok(Self {
+            version: parsed.get("version").and_then(JsonValue::as_i64),
+            root: parsed
+                .get("root")
+                .and_then(JsonValue::as_str)
+                .unwrap_or("root")
+                .to_string(),
+            nodes,
+            raw,
+        })
+    }
+
+    /// The inputs of a node, as given in the lock file
+    fn inputs(&self, node: &str) -> Option<&Map<String, JsonValue>> {
+        self.nodes.get(node)?.get("inputs")?.as_object()
+    }
+
+    /// Resolve an input of a node to the name of the node it refers to,
+    /// following `follows` paths, which start at the root node
+    pub fn resolve(&self, spec: &JsonValue) -> PyResult<String> {
+        self.resolve_within(spec, 0)
+    }
+
+    fn resolve_within(
+        &self,
+        spec: &JsonValue,
+        depth: usize,
+    ) -> PyResult<String> {
+        let path = match spec {
+            JsonValue::String(node) => return Ok(node.clone()),
+            JsonValue::Array(path) => path,
+            _ => Err(invalid(format!(
+                "Invalid input reference {} in flake.lock",
+                spec
+            )))?,
+        };
+        // Every nested `follows` passes through another input, so nesting
+        // deeper than there are inputs means the `follows` form a cycle
+        let edges = self
+            .nodes
+            .keys()
+            .map(|node| self.inputs(node).map_or(0, Map::len))
+            .sum::<usize>();
+        if depth > edges {
+            return Err(invalid(format!(
+                "`follows` of flake.lock form a cycle at {}",
+                spec
+            )));
+        }
+
+        let mut node = self.root.clone();
+        for name in path {
+            let name = name.as_str().ok_or_else(|| {
+                invalid(format!("Invalid `follows` {} in flake.lock", spec))
+            })?;
+            let input = self
+                .inputs(&node)
+                .and_then(|inputs| inputs.get(name))
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "Input `{}` of node `{}` does not exist in flake.lock",
+                        name, node
+                    ))
+                })?;
+            node = self.resolve_within(input, depth + 1)?;
+        }
+        Ok(node)
+    }
+}
+
+/// Parse the `flake.lock` of a flake and resolve the graph of its inputs.
+///
+/// Every node of the lock is returned with its pins, and with its inputs
+/// resolved to the names of the nodes they refer to. Inputs that `follows`
+/// another input keep the path they follow, starting at the root node.
+///
+/// Args:
+///   - path (str): The path to the `flake.lock`, or the directory of the
+///                 flake.
+///
+/// Returns:
+///   - _FlakeLock: The version of the lock file, the name of the `root`
+///                 node, and the `nodes` by name. Each node has the `type`,
+///                 `rev`, `narHash` and `lastModified` of its pin (`None`
+///                 when not pinned), the raw `locked` and `original`
+///                 references, whether it is a `flake`, and its `inputs`
+///                 with the `node` they resolve to and the path they
+///                 `follows`, if any.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ParseError: If the lock file is malformed, or a `follows` cannot be
+///                 resolved.
+///
+/// Example:
+/// ```python
+/// >>> lock = load_flake_lock("path/to/flake")
+/// >>> lock["nodes"]["nixpkgs"]["rev"]
+/// '7e2a6a2c2b0f3f9a7b1c4f5a3e2d1c0b9a8f7e6d'
+/// >>> lock["nodes"]["home-manager"]["inputs"]["nixpkgs"]
+/// {'node': 'nixpkgs', 'follows': ['nixpkgs']}
+/// ```
+#[pyfunction]
+pub fn load_flake_lock(py: Python<'_>, path: String) -> PyResult<PyObject> {
+    let path = PathBuf::from(path);
+    let path = if path.is_dir() {
+        path.join("flake.lock")
+    } else {
+        path
+    };
+    let lock = FlakeLock::read(&path)?;
+
+    let nodes = PyDict::new(py);
+    for (name, node) in lock.nodes.iter() {
+        let locked = node.get("locked");
+        let pin = |key: &str| match locked.and_then(|l| l.get(key)) {
+            Some(value) => json_to_py(py, value),
+            None => Ok(into_pyany!(PyNone::get(py))),
+        };
+        let converted = PyDict::new(py);
+        for key in ["type", "rev", "narHash", "lastModified"] {
+            converted.set_item(key, pin(key)?)?;
+        }
+        for key in ["locked", "original"] {
+            let value = node.get(key).cloned().unwrap_or_default();
+            converted.set_item(key, json_to_py(py, &value)?)?;
+        }
+        let is_flake = node.get("flake").and_then(JsonValue::as_bool);
+        converted.set_item("flake", is_flake.unwrap_or(true))?;
+
+        let inputs = PyDict::new(py);
+        for (input, spec) in lock.inputs(name).into_iter().flatten() {
+            let edge = PyDict::new(py);
+            edge.set_item("node", lock.resolve(spec)?)?;
+            match spec {
+                JsonValue::Array(_) => {
+                    edge.set_item("follows", json_to_py(py, spec)?)?
+                }
+                _ => edge.set_item("follows", PyNone::get(py))?,
+            }
+            inputs.set_item(input, edge)?;
+        }
+        converted.set_item("inputs", inputs)?;
+        nodes.set_item(name, converted)?;
+    }
+
+    let result = PyDict::new(py);
+    result.set_item("version", lock.version)?;
+    result.set_item("root", &lock.root)?;
+    result.set_item("nodes", nodes)?;
+    Ok(result.into_any().unbind())
+}
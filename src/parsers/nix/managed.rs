@@ -0,0 +1,78 @@
+use pyo3::prelude::*;
+use pyo3::{PyObject, PyResult};
+
+use super::parse_root;
+use crate::parsers::managed;
+use crate::parsers::utils::TryToPyObject;
+
+/// List the regions of a nix file managed by a tool.
+///
+/// A managed region is delimited by line comments, from
+/// `# cosutils:begin <name>` to `# cosutils:end`, and its content is meant to
+/// be regenerated with `replace_managed` while the user edits the rest of the
+/// file, like the bindings of an attrset owned by a generator.
+///
+/// Args:
+///   - content (str): The nix code as a string.
+///
+/// Returns:
+///   - list[_ManagedRegion]: The regions, with their `name`, the `start` and
+///                           `end` byte offsets of the lines between their
+///                           markers, the `line` of their begin marker and
+///                           their `content`.
+///
+/// Raises:
+///   - ParseError: If the markers are unnamed, nested, duplicated or do not
+///                 pair up.
+#[pyfunction]
+pub fn managed_regions(
+    py: Python<'_>,
+    content: &str,
+) -> PyResult<Vec<PyObject>> {
+    managed::regions(content, "#")?
+        .iter()
+        .map(|r| r.try_to_pyobject(py))
+        .collect()
+}
+
+/// Replace the content of a region of a nix file managed by a tool, see
+/// `managed_regions`.
+///
+/// The markers and everything outside of the region are kept as they are,
+/// comments and formatting included, and the replacement is indented like
+/// the begin marker.
+///
+/// Args:
+///   - content (str): The nix code as a string.
+///   - name (str): The name of the region.
+///   - replacement (str): The new content of the region.
+///
+/// Returns:
+///   - str: The updated code.
+///
+/// Raises:
+///   - KeyError: If the code has no region of that name.
+///   - ParseError: If the markers are malformed, or the updated code cannot
+///                 be parsed.
+///
+/// Example:
+/// ```python
+/// >>> code = "{\n  # cosutils:begin gen\n  a = 1;\n  # cosutils:end\n}\n"
+/// >>> print(replace_managed(code, "gen", "b = 2;\nc = 3;"))
+/// {
+///   # cosutils:begin gen
+///   b = 2;
+///   c = 3;
+///   # cosutils:end
+/// }
+/// ```
+#[pyfunction]
+pub fn replace_managed(
+    content: &str,
+    name: &str,
+    replacement: &str,
+) -> PyResult<String> {
+    let replaced = managed::replace(content, "#", name, replacement)?;
+    parse_root(&replaced, None)?;
+    Ok(replaced)
+}
@@ -0,0 +1,96 @@
+use annotate_snippets::Snippet;
+use pyo3::prelude::*;
+use pyo3::PyResult;
+use rnix::{tokenize, Root, SyntaxKind};
+
+use super::parse_errors_into_pyerr;
+use crate::parsers::utils::display_source;
+
+fn is_trivia(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::TOKEN_WHITESPACE | SyntaxKind::TOKEN_COMMENT
+    )
+}
+
+/// Whether two tokens would still be lexed the same way if written next to
+/// each other, e.g. `a` and `b` would merge into `ab`, and `/` and `b` into
+/// the path `/b`
+fn can_touch(left: (SyntaxKind, &str), right: (SyntaxKind, &str)) -> bool {
+    let joined = format!("{}{}", left.1, right.1);
+    tokenize(&joined) == [left, right]
+}
+
+/// Remove trivia, keeping a single space wherever the neighbouring tokens
+/// need to be separated, or everywhere if `spaced`
+fn join_tokens(tokens: &[(SyntaxKind, &str)], spaced: bool) -> String {
+    let mut compacted = String::new();
+    let mut last: Option<(SyntaxKind, &str)> = None;
+    let mut separated = false;
+    for &token in tokens {
+        if is_trivia(token.0) {
+            separated = true;
+            continue;
+        }
+        if let Some(last) = last.filter(|_| separated) {
+            if spaced || !can_touch(last, token) {
+                compacted.push(' ');
+            }
+        }
+        compacted.push_str(token.1);
+        last = Some(token);
+        separated = false;
+    }
+    compacted
+}
+
+/// Compact a nix expression by removing comments and redundant whitespace.
+///
+/// Only whitespace and comments between tokens are removed, the content of
+/// strings is kept as is, so the indentation stripped from indented strings
+/// (`''...''`) stays the same.
+///
+/// Args:
+///   - content (str): The nix expression to compact.
+///
+/// Returns:
+///   - str: An equivalent expression without comments and with as little
+///          whitespace as possible.
+///
+/// Raises:
+///   - ParseError: If the nix expression cannot be parsed.
+///
+/// Example:
+/// ```python
+/// >>> compact("let x = 42; /* the answer */ in { inherit x; }")
+/// 'let x=42;in{inherit x;}'
+/// ```
+#[pyfunction]
+pub fn compact(content: String) -> PyResult<String> {
+    let parsed = Root::parse(&content);
+    if !parsed.errors().is_empty() {
+        let source = display_source(&content);
+        let snippet = Snippet::source(&source).fold(true);
+        return Err(parse_errors_into_pyerr(parsed.errors(), snippet));
+    }
+
+    let tokens = tokenize(&content);
+    let significant = |tokens: &[(SyntaxKind, &str)]| {
+        tokens
+            .iter()
+            .filter(|(kind, _)| !is_trivia(*kind))
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    let expected = significant(&tokens);
+
+    // The lexer of nix depends on context, e.g. inside of strings, so checking
+    // pairs of tokens cannot prove that the whole result is lexed the same,
+    // fall back to single spaces, which are always safe, if it is not
+    let compacted = join_tokens(&tokens, false);
+    if significant(&tokenize(&compacted)) == expected {
+        Ok(compacted)
+    } else {
+        Ok(join_tokens(&tokens, true))
+    }
+}
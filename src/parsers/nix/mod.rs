@@ -0,0 +1,1654 @@
+use std::iter::zip;
+use std::ops::Range;
+use std::sync::Arc;
+
+use annotate_snippets::{Annotation, Level, Snippet};
+use codemap::{File, Span};
+use pyo3::create_exception;
+use pyo3::exceptions::PyUserWarning;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::PyObject;
+use pyo3::{pyfunction, PyResult};
+use rnix::ast::{AstNode, Expr};
+use rnix::parser::ParseError as RnixParseError;
+use rnix::{Root, WalkEvent};
+use tvix_eval::{
+    Error as TvixError, ErrorKind as TvixErrorKind, EvalWarning, WarningKind,
+};
+
+use crate::parsers::floats::FloatOptions;
+use crate::parsers::overflow::IntOverflow;
+use crate::parsers::render::renderer;
+use crate::parsers::utils::{
+    coded_err, display_source, ErrorCode, EvaluationError, FileSource,
+    IntoAnnotation, IntoPyErr, IntoRange, ParseError,
+};
+
+mod ast;
+mod cache;
+mod comparison;
+mod complexity;
+mod convert;
+mod evaluator;
+mod fetch;
+mod flake;
+mod function;
+mod imports;
+mod json;
+mod limits;
+mod linter;
+mod literal_bindings;
+mod lock;
+mod managed;
+mod minify;
+mod modules;
+mod option_coverage;
+mod options;
+mod order;
+mod parameters;
+mod positions;
+mod profiler;
+mod pyio;
+mod renaming;
+mod report;
+mod sandbox;
+mod serialize;
+mod session;
+mod sources;
+mod store;
+mod suggest;
+mod tokens;
+mod validate;
+mod value_diff;
+mod writers;
+
+pub use ast::{parse, parse_file, parse_to_json, NixAst};
+use cache::CacheOption;
+pub use comparison::compare;
+pub use complexity::metrics;
+use convert::PathsAs;
+pub use evaluator::Evaluator;
+use evaluator::{DepthOption, NixPath};
+pub use fetch::FetchCache;
+pub use flake::eval_flake;
+pub use function::{NixFunction, NixThunk};
+use limits::depth_limit;
+pub use linter::lint;
+pub use literal_bindings::literals;
+pub use lock::load_flake_lock;
+pub use managed::{managed_regions, replace_managed};
+pub use minify::compact;
+pub use modules::eval_modules;
+pub use option_coverage::coverage;
+pub use options::extract_options;
+pub use parameters::formals;
+pub use renaming::rename;
+pub use serialize::{dumps, escape_string, format_attrpath, quote_attr};
+pub use session::Session;
+pub use tokens::{tokenize, TokenKind};
+
+create_exception!(nix, TraceWarning, PyUserWarning);
+
+impl IntoRange<usize> for Span {
+    fn into_range(self) -> Range<usize> {
+        // pub struct Span { low: Pos, high: Pos };
+        // struct Pos(u32);
+        // Cannot access Pos.0, so we need to use unsafe code to access the low
+        // and high positions.
+        unsafe {
+            let low = self.low();
+            let high = self.high();
+            Range {
+                start: ((&raw const low) as *const u32).read() as usize - 1,
+                end: ((&raw const high) as *const u32).read() as usize - 1,
+            }
+        }
+    }
+}
+
+impl<'a> IntoAnnotation<'a> for &RnixParseError {
+    fn into_annotation(self) -> (Option<Annotation<'a>>, String) {
+        match self {
+            RnixParseError::Unexpected(range) => (
+                Some(Level::Error.span(Range::<usize>::from(*range))),
+                "error node".into(),
+            ),
+            RnixParseError::UnexpectedExtra(range) => (
+                Some(Level::Error.span(Range::<usize>::from(*range))),
+                "unexpected token at".into(),
+            ),
+            RnixParseError::UnexpectedWanted(got, range, kinds) => (
+                Some(Level::Error.span(Range::<usize>::from(*range))),
+                format!("expect any of {:?}, found {:?}", kinds, got),
+            ),
+            RnixParseError::UnexpectedDoubleBind(range) => (
+                Some(Level::Error.span(Range::<usize>::from(*range))),
+                "unexpected double bind".into(),
+            ),
+            RnixParseError::UnexpectedEOF => {
+                (None, "unexpected EOF".to_string())
+            }
+            RnixParseError::UnexpectedEOFWanted(kinds) => {
+                (None, format!("unexpected EOF, expected any of {:?}", kinds))
+            }
+            RnixParseError::DuplicatedArgs(range, ident) => (
+                Some(Level::Error.span(Range::<usize>::from(*range))),
+                format!("duplicated argument {}", ident),
+            ),
+            RnixParseError::RecursionLimitExceeded => (
+                None,
+                "recursion limit of the parser exceeded, the code is nested \
+                too deeply"
+                    .to_string(),
+            ),
+            _ => (None, "unknown error".to_string()),
+        }
+    }
+}
+
+/// Render the errors of parsing nix code as a single `ParseError`
+fn parse_errors_into_pyerr(
+    errors: &[RnixParseError],
+    snippet: Snippet,
+) -> PyErr {
+    let mut annotations = Vec::new();
+    let mut anno_messages = Vec::new();
+    let mut messages = Vec::new();
+
+    for error in errors {
+        let (annotation, message) = error.into_annotation();
+        if let Some(annotation) = annotation {
+            annotations.push(annotation);
+            anno_messages.push(message);
+        } else {
+            messages.push(message);
+        }
+    }
+
+    let annotations =
+        zip(annotations, anno_messages.iter()).map(|(a, m)| a.label(m));
+    let message = Level::Error
+        .title("failed to parse Nix code")
+        .snippet(snippet.annotations(annotations));
+    let message = renderer().render(message).to_string();
+    coded_err::<ParseError>(ErrorCode::NixSyntax, message)
+}
+
+/// Parse nix code with rnix only, raising its syntax errors located in
+/// `origin`
+fn parse_root(content: &str, origin: Option<&str>) -> PyResult<Root> {
+    let parsed = Root::parse(content);
+    if !parsed.errors().is_empty() {
+        let source = display_source(content);
+        let snippet = Snippet::source(&source).fold(true);
+        let snippet = match origin {
+            Some(origin) => snippet.origin(origin),
+            None => snippet,
+        };
+        return Err(parse_errors_into_pyerr(parsed.errors(), snippet));
+    }
+    Ok(parsed.tree())
+}
+
+/// Raise a `ParseError` if the expressions of `root` are nested deeper than
+/// `max_depth`, located at the first expression past it
+fn check_nesting(
+    content: &str,
+    origin: Option<&str>,
+    root: &Root,
+    max_depth: usize,
+) -> PyResult<()> {
+    let mut depth = 0;
+    // Walked without recursing, the tree may be too deep for the stack
+    for event in root.syntax().preorder() {
+        match event {
+            WalkEvent::Enter(node) if Expr::can_cast(node.kind()) => {
+                depth += 1;
+                if depth <= max_depth {
+                    continue;
+                }
+                let source = display_source(content);
+                let snippet = Snippet::source(&source).fold(true);
+                let snippet = match origin {
+                    Some(origin) => snippet.origin(origin),
+                    None => snippet,
+                };
+                let range = Range::<usize>::from(node.text_range());
+                let label = format!("nested {} expressions deep", depth);
+                let message = Level::Error
+                    .title(&format!(
+                        "nix code is nested deeper than its limit of {}",
+                        max_depth
+                    ))
+                    .snippet(
+                        snippet
+                            .annotation(Level::Error.span(range).label(&label)),
+                    );
+                let message = renderer().render(message).to_string();
+                return Err(coded_err::<ParseError>(
+                    ErrorCode::NixNestingLimit,
+                    message,
+                ));
+            }
+            WalkEvent::Leave(node) if Expr::can_cast(node.kind()) => {
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// A frame of the trace of an evaluation error, located in its file
+struct Frame {
+    file: Arc<File>,
+    range: Range<usize>,
+    /// Whether the error was raised there, rather than called from there
+    raised: bool,
+}
+
+/// Render an evaluation error raised at `error.span`, called from the
+/// `outer` spans, the innermost first. Frames in the evaluated expression
+/// are annotated on `snippet`, the others on snippets of their own files,
+/// and a suggestion to fix a typo comes last.
+fn render_trace(
+    error: &TvixError,
+    outer: &[Span],
+    contexts: &[String],
+    snippet: Snippet,
+) -> String {
+    let codemap = error.source.codemap();
+    let frames = std::iter::once((error.span, true))
+        .chain(outer.iter().map(|span| (*span, false)))
+        .map(|(span, raised)| {
+            let file = codemap.find_file(span.low()).clone();
+            let start = (span.low() - file.span.low()) as usize;
+            let end = (span.high() - file.span.low()) as usize;
+            Frame {
+                file,
+                range: start..end,
+                raised,
+            }
+        })
+        // Code evaluated on values of the evaluation, like `deepSeq`, is
+        // not part of what the user wrote
+        .filter(|f| f.raised || !f.file.source().contains("__cosutils"))
+        .collect::<Vec<_>>();
+    let annotation = |frame: &Frame| match frame.raised {
+        true => Level::Error.span(frame.range.clone()),
+        false => Level::Note
+            .span(frame.range.clone())
+            .label("while evaluating this"),
+    };
+
+    // The evaluated expression is the first file of the source map
+    let (main, others): (Vec<_>, Vec<_>) = frames
+        .iter()
+        .partition(|f| f.file.span.into_range().start == 0);
+    let mut files = Vec::<&Arc<File>>::new();
+    for frame in &others {
+        if !files.iter().any(|f| Arc::ptr_eq(f, &frame.file)) {
+            files.push(&frame.file);
+        }
+    }
+    let sources = files
+        .iter()
+        .map(|f| display_source(f.source()))
+        .collect::<Vec<_>>();
+    let snippets = zip(&files, &sources).map(|(file, source)| {
+        Snippet::source(source)
+            .origin(file.name())
+            .fold(true)
+            .annotations(
+                others
+                    .iter()
+                    .filter(|f| Arc::ptr_eq(&f.file, file))
+                    .map(|f| annotation(f)),
+            )
+    });
+
+    let title = error.to_string();
+    let mut message = Level::Error.title(&title);
+    // Without annotations, the whole expression would be shown
+    if !main.is_empty() {
+        message = message
+            .snippet(snippet.annotations(main.iter().map(|f| annotation(f))));
+    }
+    let suggestion = suggest::suggestion(error);
+    let message = message
+        .snippets(snippets)
+        .footers(contexts.iter().map(|c| Level::Note.title(c)))
+        .footers(suggestion.iter().map(|s| Level::Help.title(s)));
+    renderer().render(message).to_string()
+}
+
+impl IntoPyErr for TvixError {
+    fn into_pyerr(self, snippet: Snippet) -> PyErr {
+        // Errors raised in called functions and builtins are wrapped in
+        // errors located at the calls, down to where they were raised
+        let mut error = self;
+        let mut outer = Vec::new();
+        let mut contexts = Vec::new();
+        loop {
+            match error.kind {
+                TvixErrorKind::NativeError { err, .. }
+                | TvixErrorKind::BytecodeError(err) => {
+                    outer.push(error.span);
+                    contexts.extend(error.contexts);
+                    error = *err;
+                }
+                _ => break,
+            }
+        }
+        outer.reverse();
+        contexts.extend(error.contexts.iter().cloned());
+
+        match &error.kind {
+            TvixErrorKind::ParseErrors(errors) => {
+                parse_errors_into_pyerr(errors, snippet)
+            }
+            _ => {
+                let message = render_trace(&error, &outer, &contexts, snippet);
+                coded_err::<EvaluationError>(ErrorCode::NixEvaluation, message)
+            }
+        }
+    }
+}
+
+/// Describe a warning emitted by tvix
+fn describe_warning(kind: &WarningKind) -> String {
+    match kind {
+        WarningKind::UselessInherit => {
+            "inherit does nothing (this variable already exists with the \
+            same value)"
+                .to_string()
+        }
+        WarningKind::UnusedBinding => "variable is unused".to_string(),
+        WarningKind::ShadowedGlobal(name) => {
+            format!("declared variable '{}' shadows a built-in global", name)
+        }
+        WarningKind::DeprecatedLegacyLet => {
+            "legacy `let` syntax used, please rewrite this as `let .. in ...`"
+                .to_string()
+        }
+        WarningKind::DeprecatedLiteralURL => {
+            "URL literal syntax is deprecated, use a quoted string instead"
+                .to_string()
+        }
+        WarningKind::DeadCode => "this code will never be executed".to_string(),
+        WarningKind::EmptyInherit => "this `inherit` statement is empty".into(),
+        WarningKind::EmptyLet => "this `let`-expression contains no bindings, \
+            the `let` can be removed"
+            .to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Render the warnings of a strict evaluation as a single error
+fn warnings_into_pyerr(
+    warnings: &[EvalWarning],
+    expr: &str,
+    location: &str,
+) -> PyErr {
+    let messages = warnings
+        .iter()
+        .map(|w| (w.span.into_range(), describe_warning(&w.kind)))
+        .collect::<Vec<_>>();
+    // Warnings of imported files cannot be located in this snippet
+    let (located, unlocated): (Vec<_>, Vec<_>) = messages
+        .iter()
+        .partition(|(range, _)| range.end <= expr.len());
+
+    let source = display_source(expr);
+    let snippet = Snippet::source(&source).origin(location).fold(true);
+    let annotations = located
+        .into_iter()
+        .map(|(range, m)| Level::Warning.span(range.clone()).label(m));
+    let message = Level::Error
+        .title("evaluation emitted warnings in strict mode")
+        .snippet(snippet.annotations(annotations))
+        .footers(unlocated.into_iter().map(|(_, m)| Level::Warning.title(m)));
+    let message = renderer().render(message).to_string();
+    coded_err::<EvaluationError>(ErrorCode::NixWarning, message)
+}
+
+/// Evaluate a nix file and convert it to Python object.
+///
+/// Args:
+///   - path (str | os.PathLike | IO): The path to the nix file, or a
+///                                    file-like object whose `read` returns
+///                                    its content as a string or UTF-8
+///                                    bytes, like `sys.stdin`. A stream
+///                                    read from a file is evaluated as that
+///                                    file, other streams like `evals`
+///                                    without `dir`, and labelled in errors
+///                                    by their `name`, like `<stdin>`, or as
+///                                    `<stream>`.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - lazy (bool): Only evaluate what is needed to produce the top-level
+///                  value, returning unevaluated parts as `NixThunk`.
+///   - depth (str | int): How deep the result is evaluated, `deep`,
+///                        `shallow` or a number of levels of attrsets and
+///                        lists, see `Evaluator`.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - cache (bool | str): Cache converted results in memory, or on disk in
+///                         the given directory, see `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - max_depth (int): Refuse code nesting expressions deeper than this,
+///                      which can only lower the fixed limit of the parser,
+///                      see `Evaluator`.
+///   - max_call_depth (int): Abort the evaluation once it nests more than
+///                           this many calls, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - collect_traces (bool): Return the messages of `builtins.trace` and
+///                            the warnings along with the value, instead of
+///                            emitting them as `TraceWarning`.
+///   - collect_conversion_errors (bool): Raise a single `ConversionError`
+///                                       listing every part of the result
+///                                       that cannot be converted, see
+///                                       `Evaluator`.
+///   - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+///                     for `pathlib.Path`, see `Evaluator`.
+///   - ordered (bool): Convert attrsets with their attributes in the order
+///                     they are defined in the source, see `Evaluator`.
+///   - profile (bool): Return where the evaluation spent its time along with
+///                     the value, to find what slows it down, see
+///                     `Evaluator`.
+///   - override (dict | None): Attributes merged into the result like
+///                             `lib.recursiveUpdate`, to tweak a config
+///                             without editing its file. Values are converted
+///                             like the args of `evals`, nested dicts are
+///                             merged into the attrsets they override
+///                             attribute by attribute, and any other value
+///                             replaces the value it overrides.
+///   - int_overflow (str | None): What to do with the integers of `override`
+///                                that do not fit into 64 bits, see
+///                                `Evaluator`.
+///   - float_policy (str | None): What to do with the infinite and NaN floats
+///                                of the result and of `override`, see
+///                                `Evaluator`.
+///
+/// Returns:
+///   - _ProfiledNixValue: The evaluated nix expression as any Python object,
+///                        or a tuple of it, its traces when collecting them,
+///                        and its profile when profiling it.
+///
+/// Raises:
+///   - IOError: If the file or the stream cannot be read.
+///   - TypeError: If `path` is neither a path nor a file-like object.
+///   - ValueError: If the depth, the overflow policy or the float policy is
+///                 unknown, or a depth limit is not positive.
+///   - ParseError: If the nix file cannot be parsed, or is nested too
+///                 deeply.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If the evaluation exceeds its memory or call depth
+///                         limit.
+///   - ConversionError: If the result cannot be converted to a Python object.
+///
+/// Example:
+/// ```python
+/// # `path/to/file.nix` contains:
+/// # ```
+/// # {a = 1;}
+/// # ```
+/// >>> eval("path/to/file.nix")
+/// {'a': 1}
+/// >>> eval("path/to/file.nix", override={"a": 2, "b": {"c": True}})
+/// {'a': 2, 'b': {'c': True}}
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    strict = None,
+    lazy = false,
+    depth = None,
+    nix_path = None,
+    cache = None,
+    timeout = None,
+    max_memory_mb = None,
+    max_depth = None,
+    max_call_depth = None,
+    normalize_newlines = true,
+    collect_traces = false,
+    collect_conversion_errors = false,
+    paths_as = "str",
+    ordered = false,
+    profile = false,
+    r#override = None,
+    int_overflow = None,
+    float_policy = None
+))]
+pub fn eval(
+    py: Python<'_>,
+    path: FileSource<'_>,
+    strict: Option<bool>,
+    lazy: bool,
+    depth: Option<DepthOption>,
+    nix_path: Option<NixPath>,
+    cache: Option<CacheOption>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    max_depth: Option<usize>,
+    max_call_depth: Option<usize>,
+    normalize_newlines: bool,
+    collect_traces: bool,
+    collect_conversion_errors: bool,
+    paths_as: &str,
+    ordered: bool,
+    profile: bool,
+    r#override: Option<Bound<'_, PyDict>>,
+    int_overflow: Option<&str>,
+    float_policy: Option<&str>,
+) -> PyResult<PyObject> {
+    Evaluator {
+        lazy,
+        depth: depth.map(DepthOption::into_levels).transpose()?.flatten(),
+        cache: cache.and_then(CacheOption::into_cache),
+        max_depth: max_depth
+            .map(|d| depth_limit(d, "Nesting limit"))
+            .transpose()?,
+        max_call_depth: max_call_depth
+            .map(|d| depth_limit(d, "Call depth limit"))
+            .transpose()?,
+        collect_traces,
+        collect_conversion_errors,
+        paths_as: PathsAs::of_name(paths_as)?,
+        ordered,
+        profile,
+        int_overflow: int_overflow.map(IntOverflow::of_name).transpose()?,
+        floats: FloatOptions::new(float_policy, None, None)?,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .eval(py, path, r#override)
+}
+
+/// Evaluate independent nix files in parallel and convert them to Python
+/// objects.
+///
+/// Each file is evaluated on its own, on one of the threads of the batch
+/// operations, with the GIL released during the evaluation and only taken
+/// to convert its result. A file failing does not stop the others, its
+/// error is returned in place of its value. Results are evaluated deeply,
+/// as unevaluated values and functions cannot leave the thread of their
+/// evaluation, and are never cached.
+///
+/// Args:
+///   - paths (list[str]): The paths to the nix files.
+///   - jobs (int | None): The number of files evaluated at once, `None` as
+///                        set by `set_threads`.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation of a file after this many
+///                      seconds.
+///   - max_memory_mb (int): Abort the evaluation of a file once it allocated
+///                          more than this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - collect_traces (bool): Return the messages of `builtins.trace` and
+///                            the warnings along with each value, instead of
+///                            emitting them as `TraceWarning`.
+///   - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+///                     for `pathlib.Path`, see `Evaluator`.
+///   - ordered (bool): Convert attrsets with their attributes in the order
+///                     they are defined in the source, see `Evaluator`.
+///
+/// Returns:
+///   - list[_ProfiledNixValue | Exception]: The value of each file, in the
+///                                          order of `paths`, or a tuple of it
+///                                          and its traces when collecting
+///                                          them. Files that cannot be read,
+///                                          parsed, evaluated or converted give
+///                                          the exception `eval` would raise
+///                                          instead.
+///
+/// Raises:
+///   - ValueError: If the number of jobs is not positive.
+///
+/// Example:
+/// ```python
+/// >>> hosts = sorted(glob.glob("hosts/*.nix"))
+/// >>> results = eval_many(hosts, jobs=8)
+/// >>> failed = {
+/// ...     host: result
+/// ...     for host, result in zip(hosts, results)
+/// ...     if isinstance(result, Exception)
+/// ... }
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    paths,
+    jobs = None,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true,
+    collect_traces = false,
+    paths_as = "str",
+    ordered = false
+))]
+pub fn eval_many(
+    py: Python<'_>,
+    paths: Vec<String>,
+    jobs: Option<usize>,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+    collect_traces: bool,
+    paths_as: &str,
+    ordered: bool,
+) -> PyResult<Vec<PyObject>> {
+    Evaluator {
+        collect_traces,
+        paths_as: PathsAs::of_name(paths_as)?,
+        ordered,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .eval_many(py, paths, jobs)
+}
+
+/// Evaluate a nix file to a JSON string.
+///
+/// The value is serialized in Rust without converting it to Python objects,
+/// which dominates the cost of `eval` on large attrsets, for pipelines
+/// parsing the JSON with a faster library. Attributes are sorted like with
+/// `builtins.toJSON`, and paths are written as strings. Results are never
+/// cached, and traces are emitted as `TraceWarning`.
+///
+/// Args:
+///   - path (str): The path to the nix file.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - float_policy (str | None): What to do with infinite and NaN floats,
+///                                see `Evaluator`.
+///   - float_repr (str | None): How to write finite floats, `"shortest"`,
+///                              `"decimal"` or `"scientific"`, see
+///                              `Evaluator`.
+///   - float_precision (int | None): The digits written after the point by
+///                                   the `decimal` and `scientific` reprs.
+///
+/// Returns:
+///   - str: The evaluated nix expression as compact JSON.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ValueError: If a float policy or repr is unknown, or a precision is
+///                 given without the `decimal` or `scientific` repr.
+///   - ParseError: If the nix file cannot be parsed.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///   - ConversionError: If the result contains a function or a float that
+///                      JSON cannot represent.
+///
+/// Example:
+/// ```python
+/// # `path/to/file.nix` contains:
+/// # ```
+/// # { b = [ 1 2.5 ]; a = "x"; }
+/// # ```
+/// >>> eval_to_json("path/to/file.nix")
+/// '{"a":"x","b":[1,2.5]}'
+/// >>> orjson.loads(eval_to_json("path/to/file.nix"))
+/// {'a': 'x', 'b': [1, 2.5]}
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true,
+    float_policy = None,
+    float_repr = None,
+    float_precision = None
+))]
+pub fn eval_to_json(
+    py: Python<'_>,
+    path: String,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+    float_policy: Option<&str>,
+    float_repr: Option<&str>,
+    float_precision: Option<usize>,
+) -> PyResult<String> {
+    Evaluator {
+        floats: FloatOptions::new(float_policy, float_repr, float_precision)?,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .eval_to_json(py, path)
+}
+
+/// Evaluate a nix file to a TOML document.
+///
+/// Like `eval_to_json`, the value is serialized in Rust, for generating the
+/// configuration of applications reading TOML. Attributes are sorted like
+/// nix sorts them, attrsets become tables and lists of attrsets arrays of
+/// tables, other lists and the attrsets in them being written inline. Paths
+/// are written as strings. Infinite and NaN floats are written as `inf` and
+/// `nan`, unless `float_policy` says otherwise. Results are never cached,
+/// and traces are emitted as `TraceWarning`.
+///
+/// Args:
+///   - path (str): The path to the nix file.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - float_policy (str | None): What to do with infinite and NaN floats,
+///                                see `Evaluator`.
+///   - float_repr (str | None): How to write finite floats, `"shortest"`,
+///                              `"decimal"` or `"scientific"`, see
+///                              `Evaluator`.
+///   - float_precision (int | None): The digits written after the point by
+///                                   the `decimal` and `scientific` reprs.
+///
+/// Returns:
+///   - str: The evaluated nix expression as a TOML document.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ValueError: If a float policy or repr is unknown, or a precision is
+///                 given without the `decimal` or `scientific` repr.
+///   - ParseError: If the nix file cannot be parsed.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///   - ConversionError: If the result is not an attrset, or contains a
+///                      function or a `null`, which TOML has no literal for.
+///
+/// Example:
+/// ```python
+/// # `path/to/file.nix` contains:
+/// # ```
+/// # {
+/// #   name = "app";
+/// #   server = { port = 8080; hosts = [ "a" "b" ]; };
+/// #   users = [ { name = "alice"; } { name = "bob"; } ];
+/// # }
+/// # ```
+/// >>> print(eval_to_toml("path/to/file.nix"))
+/// name = "app"
+///
+/// [server]
+/// hosts = ["a", "b"]
+/// port = 8080
+///
+/// [[users]]
+/// name = "alice"
+///
+/// [[users]]
+/// name = "bob"
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true,
+    float_policy = None,
+    float_repr = None,
+    float_precision = None
+))]
+pub fn eval_to_toml(
+    py: Python<'_>,
+    path: String,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+    float_policy: Option<&str>,
+    float_repr: Option<&str>,
+    float_precision: Option<usize>,
+) -> PyResult<String> {
+    Evaluator {
+        floats: FloatOptions::new(float_policy, float_repr, float_precision)?,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .eval_to_toml(py, path)
+}
+
+/// Evaluate a nix file to a YAML document.
+///
+/// Like `eval_to_json`, the value is serialized in Rust, for generating the
+/// configuration of applications reading YAML. The document is in block
+/// style, with attributes sorted like nix sorts them. Strings are written
+/// plain when no YAML reader can take them for another type, like `yes` or
+/// `1.0`, and double-quoted otherwise. Paths are written as strings.
+/// Infinite and NaN floats are written as `.inf` and `.nan`, unless
+/// `float_policy` says otherwise. Results are never cached, and traces are
+/// emitted as `TraceWarning`.
+///
+/// Args:
+///   - path (str): The path to the nix file.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - float_policy (str | None): What to do with infinite and NaN floats,
+///                                see `Evaluator`.
+///   - float_repr (str | None): How to write finite floats, `"shortest"`,
+///                              `"decimal"` or `"scientific"`, see
+///                              `Evaluator`.
+///   - float_precision (int | None): The digits written after the point by
+///                                   the `decimal` and `scientific` reprs.
+///
+/// Returns:
+///   - str: The evaluated nix expression as a YAML document.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ValueError: If a float policy or repr is unknown, or a precision is
+///                 given without the `decimal` or `scientific` repr.
+///   - ParseError: If the nix file cannot be parsed.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///   - ConversionError: If the result contains a function.
+///
+/// Example:
+/// ```python
+/// # `path/to/file.nix` contains:
+/// # ```
+/// # {
+/// #   name = "app";
+/// #   enabled = "yes";
+/// #   users = [ { name = "alice"; groups = [ "wheel" ]; } ];
+/// #   extra = { };
+/// # }
+/// # ```
+/// >>> print(eval_to_yaml("path/to/file.nix"))
+/// enabled: "yes"
+/// extra: {}
+/// name: app
+/// users:
+///   - groups:
+///       - wheel
+///     name: alice
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true,
+    float_policy = None,
+    float_repr = None,
+    float_precision = None
+))]
+pub fn eval_to_yaml(
+    py: Python<'_>,
+    path: String,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+    float_policy: Option<&str>,
+    float_repr: Option<&str>,
+    float_precision: Option<usize>,
+) -> PyResult<String> {
+    Evaluator {
+        floats: FloatOptions::new(float_policy, float_repr, float_precision)?,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .eval_to_yaml(py, path)
+}
+
+/// Evaluate two nix files and diff their values, attribute by attribute.
+///
+/// Both files are evaluated deeply, and their values compared in Rust
+/// without converting the attributes that did not change to Python objects.
+/// Attrsets are walked down to the attribute paths whose values differ,
+/// other values like lists are compared as a whole, like nix compares them:
+/// integers equal floats of the same value, and functions are never equal.
+/// Only the values at the reported paths are converted, like with `eval`.
+/// Traces of both evaluations are emitted as `TraceWarning`.
+///
+/// Args:
+///   - path_a (str): The path to the nix file of the old value.
+///   - path_b (str): The path to the nix file of the new value.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort each evaluation after this many seconds.
+///   - max_memory_mb (int): Abort each evaluation once it allocated more
+///                          than this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///
+/// Returns:
+///   - _NixDiff: The attribute paths `added` to the new value with their
+///               new values, `removed` from it with their old values, and
+///               `changed` with their `old` and `new` values, in the sorted
+///               order of nix. Paths are written like in nix, the empty path
+///               standing for whole values that are not both attrsets.
+///
+/// Raises:
+///   - IOError: If a file cannot be read.
+///   - ParseError: If a nix file cannot be parsed.
+///   - EvaluationError: If a nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If an evaluation exceeds its memory limit.
+///   - ConversionError: If a reported value cannot be converted to Python.
+///
+/// Example:
+/// ```python
+/// # `old.nix` contains:
+/// # ```
+/// # { networking.hostName = "a"; services.nginx.enable = true; }
+/// # ```
+/// # `new.nix` contains:
+/// # ```
+/// # {
+/// #   networking.hostName = "b";
+/// #   services."my.app".port = 8080;
+/// #   services.nginx.enable = true;
+/// # }
+/// # ```
+/// >>> changes = diff("old.nix", "new.nix")
+/// >>> changes["added"], changes["removed"]
+/// ({'services."my.app"': {'port': 8080}}, {})
+/// >>> changes["changed"]
+/// {'networking.hostName': {'old': 'a', 'new': 'b'}}
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path_a,
+    path_b,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true
+))]
+pub fn diff(
+    py: Python<'_>,
+    path_a: String,
+    path_b: String,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+) -> PyResult<PyObject> {
+    Evaluator::from_profile(
+        strict,
+        nix_path,
+        timeout,
+        max_memory_mb,
+        normalize_newlines,
+    )?
+    .diff(py, path_a, path_b)
+}
+
+/// Evaluate a nix file and validate its value against a JSON Schema, then
+/// convert it to Python objects.
+///
+/// The file is evaluated deeply and its value validated in Rust, before
+/// anything is converted. Every value that does not match the schema is
+/// reported, each pointing at the expression defining it, found in the
+/// syntax through functions, `let` and `with`, and into the files imported
+/// with a path literal, like `import ./hosts.nix`, or at the closest
+/// definition enclosing it when it is defined elsewhere, like by a function
+/// call. Snippets show the files as the evaluation read them.
+///
+/// The schema supports the `type`, `enum`, `const`, `anyOf`, `properties`,
+/// `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+/// `minLength`, `maxLength`, `pattern`, `minimum`, `maximum`,
+/// `exclusiveMinimum` and `exclusiveMaximum` keywords of JSON Schema, and
+/// ignores the others. Nix attrsets are `object`, lists `array`, paths
+/// `string`, and integers are also `number`. Patterns are searched with the
+/// `re` module of Python.
+///
+/// Args:
+///   - path (str | os.PathLike | IO): The path to the nix file, or a
+///                                    file-like object, see `eval`.
+///   - schema (dict): The JSON Schema of the value.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///
+/// Returns:
+///   - _EvaluatedNixValue: The evaluated value, converted like with `eval`.
+///
+/// Raises:
+///   - IOError: If the file or the stream cannot be read.
+///   - TypeError: If `path` is neither a path nor a file-like object, or a
+///                keyword of the schema has the wrong type, like
+///                `properties` that is not a dict.
+///   - ParseError: If the nix file cannot be parsed.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out, or with the code `NIX_E040` if its value does
+///                      not match the schema, listing each violation in its
+///                      `errors` attribute.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///   - ConversionError: If the value cannot be converted to Python.
+///
+/// Example:
+/// ```python
+/// # `config.nix` contains:
+/// # ```
+/// # {
+/// #   hosts = "a.example.com";
+/// #   port = 8080;
+/// # }
+/// # ```
+/// >>> schema = {
+/// ...     "type": "object",
+/// ...     "properties": {
+/// ...         "hosts": {"type": "array", "items": {"type": "string"}},
+/// ...         "port": {"type": "integer", "maximum": 65535},
+/// ...     },
+/// ...     "required": ["hosts", "port"],
+/// ... }
+/// >>> eval_validated("config.nix", schema)
+/// Traceback (most recent call last):
+///   ...
+/// EvaluationError: error: `hosts`: expected array, got string
+///  --> config.nix:2:11
+///   |
+/// 2 |   hosts = "a.example.com";
+///   |           ^^^^^^^^^^^^^^^ defined here
+///   |
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    schema,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true
+))]
+pub fn eval_validated(
+    py: Python<'_>,
+    path: FileSource<'_>,
+    schema: Bound<'_, PyDict>,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+) -> PyResult<PyObject> {
+    Evaluator::from_profile(
+        strict,
+        nix_path,
+        timeout,
+        max_memory_mb,
+        normalize_newlines,
+    )?
+    .eval_validated(py, path, schema)
+}
+
+/// Evaluate a nix file and convert it to Python objects, along with where
+/// each of its attributes is defined, to point users back to their nix
+/// files.
+///
+/// The file is evaluated deeply, and the attributes of its value are looked
+/// up in its syntax, through functions, `let`, `with` and nested attrsets,
+/// down from the top-level attrset, and into the files imported with a path
+/// literal, like `import ./hosts.nix`, as the evaluation read them. Each is
+/// located at its name in the binding defining it, like
+/// `builtins.unsafeGetAttrPos` does, so `a` of `a.b = 1;` is located at
+/// `a`. Attributes defined elsewhere, like by a function call, have no
+/// position, and neither have the attributes nested in them, nor the
+/// attributes of attrsets in lists.
+///
+/// Args:
+///   - path (str | os.PathLike | IO): The path to the nix file, or a
+///                                    file-like object, see `eval`.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///
+/// Returns:
+///   - tuple[_EvaluatedNixValue, dict]: The evaluated value, converted like
+///                                      with `eval`, and the positions of
+///                                      its attributes by attribute path,
+///                                      written like in nix, each with the
+///                                      `file`, as given for the evaluated
+///                                      file and as the canonical path of
+///                                      an imported one, and the 1-based
+///                                      `line` and `column`, in bytes.
+///
+/// Raises:
+///   - IOError: If the file or the stream cannot be read.
+///   - TypeError: If `path` is neither a path nor a file-like object.
+///   - ParseError: If the nix file cannot be parsed.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///   - ConversionError: If the value cannot be converted to Python.
+///
+/// Example:
+/// ```python
+/// # `config.nix` contains:
+/// # ```
+/// # { lib, ... }: {
+/// #   services.nginx.enable = true;
+/// #   networking = {
+/// #     hostName = "foo";
+/// #   };
+/// # }
+/// # ```
+/// >>> value, positions = eval_with_positions("config.nix")
+/// >>> positions["networking.hostName"]
+/// {'file': 'config.nix', 'line': 4, 'column': 5}
+/// >>> positions["services"]["line"], positions["services.nginx"]["column"]
+/// (2, 12)
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true
+))]
+pub fn eval_with_positions(
+    py: Python<'_>,
+    path: FileSource<'_>,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+) -> PyResult<PyObject> {
+    Evaluator::from_profile(
+        strict,
+        nix_path,
+        timeout,
+        max_memory_mb,
+        normalize_newlines,
+    )?
+    .eval_with_positions(py, path)
+}
+
+/// Evaluate a nix expression to a derivation, and describe what it would
+/// build without building it.
+///
+/// The expression is evaluated with a `derivation` builtin that returns the
+/// attrset nix would, but gives each output a placeholder store path derived
+/// from the attributes of the derivation, instead of the path nix would
+/// compute. Fixed-output derivations with a SHA-256 `outputHash` get the
+/// path nix computes, which only depends on their name and hash. Derivations
+/// made without the builtin are described too, as long as they are attrsets
+/// with `type = "derivation"`. Only the description is evaluated deeply, and
+/// it only contains strings, so it never raises a `ConversionError` on the
+/// internal attributes of the derivation.
+///
+/// Args:
+///   - content (str): The nix expression to evaluate.
+///   - dir (str): The base directory to evaluate the expression in, see
+///                `evals`.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - virtual_name (str): The file name of the expression in errors, see
+///                         `evals`.
+///
+/// Returns:
+///   - _Derivation: The `name`, `system` and `builder` of the derivation,
+///                  the `args` of the builder, its `env` with every
+///                  attribute coerced to a string like nix does and the
+///                  paths of the outputs, the `outputs` by name, and the
+///                  `outPath` and `drvPath` of the derivation. Paths are
+///                  not copied to the store. Results are never cached.
+///
+/// Raises:
+///   - ValueError: If the virtual name is not a file name.
+///   - ParseError: If the nix expression cannot be parsed.
+///   - EvaluationError: If the expression does not evaluate to a
+///                      derivation, an attribute cannot be coerced to a
+///                      string, or the evaluation times out.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///
+/// Example:
+/// ```python
+/// >>> drv = eval_drv('''
+/// ... derivation {
+/// ...   name = "hello";
+/// ...   system = "x86_64-linux";
+/// ...   builder = "/bin/sh";
+/// ...   args = [ "-c" "echo hi > $out" ];
+/// ...   outputs = [ "out" "doc" ];
+/// ... }
+/// ... ''')
+/// >>> drv["args"], sorted(drv["outputs"])
+/// (['-c', 'echo hi > $out'], ['doc', 'out'])
+/// >>> drv["env"]["outputs"]
+/// 'out doc'
+/// >>> drv["outputs"]["doc"] == drv["outPath"] + "-doc"
+/// True
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    content,
+    dir = None,
+    strict = None,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true,
+    virtual_name = None
+))]
+pub fn eval_drv(
+    py: Python<'_>,
+    content: String,
+    dir: Option<String>,
+    strict: Option<bool>,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+    virtual_name: Option<&str>,
+) -> PyResult<PyObject> {
+    Evaluator::from_profile(
+        strict,
+        nix_path,
+        timeout,
+        max_memory_mb,
+        normalize_newlines,
+    )?
+    .eval_drv(py, content, dir, virtual_name)
+}
+
+/// Evaluate only an attribute of a nix file and convert it to a Python
+/// object.
+///
+/// The file is evaluated lazily, and only the attrsets on the way to the
+/// attribute are forced, so the other attributes of a large configuration
+/// cost nothing. The attribute itself is evaluated deeply unless `lazy`.
+///
+/// Args:
+///   - path (str): The path to the nix file.
+///   - attr_path (str): The attribute to evaluate, like `services.nginx`.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - lazy (bool): Only evaluate the attribute to its top-level value,
+///                  returning unevaluated parts as `NixThunk`.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - collect_traces (bool): Return the messages of `builtins.trace` and
+///                            the warnings along with the value, instead of
+///                            emitting them as `TraceWarning`.
+///   - collect_conversion_errors (bool): Raise a single `ConversionError`
+///                                       listing every part of the result
+///                                       that cannot be converted, see
+///                                       `Evaluator`.
+///   - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+///                     for `pathlib.Path`, see `Evaluator`.
+///   - ordered (bool): Convert attrsets with their attributes in the order
+///                     they are defined in the source, see `Evaluator`.
+///
+/// Returns:
+///   - _EvaluatedNixValue: The evaluated attribute as any Python object, or
+///                         a tuple of it and its traces when collecting
+///                         them. Results are never cached.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ParseError: If the nix file cannot be parsed.
+///   - ValueError: If the attribute path is empty.
+///   - EvaluationError: If the attribute is missing or cannot be evaluated,
+///                      or the evaluation times out.
+///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+///   - ConversionError: If the result cannot be converted to a Python object.
+///
+/// Example:
+/// ```python
+/// # `path/to/file.nix` contains:
+/// # ```
+/// # { a.b = 1; c = throw "expensive"; }
+/// # ```
+/// >>> eval_attr("path/to/file.nix", "a")
+/// {'b': 1}
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    path,
+    attr_path,
+    strict = None,
+    lazy = false,
+    nix_path = None,
+    timeout = None,
+    max_memory_mb = None,
+    normalize_newlines = true,
+    collect_traces = false,
+    collect_conversion_errors = false,
+    paths_as = "str",
+    ordered = false
+))]
+pub fn eval_attr(
+    py: Python<'_>,
+    path: String,
+    attr_path: &str,
+    strict: Option<bool>,
+    lazy: bool,
+    nix_path: Option<NixPath>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    normalize_newlines: bool,
+    collect_traces: bool,
+    collect_conversion_errors: bool,
+    paths_as: &str,
+    ordered: bool,
+) -> PyResult<PyObject> {
+    Evaluator {
+        lazy,
+        collect_traces,
+        collect_conversion_errors,
+        paths_as: PathsAs::of_name(paths_as)?,
+        ordered,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .eval_attr(py, path, attr_path)
+}
+
+/// Evaluate a nix expression and convert it to Python object.
+///
+/// Args:
+///   - expr (str): The nix expression to evaluate.
+///   - dir (str): The base directory to evaluate the expression in, as if
+///                it was the content of a file of the directory named
+///                `virtual_name`. Relative paths like `./data.json` or
+///                `import ./lib.nix` are resolved from it, whether the
+///                virtual file exists or not.
+///   - args (dict): If given, the expression must evaluate to a function,
+///                  which is called with `args` converted to a nix attrset,
+///                  like `nix-instantiate --arg`.
+///   - scope (dict): Variables visible in the top-level scope of the
+///                   expression, converted to nix values, as if it was
+///                   wrapped in a `let` binding them but without shifting
+///                   the positions of its errors. They shadow the builtins
+///                   of the same name, like `let` bindings do.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - lazy (bool): Only evaluate what is needed to produce the top-level
+///                  value, returning unevaluated parts as `NixThunk`.
+///   - depth (str | int): How deep the result is evaluated, `deep`,
+///                        `shallow` or a number of levels of attrsets and
+///                        lists, see `Evaluator`.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///   - cache (bool | str): Cache converted results in memory, or on disk in
+///                         the given directory, see `Evaluator`.
+///   - timeout (float): Abort the evaluation after this many seconds.
+///   - max_memory_mb (int): Abort the evaluation once it allocated more than
+///                          this many MiB, see `Evaluator`.
+///   - max_depth (int): Refuse code nesting expressions deeper than this,
+///                      which can only lower the fixed limit of the parser,
+///                      see `Evaluator`.
+///   - max_call_depth (int): Abort the evaluation once it nests more than
+///                           this many calls, see `Evaluator`.
+///   - normalize_newlines (bool): Convert `\r\n` and `\r` line endings to
+///                                `\n`, see `Evaluator`.
+///   - collect_traces (bool): Return the messages of `builtins.trace` and
+///                            the warnings along with the value, instead of
+///                            emitting them as `TraceWarning`.
+///   - collect_conversion_errors (bool): Raise a single `ConversionError`
+///                                       listing every part of the result
+///                                       that cannot be converted, see
+///                                       `Evaluator`.
+///   - paths_as (str): What nix paths are converted to, `str` or `pathlib`
+///                     for `pathlib.Path`, see `Evaluator`.
+///   - ordered (bool): Convert attrsets with their attributes in the order
+///                     they are defined in the source, see `Evaluator`.
+///   - virtual_name (str): The file name of the expression in errors, and
+///                         in its directory if `dir` is given, so snippets
+///                         evaluated in the same directory can be told
+///                         apart. Defaults to `virtual.nix` in `dir`, and
+///                         to `tempfile` without it.
+///   - allow_import (bool): Let the expression import and read files.
+///                          `False` evaluates the string alone, any
+///                          `import`, `builtins.readFile` or other access to
+///                          a path raising an `EvaluationError`, so inline
+///                          snippets can be evaluated safely.
+///   - jail (bool): Only let the expression access the files under `dir`,
+///                  like a `restrict_paths` of `dir` alone, on top of the
+///                  paths the evaluator restricts it to. Importing or reading
+///                  any other file, like `../secret`, or a symlink leading
+///                  out of `dir`, raises an `EvaluationError`.
+///   - int_overflow (str | None): What to do with the integers of `args` and
+///                                the scope that do not fit into 64 bits, see
+///                                `Evaluator`.
+///   - float_policy (str | None): What to do with the infinite and NaN floats
+///                                of the result, `args` and the scope, see
+///                                `Evaluator`.
+///
+/// Returns:
+///   - _EvaluatedNixValue: The evaluated nix expression as any Python object,
+///                         or a tuple of it and its traces when collecting
+///                         them.
+///
+/// Raises:
+///   - ValueError: If the virtual name is not a file name, the depth, the
+///                 overflow policy or the float policy is unknown, a depth
+///                 limit is not positive, or the expression is jailed
+///                 without `dir`.
+///   - ParseError: If the nix file cannot be parsed, or is nested too
+///                 deeply.
+///   - EvaluationError: If the nix expression cannot be evaluated, or times
+///                      out.
+///   - ResourceLimitError: If the evaluation exceeds its memory or call depth
+///                         limit.
+///   - TypeError: If a variable of the scope is not named by a string.
+///   - ConversionError: If `args` or the scope cannot be converted to nix
+///                      values, or the result cannot be converted to a
+///                      Python object.
+///
+/// Example:
+/// ```python
+/// >>> evals("{a = 1;}")
+/// {'a': 1}
+/// >>> evals("{ name, enable ? false }: { inherit name enable; }",
+/// ...       args={"name": "foo"})
+/// {'enable': False, 'name': 'foo'}
+/// >>> evals('"${hostname}.example.com"', scope={"hostname": "web01"})
+/// 'web01.example.com'
+/// >>> evals('builtins.trace "hi" 1', collect_traces=True)
+/// (1, [{'kind': 'trace', 'message': 'hi', 'line': None}])
+/// >>> evals("(import ./lib.nix).version", dir="config", jail=True)
+/// '1.2.0'
+/// ```
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (
+    content,
+    dir = None,
+    args = None,
+    scope = None,
+    strict = None,
+    lazy = false,
+    depth = None,
+    nix_path = None,
+    cache = None,
+    timeout = None,
+    max_memory_mb = None,
+    max_depth = None,
+    max_call_depth = None,
+    normalize_newlines = true,
+    collect_traces = false,
+    collect_conversion_errors = false,
+    paths_as = "str",
+    ordered = false,
+    virtual_name = None,
+    allow_import = true,
+    jail = false,
+    int_overflow = None,
+    float_policy = None
+))]
+pub fn evals(
+    py: Python<'_>,
+    content: String,
+    dir: Option<String>,
+    args: Option<Bound<'_, PyDict>>,
+    scope: Option<Bound<'_, PyDict>>,
+    strict: Option<bool>,
+    lazy: bool,
+    depth: Option<DepthOption>,
+    nix_path: Option<NixPath>,
+    cache: Option<CacheOption>,
+    timeout: Option<f64>,
+    max_memory_mb: Option<usize>,
+    max_depth: Option<usize>,
+    max_call_depth: Option<usize>,
+    normalize_newlines: bool,
+    collect_traces: bool,
+    collect_conversion_errors: bool,
+    paths_as: &str,
+    ordered: bool,
+    virtual_name: Option<&str>,
+    allow_import: bool,
+    jail: bool,
+    int_overflow: Option<&str>,
+    float_policy: Option<&str>,
+) -> PyResult<PyObject> {
+    Evaluator {
+        lazy,
+        depth: depth.map(DepthOption::into_levels).transpose()?.flatten(),
+        cache: cache.and_then(CacheOption::into_cache),
+        max_depth: max_depth
+            .map(|d| depth_limit(d, "Nesting limit"))
+            .transpose()?,
+        max_call_depth: max_call_depth
+            .map(|d| depth_limit(d, "Call depth limit"))
+            .transpose()?,
+        collect_traces,
+        collect_conversion_errors,
+        paths_as: PathsAs::of_name(paths_as)?,
+        ordered,
+        restrict_paths: (!allow_import).then(|| Arc::from([])),
+        int_overflow: int_overflow.map(IntOverflow::of_name).transpose()?,
+        floats: FloatOptions::new(float_policy, None, None)?,
+        ..Evaluator::from_profile(
+            strict,
+            nix_path,
+            timeout,
+            max_memory_mb,
+            normalize_newlines,
+        )?
+    }
+    .evals(py, content, dir, args, scope, virtual_name, jail)
+}
@@ -0,0 +1,136 @@
+use std::fs;
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use tvix_eval::{NixAttrs, NixList, Value as TvixValue};
+
+use super::convert::Converter;
+use super::evaluator::{Evaluator, NixPath};
+use crate::parsers::profile;
+use crate::parsers::utils::TryFromPyObject;
+
+/// Evaluates modules with a bundled subset of the module system of nixpkgs
+const EVAL_MODULES: &str = include_str!("eval-modules.nix");
+
+/// Convert a module given from Python, files being imported by their
+/// absolute path
+fn module_of(module: &Bound<'_, PyAny>) -> PyResult<TvixValue> {
+    if module.is_instance_of::<PyDict>() {
+        return TvixValue::try_from_pyobject(module);
+    }
+    let path = module.extract::<PathBuf>()?;
+    let path = fs::canonicalize(&path).map_err(|e| {
+        PyIOError::new_err(format!(
+            "Failed to find module {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(TvixValue::String(path.to_string_lossy().as_ref().into()))
+}
+
+/// Evaluate NixOS-style modules and convert their final `config` to Python
+/// object, like `lib.evalModules` of nixpkgs without running nix.
+///
+/// The modules, their `imports` and the options they declare are collected,
+/// then the definitions of each option are merged by priority (`mkDefault`,
+/// `mkForce`, `mkOverride`) and order (`mkBefore`, `mkAfter`), conditions
+/// (`mkIf`) and merges (`mkMerge`) included, defaults and `apply` taken
+/// into account, and checked against the type of the option. Modules are
+/// given `config`, `options`, `lib` and the `special_args`, and can ask for
+/// the arguments of `_module.args`, like `pkgs`.
+///
+/// nixpkgs itself is not needed: the `lib` given to modules is a bundled
+/// subset of it, with the builtins, `mkOption`, `mkEnableOption`, the
+/// `mk*` properties, common helpers like `optionalAttrs` or
+/// `mapAttrsToList`, and the common `types`: `anything`, `raw`, `bool`,
+/// `int`, `ints.*`, `port`, `float`, `number`, `str`, `lines`, `commas`,
+/// `path`, `package`, `attrs`, `enum`, `nullOr`, `either`, `oneOf`,
+/// `listOf`, `attrsOf`, `lazyAttrsOf`, `submodule` and `submoduleWith`.
+/// Freeform modules and `disabledModules` are not supported.
+///
+/// Args:
+///   - modules (list[str | os.PathLike | dict]): The modules, as files or as
+///                                               attrsets of definitions,
+///                                               like `{"networking":
+///                                               {"hostName": "foo"}}`.
+///   - special_args (dict): Arguments given to every module, available in
+///                          `imports` unlike `_module.args`, converted like
+///                          `evals` args.
+///   - strict (bool | None): Turn warnings emitted during evaluation
+///                           (unused bindings, deprecated syntax, ...) into
+///                           errors, `None` as in the selected profile.
+///   - lazy (bool): Only evaluate what is needed to produce the top-level
+///                  value, returning unevaluated parts as `NixThunk`.
+///   - nix_path (list[str] | str): Where `<name>` paths are looked up, see
+///                                 `Evaluator`.
+///
+/// Returns:
+///   - _EvaluatedNixValue: The final `config` as any Python object
+///
+/// Raises:
+///   - IOError: If a module file cannot be found or read.
+///   - ParseError: If a module cannot be parsed.
+///   - EvaluationError: If a module cannot be evaluated, defines an option
+///                      that does not exist, or with a value not of its
+///                      type, or conflicting values.
+///   - ConversionError: If a module or an argument cannot be converted to a
+///                      nix value, or the result to a Python object.
+///
+/// Example:
+/// ```python
+/// >>> eval_modules(["hosts/laptop.nix", {"networking": {"hostName": "x"}}])["networking"]
+/// {'hostName': 'x', 'firewall': {'enable': True}}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    modules,
+    special_args = None,
+    strict = None,
+    lazy = false,
+    nix_path = None
+))]
+pub fn eval_modules(
+    py: Python<'_>,
+    modules: Vec<Bound<'_, PyAny>>,
+    special_args: Option<Bound<'_, PyDict>>,
+    strict: Option<bool>,
+    lazy: bool,
+    nix_path: Option<NixPath>,
+) -> PyResult<PyObject> {
+    let modules = modules
+        .iter()
+        .map(module_of)
+        .collect::<PyResult<Vec<_>>>()?;
+    let special_args = match special_args {
+        Some(args) => TvixValue::try_from_pyobject(args.as_any())?,
+        None => TvixValue::attrs(NixAttrs::empty()),
+    };
+    let args = TvixValue::attrs(NixAttrs::from_iter([
+        (
+            "modules".to_string(),
+            TvixValue::List(NixList::from(modules)),
+        ),
+        ("specialArgs".to_string(), special_args),
+    ]));
+
+    let evaluator = Evaluator {
+        strict: strict.unwrap_or(profile::current().nix_strict),
+        lazy,
+        nix_path: nix_path.map(NixPath::into_string),
+        ..Default::default()
+    };
+    let (value, context) =
+        evaluator.eval_expr(EVAL_MODULES, None, None, Some(args), None)?;
+    let object = Converter {
+        context: Some(&context),
+        order: None,
+        detached: false,
+    }
+    .convert(py, &value)?;
+    context.emit_traces(py)?;
+    Ok(object)
+}
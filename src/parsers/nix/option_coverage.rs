@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+use std::str::from_utf8;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+use pyo3::{PyObject, PyResult};
+use tvix_eval::{NixAttrs, Value as TvixValue};
+
+use super::evaluator::{Context, Evaluator};
+use crate::parsers::schema::{attrpath, is_option};
+use crate::parsers::utils::{
+    coded_err, read_file, ConversionError, ErrorCode, EvaluationError,
+};
+
+/// Options of a schema, grouped by how a configuration defines them
+#[derive(Default)]
+struct Coverage {
+    set: Vec<String>,
+    default: Vec<String>,
+    unknown: Vec<String>,
+}
+
+impl Coverage {
+    /// Compare the attributes of a configuration with a group of options,
+    /// forcing only the values needed to descend into nested groups
+    fn walk(
+        &mut self,
+        context: &Context,
+        schema: &Bound<'_, PyDict>,
+        config: Option<&NixAttrs>,
+        path: &mut Vec<String>,
+    ) -> PyResult<()> {
+        let mut defined = Vec::new();
+        for (k, v) in config.iter().flat_map(|c| c.iter()) {
+            let name = from_utf8(k.as_bytes()).map_err(|e| {
+                coded_err::<ConversionError>(
+                    ErrorCode::NixConversion,
+                    format!(
+                        "Failed to convert bytes to string ({}) on {}",
+                        e, k
+                    ),
+                )
+            })?;
+            defined.push((name.to_string(), v.clone()));
+        }
+
+        for (name, node) in schema.iter() {
+            let name = name.extract::<String>()?;
+            let Ok(node) = node.downcast::<PyDict>() else {
+                // Not part of the schema, such as `_type` or `description`
+                continue;
+            };
+            let value = defined
+                .iter()
+                .position(|(n, _)| *n == name)
+                .map(|i| defined.swap_remove(i).1);
+            path.push(name);
+
+            if is_option(node)? {
+                match value {
+                    Some(_) => self.set.push(attrpath(path)),
+                    None => self.default.push(attrpath(path)),
+                }
+            } else {
+                match value.map(|v| context.whnf(v)).transpose()? {
+                    Some(TvixValue::Attrs(attrs)) => {
+                        self.walk(context, node, Some(&attrs), path)?
+                    }
+                    // A group of options is set to something else than an
+                    // attrset, none of its options can be defined by it
+                    Some(_) => self.unknown.push(attrpath(path)),
+                    None => self.walk(context, node, None, path)?,
+                }
+            }
+            path.pop();
+        }
+
+        for (name, _) in defined {
+            path.push(name);
+            self.unknown.push(attrpath(path));
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Evaluate a nix config file and report which options of a schema it
+/// defines.
+///
+/// Options are the nodes of the schema that are attrsets with
+/// `_type = "option"`, like the result of `lib.mkOption`, every other attrset
+/// of the schema is a group of options. Only the values needed to tell the
+/// options apart are evaluated, so values of options are never forced.
+///
+/// Args:
+///   - entry (str): The path to the nix file evaluating to the config.
+///   - options_schema (dict): The options the config is checked against.
+///
+/// Returns:
+///   - dict[str, list[str]]: The attribute paths of the options that are
+///                           `set` by the config, left to their `default`,
+///                           and the paths that are set by the config but
+///                           `unknown` to the schema.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ParseError: If the nix file cannot be parsed.
+///   - EvaluationError: If the config cannot be evaluated, or is not an
+///                      attrset.
+///   - ConversionError: If an attribute name is not valid UTF-8.
+///
+/// Example:
+/// ```python
+/// # `path/to/config.nix` contains:
+/// # ```
+/// # { services.nginx.enable = true; services.foo = 1; }
+/// # ```
+/// >>> option = {"_type": "option"}
+/// >>> coverage(
+/// ...     "path/to/config.nix",
+/// ...     {"services": {"nginx": {"enable": option, "package": option}}},
+/// ... )
+/// {'set': ['services.nginx.enable'], 'default': ['services.nginx.package'], 'unknown': ['services.foo']}
+/// ```
+#[pyfunction]
+pub fn coverage(
+    py: Python<'_>,
+    entry: String,
+    options_schema: Bound<'_, PyDict>,
+) -> PyResult<PyObject> {
+    let path = PathBuf::from(entry);
+    let content = read_file(&path)?;
+    let evaluator = Evaluator {
+        lazy: true,
+        ..Default::default()
+    };
+    let (value, context) =
+        evaluator.eval_expr(&content, Some(path), None, None, None)?;
+
+    let mut coverage = Coverage::default();
+    match context.whnf(value)? {
+        TvixValue::Attrs(attrs) => coverage.walk(
+            &context,
+            &options_schema,
+            Some(&attrs),
+            &mut Vec::new(),
+        )?,
+        value => Err(coded_err::<EvaluationError>(
+            ErrorCode::NixEvaluation,
+            format!(
+                "The config evaluates to a {} instead of an attrset",
+                value.type_of()
+            ),
+        ))?,
+    }
+    context.emit_traces(py)?;
+
+    let report = PyDict::new(py);
+    for (key, paths) in [
+        ("set", coverage.set),
+        ("default", coverage.default),
+        ("unknown", coverage.unknown),
+    ] {
+        let paths = paths
+            .iter()
+            .map(|p| PyString::new(py, p))
+            .collect::<Vec<_>>();
+        report.set_item(key, paths)?;
+    }
+    Ok(report.into_any().unbind())
+}
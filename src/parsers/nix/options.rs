@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Expr, HasEntry};
+
+use super::ast::{attrpath_names, function_name, Lines};
+use super::parse_root;
+use crate::parsers::schema::attrpath;
+use crate::parsers::utils::read_file;
+
+/// The fields of a `mkOption` declaration, as the source text of their
+/// expression except for literal descriptions
+#[derive(Default)]
+struct Declaration {
+    kind: Option<String>,
+    default: Option<String>,
+    example: Option<String>,
+    description: Option<String>,
+}
+
+/// The text of a description, unwrapping the `mdDoc` and `literalMD` calls
+/// marking it as markdown, as the string itself if it is not interpolated
+fn description(expr: Expr) -> String {
+    match &expr {
+        Expr::Apply(apply)
+            if matches!(
+                function_name(apply).as_deref(),
+                Some("mdDoc" | "literalMD")
+            ) =>
+        {
+            if let Some(argument) = apply.argument() {
+                return description(argument);
+            }
+        }
+        Expr::Paren(paren) => {
+            if let Some(inner) = paren.expr() {
+                return description(inner);
+            }
+        }
+        Expr::Str(s) => match s.normalized_parts().as_slice() {
+            [] => return String::new(),
+            [ast::InterpolPart::Literal(s)] => return s.clone(),
+            _ => {}
+        },
+        _ => {}
+    }
+    expr.syntax().text().to_string()
+}
+
+impl Declaration {
+    /// Read the fields of the attrset given to `mkOption`
+    fn of_mk_option(argument: Option<Expr>) -> Self {
+        let mut declaration = Declaration::default();
+        let mut argument = argument;
+        while let Some(Expr::Paren(paren)) = &argument {
+            argument = paren.expr();
+        }
+        let Some(Expr::AttrSet(set)) = argument else {
+            return declaration;
+        };
+        for binding in set.attrpath_values() {
+            let names = attrpath_names(binding.attrpath());
+            let (Some([name]), Some(value)) =
+                (names.as_deref(), binding.value())
+            else {
+                continue;
+            };
+            let text = value.syntax().text().to_string();
+            match name.as_str() {
+                "type" => declaration.kind = Some(text),
+                "default" => declaration.default = Some(text),
+                "example" => declaration.example = Some(text),
+                "description" => {
+                    declaration.description = Some(description(value))
+                }
+                _ => {}
+            }
+        }
+        declaration
+    }
+
+    /// The declaration `mkEnableOption` makes for its name, like nixpkgs
+    fn of_mk_enable_option(argument: Option<Expr>) -> Self {
+        let name = argument.map(description).unwrap_or_default();
+        Declaration {
+            kind: Some("types.bool".to_string()),
+            default: Some("false".to_string()),
+            example: Some("true".to_string()),
+            description: Some(format!("Whether to enable {}.", name)),
+        }
+    }
+}
+
+/// Collects the options declared by a module
+struct Options<'py> {
+    py: Python<'py>,
+    lines: Lines,
+    found: Vec<PyObject>,
+}
+
+impl Options<'_> {
+    /// Find the `options` of the attrset a module evaluates to
+    fn module(&mut self, expr: Option<Expr>) -> PyResult<()> {
+        match expr {
+            Some(Expr::AttrSet(set)) => {
+                for binding in set.attrpath_values() {
+                    // Interpolated names are only known once evaluated
+                    let Some(names) = attrpath_names(binding.attrpath()) else {
+                        continue;
+                    };
+                    if names.first().map(String::as_str) == Some("options") {
+                        self.options(
+                            binding.value(),
+                            &mut names[1..].to_vec(),
+                        )?;
+                    }
+                }
+            }
+            Some(Expr::Lambda(lambda)) => self.module(lambda.body())?,
+            Some(Expr::LetIn(let_in)) => self.module(let_in.body())?,
+            Some(Expr::With(with)) => self.module(with.body())?,
+            Some(Expr::Paren(paren)) => self.module(paren.expr())?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Collect the options declared in an attrset of options, nested `path`
+    /// deep
+    fn options(
+        &mut self,
+        expr: Option<Expr>,
+        path: &mut Vec<String>,
+    ) -> PyResult<()> {
+        match expr {
+            Some(Expr::AttrSet(set)) => {
+                for binding in set.attrpath_values() {
+                    let Some(names) = attrpath_names(binding.attrpath()) else {
+                        continue;
+                    };
+                    let depth = path.len();
+                    path.extend(names);
+                    self.options(binding.value(), path)?;
+                    path.truncate(depth);
+                }
+            }
+            Some(Expr::Apply(apply)) => {
+                let declaration = match function_name(&apply).as_deref() {
+                    Some("mkOption") => {
+                        Declaration::of_mk_option(apply.argument())
+                    }
+                    Some("mkEnableOption") => {
+                        Declaration::of_mk_enable_option(apply.argument())
+                    }
+                    _ => return Ok(()),
+                };
+                self.declare(path, &apply, declaration)?;
+            }
+            Some(Expr::LetIn(let_in)) => self.options(let_in.body(), path)?,
+            Some(Expr::With(with)) => self.options(with.body(), path)?,
+            Some(Expr::Paren(paren)) => self.options(paren.expr(), path)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn declare(
+        &mut self,
+        path: &[String],
+        apply: &ast::Apply,
+        declaration: Declaration,
+    ) -> PyResult<()> {
+        let start = usize::from(apply.syntax().text_range().start());
+        let option = PyDict::new(self.py);
+        option.set_item("path", attrpath(path))?;
+        option.set_item("type", declaration.kind)?;
+        option.set_item("default", declaration.default)?;
+        option.set_item("example", declaration.example)?;
+        option.set_item("description", declaration.description)?;
+        option.set_item("line", self.lines.of(start))?;
+        self.found.push(option.into_any().unbind());
+        Ok(())
+    }
+}
+
+/// Extract the options a nix module declares without evaluating it, to
+/// document a set of modules.
+///
+/// Options are found under the `options` attribute of the attrset the module
+/// is made of, through functions, `let` and `with`, where they are declared
+/// by `mkOption` or `mkEnableOption`, with or without `lib.`. Their `type`,
+/// `default` and `example` are given as the source text of their
+/// expression, as nothing is evaluated, and their `description` as the
+/// string it is when it is a string without interpolation, unwrapping
+/// `mdDoc` and `literalMD`. `mkEnableOption` options are described like
+/// nixpkgs declares them. Options declared by other functions, or nested in
+/// the `options` of a submodule type, are not extracted.
+///
+/// Args:
+///   - path (str): The path to the nix module.
+///
+/// Returns:
+///   - list[_OptionDeclaration]: The options in the order of the source, with
+///                               their attribute `path` under `options`,
+///                               their `type`, `default`, `example` and
+///                               `description`, `None` when not declared, and
+///                               the `line` of their declaration.
+///
+/// Raises:
+///   - IOError: If the file cannot be read.
+///   - ParseError: If the nix file cannot be parsed.
+///
+/// Example:
+/// ```python
+/// # `module.nix` contains:
+/// # ```
+/// # { lib, ... }: {
+/// #   options.services.app = {
+/// #     enable = lib.mkEnableOption "the app";
+/// #     port = lib.mkOption {
+/// #       type = lib.types.port;
+/// #       default = 8080;
+/// #       description = "The port the app listens on.";
+/// #     };
+/// #   };
+/// # }
+/// # ```
+/// >>> options = extract_options("module.nix")
+/// >>> [(o["path"], o["type"], o["default"]) for o in options]
+/// [('services.app.enable', 'types.bool', 'false'),
+///  ('services.app.port', 'lib.types.port', '8080')]
+/// >>> options[1]["description"]
+/// 'The port the app listens on.'
+/// ```
+#[pyfunction]
+pub fn extract_options(
+    py: Python<'_>,
+    path: String,
+) -> PyResult<Vec<PyObject>> {
+    let path = PathBuf::from(path);
+    let content = read_file(&path)?;
+    let root = parse_root(&content, Some(&path.to_string_lossy()))?;
+    let mut options = Options {
+        py,
+        lines: Lines::new(&content),
+        found: Vec::new(),
+    };
+    options.module(root.expr())?;
+    Ok(options.found)
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use rnix::ast::{self, BinOpKind, Expr, HasEntry};
+use rnix::Root;
+
+use super::ast::{attr_name, attrpath_names};
+
+/// The order attributes are defined in by the source of an evaluation, to
+/// convert attrsets in that order instead of the sorted order of nix
+#[derive(Default)]
+pub(super) struct KeyOrder {
+    keys: Vec<String>,
+    children: HashMap<String, KeyOrder>,
+}
+
+impl KeyOrder {
+    /// Find the order of the attrsets an expression evaluates to, looking
+    /// through functions, `let`, `with`, `if` and `//`. Attributes computed
+    /// or defined in other files are not found.
+    pub fn of_source(expr: &str) -> Self {
+        let mut order = KeyOrder::default();
+        // The source was evaluated already, it parses
+        order.collect(Root::parse(expr).tree().expr());
+        order
+    }
+
+    /// The order of the attrset at `path`, if it is found in the source
+    pub fn at(&self, path: &[String]) -> Option<&Self> {
+        path.iter()
+            .try_fold(self, |order, name| order.children.get(name))
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Self> {
+        self.children.get(name)
+    }
+
+    /// Where a key is defined among the keys of the attrset, `None` if it is
+    /// not found in the source
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.keys.iter().position(|k| k == name)
+    }
+
+    fn entry(&mut self, name: String) -> &mut Self {
+        if !self.children.contains_key(&name) {
+            self.keys.push(name.clone());
+        }
+        self.children.entry(name).or_default()
+    }
+
+    fn collect(&mut self, expr: Option<Expr>) {
+        match expr {
+            Some(Expr::AttrSet(set)) => {
+                for entry in set.entries() {
+                    match entry {
+                        ast::Entry::AttrpathValue(binding) => {
+                            let Some(names) =
+                                attrpath_names(binding.attrpath())
+                            else {
+                                continue;
+                            };
+                            let order = names
+                                .into_iter()
+                                .fold(&mut *self, |order, name| {
+                                    order.entry(name)
+                                });
+                            order.collect(binding.value());
+                        }
+                        ast::Entry::Inherit(inherit) => {
+                            for attr in inherit.attrs() {
+                                if let Some(name) = attr_name(&attr) {
+                                    self.entry(name);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Some(Expr::BinOp(op))
+                if op.operator() == Some(BinOpKind::Update) =>
+            {
+                self.collect(op.lhs());
+                self.collect(op.rhs());
+            }
+            Some(Expr::IfElse(if_else)) => {
+                self.collect(if_else.body());
+                self.collect(if_else.else_body());
+            }
+            Some(Expr::Lambda(lambda)) => self.collect(lambda.body()),
+            Some(Expr::LetIn(let_in)) => self.collect(let_in.body()),
+            Some(Expr::With(with)) => self.collect(with.body()),
+            Some(Expr::Paren(paren)) => self.collect(paren.expr()),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Expr};
+
+use super::ast::{lookup, Lines};
+use super::parse_root;
+use super::renaming::split;
+
+/// The name bound by a parameter, like the `pkgs` of `pkgs: ...`
+fn ident_name(ident: Option<ast::Ident>) -> Option<String> {
+    ident?.ident_token().map(|t| t.to_string())
+}
+
+/// Describe the parameters of a function
+fn describe(
+    py: Python<'_>,
+    lambda: &ast::Lambda,
+    lines: &Lines,
+) -> PyResult<PyObject> {
+    let described = PyDict::new(py);
+    match lambda.param() {
+        Some(ast::Param::Pattern(pattern)) => {
+            let mut formals = Vec::new();
+            for entry in pattern.pat_entries() {
+                let start = usize::from(entry.syntax().text_range().start());
+                let formal = PyDict::new(py);
+                formal.set_item("name", ident_name(entry.ident()))?;
+                formal.set_item(
+                    "default",
+                    entry.default().map(|d| d.syntax().text().to_string()),
+                )?;
+                formal.set_item("line", lines.of(start))?;
+                formals.push(formal);
+            }
+            let bind = pattern.pat_bind().and_then(|b| ident_name(b.ident()));
+            described.set_item("name", bind)?;
+            described.set_item("formals", formals)?;
+            described
+                .set_item("ellipsis", pattern.ellipsis_token().is_some())?;
+        }
+        Some(ast::Param::IdentParam(param)) => {
+            described.set_item("name", ident_name(param.ident()))?;
+            described.set_item("formals", py.None())?;
+            described.set_item("ellipsis", false)?;
+        }
+        None => {
+            described.set_item("name", py.None())?;
+            described.set_item("formals", py.None())?;
+            described.set_item("ellipsis", false)?;
+        }
+    }
+    Ok(described.into_any().unbind())
+}
+
+/// Get the parameters of a nix function without evaluating it, to document
+/// module-style functions like `{ pkgs, lib, extra ? null, ... }: ...`.
+///
+/// Defaults are given as the source text of their expression, as nothing is
+/// evaluated.
+///
+/// Args:
+///   - content (str): The nix expression.
+///   - attr_path (str | None): The attribute bound to the function, looked
+///                             up like `NixAst.get`, the whole expression if
+///                             `None`. The last binding wins if the
+///                             attribute is defined several times.
+///
+/// Returns:
+///   - _Formals: The `name` the argument is bound to, like `args` of
+///               `args@{ ... }` or `x` of `x: ...`, the `formals` of the
+///               attrset pattern with their `name`, `default` and `line`, or
+///               `None` for a plain argument, and whether the pattern has an
+///               `ellipsis`.
+///
+/// Raises:
+///   - ParseError: If the nix code cannot be parsed.
+///   - ValueError: If the attribute path is invalid or not bound, or its
+///                 value is not a function.
+///
+/// Example:
+/// ```python
+/// >>> formals("{ pkgs, lib, extra ? null, ... }: { }")
+/// {'name': None, 'formals': [{'name': 'pkgs', 'default': None, 'line': 1}, {'name': 'lib', 'default': None, 'line': 1}, {'name': 'extra', 'default': 'null', 'line': 1}], 'ellipsis': True}
+/// >>> formals("{ lib }: { mk = name: { inherit name; }; }", "mk")
+/// {'name': 'name', 'formals': None, 'ellipsis': False}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (content, attr_path = None))]
+pub fn formals(
+    py: Python<'_>,
+    content: &str,
+    attr_path: Option<&str>,
+) -> PyResult<PyObject> {
+    let root = parse_root(content, None)?;
+    let path = attr_path.map(|p| split(p, "function")).transpose()?;
+    let mut found = Vec::new();
+    lookup(root.expr(), path.as_deref().unwrap_or_default(), &mut found);
+    let mut function = found.pop().ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "Attribute `{}` is not bound",
+            attr_path.unwrap_or_default()
+        ))
+    })?;
+    while let Expr::Paren(paren) = &function {
+        let Some(inner) = paren.expr() else {
+            break;
+        };
+        function = inner;
+    }
+    match &function {
+        Expr::Lambda(lambda) => describe(py, lambda, &Lines::new(content)),
+        other => Err(PyValueError::new_err(format!(
+            "{} is a {:?} instead of a function",
+            match attr_path {
+                Some(path) => format!("`{}`", path),
+                None => "The expression".to_string(),
+            },
+            other.syntax().kind()
+        ))),
+    }
+}
@@ -0,0 +1,176 @@
+use std::rc::Rc;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{AstNode, Expr, HasEntry};
+use rnix::TextRange;
+use tvix_eval::Value as TvixValue;
+
+use super::ast::attr_name;
+use super::sources::{SourceFile, Sources};
+use super::writers::evaluated;
+use crate::parsers::schema::attrpath;
+
+/// The range of the attribute name defining `path` in an expression and the
+/// file it is in, where `unsafeGetAttrPos` locates it, found in the syntax
+/// like `lookup` and through the files imported with a path literal
+fn attr_position(
+    sources: &Sources,
+    file: &Rc<SourceFile>,
+    expr: Option<Expr>,
+    path: &[String],
+) -> Option<(Rc<SourceFile>, TextRange)> {
+    match expr? {
+        Expr::AttrSet(set) => {
+            for binding in set.attrpath_values() {
+                let Some(attrs) = binding.attrpath() else {
+                    continue;
+                };
+                let attrs = attrs.attrs().collect::<Vec<_>>();
+                let Some(names) =
+                    attrs.iter().map(attr_name).collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                let matched =
+                    names.iter().zip(path).take_while(|(a, b)| a == b).count();
+                if matched == 0 {
+                    continue;
+                }
+                // The path may end within the attrpath, like `a` in
+                // `a.b = 1;`
+                if matched == path.len() {
+                    let range = attrs[matched - 1].syntax().text_range();
+                    return Some((Rc::clone(file), range));
+                }
+                if matched == names.len() {
+                    let found = attr_position(
+                        sources,
+                        file,
+                        binding.value(),
+                        &path[matched..],
+                    );
+                    if found.is_some() {
+                        return found;
+                    }
+                }
+            }
+            let [name] = path else {
+                return None;
+            };
+            set.inherits()
+                .flat_map(|inherit| inherit.attrs())
+                .find(|attr| attr_name(attr).as_ref() == Some(name))
+                .map(|attr| (Rc::clone(file), attr.syntax().text_range()))
+        }
+        // The attributes of an imported file are defined in the file
+        Expr::Apply(apply) => {
+            let imported = sources.imported(file, &apply)?;
+            attr_position(sources, &imported, imported.root.clone(), path)
+        }
+        Expr::Lambda(lambda) => {
+            attr_position(sources, file, lambda.body(), path)
+        }
+        Expr::LetIn(let_in) => {
+            attr_position(sources, file, let_in.body(), path)
+        }
+        Expr::With(with) => attr_position(sources, file, with.body(), path),
+        Expr::Paren(paren) => attr_position(sources, file, paren.expr(), path),
+        _ => None,
+    }
+}
+
+/// Collects the positions of the attributes of an evaluated value
+struct Positions<'py, 'a> {
+    py: Python<'py>,
+    sources: &'a Sources,
+    found: Bound<'py, PyDict>,
+}
+
+impl Positions<'_, '_> {
+    fn walk(&self, value: &TvixValue, path: &mut Vec<String>) -> PyResult<()> {
+        let TvixValue::Attrs(attrs) = evaluated(value) else {
+            return Ok(());
+        };
+        let main = &self.sources.main;
+        for (name, value) in attrs.iter() {
+            path.push(String::from_utf8_lossy(name.as_bytes()).into_owned());
+            // Attributes nested in one that is not defined in the files are
+            // not either
+            if let Some((file, range)) =
+                attr_position(self.sources, main, main.root.clone(), path)
+            {
+                let start = usize::from(range.start());
+                let position = PyDict::new(self.py);
+                position.set_item("file", &file.name)?;
+                position.set_item("line", file.lines.of(start))?;
+                position.set_item("column", file.lines.column(start))?;
+                self.found.set_item(attrpath(path), position)?;
+                self.walk(value, path)?;
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+/// The positions of the definitions of the attributes of an evaluated
+/// value, by attribute path, for those defined in the files of `sources`
+pub(super) fn positions(
+    py: Python<'_>,
+    value: &TvixValue,
+    sources: &Sources,
+) -> PyResult<PyObject> {
+    let positions = Positions {
+        py,
+        sources,
+        found: PyDict::new(py),
+    };
+    positions.walk(value, &mut Vec::new())?;
+    Ok(positions.found.into_any().unbind())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::parsers::nix::evaluator::Evaluator;
+    use crate::parsers::utils::FileSource;
+
+    #[test]
+    fn attributes_of_imported_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("cosutils-positions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let imported = "{ lib }: {\n  hostName = \"web\";\n}\n";
+        fs::write(dir.join("networking.nix"), imported).unwrap();
+        let main = dir.join("main.nix");
+        let content =
+            "{\n  networking = import ./networking.nix { lib = 1; };\n}\n";
+        fs::write(&main, content).unwrap();
+        let found = Python::with_gil(|py| {
+            let evaluated = Evaluator::default()
+                .eval_with_positions(py, FileSource::Path(main.clone()))?;
+            let (_, positions): (PyObject, Bound<'_, PyDict>) =
+                evaluated.extract(py)?;
+            let position = |path: &str| {
+                let position = positions.get_item(path)?.unwrap();
+                let file: String = position.get_item("file")?.extract()?;
+                let line: usize = position.get_item("line")?.extract()?;
+                let column: usize = position.get_item("column")?.extract()?;
+                PyResult::Ok((file, line, column))
+            };
+            PyResult::Ok((
+                position("networking")?,
+                position("networking.hostName")?,
+            ))
+        });
+        fs::remove_dir_all(&dir).unwrap();
+        let (networking, host_name) = found.unwrap();
+        assert_eq!(networking, (main.display().to_string(), 2, 3));
+        assert!(host_name.0.ends_with("networking.nix"));
+        assert_eq!((host_name.1, host_name.2), (2, 3));
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use codemap::Span;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use tvix_eval::generators::VMRequest;
+use tvix_eval::observer::RuntimeObserver;
+use tvix_eval::{CodeIdx, Lambda, Op, SourceCode, Value as TvixValue};
+
+/// Number of builtins and of expressions reported by a profile, the slowest
+/// first
+const HOTTEST: usize = 20;
+
+/// What an evaluation spent its time in
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Slot {
+    /// The bytecode of nix functions and thunks
+    Nix,
+    Builtin(&'static str),
+}
+
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    /// The time spent in the slot itself, without what it called
+    time: Duration,
+}
+
+/// What the nix code compiled from an expression cost
+#[derive(Default)]
+struct SpanStats {
+    /// The times the thunks starting with the expression were forced
+    forces: u64,
+    /// The times the functions whose body starts with it were called
+    calls: u64,
+    /// The time spent running the instructions of the expression itself
+    time: Duration,
+}
+
+/// Where the evaluations of a context spent their time, in nix code as a
+/// whole and by expression, and in each builtin
+#[derive(Default)]
+pub(super) struct Profile {
+    time: Duration,
+    stats: HashMap<Slot, Stats>,
+    spans: HashMap<Span, SpanStats>,
+    max_depth: usize,
+    /// The slots entered and not left yet, the last one running since the
+    /// instant of the last event
+    running: Vec<Slot>,
+    since: Option<Instant>,
+    /// The lambdas of the running call frames with their depth, the
+    /// innermost last
+    frames: Vec<(Rc<Lambda>, usize)>,
+    /// The call frames suspended by a call, to tell a frame resuming from a
+    /// frame entered anew
+    suspended: Vec<(Rc<Lambda>, usize)>,
+    /// The expression of the instruction running, if nix code is
+    op: Option<Span>,
+}
+
+impl Profile {
+    /// Charge the time since the last event to the running slot
+    fn charge(&mut self) {
+        let now = Instant::now();
+        if let (Some(since), Some(slot)) = (self.since, self.running.last()) {
+            self.stats.entry(*slot).or_default().time += now - since;
+            if let (Slot::Nix, Some(span)) = (slot, self.op) {
+                self.spans.entry(span).or_default().time += now - since;
+            }
+        }
+        self.since = Some(now);
+    }
+
+    fn enter(&mut self, slot: Slot) {
+        self.charge();
+        // A builtin guarded by the limits is called by its guard, which has
+        // the same name
+        if matches!(slot, Slot::Nix) || self.running.last() != Some(&slot) {
+            self.stats.entry(slot).or_default().calls += 1;
+        }
+        self.running.push(slot);
+        self.op = None;
+    }
+
+    fn leave(&mut self) {
+        self.charge();
+        self.running.pop();
+        self.op = None;
+    }
+
+    /// Enter the call frame of `lambda`, counting a force or a call of its
+    /// first expression unless the frame resumes after a call
+    fn enter_frame(
+        &mut self,
+        arg_count: usize,
+        lambda: &Rc<Lambda>,
+        depth: usize,
+    ) {
+        self.enter(Slot::Nix);
+        self.max_depth = self.max_depth.max(depth);
+        let resumed = self
+            .suspended
+            .last()
+            .is_some_and(|(l, d)| Rc::ptr_eq(l, lambda) && *d == depth);
+        if resumed {
+            self.suspended.pop();
+        } else {
+            self.count(arg_count, lambda);
+        }
+        self.frames.push((Rc::clone(lambda), depth));
+    }
+
+    /// Leave the running call frame, to resume it later if `suspended`
+    fn leave_frame(&mut self, suspended: bool) {
+        self.leave();
+        let frame = self.frames.pop();
+        if suspended {
+            self.suspended.extend(frame);
+        }
+    }
+
+    /// Count a force of `lambda` if it is a thunk, taking no argument, or
+    /// else a call
+    fn count(&mut self, arg_count: usize, lambda: &Lambda) {
+        let span = lambda.chunk.get_span(CodeIdx(0));
+        let stats = self.spans.entry(span).or_default();
+        if arg_count == 0 {
+            stats.forces += 1;
+        } else {
+            stats.calls += 1;
+        }
+    }
+
+    /// Charge the time to the expression of the instruction at `ip` of the
+    /// running frame from now on
+    fn execute(&mut self, ip: CodeIdx) {
+        let Some((lambda, _)) = self.frames.last() else {
+            return;
+        };
+        let span = lambda.chunk.get_span(ip);
+        if self.op != Some(span) {
+            self.charge();
+            self.op = Some(span);
+        }
+    }
+
+    /// Run an evaluation measured in this profile, if any, forwarding the
+    /// events to the `observer` of its limits
+    pub fn run<T>(
+        profile: Option<&mut Profile>,
+        observer: Option<&mut dyn RuntimeObserver>,
+        evaluate: impl FnOnce(Option<&mut dyn RuntimeObserver>) -> T,
+    ) -> T {
+        let Some(profile) = profile else {
+            return evaluate(observer);
+        };
+        // Frames left by an evaluation aborted by its limits never resume
+        profile.running.clear();
+        profile.frames.clear();
+        profile.suspended.clear();
+        profile.op = None;
+        profile.since = None;
+        let started = Instant::now();
+        let mut profiler = Profiler {
+            profile,
+            inner: observer,
+        };
+        let result = evaluate(Some(&mut profiler));
+        let profile = profiler.profile;
+        profile.charge();
+        profile.time += started.elapsed();
+        result
+    }
+}
+
+impl Profile {
+    /// Convert the profile to a dict, locating its expressions in `source`
+    pub fn to_pyobject(
+        &self,
+        py: Python<'_>,
+        source: &SourceCode,
+    ) -> PyResult<PyObject> {
+        let mut builtins = self
+            .stats
+            .iter()
+            .filter_map(|(slot, stats)| match slot {
+                Slot::Builtin(name) => Some((*name, stats)),
+                Slot::Nix => None,
+            })
+            .collect::<Vec<_>>();
+        builtins.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+        let builtins = builtins
+            .into_iter()
+            .take(HOTTEST)
+            .map(|(name, stats)| {
+                let builtin = PyDict::new(py);
+                builtin.set_item("name", name)?;
+                builtin.set_item("calls", stats.calls)?;
+                builtin.set_item("time", stats.time.as_secs_f64())?;
+                Ok(builtin)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let mut spans = self.spans.iter().collect::<Vec<_>>();
+        spans.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+        let codemap = source.codemap();
+        let expressions = spans
+            .into_iter()
+            .take(HOTTEST)
+            .map(|(span, stats)| {
+                let location = codemap.look_up_span(*span);
+                let text = location.file.source_slice(*span);
+                let expression = PyDict::new(py);
+                expression.set_item("file", location.file.name())?;
+                expression.set_item("line", location.begin.line + 1)?;
+                expression.set_item("column", location.begin.column + 1)?;
+                expression
+                    .set_item("text", text.lines().next().unwrap_or(""))?;
+                expression.set_item("forces", stats.forces)?;
+                expression.set_item("calls", stats.calls)?;
+                expression.set_item("time", stats.time.as_secs_f64())?;
+                Ok(expression)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let nix = self.stats.get(&Slot::Nix);
+        let profile = PyDict::new(py);
+        profile.set_item("time", self.time.as_secs_f64())?;
+        profile
+            .set_item("nix_time", nix.map_or(0.0, |s| s.time.as_secs_f64()))?;
+        profile.set_item("frames", nix.map_or(0, |s| s.calls))?;
+        profile.set_item("max_depth", self.max_depth)?;
+        profile.set_item("builtins", builtins)?;
+        profile.set_item("expressions", expressions)?;
+        Ok(profile.into_any().unbind())
+    }
+}
+
+/// Observes an evaluation to fill a `Profile`
+struct Profiler<'a> {
+    profile: &'a mut Profile,
+    /// The observer enforcing the limits of the evaluation
+    inner: Option<&'a mut dyn RuntimeObserver>,
+}
+
+impl RuntimeObserver for Profiler<'_> {
+    fn observe_enter_call_frame(
+        &mut self,
+        arg_count: usize,
+        lambda: &Rc<Lambda>,
+        call_depth: usize,
+    ) {
+        self.profile.enter_frame(arg_count, lambda, call_depth);
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_enter_call_frame(arg_count, lambda, call_depth);
+        }
+    }
+
+    fn observe_exit_call_frame(
+        &mut self,
+        frame_at: usize,
+        stack: &[TvixValue],
+    ) {
+        self.profile.leave_frame(false);
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_exit_call_frame(frame_at, stack);
+        }
+    }
+
+    fn observe_suspend_call_frame(
+        &mut self,
+        frame_at: usize,
+        stack: &[TvixValue],
+    ) {
+        self.profile.leave_frame(true);
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_suspend_call_frame(frame_at, stack);
+        }
+    }
+
+    fn observe_tvix_tail_call(&mut self, frame_at: usize, lambda: &Rc<Lambda>) {
+        // The running frame goes on with the called function
+        self.profile.count(1, lambda);
+        if let Some((running, _)) = self.profile.frames.last_mut() {
+            *running = Rc::clone(lambda);
+        }
+        self.profile.op = None;
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_tvix_tail_call(frame_at, lambda);
+        }
+    }
+
+    fn observe_enter_builtin(&mut self, name: &'static str) {
+        self.profile.enter(Slot::Builtin(name));
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_enter_builtin(name);
+        }
+    }
+
+    fn observe_exit_builtin(
+        &mut self,
+        name: &'static str,
+        stack: &[TvixValue],
+    ) {
+        self.profile.leave();
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_exit_builtin(name, stack);
+        }
+    }
+
+    fn observe_execute_op(
+        &mut self,
+        ip: CodeIdx,
+        op: &Op,
+        stack: &[TvixValue],
+    ) {
+        self.profile.execute(ip);
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_execute_op(ip, op, stack);
+        }
+    }
+
+    fn observe_generator_request(&mut self, name: &str, msg: &VMRequest) {
+        if let Some(inner) = self.inner.as_deref_mut() {
+            inner.observe_generator_request(name, msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::{PyList, PyTuple};
+
+    use super::*;
+    use crate::parsers::nix::evaluator::Evaluator;
+
+    #[test]
+    fn expressions_are_located() {
+        let evaluator = Evaluator {
+            profile: true,
+            ..Default::default()
+        };
+        let expr = "let\n  f = n: if n == 0 then 0 else f (n - 1);\nin f 100";
+        Python::with_gil(|py| {
+            let evaluated = evaluator
+                .evals(py, expr.to_string(), None, None, None, None, false)
+                .unwrap();
+            let evaluated = evaluated.bind(py).downcast::<PyTuple>().unwrap();
+            let value: i64 = evaluated.get_item(0).unwrap().extract().unwrap();
+            assert_eq!(value, 0);
+            let profile = evaluated.get_item(1).unwrap();
+            let expressions = profile.get_item("expressions").unwrap();
+            let called = expressions
+                .downcast::<PyList>()
+                .unwrap()
+                .iter()
+                .map(|e| {
+                    let field = |name| e.get_item(name).unwrap();
+                    let calls: u64 = field("calls").extract().unwrap();
+                    let line: usize = field("line").extract().unwrap();
+                    (calls, line)
+                })
+                .max()
+                .unwrap();
+            // The body of `f`, on the second line, ran for each `n`
+            assert_eq!(called, (101, 2));
+        });
+    }
+}
@@ -0,0 +1,159 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use pyo3::exceptions::{
+    PyFileNotFoundError, PyNotADirectoryError, PyPermissionError, PyTypeError,
+};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use pyo3::{PyObject, PyResult};
+use tvix_eval::{EvalIO, FileType};
+
+/// The methods a Python object must have to serve the files of evaluations
+const METHODS: &[&str] = &["read_file", "path_exists", "read_dir"];
+
+/// Convert an exception raised by a method of the IO object to the error
+/// tvix reports, keeping the kinds it tells apart
+fn io_error(py: Python<'_>, err: PyErr) -> io::Error {
+    let kind = if err.is_instance_of::<PyFileNotFoundError>(py) {
+        io::ErrorKind::NotFound
+    } else if err.is_instance_of::<PyPermissionError>(py) {
+        io::ErrorKind::PermissionDenied
+    } else if err.is_instance_of::<PyNotADirectoryError>(py) {
+        io::ErrorKind::NotADirectory
+    } else {
+        io::ErrorKind::Other
+    };
+    io::Error::new(kind, err.to_string())
+}
+
+/// The kind of a file, named like the values of `builtins.readDir`
+fn file_type(kind: &str) -> io::Result<FileType> {
+    match kind {
+        "regular" => Ok(FileType::Regular),
+        "directory" => Ok(FileType::Directory),
+        "symlink" => Ok(FileType::Symlink),
+        "unknown" => Ok(FileType::Unknown),
+        kind => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Unknown file type `{}`, expected `regular`, `directory`, \
+                `symlink` or `unknown`",
+                kind
+            ),
+        )),
+    }
+}
+
+/// Serves the files of evaluations from a Python object instead of the file
+/// system, like a database or an in-memory file system
+#[derive(Clone)]
+pub(super) struct PyIO {
+    io: Arc<PyObject>,
+}
+
+impl PyIO {
+    pub fn new(io: &Bound<'_, PyAny>) -> PyResult<Self> {
+        for method in METHODS {
+            let callable = io.getattr(*method).is_ok_and(|m| m.is_callable());
+            if !callable {
+                return Err(PyTypeError::new_err(format!(
+                    "The io must have a callable `{}` method, {} has none",
+                    method,
+                    io.get_type()
+                )));
+            }
+        }
+        Ok(Self {
+            io: Arc::new(io.clone().unbind()),
+        })
+    }
+
+    /// The Python object serving the files
+    pub fn object<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.io.bind(py)
+    }
+
+    /// Call a method of the IO object with `path`, and extract its result
+    fn call<T>(
+        &self,
+        method: &str,
+        path: &Path,
+        extract: impl FnOnce(&Bound<'_, PyAny>) -> PyResult<T>,
+    ) -> io::Result<T> {
+        Python::with_gil(|py| {
+            self.io
+                .call_method1(py, method, (path.to_string_lossy(),))
+                .and_then(|result| extract(result.bind(py)))
+                .map_err(|e| io_error(py, e))
+        })
+    }
+
+    /// Read a file, given as `bytes` or as `str`
+    pub fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.call("read_file", path, |content| {
+            match content.downcast::<PyBytes>() {
+                Ok(bytes) => Ok(bytes.as_bytes().to_vec()),
+                Err(_) => Ok(content.extract::<String>()?.into_bytes()),
+            }
+        })
+    }
+}
+
+impl EvalIO for PyIO {
+    fn path_exists(&self, path: &Path) -> io::Result<bool> {
+        self.call("path_exists", path, |exists| exists.extract::<bool>())
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        Ok(Box::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<FileType> {
+        let has_file_type = Python::with_gil(|py| {
+            self.io.bind(py).hasattr("file_type").unwrap_or(false)
+        });
+        if has_file_type {
+            let kind =
+                self.call("file_type", path, |kind| kind.extract::<String>())?;
+            return file_type(&kind);
+        }
+
+        // Without `file_type`, whatever can be listed is a directory
+        if !self.path_exists(path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            ));
+        }
+        Ok(match self.read_dir(path) {
+            Ok(_) => FileType::Directory,
+            Err(_) => FileType::Regular,
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(Bytes, FileType)>> {
+        let entries = self.call("read_dir", path, |entries| {
+            entries
+                .call_method0("items")?
+                .try_iter()?
+                .map(|entry| entry?.extract::<(String, String)>())
+                .collect::<PyResult<Vec<_>>>()
+        })?;
+        entries
+            .into_iter()
+            .map(|(name, kind)| Ok((Bytes::from(name), file_type(&kind)?)))
+            .collect()
+    }
+
+    fn import_path(&self, path: &Path) -> io::Result<PathBuf> {
+        // There is no store to copy to, paths stay where the object has them
+        Ok(path.to_path_buf())
+    }
+
+    fn store_dir(&self) -> Option<String> {
+        None
+    }
+}
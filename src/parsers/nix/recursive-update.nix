@@ -0,0 +1,14 @@
+# Merge `rhs` into `lhs` like `lib.recursiveUpdate`: attrsets on both sides
+# are merged attribute by attribute, and any other value of `rhs` replaces the
+# one of `lhs`, which is only evaluated as deep as `rhs` goes into it
+let
+  update = lhs: rhs:
+    if builtins.isAttrs lhs && builtins.isAttrs rhs then
+      lhs
+      // builtins.mapAttrs
+        (name: value: if lhs ? ${name} then update lhs.${name} value else value)
+        rhs
+    else
+      rhs;
+in
+update
@@ -0,0 +1,356 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use rnix::ast::{self, AstNode, Attr, Expr, HasEntry};
+use rnix::Root;
+
+use super::ast::{attr_name, function_name};
+use super::complexity::imported;
+use super::parse_root;
+use crate::parsers::schema::attrpath;
+use crate::parsers::utils::read_file;
+
+/// A replacement of a byte range of a file
+struct Edit {
+    path: PathBuf,
+    start: usize,
+    end: usize,
+    line: usize,
+    old: String,
+    new: String,
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// The byte range of consecutive attributes of an attrpath
+fn span(attrs: &[Attr]) -> Option<Range<usize>> {
+    let (first, last) = (attrs.first()?, attrs.last()?);
+    Some(Range {
+        start: usize::from(first.syntax().text_range().start()),
+        end: usize::from(last.syntax().text_range().end()),
+    })
+}
+
+/// Whether the leading names of an attrpath are exactly `path`
+fn starts_with(names: &[Option<String>], path: &[String]) -> bool {
+    names.len() >= path.len()
+        && names
+            .iter()
+            .zip(path)
+            .all(|(name, segment)| name.as_ref() == Some(segment))
+}
+
+/// Whether an expression applies one of the `mk*` helpers of nixpkgs, like
+/// `mkIf cond { ... }` or `lib.mkMerge [ ... ]`, which keep the attributes
+/// their argument defines
+fn is_wrapper(apply: &ast::Apply) -> bool {
+    function_name(apply).is_some_and(|n| n.starts_with("mk"))
+}
+
+/// Finds the definitions and references of an attribute path in the files
+/// of an import graph
+struct Renamer<'a> {
+    from: &'a [String],
+    to: &'a [String],
+    edits: Vec<Edit>,
+}
+
+impl Renamer<'_> {
+    fn edit(
+        &mut self,
+        path: &Path,
+        content: &str,
+        range: Range<usize>,
+        new: String,
+    ) {
+        self.edits.push(Edit {
+            path: path.to_path_buf(),
+            start: range.start,
+            end: range.end,
+            line: line_of(content, range.start),
+            old: content[range].to_string(),
+            new,
+        });
+    }
+
+    /// Rename the definitions of an expression whose attributes are defined
+    /// at `depth` segments of the path deep
+    fn definitions(
+        &mut self,
+        path: &Path,
+        content: &str,
+        expr: Option<Expr>,
+        depth: usize,
+    ) -> PyResult<()> {
+        match expr {
+            Some(Expr::AttrSet(set)) => {
+                for binding in set.attrpath_values() {
+                    self.binding(path, content, &binding, depth)?;
+                }
+            }
+            Some(Expr::Lambda(lambda)) => {
+                self.definitions(path, content, lambda.body(), depth)?
+            }
+            Some(Expr::LetIn(let_in)) => {
+                self.definitions(path, content, let_in.body(), depth)?
+            }
+            Some(Expr::With(with)) => {
+                self.definitions(path, content, with.body(), depth)?
+            }
+            Some(Expr::Paren(paren)) => {
+                self.definitions(path, content, paren.expr(), depth)?
+            }
+            Some(Expr::IfElse(if_else)) => {
+                self.definitions(path, content, if_else.body(), depth)?;
+                self.definitions(path, content, if_else.else_body(), depth)?;
+            }
+            Some(Expr::List(list)) => {
+                for item in list.items() {
+                    self.definitions(path, content, Some(item), depth)?;
+                }
+            }
+            Some(Expr::Apply(apply)) if is_wrapper(&apply) => {
+                self.definitions(path, content, apply.argument(), depth)?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn binding(
+        &mut self,
+        path: &Path,
+        content: &str,
+        binding: &ast::AttrpathValue,
+        depth: usize,
+    ) -> PyResult<()> {
+        let attrs = binding
+            .attrpath()
+            .map(|p| p.attrs().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let rest = &self.from[depth..];
+        let matched = attrs
+            .iter()
+            .zip(rest)
+            .take_while(|(attr, name)| attr_name(attr).as_ref() == Some(name))
+            .count();
+
+        if matched == rest.len() {
+            // The enclosing attrsets stay as they are, so they must be
+            // common to both paths
+            if !self.to.starts_with(&self.from[..depth]) {
+                let start = binding.syntax().text_range().start();
+                return Err(PyValueError::new_err(format!(
+                    "Cannot rename the definition at {}:{} in place, it is \
+                    nested in `{}`",
+                    path.display(),
+                    line_of(content, usize::from(start)),
+                    attrpath(&self.from[..depth])
+                )));
+            }
+            if let Some(range) = span(&attrs[..matched]) {
+                let new = attrpath(&self.to[depth..]);
+                self.edit(path, content, range, new);
+            }
+        } else if matched > 0 && matched == attrs.len() {
+            self.definitions(path, content, binding.value(), depth + matched)?;
+        }
+        Ok(())
+    }
+
+    /// Rename the selections of the path, like `config.services.foo`
+    fn references(&mut self, path: &Path, content: &str, root: &Root) {
+        for node in root.syntax().descendants() {
+            let (base, attrpath_node) = if let Some(select) =
+                ast::Select::cast(node.clone())
+            {
+                (select.expr(), select.attrpath())
+            } else if let Some(has_attr) = ast::HasAttr::cast(node.clone()) {
+                (has_attr.expr(), has_attr.attrpath())
+            } else {
+                continue;
+            };
+            let attrs = attrpath_node
+                .map(|p| p.attrs().collect::<Vec<_>>())
+                .unwrap_or_default();
+            let names = attrs.iter().map(attr_name).collect::<Vec<_>>();
+            if starts_with(&names, self.from) {
+                if let Some(range) = span(&attrs[..self.from.len()]) {
+                    self.edit(path, content, range, attrpath(self.to));
+                }
+                continue;
+            }
+
+            // The selected variable may be the first segment of the path,
+            // single segments would rename the variable itself
+            let Some(Expr::Ident(ident)) = base.filter(|_| self.from.len() > 1)
+            else {
+                continue;
+            };
+            let mut full = vec![ident.ident_token().map(|t| t.to_string())];
+            full.extend(names);
+            if starts_with(&full, self.from) {
+                let range = ident.syntax().text_range();
+                let start = usize::from(range.start());
+                let end = span(&attrs[..self.from.len() - 1])
+                    .map_or(usize::from(range.end()), |r| r.end);
+                self.edit(path, content, start..end, attrpath(self.to));
+            }
+        }
+    }
+}
+
+/// The nix file an import refers to, `None` for search paths like
+/// `<nixpkgs>` and paths that do not exist
+fn resolve_import(file: &Path, import: &str) -> Option<PathBuf> {
+    if import.starts_with('<') || import.starts_with('~') {
+        return None;
+    }
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let path = dir.join(import);
+    let path = if path.is_dir() {
+        path.join("default.nix")
+    } else {
+        path
+    };
+    path.is_file().then_some(path)
+}
+
+pub(super) fn split(path: &str, name: &str) -> PyResult<Vec<String>> {
+    let segments = path.split('.').map(str::to_string).collect::<Vec<_>>();
+    if segments.iter().any(String::is_empty) {
+        return Err(PyValueError::new_err(format!(
+            "Invalid {} attribute path `{}`",
+            name, path
+        )));
+    }
+    Ok(segments)
+}
+
+/// Rename an attribute across the files imported by a nix entry point.
+///
+/// The files are those reachable from the entry point through `import`,
+/// `callPackage` or the `imports` of modules, as written in the code. Only the
+/// syntax is inspected, nothing is evaluated:
+///   - definitions are bindings of the path, matched from the top-level
+///     attrset of each file, through functions, `let`, `with`, `if`, lists and
+///     `mk*` helpers like `mkIf`, so `{ a.b = 1; }` and `{ a = { b = 1; }; }`
+///     both define `a.b`,
+///   - references are selections of the path, or of its tail by a variable
+///     named like its first segment, so `x.a.b` and `a.b` both refer to
+///     `a.b`, as does `x ? a.b`.
+///
+/// Values bound to other names, like `cfg = config.a;`, are not followed.
+///
+/// Args:
+///   - root_entry (str): The path to the nix entry point.
+///   - from_attr_path (str): The attribute path to rename, like
+///                           `services.foo.enable`.
+///   - to_attr_path (str): The new attribute path.
+///   - apply (bool): Write the edits to the files too.
+///
+/// Returns:
+///   - list[_Edit]: The edits, sorted by `path` and `start`, each replacing
+///                  the bytes from `start` to `end` of the file, on the given
+///                  `line`, with `new` instead of `old`.
+///
+/// Raises:
+///   - IOError: If a file cannot be read or written.
+///   - ParseError: If a file cannot be parsed.
+///   - ValueError: If an attribute path is empty, or a definition is nested
+///                 in an attrset that the rename would have to move.
+///
+/// Example:
+/// ```python
+/// # `default.nix` contains:
+/// # ```
+/// # { imports = [ ./foo.nix ]; foo.enable = true; }
+/// # ```
+/// # `foo.nix` contains:
+/// # ```
+/// # { config, ... }: { bar = config.foo.enable; }
+/// # ```
+/// >>> [e["new"] for e in rename("default.nix", "foo.enable", "foo.on")]
+/// ['foo.on', 'foo.on']
+/// ```
+#[pyfunction]
+#[pyo3(signature = (root_entry, from_attr_path, to_attr_path, apply = false))]
+pub fn rename(
+    py: Python<'_>,
+    root_entry: String,
+    from_attr_path: &str,
+    to_attr_path: &str,
+    apply: bool,
+) -> PyResult<Vec<PyObject>> {
+    let from = split(from_attr_path, "source")?;
+    let to = split(to_attr_path, "target")?;
+    let mut renamer = Renamer {
+        from: &from,
+        to: &to,
+        edits: Vec::new(),
+    };
+
+    let root_entry = PathBuf::from(root_entry);
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::from([root_entry]);
+    while let Some(path) = queue.pop_front() {
+        if !seen
+            .insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone()))
+        {
+            continue;
+        }
+        let content = read_file(&path)?;
+        let root = parse_root(&content, Some(&path.to_string_lossy()))?;
+
+        renamer.definitions(&path, &content, root.expr(), 0)?;
+        renamer.references(&path, &content, &root);
+        for node in root.syntax().descendants() {
+            queue.extend(
+                imported(&node)
+                    .iter()
+                    .filter_map(|import| resolve_import(&path, import)),
+            );
+        }
+    }
+
+    let mut edits = renamer.edits;
+    edits.sort_by(|a, b| (&a.path, a.start).cmp(&(&b.path, b.start)));
+    if apply {
+        for chunk in edits.chunk_by(|a, b| a.path == b.path) {
+            let path = &chunk[0].path;
+            let mut content = read_file(path)?;
+            for edit in chunk.iter().rev() {
+                content.replace_range(edit.start..edit.end, &edit.new);
+            }
+            fs::write(path, content).map_err(|e| {
+                PyIOError::new_err(format!(
+                    "Failed to write file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        }
+    }
+
+    edits
+        .iter()
+        .map(|edit| {
+            let dict = PyDict::new(py);
+            dict.set_item("path", edit.path.to_string_lossy())?;
+            dict.set_item("start", edit.start)?;
+            dict.set_item("end", edit.end)?;
+            dict.set_item("line", edit.line)?;
+            dict.set_item("old", &edit.old)?;
+            dict.set_item("new", &edit.new)?;
+            Ok(dict.into_any().unbind())
+        })
+        .collect()
+}
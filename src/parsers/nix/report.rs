@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use genawaiter::rc::Gen;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use pyo3::{PyErr, PyObject, PyResult};
+use tvix_eval::generators::{self, pin_generator};
+use tvix_eval::{Builtin, ErrorKind as TvixErrorKind, Value as TvixValue};
+
+use super::cache::RecordingIO;
+
+/// What became of a fetch an evaluation attempted
+#[derive(Clone, Copy)]
+pub(super) enum Outcome {
+    /// The fetcher was called and returned a path
+    Fetched,
+    /// The path was taken from the fetch cache
+    Cached,
+    /// The fetch cache is offline and had no copy
+    Offline,
+    /// No fetcher was given to the evaluator
+    Denied,
+    /// The fetcher raised an exception
+    Failed,
+}
+
+impl Outcome {
+    fn name(self) -> &'static str {
+        match self {
+            Outcome::Fetched => "fetched",
+            Outcome::Cached => "cached",
+            Outcome::Offline => "offline",
+            Outcome::Denied => "denied",
+            Outcome::Failed => "failed",
+        }
+    }
+}
+
+/// A fetch attempted by an evaluation
+pub(super) struct Fetch {
+    pub fetcher: &'static str,
+    /// The argument of the fetcher, described as JSON when possible
+    pub argument: String,
+    pub outcome: Outcome,
+}
+
+/// The fetches attempted by evaluations sharing the same context
+pub(super) type Fetches = Rc<RefCell<Vec<Fetch>>>;
+
+/// Stand in for a fetcher the evaluator was given no callable for, failing
+/// like a pure evaluation would but recording the attempt
+pub(super) fn denied_fetcher(
+    name: &'static str,
+    fetches: &Fetches,
+) -> TvixValue {
+    let fetches = fetches.clone();
+    let builtin = Builtin::new(name, None, 1, move |args| {
+        let fetches = fetches.clone();
+        Gen::new(|co| {
+            pin_generator(async move {
+                let argument =
+                    generators::request_deep_force(&co, args[0].clone()).await;
+                fetches.borrow_mut().push(Fetch {
+                    fetcher: name,
+                    argument: argument.to_string(),
+                    outcome: Outcome::Denied,
+                });
+                Err(TvixErrorKind::Abort(format!(
+                    "fetcher `{}` is not allowed, the evaluator has no \
+                    fetcher for it",
+                    name
+                )))
+            })
+        })
+    });
+    TvixValue::Builtin(builtin)
+}
+
+/// What an evaluation accessed and consumed, to audit untrusted code
+pub(super) struct Report {
+    pub time: Duration,
+    /// The most bytes the evaluation had allocated at once
+    pub peak_memory: usize,
+    pub fetches: Fetches,
+}
+
+impl Report {
+    pub fn to_pyobject(
+        &self,
+        py: Python<'_>,
+        io: &RecordingIO,
+    ) -> PyResult<PyObject> {
+        let files = PyList::empty(py);
+        for (path, access, denied) in io.accesses() {
+            let file = PyDict::new(py);
+            file.set_item("path", path.to_string_lossy())?;
+            file.set_item("access", access)?;
+            file.set_item("denied", denied)?;
+            files.append(file)?;
+        }
+        let fetches = PyList::empty(py);
+        for fetch in self.fetches.borrow().iter() {
+            let attempt = PyDict::new(py);
+            attempt.set_item("fetcher", fetch.fetcher)?;
+            attempt.set_item("argument", &fetch.argument)?;
+            attempt.set_item("outcome", fetch.outcome.name())?;
+            fetches.append(attempt)?;
+        }
+
+        let report = PyDict::new(py);
+        report.set_item("files", files)?;
+        report.set_item("fetches", fetches)?;
+        report.set_item("time", self.time.as_secs_f64())?;
+        report.set_item("peak_memory", self.peak_memory)?;
+        Ok(report.into_any().unbind())
+    }
+
+    /// Attach this report to the error the evaluation failed with, as its
+    /// `report` attribute
+    pub fn attached(&self, error: PyErr, io: &RecordingIO) -> PyErr {
+        Python::with_gil(|py| {
+            if let Ok(report) = self.to_pyobject(py, io) {
+                let _ = error.value(py).setattr("report", report);
+            }
+            error
+        })
+    }
+}
@@ -0,0 +1,121 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tvix_eval::{EvalIO, FileType};
+
+/// Make a path absolute and remove its `.` and `..` components, without
+/// resolving symlinks
+fn absolute(path: &Path) -> PathBuf {
+    let path = match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// The directories evaluations may access, as given and with their symlinks
+/// resolved
+pub(super) fn allowed_paths(paths: Vec<PathBuf>) -> Arc<[PathBuf]> {
+    let mut allowed = Vec::with_capacity(paths.len());
+    for path in paths {
+        let path = absolute(&path);
+        if let Ok(canonical) = fs::canonicalize(&path) {
+            allowed.push(canonical);
+        }
+        allowed.push(path);
+    }
+    allowed.dedup();
+    allowed.into()
+}
+
+/// Wraps the IO of an evaluation to deny any access outside of a list of
+/// directories, so untrusted expressions cannot read the rest of the system
+pub(super) struct SandboxIO {
+    inner: Rc<dyn EvalIO>,
+    allowed: Arc<[PathBuf]>,
+}
+
+impl SandboxIO {
+    pub fn new(inner: Rc<dyn EvalIO>, allowed: Arc<[PathBuf]>) -> Self {
+        Self { inner, allowed }
+    }
+
+    /// Fail unless `path` is in one of the allowed directories, both as
+    /// written and once its symlinks are resolved, so a symlink cannot lead
+    /// out of them
+    fn check(&self, path: &Path) -> io::Result<()> {
+        let is_allowed =
+            |path: &Path| self.allowed.iter().any(|dir| path.starts_with(dir));
+        let absolute = absolute(path);
+        let escapes =
+            fs::canonicalize(&absolute).is_ok_and(|real| !is_allowed(&real));
+        if is_allowed(&absolute) && !escapes {
+            return Ok(());
+        }
+        let message = if self.allowed.is_empty() {
+            format!(
+                "access to {} is denied, imports and file reads are \
+                disabled",
+                path.display()
+            )
+        } else {
+            format!(
+                "access to {} is denied, it is outside of the allowed paths \
+                {}",
+                path.display(),
+                self.allowed
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, message))
+    }
+}
+
+impl EvalIO for SandboxIO {
+    fn path_exists(&self, path: &Path) -> io::Result<bool> {
+        self.check(path)?;
+        self.inner.path_exists(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        self.check(path)?;
+        self.inner.open(path)
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<FileType> {
+        self.check(path)?;
+        self.inner.file_type(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(Bytes, FileType)>> {
+        self.check(path)?;
+        self.inner.read_dir(path)
+    }
+
+    fn import_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.check(path)?;
+        self.inner.import_path(path)
+    }
+
+    fn store_dir(&self) -> Option<String> {
+        self.inner.store_dir()
+    }
+}
@@ -0,0 +1,370 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::PyResult;
+
+use crate::parsers::floats::{FloatOptions, FloatPolicy, NonFinite};
+use crate::parsers::overflow::{self, narrowed, Narrowed};
+use crate::parsers::utils::{coded_err, ConversionError, ErrorCode};
+
+/// The keywords of nix, which cannot be written as bare attribute names
+const KEYWORDS: &[&str] = &[
+    "assert", "else", "if", "in", "inherit", "let", "or", "rec", "then", "with",
+];
+
+/// Write a string as a nix string literal, escaping the characters nix
+/// would interpret
+fn string(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            // Only `${` starts an interpolation, a lone `$` is kept
+            '$' if chars.peek() == Some(&'{') => literal.push_str("\\$"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Write a string as an indented nix string literal, escaping what nix would
+/// interpret, and the whitespace it starts with so that nix strips neither
+/// its indentation nor its first line
+fn indented_string(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 4);
+    literal.push_str("''");
+    let mut chars = s.chars().peekable();
+    let mut first = true;
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' if first => literal.push_str("''\\ "),
+            '\t' if first => literal.push_str("''\\t"),
+            '\n' if first => literal.push_str("''\\n"),
+            '\r' => literal.push_str("''\\r"),
+            '\'' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                literal.push_str("'''");
+            }
+            // A quote before the closing `''` would make it `'''`
+            '\'' if chars.peek().is_none() => literal.push_str("''\\'"),
+            '$' if chars.peek() == Some(&'{') => literal.push_str("''$"),
+            c => literal.push(c),
+        }
+        first = false;
+    }
+    literal.push_str("''");
+    literal
+}
+
+/// Write an attribute name, quoted unless it is a valid identifier
+fn name(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| {
+            c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-')
+        })
+        && !KEYWORDS.contains(&name);
+    if is_identifier {
+        name.to_string()
+    } else {
+        string(name)
+    }
+}
+
+/// Write a float so nix reads it back as a float, whose literals always
+/// have a dot, or as `null` if it is not finite and the policy asks so
+fn float(f: f64, floats: &FloatPolicy) -> PyResult<String> {
+    if f.is_finite() {
+        return Ok(floats.literal(f));
+    }
+    if floats.non_finite == Some(NonFinite::Null) {
+        return Ok("null".to_string());
+    }
+    Err(coded_err::<ConversionError>(
+        ErrorCode::NixConversion,
+        format!("Float {} has no nix literal", f),
+    ))
+}
+
+/// Writes Python values as nix expressions
+struct Writer {
+    indent: Option<usize>,
+    floats: FloatPolicy,
+    out: String,
+}
+
+impl Writer {
+    /// Start a new line at `depth` levels of indentation, or separate the
+    /// items by a space when writing on a single line
+    fn newline(&mut self, depth: usize) {
+        match self.indent {
+            Some(indent) => {
+                self.out.push('\n');
+                self.out.push_str(&" ".repeat(indent * depth));
+            }
+            None => self.out.push(' '),
+        }
+    }
+
+    fn write(
+        &mut self,
+        obj: &Bound<'_, PyAny>,
+        depth: usize,
+        in_list: bool,
+    ) -> PyResult<()> {
+        let negative = |literal: String| {
+            // Items of lists are separated by spaces, so `[ -1 ]` would
+            // not parse
+            if in_list && literal.starts_with('-') {
+                format!("({})", literal)
+            } else {
+                literal
+            }
+        };
+        if obj.is_none() {
+            self.out.push_str("null");
+        } else if let Ok(b) = obj.downcast::<PyBool>() {
+            self.out
+                .push_str(if b.is_true() { "true" } else { "false" });
+        } else if let Ok(i) = obj.downcast::<PyInt>() {
+            match narrowed(i, overflow::policy())? {
+                Some(Narrowed::Int(i)) => {
+                    self.out.push_str(&negative(i.to_string()))
+                }
+                Some(Narrowed::Digits(digits)) => {
+                    self.out.push_str(&string(&digits))
+                }
+                None => Err(coded_err::<ConversionError>(
+                    ErrorCode::NixConversion,
+                    format!(
+                        "Integer {} does not fit into a 64-bit nix integer",
+                        i
+                    ),
+                ))?,
+            }
+        } else if let Ok(f) = obj.downcast::<PyFloat>() {
+            self.out
+                .push_str(&negative(float(f.value(), &self.floats)?));
+        } else if let Ok(s) = obj.downcast::<PyString>() {
+            self.out.push_str(&string(s.to_str()?));
+        } else if obj.is_instance_of::<PyList>()
+            || obj.is_instance_of::<PyTuple>()
+        {
+            self.out.push('[');
+            let mut empty = true;
+            for item in obj.try_iter()? {
+                self.newline(depth + 1);
+                self.write(&item?, depth + 1, true)?;
+                empty = false;
+            }
+            self.close(']', depth, empty);
+        } else if let Ok(dict) = obj.downcast::<PyDict>() {
+            self.out.push('{');
+            for (k, v) in dict.iter() {
+                let key = k.downcast::<PyString>().map_err(|_| {
+                    coded_err::<ConversionError>(
+                        ErrorCode::NixConversion,
+                        format!(
+                            "Attribute names must be strings, got {}",
+                            k.get_type()
+                        ),
+                    )
+                })?;
+                self.newline(depth + 1);
+                self.out.push_str(&name(key.to_str()?));
+                self.out.push_str(" = ");
+                self.write(&v, depth + 1, false)?;
+                self.out.push(';');
+            }
+            self.close('}', depth, dict.is_empty());
+        } else {
+            return Err(coded_err::<ConversionError>(
+                ErrorCode::NixConversion,
+                format!(
+                    "Cannot convert python type {} to nix expression",
+                    obj.get_type().name()?
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Close a list or an attrset, written `[ ]` and `{ }` when empty
+    fn close(&mut self, bracket: char, depth: usize, empty: bool) {
+        if empty {
+            self.out.push(' ');
+        } else {
+            self.newline(depth);
+        }
+        self.out.push(bracket);
+    }
+}
+
+/// Convert a Python object to the text of a nix expression, the inverse of
+/// `evals`.
+///
+/// Strings are escaped, `${` included, so they never interpolate, and
+/// attribute names are quoted unless they are identifiers. Attributes are
+/// written in the order of the dict, which nix sorts once evaluated.
+///
+/// Args:
+///   - obj (Any): The value to convert, made of `None`, `bool`, `int`,
+///                `float`, `str`, `list`, `tuple` and `dict` with `str`
+///                keys.
+///   - indent (int | None): The number of spaces to indent nested lists and
+///                          attrsets by, one item per line. `None` writes the
+///                          expression on a single line.
+///   - float_policy (str | None): What to do with infinite and NaN floats,
+///                                `"none"` writing them as `null`, or
+///                                `"error"`. `None` follows
+///                                `set_float_policy`.
+///   - float_repr (str | None): How to write finite floats, `"shortest"`,
+///                              `"decimal"` or `"scientific"`. `None`
+///                              follows `set_float_policy`.
+///   - float_precision (int | None): The digits written after the point by
+///                                   the `decimal` and `scientific` reprs,
+///                                   all of them if `None`.
+///
+/// Returns:
+///   - str: The nix expression.
+///
+/// Raises:
+///   - ConversionError: If a value has no nix literal, like an integer
+///                      overflowing 64 bits, an infinite float or an object
+///                      of another type, or a dict key is not a string.
+///   - ValueError: If a float policy or repr is unknown, or a precision is
+///                 given without the `decimal` or `scientific` repr.
+///
+/// Example:
+/// ```python
+/// >>> print(dumps({"name": "hello", "meta.broken": False, "ports": [80, -1]}))
+/// {
+///   name = "hello";
+///   "meta.broken" = false;
+///   ports = [
+///     80
+///     (-1)
+///   ];
+/// }
+/// >>> dumps({"a": "${x}"}, indent=None)
+/// '{ a = "\\${x}"; }'
+/// >>> dumps([0.1, float("inf")], indent=None, float_policy="none",
+/// ...       float_repr="scientific")
+/// '[ 1.0e-1 null ]'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    obj,
+    indent = Some(2),
+    float_policy = None,
+    float_repr = None,
+    float_precision = None,
+))]
+pub fn dumps(
+    obj: &Bound<'_, PyAny>,
+    indent: Option<usize>,
+    float_policy: Option<&str>,
+    float_repr: Option<&str>,
+    float_precision: Option<usize>,
+) -> PyResult<String> {
+    let floats = FloatOptions::new(float_policy, float_repr, float_precision)?;
+    let mut writer = Writer {
+        indent,
+        floats: floats.resolve(),
+        out: String::new(),
+    };
+    writer.write(obj, 0, false)?;
+    Ok(writer.out)
+}
+
+/// Write a string as a nix string literal, so that nix reads it back as the
+/// same string.
+///
+/// `${` is escaped so it never interpolates. Indented strings escape `''`
+/// and `${` the way `''` strings do, and the whitespace the string starts
+/// with, which nix would otherwise strip as indentation.
+///
+/// Args:
+///   - s (str): The string to write.
+///   - indented (bool): Write a `''` string rather than a `"` string, for
+///                      strings spanning several lines.
+///
+/// Returns:
+///   - str: The nix string literal, with its quotes.
+///
+/// Example:
+/// ```python
+/// >>> print(escape_string('say "hi" to ${name}\n'))
+/// "say \"hi\" to \${name}\n"
+/// >>> print(escape_string("  echo ''${HOME}''\n", indented=True))
+/// ''''\  echo '''''${HOME}'''
+/// ''
+/// ```
+#[pyfunction]
+#[pyo3(signature = (s, indented = false))]
+pub fn escape_string(s: &str, indented: bool) -> String {
+    if indented {
+        indented_string(s)
+    } else {
+        string(s)
+    }
+}
+
+/// Write an attribute name, quoted unless it is an identifier that is not a
+/// keyword of nix.
+///
+/// Args:
+///   - key (str): The attribute name.
+///
+/// Returns:
+///   - str: The name as written in an attrset or an attribute path.
+///
+/// Example:
+/// ```python
+/// >>> quote_attr("enable"), quote_attr("my.app"), quote_attr("in")
+/// ('enable', '"my.app"', '"in"')
+/// ```
+#[pyfunction]
+pub fn quote_attr(key: &str) -> String {
+    name(key)
+}
+
+/// Write an attribute path, quoting the names that need it like
+/// `quote_attr`.
+///
+/// Args:
+///   - path (list[str]): The names of the path.
+///
+/// Returns:
+///   - str: The attribute path, its names separated by dots.
+///
+/// Raises:
+///   - ValueError: If the path is empty.
+///
+/// Example:
+/// ```python
+/// >>> print(format_attrpath(["services", "weird key", "${x}"]))
+/// services."weird key"."\${x}"
+/// ```
+#[pyfunction]
+pub fn format_attrpath(path: Vec<String>) -> PyResult<String> {
+    if path.is_empty() {
+        return Err(PyValueError::new_err(
+            "An attribute path has at least one name",
+        ));
+    }
+    Ok(path
+        .iter()
+        .map(|key| name(key))
+        .collect::<Vec<_>>()
+        .join("."))
+}
@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use smol_str::SmolStr;
+use tvix_eval::Value as TvixValue;
+
+use super::evaluator::{scope_of, virtual_path, Context, Evaluator, Scope};
+use crate::parsers::utils::normalized_newlines;
+
+/// A scope kept across evaluations, for interactive exploration like the
+/// nix REPL.
+///
+/// Each variable defined is evaluated lazily and kept as is, so the values
+/// later expressions force in it stay evaluated for the next ones: defining
+/// `pkgs` as nixpkgs evaluates each package once however many times it is
+/// explored. Expressions are evaluated with the settings of the evaluator,
+/// and never cached. Files read and fetches attempted are recorded for the
+/// whole session, so the reports of its evaluations list those of the
+/// previous ones too.
+///
+/// Like `NixThunk`, a session can only be used on the thread that created
+/// it.
+///
+/// Args:
+///   - evaluator (Evaluator): The evaluator of the expressions, one with
+///                            the default settings if not given.
+///   - dir (str): The directory the expressions are evaluated in, relative
+///                paths being resolved from it.
+///   - scope (dict): The variables the session starts with, converted
+///                   like the `scope` of `evals`.
+///
+/// Raises:
+///   - TypeError: If a variable of `scope` is not named by a string.
+///   - ConversionError: If a value of `scope` cannot be converted.
+///
+/// Example:
+/// ```python
+/// >>> s = Session(scope={"name": "hello"})
+/// >>> s.define("pkgs", "import <nixpkgs> {}")
+/// >>> s.eval("pkgs.${name}.meta.license.spdxId")
+/// 'GPL-3.0-or-later'
+/// >>> s.names
+/// ['name', 'pkgs']
+/// ```
+#[pyclass(module = "cosutils.rustlib.parsers.nix", unsendable)]
+pub struct Session {
+    evaluator: Evaluator,
+    location: Option<PathBuf>,
+    scope: RefCell<Scope>,
+    /// The last evaluation of the session, whose source map and recordings
+    /// the next one shares
+    context: RefCell<Option<Rc<Context>>>,
+}
+
+impl Session {
+    /// Evaluate an expression in the scope of the session
+    fn evaluated(
+        &self,
+        expr: &str,
+        evaluator: &Evaluator,
+    ) -> PyResult<(TvixValue, Rc<Context>)> {
+        // Builtins called by the evaluation may define variables meanwhile
+        let scope = self.scope.borrow().clone();
+        let within = self.context.borrow().clone();
+        let (value, context) = evaluator.eval_expr_within(
+            expr,
+            self.location.clone(),
+            Some("session"),
+            None,
+            Some(&scope),
+            within.as_deref(),
+        )?;
+        *self.context.borrow_mut() = Some(Rc::clone(&context));
+        Ok((value, context))
+    }
+}
+
+#[pymethods]
+impl Session {
+    #[new]
+    #[pyo3(signature = (evaluator = None, dir = None, scope = None))]
+    fn new(
+        evaluator: Option<Evaluator>,
+        dir: Option<String>,
+        scope: Option<Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let evaluator = match evaluator {
+            Some(evaluator) => evaluator,
+            None => Evaluator::from_profile(None, None, None, None, true)?,
+        };
+        let scope = scope
+            .as_ref()
+            .map(|s| scope_of(s, evaluator.number_policies()))
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            evaluator,
+            location: virtual_path(dir, None)?,
+            scope: RefCell::new(scope),
+            context: RefCell::new(None),
+        })
+    }
+
+    /// Define a variable of the session as the value of a nix expression.
+    ///
+    /// The expression is only evaluated to weak head normal form, the rest
+    /// of its value being evaluated as later expressions use it. It can
+    /// refer to the variables defined before, including the one it
+    /// redefines.
+    ///
+    /// Args:
+    ///   - name (str): The name of the variable.
+    ///   - expr (str): The nix expression it is defined as.
+    ///
+    /// Raises:
+    ///   - ParseError: If the nix expression cannot be parsed.
+    ///   - EvaluationError: If the nix expression cannot be evaluated, or
+    ///                      times out.
+    ///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+    fn define(&self, py: Python<'_>, name: &str, expr: &str) -> PyResult<()> {
+        let expr = normalized_newlines(expr, self.evaluator.normalize_newlines);
+        let lazy = Evaluator {
+            lazy: true,
+            ..self.evaluator.clone()
+        };
+        let (value, context) = self.evaluated(&expr, &lazy)?;
+        context.emit_traces(py)?;
+        self.scope.borrow_mut().insert(SmolStr::new(name), value);
+        Ok(())
+    }
+
+    /// Evaluate a nix expression in the scope of the session, and convert
+    /// its result like `Evaluator.evals`.
+    ///
+    /// Args:
+    ///   - expr (str): The nix expression.
+    ///
+    /// Returns:
+    ///   - _ProfiledNixValue: The evaluated expression as any Python object,
+    ///                        along with its traces, profile and report, as
+    ///                        collected by the evaluator.
+    ///
+    /// Raises:
+    ///   - ParseError: If the nix expression cannot be parsed.
+    ///   - EvaluationError: If the nix expression cannot be evaluated, or
+    ///                      times out.
+    ///   - ResourceLimitError: If the evaluation exceeds its memory limit.
+    ///   - ConversionError: If the result cannot be converted.
+    fn eval(&self, py: Python<'_>, expr: &str) -> PyResult<PyObject> {
+        let expr = normalized_newlines(expr, self.evaluator.normalize_newlines);
+        let (value, context) = self.evaluated(&expr, &self.evaluator)?;
+        self.evaluator.converted(py, &expr, &value, &context, false)
+    }
+
+    /// The variables of the session, sorted, to complete expressions.
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        let mut names = self
+            .scope
+            .borrow()
+            .keys()
+            .map(SmolStr::to_string)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Session {}>", self.names().join(", "))
+    }
+
+    fn _repr_pretty_(
+        slf: &Bound<'_, Self>,
+        printer: &Bound<'_, PyAny>,
+        _cycle: bool,
+    ) -> PyResult<()> {
+        printer.call_method1("text", (slf.repr()?,))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use rnix::ast::{self, AstNode, Expr};
+
+use super::ast::Lines;
+use super::complexity::is_importer;
+use super::imports::Imports;
+
+/// A nix file as an evaluation read it
+pub(super) struct SourceFile {
+    /// The name of the file in diagnostics, the path as given for the
+    /// evaluated file and the canonical path for imported ones
+    pub name: String,
+    pub content: String,
+    pub root: Option<Expr>,
+    pub lines: Lines,
+    /// The path imports are resolved from
+    path: PathBuf,
+}
+
+impl SourceFile {
+    fn new(name: String, path: PathBuf, content: String) -> Rc<Self> {
+        // The file was evaluated, it parses
+        let root = rnix::Root::parse(&content).tree().expr();
+        Rc::new(Self {
+            name,
+            lines: Lines::new(&content),
+            content,
+            root,
+            path,
+        })
+    }
+}
+
+/// The files an evaluation read, the evaluated one and those it imported,
+/// to locate the definitions of its values in the code it evaluated rather
+/// than in the files as they are now
+pub(super) struct Sources {
+    pub main: Rc<SourceFile>,
+    imports: Imports,
+    parsed: RefCell<HashMap<PathBuf, Rc<SourceFile>>>,
+}
+
+impl Sources {
+    /// The sources of an evaluation of `content`, named `name`, whose
+    /// imports kept their sources
+    pub fn new(name: &str, content: &str, imports: Imports) -> Self {
+        let main = SourceFile::new(
+            name.to_string(),
+            PathBuf::from(name),
+            content.to_string(),
+        );
+        Self {
+            main,
+            imports,
+            parsed: RefCell::default(),
+        }
+    }
+
+    /// The file evaluated by an `import` of a path literal in `from`, like
+    /// `import ./hosts.nix` or `callPackage ./hello.nix { }`, if the
+    /// evaluation imported it
+    pub fn imported(
+        &self,
+        from: &SourceFile,
+        apply: &ast::Apply,
+    ) -> Option<Rc<SourceFile>> {
+        // The function an imported file evaluates to may be applied as well
+        let apply = match apply.lambda()? {
+            Expr::Apply(inner) => inner,
+            _ => apply.clone(),
+        };
+        if !apply.lambda().is_some_and(|l| is_importer(&l)) {
+            return None;
+        }
+        let Some(Expr::Path(literal)) = apply.argument() else {
+            return None;
+        };
+        let literal = literal.syntax().text().to_string();
+        if literal.starts_with('<') || literal.starts_with('~') {
+            return None;
+        }
+        let dir = from.path.parent().unwrap_or(Path::new(""));
+        let path = dir.join(literal);
+        // Importing a directory evaluates its `default.nix`
+        let default = path.join("default.nix");
+        self.file(path).or_else(|| self.file(default))
+    }
+
+    /// The imported file at `path`, parsed once
+    fn file(&self, path: PathBuf) -> Option<Rc<SourceFile>> {
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        if let Some(file) = self.parsed.borrow().get(&path) {
+            return Some(Rc::clone(file));
+        }
+        let content = self.imports.source(&path)?;
+        let file =
+            SourceFile::new(path.display().to_string(), path.clone(), content);
+        self.parsed.borrow_mut().insert(path, Rc::clone(&file));
+        Some(file)
+    }
+}
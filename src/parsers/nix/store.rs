@@ -0,0 +1,593 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use bytes::Bytes;
+use data_encoding::{BASE64, HEXLOWER, HEXLOWER_PERMISSIVE};
+use genawaiter::rc::Gen;
+use sha2::{Digest, Sha256};
+use tvix_eval::generators::{self, pin_generator};
+use tvix_eval::{Builtin, EvalIO, FileType, NixAttrs};
+use tvix_eval::{ErrorKind as TvixErrorKind, Value as TvixValue};
+
+use super::writers::evaluated;
+
+/// The nix store all store paths are computed for
+pub const STORE_DIR: &str = "/nix/store";
+
+/// The alphabet of the base32 encoding used by nix, omitting `e o t u`
+const NIX_BASE32_ALPHABET: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+
+/// Encode bytes in the base32 variant of nix, which processes the input
+/// starting from its last byte
+pub fn nix_base32(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return String::new();
+    }
+    let len = (bytes.len() * 8 - 1) / 5 + 1;
+    (0..len)
+        .rev()
+        .map(|n| {
+            let b = n * 5;
+            let (i, j) = (b / 8, b % 8);
+            let mut c = (bytes[i] as u16) >> j;
+            if i + 1 < bytes.len() {
+                c |= (bytes[i + 1] as u16) << (8 - j);
+            }
+            NIX_BASE32_ALPHABET[(c & 0x1f) as usize] as char
+        })
+        .collect()
+}
+
+/// Decode nix base32 digits, the inverse of `nix_base32`
+fn parse_nix_base32(digits: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![0; digits.len() * 5 / 8];
+    for (n, c) in digits.bytes().rev().enumerate() {
+        let digit = NIX_BASE32_ALPHABET.iter().position(|&d| d == c)? as u16;
+        let b = n * 5;
+        let (i, j) = (b / 8, b % 8);
+        let shifted = digit << j;
+        *bytes.get_mut(i)? |= shifted as u8;
+        let carry = (shifted >> 8) as u8;
+        match bytes.get_mut(i + 1) {
+            Some(byte) => *byte |= carry,
+            // The digits must not encode more bits than the hash has
+            None if carry != 0 => return None,
+            None => {}
+        }
+    }
+    Some(bytes)
+}
+
+/// Fold a hash into `size` bytes by XOR-ing its bytes over each other
+fn compress_hash(hash: &[u8], size: usize) -> Vec<u8> {
+    let mut compressed = vec![0; size];
+    for (i, byte) in hash.iter().enumerate() {
+        compressed[i % size] ^= byte;
+    }
+    compressed
+}
+
+/// Decode an SRI hash like `sha256-<base64>`, only SHA-256 is supported
+pub fn parse_sri_sha256(sri: &str) -> Option<Vec<u8>> {
+    let encoded = sri.strip_prefix("sha256-")?;
+    let hash = BASE64.decode(encoded.as_bytes()).ok()?;
+    (hash.len() == 32).then_some(hash)
+}
+
+/// Decode a SHA-256 hash written any way nix accepts it, as an SRI hash, or
+/// as hexadecimal, nix base32 or base64 digits prefixed by `sha256:` or not
+pub fn parse_sha256(hash: &str) -> Option<Vec<u8>> {
+    if let Some(hash) = parse_sri_sha256(hash) {
+        return Some(hash);
+    }
+    let digits = hash.strip_prefix("sha256:").unwrap_or(hash);
+    let hash = match digits.len() {
+        64 => HEXLOWER_PERMISSIVE.decode(digits.as_bytes()).ok()?,
+        52 => parse_nix_base32(digits)?,
+        44 => BASE64.decode(digits.as_bytes()).ok()?,
+        _ => return None,
+    };
+    (hash.len() == 32).then_some(hash)
+}
+
+/// Compute a store path like `makeStorePath` of nix, from the kind of the
+/// path and the SHA-256 hash it is made from
+fn make_store_path(kind: &str, hash: &[u8], name: &str) -> PathBuf {
+    let fingerprint = format!(
+        "{}:sha256:{}:{}:{}",
+        kind,
+        HEXLOWER.encode(hash),
+        STORE_DIR,
+        name
+    );
+    let digest = Sha256::digest(fingerprint.as_bytes());
+    let hash = nix_base32(&compress_hash(&digest, 20));
+    PathBuf::from(format!("{}/{}-{}", STORE_DIR, hash, name))
+}
+
+/// Compute the store path of a fixed-output path added recursively with a
+/// SHA-256 hash, like the sources of flake inputs
+pub fn fixed_output_path(nar_hash: &[u8], name: &str) -> PathBuf {
+    make_store_path("source", nar_hash, name)
+}
+
+/// Compute the store path of a fixed-output path added flat, hashing the
+/// content of a single file with SHA-256
+pub fn flat_output_path(hash: &[u8], name: &str) -> PathBuf {
+    let inner = format!("fixed:out:sha256:{}:", HEXLOWER.encode(hash));
+    make_store_path("output:out", &Sha256::digest(inner.as_bytes()), name)
+}
+
+/// Check that a name can name a store path, which nix restricts to ASCII
+/// letters, digits and `+-._?=`, not starting with a dot
+pub fn check_store_name(name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 211
+        && !name.starts_with('.')
+        && name.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '+' | '-' | '.' | '_' | '?' | '=')
+        });
+    if valid {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("`{}` is not a valid store path name", name),
+    ))
+}
+
+/// Whether a file is executable by its owner, the only permission a NAR
+/// keeps. Files that are not on the file system, like those of a custom io,
+/// never are.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o100 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+/// The path of a directory entry
+fn entry_path(dir: &Path, name: &Bytes) -> PathBuf {
+    dir.join(String::from_utf8_lossy(name).as_ref())
+}
+
+/// Hashes the NAR serialization of a path, the archive nix hashes paths
+/// added to the store by, without writing it
+struct Nar<'a> {
+    io: &'a dyn EvalIO,
+    hasher: Sha256,
+    /// Whether a path under the archived one is part of the archive
+    include: &'a dyn Fn(&Path) -> bool,
+}
+
+impl Nar<'_> {
+    /// Write a string of the archive, prefixed by its length and padded to
+    /// 8 bytes
+    fn str(&mut self, bytes: &[u8]) {
+        self.hasher.update((bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+        self.hasher.update(&[0; 8][..(8 - bytes.len() % 8) % 8]);
+    }
+
+    fn node(&mut self, path: &Path, file_type: FileType) -> io::Result<()> {
+        self.str(b"(");
+        self.str(b"type");
+        match file_type {
+            FileType::Regular => {
+                self.str(b"regular");
+                if is_executable(path) {
+                    self.str(b"executable");
+                    self.str(b"");
+                }
+                let mut contents = Vec::new();
+                self.io.open(path)?.read_to_end(&mut contents)?;
+                self.str(b"contents");
+                self.str(&contents);
+            }
+            FileType::Symlink => {
+                self.str(b"symlink");
+                self.str(b"target");
+                let target = fs::read_link(path)?;
+                self.str(target.to_string_lossy().as_bytes());
+            }
+            FileType::Directory => {
+                self.str(b"directory");
+                let mut entries = self.io.read_dir(path)?;
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (name, file_type) in entries {
+                    let entry = entry_path(path, &name);
+                    if !(self.include)(&entry) {
+                        continue;
+                    }
+                    self.str(b"entry");
+                    self.str(b"(");
+                    self.str(b"name");
+                    self.str(&name);
+                    self.str(b"node");
+                    self.node(&entry, file_type)?;
+                    self.str(b")");
+                }
+            }
+            FileType::Unknown => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} is neither a file, a directory nor a symlink",
+                        path.display()
+                    ),
+                ))
+            }
+        }
+        self.str(b")");
+        Ok(())
+    }
+}
+
+/// The SHA-256 hash of the NAR serialization of a path, leaving out the
+/// paths under it that `include` rejects
+pub fn nar_hash(
+    io: &dyn EvalIO,
+    path: &Path,
+    include: &dyn Fn(&Path) -> bool,
+) -> io::Result<Vec<u8>> {
+    let mut nar = Nar {
+        io,
+        hasher: Sha256::new(),
+        include,
+    };
+    nar.str(b"nix-archive-1");
+    nar.node(path, io.file_type(path)?)?;
+    Ok(nar.hasher.finalize().to_vec())
+}
+
+/// Compute the store path nix would add a path at under `name`, and the
+/// SHA-256 hash it is computed from, of its NAR serialization if `recursive`
+/// or of the content of the file otherwise
+pub fn added_path(
+    io: &dyn EvalIO,
+    path: &Path,
+    name: &str,
+    recursive: bool,
+    include: &dyn Fn(&Path) -> bool,
+) -> io::Result<(PathBuf, Vec<u8>)> {
+    check_store_name(name)?;
+    if recursive {
+        let hash = nar_hash(io, path, include)?;
+        return Ok((fixed_output_path(&hash, name), hash));
+    }
+    let mut contents = Vec::new();
+    io.open(path)?.read_to_end(&mut contents)?;
+    let hash = Sha256::digest(&contents).to_vec();
+    Ok((flat_output_path(&hash, name), hash))
+}
+
+/// The name nix gives a path it adds to the store, its file name
+fn default_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Wraps the IO of an evaluation so that paths interpolated into strings
+/// become the store paths nix would copy them to, without copying them
+pub(super) struct StoreIO {
+    inner: Rc<dyn EvalIO>,
+}
+
+impl StoreIO {
+    pub fn new(inner: Rc<dyn EvalIO>) -> Self {
+        Self { inner }
+    }
+}
+
+impl EvalIO for StoreIO {
+    fn path_exists(&self, path: &Path) -> io::Result<bool> {
+        self.inner.path_exists(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        self.inner.open(path)
+    }
+
+    fn file_type(&self, path: &Path) -> io::Result<FileType> {
+        self.inner.file_type(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(Bytes, FileType)>> {
+        self.inner.read_dir(path)
+    }
+
+    fn import_path(&self, path: &Path) -> io::Result<PathBuf> {
+        // Paths already in the store are not added again
+        if path.starts_with(STORE_DIR) {
+            return Ok(path.to_path_buf());
+        }
+        let name = default_name(path);
+        let (store_path, _) =
+            added_path(self.inner.as_ref(), path, &name, true, &|_| true)?;
+        Ok(store_path)
+    }
+
+    fn store_dir(&self) -> Option<String> {
+        Some(STORE_DIR.to_string())
+    }
+}
+
+fn abort(message: String) -> TvixErrorKind {
+    TvixErrorKind::Abort(message)
+}
+
+/// A string or path argument of `builtins.path`
+fn path_argument(
+    value: TvixValue,
+    what: &str,
+) -> Result<String, TvixErrorKind> {
+    match value {
+        TvixValue::String(s) => Ok(s.to_string()),
+        TvixValue::Path(p) => Ok(p.to_string_lossy().into_owned()),
+        value => Err(abort(format!(
+            "`builtins.path` expects `{}` to be a string, got a {}",
+            what,
+            value.type_of()
+        ))),
+    }
+}
+
+/// The name of the type of a file given to the filters of `builtins.path`
+fn file_type_name(file_type: &FileType) -> &'static str {
+    match file_type {
+        FileType::Regular => "regular",
+        FileType::Directory => "directory",
+        FileType::Symlink => "symlink",
+        FileType::Unknown => "unknown",
+    }
+}
+
+/// Replace `builtins.path`, which tvix only has with a store, by one
+/// computing the store path nix would add the path at, applying its
+/// `filter` and checking its `sha256`, without copying it
+pub(super) fn path_builtin(io: Rc<dyn EvalIO>) -> TvixValue {
+    let builtin = Builtin::new("path", None, 1, move |args| {
+        let io = io.clone();
+        Gen::new(|co| {
+            pin_generator(async move {
+                let attrs =
+                    generators::request_force(&co, args[0].clone()).await;
+                let TvixValue::Attrs(attrs) = attrs else {
+                    return Err(abort(format!(
+                        "`builtins.path` expects an attribute set, got a {}",
+                        attrs.type_of()
+                    )));
+                };
+                let mut path = None;
+                let mut name = None;
+                let mut filter = None;
+                let mut recursive = true;
+                let mut sha256 = None;
+                for (key, value) in attrs.iter() {
+                    let key = String::from_utf8_lossy(key.as_bytes());
+                    if key == "filter" {
+                        filter = Some(value.clone());
+                        continue;
+                    }
+                    let value =
+                        generators::request_force(&co, value.clone()).await;
+                    match key.as_ref() {
+                        "path" => path = Some(path_argument(value, "path")?),
+                        "name" => name = Some(path_argument(value, "name")?),
+                        "sha256" => {
+                            sha256 = Some(path_argument(value, "sha256")?)
+                        }
+                        "recursive" => match value {
+                            TvixValue::Bool(b) => recursive = b,
+                            value => Err(abort(format!(
+                                "`builtins.path` expects `recursive` to be a \
+                                bool, got a {}",
+                                value.type_of()
+                            )))?,
+                        },
+                        key => Err(abort(format!(
+                            "unsupported argument `{}` to `builtins.path`",
+                            key
+                        )))?,
+                    }
+                }
+                let Some(path) = path.map(PathBuf::from) else {
+                    return Err(abort(
+                        "`builtins.path` needs a `path` argument".to_string(),
+                    ));
+                };
+                let io_error =
+                    |e: io::Error| abort(format!("{}: {}", path.display(), e));
+
+                // Call the filter on every entry under the path, not
+                // descending into the directories it rejects
+                let mut excluded = HashSet::new();
+                let is_dir = matches!(
+                    io.file_type(&path).map_err(io_error)?,
+                    FileType::Directory
+                );
+                if let (Some(filter), true) = (filter, is_dir) {
+                    let mut dirs = vec![path.clone()];
+                    while let Some(dir) = dirs.pop() {
+                        for (entry, file_type) in
+                            io.read_dir(&dir).map_err(io_error)?
+                        {
+                            let entry = entry_path(&dir, &entry);
+                            let arguments = [
+                                TvixValue::String(
+                                    entry.to_string_lossy().as_ref().into(),
+                                ),
+                                TvixValue::String(
+                                    file_type_name(&file_type).into(),
+                                ),
+                            ];
+                            let keep = generators::request_call_with(
+                                &co,
+                                filter.clone(),
+                                arguments,
+                            )
+                            .await;
+                            match generators::request_force(&co, keep).await {
+                                TvixValue::Bool(true) => {
+                                    if matches!(file_type, FileType::Directory)
+                                    {
+                                        dirs.push(entry);
+                                    }
+                                }
+                                TvixValue::Bool(false) => {
+                                    excluded.insert(entry);
+                                }
+                                value => Err(abort(format!(
+                                    "the filter of `builtins.path` must \
+                                    return a bool, got a {}",
+                                    value.type_of()
+                                )))?,
+                            }
+                        }
+                    }
+                }
+
+                let name = name.unwrap_or_else(|| default_name(&path));
+                let (store_path, hash) =
+                    added_path(io.as_ref(), &path, &name, recursive, &|p| {
+                        !excluded.contains(p)
+                    })
+                    .map_err(io_error)?;
+                if let Some(expected) = sha256 {
+                    if parse_sha256(&expected).as_deref() != Some(&hash) {
+                        return Err(abort(format!(
+                            "hash mismatch in path {}: expected {}, got \
+                            sha256-{}",
+                            path.display(),
+                            expected,
+                            BASE64.encode(&hash)
+                        )));
+                    }
+                }
+                let store_path = store_path.to_string_lossy();
+                Ok(TvixValue::String(store_path.as_ref().into()))
+            })
+        })
+    });
+    TvixValue::Builtin(builtin)
+}
+
+/// A string attribute of an evaluated attrset
+fn string_attr(attrs: &NixAttrs, name: &str) -> Option<String> {
+    attrs.iter().find_map(|(key, value)| {
+        match (key.as_bytes() == name.as_bytes(), evaluated(value)) {
+            (true, TvixValue::String(s)) => Some(s.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// The builtin `derivation.nix` computes the output paths of fixed-output
+/// derivations with, from the `name`, `hash`, `algo` and `mode` of the
+/// derivation. It returns `null` for hashes other than SHA-256, whose
+/// derivations keep a placeholder path.
+pub(super) fn fixed_output_builtin() -> TvixValue {
+    let builtin = Builtin::new("__cosutilsFixedOutputPath", None, 1, |args| {
+        Gen::new(|co| {
+            pin_generator(async move {
+                let TvixValue::Attrs(attrs) =
+                    generators::request_deep_force(&co, args[0].clone()).await
+                else {
+                    return Ok(TvixValue::Null);
+                };
+                let name = string_attr(&attrs, "name").unwrap_or_default();
+                let hash = match string_attr(&attrs, "algo").as_deref() {
+                    None | Some("sha256") => string_attr(&attrs, "hash")
+                        .as_deref()
+                        .and_then(parse_sha256),
+                    _ => None,
+                };
+                let path = match (hash, string_attr(&attrs, "mode").as_deref())
+                {
+                    (Some(hash), None | Some("flat")) => {
+                        flat_output_path(&hash, &name)
+                    }
+                    (Some(hash), Some("recursive" | "nar")) => {
+                        fixed_output_path(&hash, &name)
+                    }
+                    _ => return Ok(TvixValue::Null),
+                };
+                let path = path.to_string_lossy();
+                Ok(TvixValue::String(path.as_ref().into()))
+            })
+        })
+    });
+    TvixValue::Builtin(builtin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The NAR hash of an empty directory
+    const EMPTY_DIR_NAR: &str =
+        "sha256-pQpattmS9VmO3ZIQUFn66az8GSmB4IvYhTTCFn6SUmo=";
+
+    #[test]
+    fn base32_of_known_hash() {
+        let hash = Sha256::digest(b"");
+        assert_eq!(
+            nix_base32(&hash),
+            "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73"
+        );
+    }
+
+    #[test]
+    fn base32_round_trip() {
+        for len in [1, 5, 20, 32] {
+            let bytes: Vec<u8> =
+                (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            let digits = nix_base32(&bytes);
+            assert_eq!(parse_nix_base32(&digits), Some(bytes));
+        }
+        // `e` is not a digit of nix base32
+        assert_eq!(parse_nix_base32("e0"), None);
+    }
+
+    #[test]
+    fn sha256_in_every_encoding() {
+        let hash = parse_sri_sha256(EMPTY_DIR_NAR).unwrap();
+        let hex = HEXLOWER.encode(&hash);
+        let base32 = nix_base32(&hash);
+        let base64 = BASE64.encode(&hash);
+        for encoded in [hex, base32, base64] {
+            assert_eq!(parse_sha256(&encoded).as_ref(), Some(&hash));
+            let prefixed = format!("sha256:{}", encoded);
+            assert_eq!(parse_sha256(&prefixed).as_ref(), Some(&hash));
+        }
+        assert_eq!(parse_sha256("sha256:0123"), None);
+    }
+
+    #[test]
+    fn known_source_path() {
+        let hash = parse_sri_sha256(EMPTY_DIR_NAR).unwrap();
+        assert_eq!(
+            fixed_output_path(&hash, "source"),
+            Path::new("/nix/store/0ccnxa25whszw7mgbgyzdm4nqc0zwnm8-source")
+        );
+    }
+
+    #[test]
+    fn known_text_path() {
+        // `builtins.toFile "foo" "bar"`
+        let hash = Sha256::digest(b"bar");
+        assert_eq!(
+            make_store_path("text", &hash, "foo"),
+            Path::new("/nix/store/vxjiwkjkn7x4079qvh1jkl5pn05j2aw0-foo")
+        );
+    }
+}
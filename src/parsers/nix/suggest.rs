@@ -0,0 +1,308 @@
+use rnix::ast::{self, AstNode, Expr};
+use rnix::{NodeOrToken, Root, SyntaxNode, TextRange, TextSize};
+use tvix_eval::{Error as TvixError, ErrorKind as TvixErrorKind};
+
+use super::ast::{attr_name, attrpath_names};
+
+/// The variables nix defines without `builtins.`
+const GLOBALS: &[&str] = &[
+    "abort",
+    "baseNameOf",
+    "builtins",
+    "derivation",
+    "dirOf",
+    "false",
+    "fetchGit",
+    "fetchTarball",
+    "fetchTree",
+    "fromTOML",
+    "import",
+    "isNull",
+    "map",
+    "null",
+    "placeholder",
+    "removeAttrs",
+    "scopedImport",
+    "throw",
+    "toString",
+    "true",
+];
+
+/// The attributes of `builtins`
+const BUILTINS: &[&str] = &[
+    "abort",
+    "add",
+    "all",
+    "any",
+    "attrNames",
+    "attrValues",
+    "baseNameOf",
+    "bitAnd",
+    "bitOr",
+    "bitXor",
+    "catAttrs",
+    "ceil",
+    "compareVersions",
+    "concatLists",
+    "concatMap",
+    "concatStringsSep",
+    "currentSystem",
+    "deepSeq",
+    "dirOf",
+    "div",
+    "elem",
+    "elemAt",
+    "fetchGit",
+    "fetchTarball",
+    "fetchTree",
+    "fetchurl",
+    "filter",
+    "filterSource",
+    "floor",
+    "foldl'",
+    "fromJSON",
+    "fromTOML",
+    "functionArgs",
+    "genList",
+    "genericClosure",
+    "getAttr",
+    "getEnv",
+    "groupBy",
+    "hasAttr",
+    "hashString",
+    "head",
+    "import",
+    "intersectAttrs",
+    "isAttrs",
+    "isBool",
+    "isFloat",
+    "isFunction",
+    "isInt",
+    "isList",
+    "isNull",
+    "isPath",
+    "isString",
+    "length",
+    "lessThan",
+    "listToAttrs",
+    "map",
+    "mapAttrs",
+    "match",
+    "mul",
+    "nixVersion",
+    "parseDrvName",
+    "partition",
+    "path",
+    "pathExists",
+    "readDir",
+    "readFile",
+    "removeAttrs",
+    "replaceStrings",
+    "seq",
+    "sort",
+    "split",
+    "splitVersion",
+    "storeDir",
+    "stringLength",
+    "sub",
+    "substring",
+    "tail",
+    "throw",
+    "toFile",
+    "toJSON",
+    "toPath",
+    "toString",
+    "toXML",
+    "trace",
+    "tryEval",
+    "typeOf",
+    "zipAttrsWith",
+];
+
+/// The number of single character edits turning `a` into `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Suggest the candidates closest to `name`, if they are close enough for
+/// `name` to be a typo of them
+fn closest(
+    name: &str,
+    candidates: impl IntoIterator<Item = String>,
+) -> Option<String> {
+    let limit = (name.chars().count() / 3).max(1);
+    let mut close = candidates
+        .into_iter()
+        .filter(|c| c != name)
+        .map(|c| (distance(name, &c), c))
+        .filter(|(d, _)| *d <= limit)
+        .collect::<Vec<_>>();
+    close.sort();
+    close.dedup();
+    let best = close.first()?.0;
+    let names = close
+        .iter()
+        .filter(|(d, _)| *d == best)
+        .take(3)
+        .map(|(_, c)| format!("`{}`", c))
+        .collect::<Vec<_>>();
+    Some(format!("did you mean {}?", names.join(" or ")))
+}
+
+/// Whether the bindings of a node are in scope of its values, like those of
+/// a `let` or a recursive attrset
+fn is_scope(node: &SyntaxNode) -> bool {
+    ast::LetIn::can_cast(node.kind())
+        || ast::LegacyLet::can_cast(node.kind())
+        || ast::AttrSet::cast(node.clone())
+            .is_some_and(|s| s.rec_token().is_some())
+}
+
+/// The names of the arguments of a function
+fn parameters(lambda: &ast::Lambda) -> Vec<String> {
+    let name = |ident: Option<ast::Ident>| {
+        ident.and_then(|i| i.ident_token()).map(|t| t.to_string())
+    };
+    match lambda.param() {
+        Some(ast::Param::IdentParam(param)) => {
+            name(param.ident()).into_iter().collect()
+        }
+        Some(ast::Param::Pattern(pattern)) => pattern
+            .pat_entries()
+            .map(|e| name(e.ident()))
+            .chain([name(pattern.pat_bind().and_then(|b| b.ident()))])
+            .flatten()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The names of the attributes the entries of a node define at `path`, as
+/// nix merges the attrpaths of nested attributes
+fn keys_at(node: &SyntaxNode, path: &[String]) -> Vec<String> {
+    let mut keys = Vec::new();
+    for entry in node.children().filter_map(ast::Entry::cast) {
+        match entry {
+            ast::Entry::Inherit(inherit) => {
+                if path.is_empty() {
+                    keys.extend(inherit.attrs().filter_map(|a| attr_name(&a)));
+                }
+            }
+            ast::Entry::AttrpathValue(binding) => {
+                let Some(names) = attrpath_names(binding.attrpath()) else {
+                    continue;
+                };
+                if names.len() > path.len() && names.starts_with(path) {
+                    keys.push(names[path.len()].clone());
+                } else if path.starts_with(&names) {
+                    if let Some(Expr::AttrSet(set)) = binding.value() {
+                        let rest = &path[names.len()..];
+                        keys.extend(keys_at(set.syntax(), rest));
+                    }
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// The attributes at `path` of the value of an expression, when the syntax
+/// tells them, following variables to the attrsets they are bound to
+fn keys_of(expr: Expr, path: Vec<String>) -> Option<Vec<String>> {
+    match expr {
+        Expr::Paren(paren) => keys_of(paren.expr()?, path),
+        Expr::AttrSet(set) => Some(keys_at(set.syntax(), &path)),
+        Expr::Select(select) if select.default_expr().is_none() => {
+            let mut names = attrpath_names(select.attrpath())?;
+            names.extend(path);
+            keys_of(select.expr()?, names)
+        }
+        Expr::Ident(ident) => {
+            let name = ident.ident_token()?.to_string();
+            for ancestor in ident.syntax().ancestors().skip(1) {
+                if let Some(lambda) = ast::Lambda::cast(ancestor.clone()) {
+                    // Arguments are only known once the function is called
+                    if parameters(&lambda).contains(&name) {
+                        return None;
+                    }
+                } else if is_scope(&ancestor)
+                    && keys_at(&ancestor, &[]).contains(&name)
+                {
+                    let path = [vec![name], path].concat();
+                    return Some(keys_at(&ancestor, &path));
+                }
+            }
+            let builtins = BUILTINS.iter().map(|b| b.to_string()).collect();
+            (name == "builtins" && path.is_empty()).then_some(builtins)
+        }
+        _ => None,
+    }
+}
+
+/// The variables in scope at a node, `with` included when the syntax tells
+/// the attributes it brings in
+fn visible(node: &SyntaxNode) -> Vec<String> {
+    let mut names = Vec::new();
+    for ancestor in node.ancestors().skip(1) {
+        if let Some(lambda) = ast::Lambda::cast(ancestor.clone()) {
+            names.extend(parameters(&lambda));
+        } else if let Some(with) = ast::With::cast(ancestor.clone()) {
+            let namespace = with.namespace().and_then(|n| keys_of(n, vec![]));
+            names.extend(namespace.unwrap_or_default());
+        } else if is_scope(&ancestor) {
+            names.extend(keys_at(&ancestor, &[]));
+        }
+    }
+    names.extend(GLOBALS.iter().map(|g| g.to_string()));
+    names
+}
+
+/// Suggest the names an unknown variable or a missing attribute may be a
+/// typo of, among the variables in scope where it is used or the attributes
+/// of the attrset it is selected from, as far as the syntax tells them
+pub(super) fn suggestion(error: &TvixError) -> Option<String> {
+    let file = error.source.codemap().find_file(error.span.low()).clone();
+    let start = (error.span.low() - file.span.low()) as u32;
+    let end = (error.span.high() - file.span.low()) as u32;
+    let root = Root::parse(file.source()).tree();
+    let range = TextRange::new(TextSize::from(start), TextSize::from(end));
+    if range.end() > root.syntax().text_range().end() {
+        return None;
+    }
+    let node = match root.syntax().covering_element(range) {
+        NodeOrToken::Node(node) => node,
+        NodeOrToken::Token(token) => token.parent()?,
+    };
+
+    match &error.kind {
+        TvixErrorKind::UnknownStaticVariable
+        | TvixErrorKind::UnknownDynamicVariable(_) => {
+            let ident = node.ancestors().find_map(ast::Ident::cast)?;
+            let name = ident.ident_token()?.to_string();
+            closest(&name, visible(ident.syntax()))
+        }
+        TvixErrorKind::AttributeNotFound { name } => {
+            let select = node.ancestors().find_map(ast::Select::cast)?;
+            let names = select
+                .attrpath()?
+                .attrs()
+                .map(|a| attr_name(&a))
+                .collect::<Vec<_>>();
+            let at = names.iter().position(|n| n.as_ref() == Some(name))?;
+            let path = names[..at].iter().cloned().collect::<Option<_>>()?;
+            closest(name, keys_of(select.expr()?, path)?)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,126 @@
+use pyo3::prelude::*;
+use rnix::SyntaxKind;
+
+/// The kind of a token for syntax highlighting, stable across versions of
+/// rnix. Values are never reused.
+#[pyclass(module = "cosutils.rustlib.parsers.nix", eq, eq_int, frozen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace = 0,
+    Comment = 1,
+    /// `let`, `in`, `if`, `inherit`, `or`, ...
+    Keyword = 2,
+    /// Variables and attribute names, `true`, `false` and `null` included
+    Identifier = 3,
+    Integer = 4,
+    Float = 5,
+    /// The quotes of a string, `"` or `''`
+    StringDelimiter = 6,
+    /// The text and escapes of a string
+    String = 7,
+    /// The `${` and `}` around an interpolation
+    Interpolation = 8,
+    Path = 9,
+    Uri = 10,
+    /// Arithmetic, logical and attrset operators, `=` and `?` included
+    Operator = 11,
+    /// Brackets, `;`, `:`, `,`, `.`, `@` and `...`
+    Punctuation = 12,
+    /// Text the lexer does not recognize
+    Error = 13,
+}
+
+impl TokenKind {
+    fn of_syntax(kind: SyntaxKind) -> Self {
+        use SyntaxKind::*;
+        match kind {
+            TOKEN_WHITESPACE => TokenKind::Whitespace,
+            TOKEN_COMMENT => TokenKind::Comment,
+            TOKEN_ASSERT | TOKEN_ELSE | TOKEN_IF | TOKEN_IN | TOKEN_INHERIT
+            | TOKEN_LET | TOKEN_OR | TOKEN_REC | TOKEN_THEN | TOKEN_WITH => {
+                TokenKind::Keyword
+            }
+            TOKEN_IDENT => TokenKind::Identifier,
+            TOKEN_INTEGER => TokenKind::Integer,
+            TOKEN_FLOAT => TokenKind::Float,
+            TOKEN_STRING_START | TOKEN_STRING_END => TokenKind::StringDelimiter,
+            TOKEN_STRING_CONTENT => TokenKind::String,
+            TOKEN_INTERPOL_START | TOKEN_INTERPOL_END => {
+                TokenKind::Interpolation
+            }
+            TOKEN_PATH => TokenKind::Path,
+            TOKEN_URI => TokenKind::Uri,
+            TOKEN_L_BRACE | TOKEN_R_BRACE | TOKEN_L_BRACK | TOKEN_R_BRACK
+            | TOKEN_L_PAREN | TOKEN_R_PAREN | TOKEN_SEMICOLON | TOKEN_COLON
+            | TOKEN_COMMA | TOKEN_DOT | TOKEN_AT | TOKEN_ELLIPSIS => {
+                TokenKind::Punctuation
+            }
+            TOKEN_ERROR => TokenKind::Error,
+            // The other tokens are all operators
+            _ => TokenKind::Operator,
+        }
+    }
+}
+
+#[pymethods]
+impl TokenKind {
+    /// The name of the kind in snake case, like `string_delimiter`, to name
+    /// the styles of a highlighter
+    #[getter]
+    fn name(&self) -> &'static str {
+        match self {
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Comment => "comment",
+            TokenKind::Keyword => "keyword",
+            TokenKind::Identifier => "identifier",
+            TokenKind::Integer => "integer",
+            TokenKind::Float => "float",
+            TokenKind::StringDelimiter => "string_delimiter",
+            TokenKind::String => "string",
+            TokenKind::Interpolation => "interpolation",
+            TokenKind::Path => "path",
+            TokenKind::Uri => "uri",
+            TokenKind::Operator => "operator",
+            TokenKind::Punctuation => "punctuation",
+            TokenKind::Error => "error",
+        }
+    }
+}
+
+/// Split nix code into tokens for syntax highlighting.
+///
+/// The code is only lexed, not parsed, so incomplete or invalid code is
+/// tokenized too, the text the lexer does not recognize becoming `Error`
+/// tokens. The tokens cover the whole code, whitespace and comments
+/// included, so joining their text gives the code back.
+///
+/// Args:
+///   - content (str): The nix code.
+///
+/// Returns:
+///   - list[tuple[TokenKind, int, int]]: The `kind` of each token, and the
+///                                       `start` and `end` byte offsets of
+///                                       its text, in source order.
+///
+/// Example:
+/// ```python
+/// >>> [(k.name, s, e) for k, s, e in tokenize('{ a = "x${b}"; }')][:7]
+/// [('punctuation', 0, 1), ('whitespace', 1, 2), ('identifier', 2, 3),
+///  ('whitespace', 3, 4), ('operator', 4, 5), ('whitespace', 5, 6),
+///  ('string_delimiter', 6, 7)]
+/// >>> tokenize("1.5")[0][0] == TokenKind.Float
+/// True
+/// ```
+#[pyfunction]
+pub fn tokenize(content: &str) -> Vec<(TokenKind, usize, usize)> {
+    let mut start = 0;
+    rnix::tokenize(content)
+        .into_iter()
+        .map(|(kind, text)| {
+            let end = start + text.len();
+            let token = (TokenKind::of_syntax(kind), start, end);
+            start = end;
+            token
+        })
+        .collect()
+}
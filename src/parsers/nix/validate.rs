@@ -0,0 +1,515 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+use annotate_snippets::{Level, Snippet};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use pyo3::PyResult;
+use rnix::ast::{AstNode, Expr, HasEntry};
+use tvix_eval::{NixAttrs, Value as TvixValue};
+
+use super::ast::attrpath_names;
+use super::sources::{SourceFile, Sources};
+use super::value_diff::equal;
+use super::writers::evaluated;
+use crate::parsers::hook::{hooked, unhooked};
+use crate::parsers::render::renderer;
+use crate::parsers::schema::attrpath;
+use crate::parsers::utils::{
+    aggregated_err, coded_err, display_source, ErrorCode, EvaluationError,
+    TryFromPyObject,
+};
+
+/// A step of the path to a value nested in an evaluated value
+#[derive(Clone)]
+enum Segment {
+    Name(String),
+    Index(usize),
+}
+
+/// Write a path like the paths of conversion errors, as `a.b[0]`
+fn display_path(path: &[Segment]) -> String {
+    let written = path
+        .iter()
+        .map(|segment| match segment {
+            Segment::Name(name) => {
+                format!(".{}", attrpath(std::slice::from_ref(name)))
+            }
+            Segment::Index(i) => format!("[{}]", i),
+        })
+        .collect::<String>();
+    written.trim_start_matches('.').to_string()
+}
+
+/// A value that does not match its schema
+pub(super) struct Violation {
+    path: Vec<Segment>,
+    message: String,
+}
+
+/// The JSON Schema type of a value, or its nix type for functions, which
+/// JSON has no type for
+fn type_name(value: &TvixValue) -> &'static str {
+    match value {
+        TvixValue::Null => "null",
+        TvixValue::Bool(_) => "boolean",
+        TvixValue::Integer(_) => "integer",
+        TvixValue::Float(_) => "number",
+        TvixValue::String(_) | TvixValue::Path(_) => "string",
+        TvixValue::List(_) => "array",
+        TvixValue::Attrs(_) => "object",
+        value => value.type_of(),
+    }
+}
+
+/// The attribute of an evaluated attrset
+fn attr<'a>(attrs: &'a NixAttrs, name: &str) -> Option<&'a TvixValue> {
+    attrs
+        .iter()
+        .find(|(key, _)| key.as_bytes() == name.as_bytes())
+        .map(|(_, value)| value)
+}
+
+/// A keyword of a schema node that must be a dict
+fn dict_keyword<'py>(
+    node: &Bound<'py, PyDict>,
+    keyword: &str,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    node.get_item(keyword)?
+        .map(|value| {
+            value.downcast_into::<PyDict>().map_err(|_| {
+                PyTypeError::new_err(format!(
+                    "`{}` of a schema must be a dict",
+                    keyword
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// Validates an evaluated value against a JSON Schema, collecting every
+/// value that does not match it
+struct Validator {
+    path: Vec<Segment>,
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    fn violation(&mut self, message: String) {
+        self.violations.push(Violation {
+            path: self.path.clone(),
+            message,
+        });
+    }
+
+    /// Validate a value nested under `segment` of the current path
+    fn nested(
+        &mut self,
+        segment: Segment,
+        value: &TvixValue,
+        node: &Bound<'_, PyDict>,
+    ) -> PyResult<()> {
+        self.path.push(segment);
+        let result = self.validate(value, node);
+        self.path.pop();
+        result
+    }
+
+    fn validate(
+        &mut self,
+        value: &TvixValue,
+        node: &Bound<'_, PyDict>,
+    ) -> PyResult<()> {
+        let value = evaluated(value);
+        let actual = type_name(&value);
+        if let Some(expected) = node.get_item("type")? {
+            let expected = match expected.extract::<String>() {
+                Ok(expected) => vec![expected],
+                Err(_) => expected.extract::<Vec<String>>()?,
+            };
+            let matches = expected
+                .iter()
+                .any(|t| t == actual || (t == "number" && actual == "integer"));
+            if !matches {
+                // The other keywords only make sense for the expected type
+                self.violation(format!(
+                    "expected {}, got {}",
+                    expected.join(" or "),
+                    actual
+                ));
+                return Ok(());
+            }
+        }
+        if let Some(allowed) = node.get_item("enum")? {
+            let allowed = allowed.downcast_into::<PyList>().map_err(|_| {
+                PyTypeError::new_err("`enum` of a schema must be a list")
+            })?;
+            let mut found = false;
+            for item in allowed.iter() {
+                found |= equal(&value, &TvixValue::try_from_pyobject(&item)?);
+            }
+            if !found {
+                self.violation(format!(
+                    "expected one of {}, got {}",
+                    allowed.repr()?,
+                    value
+                ));
+            }
+        }
+        if let Some(constant) = node.get_item("const")? {
+            if !equal(&value, &TvixValue::try_from_pyobject(&constant)?) {
+                self.violation(format!(
+                    "expected {}, got {}",
+                    constant.repr()?,
+                    value
+                ));
+            }
+        }
+        if let Some(branches) = node.get_item("anyOf")? {
+            let mut matched = false;
+            for branch in branches.try_iter()? {
+                let branch =
+                    branch?.downcast_into::<PyDict>().map_err(|_| {
+                        PyTypeError::new_err(
+                            "`anyOf` of a schema must be a list of dicts",
+                        )
+                    })?;
+                let mut alternative = Validator {
+                    path: self.path.clone(),
+                    violations: Vec::new(),
+                };
+                alternative.validate(&value, &branch)?;
+                matched |= alternative.violations.is_empty();
+            }
+            if !matched {
+                self.violation(format!(
+                    "{} matches none of the `anyOf` schemas",
+                    actual
+                ));
+            }
+        }
+        match &value {
+            TvixValue::Attrs(attrs) => self.object(attrs, node)?,
+            TvixValue::List(list) => {
+                self.count(
+                    list.len(),
+                    node,
+                    ("minItems", "maxItems"),
+                    "items",
+                )?;
+                if let Some(items) = dict_keyword(node, "items")? {
+                    for (i, item) in list.iter().enumerate() {
+                        self.nested(Segment::Index(i), item, &items)?;
+                    }
+                }
+            }
+            TvixValue::String(s) => self.string(&s.to_string(), node)?,
+            TvixValue::Path(p) => self.string(&p.to_string_lossy(), node)?,
+            TvixValue::Integer(i) => self.number(*i as f64, node)?,
+            TvixValue::Float(f) => self.number(*f, node)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Check the number of items or characters of a value against the
+    /// minimum and maximum `keywords`
+    fn count(
+        &mut self,
+        count: usize,
+        node: &Bound<'_, PyDict>,
+        keywords: (&str, &str),
+        unit: &str,
+    ) -> PyResult<()> {
+        if let Some(min) = node.get_item(keywords.0)? {
+            let min = min.extract::<usize>()?;
+            if count < min {
+                self.violation(format!(
+                    "expected at least {} {}, got {}",
+                    min, unit, count
+                ));
+            }
+        }
+        if let Some(max) = node.get_item(keywords.1)? {
+            let max = max.extract::<usize>()?;
+            if count > max {
+                self.violation(format!(
+                    "expected at most {} {}, got {}",
+                    max, unit, count
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str, node: &Bound<'_, PyDict>) -> PyResult<()> {
+        let length = s.chars().count();
+        self.count(length, node, ("minLength", "maxLength"), "characters")?;
+        if let Some(pattern) = node.get_item("pattern")? {
+            // Patterns are matched by Python, like JSON Schema validators
+            let re = node.py().import("re")?;
+            let found = re.call_method1("search", (&pattern, s))?;
+            if found.is_none() {
+                self.violation(format!(
+                    "{:?} does not match the pattern {}",
+                    s,
+                    pattern.repr()?
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn number(&mut self, n: f64, node: &Bound<'_, PyDict>) -> PyResult<()> {
+        let bounds: [(&str, fn(f64, f64) -> bool, &str); 4] = [
+            ("minimum", |n, b| n >= b, "at least"),
+            ("maximum", |n, b| n <= b, "at most"),
+            ("exclusiveMinimum", |n, b| n > b, "more than"),
+            ("exclusiveMaximum", |n, b| n < b, "less than"),
+        ];
+        for (keyword, holds, expected) in bounds {
+            let Some(bound) = node.get_item(keyword)? else {
+                continue;
+            };
+            let bound = bound.extract::<f64>()?;
+            if !holds(n, bound) {
+                self.violation(format!(
+                    "expected {} {}, got {}",
+                    expected, bound, n
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn object(
+        &mut self,
+        attrs: &NixAttrs,
+        node: &Bound<'_, PyDict>,
+    ) -> PyResult<()> {
+        let properties = dict_keyword(node, "properties")?;
+        if let Some(required) = node.get_item("required")? {
+            for name in required.extract::<Vec<String>>()? {
+                if attr(attrs, &name).is_none() {
+                    self.violation(format!(
+                        "missing required attribute `{}`",
+                        attrpath(&[name])
+                    ));
+                }
+            }
+        }
+        if let Some(properties) = &properties {
+            for (name, property) in properties.iter() {
+                let name = name.extract::<String>()?;
+                let property =
+                    property.downcast_into::<PyDict>().map_err(|_| {
+                        PyTypeError::new_err(format!(
+                            "the schema of property `{}` must be a dict",
+                            name
+                        ))
+                    })?;
+                if let Some(value) = attr(attrs, &name) {
+                    self.nested(Segment::Name(name), value, &property)?;
+                }
+            }
+        }
+        let Some(additional) = node.get_item("additionalProperties")? else {
+            return Ok(());
+        };
+        for (name, value) in attrs.iter() {
+            let name = String::from_utf8_lossy(name.as_bytes()).into_owned();
+            if let Some(properties) = &properties {
+                if properties.contains(&name)? {
+                    continue;
+                }
+            }
+            if let Ok(schema) = additional.downcast::<PyDict>() {
+                self.nested(Segment::Name(name), value, schema)?;
+            } else if !additional.is_truthy()? {
+                self.path.push(Segment::Name(name));
+                self.violation(
+                    "attribute is not allowed by the schema".to_string(),
+                );
+                self.path.pop();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate an evaluated value against a JSON Schema, returning the values
+/// that do not match it in the order of the schema
+pub(super) fn violations(
+    value: &TvixValue,
+    schema: &Bound<'_, PyDict>,
+) -> PyResult<Vec<Violation>> {
+    let mut validator = Validator {
+        path: Vec::new(),
+        violations: Vec::new(),
+    };
+    validator.validate(value, schema)?;
+    Ok(validator.violations)
+}
+
+/// The range of the expression defining the value at `path` and the file
+/// it is in, found in the syntax like `lookup` and through the files
+/// imported with a path literal, or of the closest expression enclosing it
+/// that is found
+fn definition(
+    sources: &Sources,
+    file: &Rc<SourceFile>,
+    expr: Expr,
+    path: &[Segment],
+) -> (Rc<SourceFile>, Range<usize>) {
+    let range = Range::<usize>::from(expr.syntax().text_range());
+    let Some(first) = path.first() else {
+        return (Rc::clone(file), range);
+    };
+    let inner = match (&expr, first) {
+        (Expr::AttrSet(set), Segment::Name(_)) => {
+            for binding in set.attrpath_values() {
+                let Some(names) = attrpath_names(binding.attrpath()) else {
+                    continue;
+                };
+                let matched = names
+                    .iter()
+                    .zip(path)
+                    .take_while(
+                        |(a, b)| matches!(b, Segment::Name(name) if name == *a),
+                    )
+                    .count();
+                match (binding.value(), matched) {
+                    (_, 0) => continue,
+                    (Some(value), matched) if matched == names.len() => {
+                        return definition(
+                            sources,
+                            file,
+                            value,
+                            &path[matched..],
+                        );
+                    }
+                    // The path ends within the attrpath, like `a` in
+                    // `a.b = 1;`
+                    (_, matched) if matched == path.len() => {
+                        return (
+                            Rc::clone(file),
+                            Range::<usize>::from(binding.syntax().text_range()),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        (Expr::List(list), Segment::Index(i)) => {
+            return match list.items().nth(*i) {
+                Some(item) => definition(sources, file, item, &path[1..]),
+                None => (Rc::clone(file), range),
+            };
+        }
+        (Expr::Apply(apply), _) => {
+            // The value of an imported file is defined in the file
+            let imported = sources.imported(file, apply);
+            if let Some(imported) = imported {
+                if let Some(root) = imported.root.clone() {
+                    return definition(sources, &imported, root, path);
+                }
+            }
+            None
+        }
+        (Expr::Lambda(lambda), _) => lambda.body(),
+        (Expr::LetIn(let_in), _) => let_in.body(),
+        (Expr::With(with), _) => with.body(),
+        (Expr::Paren(paren), _) => paren.expr(),
+        _ => None,
+    };
+    match inner {
+        Some(inner) => definition(sources, file, inner, path),
+        None => (Rc::clone(file), range),
+    }
+}
+
+/// Raise the violations of a value as an `EvaluationError`, each pointing at
+/// the definition of its value in the file of the evaluation defining it
+pub(super) fn check_violations(
+    sources: &Sources,
+    violations: &[Violation],
+) -> PyResult<()> {
+    let main = &sources.main;
+    let mut errors = unhooked(|| {
+        violations
+            .iter()
+            .map(|violation| {
+                let title = match display_path(&violation.path) {
+                    path if path.is_empty() => violation.message.clone(),
+                    path => format!("`{}`: {}", path, violation.message),
+                };
+                let message = Level::Error.title(&title);
+                let defined = main.root.clone().map(|root| {
+                    definition(sources, main, root, &violation.path)
+                });
+                let rendered = match defined {
+                    Some((file, range)) => {
+                        let source = display_source(&file.content);
+                        let snippet = Snippet::source(&source)
+                            .origin(&file.name)
+                            .fold(true)
+                            .annotation(
+                                Level::Error.span(range).label("defined here"),
+                            );
+                        renderer().render(message.snippet(snippet)).to_string()
+                    }
+                    None => renderer().render(message).to_string(),
+                };
+                coded_err::<EvaluationError>(ErrorCode::NixSchema, rendered)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    });
+    match errors.next() {
+        Some(first) => Err(hooked(aggregated_err(first, errors.collect()))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+
+    use super::*;
+    use crate::parsers::nix::evaluator::Evaluator;
+    use crate::parsers::utils::FileSource;
+
+    #[test]
+    fn violations_in_imported_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("cosutils-validate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("server.nix"), "{\n  port = \"80\";\n}\n").unwrap();
+        let main = dir.join("main.nix");
+        let content =
+            "{\n  name = \"web\";\n  server = import ./server.nix;\n}\n";
+        fs::write(&main, content).unwrap();
+        let schema = "{'properties': {'server': {'properties': \
+                      {'port': {'type': 'integer'}}}}}";
+        let message = Python::with_gil(|py| {
+            let schema = py.eval(&CString::new(schema)?, None, None)?;
+            let error = Evaluator::default()
+                .eval_validated(
+                    py,
+                    FileSource::Path(main.clone()),
+                    schema.downcast_into::<PyDict>()?,
+                )
+                .unwrap_err();
+            PyResult::Ok(error.value(py).to_string())
+        });
+        fs::remove_dir_all(&dir).unwrap();
+        let message = message.unwrap();
+        let title = "`server.port`: expected integer, got string";
+        assert!(message.contains(title));
+        assert!(message.contains("server.nix:2:10"));
+        assert!(message.contains("port = \"80\";"));
+    }
+}
@@ -0,0 +1,159 @@
+use std::cmp::Ordering;
+use std::iter::zip;
+use std::rc::Rc;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+use tvix_eval::Value as TvixValue;
+
+use super::convert::Converter;
+use super::evaluator::Context;
+use super::writers::evaluated;
+use crate::parsers::schema::attrpath;
+
+/// How the value at an attribute path changed between two evaluations
+pub(super) enum Change {
+    Added(TvixValue),
+    Removed(TvixValue),
+    Changed(TvixValue, TvixValue),
+}
+
+/// Whether two evaluated values are equal like nix compares them, functions
+/// never being equal
+pub(super) fn equal(a: &TvixValue, b: &TvixValue) -> bool {
+    match (evaluated(a), evaluated(b)) {
+        (TvixValue::Null, TvixValue::Null) => true,
+        (TvixValue::Bool(a), TvixValue::Bool(b)) => a == b,
+        (TvixValue::Integer(a), TvixValue::Integer(b)) => a == b,
+        (TvixValue::Float(a), TvixValue::Float(b)) => a == b,
+        (TvixValue::Integer(i), TvixValue::Float(f))
+        | (TvixValue::Float(f), TvixValue::Integer(i)) => i as f64 == f,
+        (TvixValue::String(a), TvixValue::String(b)) => {
+            a.as_bytes() == b.as_bytes()
+        }
+        (TvixValue::Path(a), TvixValue::Path(b)) => a == b,
+        (TvixValue::List(a), TvixValue::List(b)) => {
+            a.len() == b.len()
+                && zip(a.iter(), b.iter()).all(|(a, b)| equal(a, b))
+        }
+        (TvixValue::Attrs(a), TvixValue::Attrs(b)) => {
+            a.len() == b.len()
+                && zip(a.iter(), b.iter()).all(|((ka, va), (kb, vb))| {
+                    ka.as_bytes() == kb.as_bytes() && equal(va, vb)
+                })
+        }
+        _ => false,
+    }
+}
+
+fn walk(
+    old: &TvixValue,
+    new: &TvixValue,
+    path: &mut Vec<String>,
+    changes: &mut Vec<(String, Change)>,
+) {
+    let (TvixValue::Attrs(old), TvixValue::Attrs(new)) =
+        (evaluated(old), evaluated(new))
+    else {
+        if !equal(old, new) {
+            changes.push((
+                attrpath(path),
+                Change::Changed(evaluated(old), evaluated(new)),
+            ));
+        }
+        return;
+    };
+    // Both attrsets iterate in the sorted order of their names
+    let mut old = old.iter().peekable();
+    let mut new = new.iter().peekable();
+    loop {
+        let order = match (old.peek(), new.peek()) {
+            (Some((a, _)), Some((b, _))) => a.as_bytes().cmp(b.as_bytes()),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match order {
+            Ordering::Less => {
+                let Some((name, value)) = old.next() else {
+                    break;
+                };
+                path.push(String::from_utf8_lossy(name.as_bytes()).into());
+                changes.push((attrpath(path), Change::Removed(value.clone())));
+            }
+            Ordering::Greater => {
+                let Some((name, value)) = new.next() else {
+                    break;
+                };
+                path.push(String::from_utf8_lossy(name.as_bytes()).into());
+                changes.push((attrpath(path), Change::Added(value.clone())));
+            }
+            Ordering::Equal => {
+                let (Some((name, a)), Some((_, b))) = (old.next(), new.next())
+                else {
+                    break;
+                };
+                path.push(String::from_utf8_lossy(name.as_bytes()).into());
+                walk(a, b, path, changes);
+            }
+        }
+        path.pop();
+    }
+}
+
+/// The changes from an evaluated value to another, by the attribute path
+/// they are at in the sorted order of nix. Attrsets are compared attribute
+/// by attribute, other values as a whole.
+pub(super) fn changes(
+    old: &TvixValue,
+    new: &TvixValue,
+) -> Vec<(String, Change)> {
+    let mut changes = Vec::new();
+    walk(old, new, &mut Vec::new(), &mut changes);
+    changes
+}
+
+/// Convert changes to the `added`, `removed` and `changed` dicts of `diff`,
+/// the values of each side converted from their own evaluation
+pub(super) fn changes_to_py(
+    py: Python<'_>,
+    changes: &[(String, Change)],
+    old_context: &Rc<Context>,
+    new_context: &Rc<Context>,
+) -> PyResult<PyObject> {
+    let old = Converter {
+        context: Some(old_context),
+        order: None,
+        detached: false,
+    };
+    let new = Converter {
+        context: Some(new_context),
+        order: None,
+        detached: false,
+    };
+    let added = PyDict::new(py);
+    let removed = PyDict::new(py);
+    let changed = PyDict::new(py);
+    for (path, change) in changes {
+        match change {
+            Change::Added(value) => {
+                added.set_item(path, new.convert(py, value)?)?
+            }
+            Change::Removed(value) => {
+                removed.set_item(path, old.convert(py, value)?)?
+            }
+            Change::Changed(a, b) => {
+                let values = PyDict::new(py);
+                values.set_item("old", old.convert(py, a)?)?;
+                values.set_item("new", new.convert(py, b)?)?;
+                changed.set_item(path, values)?;
+            }
+        }
+    }
+    let diff = PyDict::new(py);
+    diff.set_item("added", added)?;
+    diff.set_item("removed", removed)?;
+    diff.set_item("changed", changed)?;
+    Ok(diff.into_any().unbind())
+}
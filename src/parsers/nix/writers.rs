@@ -0,0 +1,390 @@
+use std::str::from_utf8;
+
+use pyo3::{PyErr, PyResult};
+use tvix_eval::{NixAttrs, NixString, Value as TvixValue};
+
+use crate::parsers::floats::{FloatPolicy, NonFinite};
+use crate::parsers::utils::{coded_err, ConversionError, ErrorCode};
+
+/// How a text format writes what JSON has no literal for
+struct Format {
+    name: &'static str,
+    null: Option<&'static str>,
+    infinity: &'static str,
+    nan: &'static str,
+    string: fn(&str) -> String,
+}
+
+const TOML: Format = Format {
+    name: "TOML",
+    null: None,
+    infinity: "inf",
+    nan: "nan",
+    string: quoted,
+};
+
+const YAML: Format = Format {
+    name: "YAML",
+    null: Some("null"),
+    infinity: ".inf",
+    nan: ".nan",
+    string: yaml_string,
+};
+
+fn unconvertible(format: &Format, what: String) -> PyErr {
+    coded_err::<ConversionError>(
+        ErrorCode::NixConversion,
+        format!("Cannot convert {} to {}", what, format.name),
+    )
+}
+
+/// A string as a JSON string, which is also a valid basic string of TOML and
+/// double-quoted scalar of YAML
+fn quoted(s: &str) -> String {
+    // Strings are always representable
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+/// The value of an evaluated thunk, or the value itself
+pub(super) fn evaluated(value: &TvixValue) -> TvixValue {
+    match value {
+        TvixValue::Thunk(thunk) if thunk.is_evaluated() => {
+            evaluated(&thunk.value())
+        }
+        value => value.clone(),
+    }
+}
+
+fn attr_name<'a>(name: &'a NixString, format: &Format) -> PyResult<&'a str> {
+    from_utf8(name.as_bytes()).map_err(|e| {
+        unconvertible(format, format!("attribute name {} ({})", name, e))
+    })
+}
+
+/// Write a value that is neither an attrset nor a list
+fn scalar(
+    value: &TvixValue,
+    format: &Format,
+    floats: &FloatPolicy,
+) -> PyResult<String> {
+    let null = || {
+        format
+            .null
+            .map(str::to_string)
+            .ok_or_else(|| unconvertible(format, "null".to_string()))
+    };
+    let literal = match value {
+        TvixValue::Null => null()?,
+        TvixValue::Bool(b) => b.to_string(),
+        TvixValue::Integer(i) => i.to_string(),
+        TvixValue::Float(f) if f.is_finite() => floats.literal(*f),
+        TvixValue::Float(f) => match floats.non_finite {
+            Some(NonFinite::Error) => {
+                Err(unconvertible(format, format!("float {}", f)))?
+            }
+            Some(NonFinite::Null) => null()?,
+            _ if f.is_nan() => format.nan.to_string(),
+            _ if *f > 0.0 => format.infinity.to_string(),
+            _ => format!("-{}", format.infinity),
+        },
+        TvixValue::String(s) => (format.string)(&s.to_string()),
+        TvixValue::Path(p) => match p.to_str() {
+            Some(p) => (format.string)(p),
+            None => Err(unconvertible(
+                format,
+                format!(
+                    "path {}, try wrap your path as `\"${{path}}\"`",
+                    p.display()
+                ),
+            ))?,
+        },
+        value => {
+            Err(unconvertible(format, format!("nix {}", value.type_of())))?
+        }
+    };
+    Ok(literal)
+}
+
+/// A TOML key, bare when it only has the characters bare keys allow
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        quoted(key)
+    }
+}
+
+/// Whether a value is written as a table of its own rather than inline,
+/// which lists are when they only contain attrsets
+fn is_table(value: &TvixValue) -> bool {
+    match value {
+        TvixValue::Attrs(_) => true,
+        TvixValue::List(l) => {
+            !l.is_empty()
+                && l.iter()
+                    .all(|item| matches!(evaluated(item), TvixValue::Attrs(_)))
+        }
+        _ => false,
+    }
+}
+
+struct TomlWriter<'a> {
+    out: String,
+    floats: &'a FloatPolicy,
+    /// The keys of the table being written
+    path: Vec<String>,
+}
+
+impl TomlWriter<'_> {
+    fn header(&mut self, brackets: (&str, &str)) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out.push_str(brackets.0);
+        self.out.push_str(&self.path.join("."));
+        self.out.push_str(brackets.1);
+        self.out.push('\n');
+    }
+
+    /// Write the keys of a table, then its subtables, as the keys written
+    /// after a header belong to the last one
+    fn table(&mut self, attrs: &NixAttrs) -> PyResult<()> {
+        let mut tables = Vec::new();
+        for (k, v) in attrs.iter() {
+            let key = toml_key(attr_name(k, &TOML)?);
+            let value = evaluated(v);
+            if is_table(&value) {
+                tables.push((key, value));
+                continue;
+            }
+            self.out.push_str(&key);
+            self.out.push_str(" = ");
+            self.inline(&value)?;
+            self.out.push('\n');
+        }
+        for (key, value) in tables {
+            self.path.push(key);
+            match value {
+                TvixValue::Attrs(attrs) => {
+                    self.header(("[", "]"));
+                    self.table(&attrs)?;
+                }
+                TvixValue::List(l) => {
+                    for item in l.iter() {
+                        if let TvixValue::Attrs(attrs) = evaluated(item) {
+                            self.header(("[[", "]]"));
+                            self.table(&attrs)?;
+                        }
+                    }
+                }
+                _ => unreachable!("only attrsets and lists are tables"),
+            }
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn inline(&mut self, value: &TvixValue) -> PyResult<()> {
+        match evaluated(value) {
+            TvixValue::List(l) => {
+                self.out.push('[');
+                for (i, item) in l.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.inline(item)?;
+                }
+                self.out.push(']');
+            }
+            TvixValue::Attrs(attrs) if attrs.is_empty() => {
+                self.out.push_str("{}")
+            }
+            TvixValue::Attrs(attrs) => {
+                self.out.push_str("{ ");
+                for (i, (k, v)) in attrs.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(&toml_key(attr_name(k, &TOML)?));
+                    self.out.push_str(" = ");
+                    self.inline(v)?;
+                }
+                self.out.push_str(" }");
+            }
+            value => self.out.push_str(&scalar(&value, &TOML, self.floats)?),
+        }
+        Ok(())
+    }
+}
+
+/// Serialize an evaluated nix attrset to a TOML document. Attrsets become
+/// tables, and lists of attrsets arrays of tables, unless they are nested in
+/// another list.
+pub fn tvix_to_toml(
+    value: &TvixValue,
+    floats: &FloatPolicy,
+) -> PyResult<String> {
+    let TvixValue::Attrs(attrs) = evaluated(value) else {
+        return Err(unconvertible(
+            &TOML,
+            format!(
+                "nix {}, a TOML document is an attribute set",
+                value.type_of()
+            ),
+        ));
+    };
+    let mut writer = TomlWriter {
+        out: String::new(),
+        floats,
+        path: Vec::new(),
+    };
+    writer.table(&attrs)?;
+    Ok(writer.out)
+}
+
+/// A YAML scalar for a string, plain when no YAML reader can take it for
+/// anything else than that string, double-quoted otherwise
+fn yaml_string(s: &str) -> String {
+    const RESERVED: [&str; 11] = [
+        "true", "false", "yes", "no", "on", "off", "y", "n", "null", "nan",
+        "inf",
+    ];
+    let plain = s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && !s.ends_with(' ')
+        && s.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '_' | '-' | '.' | '/' | ' ')
+        })
+        && !RESERVED.contains(&s.to_ascii_lowercase().as_str());
+    if plain {
+        s.to_string()
+    } else {
+        quoted(s)
+    }
+}
+
+/// A YAML scalar, with a sign in the exponent of floats that YAML 1.1
+/// readers need to read them as floats
+fn yaml_scalar(value: &TvixValue, floats: &FloatPolicy) -> PyResult<String> {
+    let literal = scalar(value, &YAML, floats)?;
+    if !matches!(value, TvixValue::Float(_)) {
+        return Ok(literal);
+    }
+    Ok(match literal.split_once('e') {
+        Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+            format!("{}e+{}", mantissa, exponent)
+        }
+        _ => literal,
+    })
+}
+
+struct YamlWriter<'a> {
+    out: String,
+    floats: &'a FloatPolicy,
+}
+
+impl YamlWriter<'_> {
+    fn indent(&mut self, indent: usize) {
+        self.out.push_str(&" ".repeat(indent));
+    }
+
+    /// Write the entries of a mapping at an indent, the first on the current
+    /// line if it follows the `- ` of a sequence item
+    fn mapping(
+        &mut self,
+        attrs: &NixAttrs,
+        indent: usize,
+        inline: bool,
+    ) -> PyResult<()> {
+        for (i, (k, v)) in attrs.iter().enumerate() {
+            if i > 0 || !inline {
+                self.indent(indent);
+            }
+            self.out.push_str(&yaml_string(attr_name(k, &YAML)?));
+            self.out.push(':');
+            match evaluated(v) {
+                TvixValue::Attrs(attrs) if !attrs.is_empty() => {
+                    self.out.push('\n');
+                    self.mapping(&attrs, indent + 2, false)?;
+                }
+                TvixValue::List(l) if !l.is_empty() => {
+                    self.out.push('\n');
+                    self.sequence(l.iter(), indent + 2, false)?;
+                }
+                value => self.flow(&value)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the items of a sequence at an indent, the first on the current
+    /// line if it follows the `- ` of a sequence item
+    fn sequence<'a>(
+        &mut self,
+        items: impl Iterator<Item = &'a TvixValue>,
+        indent: usize,
+        inline: bool,
+    ) -> PyResult<()> {
+        for (i, item) in items.enumerate() {
+            if i > 0 || !inline {
+                self.indent(indent);
+            }
+            self.out.push('-');
+            match evaluated(item) {
+                TvixValue::Attrs(attrs) if !attrs.is_empty() => {
+                    self.out.push(' ');
+                    self.mapping(&attrs, indent + 2, true)?;
+                }
+                TvixValue::List(l) if !l.is_empty() => {
+                    self.out.push(' ');
+                    self.sequence(l.iter(), indent + 2, true)?;
+                }
+                value => self.flow(&value)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a scalar or an empty collection after a key or a dash, ending
+    /// the line
+    fn flow(&mut self, value: &TvixValue) -> PyResult<()> {
+        self.out.push(' ');
+        match value {
+            TvixValue::Attrs(_) => self.out.push_str("{}"),
+            TvixValue::List(_) => self.out.push_str("[]"),
+            value => self.out.push_str(&yaml_scalar(value, self.floats)?),
+        }
+        self.out.push('\n');
+        Ok(())
+    }
+}
+
+/// Serialize an evaluated nix value to a YAML document in block style, with
+/// only the empty attrsets and lists in flow style.
+pub fn tvix_to_yaml(
+    value: &TvixValue,
+    floats: &FloatPolicy,
+) -> PyResult<String> {
+    let mut writer = YamlWriter {
+        out: String::new(),
+        floats,
+    };
+    match evaluated(value) {
+        TvixValue::Attrs(attrs) if !attrs.is_empty() => {
+            writer.mapping(&attrs, 0, false)?
+        }
+        TvixValue::List(l) if !l.is_empty() => {
+            writer.sequence(l.iter(), 0, false)?
+        }
+        value => {
+            writer.flow(&value)?;
+            // A document that is a scalar has no key or dash before it
+            writer.out.remove(0);
+        }
+    }
+    Ok(writer.out)
+}
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyInt;
+use pyo3::PyResult;
+
+/// What the converters do with integers that do not fit into 64 bits
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum IntOverflow {
+    /// Raise an error naming the integer
+    Error,
+    /// Keep the low 64 bits, as a two's complement integer
+    Wrap,
+    /// Keep the integer exactly, through its decimal digits
+    Digits,
+}
+
+impl IntOverflow {
+    pub fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "error" => Ok(IntOverflow::Error),
+            "wrap" => Ok(IntOverflow::Wrap),
+            "bigint-via-string" => Ok(IntOverflow::Digits),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown overflow policy `{}`, expected `error`, `wrap` or \
+                 `bigint-via-string`",
+                name
+            ))),
+        }
+    }
+
+    /// The policy named `name`, or the one selected by `set_int_overflow`
+    pub fn resolve(name: Option<&str>) -> PyResult<Option<Self>> {
+        match name {
+            Some(name) => IntOverflow::of_name(name).map(Some),
+            None => Ok(policy()),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntOverflow::Error => "error",
+            IntOverflow::Wrap => "wrap",
+            IntOverflow::Digits => "bigint-via-string",
+        }
+    }
+}
+
+const UNSET: u8 = 0;
+const ERROR: u8 = 1;
+const WRAP: u8 = 2;
+const DIGITS: u8 = 3;
+
+/// The overflow policy set from Python
+static POLICY: AtomicU8 = AtomicU8::new(UNSET);
+
+/// The overflow policy selected by `set_int_overflow`, `None` leaving it to
+/// each converter
+pub fn policy() -> Option<IntOverflow> {
+    match POLICY.load(Ordering::Relaxed) {
+        ERROR => Some(IntOverflow::Error),
+        WRAP => Some(IntOverflow::Wrap),
+        DIGITS => Some(IntOverflow::Digits),
+        _ => None,
+    }
+}
+
+/// The low 64 bits of a Python integer of any size
+pub fn wrapped(int: &Bound<'_, PyInt>) -> PyResult<i64> {
+    // `&` of Python integers works on the infinite two's complement form,
+    // negative integers included
+    let low = int.bitand(u64::MAX)?.extract::<u64>()?;
+    Ok(low as i64)
+}
+
+/// A Python integer narrowed to a nix integer under the overflow policy
+pub enum Narrowed {
+    Int(i64),
+    /// The decimal digits of an integer too large for nix
+    Digits(String),
+}
+
+/// Narrow a Python integer to 64 bits, `None` if it does not fit and
+/// `policy` asks for an error, which nix converters raise by default
+pub fn narrowed(
+    int: &Bound<'_, PyInt>,
+    policy: Option<IntOverflow>,
+) -> PyResult<Option<Narrowed>> {
+    if let Ok(i) = int.extract::<i64>() {
+        return Ok(Some(Narrowed::Int(i)));
+    }
+    let narrowed = match policy {
+        None | Some(IntOverflow::Error) => None,
+        Some(IntOverflow::Wrap) => Some(Narrowed::Int(wrapped(int)?)),
+        Some(IntOverflow::Digits) => Some(Narrowed::Digits(int.to_string())),
+    };
+    Ok(narrowed)
+}
+
+/// Choose what the converters do with integers that do not fit into 64 bits.
+///
+/// Nix integers are 64-bit, and JSONC integers are read as such, so larger
+/// integers are either given to nix from Python or written in JSONC
+/// documents. With the default policy, nix converters raise a
+/// `ConversionError` and JSONC loaders read them as floats, losing their
+/// precision, except in strict mode. The policies are:
+///   - `error`: JSONC loaders raise a `ParseError` too, even when not
+///              strict.
+///   - `wrap`: The low 64 bits are kept, as a two's complement integer, like
+///             C does.
+///   - `bigint-via-string`: The integer is kept exactly through its decimal
+///                          digits. JSONC loaders give Python integers of any
+///                          size, and Python integers given to nix become
+///                          strings of their digits.
+///
+/// The `wrap` and `bigint-via-string` policies apply to strict JSONC loaders
+/// too, which no longer reject larger integers.
+///
+/// Larger integer literals of nix code cannot be evaluated by nix, and are
+/// always rejected. The policy applies to all the converters, on all
+/// threads, but the loaders and evaluators given their own `int_overflow`.
+///
+/// Args:
+///   - policy (str | None): The overflow policy, `None` restores the
+///                          default.
+///
+/// Raises:
+///   - ValueError: If the policy is unknown.
+///
+/// Example:
+/// ```python
+/// >>> jsonc.loads("18446744073709551616")
+/// 1.8446744073709552e+19
+/// >>> set_int_overflow("bigint-via-string")
+/// >>> jsonc.loads("18446744073709551616")
+/// 18446744073709551616
+/// >>> nix.dumps({"big": 2**64}, indent=None)
+/// '{ big = "18446744073709551616"; }'
+/// >>> set_int_overflow("wrap")
+/// >>> jsonc.loads("18446744073709551617")
+/// 1
+/// ```
+#[pyfunction]
+#[pyo3(signature = (policy = None))]
+pub fn set_int_overflow(policy: Option<&str>) -> PyResult<()> {
+    let policy = match policy.map(IntOverflow::of_name).transpose()? {
+        None => UNSET,
+        Some(IntOverflow::Error) => ERROR,
+        Some(IntOverflow::Wrap) => WRAP,
+        Some(IntOverflow::Digits) => DIGITS,
+    };
+    POLICY.store(policy, Ordering::Relaxed);
+    Ok(())
+}
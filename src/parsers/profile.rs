@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{PyObject, PyResult};
+
+use crate::parsers::render::set_color;
+use crate::parsers::utils::TryToPyObject;
+
+/// Settings of the parsers shared under a name, the defaults of the
+/// arguments left unset
+pub struct Profile {
+    pub name: &'static str,
+    /// Whether error messages are colored, `None` for `NO_COLOR`
+    pub color: Option<bool>,
+    pub jsonc_strict: bool,
+    pub nix_strict: bool,
+    pub nix_timeout: Option<f64>,
+    pub nix_max_memory_mb: Option<usize>,
+}
+
+/// The profiles, the first one being the defaults of the arguments
+const PROFILES: &[Profile] = &[
+    Profile {
+        name: "default",
+        color: None,
+        jsonc_strict: false,
+        nix_strict: false,
+        nix_timeout: None,
+        nix_max_memory_mb: None,
+    },
+    // Problems panes of editors show messages verbatim, and settings files
+    // of VS Code are tolerated as the editor itself reads them
+    Profile {
+        name: "vscode",
+        color: Some(false),
+        jsonc_strict: false,
+        nix_strict: false,
+        nix_timeout: Some(10.0),
+        nix_max_memory_mb: None,
+    },
+    Profile {
+        name: "nix-strict",
+        color: None,
+        jsonc_strict: false,
+        nix_strict: true,
+        nix_timeout: Some(60.0),
+        nix_max_memory_mb: Some(2048),
+    },
+    // Logs of CI jobs are read as plain text, and nothing gets tolerated
+    Profile {
+        name: "ci",
+        color: Some(false),
+        jsonc_strict: true,
+        nix_strict: true,
+        nix_timeout: Some(300.0),
+        nix_max_memory_mb: Some(4096),
+    },
+];
+
+/// The index of the profile selected from Python
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// The profile selected by `set_profile`
+pub fn current() -> &'static Profile {
+    &PROFILES[CURRENT.load(Ordering::Relaxed)]
+}
+
+fn find(name: &str) -> PyResult<usize> {
+    PROFILES.iter().position(|p| p.name == name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "Unknown profile `{}`, expected one of `{}`",
+            name,
+            PROFILES
+                .iter()
+                .map(|p| p.name)
+                .collect::<Vec<_>>()
+                .join("`, `")
+        ))
+    })
+}
+
+impl TryToPyObject for Profile {
+    fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let jsonc = PyDict::new(py);
+        jsonc.set_item("strict", self.jsonc_strict)?;
+        let nix = PyDict::new(py);
+        nix.set_item("strict", self.nix_strict)?;
+        nix.set_item("timeout", self.nix_timeout)?;
+        nix.set_item("max_memory_mb", self.nix_max_memory_mb)?;
+
+        let profile = PyDict::new(py);
+        profile.set_item("name", self.name)?;
+        profile.set_item("color", self.color)?;
+        profile.set_item("jsonc", jsonc)?;
+        profile.set_item("nix", nix)?;
+        Ok(profile.into_any().unbind())
+    }
+}
+
+/// Get the settings of a profile of the parsers.
+///
+/// Profiles bundle the strictness, the coloring of error messages and the
+/// resource limits of evaluations under a name, so scripts of a team parse
+/// documents the same way. The settings of the `jsonc` and `nix` entries are
+/// keyword arguments of the functions of these modules, to select a profile
+/// for a single call, while `set_profile` selects it for all the calls.
+///
+/// The profiles are:
+///   - `default`: The defaults of the arguments of the parsers.
+///   - `vscode`: Plain error messages for the problems panes of editors, and
+///               evaluations timing out after 10 seconds.
+///   - `nix-strict`: Nix warnings turned into errors, and evaluations
+///                   limited to 60 seconds and 2 GiB.
+///   - `ci`: Plain error messages, strict parsers, and evaluations limited
+///           to 5 minutes and 4 GiB.
+///
+/// Strictness applies to the `jsonc` loaders and to `nix` evaluations only.
+/// Manifests have no strict mode, as TOML itself rejects the duplicate keys
+/// and the integers overflowing 64 bits that strict loaders reject, and none
+/// of the loaders reads YAML.
+///
+/// Args:
+///   - name (str | None): The name of the profile, the selected one if
+///                        `None`.
+///
+/// Returns:
+///   - _Profile: The `name` of the profile, whether it `color`s messages,
+///               `None` leaving it to `NO_COLOR`, and its `jsonc` and `nix`
+///               arguments.
+///
+/// Raises:
+///   - ValueError: If no profile has that name.
+///
+/// Example:
+/// ```python
+/// >>> profile("nix-strict")["nix"]
+/// {'strict': True, 'timeout': 60.0, 'max_memory_mb': 2048}
+/// >>> nix.evals("let unused = 1; in 2", **profile("nix-strict")["nix"])
+/// Traceback (most recent call last):
+/// ...
+/// EvaluationError: error: evaluation emitted warnings in strict mode
+/// ```
+#[pyfunction]
+#[pyo3(signature = (name = None))]
+pub fn profile(py: Python<'_>, name: Option<&str>) -> PyResult<PyObject> {
+    let index = match name {
+        Some(name) => find(name)?,
+        None => CURRENT.load(Ordering::Relaxed),
+    };
+    PROFILES[index].try_to_pyobject(py)
+}
+
+/// Select a profile of the parsers for all the later calls, see `profile`.
+///
+/// Arguments left unset take the values of the profile, so `nix.evals(expr)`
+/// is strict under `nix-strict` but `nix.evals(expr, strict=False)` is not.
+/// The coloring of error messages is set as by `set_color` too. The profile
+/// applies to all the parsers, on all threads.
+///
+/// Args:
+///   - name (str | None): The name of the profile, `None` restores
+///                        `default`.
+///
+/// Raises:
+///   - ValueError: If no profile has that name.
+#[pyfunction]
+#[pyo3(signature = (name = None))]
+pub fn set_profile(name: Option<&str>) -> PyResult<()> {
+    let index = find(name.unwrap_or("default"))?;
+    CURRENT.store(index, Ordering::Relaxed);
+    set_color(PROFILES[index].color);
+    Ok(())
+}
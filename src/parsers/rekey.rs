@@ -0,0 +1,192 @@
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple};
+use pyo3::{PyObject, PyResult};
+
+use crate::into_pyany;
+
+/// A naming convention of keys
+#[derive(Clone, Copy)]
+enum Style {
+    Snake,
+    Camel,
+    Kebab,
+}
+
+impl Style {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "snake" => Ok(Style::Snake),
+            "camel" => Ok(Style::Camel),
+            "kebab" => Ok(Style::Kebab),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown style `{}`, expected `snake`, `camel` or `kebab`",
+                name
+            ))),
+        }
+    }
+}
+
+/// Split a name into its words, at separators and at changes of case, so
+/// `HTTPServer_port-v2` gives `HTTP`, `Server`, `port` and `v2`
+pub(super) fn words(name: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for part in name.split(['_', '-', ' ', '.']).filter(|p| !p.is_empty()) {
+        let chars = part.char_indices().collect::<Vec<_>>();
+        let mut start = 0;
+        for (i, &(at, c)) in chars.iter().enumerate().skip(1) {
+            let previous = chars[i - 1].1;
+            let next = chars.get(i + 1).map(|(_, c)| *c);
+            // `aB` starts a word, and so does the `S` of `HTTPServer`
+            let boundary = c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase()
+                        && next.is_some_and(char::is_lowercase)));
+            if boundary {
+                words.push(&part[start..at]);
+                start = at;
+            }
+        }
+        words.push(&part[start..]);
+    }
+    words
+}
+
+/// Convert a name to a style, keeping its leading and trailing underscores
+/// like those of `_type` or `__functor`
+fn rekeyed(name: &str, style: Style) -> String {
+    let core = name.trim_matches('_');
+    if core.is_empty() {
+        return name.to_string();
+    }
+    let leading = &name[..name.len() - name.trim_start_matches('_').len()];
+    let trailing = &name[name.trim_end_matches('_').len()..];
+    let words = words(core);
+    let converted = match style {
+        Style::Snake | Style::Kebab => {
+            let separator = if let Style::Snake = style { "_" } else { "-" };
+            words
+                .iter()
+                .map(|w| w.to_lowercase())
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+        Style::Camel => words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let lower = word.to_lowercase();
+                let mut chars = lower.chars();
+                match chars.next() {
+                    Some(first) if i > 0 => {
+                        first.to_uppercase().chain(chars).collect()
+                    }
+                    _ => lower,
+                }
+            })
+            .collect(),
+    };
+    format!("{}{}{}", leading, converted, trailing)
+}
+
+fn rekey_value(
+    py: Python<'_>,
+    obj: &Bound<'_, PyAny>,
+    style: Style,
+    deep: bool,
+) -> PyResult<PyObject> {
+    let nested = |value: &Bound<'_, PyAny>| match deep {
+        true => rekey_value(py, value, style, deep),
+        false => Ok(value.clone().unbind()),
+    };
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        let rekeyed_dict = PyDict::new(py);
+        // The original key of each new one, to report collisions
+        let originals = PyDict::new(py);
+        for (key, value) in dict.iter() {
+            let new_key = match key.downcast::<PyString>() {
+                Ok(name) => into_pyany!(PyString::new(
+                    py,
+                    &rekeyed(name.to_str()?, style)
+                )),
+                Err(_) => key.clone().unbind(),
+            };
+            if let Some(original) = originals.get_item(&new_key)? {
+                return Err(PyValueError::new_err(format!(
+                    "The keys {} and {} both become {}",
+                    original.repr()?,
+                    key.repr()?,
+                    new_key.bind(py).repr()?
+                )));
+            }
+            originals.set_item(&new_key, &key)?;
+            rekeyed_dict.set_item(new_key, nested(&value)?)?;
+        }
+        Ok(into_pyany!(rekeyed_dict))
+    } else if let Ok(list) = obj.downcast::<PyList>() {
+        let items = list
+            .iter()
+            .map(|item| nested(&item))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(into_pyany!(PyList::new(py, items)?))
+    } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        let items = tuple
+            .iter()
+            .map(|item| nested(&item))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(into_pyany!(PyTuple::new(py, items)?))
+    } else {
+        Ok(obj.clone().unbind())
+    }
+}
+
+/// Convert the keys of a mapping to a naming convention, for bridging nix
+/// attrsets and the configuration files of tools using another one.
+///
+/// Keys are split into words at `_`, `-`, spaces and dots, and where the
+/// case changes, so `HTTPServer` is made of `HTTP` and `Server`. Leading and
+/// trailing underscores are kept, so `_type` stays `_type` in every style.
+/// Keys that are not strings are kept as is.
+///
+/// Args:
+///   - value (dict): The mapping whose keys are converted, it is not
+///                   modified.
+///   - style (str): The naming convention, `snake` for `max_connections`,
+///                  `camel` for `maxConnections` or `kebab` for
+///                  `max-connections`.
+///   - deep (bool): Convert the keys of the nested mappings too, including
+///                  those in lists.
+///
+/// Returns:
+///   - dict: A copy of the mapping with converted keys.
+///
+/// Raises:
+///   - TypeError: If the value is not a dict.
+///   - ValueError: If the style is unknown, or two keys of a mapping are
+///                 converted to the same key.
+///
+/// Example:
+/// ```python
+/// >>> rekey({"maxConnections": 1, "TLSConfig": {"certFile": "a"}})
+/// {'max_connections': 1, 'tls_config': {'cert_file': 'a'}}
+/// >>> rekey({"max_connections": 1}, style="kebab")
+/// {'max-connections': 1}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (value, style = "snake", deep = true))]
+pub fn rekey(
+    py: Python<'_>,
+    value: &Bound<'_, PyAny>,
+    style: &str,
+    deep: bool,
+) -> PyResult<PyObject> {
+    let style = Style::of_name(style)?;
+    if !value.is_instance_of::<PyDict>() {
+        return Err(PyTypeError::new_err(format!(
+            "Expected a dict, got {}",
+            value.get_type()
+        )));
+    }
+    rekey_value(py, value, style, deep)
+}
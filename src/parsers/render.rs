@@ -0,0 +1,57 @@
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use annotate_snippets::Renderer;
+use pyo3::pyfunction;
+
+const AUTO: u8 = 0;
+const ALWAYS: u8 = 1;
+const NEVER: u8 = 2;
+
+/// Whether error messages are colored, set from Python
+static COLOR: AtomicU8 = AtomicU8::new(AUTO);
+
+/// The renderer of error messages, without ANSI escapes when colors are
+/// disabled, or left to `NO_COLOR` which disables them
+pub fn renderer() -> Renderer {
+    let colored = match COLOR.load(Ordering::Relaxed) {
+        ALWAYS => true,
+        NEVER => false,
+        _ => env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+    };
+    if colored {
+        Renderer::styled()
+    } else {
+        Renderer::plain()
+    }
+}
+
+/// Choose whether error messages are colored with ANSI escapes.
+///
+/// Messages are rendered when the errors are raised, so the choice applies
+/// to every later error of all the parsers, on all threads.
+///
+/// Args:
+///   - enabled (bool | None): Whether to color messages. `None` restores the
+///                            default, coloring them unless the `NO_COLOR`
+///                            environment variable is set to a non-empty
+///                            value.
+///
+/// Example:
+/// ```python
+/// >>> set_color(False)
+/// >>> nix.evals("1 +")
+/// Traceback (most recent call last):
+/// ...
+/// ParseError: error: failed to parse Nix code
+/// ```
+#[pyfunction]
+#[pyo3(signature = (enabled = None))]
+pub fn set_color(enabled: Option<bool>) {
+    let color = match enabled {
+        None => AUTO,
+        Some(true) => ALWAYS,
+        Some(false) => NEVER,
+    };
+    COLOR.store(color, Ordering::Relaxed);
+}
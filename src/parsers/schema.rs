@@ -0,0 +1,197 @@
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple};
+use pyo3::{PyObject, PyResult};
+
+/// Whether a schema node declares a nix option rather than a group of
+/// options, like the result of `lib.mkOption`
+pub fn is_option(node: &Bound<'_, PyDict>) -> PyResult<bool> {
+    Ok(match node.get_item("_type")? {
+        Some(kind) => kind.extract::<String>().is_ok_and(|k| k == "option"),
+        None => false,
+    })
+}
+
+/// Format a path of keys the way it is written as a nix attribute path
+pub fn attrpath(path: &[String]) -> String {
+    path.iter()
+        .map(|name| {
+            let is_identifier = name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-')
+                });
+            if is_identifier {
+                name.clone()
+            } else {
+                format!("{:?}", name)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The dialect a schema is written in
+#[derive(Clone, Copy)]
+pub enum Dialect {
+    /// JSON Schema, objects declare their keys under `properties`
+    JsonSchema,
+    /// Nix options, every attrset that is not an option is a group of options
+    NixOptions,
+}
+
+impl Dialect {
+    pub fn detect(schema: &Bound<'_, PyDict>) -> PyResult<Self> {
+        for key in ["$schema", "properties", "type"] {
+            if schema.contains(key)? {
+                return Ok(Dialect::JsonSchema);
+            }
+        }
+        Ok(Dialect::NixOptions)
+    }
+
+    /// The declared default of a schema node
+    fn default<'py>(
+        self,
+        node: &Bound<'py, PyDict>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        match self {
+            Dialect::NixOptions if !is_option(node)? => Ok(None),
+            _ => node.get_item("default"),
+        }
+    }
+
+    /// The keys declared by a schema node, with their own schema
+    pub fn children<'py>(
+        self,
+        node: &Bound<'py, PyDict>,
+    ) -> PyResult<Vec<(String, Bound<'py, PyDict>)>> {
+        let declared = match self {
+            Dialect::JsonSchema => match node.get_item("properties")? {
+                Some(properties) => {
+                    properties.downcast_into::<PyDict>().map_err(|_| {
+                        PyTypeError::new_err(
+                            "`properties` of a JSON Schema must be a dict",
+                        )
+                    })?
+                }
+                None => return Ok(Vec::new()),
+            },
+            Dialect::NixOptions if is_option(node)? => return Ok(Vec::new()),
+            Dialect::NixOptions => node.clone(),
+        };
+
+        let mut children = Vec::with_capacity(declared.len());
+        for (name, child) in declared.iter() {
+            // Skip metadata such as `_type` or `description` of nix options
+            if let Ok(child) = child.downcast_into::<PyDict>() {
+                children.push((name.extract::<String>()?, child));
+            }
+        }
+        Ok(children)
+    }
+}
+
+/// Fills missing keys of values with the defaults declared by a schema
+struct Filler<'py> {
+    py: Python<'py>,
+    dialect: Dialect,
+    deepcopy: Bound<'py, PyAny>,
+    path: Vec<String>,
+    filled: Vec<String>,
+}
+
+impl<'py> Filler<'py> {
+    /// Return a copy of `value` with its missing keys filled, the original
+    /// value is left untouched
+    fn fill(
+        &mut self,
+        value: &Bound<'py, PyDict>,
+        schema: &Bound<'py, PyDict>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let completed = value.copy()?;
+        for (name, node) in self.dialect.children(schema)? {
+            self.path.push(name.clone());
+            match completed.get_item(&name)? {
+                Some(existing) => {
+                    if let Ok(existing) = existing.downcast::<PyDict>() {
+                        completed
+                            .set_item(&name, self.fill(existing, &node)?)?;
+                    }
+                }
+                None => {
+                    if let Some(default) = self.dialect.default(&node)? {
+                        // Copy the default, so the filled value does not
+                        // share mutable objects with the schema
+                        let default = self.deepcopy.call1((default,))?;
+                        completed.set_item(&name, default)?;
+                        self.filled.push(attrpath(&self.path));
+                    } else {
+                        // Only create the missing object if some of its keys
+                        // have defaults
+                        let nested = self.fill(&PyDict::new(self.py), &node)?;
+                        if !nested.is_empty() {
+                            completed.set_item(&name, nested)?;
+                        }
+                    }
+                }
+            }
+            self.path.pop();
+        }
+        Ok(completed)
+    }
+}
+
+/// Fill the keys missing from a value with the defaults declared by a schema.
+///
+/// The schema is either a JSON Schema, whose objects declare their keys under
+/// `properties`, or a nix options schema, whose options are attrsets with
+/// `_type = "option"` and every other attrset is a group of options. Missing
+/// objects are created when some of their keys have a default.
+///
+/// Args:
+///   - value (dict): The value to complete, it is not modified.
+///   - schema (dict): The schema declaring the defaults.
+///
+/// Returns:
+///   - tuple[dict, list[str]]: The completed value, and the paths of the keys
+///                             that were filled, written like nix attribute
+///                             paths.
+///
+/// Raises:
+///   - TypeError: If the `properties` of a JSON Schema are not a dict.
+///
+/// Example:
+/// ```python
+/// >>> schema = {
+/// ...     "type": "object",
+/// ...     "properties": {
+/// ...         "port": {"type": "integer", "default": 8080},
+/// ...         "tls": {"properties": {"enable": {"default": False}}},
+/// ...     },
+/// ... }
+/// >>> apply_defaults({"port": 80}, schema)
+/// ({'port': 80, 'tls': {'enable': False}}, ['tls.enable'])
+/// ```
+#[pyfunction]
+pub fn apply_defaults<'py>(
+    py: Python<'py>,
+    value: Bound<'py, PyDict>,
+    schema: Bound<'py, PyDict>,
+) -> PyResult<PyObject> {
+    let mut filler = Filler {
+        py,
+        dialect: Dialect::detect(&schema)?,
+        deepcopy: py.import("copy")?.getattr("deepcopy")?,
+        path: Vec::new(),
+        filled: Vec::new(),
+    };
+    let completed = filler.fill(&value, &schema)?;
+    let filled = filler.filled.iter().map(|p| PyString::new(py, p));
+    let filled = PyList::new(py, filled)?;
+    Ok(PyTuple::new(py, [completed.into_any(), filled.into_any()])?
+        .into_any()
+        .unbind())
+}
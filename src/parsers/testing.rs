@@ -0,0 +1,365 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyAssertionError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::PyResult;
+
+use crate::parsers::formats::load;
+use crate::parsers::hash::encode;
+use crate::parsers::schema::attrpath;
+
+/// The most differences listed by a failed assertion
+const MAX_DIFFERENCES: usize = 50;
+
+/// The longest value shown in a difference, in characters
+const MAX_SHOWN: usize = 60;
+
+/// How documents are normalized before they are compared
+#[derive(Default)]
+struct Normalize {
+    /// The paths removed from both documents, split at their dots
+    ignore: Vec<Vec<String>>,
+    sort_lists: bool,
+    strip_strings: bool,
+    /// The relative tolerance numbers are compared with
+    rel_tol: f64,
+}
+
+impl Normalize {
+    fn of_options(options: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let mut normalize = Normalize::default();
+        for (name, value) in options.iter() {
+            match name.extract::<String>()?.as_str() {
+                "ignore" => {
+                    normalize.ignore = value
+                        .extract::<Vec<String>>()?
+                        .iter()
+                        .map(|p| p.split('.').map(str::to_string).collect())
+                        .collect();
+                }
+                "sort_lists" => normalize.sort_lists = value.extract()?,
+                "strip_strings" => normalize.strip_strings = value.extract()?,
+                "rel_tol" => {
+                    normalize.rel_tol = value.extract()?;
+                    if normalize.rel_tol.is_nan() || normalize.rel_tol < 0.0 {
+                        return Err(PyValueError::new_err(
+                            "The relative tolerance `rel_tol` must be a \
+                             non-negative number",
+                        ));
+                    }
+                }
+                name => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown normalization `{}`, expected `ignore`, \
+                         `sort_lists`, `strip_strings` or `rel_tol`",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(normalize)
+    }
+
+    /// Whether the value at `path` is removed, `*` matching any key or index
+    fn ignores(&self, path: &[String]) -> bool {
+        self.ignore.iter().any(|pattern| {
+            pattern.len() == path.len()
+                && pattern.iter().zip(path).all(|(p, k)| p == "*" || p == k)
+        })
+    }
+
+    /// A normalized copy of a document, lists and tuples becoming lists
+    fn apply<'py>(
+        &self,
+        value: &Bound<'py, PyAny>,
+        path: &mut Vec<String>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let py = value.py();
+        if let Ok(dict) = value.downcast::<PyDict>() {
+            let normalized = PyDict::new(py);
+            for (key, item) in dict.iter() {
+                path.push(key.str()?.to_string());
+                if !self.ignores(path) {
+                    normalized.set_item(&key, self.apply(&item, path)?)?;
+                }
+                path.pop();
+            }
+            Ok(normalized.into_any())
+        } else if value.is_instance_of::<PyList>()
+            || value.is_instance_of::<PyTuple>()
+        {
+            let mut items = Vec::new();
+            for (i, item) in value.try_iter()?.enumerate() {
+                path.push(i.to_string());
+                if !self.ignores(path) {
+                    items.push(self.apply(&item?, path)?);
+                }
+                path.pop();
+            }
+            if self.sort_lists {
+                // Items of any types are ordered by their canonical form
+                let mut keyed = items
+                    .into_iter()
+                    .map(|item| {
+                        let mut key = Vec::new();
+                        encode(&item, &mut key)?;
+                        Ok((key, item))
+                    })
+                    .collect::<PyResult<Vec<_>>>()?;
+                keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+                items = keyed.into_iter().map(|(_, item)| item).collect();
+            }
+            Ok(PyList::new(py, items)?.into_any())
+        } else if self.strip_strings && value.is_instance_of::<PyString>() {
+            let s = value.downcast::<PyString>()?.to_str()?;
+            Ok(PyString::new(py, s.trim()).into_any())
+        } else {
+            Ok(value.clone())
+        }
+    }
+}
+
+/// A step of the path to a value in a document
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Format the path to a value, like `users.alice.groups[0]`
+fn location(path: &[Step]) -> String {
+    if path.is_empty() {
+        return "the document".to_string();
+    }
+    let mut location = String::new();
+    for step in path {
+        match step {
+            Step::Key(key) => {
+                if !location.is_empty() {
+                    location.push('.');
+                }
+                location.push_str(&attrpath(std::slice::from_ref(key)));
+            }
+            Step::Index(i) => location.push_str(&format!("[{}]", i)),
+        }
+    }
+    format!("`{}`", location)
+}
+
+/// The repr of a value, shortened to fit in a line
+fn shown(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    let repr = value.repr()?.to_string();
+    if repr.chars().count() <= MAX_SHOWN {
+        return Ok(repr);
+    }
+    let shortened = repr.chars().take(MAX_SHOWN - 3).collect::<String>();
+    Ok(format!("{}...", shortened))
+}
+
+/// Whether two numbers are equal up to a relative tolerance, booleans not
+/// counting as numbers
+fn close(a: &Bound<'_, PyAny>, b: &Bound<'_, PyAny>, rel_tol: f64) -> bool {
+    let number = |v: &Bound<'_, PyAny>| {
+        let is_number = !v.is_instance_of::<PyBool>()
+            && (v.is_instance_of::<PyInt>() || v.is_instance_of::<PyFloat>());
+        if is_number {
+            v.extract::<f64>().ok()
+        } else {
+            None
+        }
+    };
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => (a - b).abs() <= rel_tol * a.abs().max(b.abs()),
+        _ => false,
+    }
+}
+
+/// Collect the differences between two normalized documents
+fn compare(
+    expected: &Bound<'_, PyAny>,
+    actual: &Bound<'_, PyAny>,
+    rel_tol: f64,
+    path: &mut Vec<Step>,
+    differences: &mut Vec<String>,
+) -> PyResult<()> {
+    if let (Ok(expected), Ok(actual)) =
+        (expected.downcast::<PyDict>(), actual.downcast::<PyDict>())
+    {
+        for (key, value) in expected.iter() {
+            path.push(Step::Key(key.str()?.to_string()));
+            match actual.get_item(&key)? {
+                Some(other) => {
+                    compare(&value, &other, rel_tol, path, differences)?
+                }
+                None => differences.push(format!(
+                    "{}: missing, expected {}",
+                    location(path),
+                    shown(&value)?
+                )),
+            }
+            path.pop();
+        }
+        for (key, value) in actual.iter() {
+            if !expected.contains(&key)? {
+                path.push(Step::Key(key.str()?.to_string()));
+                differences.push(format!(
+                    "{}: unexpected {}",
+                    location(path),
+                    shown(&value)?
+                ));
+                path.pop();
+            }
+        }
+        return Ok(());
+    }
+    if let (Ok(expected), Ok(actual)) =
+        (expected.downcast::<PyList>(), actual.downcast::<PyList>())
+    {
+        let len = expected.len().max(actual.len());
+        for i in 0..len {
+            path.push(Step::Index(i));
+            match (expected.get_item(i).ok(), actual.get_item(i).ok()) {
+                (Some(a), Some(b)) => {
+                    compare(&a, &b, rel_tol, path, differences)?
+                }
+                (Some(a), None) => differences.push(format!(
+                    "{}: missing, expected {}",
+                    location(path),
+                    shown(&a)?
+                )),
+                (None, Some(b)) => differences.push(format!(
+                    "{}: unexpected {}",
+                    location(path),
+                    shown(&b)?
+                )),
+                (None, None) => {}
+            }
+            path.pop();
+        }
+        return Ok(());
+    }
+
+    // `True == 1` in Python, but not in the documents
+    let same = expected.is_instance_of::<PyBool>()
+        == actual.is_instance_of::<PyBool>()
+        && (expected.eq(actual)? || close(expected, actual, rel_tol));
+    if !same {
+        differences.push(format!(
+            "{}: expected {}, got {}",
+            location(path),
+            shown(expected)?,
+            shown(actual)?
+        ));
+    }
+    Ok(())
+}
+
+/// Assert that a document matches a golden file, for snapshot tests.
+///
+/// Both files are loaded with the parser of their extension, so a nix file
+/// can be checked against the JSON it should evaluate to, and normalized
+/// before they are compared. On mismatch, the assertion lists where the
+/// documents differ, by the path of each value, instead of showing both
+/// documents. The normalizations are:
+///   - `ignore` (list[str]): The dotted paths of values to remove, `*`
+///                           matching any key or list index.
+///   - `sort_lists` (bool): Compare lists regardless of the order of their
+///                          items.
+///   - `strip_strings` (bool): Strip the leading and trailing whitespace of
+///                             strings.
+///   - `rel_tol` (float): The relative tolerance numbers are compared with.
+///
+/// Args:
+///   - path (str | os.PathLike): The document under test.
+///   - golden_path (str | os.PathLike): The golden file the document is
+///                                      expected to match.
+///   - normalize (dict | Callable): The normalizations applied to both
+///                                  documents by name, or a function
+///                                  normalizing each document instead.
+///
+/// Raises:
+///   - AssertionError: If the documents do not match.
+///   - ValueError: If a normalization is unknown, or no parser handles the
+///                 extension of a file.
+///   - TypeError: If `normalize` is neither a dict nor callable, or lists
+///                to sort contain values of other types than the parsers
+///                return.
+///   - IOError, ParseError, EvaluationError: If a file cannot be loaded.
+///
+/// Example:
+/// ```python
+/// >>> assert_matches("hosts/laptop.nix", "golden/laptop.json")
+/// Traceback (most recent call last):
+///   ...
+/// AssertionError: hosts/laptop.nix does not match golden/laptop.json, 2 differences:
+///   - `networking.hostName`: expected 'laptop', got 'desktop'
+///   - `users.bob`: unexpected {'isNormalUser': True}
+/// >>> assert_matches(
+/// ...     "hosts/laptop.nix",
+/// ...     "golden/laptop.json",
+/// ...     normalize={"ignore": ["system.build", "users.*.uid"]},
+/// ... )
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, golden_path, normalize = None))]
+pub fn assert_matches(
+    py: Python<'_>,
+    path: PathBuf,
+    golden_path: PathBuf,
+    normalize: Option<&Bound<'_, PyAny>>,
+) -> PyResult<()> {
+    let actual = load(py, &path)?.into_bound(py);
+    let expected = load(py, &golden_path)?.into_bound(py);
+    let (actual, expected, rel_tol) = match normalize {
+        None => (actual, expected, 0.0),
+        Some(options) if options.is_instance_of::<PyDict>() => {
+            let normalize = Normalize::of_options(options.downcast()?)?;
+            (
+                normalize.apply(&actual, &mut Vec::new())?,
+                normalize.apply(&expected, &mut Vec::new())?,
+                normalize.rel_tol,
+            )
+        }
+        Some(function) if function.is_callable() => (
+            function.call1((actual,))?,
+            function.call1((expected,))?,
+            0.0,
+        ),
+        Some(other) => {
+            return Err(PyTypeError::new_err(format!(
+                "Cannot normalize documents with python type {}, expected a \
+                 dict of normalizations or a function",
+                other.get_type().name()?
+            )))
+        }
+    };
+
+    let mut differences = Vec::new();
+    compare(
+        &expected,
+        &actual,
+        rel_tol,
+        &mut Vec::new(),
+        &mut differences,
+    )?;
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!(
+        "{} does not match {}, {} difference{}:",
+        path.display(),
+        golden_path.display(),
+        differences.len(),
+        if differences.len() == 1 { "" } else { "s" }
+    );
+    for difference in differences.iter().take(MAX_DIFFERENCES) {
+        message.push_str(&format!("\n  - {}", difference));
+    }
+    if differences.len() > MAX_DIFFERENCES {
+        message.push_str(&format!(
+            "\n  ... and {} more",
+            differences.len() - MAX_DIFFERENCES
+        ));
+    }
+    Err(PyAssertionError::new_err(message))
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::PyResult;
+
+/// The number of threads of the batch operations set from Python, `0` for
+/// the default
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of threads of the batch operations when none is set, from
+/// `COSUTILS_THREADS` or else the number of CPUs available to the process
+fn default_threads() -> usize {
+    env::var("COSUTILS_THREADS")
+        .ok()
+        .and_then(|n| n.trim().parse::<NonZeroUsize>().ok())
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+}
+
+fn checked(threads: usize) -> PyResult<usize> {
+    if threads == 0 {
+        return Err(PyValueError::new_err(
+            "The number of threads must be positive, 1 disables parallelism",
+        ));
+    }
+    Ok(threads)
+}
+
+/// The number of threads a batch operation runs on, overridden by the
+/// `jobs` argument of the call if given
+pub fn threads(jobs: Option<usize>) -> PyResult<usize> {
+    match jobs {
+        Some(jobs) => checked(jobs),
+        None => match THREADS.load(Ordering::Relaxed) {
+            0 => Ok(default_threads()),
+            threads => Ok(threads),
+        },
+    }
+}
+
+/// Apply `f` to each item on up to `threads` threads, keeping the order of
+/// the items. The GIL is released meanwhile, so `f` must acquire it to
+/// touch Python objects. Each thread takes the next item left, so slow
+/// items do not hold back the others.
+pub fn map<T, R, F>(
+    py: Python<'_>,
+    items: &[T],
+    threads: usize,
+    f: F,
+) -> Vec<PyResult<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> PyResult<R> + Sync,
+{
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(
+        std::iter::repeat_with(|| None)
+            .take(items.len())
+            .collect::<Vec<_>>(),
+    );
+    py.allow_threads(|| {
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    let result = f(item);
+                    // A poisoned lock only means another item panicked
+                    let mut results =
+                        results.lock().unwrap_or_else(|e| e.into_inner());
+                    results[i] = Some(result);
+                });
+            }
+        })
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|r| r.expect("Every item is mapped once the threads joined"))
+        .collect()
+}
+
+/// Choose how many threads the batch operations of the parsers run on.
+///
+/// Batch operations process their items in parallel, each on a single
+/// thread, and take a `jobs` argument overriding this setting for one
+/// call. By default they use the number in the `COSUTILS_THREADS`
+/// environment variable, or else the number of CPUs available to the
+/// process, which constrained environments like CI containers may report
+/// too high. The setting applies to all the batch operations, on all
+/// threads.
+///
+/// Args:
+///   - n (int | None): The number of threads, `1` disabling parallelism so
+///                     every item is processed on the calling thread.
+///                     `None` restores the default.
+///
+/// Raises:
+///   - ValueError: If the number of threads is not positive.
+///
+/// Example:
+/// ```python
+/// >>> set_threads(1)  # Run batch operations on the calling thread only
+/// >>> set_threads(None)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (n = None))]
+pub fn set_threads(n: Option<usize>) -> PyResult<()> {
+    let threads = n.map(checked).transpose()?.unwrap_or(0);
+    THREADS.store(threads, Ordering::Relaxed);
+    Ok(())
+}
@@ -0,0 +1,653 @@
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{
+    IntoPyDict, PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple,
+};
+use pyo3::{PyObject, PyResult};
+
+use crate::into_pyany;
+
+/// The types values can be coerced to
+#[derive(Clone, Copy)]
+enum Type {
+    Str,
+    Int,
+    Float,
+    Bool,
+    /// A `datetime.timedelta`, from seconds or a string like `1h30m`
+    Duration,
+    /// A number of bytes, from a string like `512MiB`
+    Size,
+    /// A `pathlib.Path`, with `~` expanded
+    Path,
+}
+
+impl Type {
+    fn of_name(name: &str) -> PyResult<Self> {
+        match name {
+            "str" => Ok(Type::Str),
+            "int" => Ok(Type::Int),
+            "float" => Ok(Type::Float),
+            "bool" | "bool-ish" => Ok(Type::Bool),
+            "duration" => Ok(Type::Duration),
+            "size" => Ok(Type::Size),
+            "path" => Ok(Type::Path),
+            name => Err(PyValueError::new_err(format!(
+                "Unknown type `{}`, expected `str`, `int`, `float`, `bool`, \
+                 `duration`, `size` or `path`",
+                name
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Str => "str",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Duration => "duration",
+            Type::Size => "size",
+            Type::Path => "path",
+        }
+    }
+
+    /// Convert a scalar to this type, `None` if it has no such form
+    fn coerce(
+        self,
+        py: Python<'_>,
+        obj: &Bound<'_, PyAny>,
+    ) -> PyResult<Option<PyObject>> {
+        let text = obj
+            .downcast::<PyString>()
+            .ok()
+            .map(|s| s.to_str().map(str::trim))
+            .transpose()?;
+        let is_bool = obj.is_instance_of::<PyBool>();
+        let is_int = obj.is_instance_of::<PyInt>() && !is_bool;
+        let is_float = obj.is_instance_of::<PyFloat>();
+        let coerced = match self {
+            Type::Str if text.is_some() => Some(obj.clone().unbind()),
+            Type::Str if is_bool => {
+                let b = obj.extract::<bool>()?;
+                Some(into_pyany!(PyString::new(py, &b.to_string())))
+            }
+            Type::Str if is_int || is_float => Some(into_pyany!(obj.str()?)),
+            Type::Int if is_int => Some(obj.clone().unbind()),
+            Type::Int if is_float => {
+                let f = obj.extract::<f64>()?;
+                match f.fract() == 0.0 {
+                    true => Some(obj.call_method0("__int__")?.unbind()),
+                    false => None,
+                }
+            }
+            Type::Int => match text {
+                // Parsed by Python, so integers of any size are kept
+                Some(text) => py
+                    .get_type::<PyInt>()
+                    .call1((text,))
+                    .ok()
+                    .map(Bound::unbind),
+                None => None,
+            },
+            Type::Float if is_float => Some(obj.clone().unbind()),
+            Type::Float if is_int => {
+                Some(into_pyany!(PyFloat::new(py, obj.extract::<f64>()?)))
+            }
+            Type::Float => text
+                .and_then(|t| t.parse::<f64>().ok())
+                .map(|f| into_pyany!(PyFloat::new(py, f))),
+            Type::Bool if is_bool => Some(obj.clone().unbind()),
+            Type::Bool => {
+                let b = match text.map(str::to_ascii_lowercase).as_deref() {
+                    Some("true" | "yes" | "on" | "1") => Some(true),
+                    Some("false" | "no" | "off" | "0") => Some(false),
+                    Some(_) => None,
+                    None if is_int => match obj.extract::<i64>() {
+                        Ok(1) => Some(true),
+                        Ok(0) => Some(false),
+                        _ => None,
+                    },
+                    None => None,
+                };
+                b.map(|b| into_pyany!(PyBool::new(py, b)))
+            }
+            Type::Duration => {
+                let seconds = match text {
+                    Some(text) => seconds(text),
+                    None if is_int || is_float => Some(obj.extract::<f64>()?),
+                    None => None,
+                };
+                match seconds {
+                    Some(seconds) => {
+                        let timedelta =
+                            py.import("datetime")?.getattr("timedelta")?;
+                        let kwargs = [("seconds", seconds)].into_py_dict(py)?;
+                        Some(timedelta.call((), Some(&kwargs))?.unbind())
+                    }
+                    None => None,
+                }
+            }
+            Type::Size if is_int => Some(obj.clone().unbind()),
+            Type::Size => text
+                .and_then(bytes)
+                .map(|b| b.into_pyobject(py).map(|b| b.into_any().unbind()))
+                .transpose()?,
+            Type::Path => match text {
+                Some(_) => {
+                    let path = py.import("pathlib")?.getattr("Path")?;
+                    Some(
+                        path.call1((obj,))?
+                            .call_method0("expanduser")?
+                            .unbind(),
+                    )
+                }
+                None => None,
+            },
+            _ => None,
+        };
+        Ok(coerced)
+    }
+}
+
+/// Split a quantity like `1.5GiB` into its number and its unit
+fn quantity(text: &str) -> Option<(&str, &str)> {
+    let unit = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    if unit == 0 {
+        return None;
+    }
+    Some((&text[..unit], text[unit..].trim_start()))
+}
+
+/// Parse a duration like `90`, `1h30m` or `2d 12h` to seconds, bare numbers
+/// being seconds
+fn seconds(text: &str) -> Option<f64> {
+    if let Ok(seconds) = text.parse::<f64>() {
+        return Some(seconds);
+    }
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (number, tail) = quantity(rest)?;
+        let end = tail
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(tail.len());
+        let scale = match &tail[..end] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            "h" | "hr" | "hour" | "hours" => 3600.0,
+            "d" | "day" | "days" => 86400.0,
+            "w" | "week" | "weeks" => 604800.0,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * scale;
+        rest = tail[end..].trim_start();
+    }
+    Some(total)
+}
+
+/// Parse a size like `512`, `10MB` or `1.5GiB` to bytes. SI units like `kB`
+/// are powers of 1000, IEC units like `KiB` and bare `K`, `M`, `G` and `T`
+/// powers of 1024.
+fn bytes(text: &str) -> Option<u128> {
+    let (number, unit) = quantity(text)?;
+    let scale: u128 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1000,
+        "mb" => 1000_u128.pow(2),
+        "gb" => 1000_u128.pow(3),
+        "tb" => 1000_u128.pow(4),
+        _ => return None,
+    };
+    // Integers are multiplied exactly, fractions are rounded to a byte
+    if let Ok(number) = number.parse::<u128>() {
+        return number.checked_mul(scale);
+    }
+    let bytes = (number.parse::<f64>().ok()? * scale as f64).round();
+    (bytes.is_finite() && bytes < u128::MAX as f64).then_some(bytes as u128)
+}
+
+/// A segment of a path a value is found at, a key or a list index
+enum Segment {
+    Key(PyObject),
+    Index(usize),
+}
+
+/// Applies the rules of `transform` to every node of a value
+struct Transformer<'py> {
+    py: Python<'py>,
+    visitor: Option<Bound<'py, PyAny>>,
+    rename: HashMap<String, String>,
+    /// The paths of the values to coerce, split on `.`
+    coerce: Vec<(Vec<String>, Type)>,
+    drop_nulls: bool,
+    path: Vec<Segment>,
+}
+
+impl Transformer<'_> {
+    /// The path as written in errors, like `a.b[0]`
+    fn display_path(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                Segment::Key(key) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(&key.bind(self.py).to_string());
+                }
+                Segment::Index(i) => path.push_str(&format!("[{}]", i)),
+            }
+        }
+        path
+    }
+
+    /// The type the value at the current path is coerced to, if any. List
+    /// indices are not part of the paths of the rules, items of a list are
+    /// coerced like the list.
+    fn coerced_type(&self) -> Option<Type> {
+        let keys = self
+            .path
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Key(key) => Some(key),
+                Segment::Index(_) => None,
+            })
+            .collect::<Vec<_>>();
+        self.coerce.iter().find_map(|(pattern, kind)| {
+            let matches = pattern.len() == keys.len()
+                && pattern.iter().zip(&keys).all(|(p, key)| {
+                    p == "*"
+                        || key
+                            .bind(self.py)
+                            .downcast::<PyString>()
+                            .is_ok_and(|k| k.to_str().is_ok_and(|k| k == p))
+                });
+            matches.then_some(*kind)
+        })
+    }
+
+    /// Transform the node at the current path, `None` if it is dropped
+    fn node(&mut self, obj: &Bound<'_, PyAny>) -> PyResult<Option<PyObject>> {
+        let py = self.py;
+        let transformed = if let Ok(dict) = obj.downcast::<PyDict>() {
+            let transformed = PyDict::new(py);
+            for (key, value) in dict.iter() {
+                let key = match key.downcast::<PyString>() {
+                    Ok(name) => match self.rename.get(name.to_str()?) {
+                        Some(renamed) => {
+                            into_pyany!(PyString::new(py, renamed))
+                        }
+                        None => key.unbind(),
+                    },
+                    Err(_) => key.unbind(),
+                };
+                self.path.push(Segment::Key(key.clone_ref(py)));
+                let value = self.node(&value)?;
+                self.path.pop();
+                if let Some(value) = value {
+                    transformed.set_item(key, value)?;
+                }
+            }
+            into_pyany!(transformed)
+        } else if let Ok(list) = obj.downcast::<PyList>() {
+            into_pyany!(PyList::new(py, self.items(list.iter())?)?)
+        } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+            into_pyany!(PyTuple::new(py, self.items(tuple.iter())?)?)
+        } else {
+            match self.coerced_type() {
+                Some(kind) if !obj.is_none() => {
+                    kind.coerce(py, obj)?.ok_or_else(|| {
+                        PyValueError::new_err(format!(
+                            "Cannot coerce {} at `{}` to {}",
+                            obj.repr()
+                                .map(|r| r.to_string())
+                                .unwrap_or_default(),
+                            self.display_path(),
+                            kind.name()
+                        ))
+                    })?
+                }
+                _ => obj.clone().unbind(),
+            }
+        };
+
+        let transformed = match &self.visitor {
+            Some(visitor) => {
+                let path = self
+                    .path
+                    .iter()
+                    .map(|segment| match segment {
+                        Segment::Key(key) => Ok(key.clone_ref(py)),
+                        Segment::Index(i) => {
+                            Ok(into_pyany!(i.into_pyobject(py)?))
+                        }
+                    })
+                    .collect::<PyResult<Vec<PyObject>>>()?;
+                let path = PyTuple::new(py, path)?;
+                visitor.call1((path, transformed))?.unbind()
+            }
+            None => transformed,
+        };
+        if self.drop_nulls && transformed.is_none(py) && !self.path.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(transformed))
+    }
+
+    fn items<'a>(
+        &mut self,
+        items: impl Iterator<Item = Bound<'a, PyAny>>,
+    ) -> PyResult<Vec<PyObject>> {
+        let mut transformed = Vec::new();
+        for (i, item) in items.enumerate() {
+            self.path.push(Segment::Index(i));
+            let item = self.node(&item)?;
+            self.path.pop();
+            transformed.extend(item);
+        }
+        Ok(transformed)
+    }
+}
+
+/// Transform every node of a parsed document, for structural migrations of
+/// configurations.
+///
+/// The rules apply to each node in turn: its keys are renamed, then values
+/// are coerced, then the visitor is called, then nulls are dropped. Nodes are
+/// transformed bottom-up, so the visitor of a dict or a list is given its
+/// transformed items. Dicts, lists and tuples are copied, the value is not
+/// modified.
+///
+/// Args:
+///   - value (Any): The parsed document.
+///   - visitor (Callable | None): Called with the path of each node, as a
+///                                tuple of its keys and list indices, and
+///                                the node, returning the node to put in
+///                                its place.
+///   - rename (dict[str, str]): New names of dict keys, wherever they are
+///                              found.
+///   - coerce (dict[str, str]): The types scalars are converted to, among
+///                              `str`, `int`, `float`, `bool` and the types
+///                              of `coerce`, by their path of keys separated
+///                              by `.`, where `*` matches any key, the most
+///                              specific path winning. Items of lists have
+///                              the path of their list. Strings like `"80"`,
+///                              `"1.5"` or `"yes"` are parsed, and `None` is
+///                              left as is.
+///   - drop_nulls (bool): Remove the dict entries and list items that are
+///                        `None`, once the visitor has been called on them.
+///
+/// Returns:
+///   - Any: The transformed copy of the document.
+///
+/// Raises:
+///   - ValueError: If a rule is malformed, or a value cannot be coerced to
+///                 its type.
+///   - Exception: Whatever the visitor raises.
+///
+/// Example:
+/// ```python
+/// >>> transform(
+/// ...     {"listen": {"port": "80"}, "hosts": ["a", None]},
+/// ...     rename={"listen": "server"},
+/// ...     coerce={"server.port": "int"},
+/// ...     drop_nulls=True,
+/// ... )
+/// {'server': {'port': 80}, 'hosts': ['a']}
+/// >>> transform({"a": [1, 2]}, lambda p, v: v * 10 if len(p) == 2 else v)
+/// {'a': [10, 20]}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    value,
+    visitor = None,
+    rename = None,
+    coerce = None,
+    drop_nulls = false
+))]
+pub fn transform<'py>(
+    py: Python<'py>,
+    value: &Bound<'py, PyAny>,
+    visitor: Option<Bound<'py, PyAny>>,
+    rename: Option<HashMap<String, String>>,
+    coerce: Option<HashMap<String, String>>,
+    drop_nulls: bool,
+) -> PyResult<PyObject> {
+    let mut coerce = coerce
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(path, kind)| {
+            let segments =
+                path.split('.').map(str::to_string).collect::<Vec<_>>();
+            if segments.iter().any(String::is_empty) {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid coerce path `{}`",
+                    path
+                )));
+            }
+            Ok((segments, Type::of_name(&kind)?))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    coerce.sort_by_key(|(p, _)| p.iter().filter(|s| *s == "*").count());
+    let mut transformer = Transformer {
+        py,
+        visitor,
+        rename: rename.unwrap_or_default(),
+        coerce,
+        drop_nulls,
+        path: Vec::new(),
+    };
+    Ok(transformer.node(value)?.unwrap_or_else(|| py.None()))
+}
+
+/// The types a value is coerced to by `coerce`, mirroring its structure
+enum Spec {
+    /// Every leaf below is coerced to the type
+    Leaf(Type),
+    /// The values of a dict by key, the others by the spec of `*` if any.
+    /// Values without a spec are left as is.
+    Keys {
+        keys: HashMap<String, Option<Spec>>,
+        others: Option<Box<Spec>>,
+    },
+    /// Every item of a list or a tuple
+    Items(Box<Spec>),
+}
+
+impl Spec {
+    fn of_pyobject(obj: &Bound<'_, PyAny>, path: &str) -> PyResult<Self> {
+        let invalid = || {
+            PyValueError::new_err(format!(
+                "Invalid spec at `{}`, expected the name of a type, a dict \
+                 of specs or a list of one spec",
+                path
+            ))
+        };
+        let child = |key: &str| match path {
+            "" => key.to_string(),
+            _ => format!("{}.{}", path, key),
+        };
+        if let Ok(name) = obj.downcast::<PyString>() {
+            Ok(Spec::Leaf(Type::of_name(name.to_str()?)?))
+        } else if let Ok(dict) = obj.downcast::<PyDict>() {
+            let mut keys = HashMap::with_capacity(dict.len());
+            let mut others = None;
+            for (key, spec) in dict.iter() {
+                let key = key.extract::<String>().map_err(|_| invalid())?;
+                let spec = match spec.is_none() {
+                    true => None,
+                    false => Some(Spec::of_pyobject(&spec, &child(&key))?),
+                };
+                match (key.as_str(), spec) {
+                    ("*", Some(spec)) => others = Some(Box::new(spec)),
+                    (_, spec) => {
+                        keys.insert(key, spec);
+                    }
+                }
+            }
+            Ok(Spec::Keys { keys, others })
+        } else if let Ok(list) = obj.downcast::<PyList>() {
+            match list.len() {
+                1 => Ok(Spec::Items(Box::new(Spec::of_pyobject(
+                    &list.get_item(0)?,
+                    &format!("{}[]", path),
+                )?))),
+                _ => Err(invalid()),
+            }
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// Coerce the value at `path` by this spec
+    fn apply(
+        &self,
+        py: Python<'_>,
+        obj: &Bound<'_, PyAny>,
+        path: &mut Vec<String>,
+    ) -> PyResult<PyObject> {
+        let mismatch = |expected: &str, path: &[String]| {
+            PyValueError::new_err(format!(
+                "Expected {} at `{}` by the spec, got {}",
+                expected,
+                path.concat(),
+                obj.get_type()
+                    .name()
+                    .map(|n| n.to_string())
+                    .unwrap_or_default()
+            ))
+        };
+        let is_sequence =
+            obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>();
+        match self {
+            _ if obj.is_none() => Ok(obj.clone().unbind()),
+            Spec::Leaf(_) | Spec::Items(_) if is_sequence => {
+                let spec = match self {
+                    Spec::Items(spec) => spec.as_ref(),
+                    leaf => leaf,
+                };
+                let mut items = Vec::new();
+                for (i, item) in obj.try_iter()?.enumerate() {
+                    path.push(format!("[{}]", i));
+                    items.push(spec.apply(py, &item?, path)?);
+                    path.pop();
+                }
+                if obj.is_instance_of::<PyTuple>() {
+                    Ok(into_pyany!(PyTuple::new(py, items)?))
+                } else {
+                    Ok(into_pyany!(PyList::new(py, items)?))
+                }
+            }
+            Spec::Items(_) => Err(mismatch("a list", path)),
+            Spec::Leaf(_) | Spec::Keys { .. } => {
+                if let Ok(dict) = obj.downcast::<PyDict>() {
+                    let coerced = PyDict::new(py);
+                    for (key, value) in dict.iter() {
+                        let name = key.str()?.to_string();
+                        let spec = match self {
+                            Spec::Keys { keys, others } => {
+                                match keys.get(&name) {
+                                    Some(spec) => spec.as_ref(),
+                                    None => others.as_deref(),
+                                }
+                            }
+                            leaf => Some(leaf),
+                        };
+                        let value = match spec {
+                            Some(spec) => {
+                                path.push(match path.is_empty() {
+                                    true => name,
+                                    false => format!(".{}", name),
+                                });
+                                let value = spec.apply(py, &value, path)?;
+                                path.pop();
+                                value
+                            }
+                            None => value.unbind(),
+                        };
+                        coerced.set_item(key, value)?;
+                    }
+                    return Ok(into_pyany!(coerced));
+                }
+                let Spec::Leaf(kind) = self else {
+                    return Err(mismatch("a dict", path));
+                };
+                kind.coerce(py, obj)?.ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "Cannot coerce {} at `{}` to {}",
+                        obj.repr().map(|r| r.to_string()).unwrap_or_default(),
+                        path.concat(),
+                        kind.name()
+                    ))
+                })
+            }
+        }
+    }
+}
+
+/// Coerce the leaves of a parsed document to types, normalizing the
+/// string-typed values of legacy configurations in one pass.
+///
+/// The spec mirrors the structure of the value. The name of a type applies
+/// to every leaf below it, a dict gives the specs of the values of a dict by
+/// key, with `*` matching the other keys, and a list of one spec applies it
+/// to every item of a list. Values without a spec, or with a `None` spec,
+/// are left as is, and so is `None` wherever it is found. The types are:
+///   - `int`: Integers, from floats without a fraction or strings like
+///            `"80"`.
+///   - `bool-ish`: Booleans, from `1` and `0` or strings like `"yes"`,
+///                 `"on"` or `"false"`, whatever their case.
+///   - `duration`: A `datetime.timedelta`, from a number of seconds or a
+///                 string like `"90"`, `"1h30m"` or `"2d 12h"`, with units
+///                 from `ns` to `w`.
+///   - `size`: A number of bytes, from a string like `"512"`, `"10MB"` or
+///             `"1.5GiB"`. SI units like `kB` are powers of 1000, IEC units
+///             like `KiB` and bare `K`, `M`, `G` or `T` powers of 1024.
+///   - `path`: A `pathlib.Path`, from a string, with a leading `~` expanded
+///             to the home directory.
+///   - `str`, `float` and `bool`: As with `transform`.
+///
+/// Args:
+///   - value (Any): The parsed document, it is not modified.
+///   - spec (str | dict | list): The types of its leaves.
+///
+/// Returns:
+///   - Any: The coerced copy of the document.
+///
+/// Raises:
+///   - ValueError: If the spec is malformed, a value does not have the
+///                 structure of its spec, or cannot be coerced to its type.
+///
+/// Example:
+/// ```python
+/// >>> coerce(
+/// ...     {"port": "8080", "timeout": "1m30s", "cache": {"max": "1.5GiB"},
+/// ...      "debug": "off", "dirs": ["~/a"]},
+/// ...     {"port": "int", "timeout": "duration", "cache": "size",
+/// ...      "debug": "bool-ish", "dirs": ["path"]},
+/// ... )
+/// {'port': 8080, 'timeout': datetime.timedelta(seconds=90),
+///  'cache': {'max': 1610612736}, 'debug': False,
+///  'dirs': [PosixPath('/home/me/a')]}
+/// ```
+#[pyfunction]
+pub fn coerce(
+    py: Python<'_>,
+    value: &Bound<'_, PyAny>,
+    spec: &Bound<'_, PyAny>,
+) -> PyResult<PyObject> {
+    let spec = Spec::of_pyobject(spec, "")?;
+    spec.apply(py, value, &mut Vec::new())
+}
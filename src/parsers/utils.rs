@@ -1,12 +1,327 @@
+use std::borrow::Cow;
+use std::fs;
 use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 use annotate_snippets::{Annotation, Snippet};
-use pyo3::exceptions::PyValueError;
-use pyo3::{create_exception, PyErr, PyObject, PyResult, Python};
+use pyo3::exceptions::{PyIOError, PyTypeError, PyValueError};
+use pyo3::types::{
+    PyAnyMethods, PyBytes, PyBytesMethods, PyString, PyTypeMethods,
+};
+use pyo3::{
+    create_exception, Bound, FromPyObject, PyAny, PyErr, PyObject, PyResult,
+    PyTypeInfo, Python,
+};
+
+use crate::parsers::hook::hooked;
 
 create_exception!(parsers, ParseError, PyValueError);
 create_exception!(parsers, EvaluationError, PyValueError);
 create_exception!(parsers, ConversionError, PyValueError);
+create_exception!(parsers, ResourceLimitError, EvaluationError);
+
+/// Stable identifiers of the errors raised by the loaders, exposed to Python
+/// as the `code` attribute of the exception. Codes are never reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The JSONC content is not syntactically valid
+    JsoncSyntax,
+    /// The JSONC content contains no value
+    JsoncEmpty,
+    /// A JSONC number is neither an integer nor a float
+    JsoncInvalidNumber,
+    /// The Python value cannot be serialized to JSON
+    JsoncConversion,
+    /// (strict) An object defines the same key more than once
+    JsoncDuplicateKey,
+    /// (strict) A string contains an unpaired UTF-16 surrogate escape
+    JsoncLoneSurrogate,
+    /// (strict) A number cannot be represented without loss
+    JsoncOverlongNumber,
+    /// The nix code is not syntactically valid
+    NixSyntax,
+    /// The nix expression failed to evaluate
+    NixEvaluation,
+    /// The nix value cannot be converted from or to Python
+    NixConversion,
+    /// A nix file imports itself, directly or through other files
+    NixImportCycle,
+    /// (strict) The evaluation emitted warnings
+    NixWarning,
+    /// The `flake.lock` of a flake is malformed
+    NixFlakeLock,
+    /// An input of a flake is not available without fetching it
+    NixFlakeInput,
+    /// An offline evaluation needs resources that are not fetched yet
+    NixFetchOffline,
+    /// The evaluation did not finish before its timeout
+    NixTimeout,
+    /// The evaluation allocated more memory than its limit
+    NixMemoryLimit,
+    /// The evaluation nested more function calls than its limit
+    NixCallDepth,
+    /// The nix code nests expressions deeper than its limit
+    NixNestingLimit,
+    /// The evaluated nix value does not match its schema
+    NixSchema,
+    /// The TOML manifest is not syntactically valid
+    ManifestSyntax,
+    /// The manifest does not describe a valid deployment
+    ManifestInvalid,
+    /// Several sources of the manifest are deployed to the same target
+    ManifestConflict,
+    /// The markers of the managed regions of a document do not pair up
+    ManagedMarkers,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 24] = [
+        ErrorCode::JsoncSyntax,
+        ErrorCode::JsoncEmpty,
+        ErrorCode::JsoncInvalidNumber,
+        ErrorCode::JsoncConversion,
+        ErrorCode::JsoncDuplicateKey,
+        ErrorCode::JsoncLoneSurrogate,
+        ErrorCode::JsoncOverlongNumber,
+        ErrorCode::NixSyntax,
+        ErrorCode::NixEvaluation,
+        ErrorCode::NixConversion,
+        ErrorCode::NixImportCycle,
+        ErrorCode::NixWarning,
+        ErrorCode::NixFlakeLock,
+        ErrorCode::NixFlakeInput,
+        ErrorCode::NixFetchOffline,
+        ErrorCode::NixTimeout,
+        ErrorCode::NixMemoryLimit,
+        ErrorCode::NixCallDepth,
+        ErrorCode::NixNestingLimit,
+        ErrorCode::NixSchema,
+        ErrorCode::ManifestSyntax,
+        ErrorCode::ManifestInvalid,
+        ErrorCode::ManifestConflict,
+        ErrorCode::ManagedMarkers,
+    ];
+
+    /// The code named by its identifier, in any case
+    pub fn of_str(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.as_str().eq_ignore_ascii_case(code))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::JsoncSyntax => "JSONC_E001",
+            ErrorCode::JsoncEmpty => "JSONC_E002",
+            ErrorCode::JsoncInvalidNumber => "JSONC_E003",
+            ErrorCode::JsoncConversion => "JSONC_E004",
+            ErrorCode::JsoncDuplicateKey => "JSONC_E010",
+            ErrorCode::JsoncLoneSurrogate => "JSONC_E011",
+            ErrorCode::JsoncOverlongNumber => "JSONC_E012",
+            ErrorCode::NixSyntax => "NIX_E001",
+            ErrorCode::NixEvaluation => "NIX_E002",
+            ErrorCode::NixConversion => "NIX_E003",
+            ErrorCode::NixImportCycle => "NIX_E004",
+            ErrorCode::NixWarning => "NIX_E010",
+            ErrorCode::NixFlakeLock => "NIX_E020",
+            ErrorCode::NixFlakeInput => "NIX_E021",
+            ErrorCode::NixFetchOffline => "NIX_E022",
+            ErrorCode::NixTimeout => "NIX_E030",
+            ErrorCode::NixMemoryLimit => "NIX_E031",
+            ErrorCode::NixCallDepth => "NIX_E032",
+            ErrorCode::NixNestingLimit => "NIX_E033",
+            ErrorCode::NixSchema => "NIX_E040",
+            ErrorCode::ManifestSyntax => "MANIFEST_E001",
+            ErrorCode::ManifestInvalid => "MANIFEST_E002",
+            ErrorCode::ManifestConflict => "MANIFEST_E003",
+            ErrorCode::ManagedMarkers => "MANAGED_E001",
+        }
+    }
+}
+
+/// Create an exception of type `E` whose `code` attribute is set to `code`,
+/// and whose `errors` attribute lists only itself, translated by the error
+/// hook
+pub fn coded_err<E: PyTypeInfo>(code: ErrorCode, message: String) -> PyErr {
+    let err = Python::with_gil(|py| {
+        let err = PyErr::new::<E, _>(message);
+        // Setting an attribute on a fresh exception instance cannot fail
+        let _ = err.value(py).setattr("code", code.as_str());
+        let _ = err.value(py).setattr("errors", [err.value(py)]);
+        err
+    });
+    hooked(err)
+}
+
+/// Combine several errors into one of the type of the first, whose message
+/// renders all of them and whose `errors` attribute lists each of them. The
+/// errors must be created `unhooked`, and the result given to `hooked`.
+pub fn aggregated_err(first: PyErr, others: Vec<PyErr>) -> PyErr {
+    Python::with_gil(|py| {
+        let errors = std::iter::once(&first)
+            .chain(&others)
+            .map(|e| e.value(py).clone())
+            .collect::<Vec<_>>();
+        let combined = if others.is_empty() {
+            first
+        } else {
+            let message = errors
+                .iter()
+                .map(|e| e.str().map(|s| s.to_string()).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let combined = PyErr::from_type(first.get_type(py), message);
+            if let Ok(code) = first.value(py).getattr("code") {
+                let _ = combined.value(py).setattr("code", code);
+            }
+            combined
+        };
+        // Setting an attribute on an exception instance cannot fail
+        let _ = combined.value(py).setattr("errors", errors);
+        combined
+    })
+}
+
+/// Read a file to be parsed, failing with an `IOError` naming the file
+pub fn read_file(path: &Path) -> PyResult<String> {
+    fs::read_to_string(path).map_err(|e| {
+        PyIOError::new_err(format!(
+            "Failed to read file {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// A file to load, given by its path or as a file-like object, like
+/// `sys.stdin` or a socket made a file with `makefile`
+#[derive(FromPyObject)]
+pub enum FileSource<'py> {
+    Path(PathBuf),
+    Stream(Bound<'py, PyAny>),
+}
+
+impl FileSource<'_> {
+    /// The path labelling the source in errors: the path of the file, or the
+    /// `name` of the stream, `<stream>` if it has none
+    pub fn origin(&self) -> PathBuf {
+        match self {
+            FileSource::Path(path) => path.clone(),
+            FileSource::Stream(stream) => stream
+                .getattr("name")
+                .and_then(|name| name.extract::<String>())
+                .unwrap_or_else(|_| "<stream>".to_string())
+                .into(),
+        }
+    }
+
+    /// Read the content of the source, with `read_file` for a path, or the
+    /// `read` method of the stream, returning a string or UTF-8 bytes
+    pub fn read(
+        &self,
+        read_file: impl FnOnce(&Path) -> PyResult<String>,
+    ) -> PyResult<String> {
+        let stream = match self {
+            FileSource::Path(path) => return read_file(path),
+            FileSource::Stream(stream) => stream,
+        };
+        if !stream.hasattr("read")? {
+            return Err(PyTypeError::new_err(format!(
+                "Expected a path or a file-like object, got `{}`",
+                stream.get_type().name()?
+            )));
+        }
+        let content = stream.call_method0("read")?;
+        match content.downcast::<PyBytes>() {
+            Ok(bytes) => {
+                String::from_utf8(bytes.as_bytes().to_vec()).map_err(|_| {
+                    PyIOError::new_err(format!(
+                        "Failed to read {}: stream did not contain valid \
+                        UTF-8",
+                        self.origin().display()
+                    ))
+                })
+            }
+            Err(_) => content.extract(),
+        }
+    }
+}
+
+/// Convert the Windows (`\r\n`) and classic Mac OS (`\r`) line endings of
+/// content to be loaded to `\n` if `normalize`, so offsets into it, e.g. in
+/// errors, do not depend on the line endings it was saved with
+pub fn normalized_newlines(content: &str, normalize: bool) -> Cow<'_, str> {
+    if normalize && content.contains('\r') {
+        Cow::Owned(content.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Replacement for a character whose width on a terminal is unpredictable,
+/// with the same length in UTF-8
+fn display_replacement(c: char, next: Option<char>) -> Option<char> {
+    match c {
+        '\n' => None,
+        // Keep line endings of Windows, snippets know how to handle them
+        '\r' if next == Some('\n') => None,
+        // Tabs are expanded to a varying number of columns
+        '\t' => Some(' '),
+        c if c.is_ascii_control() => Some(' '),
+        // C1 control codes and the Arabic letter mark
+        '\u{80}'..='\u{9f}' | '\u{61c}' => Some('\u{b7}'),
+        // Bidirectional marks, embeddings, overrides and isolates reorder
+        // the line they appear in
+        '\u{200e}'
+        | '\u{200f}'
+        | '\u{202a}'..='\u{202e}'
+        | '\u{2066}'..='\u{2069}' => Some('\u{fffd}'),
+        _ => None,
+    }
+}
+
+/// Prepare source code to be rendered in a snippet, so that annotations line
+/// up with the characters they point at.
+///
+/// Wide characters and combining marks are measured by the renderer, but
+/// tabs, control codes and bidirectional formatting characters are not
+/// displayed with a predictable width or order. Each one is replaced by a
+/// visible character of the same length in UTF-8, so byte ranges into the
+/// original source stay valid.
+pub fn display_source(source: &str) -> Cow<'_, str> {
+    let mut chars = source.chars().peekable();
+    let mut displayed = String::with_capacity(source.len());
+    let mut replaced = false;
+    while let Some(c) = chars.next() {
+        match display_replacement(c, chars.peek().copied()) {
+            Some(replacement) => {
+                debug_assert_eq!(replacement.len_utf8(), c.len_utf8());
+                displayed.push(replacement);
+                replaced = true;
+            }
+            None => displayed.push(c),
+        }
+    }
+    if replaced {
+        Cow::Owned(displayed)
+    } else {
+        Cow::Borrowed(source)
+    }
+}
+
+/// Keys up to this length are interned, longer ones are rarely repeated
+const INTERNED_KEY_LEN: usize = 32;
+
+/// A key of a converted dict, interned when short so the keys repeated by
+/// the objects of a document share one string, which Python also compares
+/// by identity when looking them up
+pub fn dict_key<'py>(py: Python<'py>, key: &str) -> Bound<'py, PyString> {
+    if key.len() <= INTERNED_KEY_LEN {
+        PyString::intern(py, key)
+    } else {
+        PyString::new(py, key)
+    }
+}
 
 pub trait IntoRange<T> {
     fn into_range(self) -> Range<T>;
@@ -30,3 +345,7 @@ macro_rules! into_pyany {
 pub trait TryToPyObject {
     fn try_to_pyobject(&self, py: Python<'_>) -> PyResult<PyObject>;
 }
+
+pub trait TryFromPyObject: Sized {
+    fn try_from_pyobject(obj: &Bound<'_, PyAny>) -> PyResult<Self>;
+}